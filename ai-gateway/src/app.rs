@@ -35,13 +35,20 @@ use crate::{
     config::{Config, cache::CacheStore, server::TlsConfig},
     control_plane::control_plane_state::StateWithMetadata,
     discover::monitor::{
-        health::provider::HealthMonitorMap, metrics::EndpointMetricsRegistry,
+        health::{HealthStore, provider::HealthMonitorMap},
+        metrics::EndpointMetricsRegistry,
         rate_limit::RateLimitMonitorMap,
     },
+    dispatcher::{key_pool::KeyPools, warm_pool::WarmClients},
     error::{init::InitError, runtime::RuntimeError},
-    logger::service::JawnClient,
+    logger::{
+        health::LoggingHealth, redaction::BodyRedactor, service::JawnClient,
+    },
     metrics::{self, Metrics, attribute_extractor::AttributeExtractor},
-    middleware::response_headers::ResponseHeaderLayer,
+    middleware::{
+        rate_limit::model::ModelRateLimits, request_id::RequestIdLayer,
+        response_headers::ResponseHeaderLayer,
+    },
     router::meta::MetaRouter,
     store::{connect, minio::BaseMinioClient, router::RouterStore},
     types::provider::ProviderKeys,
@@ -84,6 +91,9 @@ pub type BoxedHyperServiceStack = BoxCloneService<
 /// 1. `HandleError`
 /// 2. Authn/Authz
 /// 3. Unauthenticated and authenticated rate limit layers
+///    - Per org spend limits
+///    - Per tenant concurrent stream limits
+///    - Request fingerprinting
 /// 4. `MetaRouter`
 ///
 /// -- Router specific MW, must not require Clone on inner Service --
@@ -152,6 +162,7 @@ pub type BoxedHyperServiceStack = BoxCloneService<
 ///   - `MapperContext`
 ///   - `AuthContext`
 ///   - `ProviderRequestId`
+///   - `EffectiveRequest`, if enabled
 #[derive(Clone)]
 pub struct App {
     pub state: AppState,
@@ -198,7 +209,6 @@ impl App {
     /// that are shared across the application. This includes setting up
     /// metrics, monitoring, caching, and API keys.
     async fn build_app_state(config: Config) -> Result<AppState, InitError> {
-        let minio = BaseMinioClient::new(config.minio.clone())?;
         let router_store = if config.deployment_target.is_cloud() {
             let pg_pool = connect(&config.database).await?;
             let router_store = RouterStore::new(pg_pool.clone())?;
@@ -206,12 +216,29 @@ impl App {
         } else {
             None
         };
+        Self::build_app_state_with_store(config, router_store).await
+    }
+
+    /// Like [`Self::build_app_state`], with the given router store rather
+    /// than one connected to the configured database.
+    pub(crate) async fn build_app_state_with_store(
+        config: Config,
+        router_store: Option<RouterStore>,
+    ) -> Result<AppState, InitError> {
+        let minio = BaseMinioClient::new(config.minio.clone())?;
         let jawn_http_client = JawnClient::new()?;
 
         let meter = global::meter(SERVICE_NAME);
         let metrics = metrics::Metrics::new(&meter);
         let endpoint_metrics = EndpointMetricsRegistry::new(&config);
         let health_monitor = HealthMonitorMap::default();
+        let health_store = config
+            .discover
+            .monitor
+            .persistence
+            .as_ref()
+            .map(HealthStore::new)
+            .transpose()?;
         let rate_limit_monitor = RateLimitMonitorMap::default();
 
         let global_rate_limit = config
@@ -246,7 +273,19 @@ impl App {
         } else {
             None
         };
+        let body_redactor = config
+            .redaction
+            .as_ref()
+            .map(BodyRedactor::new)
+            .transpose()?;
+        let logging_health =
+            LoggingHealth::new(config.helicone.logging_degradation.clone());
         let provider_keys = ProviderKeys::new(&config, &metrics);
+        let provider_key_pools = if config.deployment_target.is_cloud() {
+            KeyPools::default()
+        } else {
+            KeyPools::from_env(&config.providers)
+        };
 
         let app_state = AppState(Arc::new(InnerAppState {
             config,
@@ -257,11 +296,14 @@ impl App {
                 StateWithMetadata::default(),
             )),
             provider_keys,
+            provider_key_pools,
             global_rate_limit,
             router_rate_limits: RwLock::new(HashMap::default()),
+            model_rate_limits: RwLock::new(ModelRateLimits::default()),
             metrics,
             endpoint_metrics,
             health_monitors: health_monitor,
+            health_store,
             rate_limit_monitors: rate_limit_monitor,
             rate_limit_senders: RwLock::new(HashMap::default()),
             rate_limit_receivers: RwLock::new(HashMap::default()),
@@ -269,6 +311,9 @@ impl App {
             router_tx: RwLock::new(None),
             helicone_api_keys: RwLock::new(helicone_api_keys),
             router_organization_map: RwLock::new(HashMap::default()),
+            warm_clients: WarmClients::default(),
+            body_redactor,
+            logging_health,
         }));
 
         Ok(app_state)
@@ -289,6 +334,13 @@ impl App {
                 .build()?;
 
         let router = MetaRouter::build(app_state.clone()).await?;
+        // the plugin sees requests as they are routed, after all global
+        // middleware
+        #[cfg(feature = "wasm-plugins")]
+        let router = tower::Layer::layer(
+            &crate::middleware::wasm_plugin::WasmPluginLayer::new(&app_state)?,
+            router,
+        );
 
         let compression_layer = CompressionLayer::new()
             .gzip(true)
@@ -303,6 +355,7 @@ impl App {
 
         // global middleware is applied here
         let service_stack = ServiceBuilder::new()
+            .layer(RequestIdLayer::new())
             .layer(CatchPanicLayer::custom(PanicResponder))
             .layer(SetSensitiveHeadersLayer::new(std::iter::once(
                 http::header::AUTHORIZATION,
@@ -16,12 +16,17 @@ use crate::{
     },
     control_plane::{control_plane_state::StateWithMetadata, types::Key},
     discover::monitor::{
-        health::provider::HealthMonitorMap, metrics::EndpointMetricsRegistry,
+        health::{HealthStore, provider::HealthMonitorMap},
+        metrics::EndpointMetricsRegistry,
         rate_limit::RateLimitMonitorMap,
     },
+    dispatcher::{key_pool::KeyPools, warm_pool::WarmClients},
     error::init::InitError,
-    logger::service::JawnClient,
+    logger::{
+        health::LoggingHealth, redaction::BodyRedactor, service::JawnClient,
+    },
     metrics::Metrics,
+    middleware::rate_limit::model::ModelRateLimits,
     router::service::Router,
     store::{minio::BaseMinioClient, router::RouterStore},
     types::{
@@ -58,6 +63,9 @@ pub struct InnerAppState {
     pub cache_manager: Option<CacheClient>,
     pub global_rate_limit: Option<Arc<RateLimiterConfig>>,
     pub router_rate_limits: RwLock<HashMap<RouterId, Arc<RateLimiterConfig>>>,
+    /// Limits per model of each router, checked once the model a request is
+    /// sent with has been resolved.
+    pub model_rate_limits: RwLock<ModelRateLimits>,
     /// Top level metrics which are exported to OpenTelemetry.
     pub metrics: Metrics,
     /// Metrics to track provider health and rate limits.
@@ -65,6 +73,9 @@ pub struct InnerAppState {
     /// dynamically updated based on provider health and rate limits.
     pub endpoint_metrics: EndpointMetricsRegistry,
     pub health_monitors: HealthMonitorMap,
+    /// Where unhealthy providers are persisted, if persistence is
+    /// configured.
+    pub health_store: Option<HealthStore>,
     pub rate_limit_monitors: RateLimitMonitorMap,
     pub rate_limit_senders: RateLimitEventSenders,
     pub rate_limit_receivers: RateLimitEventReceivers,
@@ -73,8 +84,17 @@ pub struct InnerAppState {
     pub control_plane_state: Arc<RwLock<StateWithMetadata>>,
 
     pub provider_keys: ProviderKeys,
+    /// Pools of provider keys for providers with several keys configured.
+    pub provider_key_pools: KeyPools,
     pub helicone_api_keys: RwLock<Option<HashSet<Key>>>,
     pub router_organization_map: RwLock<HashMap<RouterId, OrgId>>,
+    /// Clients kept warm by the
+    /// [`WarmPool`](crate::dispatcher::warm_pool::WarmPool).
+    pub warm_clients: WarmClients,
+    /// Redacts personal data from the bodies logged to Helicone, if
+    /// redaction is configured.
+    pub body_redactor: Option<BodyRedactor>,
+    pub logging_health: LoggingHealth,
 }
 
 impl AppState {
@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, num::NonZeroUsize, time::Duration};
 
 use derive_more::{AsRef, From};
 use indexmap::IndexSet;
-use nonempty_collections::{NESet, nes};
+use nonempty_collections::{NEMap, NESet, NEVec, nes};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
@@ -102,6 +102,34 @@ impl BalanceConfig {
         )]))
     }
 
+    #[cfg(any(test, feature = "testing"))]
+    #[must_use]
+    pub fn vertex() -> Self {
+        Self(HashMap::from([(
+            EndpointType::Chat,
+            BalanceConfigInner::ProviderWeighted {
+                providers: nes![WeightedProvider {
+                    provider: InferenceProvider::Named("vertex".into()),
+                    weight: Decimal::from(1),
+                }],
+            },
+        )]))
+    }
+
+    #[cfg(any(test, feature = "testing"))]
+    #[must_use]
+    pub fn azure() -> Self {
+        Self(HashMap::from([(
+            EndpointType::Chat,
+            BalanceConfigInner::ProviderWeighted {
+                providers: nes![WeightedProvider {
+                    provider: InferenceProvider::Named("azure".into()),
+                    weight: Decimal::from(1),
+                }],
+            },
+        )]))
+    }
+
     #[cfg(any(test, feature = "testing"))]
     #[must_use]
     pub fn mistral() -> Self {
@@ -116,6 +144,20 @@ impl BalanceConfig {
         )]))
     }
 
+    #[cfg(any(test, feature = "testing"))]
+    #[must_use]
+    pub fn groq() -> Self {
+        Self(HashMap::from([(
+            EndpointType::Chat,
+            BalanceConfigInner::ProviderWeighted {
+                providers: nes![WeightedProvider {
+                    provider: InferenceProvider::Named("groq".into()),
+                    weight: Decimal::from(1),
+                }],
+            },
+        )]))
+    }
+
     #[must_use]
     pub fn providers(&self) -> IndexSet<InferenceProvider> {
         self.0
@@ -123,6 +165,13 @@ impl BalanceConfig {
             .flat_map(BalanceConfigInner::providers)
             .collect()
     }
+
+    /// The name of the strategy used to balance requests to the given
+    /// endpoint type, e.g. `model-weighted`.
+    #[must_use]
+    pub fn strategy(&self, endpoint_type: EndpointType) -> Option<&str> {
+        self.0.get(&endpoint_type).map(AsRef::as_ref)
+    }
 }
 
 /// Configurations which drive the strategy used for the
@@ -145,23 +194,70 @@ pub enum BalanceConfigInner {
     /// latency, but not always.
     #[serde(alias = "latency")]
     BalancedLatency { providers: NESet<InferenceProvider> },
+    /// Distributes and load balances requests among a set of providers
+    /// according to their weights, favoring providers with lower observed
+    /// latency.
+    WeightedLatency {
+        providers: NESet<WeightedProvider>,
+        /// How strongly latency affects the weights, from `0.0`, where only
+        /// the weights are considered, to `1.0`, where each provider's
+        /// weight is divided by its mean latency in seconds.
+        latency_bias: Decimal,
+    },
+    /// Samples two providers according to their weights, and sends the
+    /// request to the one with fewer requests in flight.
+    LeastInFlight { providers: NESet<WeightedProvider> },
     /// Distributes and load balances requests among a set of (providers,model).
-    ModelWeighted { models: NESet<WeightedModel> },
+    ModelWeighted {
+        models: NESet<WeightedModel>,
+        /// If set, each request is sent to several models in parallel rather
+        /// than to a single sampled model.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fan_out: Option<FanOutConfig>,
+    },
     /// Distributes and load balances requests among a set of (providers,model).
     ModelLatency { models: NESet<ModelId> },
+    /// Sends each request to the cheapest (provider, model) that is healthy
+    /// and not rate limited, preferring the lower latency one among models
+    /// with the same price.
+    CostWeighted {
+        /// Price per 1k tokens of each model. Prices can be overridden per
+        /// request with the `helicone-cost-priority` header.
+        models: NEMap<ModelId, Decimal>,
+    },
+    /// Sends requests to each of the providers in turn, in the order they
+    /// are listed. Unlike the other strategies there is no randomness, which
+    /// makes the distribution of requests predictable.
+    RoundRobin { providers: NEVec<InferenceProvider> },
+    /// Sends simple requests to a cheaper, faster model and complex ones to
+    /// a stronger model, based on an estimate of the complexity of the
+    /// prompt.
+    Complexity {
+        /// The model simple requests are sent to.
+        simple: ModelId,
+        /// The model complex requests are sent to.
+        complex: ModelId,
+        #[serde(default)]
+        thresholds: ComplexityThresholds,
+    },
 }
 
 impl BalanceConfigInner {
     #[must_use]
     pub fn providers(&self) -> IndexSet<InferenceProvider> {
         match self {
-            Self::ProviderWeighted { providers } => {
+            Self::ProviderWeighted { providers }
+            | Self::WeightedLatency { providers, .. }
+            | Self::LeastInFlight { providers } => {
                 providers.iter().map(|t| t.provider.clone()).collect()
             }
             Self::BalancedLatency { providers } => {
                 providers.iter().cloned().collect()
             }
-            Self::ModelWeighted { models } => models
+            Self::RoundRobin { providers } => {
+                providers.iter().cloned().collect()
+            }
+            Self::ModelWeighted { models, .. } => models
                 .iter()
                 .filter_map(|model| {
                     if let Some(provider) = model.model.inference_provider() { Some(provider) } else {
@@ -179,6 +275,42 @@ impl BalanceConfigInner {
                     }
                 })
                 .collect(),
+            Self::CostWeighted { models } => models
+                .into_iter()
+                .filter_map(|(model, _)| {
+                    if let Some(provider) = model.inference_provider() { Some(provider) } else {
+                        tracing::warn!(model = ?model, "Model has no inference provider");
+                        None
+                    }
+                })
+                .collect(),
+            Self::Complexity {
+                simple, complex, ..
+            } => [simple, complex]
+                .into_iter()
+                .filter_map(|model| {
+                    if let Some(provider) = model.inference_provider() { Some(provider) } else {
+                        tracing::warn!(model = ?model, "Model has no inference provider");
+                        None
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// The latency bias of a [`Self::WeightedLatency`] balancer.
+    #[must_use]
+    pub fn latency_bias(&self) -> Option<Decimal> {
+        match self {
+            Self::WeightedLatency { latency_bias, .. } => Some(*latency_bias),
+            Self::ProviderWeighted { .. }
+            | Self::LeastInFlight { .. }
+            | Self::BalancedLatency { .. }
+            | Self::ModelWeighted { .. }
+            | Self::ModelLatency { .. }
+            | Self::CostWeighted { .. }
+            | Self::RoundRobin { .. }
+            | Self::Complexity { .. } => None,
         }
     }
 }
@@ -196,3 +328,92 @@ pub struct WeightedModel {
     pub model: ModelId,
     pub weight: Decimal,
 }
+
+/// Configures how a [`BalanceConfigInner::ModelWeighted`] router fans a
+/// request out to multiple models, e.g. to compare the quality of their
+/// responses.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, Hash, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FanOutConfig {
+    /// The number of models each request is sent to, sampled according to
+    /// their weights.
+    pub count: NonZeroUsize,
+    #[serde(default)]
+    pub selection: FanOutSelection,
+    /// How long to wait for the fanned out responses. Requests still pending
+    /// when it elapses are aborted.
+    #[serde(
+        default,
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timeout: Option<Duration>,
+    #[serde(default)]
+    pub on_timeout: FanOutOnTimeout,
+}
+
+/// The thresholds above which a [`BalanceConfigInner::Complexity`] router
+/// considers a request complex. A request exceeding any of them is complex.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Eq, Hash, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ComplexityThresholds {
+    /// The number of characters of message content above which a prompt is
+    /// complex.
+    #[serde(default = "default_max_prompt_chars")]
+    pub max_prompt_chars: usize,
+    /// Whether requests which provide tools are complex.
+    #[serde(default = "default_true")]
+    pub tools: bool,
+    /// Whether requests which include images are complex.
+    #[serde(default = "default_true")]
+    pub images: bool,
+}
+
+impl Default for ComplexityThresholds {
+    fn default() -> Self {
+        Self {
+            max_prompt_chars: default_max_prompt_chars(),
+            tools: true,
+            images: true,
+        }
+    }
+}
+
+fn default_max_prompt_chars() -> usize {
+    2000
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Which of the fanned out responses are returned to the client.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, Hash, PartialEq,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum FanOutSelection {
+    /// Return the first successful response. The remaining requests still
+    /// run to completion so that they are logged.
+    #[default]
+    First,
+    /// Wait for every response and return them all in a single list.
+    ///
+    /// Not supported for streaming requests.
+    All,
+}
+
+/// What a fan out router returns when its
+/// [`timeout`](FanOutConfig::timeout) elapses before every response arrived.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, Hash, PartialEq,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum FanOutOnTimeout {
+    /// Return the responses that arrived in time, as if the timed out models
+    /// had not been sampled. Fails with a `504` if none did.
+    #[default]
+    Partial,
+    /// Fail the request with a `504`.
+    Fail,
+}
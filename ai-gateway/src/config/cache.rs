@@ -1,5 +1,11 @@
+use std::time::Duration;
+
+use http::StatusCode;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
+use crate::{error::init::InitError, types::model_id::ModelId};
+
 pub(crate) const MAX_BUCKET_SIZE: u8 = 10;
 pub(crate) const DEFAULT_BUCKETS: u8 = 1;
 
@@ -15,6 +21,121 @@ pub struct CacheConfig {
     pub buckets: u8,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub seed: Option<String>,
+    /// If enabled, streaming responses are collected and cached as a single
+    /// response, and cache hits replay the whole stream at once.
+    ///
+    /// Otherwise, streaming requests bypass the cache entirely.
+    pub cache_streams: bool,
+    /// If enabled, cache entries are shared by all tenants, so a tenant may
+    /// receive a response cached for another tenant's identical request.
+    ///
+    /// Otherwise, cache entries are scoped to the organization of the
+    /// authenticated caller.
+    pub share_across_tenants: bool,
+    /// Header identifying the tenant of unauthenticated requests, whose
+    /// value scopes cache entries like the organization of authenticated
+    /// callers does.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_header: Option<String>,
+    /// If set, requests that miss the exact match cache may be served a
+    /// response cached for a similar prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub semantic: Option<SemanticCacheConfig>,
+    /// If set, client error responses with one of the configured status
+    /// codes are cached too.
+    ///
+    /// Otherwise, only successful responses are cached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_responses: Option<ErrorResponseCacheConfig>,
+}
+
+impl CacheConfig {
+    pub fn validate(&self) -> Result<(), InitError> {
+        if let Some(error_responses) = &self.error_responses {
+            error_responses.validate()?;
+        }
+        let Some(semantic) = &self.semantic else {
+            return Ok(());
+        };
+        if semantic.threshold <= Decimal::ZERO
+            || semantic.threshold > Decimal::ONE
+        {
+            return Err(InitError::InvalidSemanticCacheThreshold(
+                semantic.threshold,
+            ));
+        }
+        if semantic.embedding_model.inference_provider().is_none() {
+            return Err(InitError::ModelIdNotRecognized(
+                semantic.embedding_model.to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Serves a cached response when the embedding of a request's prompt is
+/// close enough to the embedding of a cached request's prompt.
+///
+/// Embeddings of cached prompts are kept in memory, so they are not shared
+/// between gateway instances even when the cache store is.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SemanticCacheConfig {
+    /// The minimum cosine similarity, in `(0, 1]`, between two prompts for
+    /// them to share a cached response.
+    pub threshold: Decimal,
+    /// The model used to embed prompts, e.g.
+    /// `openai/text-embedding-3-small`.
+    pub embedding_model: ModelId,
+}
+
+/// Caching of error responses that are the same every time a request is
+/// sent, e.g. validation errors, so that clients retrying them don't add
+/// load on the provider.
+///
+/// Server errors and authentication errors are never cached, since they
+/// depend on the state of the provider or the caller rather than on the
+/// request.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ErrorResponseCacheConfig {
+    /// The 4xx status codes of the responses to cache, e.g. `400` or `422`.
+    pub status_codes: Vec<u16>,
+    /// How long error responses are cached for, regardless of the cache
+    /// directive.
+    #[serde(with = "humantime_serde", default = "default_error_ttl")]
+    pub ttl: Duration,
+}
+
+impl ErrorResponseCacheConfig {
+    /// Client errors which may succeed when retried, and so are never
+    /// cached.
+    const NEVER_CACHED: [StatusCode; 4] = [
+        StatusCode::UNAUTHORIZED,
+        StatusCode::FORBIDDEN,
+        StatusCode::PROXY_AUTHENTICATION_REQUIRED,
+        StatusCode::TOO_MANY_REQUESTS,
+    ];
+
+    fn validate(&self) -> Result<(), InitError> {
+        for status in &self.status_codes {
+            let cacheable = StatusCode::from_u16(*status).is_ok_and(|status| {
+                status.is_client_error()
+                    && !Self::NEVER_CACHED.contains(&status)
+            });
+            if !cacheable {
+                return Err(InitError::InvalidCacheableStatusCode(*status));
+            }
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn is_cacheable(&self, status: StatusCode) -> bool {
+        status.is_client_error()
+            && !Self::NEVER_CACHED.contains(&status)
+            && self.status_codes.contains(&status.as_u16())
+    }
 }
 
 #[cfg(feature = "testing")]
@@ -24,6 +145,11 @@ impl crate::tests::TestDefault for CacheConfig {
             directive: None,
             buckets: DEFAULT_BUCKETS,
             seed: None,
+            cache_streams: false,
+            share_across_tenants: false,
+            tenant_header: None,
+            semantic: None,
+            error_responses: None,
         }
     }
 }
@@ -57,6 +183,10 @@ fn default_max_size() -> usize {
     1024 * 1024 * 256
 }
 
+fn default_error_ttl() -> Duration {
+    Duration::from_secs(60)
+}
+
 fn default_buckets() -> u8 {
     1
 }
@@ -64,3 +194,33 @@ fn default_buckets() -> u8 {
 fn default_host_url() -> url::Url {
     "redis://localhost:6340".parse().unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_and_auth_errors_are_never_cacheable() {
+        for status in [500, 503, 401, 403, 429, 200, 302] {
+            let config = ErrorResponseCacheConfig {
+                status_codes: vec![status],
+                ttl: default_error_ttl(),
+            };
+            assert!(
+                matches!(
+                    config.validate(),
+                    Err(InitError::InvalidCacheableStatusCode(_))
+                ),
+                "{status} should not be cacheable"
+            );
+        }
+
+        let config = ErrorResponseCacheConfig {
+            status_codes: vec![400, 422],
+            ttl: default_error_ttl(),
+        };
+        assert!(config.validate().is_ok());
+        assert!(config.is_cacheable(StatusCode::BAD_REQUEST));
+        assert!(!config.is_cacheable(StatusCode::NOT_FOUND));
+    }
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+/// Where clients may send their Helicone API key.
+///
+/// Every format authenticates the same key, so requests are authorized the
+/// same way regardless of how the key was sent.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ClientAuthConfig {
+    /// Accepted formats, checked in order until one of them is present on
+    /// the request.
+    pub formats: Vec<ClientAuthFormat>,
+    /// Name of the query parameter read by the `query-param` format.
+    pub query_param: String,
+}
+
+impl Default for ClientAuthConfig {
+    fn default() -> Self {
+        Self {
+            formats: vec![ClientAuthFormat::Bearer],
+            query_param: "helicone-api-key".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClientAuthFormat {
+    /// `Authorization: Bearer <key>`, as sent by the `OpenAI` SDKs.
+    Bearer,
+    /// `x-api-key: <key>`, as sent by the Anthropic SDKs.
+    #[serde(rename = "x-api-key")]
+    ApiKeyHeader,
+    /// A query parameter, for restricted clients that can't set headers,
+    /// e.g. browser `EventSource`s. Keys in URLs are more likely to leak
+    /// through logs, so this should only be enabled if needed.
+    QueryParam,
+}
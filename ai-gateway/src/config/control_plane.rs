@@ -9,6 +9,46 @@ use crate::config::retry::RetryConfig;
 #[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
 pub struct ControlPlaneConfig {
     pub retry: RetryConfig,
+    /// How requests that arrive before the control plane has synced are
+    /// handled.
+    pub auth_not_ready: AuthNotReadyConfig,
+}
+
+/// Requests can't be authenticated until the initial sync with the control
+/// plane completes, which takes a moment after startup.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct AuthNotReadyConfig {
+    /// How long a request waits for the sync to complete before it's
+    /// rejected. Zero rejects requests immediately.
+    #[serde(with = "humantime_serde")]
+    pub wait: Duration,
+    /// Sent in the `Retry-After` header of rejected requests, rounded up to
+    /// whole seconds.
+    #[serde(with = "humantime_serde")]
+    pub retry_after: Duration,
+}
+
+impl Default for AuthNotReadyConfig {
+    fn default() -> Self {
+        Self {
+            wait: Duration::from_secs(5),
+            retry_after: Duration::from_secs(1),
+        }
+    }
+}
+
+impl AuthNotReadyConfig {
+    /// The `Retry-After` value in whole seconds.
+    #[must_use]
+    pub fn retry_after_secs(&self) -> u64 {
+        let secs = self.retry_after.as_secs();
+        if self.retry_after.subsec_nanos() > 0 {
+            secs + 1
+        } else {
+            secs
+        }
+    }
 }
 
 impl Default for ControlPlaneConfig {
@@ -20,6 +60,7 @@ impl Default for ControlPlaneConfig {
                 max_retries: 15,
                 factor: Decimal::from(2),
             },
+            auth_not_ready: AuthNotReadyConfig::default(),
         }
     }
 }
@@ -1,4 +1,6 @@
-use std::time::Duration;
+use std::{
+    collections::BTreeMap, net::IpAddr, num::NonZeroU32, time::Duration,
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -8,6 +10,24 @@ pub struct DispatcherConfig {
     pub timeout: Duration,
     #[serde(default = "default_connection_timeout", with = "humantime_serde")]
     pub connection_timeout: Duration,
+    #[serde(default)]
+    pub dns: DnsConfig,
+    /// If enabled, requests routed to a provider without a configured API
+    /// key are rejected before they are dispatched, rather than being sent
+    /// to the provider unauthenticated.
+    #[serde(default)]
+    pub require_provider_keys: bool,
+    /// If enabled and a provider has several keys configured with
+    /// `{PROVIDER}_API_KEYS`, the requests of an organization consistently
+    /// use the same key, rather than the keys being used in turn. Keys the
+    /// provider rate limited are still rotated away from.
+    #[serde(default)]
+    pub provider_key_affinity: bool,
+    /// If set, idle connections to each provider are opened ahead of
+    /// traffic, more of them for providers that recently served more
+    /// requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_pool: Option<WarmPoolConfig>,
 }
 
 impl Default for DispatcherConfig {
@@ -15,6 +35,71 @@ impl Default for DispatcherConfig {
         Self {
             timeout: default_timeout(),
             connection_timeout: default_connection_timeout(),
+            dns: DnsConfig::default(),
+            require_provider_keys: false,
+            provider_key_affinity: false,
+            warm_pool: None,
+        }
+    }
+}
+
+/// DNS resolution for upstream provider requests.
+///
+/// If neither option is set, the system resolver is used for every new
+/// connection.
+#[derive(
+    Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DnsConfig {
+    /// How long addresses resolved by the system resolver are cached.
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    pub cache_ttl: Option<Duration>,
+    /// Static host to IP address overrides, e.g. to pin a provider's host to
+    /// specific addresses. Overridden hosts never hit the system resolver.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, Vec<IpAddr>>,
+}
+
+/// Sizing of the pool of warm connections kept open to each provider.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WarmPoolConfig {
+    /// How often pool sizes are adjusted to recent traffic and the pools are
+    /// topped up.
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    /// Connections kept warm for a provider without recent traffic.
+    pub min_connections: usize,
+    /// Upper bound on the connections kept warm for a provider.
+    pub max_connections: usize,
+    /// One more connection is kept warm for every this many requests a
+    /// provider served within the health monitor window.
+    pub requests_per_connection: NonZeroU32,
+}
+
+impl WarmPoolConfig {
+    /// The number of connections to keep warm for a provider that served
+    /// `recent_requests` within the health monitor window.
+    #[must_use]
+    pub fn pool_size(&self, recent_requests: u32) -> usize {
+        let wanted =
+            recent_requests.div_ceil(self.requests_per_connection.get());
+        usize::try_from(wanted)
+            .unwrap_or(usize::MAX)
+            .max(self.min_connections)
+            .min(self.max_connections)
+    }
+}
+
+impl Default for WarmPoolConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            min_connections: 1,
+            max_connections: 16,
+            requests_per_connection: NonZeroU32::new(10)
+                .expect("10 is non-zero"),
         }
     }
 }
@@ -33,3 +118,23 @@ fn default_timeout() -> Duration {
 fn default_connection_timeout() -> Duration {
     Duration::from_secs(10)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_size_follows_recent_requests() {
+        let config = WarmPoolConfig {
+            min_connections: 1,
+            max_connections: 4,
+            requests_per_connection: NonZeroU32::new(10).unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(config.pool_size(0), 1);
+        assert_eq!(config.pool_size(10), 1);
+        assert_eq!(config.pool_size(11), 2);
+        assert_eq!(config.pool_size(30), 3);
+        assert_eq!(config.pool_size(1000), 4);
+    }
+}
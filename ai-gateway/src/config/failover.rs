@@ -0,0 +1,153 @@
+use std::{collections::HashMap, num::NonZeroU32};
+
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::types::provider::InferenceProvider;
+
+/// Resends requests to another provider when the provider they were sent to
+/// fails in a way that is likely transient, e.g. because it is overloaded.
+///
+/// Failovers are triggered by the status code of the error response, or by
+/// the type of error the provider reports in its body, e.g. Anthropic's
+/// `overloaded_error`.
+///
+/// Streaming requests fail over the same way, since a stream that errors
+/// does so with its status code before any of its body is sent.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FailoverConfig {
+    /// Providers to fail over to, in order of preference. They don't need to
+    /// be load balanced by the router, so they can be kept as backups that
+    /// only receive failed over requests. Providers that already failed the
+    /// request are skipped.
+    pub providers: Vec<InferenceProvider>,
+    /// Status codes of error responses that trigger a failover, from any
+    /// provider.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub status_codes: Vec<u16>,
+    /// Whether any server error, i.e. a 5xx status code, triggers a
+    /// failover, in addition to the `status-codes`.
+    #[serde(default)]
+    pub server_errors: bool,
+    /// Maximum number of providers a request is sent to, including the
+    /// provider it was balanced to. Unlimited if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<NonZeroU32>,
+    /// Error types that trigger a failover, by provider.
+    ///
+    /// The error type is read from the `error.type` field of the provider's
+    /// error response, or `error.status` for providers like Gemini that
+    /// don't set a type.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub error_types: HashMap<InferenceProvider, Vec<String>>,
+}
+
+impl FailoverConfig {
+    /// Returns whether an error response from the `provider` should be
+    /// failed over to another provider.
+    #[must_use]
+    pub fn triggers(
+        &self,
+        provider: &InferenceProvider,
+        status: StatusCode,
+        error_type: Option<&str>,
+    ) -> bool {
+        if !status.is_client_error() && !status.is_server_error() {
+            return false;
+        }
+        if (self.server_errors && status.is_server_error())
+            || self.status_codes.contains(&status.as_u16())
+        {
+            return true;
+        }
+        error_type.is_some_and(|error_type| {
+            self.error_types
+                .get(provider)
+                .is_some_and(|types| types.iter().any(|t| t == error_type))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> FailoverConfig {
+        FailoverConfig {
+            providers: vec![InferenceProvider::OpenAI],
+            status_codes: vec![503],
+            error_types: HashMap::from([(
+                InferenceProvider::Anthropic,
+                vec!["overloaded_error".to_string()],
+            )]),
+            server_errors: false,
+            max_attempts: None,
+        }
+    }
+
+    #[test]
+    fn error_types_trigger_per_provider() {
+        let config = config();
+        let status = StatusCode::from_u16(529).unwrap();
+        assert!(config.triggers(
+            &InferenceProvider::Anthropic,
+            status,
+            Some("overloaded_error")
+        ));
+        assert!(!config.triggers(
+            &InferenceProvider::Anthropic,
+            status,
+            Some("invalid_request_error")
+        ));
+        assert!(!config.triggers(
+            &InferenceProvider::GoogleGemini,
+            status,
+            Some("overloaded_error")
+        ));
+    }
+
+    #[test]
+    fn status_codes_trigger_for_any_provider() {
+        let config = config();
+        assert!(config.triggers(
+            &InferenceProvider::GoogleGemini,
+            StatusCode::SERVICE_UNAVAILABLE,
+            None
+        ));
+        assert!(!config.triggers(
+            &InferenceProvider::GoogleGemini,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            None
+        ));
+        // successful responses never fail over
+        assert!(!config.triggers(
+            &InferenceProvider::Anthropic,
+            StatusCode::OK,
+            Some("overloaded_error")
+        ));
+    }
+
+    #[test]
+    fn server_errors_trigger_for_any_5xx() {
+        let config = FailoverConfig {
+            server_errors: true,
+            ..config()
+        };
+        assert!(config.triggers(
+            &InferenceProvider::GoogleGemini,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            None
+        ));
+        assert!(config.triggers(
+            &InferenceProvider::OpenAI,
+            StatusCode::BAD_GATEWAY,
+            None
+        ));
+        assert!(!config.triggers(
+            &InferenceProvider::OpenAI,
+            StatusCode::TOO_MANY_REQUESTS,
+            None
+        ));
+    }
+}
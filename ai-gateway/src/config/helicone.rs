@@ -1,3 +1,5 @@
+use std::{num::NonZeroU32, time::Duration};
+
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -41,6 +43,37 @@ pub struct HeliconeConfig {
     /// The mode of Helicone features to enable.
     #[serde(default)]
     pub features: HeliconeFeatures,
+    /// If enabled, the token usage of responses that don't report it is
+    /// estimated when logging, and the log is flagged as estimated.
+    #[serde(default)]
+    pub estimate_missing_usage: bool,
+    /// When the gateway considers the logging backend to be down.
+    #[serde(default)]
+    pub logging_degradation: LoggingDegradationConfig,
+}
+
+/// Requests are still served while logs can't be sent to Helicone, but
+/// once enough logs in a row have failed, the gateway enters a degraded
+/// mode that is signaled by the `logging_degraded` metric and a warning
+/// logged periodically, until a log succeeds again.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LoggingDegradationConfig {
+    /// The number of consecutive failed logs after which the gateway enters
+    /// the degraded mode.
+    pub failure_threshold: NonZeroU32,
+    /// How often the warning is repeated while in the degraded mode.
+    #[serde(with = "humantime_serde")]
+    pub warning_interval: Duration,
+}
+
+impl Default for LoggingDegradationConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: NonZeroU32::new(5).expect("5 is non-zero"),
+            warning_interval: Duration::from_secs(60),
+        }
+    }
 }
 
 impl HeliconeConfig {
@@ -74,6 +107,8 @@ impl Default for HeliconeConfig {
             base_url: default_base_url(),
             websocket_url: default_websocket_url(),
             features: HeliconeFeatures::None,
+            estimate_missing_usage: false,
+            logging_degradation: LoggingDegradationConfig::default(),
         }
     }
 }
@@ -105,6 +140,8 @@ impl crate::tests::TestDefault for HeliconeConfig {
                 .unwrap(),
             features: HeliconeFeatures::All,
             api_key: default_api_key(),
+            estimate_missing_usage: false,
+            logging_degradation: LoggingDegradationConfig::default(),
         }
     }
 }
@@ -129,6 +166,8 @@ impl<'de> Deserialize<'de> for HeliconeConfig {
             BaseUrl,
             WebsocketUrl,
             Features,
+            EstimateMissingUsage,
+            LoggingDegradation,
             Authentication,
             Observability,
             #[serde(rename = "__prompts")]
@@ -155,6 +194,8 @@ impl<'de> Deserialize<'de> for HeliconeConfig {
                 let mut base_url = None;
                 let mut websocket_url = None;
                 let mut features = None;
+                let mut estimate_missing_usage = None;
+                let mut logging_degradation = None;
                 let mut authentication = None;
                 let mut observability = None;
                 let mut prompts = None;
@@ -193,6 +234,22 @@ impl<'de> Deserialize<'de> for HeliconeConfig {
                             }
                             features = Some(map.next_value()?);
                         }
+                        Field::EstimateMissingUsage => {
+                            if estimate_missing_usage.is_some() {
+                                return Err(de::Error::duplicate_field(
+                                    "estimate_missing_usage",
+                                ));
+                            }
+                            estimate_missing_usage = Some(map.next_value()?);
+                        }
+                        Field::LoggingDegradation => {
+                            if logging_degradation.is_some() {
+                                return Err(de::Error::duplicate_field(
+                                    "logging_degradation",
+                                ));
+                            }
+                            logging_degradation = Some(map.next_value()?);
+                        }
                         Field::Authentication => {
                             if authentication.is_some() {
                                 return Err(de::Error::duplicate_field(
@@ -252,6 +309,10 @@ impl<'de> Deserialize<'de> for HeliconeConfig {
                     websocket_url: websocket_url
                         .unwrap_or_else(default_websocket_url),
                     features,
+                    estimate_missing_usage: estimate_missing_usage
+                        .unwrap_or_default(),
+                    logging_degradation: logging_degradation
+                        .unwrap_or_default(),
                 })
             }
         }
@@ -261,6 +322,8 @@ impl<'de> Deserialize<'de> for HeliconeConfig {
             "base_url",
             "websocket_url",
             "features",
+            "estimate_missing_usage",
+            "logging_degradation",
             "authentication",
             "observability",
             "__prompts",
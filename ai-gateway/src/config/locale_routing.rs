@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::provider::InferenceProvider;
+
+/// Routes requests to a provider based on their locale, e.g. to send
+/// non-English prompts to a provider with strong multilingual models.
+///
+/// The locale is read from the `x-helicone-locale` header, or optionally
+/// detected from the prompt. Requests whose locale isn't listed are
+/// balanced as usual.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LocaleRoutingConfig {
+    /// Providers keyed by language tag, e.g. `de` or `pt-BR`.
+    ///
+    /// Tags are matched case-insensitively, falling back to the primary
+    /// language, so `de` also matches requests declaring `de-AT`. Each
+    /// provider must be one of the router's load balanced providers.
+    pub locales: HashMap<String, InferenceProvider>,
+    /// If enabled, the locale of requests without an `x-helicone-locale`
+    /// header is detected from the script of the prompt. Only languages with
+    /// a distinctive script, e.g. `ja` or `ru`, can be detected.
+    #[serde(default)]
+    pub detect: bool,
+}
+
+impl LocaleRoutingConfig {
+    /// Returns the provider requests with the given locale are routed to.
+    #[must_use]
+    pub fn provider(&self, locale: &str) -> Option<&InferenceProvider> {
+        let locale = locale.trim();
+        let primary = locale.split(['-', '_']).next().unwrap_or(locale);
+        self.lookup(locale).or_else(|| self.lookup(primary))
+    }
+
+    fn lookup(&self, tag: &str) -> Option<&InferenceProvider> {
+        self.locales
+            .iter()
+            .find(|(locale, _)| locale.eq_ignore_ascii_case(tag))
+            .map(|(_, provider)| provider)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locales_fall_back_to_primary_language() {
+        let config = LocaleRoutingConfig {
+            locales: HashMap::from([
+                ("de".to_string(), InferenceProvider::Anthropic),
+                ("pt-BR".to_string(), InferenceProvider::GoogleGemini),
+            ]),
+            detect: false,
+        };
+        assert_eq!(config.provider("de"), Some(&InferenceProvider::Anthropic));
+        assert_eq!(
+            config.provider("de-AT"),
+            Some(&InferenceProvider::Anthropic)
+        );
+        assert_eq!(
+            config.provider("pt-br"),
+            Some(&InferenceProvider::GoogleGemini)
+        );
+        assert_eq!(config.provider("pt-PT"), None);
+        assert_eq!(config.provider("fr"), None);
+    }
+}
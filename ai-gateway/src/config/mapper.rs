@@ -0,0 +1,175 @@
+use std::num::NonZeroUsize;
+
+use serde::{Deserialize, Serialize};
+
+/// Configuration for how request and response bodies are mapped between
+/// provider formats.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct MapperConfig {
+    /// If enabled, OpenAI-format responses from providers that don't return a
+    /// `system_fingerprint` will have a stable one synthesized from the
+    /// provider and model that served the request and the gateway's config.
+    ///
+    /// Fingerprints returned by the provider are always passed through as is.
+    pub synthesize_system_fingerprint: bool,
+    /// If enabled, chat completion requests containing only system messages
+    /// are forwarded to providers that accept them, rather than rejected.
+    ///
+    /// Requests with an empty messages array are always rejected.
+    pub allow_system_only_messages: bool,
+    /// If enabled, non-streaming JSON response bodies are pretty-printed,
+    /// which can be useful when debugging.
+    ///
+    /// Otherwise, response bodies are returned minified, or exactly as the
+    /// provider returned them when no mapping is needed.
+    pub pretty_print_responses: bool,
+    /// If enabled, the `usage` of OpenAI-format responses is normalized to
+    /// `OpenAI`'s shape for every provider: prompt tokens include cached
+    /// tokens, and the cached, cache creation and reasoning tokens the
+    /// provider reported are broken down in `prompt_tokens_details` and
+    /// `completion_tokens_details`.
+    ///
+    /// Otherwise, only the token totals are mapped for providers that don't
+    /// use the `OpenAI` format.
+    pub normalize_usage: bool,
+    /// What to do when a non-streaming chat completion request sets
+    /// `tool_choice: required` and the provider responds without calling a
+    /// tool.
+    pub required_tool_choice: RequiredToolChoice,
+    /// What to do when a non-streaming chat completion request asks for
+    /// `json_schema` structured output and the content of the provider's
+    /// response doesn't match the schema.
+    pub response_schema_validation: ResponseSchemaValidation,
+    /// If set, requests to providers that support tenant attribution are
+    /// tagged with the authenticated caller when the client didn't set an
+    /// identifier itself, i.e. `user` for `OpenAI` and `metadata.user_id`
+    /// for `Anthropic`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_attribution: Option<TenantAttributionConfig>,
+    /// If set, at most this many `choices` of OpenAI-format responses are
+    /// returned to the client, and any extra ones the provider returned,
+    /// e.g. because the request set `n`, are dropped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_choices: Option<NonZeroUsize>,
+    /// What to do when a chat completion request sets a `seed` for a
+    /// provider that can't honor it, e.g. `Anthropic`, so the response
+    /// isn't reproducible.
+    pub unsupported_seed: UnsupportedSeed,
+    /// What to do when a request mapped to a provider that requires
+    /// messages to alternate between the `user` and `assistant` roles, e.g.
+    /// `Anthropic`, contains consecutive messages of the same role.
+    pub role_alternation: RoleAlternation,
+    /// If set, request bodies larger than this many bytes are rejected with
+    /// a `413` before they are mapped. Routers and providers can set lower
+    /// limits of their own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_request_body_bytes: Option<usize>,
+    /// If set, non-streaming response bodies larger than this many bytes
+    /// are failed with a `502` rather than buffered to be mapped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_response_body_bytes: Option<usize>,
+    /// What to do when a chat completion request has top-level fields that
+    /// aren't part of the `OpenAI` API, e.g. a misspelled `temprature`.
+    pub unknown_fields: UnknownFields,
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TenantAttributionConfig {
+    /// Which identifier of the caller is sent to the provider.
+    pub identifier: TenantIdentifier,
+    /// If enabled, a SHA-256 hash of the identifier is sent instead of the
+    /// identifier itself.
+    pub hash: bool,
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum TenantIdentifier {
+    /// The organization the caller's API key belongs to.
+    #[default]
+    Org,
+    /// The user the caller's API key belongs to.
+    User,
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum RequiredToolChoice {
+    /// Return the provider's response as is.
+    #[default]
+    Passthrough,
+    /// Retry the request once with an additional system message instructing
+    /// the model to call a tool, and return an error if the provider still
+    /// doesn't call one.
+    Retry,
+    /// Return an error.
+    Error,
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnsupportedSeed {
+    /// Send the request without the seed.
+    #[default]
+    Passthrough,
+    /// Return an error.
+    Reject,
+    /// Send the request without the seed, and set the
+    /// `helicone-nondeterministic` header on the response.
+    Header,
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoleAlternation {
+    /// Send the messages as is, and let the provider reject them.
+    #[default]
+    Passthrough,
+    /// Merge the content of consecutive messages of the same role into one
+    /// message.
+    Merge,
+    /// Insert a placeholder message of the other role between consecutive
+    /// messages of the same role. Consecutive tool results are still merged.
+    Insert,
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResponseSchemaValidation {
+    /// Return the provider's response as is, without validating it.
+    #[default]
+    Passthrough,
+    /// Retry the request once, and return an error if the provider's
+    /// response still doesn't match the schema.
+    Retry,
+    /// Return an error.
+    Error,
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnknownFields {
+    /// Ignore the fields, like most providers do.
+    #[default]
+    Passthrough,
+    /// Return an error naming the fields.
+    Reject,
+}
@@ -1,22 +1,41 @@
 pub mod balance;
 pub mod cache;
+pub mod client_auth;
 pub mod control_plane;
 pub mod database;
 pub mod deployment_target;
 pub mod discover;
 pub mod dispatcher;
+pub mod failover;
 pub mod helicone;
+pub mod locale_routing;
+pub mod mapper;
 pub mod minio;
 pub mod model_mapping;
+pub mod moderation;
 pub mod monitor;
+pub mod pricing;
+pub mod prompts;
 pub mod providers;
+pub mod queue;
 pub mod rate_limit;
+pub mod redaction;
 pub mod redis;
+pub mod request_fingerprint;
 pub mod response_headers;
 pub mod retry;
 pub mod router;
+pub mod sampling_bounds;
 pub mod server;
+pub mod spend_limit;
+pub mod stickiness;
+pub mod stream_limit;
+pub mod stream_moderation;
+pub mod tool_call_turns;
 pub mod validation;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm_plugin;
+pub mod weight_schedule;
 use std::path::PathBuf;
 
 use config::ConfigError;
@@ -49,7 +68,7 @@ pub enum Error {
     UrlParse(#[from] url::ParseError),
 }
 
-#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct MiddlewareConfig {
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -70,6 +89,8 @@ pub struct Config {
     pub dispatcher: self::dispatcher::DispatcherConfig,
     pub discover: self::discover::DiscoverConfig,
     pub response_headers: self::response_headers::ResponseHeadersConfig,
+    pub mapper: self::mapper::MapperConfig,
+    pub prompts: self::prompts::PromptsConfig,
     pub deployment_target: self::deployment_target::DeploymentTarget,
     pub control_plane: self::control_plane::ControlPlaneConfig,
 
@@ -77,6 +98,8 @@ pub struct Config {
     /// model mapping, then we fallback to this.
     pub default_model_mapping: self::model_mapping::ModelMappingConfig,
     pub helicone: self::helicone::HeliconeConfig,
+    /// How clients send their Helicone API key when auth is enabled.
+    pub client_auth: self::client_auth::ClientAuthConfig,
     /// *ALL* supported providers, independent of router configuration.
     pub providers: self::providers::ProvidersConfig,
 
@@ -84,6 +107,31 @@ pub struct Config {
     pub cache_store: Option<self::cache::CacheStore>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rate_limit_store: Option<self::rate_limit::RateLimitStore>,
+    /// Per-organization spend caps, applied to ALL routes on the
+    /// application.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spend_limit: Option<self::spend_limit::SpendLimitConfig>,
+    /// If set, a fingerprint of each request body is recorded in logs and
+    /// metrics to help identify duplicate requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_fingerprint:
+        Option<self::request_fingerprint::RequestFingerprintConfig>,
+    /// Per-tenant caps on concurrent streaming responses, applied to ALL
+    /// routes on the application.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_limit: Option<self::stream_limit::StreamLimitConfig>,
+    /// If set, the number of assistant tool call turns in each chat
+    /// completion request's messages is recorded in logs and metrics, to
+    /// help identify runaway agent loops.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_turns: Option<self::tool_call_turns::ToolCallTurnsConfig>,
+    /// If set, personal data such as email addresses is redacted from the
+    /// request and response bodies logged to Helicone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redaction: Option<self::redaction::RedactionConfig>,
+    /// Prices of models per thousand tokens. If set, the cost of each
+    /// request is recorded in logs and metrics.
+    pub pricing: self::pricing::PricingConfig,
     /// Global middleware configuration, e.g. rate limiting, caching, etc.
     ///
     /// This configuration will be for middleware that is applied to ALL
@@ -96,6 +144,12 @@ pub struct Config {
     /// requests to the unified API (`/ai`)
     pub unified_api: MiddlewareConfig,
     pub routers: self::router::RouterConfigs,
+    /// Optional WASM plugin applied to all requests before they are routed.
+    ///
+    /// Only available with the `wasm-plugins` feature.
+    #[cfg(feature = "wasm-plugins")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wasm_plugin: Option<self::wasm_plugin::WasmPluginConfig>,
 }
 
 impl Config {
@@ -211,14 +265,25 @@ impl crate::tests::TestDefault for Config {
             unified_api: MiddlewareConfig::default(),
             providers: self::providers::ProvidersConfig::default(),
             helicone: self::helicone::HeliconeConfig::test_default(),
+            client_auth: self::client_auth::ClientAuthConfig::default(),
             deployment_target:
                 self::deployment_target::DeploymentTarget::Sidecar,
             discover: self::discover::DiscoverConfig::test_default(),
             cache_store: Some(self::cache::CacheStore::default()),
             rate_limit_store: Some(self::rate_limit::RateLimitStore::default()),
+            spend_limit: None,
+            request_fingerprint: None,
+            stream_limit: None,
+            tool_call_turns: None,
+            redaction: None,
+            pricing: self::pricing::PricingConfig::default(),
             routers: self::router::RouterConfigs::test_default(),
             response_headers:
                 self::response_headers::ResponseHeadersConfig::default(),
+            mapper: self::mapper::MapperConfig::default(),
+            prompts: self::prompts::PromptsConfig::default(),
+            #[cfg(feature = "wasm-plugins")]
+            wasm_plugin: None,
         }
     }
 }
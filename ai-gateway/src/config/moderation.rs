@@ -0,0 +1,58 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::init::InitError, types::model_id::ModelId};
+
+/// Checks the last user message of each chat request with a moderation
+/// model before the request is dispatched.
+///
+/// Requests scoring at least `threshold` in any of the blocking categories
+/// are rejected with a `400`. Scores of other categories that reach the
+/// threshold are logged as `helicone-property-moderation-*` properties, so
+/// flagged requests can be reviewed without being blocked.
+///
+/// The model's provider must serve an OpenAI compatible `/v1/moderations`
+/// endpoint. If the check itself fails the request fails too, rather than
+/// being dispatched unchecked.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ModerationConfig {
+    /// The moderation model, e.g. `openai/omni-moderation-latest`.
+    pub model: ModelId,
+    /// Categories that block a request, e.g. `violence` or
+    /// `self-harm/intent`.
+    pub blocking_categories: Vec<String>,
+    /// The minimum score, in `(0, 1]`, for a category to be flagged.
+    #[serde(default = "default_threshold")]
+    pub threshold: Decimal,
+}
+
+impl ModerationConfig {
+    pub fn validate(&self) -> Result<(), InitError> {
+        if self.threshold <= Decimal::ZERO || self.threshold > Decimal::ONE {
+            return Err(InitError::InvalidModeration(format!(
+                "threshold must be in (0, 1]: {}",
+                self.threshold
+            )));
+        }
+        if self
+            .blocking_categories
+            .iter()
+            .any(|category| category.trim().is_empty())
+        {
+            return Err(InitError::InvalidModeration(
+                "blocking categories must not be empty".to_string(),
+            ));
+        }
+        if self.model.inference_provider().is_none() {
+            return Err(InitError::ModelIdNotRecognized(
+                self.model.to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn default_threshold() -> Decimal {
+    Decimal::new(5, 1)
+}
@@ -6,6 +6,8 @@ use rust_decimal::{
 };
 use serde::{Deserialize, Serialize};
 
+use crate::{config::redis::RedisConfig, types::provider::InferenceProvider};
+
 const DEFAULT_ERROR_THRESHOLD: f64 = 0.15;
 
 #[derive(
@@ -14,6 +16,16 @@ const DEFAULT_ERROR_THRESHOLD: f64 = 0.15;
 #[serde(deny_unknown_fields, default, rename_all = "kebab-case")]
 pub struct MonitorConfig {
     pub health: HealthMonitorConfig,
+    /// If set, providers are actively probed with synthetic requests so that
+    /// outages are detected even when a provider receives no live traffic.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probe: Option<ProbeConfig>,
+    /// If set, unhealthy providers are recorded in Redis so that replicas
+    /// started later, e.g. after a restart or when scaling out, don't send
+    /// traffic to them before they have seen enough requests to judge their
+    /// health themselves.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persistence: Option<HealthPersistenceConfig>,
 }
 
 impl MonitorConfig {
@@ -77,6 +89,82 @@ pub enum GracePeriod {
     },
 }
 
+/// Synthetic requests sent to providers by the
+/// [`HealthProbe`](crate::discover::monitor::health::HealthProbe).
+///
+/// Probe results are recorded in the same metrics as live traffic, so the
+/// probe interval should be short enough that the probes sent within the
+/// health monitor's `window` satisfy its `grace-period`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Hash)]
+#[serde(deny_unknown_fields, default, rename_all = "kebab-case")]
+pub struct ProbeConfig {
+    /// How often each provider is probed.
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    /// How long to wait for a provider to respond to a probe before
+    /// considering it failed.
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    /// The request sent in each probe.
+    pub endpoint: ProbeEndpoint,
+    /// The content of the user message sent in each probe.
+    pub prompt: String,
+    /// The `max_tokens` sent in each probe, kept small so that probes are
+    /// cheap.
+    pub max_tokens: u32,
+    /// The providers to probe. If empty, all providers used by the configured
+    /// routers are probed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub providers: Vec<InferenceProvider>,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(2),
+            timeout: Duration::from_secs(10),
+            endpoint: ProbeEndpoint::default(),
+            prompt: "ping".to_string(),
+            max_tokens: 1,
+            providers: Vec::new(),
+        }
+    }
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProbeEndpoint {
+    /// A chat completion of `max-tokens` tokens, which is served the same way
+    /// as live traffic.
+    #[default]
+    ChatCompletion,
+    /// A listing of the provider's models, which costs nothing but doesn't
+    /// show whether the provider can serve completions.
+    ///
+    /// Providers without a models endpoint, e.g. Bedrock, are sent a chat
+    /// completion instead.
+    Models,
+}
+
+/// Where and for how long unhealthy providers are persisted by the
+/// [`HealthStore`](crate::discover::monitor::health::HealthStore).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Hash)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct HealthPersistenceConfig {
+    #[serde(default)]
+    pub redis: RedisConfig,
+    /// How long a provider is considered unhealthy by other replicas after
+    /// it was last seen unhealthy.
+    #[serde(default = "default_persistence_ttl", with = "humantime_serde")]
+    pub ttl: Duration,
+}
+
+fn default_persistence_ttl() -> Duration {
+    Duration::from_secs(60)
+}
+
 fn default_grace_period() -> GracePeriod {
     GracePeriod::Requests { min_requests: 20 }
 }
@@ -127,6 +215,8 @@ impl crate::tests::TestDefault for MonitorConfig {
     fn test_default() -> Self {
         Self {
             health: HealthMonitorConfig::test_default(),
+            probe: None,
+            persistence: None,
         }
     }
 }
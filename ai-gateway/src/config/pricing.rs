@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use derive_more::Deref;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::types::model_id::{ModelId, ModelIdWithoutVersion};
+
+/// Prices of models, keyed by model, e.g. `openai/gpt-4o-mini`, used to
+/// compute the cost of requests from the token usage reported by providers.
+///
+/// Dated versions of a model use its price unless they have their own.
+#[derive(
+    Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq, Deref,
+)]
+pub struct PricingConfig(HashMap<ModelId, ModelPrice>);
+
+impl PricingConfig {
+    /// Returns the cost in USD of the given number of tokens of `model`, or
+    /// `None` if the model has no price.
+    #[must_use]
+    pub fn cost(
+        &self,
+        model: &ModelId,
+        prompt_tokens: u64,
+        completion_tokens: u64,
+    ) -> Option<Decimal> {
+        let price = self.0.get(model).or_else(|| {
+            let model = ModelIdWithoutVersion::from(model.clone());
+            self.0
+                .iter()
+                .find(|(priced, _)| {
+                    ModelIdWithoutVersion::from((*priced).clone()) == model
+                })
+                .map(|(_, price)| price)
+        })?;
+        Some(price.total(prompt_tokens, completion_tokens))
+    }
+}
+
+impl FromIterator<(ModelId, ModelPrice)> for PricingConfig {
+    fn from_iter<T: IntoIterator<Item = (ModelId, ModelPrice)>>(
+        iter: T,
+    ) -> Self {
+        Self(HashMap::from_iter(iter))
+    }
+}
+
+/// The price in USD per thousand tokens.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ModelPrice {
+    #[serde(default)]
+    pub input: Decimal,
+    #[serde(default)]
+    pub output: Decimal,
+}
+
+impl ModelPrice {
+    /// Returns the cost in USD of the given number of tokens.
+    #[must_use]
+    pub fn total(&self, input_tokens: u64, output_tokens: u64) -> Decimal {
+        let per_token = Decimal::from(1_000);
+        (self.input * Decimal::from(input_tokens)
+            + self.output * Decimal::from(output_tokens))
+            / per_token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn model(model: &str) -> ModelId {
+        ModelId::from_str(model).unwrap()
+    }
+
+    fn pricing() -> PricingConfig {
+        PricingConfig::from_iter([(
+            model("openai/gpt-4o-mini"),
+            ModelPrice {
+                input: Decimal::from_str("0.15").unwrap(),
+                output: Decimal::from_str("0.6").unwrap(),
+            },
+        )])
+    }
+
+    #[test]
+    fn cost_of_known_model_is_per_thousand_tokens() {
+        let cost = pricing().cost(&model("openai/gpt-4o-mini"), 2_000, 500);
+        assert_eq!(cost, Some(Decimal::from_str("0.6").unwrap()));
+        // dated versions use the price of their model
+        let cost =
+            pricing().cost(&model("openai/gpt-4o-mini-2024-07-18"), 1_000, 0);
+        assert_eq!(cost, Some(Decimal::from_str("0.15").unwrap()));
+    }
+
+    #[test]
+    fn unknown_model_has_no_cost() {
+        assert_eq!(pricing().cost(&model("openai/gpt-4o"), 1_000, 1_000), None);
+    }
+
+    #[test]
+    fn pricing_deserializes() {
+        let yaml = r#"
+openai/gpt-4o-mini:
+  input: 0.15
+  output: 0.6
+"#;
+        let config: PricingConfig = serde_yml::from_str(yaml).unwrap();
+        assert_eq!(config, pricing());
+    }
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// Configuration for how prompt templates are applied to requests.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PromptsConfig {
+    /// Maximum nesting depth of the `tools` and `response_format` schemas in
+    /// a prompt template. Requests with deeper schemas are rejected rather
+    /// than processed.
+    pub max_schema_depth: usize,
+    /// Log the id and version of each resolved prompt, along with the names
+    /// of the variables that were substituted. Variable values are never
+    /// logged.
+    pub log_resolution: bool,
+    /// What to do with requests whose `prompt_id` is present, but whose
+    /// prompt parameters are malformed.
+    pub malformed_params: MalformedPromptParams,
+}
+
+/// How requests with malformed prompt parameters, e.g. a `prompt_id` that
+/// isn't a string, are handled.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum MalformedPromptParams {
+    /// The request is rejected with a 400 describing the problem.
+    #[default]
+    Reject,
+    /// The prompt is ignored and the request is sent as is.
+    Ignore,
+}
+
+impl Default for PromptsConfig {
+    fn default() -> Self {
+        Self {
+            max_schema_depth: default_max_schema_depth(),
+            log_resolution: false,
+            malformed_params: MalformedPromptParams::default(),
+        }
+    }
+}
+
+fn default_max_schema_depth() -> usize {
+    64
+}
@@ -1,4 +1,4 @@
-use std::fmt;
+use std::{fmt, num::NonZeroUsize, time::Duration};
 
 use derive_more::{AsRef, Deref, DerefMut};
 use indexmap::{IndexMap, IndexSet};
@@ -24,8 +24,176 @@ pub struct GlobalProviderConfig {
     /// instead load the models from the provider's respective APIs
     pub models: IndexSet<ModelId>,
     pub base_url: Url,
+    /// Base URLs of models that aren't served from `base-url`, such as
+    /// models on beta endpoints or regional hosts, keyed by model name.
+    /// Requests for these exact models are sent to their base URL instead.
+    #[serde(default)]
+    pub model_base_urls: IndexMap<ModelId, Url>,
+    /// The version of the provider's API requests are made against, i.e.
+    /// the `anthropic-version` header for `Anthropic` and the `api-version`
+    /// query parameter for Azure `OpenAI`.
     #[serde(default)]
     pub version: Option<String>,
+    /// Names of the deployments that serve each model, keyed by model name.
+    /// Only used by Azure `OpenAI`, whose request URLs contain the
+    /// deployment rather than the model. Models without a deployment are
+    /// assumed to be deployed under their own name.
+    #[serde(default)]
+    pub deployments: IndexMap<ModelId, String>,
+    /// Which kinds of requests the provider accepts. Requests of a kind
+    /// the provider doesn't support are converted by the gateway.
+    #[serde(default)]
+    pub streaming: StreamingSupport,
+    /// The `User-Agent` header sent to the provider. If not set, a user
+    /// agent identifying the gateway is sent.
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Additional headers sent with every request to the provider, e.g.
+    /// for gateways in front of it. Values may reference environment
+    /// variables as `${NAME}`, which are resolved at startup.
+    ///
+    /// Headers the gateway sets itself, such as the provider's auth and
+    /// content type headers, can't be overridden.
+    #[serde(default)]
+    pub headers: IndexMap<String, String>,
+    /// TCP settings for connections to the provider.
+    #[serde(default)]
+    pub tcp: TcpConfig,
+    /// Limits on the size of bodies buffered when mapping requests to the
+    /// provider.
+    #[serde(default)]
+    pub mapping_limits: MappingLimits,
+    /// Splitting of embeddings requests with more inputs than the provider
+    /// accepts in a single request.
+    #[serde(default)]
+    pub embeddings: EmbeddingsConfig,
+    /// Whether the provider deduplicates requests by an `Idempotency-Key`
+    /// header. If so, a key is generated for each request that doesn't
+    /// already have one, and the same key is sent with each of its retries
+    /// so that they aren't charged twice.
+    #[serde(default)]
+    pub idempotency_keys: bool,
+    /// Finish reasons the provider uses for responses that were truncated
+    /// at the maximum number of tokens, other than `length`. These are
+    /// returned to clients as `length`, so that truncation can be detected
+    /// the same way for every provider.
+    #[serde(default)]
+    pub length_finish_reasons: Vec<String>,
+    /// The Google Cloud project requests are sent to. Only used by Vertex
+    /// AI, which falls back to the project of its service account.
+    #[serde(default)]
+    pub project: Option<String>,
+    /// The Google Cloud region requests are sent to. Only used by Vertex
+    /// AI, whose regional base URL is built from it.
+    #[serde(default)]
+    pub region: Option<String>,
+    /// The ARN of an AWS IAM role to assume with the configured credentials.
+    /// Only used by Bedrock, which then signs requests with the temporary
+    /// credentials of the role.
+    #[serde(default)]
+    pub role_arn: Option<String>,
+    /// The external id required by the trust policy of `role-arn`, if any.
+    #[serde(default)]
+    pub external_id: Option<String>,
+}
+
+/// Splitting of embeddings requests whose `input` array has more items than
+/// the provider accepts in a single request.
+///
+/// Split requests are sent to the provider in parallel, and the embeddings
+/// are returned to the client in the order of the original inputs.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, PartialEq,
+)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct EmbeddingsConfig {
+    /// Maximum number of inputs sent to the provider per request. If not
+    /// set, requests are never split.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_inputs: Option<NonZeroUsize>,
+    /// How to respond if some, but not all, of the split requests fail.
+    pub on_partial_failure: PartialFailure,
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, PartialEq,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum PartialFailure {
+    /// The first failed response is returned.
+    #[default]
+    Fail,
+    /// The embeddings of the successful requests are returned, with their
+    /// `index` referring to the original inputs.
+    Partial,
+}
+
+/// Limits on the size of request and response bodies that are buffered in
+/// order to be mapped between provider formats, and on the images in them.
+///
+/// Requests proxied without mapping are streamed through and aren't
+/// subject to these limits.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, PartialEq,
+)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct MappingLimits {
+    /// Maximum size in bytes of a request body to be mapped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_request_bytes: Option<usize>,
+    /// Maximum size in bytes of a non-streaming response body to be
+    /// mapped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<usize>,
+    /// Maximum number of images in a chat completion request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_images: Option<usize>,
+    /// Maximum decoded size in bytes of each base64 encoded image in a chat
+    /// completion request. Images referenced by URL are fetched by the
+    /// provider, so their size isn't known and they aren't limited.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_image_bytes: Option<usize>,
+    /// If enabled, images larger than `max-image-bytes` are downscaled until
+    /// they fit, rather than rejected.
+    pub downscale_images: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TcpConfig {
+    /// Whether to disable Nagle's algorithm, so that small writes are sent
+    /// immediately.
+    pub nodelay: bool,
+    /// How often keepalive probes are sent on idle connections, so that
+    /// dead connections are detected. If not set, keepalive is disabled.
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    pub keepalive: Option<Duration>,
+}
+
+impl Default for TcpConfig {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            keepalive: None,
+        }
+    }
+}
+
+/// Whether a provider supports streaming, non-streaming, or both kinds of
+/// chat completion requests.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, PartialEq,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum StreamingSupport {
+    #[default]
+    Both,
+    /// Non-streaming requests are sent as streaming requests, and the
+    /// streamed chunks are aggregated into a single response.
+    StreamOnly,
+    /// Streaming requests are sent as non-streaming requests, and the
+    /// response is sent to the client as a stream with a single chunk.
+    NonStreamOnly,
 }
 
 /// Map of *ALL* supported providers.
@@ -47,7 +215,35 @@ impl<'de> Deserialize<'de> for ProvidersConfig {
             models: IndexSet<String>,
             base_url: Url,
             #[serde(default)]
+            model_base_urls: IndexMap<String, Url>,
+            #[serde(default)]
             version: Option<String>,
+            #[serde(default)]
+            deployments: IndexMap<String, String>,
+            #[serde(default)]
+            streaming: StreamingSupport,
+            #[serde(default)]
+            user_agent: Option<String>,
+            #[serde(default)]
+            headers: IndexMap<String, String>,
+            #[serde(default)]
+            tcp: TcpConfig,
+            #[serde(default)]
+            mapping_limits: MappingLimits,
+            #[serde(default)]
+            embeddings: EmbeddingsConfig,
+            #[serde(default)]
+            idempotency_keys: bool,
+            #[serde(default)]
+            length_finish_reasons: Vec<String>,
+            #[serde(default)]
+            project: Option<String>,
+            #[serde(default)]
+            region: Option<String>,
+            #[serde(default)]
+            role_arn: Option<String>,
+            #[serde(default)]
+            external_id: Option<String>,
         }
 
         impl<'de> Visitor<'de> for ProvidersConfigVisitor {
@@ -76,27 +272,56 @@ impl<'de> Deserialize<'de> for ProvidersConfig {
 
                     // Convert model strings to ModelId using the provider
                     // context
+                    let model_id = |model_str: &str| {
+                        ModelId::from_str_and_provider(
+                            provider.clone(),
+                            model_str,
+                        )
+                        .map_err(|e| {
+                            de::Error::custom(format!(
+                                "Invalid model '{model_str}' for provider \
+                                 {provider}: {e}"
+                            ))
+                        })
+                    };
                     let models = raw_config
                         .models
+                        .iter()
+                        .map(|model_str| model_id(model_str))
+                        .collect::<Result<IndexSet<_>, _>>()?;
+                    let model_base_urls = raw_config
+                        .model_base_urls
                         .into_iter()
-                        .map(|model_str| {
-                            ModelId::from_str_and_provider(
-                                provider.clone(),
-                                &model_str,
-                            )
-                            .map_err(|e| {
-                                de::Error::custom(format!(
-                                    "Invalid model '{model_str}' for provider \
-                                     {provider}: {e}"
-                                ))
-                            })
+                        .map(|(model_str, url)| {
+                            Ok((model_id(&model_str)?, url))
                         })
-                        .collect::<Result<IndexSet<_>, _>>()?;
+                        .collect::<Result<IndexMap<_, _>, _>>()?;
+                    let deployments = raw_config
+                        .deployments
+                        .into_iter()
+                        .map(|(model_str, deployment)| {
+                            Ok((model_id(&model_str)?, deployment))
+                        })
+                        .collect::<Result<IndexMap<_, _>, _>>()?;
 
                     let config = GlobalProviderConfig {
                         models,
                         base_url: raw_config.base_url,
+                        model_base_urls,
                         version: raw_config.version,
+                        deployments,
+                        streaming: raw_config.streaming,
+                        user_agent: raw_config.user_agent,
+                        headers: raw_config.headers,
+                        tcp: raw_config.tcp,
+                        mapping_limits: raw_config.mapping_limits,
+                        embeddings: raw_config.embeddings,
+                        idempotency_keys: raw_config.idempotency_keys,
+                        length_finish_reasons: raw_config.length_finish_reasons,
+                        project: raw_config.project,
+                        region: raw_config.region,
+                        role_arn: raw_config.role_arn,
+                        external_id: raw_config.external_id,
                     };
 
                     providers.insert(provider, config);
@@ -121,8 +346,31 @@ impl Serialize for ProvidersConfig {
         struct SerializedGlobalProviderConfig {
             models: IndexSet<String>,
             base_url: Url,
+            #[serde(skip_serializing_if = "IndexMap::is_empty")]
+            model_base_urls: IndexMap<String, Url>,
             #[serde(skip_serializing_if = "Option::is_none")]
             version: Option<String>,
+            #[serde(skip_serializing_if = "IndexMap::is_empty")]
+            deployments: IndexMap<String, String>,
+            streaming: StreamingSupport,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            user_agent: Option<String>,
+            #[serde(skip_serializing_if = "IndexMap::is_empty")]
+            headers: IndexMap<String, String>,
+            tcp: TcpConfig,
+            mapping_limits: MappingLimits,
+            embeddings: EmbeddingsConfig,
+            idempotency_keys: bool,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            length_finish_reasons: Vec<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            project: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            region: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            role_arn: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            external_id: Option<String>,
         }
 
         let mut map = serializer.serialize_map(Some(self.0.len()))?;
@@ -135,7 +383,31 @@ impl Serialize for ProvidersConfig {
             let serialized_config = SerializedGlobalProviderConfig {
                 models: models_as_strings,
                 base_url: config.base_url.clone(),
+                model_base_urls: config
+                    .model_base_urls
+                    .iter()
+                    .map(|(model, url)| (model.to_string(), url.clone()))
+                    .collect(),
                 version: config.version.clone(),
+                deployments: config
+                    .deployments
+                    .iter()
+                    .map(|(model, deployment)| {
+                        (model.to_string(), deployment.clone())
+                    })
+                    .collect(),
+                streaming: config.streaming,
+                user_agent: config.user_agent.clone(),
+                headers: config.headers.clone(),
+                tcp: config.tcp.clone(),
+                mapping_limits: config.mapping_limits,
+                embeddings: config.embeddings,
+                idempotency_keys: config.idempotency_keys,
+                length_finish_reasons: config.length_finish_reasons.clone(),
+                project: config.project.clone(),
+                region: config.region.clone(),
+                role_arn: config.role_arn.clone(),
+                external_id: config.external_id.clone(),
             };
 
             map.serialize_entry(provider, &serialized_config)?;
@@ -172,6 +444,93 @@ mod tests {
         // just want to make sure we don't panic...
     }
 
+    #[test]
+    fn tcp_config_defaults_to_nodelay_without_keepalive() {
+        for config in ProvidersConfig::default().values() {
+            assert_eq!(config.tcp, TcpConfig::default());
+        }
+        assert!(TcpConfig::default().nodelay);
+        assert_eq!(TcpConfig::default().keepalive, None);
+    }
+
+    #[test]
+    fn model_base_urls_deserialize() {
+        let yaml = r#"
+openai:
+  models:
+    - "gpt-4o-mini"
+    - "gpt-4o"
+  base-url: https://api.openai.com
+  model-base-urls:
+    gpt-4o: https://eu.api.openai.com
+"#;
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let openai = config.get(&InferenceProvider::OpenAI).unwrap();
+        let gpt_4o =
+            ModelId::from_str_and_provider(InferenceProvider::OpenAI, "gpt-4o")
+                .unwrap();
+        assert_eq!(
+            openai.model_base_urls.get(&gpt_4o).map(Url::as_str),
+            Some("https://eu.api.openai.com/")
+        );
+        assert_eq!(openai.model_base_urls.len(), 1);
+
+        let serialized = serde_json::to_string(&config).unwrap();
+        let deserialized: ProvidersConfig =
+            serde_json::from_str(&serialized).unwrap();
+        assert_eq!(config, deserialized);
+    }
+
+    #[test]
+    fn deployments_deserialize() {
+        let yaml = r#"
+azure:
+  models:
+    - "gpt-4o"
+  base-url: https://my-resource.openai.azure.com
+  version: "2024-10-21"
+  deployments:
+    gpt-4o: my-gpt-4o
+"#;
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let provider = InferenceProvider::Named("azure".into());
+        let azure = config.get(&provider).unwrap();
+        let gpt_4o =
+            ModelId::from_str_and_provider(provider.clone(), "gpt-4o").unwrap();
+        assert_eq!(
+            azure.deployments.get(&gpt_4o).map(String::as_str),
+            Some("my-gpt-4o")
+        );
+        assert_eq!(azure.version.as_deref(), Some("2024-10-21"));
+
+        let serialized = serde_json::to_string(&config).unwrap();
+        let deserialized: ProvidersConfig =
+            serde_json::from_str(&serialized).unwrap();
+        assert_eq!(config, deserialized);
+    }
+
+    #[test]
+    fn tcp_config_deserializes() {
+        let yaml = r#"
+openai:
+  models:
+    - "gpt-4o-mini"
+  base-url: https://api.openai.com
+  tcp:
+    nodelay: false
+    keepalive: 30s
+"#;
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let tcp = &config.get(&InferenceProvider::OpenAI).unwrap().tcp;
+        assert!(!tcp.nodelay);
+        assert_eq!(tcp.keepalive, Some(Duration::from_secs(30)));
+
+        let serialized = serde_json::to_string(&config).unwrap();
+        let deserialized: ProvidersConfig =
+            serde_json::from_str(&serialized).unwrap();
+        assert_eq!(config, deserialized);
+    }
+
     #[test]
     fn test_providers_config_custom_deserialize() {
         use chrono::TimeZone;
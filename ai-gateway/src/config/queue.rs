@@ -0,0 +1,69 @@
+use std::{collections::HashMap, num::NonZeroUsize};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::model_id::ModelId;
+
+/// Bounds how many requests a router dispatches at once. Requests beyond
+/// the bound wait in a queue, from which requests for higher tier models
+/// are admitted first, so that under load premium traffic isn't held up
+/// behind free traffic. Requests for models of the same tier are admitted
+/// in the order they arrived.
+///
+/// Requests are counted per gateway instance, from when they are dispatched
+/// until their response body has been read.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct QueueConfig {
+    /// Maximum number of requests dispatched at once.
+    pub max_concurrent: NonZeroUsize,
+    /// The tier of each model, e.g. `openai/gpt-4o: premium`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tiers: HashMap<ModelId, ModelTier>,
+    /// The tier of models without one in `tiers`.
+    #[serde(default)]
+    pub default_tier: ModelTier,
+}
+
+impl QueueConfig {
+    #[must_use]
+    pub fn tier(&self, model: Option<&ModelId>) -> ModelTier {
+        model
+            .and_then(|model| self.tiers.get(model))
+            .copied()
+            .unwrap_or(self.default_tier)
+    }
+}
+
+/// How valuable requests for a model are, in increasing order of priority.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ModelTier {
+    Free,
+    #[default]
+    Standard,
+    Premium,
+}
+
+#[cfg(feature = "testing")]
+impl crate::tests::TestDefault for QueueConfig {
+    fn test_default() -> Self {
+        Self {
+            max_concurrent: NonZeroUsize::new(1).expect("1 is non-zero"),
+            tiers: HashMap::new(),
+            default_tier: ModelTier::Standard,
+        }
+    }
+}
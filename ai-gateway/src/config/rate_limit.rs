@@ -1,6 +1,11 @@
-use std::{num::NonZeroU32, time::Duration};
+use std::{
+    collections::HashMap,
+    num::{NonZeroU32, NonZeroU64},
+    time::Duration,
+};
 
 use axum_core::response::IntoResponse;
+use governor::Quota;
 use http::StatusCode;
 use serde::{Deserialize, Serialize};
 use tower_governor::governor::{GovernorConfig, GovernorConfigBuilder};
@@ -15,7 +20,7 @@ use crate::{
         mapper::openai::SERVER_ERROR_TYPE,
         rate_limit::extractor::RateLimitKeyExtractor,
     },
-    types::json::Json,
+    types::{json::Json, model_id::ModelId},
 };
 
 pub type RateLimiterConfig = GovernorConfig<
@@ -23,9 +28,7 @@ pub type RateLimiterConfig = GovernorConfig<
     governor::middleware::StateInformationMiddleware,
 >;
 
-#[derive(
-    Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq, Hash,
-)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct RateLimitConfig {
     /// If not set, the store from the rate-limit-store config will be
@@ -170,12 +173,27 @@ pub fn store_enabled_for_test_redis() -> RateLimitStore {
     })
 }
 
-#[derive(
-    Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq, Hash,
-)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct LimitsConfig {
     pub per_api_key: GcraConfig,
+    /// Limits requests per distinct value of a field of the request body,
+    /// in addition to the limit per API key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_body_field: Option<BodyFieldLimitConfig>,
+    /// Limits requests to a router per model, e.g. `openai/gpt-4o`, in
+    /// addition to the limit per API key. Models without a limit aren't
+    /// limited.
+    ///
+    /// Requests are limited by the model they are sent to the provider with,
+    /// regardless of its version. The limits are always tracked in memory,
+    /// per gateway instance.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub per_model: HashMap<ModelId, GcraConfig>,
+    /// Limits the tokens each API key may consume, in addition to the
+    /// number of requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_limit: Option<TokenLimitConfig>,
 }
 
 #[cfg(feature = "testing")]
@@ -183,7 +201,59 @@ impl crate::tests::TestDefault for LimitsConfig {
     fn test_default() -> Self {
         Self {
             per_api_key: GcraConfig::test_default(),
+            per_body_field: None,
+            per_model: HashMap::new(),
+            token_limit: None,
+        }
+    }
+}
+
+/// A budget of tokens per API key, refilled continuously.
+///
+/// Requests are rejected up front if an estimate of their prompt tokens
+/// exceeds the remaining budget, and the budget is reconciled with the
+/// `usage.total_tokens` reported by the provider once they complete. The
+/// limit is always tracked in memory, per gateway instance.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TokenLimitConfig {
+    /// The duration it takes to refill the entire budget.
+    #[serde(
+        with = "humantime_serde",
+        default = "default_token_refill_frequency"
+    )]
+    pub refill_frequency: Duration,
+    /// The number of tokens in the budget.
+    pub capacity: NonZeroU64,
+}
+
+fn default_token_refill_frequency() -> Duration {
+    Duration::from_secs(60)
+}
+
+/// A limit shared by all requests whose body has the same value at
+/// `pointer`, e.g. the same `project_id`.
+///
+/// Requests without a string or number at `pointer` aren't subject to this
+/// limit. The limit is always tracked in memory, per gateway instance.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BodyFieldLimitConfig {
+    /// A JSON pointer to the field of the request body to limit by, e.g.
+    /// `/metadata/project_id`.
+    pub pointer: String,
+    #[serde(default)]
+    pub limit: GcraConfig,
+}
+
+impl BodyFieldLimitConfig {
+    pub fn validate(&self) -> Result<(), InitError> {
+        if !self.pointer.starts_with('/') {
+            return Err(InitError::InvalidRateLimitConfig(
+                "per-body-field pointer must start with '/'",
+            ));
         }
+        Ok(())
     }
 }
 
@@ -198,6 +268,26 @@ pub struct GcraConfig {
     pub capacity: NonZeroU32,
 }
 
+impl GcraConfig {
+    /// The quota of an in memory limiter enforcing this limit.
+    pub(crate) fn quota(&self) -> Quota {
+        let per_cell_duration = self
+            .refill_frequency
+            .checked_div(self.capacity.into())
+            .filter(|duration| !duration.is_zero())
+            .unwrap_or_else(|| {
+                tracing::warn!(
+                    "fill_frequency is too small for capacity, using default \
+                     fill frequency"
+                );
+                default_refill_frequency()
+            });
+        Quota::with_period(per_cell_duration)
+            .expect("period is non-zero")
+            .allow_burst(self.capacity)
+    }
+}
+
 impl Default for GcraConfig {
     fn default() -> Self {
         Self {
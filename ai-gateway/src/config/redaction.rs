@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// Redaction of personal data from the request and response bodies that are
+/// logged to Helicone.
+///
+/// Only the copies of the bodies that are logged are redacted, what is sent
+/// to the provider and returned to the client is never changed.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RedactionConfig {
+    /// Built-in patterns to redact.
+    #[serde(default = "default_patterns")]
+    pub patterns: Vec<RedactionPattern>,
+    /// Additional regular expressions to redact, e.g. internal account
+    /// numbers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_patterns: Vec<String>,
+    /// The text each match is replaced with.
+    #[serde(default = "default_placeholder")]
+    pub placeholder: String,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            patterns: default_patterns(),
+            custom_patterns: Vec::new(),
+            placeholder: default_placeholder(),
+        }
+    }
+}
+
+fn default_patterns() -> Vec<RedactionPattern> {
+    vec![
+        RedactionPattern::Email,
+        RedactionPattern::Phone,
+        RedactionPattern::CreditCard,
+    ]
+}
+
+fn default_placeholder() -> String {
+    "[REDACTED]".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum RedactionPattern {
+    /// Email addresses.
+    Email,
+    /// Phone numbers of at least 8 digits, optionally separated by dots or
+    /// dashes.
+    Phone,
+    /// Credit card numbers of 13 to 19 digits, optionally separated by
+    /// spaces or dashes, with a valid Luhn checksum.
+    CreditCard,
+}
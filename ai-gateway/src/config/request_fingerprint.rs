@@ -0,0 +1,30 @@
+use std::num::NonZeroU16;
+
+use serde::{Deserialize, Serialize};
+
+/// Fingerprinting of request bodies, to help identify clients that send
+/// many identical requests and would benefit from caching.
+///
+/// Only a hash of the normalized request body is recorded, never the body
+/// itself. The full fingerprint is logged, while the metric is only
+/// labelled with the bucket the fingerprint falls into, to keep its
+/// cardinality low.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RequestFingerprintConfig {
+    /// The number of buckets fingerprints are spread over in metrics.
+    #[serde(default = "default_buckets")]
+    pub buckets: NonZeroU16,
+}
+
+impl Default for RequestFingerprintConfig {
+    fn default() -> Self {
+        Self {
+            buckets: default_buckets(),
+        }
+    }
+}
+
+fn default_buckets() -> NonZeroU16 {
+    NonZeroU16::new(16).expect("16 is non-zero")
+}
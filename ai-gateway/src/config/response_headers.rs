@@ -10,6 +10,12 @@ pub struct ResponseHeadersConfig {
     pub provider: bool,
     #[serde(default = "default_true")]
     pub provider_request_id: bool,
+    /// Returns a summary of the body sent to the provider, after all
+    /// transformations, in the `helicone-effective-request` header. The
+    /// summary is the body's SHA-256 digest and size, so clients can verify
+    /// what was dispatched without the body being exposed.
+    #[serde(default)]
+    pub effective_request: bool,
 }
 
 impl Default for ResponseHeadersConfig {
@@ -17,6 +23,7 @@ impl Default for ResponseHeadersConfig {
         Self {
             provider: true,
             provider_request_id: true,
+            effective_request: false,
         }
     }
 }
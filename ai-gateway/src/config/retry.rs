@@ -67,6 +67,19 @@ impl RetryConfig {
             }
         }
     }
+
+    /// The longest a retry waits for, including when a provider asks for a
+    /// longer wait with a `Retry-After` header.
+    ///
+    /// Constant retries wait for at most their delay or the default
+    /// `max-delay` of exponential retries, whichever is longer.
+    #[must_use]
+    pub fn max_delay(&self) -> Duration {
+        match self {
+            Self::Exponential { max_delay, .. } => *max_delay,
+            Self::Constant { delay, .. } => (*delay).max(default_max_delay()),
+        }
+    }
 }
 
 fn default_factor() -> Decimal {
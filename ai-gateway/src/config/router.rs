@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use derive_more::{AsMut, AsRef};
 use rust_decimal::Decimal;
@@ -11,9 +11,16 @@ use super::{
     retry::RetryConfig,
 };
 use crate::{
-    config::{cache::CacheConfig, rate_limit::RateLimitConfig},
+    config::{
+        cache::CacheConfig, failover::FailoverConfig,
+        locale_routing::LocaleRoutingConfig, moderation::ModerationConfig,
+        queue::QueueConfig, rate_limit::RateLimitConfig,
+        sampling_bounds::SamplingBoundsConfig, stickiness::StickinessConfig,
+        stream_moderation::StreamModerationConfig,
+        weight_schedule::WeightScheduleConfig,
+    },
     error::init::InitError,
-    types::{provider::InferenceProvider, router::RouterId},
+    types::{model_id::ModelId, provider::InferenceProvider, router::RouterId},
 };
 
 #[derive(
@@ -45,17 +52,88 @@ pub struct RouterConfig {
     pub cache: Option<CacheConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retries: Option<RetryConfig>,
+    /// Status codes that non-streaming requests are retried on, in addition
+    /// to server errors, e.g. `429`. A `Retry-After` header on the response
+    /// is waited for instead of the retry strategy's delay.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub retry_status_codes: Vec<u16>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rate_limit: Option<RateLimitConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub providers: Option<HashMap<InferenceProvider, RouterProviderConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale_routing: Option<LocaleRoutingConfig>,
+    #[serde(alias = "fallback", skip_serializing_if = "Option::is_none")]
+    pub failover: Option<FailoverConfig>,
+    /// Headers that every request to the router must include, e.g. a tenant
+    /// header. Requests missing any of them are rejected before dispatch.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub required_headers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight_schedule: Option<WeightScheduleConfig>,
+    /// A system prompt prepended to the messages of every chat request, e.g.
+    /// a safety or brand instruction. It comes ahead of the system messages
+    /// of both the client and stored prompts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt_prefix: Option<String>,
+    /// Log which transformations the gateway applied to the body of each
+    /// request, e.g. mapping it to another provider's format, so that
+    /// differences between what the client sent and what the provider
+    /// received can be explained. Only the kind of each transformation is
+    /// logged, never the content.
+    pub audit_transformations: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_moderation: Option<StreamModerationConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moderation: Option<ModerationConfig>,
+    /// How long a provider has to respond to a request before it is failed
+    /// with a `504`. For streaming requests this only limits the time until
+    /// the stream starts, not the whole stream.
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    pub request_timeout: Option<Duration>,
+    /// Overrides of `request-timeout` for specific models, e.g. reasoning
+    /// models which take much longer to respond than chat models.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub model_timeouts: HashMap<ModelId, ModelTimeout>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue: Option<QueueConfig>,
+    /// Reject requests for stored prompts that don't provide an input for
+    /// every variable of the prompt, rather than sending the unresolved
+    /// variables to the provider. Can also be enabled per request with the
+    /// `helicone-prompt-strict` header.
+    pub strict_prompt_inputs: bool,
+    /// Request bodies larger than this many bytes are rejected with a `413`
+    /// before they are mapped, if it's lower than the global
+    /// `mapper.max-request-body-bytes`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_request_body_bytes: Option<usize>,
+    /// Sampling parameters outside of these bounds are clamped to them
+    /// before the request is dispatched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling_bounds: Option<SamplingBoundsConfig>,
+    /// Send the requests of a conversation to the same provider.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stickiness: Option<StickinessConfig>,
 }
 
 impl RouterConfig {
     pub fn validate(&self) -> Result<(), InitError> {
         for balance_config in self.load_balance.0.values() {
             match balance_config {
-                BalanceConfigInner::ProviderWeighted { providers } => {
+                BalanceConfigInner::ProviderWeighted { providers }
+                | BalanceConfigInner::LeastInFlight { providers } => {
+                    let total =
+                        providers.iter().map(|t| t.weight).sum::<Decimal>();
+                    if total != Decimal::from(1) {
+                        return Err(InitError::InvalidBalancer(format!(
+                            "Balance weights dont sum to 1: {total}"
+                        )));
+                    }
+                }
+                BalanceConfigInner::WeightedLatency {
+                    providers,
+                    latency_bias,
+                } => {
                     let total =
                         providers.iter().map(|t| t.weight).sum::<Decimal>();
                     if total != Decimal::from(1) {
@@ -63,8 +141,16 @@ impl RouterConfig {
                             "Balance weights dont sum to 1: {total}"
                         )));
                     }
+                    if *latency_bias < Decimal::ZERO
+                        || *latency_bias > Decimal::ONE
+                    {
+                        return Err(InitError::InvalidBalancer(format!(
+                            "Latency bias must be between 0 and 1: \
+                             {latency_bias}"
+                        )));
+                    }
                 }
-                BalanceConfigInner::ModelWeighted { models } => {
+                BalanceConfigInner::ModelWeighted { models, fan_out } => {
                     let total =
                         models.iter().map(|m| m.weight).sum::<Decimal>();
                     if total != Decimal::from(1) {
@@ -72,19 +158,199 @@ impl RouterConfig {
                             "Balance weights dont sum to 1: {total}"
                         )));
                     }
+                    if let Some(fan_out) = fan_out
+                        && fan_out.count.get() > models.len().get()
+                    {
+                        return Err(InitError::InvalidBalancer(format!(
+                            "Fan out count {} exceeds number of models: {}",
+                            fan_out.count,
+                            models.len()
+                        )));
+                    }
+                }
+                BalanceConfigInner::CostWeighted { models } => {
+                    for (model, price) in models {
+                        if model.inference_provider().is_none() {
+                            return Err(InitError::ModelIdNotRecognized(
+                                model.to_string(),
+                            ));
+                        }
+                        if price.is_sign_negative() {
+                            return Err(InitError::InvalidBalancer(format!(
+                                "Price of {model} must not be negative: \
+                                 {price}"
+                            )));
+                        }
+                    }
+                }
+                BalanceConfigInner::Complexity {
+                    simple, complex, ..
+                } => {
+                    for model in [simple, complex] {
+                        if model.inference_provider().is_none() {
+                            return Err(InitError::ModelIdNotRecognized(
+                                model.to_string(),
+                            ));
+                        }
+                    }
+                }
+                BalanceConfigInner::RoundRobin { providers } => {
+                    if balance_config.providers().len() != providers.len().get()
+                    {
+                        return Err(InitError::InvalidBalancer(
+                            "Round robin providers must be distinct"
+                                .to_string(),
+                        ));
+                    }
                 }
                 BalanceConfigInner::BalancedLatency { .. }
                 | BalanceConfigInner::ModelLatency { .. } => {}
             }
         }
 
+        if let Some(locale_routing) = &self.locale_routing {
+            let providers = self.load_balance.providers();
+            for (locale, provider) in &locale_routing.locales {
+                if !providers.contains(provider) {
+                    return Err(InitError::InvalidBalancer(format!(
+                        "Provider {provider} for locale {locale} is not load \
+                         balanced by the router"
+                    )));
+                }
+            }
+        }
+
+        if let Some(per_body_field) = self
+            .rate_limit
+            .as_ref()
+            .and_then(|rate_limit| rate_limit.limits.per_body_field.as_ref())
+        {
+            per_body_field.validate()?;
+        }
+
+        if let Some(failover) = &self.failover {
+            for status in &failover.status_codes {
+                if !(400..600).contains(status) {
+                    return Err(InitError::InvalidBalancer(format!(
+                        "Failover status code {status} is not an error status"
+                    )));
+                }
+            }
+        }
+
+        for status in &self.retry_status_codes {
+            if !(400..600).contains(status) {
+                return Err(InitError::InvalidRetryStatusCode(*status));
+            }
+        }
+
+        if let Some(weight_schedule) = &self.weight_schedule {
+            let providers = self.load_balance.providers();
+            for window in &weight_schedule.windows {
+                for (provider, weight) in &window.weights {
+                    if !providers.contains(provider) {
+                        return Err(InitError::InvalidBalancer(format!(
+                            "Provider {provider} in weight schedule is not \
+                             load balanced by the router"
+                        )));
+                    }
+                    if weight.is_sign_negative() {
+                        return Err(InitError::InvalidWeight(provider.clone()));
+                    }
+                }
+            }
+        }
+
+        if let Some(stream_moderation) = &self.stream_moderation
+            && stream_moderation
+                .blocked_terms
+                .iter()
+                .any(|term| term.trim().is_empty())
+        {
+            return Err(InitError::InvalidStreamModeration(
+                "blocked terms must not be empty".to_string(),
+            ));
+        }
+
+        if let Some(moderation) = &self.moderation {
+            moderation.validate()?;
+        }
+
+        if let Some(sampling_bounds) = &self.sampling_bounds {
+            sampling_bounds.validate()?;
+        }
+
+        if let Some(stickiness) = &self.stickiness {
+            stickiness.validate()?;
+        }
+
+        if let Some(cache) = &self.cache {
+            cache.validate()?;
+        }
+
+        for name in &self.required_headers {
+            if http::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(InitError::InvalidRequiredHeader(name.clone()));
+            }
+        }
+
         Ok(())
     }
 
+    /// Returns the first required header missing from `headers`, if any.
+    #[must_use]
+    pub fn missing_header(&self, headers: &http::HeaderMap) -> Option<&str> {
+        self.required_headers
+            .iter()
+            .map(String::as_str)
+            .find(|name| !headers.contains_key(*name))
+    }
+
     #[must_use]
     pub fn model_mappings(&self) -> Option<&ModelMappingConfig> {
         self.model_mappings.as_ref()
     }
+
+    /// The timeout of a request dispatched to `model`: the model's override
+    /// if it has one, otherwise the router's request timeout.
+    #[must_use]
+    pub fn timeout_for(
+        &self,
+        model: Option<&ModelId>,
+        is_stream: bool,
+    ) -> Option<Duration> {
+        model
+            .and_then(|model| self.model_timeouts.get(model))
+            .map(|timeout| timeout.get(is_stream))
+            .or(self.request_timeout)
+    }
+}
+
+/// The request timeout of a model, which may differ for streaming requests
+/// since only the time until their stream starts is limited.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ModelTimeout {
+    #[serde(with = "humantime_serde")]
+    pub request: Duration,
+    /// Defaults to `request`.
+    #[serde(
+        default,
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stream: Option<Duration>,
+}
+
+impl ModelTimeout {
+    #[must_use]
+    pub fn get(&self, is_stream: bool) -> Duration {
+        if is_stream {
+            self.stream.unwrap_or(self.request)
+        } else {
+            self.request
+        }
+    }
 }
 
 #[cfg(feature = "testing")]
@@ -98,14 +364,30 @@ impl crate::tests::TestDefault for RouterConfigs {
                 load_balance: BalanceConfig(HashMap::from([(
                     crate::endpoints::EndpointType::Chat,
                     BalanceConfigInner::BalancedLatency {
-                        providers: nonempty_collections::nes![
+                        providers: nes![
                             crate::types::provider::InferenceProvider::OpenAI
                         ],
                     },
                 )])),
                 retries: None,
+                retry_status_codes: Vec::new(),
                 rate_limit: None,
                 providers: None,
+                locale_routing: None,
+                failover: None,
+                required_headers: Vec::new(),
+                weight_schedule: None,
+                system_prompt_prefix: None,
+                audit_transformations: false,
+                stream_moderation: None,
+                moderation: None,
+                request_timeout: None,
+                model_timeouts: HashMap::new(),
+                queue: None,
+                strict_prompt_inputs: false,
+                max_request_body_bytes: None,
+                sampling_bounds: None,
+                stickiness: None,
             },
         )]))
     }
@@ -121,16 +403,27 @@ pub struct RouterProviderConfig {
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::{str::FromStr, time::Duration};
+
+    use nonempty_collections::{NEMap, nes};
 
     use super::*;
-    use crate::config::cache::CacheConfig;
+    use crate::{
+        config::{balance::WeightedProvider, cache::CacheConfig},
+        endpoints::EndpointType,
+        types::model_id::ModelId,
+    };
 
     fn test_router_config() -> RouterConfig {
         let cache = CacheConfig {
             directive: Some("max-age=3600, max-stale=1800".to_string()),
             buckets: 10,
             seed: Some("test-seed".to_string()),
+            cache_streams: false,
+            share_across_tenants: false,
+            tenant_header: Some("x-tenant-id".to_string()),
+            semantic: None,
+            error_responses: None,
         };
 
         let balance = BalanceConfig::default();
@@ -146,8 +439,24 @@ mod tests {
             cache: Some(cache),
             load_balance: balance,
             retries: Some(retries),
+            retry_status_codes: vec![429],
             rate_limit: None,
             providers: None,
+            locale_routing: None,
+            failover: None,
+            required_headers: vec!["x-tenant-id".to_string()],
+            weight_schedule: None,
+            system_prompt_prefix: None,
+            audit_transformations: false,
+            stream_moderation: None,
+            moderation: None,
+            request_timeout: None,
+            model_timeouts: HashMap::new(),
+            queue: None,
+            strict_prompt_inputs: false,
+            max_request_body_bytes: None,
+            sampling_bounds: None,
+            stickiness: None,
         }
     }
 
@@ -160,6 +469,125 @@ mod tests {
         assert_eq!(config, deserialized);
     }
 
+    #[test]
+    fn locale_providers_must_be_load_balanced() {
+        let mut config = RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            locale_routing: Some(LocaleRoutingConfig {
+                locales: HashMap::from([(
+                    "de".to_string(),
+                    InferenceProvider::Anthropic,
+                )]),
+                detect: false,
+            }),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        config.load_balance = BalanceConfig::anthropic_chat();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn failover_status_codes_must_be_errors() {
+        let config = |status_codes| RouterConfig {
+            failover: Some(FailoverConfig {
+                providers: vec![InferenceProvider::OpenAI],
+                status_codes,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(config(vec![200]).validate().is_err());
+        assert!(config(vec![503, 529]).validate().is_ok());
+    }
+
+    #[test]
+    fn required_headers_must_be_valid_header_names() {
+        let mut config = RouterConfig {
+            required_headers: vec!["x-tenant id".to_string()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        config.required_headers = vec!["X-Tenant-Id".to_string()];
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn latency_bias_must_be_between_zero_and_one() {
+        let config = |latency_bias| RouterConfig {
+            load_balance: BalanceConfig(HashMap::from([(
+                EndpointType::Chat,
+                BalanceConfigInner::WeightedLatency {
+                    providers: nes![WeightedProvider {
+                        provider: InferenceProvider::OpenAI,
+                        weight: Decimal::ONE,
+                    }],
+                    latency_bias,
+                },
+            )])),
+            ..Default::default()
+        };
+        assert!(config(Decimal::new(5, 1)).validate().is_ok());
+        assert!(config(Decimal::ZERO).validate().is_ok());
+        assert!(config(Decimal::ONE).validate().is_ok());
+        assert!(config(Decimal::new(11, 1)).validate().is_err());
+        assert!(config(Decimal::new(-1, 1)).validate().is_err());
+    }
+
+    #[test]
+    fn cost_weighted_prices_must_not_be_negative() {
+        let config = |price| RouterConfig {
+            load_balance: BalanceConfig(HashMap::from([(
+                EndpointType::Chat,
+                BalanceConfigInner::CostWeighted {
+                    models: NEMap::new(
+                        ModelId::from_str("openai/gpt-4o-mini").unwrap(),
+                        price,
+                    ),
+                },
+            )])),
+            ..Default::default()
+        };
+        assert!(config(Decimal::new(15, 2)).validate().is_ok());
+        assert!(config(Decimal::ZERO).validate().is_ok());
+        assert!(config(Decimal::new(-1, 2)).validate().is_err());
+    }
+
+    #[test]
+    fn model_timeout_overrides_router_timeout() {
+        let reasoning = ModelId::from_str("openai/o3").unwrap();
+        let config = RouterConfig {
+            request_timeout: Some(Duration::from_secs(30)),
+            model_timeouts: HashMap::from([(
+                reasoning.clone(),
+                ModelTimeout {
+                    request: Duration::from_secs(600),
+                    stream: Some(Duration::from_secs(120)),
+                },
+            )]),
+            ..Default::default()
+        };
+        let fast = ModelId::from_str("openai/gpt-4o-mini").unwrap();
+        assert_eq!(
+            config.timeout_for(Some(&reasoning), false),
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(
+            config.timeout_for(Some(&reasoning), true),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            config.timeout_for(Some(&fast), false),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            config.timeout_for(None, true),
+            Some(Duration::from_secs(30))
+        );
+    }
+
     #[test]
     fn router_configs_round_trip() {
         let config = RouterConfigs::default();
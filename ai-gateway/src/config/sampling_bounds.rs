@@ -0,0 +1,59 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::error::init::InitError;
+
+/// Allowed ranges of the sampling parameters of chat requests, e.g. to cap
+/// `temperature` at `1.0` for providers that reject higher values.
+///
+/// Values outside of a range are clamped to it rather than rejected.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SamplingBoundsConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<Bounds>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<Bounds>,
+}
+
+/// An inclusive range, open on the sides without a bound.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, PartialEq,
+)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Bounds {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<Decimal>,
+}
+
+impl SamplingBoundsConfig {
+    pub fn validate(&self) -> Result<(), InitError> {
+        for (param, bounds) in
+            [("temperature", self.temperature), ("top-p", self.top_p)]
+        {
+            let Some(bounds) = bounds else {
+                continue;
+            };
+            if bounds
+                .min
+                .into_iter()
+                .chain(bounds.max)
+                .any(|bound| bound.is_sign_negative())
+            {
+                return Err(InitError::InvalidSamplingBounds(format!(
+                    "{param} bounds must not be negative"
+                )));
+            }
+            if let (Some(min), Some(max)) = (bounds.min, bounds.max)
+                && min > max
+            {
+                return Err(InitError::InvalidSamplingBounds(format!(
+                    "{param} min {min} is greater than max {max}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::config::rate_limit::RateLimitStore;
+
+/// Caps on how much each organization may spend, estimated from the token
+/// usage of their requests.
+///
+/// Spend is tracked per calendar window in UTC. Once an organization has
+/// reached a cap, further requests are rejected until the window resets.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SpendLimitConfig {
+    /// If not set, the store from the rate-limit-store config will be
+    /// used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store: Option<RateLimitStore>,
+    /// Maximum spend in USD per organization per day.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily: Option<Decimal>,
+    /// Maximum spend in USD per organization per month.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly: Option<Decimal>,
+    /// Cost of models that aren't listed in `costs`.
+    #[serde(default)]
+    pub default_cost: TokenCost,
+    /// Cost per model, keyed by model name, e.g. `gpt-4o-mini`.
+    #[serde(default)]
+    pub costs: HashMap<String, TokenCost>,
+}
+
+impl SpendLimitConfig {
+    /// Returns the cost of the given model, falling back to the default
+    /// cost for unknown models.
+    ///
+    /// Models may be given with or without a `provider/` prefix.
+    #[must_use]
+    pub fn cost(&self, model: Option<&str>) -> &TokenCost {
+        model
+            .and_then(|model| {
+                self.costs.get(model).or_else(|| {
+                    let (_provider, model) = model.split_once('/')?;
+                    self.costs.get(model)
+                })
+            })
+            .unwrap_or(&self.default_cost)
+    }
+
+    /// The configured caps, along with the window they apply to.
+    pub fn limits(&self) -> impl Iterator<Item = (SpendWindow, Decimal)> {
+        [
+            (SpendWindow::Daily, self.daily),
+            (SpendWindow::Monthly, self.monthly),
+        ]
+        .into_iter()
+        .filter_map(|(window, limit)| limit.map(|limit| (window, limit)))
+    }
+}
+
+/// The cost in USD per million tokens.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TokenCost {
+    #[serde(default)]
+    pub input: Decimal,
+    #[serde(default)]
+    pub output: Decimal,
+}
+
+impl TokenCost {
+    /// Returns the cost in USD of the given number of tokens.
+    #[must_use]
+    pub fn total(&self, input_tokens: u64, output_tokens: u64) -> Decimal {
+        let per_token = Decimal::from(1_000_000);
+        (self.input * Decimal::from(input_tokens)
+            + self.output * Decimal::from(output_tokens))
+            / per_token
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Hash, strum::Display, strum::AsRefStr,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum SpendWindow {
+    Daily,
+    Monthly,
+}
+
+#[cfg(feature = "testing")]
+impl crate::tests::TestDefault for SpendLimitConfig {
+    fn test_default() -> Self {
+        Self {
+            store: None,
+            daily: Some(Decimal::from(1)),
+            monthly: None,
+            default_cost: TokenCost {
+                input: Decimal::from(10_000),
+                output: Decimal::from(10_000),
+            },
+            costs: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cost_falls_back_to_default() {
+        let config = SpendLimitConfig {
+            default_cost: TokenCost {
+                input: Decimal::from(1),
+                output: Decimal::from(2),
+            },
+            costs: HashMap::from([(
+                "gpt-4o-mini".to_string(),
+                TokenCost {
+                    input: Decimal::from(3),
+                    output: Decimal::from(4),
+                },
+            )]),
+            ..Default::default()
+        };
+        assert_eq!(config.cost(Some("gpt-4o-mini")).input, Decimal::from(3));
+        assert_eq!(
+            config.cost(Some("openai/gpt-4o-mini")).input,
+            Decimal::from(3)
+        );
+        assert_eq!(config.cost(Some("gpt-4o")).input, Decimal::from(1));
+        assert_eq!(config.cost(None).input, Decimal::from(1));
+    }
+
+    #[test]
+    fn total_cost_is_per_million_tokens() {
+        let cost = TokenCost {
+            input: Decimal::from(2),
+            output: Decimal::from(8),
+        };
+        assert_eq!(cost.total(500_000, 250_000), Decimal::from(3));
+    }
+}
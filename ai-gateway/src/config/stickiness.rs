@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::init::InitError;
+
+/// Sends every request of a conversation to the provider that served the
+/// conversation's first request, e.g. so that the provider's prompt cache is
+/// reused on every turn.
+///
+/// Conversations are identified by the `x-helicone-conversation-id` header,
+/// or optionally by their leading messages. Requests outside of a
+/// conversation are balanced as usual.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct StickinessConfig {
+    /// How long a conversation sticks to its provider after its last
+    /// request.
+    #[serde(default = "default_ttl", with = "humantime_serde")]
+    pub ttl: Duration,
+    /// If set, the conversation of requests without an
+    /// `x-helicone-conversation-id` header is derived from a hash of their
+    /// leading messages, which stay the same as the conversation goes on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derive: Option<ConversationKeyConfig>,
+}
+
+impl Default for StickinessConfig {
+    fn default() -> Self {
+        Self {
+            ttl: default_ttl(),
+            derive: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConversationKeyConfig {
+    /// The number of leading user messages hashed, along with the system
+    /// and assistant messages between them. Requests with fewer user
+    /// messages are balanced as usual.
+    #[serde(default = "default_prefix_user_messages")]
+    pub prefix_user_messages: usize,
+}
+
+impl Default for ConversationKeyConfig {
+    fn default() -> Self {
+        Self {
+            prefix_user_messages: default_prefix_user_messages(),
+        }
+    }
+}
+
+impl StickinessConfig {
+    pub fn validate(&self) -> Result<(), InitError> {
+        if self.ttl.is_zero() {
+            return Err(InitError::InvalidStickiness(
+                "ttl must be greater than zero".to_string(),
+            ));
+        }
+        if self
+            .derive
+            .is_some_and(|derive| derive.prefix_user_messages == 0)
+        {
+            return Err(InitError::InvalidStickiness(
+                "prefix-user-messages must be greater than zero".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn default_ttl() -> Duration {
+    Duration::from_secs(60 * 30)
+}
+
+fn default_prefix_user_messages() -> usize {
+    1
+}
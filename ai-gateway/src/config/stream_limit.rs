@@ -0,0 +1,30 @@
+use std::num::NonZeroU32;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::mapper::TenantIdentifier;
+
+/// Caps on how many streaming responses each tenant may have open at once.
+///
+/// Streams are counted per gateway instance. Once a tenant has reached the
+/// cap, new streaming requests are rejected until one of its streams
+/// completes or is aborted.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct StreamLimitConfig {
+    /// Maximum number of concurrent streams per tenant.
+    pub max_concurrent: NonZeroU32,
+    /// Which identifier of the caller streams are counted against.
+    #[serde(default)]
+    pub identifier: TenantIdentifier,
+}
+
+#[cfg(feature = "testing")]
+impl crate::tests::TestDefault for StreamLimitConfig {
+    fn test_default() -> Self {
+        Self {
+            max_concurrent: NonZeroU32::new(2).expect("2 is non-zero"),
+            identifier: TenantIdentifier::Org,
+        }
+    }
+}
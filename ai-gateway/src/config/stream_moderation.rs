@@ -0,0 +1,36 @@
+use std::num::NonZeroUsize;
+
+use serde::{Deserialize, Serialize};
+
+/// Holds back the start of streaming responses until it has been checked for
+/// blocked terms.
+///
+/// The first `buffer-chars` characters of content are buffered before
+/// anything is sent to the client. If they contain a blocked term, the
+/// stream is rejected instead, otherwise the buffer is flushed and the rest
+/// of the stream is forwarded as it arrives. Larger buffers catch more at
+/// the cost of a longer time to first token.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct StreamModerationConfig {
+    /// Number of content characters to check before the stream is emitted.
+    #[serde(default = "default_buffer_chars")]
+    pub buffer_chars: NonZeroUsize,
+    /// Terms that block a stream if they appear in its buffered content.
+    /// Matching is case insensitive.
+    pub blocked_terms: Vec<String>,
+}
+
+fn default_buffer_chars() -> NonZeroUsize {
+    NonZeroUsize::new(200).expect("200 is non-zero")
+}
+
+#[cfg(feature = "testing")]
+impl crate::tests::TestDefault for StreamModerationConfig {
+    fn test_default() -> Self {
+        Self {
+            buffer_chars: default_buffer_chars(),
+            blocked_terms: vec!["forbidden".to_string()],
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use std::num::NonZeroU32;
+
+use serde::{Deserialize, Serialize};
+
+/// Tracking of how many assistant tool call turns the conversation in a
+/// chat completion request has, derived from its messages.
+///
+/// A turn is an assistant message that calls at least one tool, so agents
+/// stuck in a loop of calling tools show up as conversations with many
+/// turns.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ToolCallTurnsConfig {
+    /// If set, requests whose messages contain more tool call turns than
+    /// this are rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<NonZeroU32>,
+}
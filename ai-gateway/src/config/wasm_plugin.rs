@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// A WASM plugin that can transform request and response bodies before they
+/// are routed.
+///
+/// See [`crate::middleware::wasm_plugin`] for the ABI plugins must implement.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WasmPluginConfig {
+    /// Path to the plugin module, either as a `.wasm` binary or `.wat` text.
+    pub path: PathBuf,
+    /// Units of fuel each plugin invocation may consume before it is aborted.
+    #[serde(default = "default_fuel")]
+    pub fuel: u64,
+    /// Maximum size in bytes the plugin's linear memory may grow to.
+    #[serde(default = "default_max_memory_bytes")]
+    pub max_memory_bytes: usize,
+}
+
+fn default_fuel() -> u64 {
+    10_000_000
+}
+
+fn default_max_memory_bytes() -> usize {
+    // 16 MiB
+    16 * 1024 * 1024
+}
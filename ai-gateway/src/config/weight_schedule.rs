@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset, NaiveTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::types::provider::InferenceProvider;
+
+/// Overrides the weights of a provider weighted router during windows of the
+/// day, e.g. to prefer a provider during its off-peak pricing hours.
+///
+/// Only applies to the `provider-weighted` balance strategy.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WeightScheduleConfig {
+    /// Offset from UTC that the window times are in, e.g. `-05:00`.
+    ///
+    /// Fixed offsets don't follow daylight saving time changes.
+    #[serde(default = "default_utc_offset", with = "utc_offset")]
+    pub utc_offset: FixedOffset,
+    /// Windows are matched in order, and the first one containing the current
+    /// time applies. Outside of all windows, the configured balance weights
+    /// are used.
+    pub windows: Vec<ScheduleWindow>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ScheduleWindow {
+    /// Start of the window, inclusive, e.g. `09:00`.
+    pub start: NaiveTime,
+    /// End of the window, exclusive, e.g. `17:00`. If it's before `start`,
+    /// the window wraps around midnight.
+    pub end: NaiveTime,
+    /// Weights used during the window. Providers without a weight keep their
+    /// configured balance weight.
+    pub weights: HashMap<InferenceProvider, Decimal>,
+}
+
+impl ScheduleWindow {
+    #[must_use]
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        in_window(self.start, self.end, time)
+    }
+}
+
+/// Whether `time` is in the window from `start`, inclusive, to `end`,
+/// exclusive, wrapping around midnight if `end` is before `start`.
+#[must_use]
+pub fn in_window(start: NaiveTime, end: NaiveTime, time: NaiveTime) -> bool {
+    if start <= end {
+        start <= time && time < end
+    } else {
+        start <= time || time < end
+    }
+}
+
+impl WeightScheduleConfig {
+    /// Returns the window containing `now`, if any.
+    #[must_use]
+    pub fn window_at(&self, now: DateTime<Utc>) -> Option<&ScheduleWindow> {
+        let time = now.with_timezone(&self.utc_offset).time();
+        self.windows.iter().find(|window| window.contains(time))
+    }
+}
+
+fn default_utc_offset() -> FixedOffset {
+    FixedOffset::east_opt(0).expect("zero offset is always valid")
+}
+
+mod utc_offset {
+    use chrono::FixedOffset;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(
+        offset: &FixedOffset,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(offset)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<FixedOffset, D::Error> {
+        let offset = String::deserialize(deserializer)?;
+        offset.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 2, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn deserializes_times_and_offset() {
+        let config =
+            serde_json::from_value::<WeightScheduleConfig>(serde_json::json!({
+                "utc-offset": "-05:00",
+                "windows": [{
+                    "start": "09:00:00",
+                    "end": "17:00:00",
+                    "weights": { "openai": 0.9, "anthropic": 0.1 },
+                }],
+            }))
+            .unwrap();
+        assert_eq!(config.utc_offset.local_minus_utc(), -5 * 3600);
+        let serialized = serde_json::to_value(&config).unwrap();
+        assert_eq!(
+            serde_json::from_value::<WeightScheduleConfig>(serialized).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn window_is_evaluated_in_configured_offset() {
+        let config = WeightScheduleConfig {
+            utc_offset: FixedOffset::west_opt(5 * 3600).unwrap(),
+            windows: vec![ScheduleWindow {
+                start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+                weights: HashMap::new(),
+            }],
+        };
+        // 09:00 and 17:00 at -05:00
+        assert!(config.window_at(at(13, 59)).is_none());
+        assert!(config.window_at(at(14, 0)).is_some());
+        assert!(config.window_at(at(21, 59)).is_some());
+        assert!(config.window_at(at(22, 0)).is_none());
+    }
+
+    #[test]
+    fn window_wraps_around_midnight() {
+        let window = ScheduleWindow {
+            start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+            weights: HashMap::new(),
+        };
+        assert!(window.contains(NaiveTime::from_hms_opt(23, 0, 0).unwrap()));
+        assert!(window.contains(NaiveTime::from_hms_opt(1, 0, 0).unwrap()));
+        assert!(!window.contains(NaiveTime::from_hms_opt(12, 0, 0).unwrap()));
+    }
+}
@@ -1,6 +1,7 @@
 pub mod factory;
 
 use std::{
+    collections::HashSet,
     convert::Infallible,
     hash::Hash,
     pin::Pin,
@@ -8,11 +9,18 @@ use std::{
 };
 
 use futures::Stream;
+use indexmap::IndexMap;
 use pin_project_lite::pin_project;
 use tokio_stream::wrappers::ReceiverStream;
 use tower::discover::Change;
 
-use crate::{discover::ServiceMap, dispatcher::DispatcherService};
+use crate::{
+    app_state::AppState,
+    discover::ServiceMap,
+    dispatcher::{DispatcherService, service::provider_key_missing},
+    endpoints::EndpointType,
+    types::provider::InferenceProvider,
+};
 
 pin_project! {
     /// Reads available models and providers from the config file.
@@ -85,3 +93,42 @@ where
         }
     }
 }
+
+/// Leaves the dispatchers of providers without a configured key, if keys are
+/// required, out of the balancer, so that requests are balanced across the
+/// providers that can serve them.
+///
+/// If no provider of an endpoint type has a key, every dispatcher for it is
+/// kept so that its requests are rejected with a descriptive error.
+pub(crate) async fn retain_keyed_providers<K>(
+    app_state: &AppState,
+    service_map: &mut IndexMap<K, DispatcherService>,
+    target: impl Fn(&K) -> (EndpointType, Option<InferenceProvider>),
+) where
+    K: Hash + Eq + Clone + std::fmt::Debug,
+{
+    let mut keyless = Vec::new();
+    let mut keyed_endpoint_types = HashSet::new();
+    for key in service_map.keys() {
+        let (endpoint_type, provider) = target(key);
+        match provider {
+            Some(provider)
+                if provider_key_missing(app_state, &provider).await =>
+            {
+                keyless.push((endpoint_type, key.clone()));
+            }
+            _ => {
+                keyed_endpoint_types.insert(endpoint_type);
+            }
+        }
+    }
+    for (endpoint_type, key) in keyless {
+        if keyed_endpoint_types.contains(&endpoint_type) {
+            tracing::warn!(
+                key = ?key,
+                "not balancing to provider without a configured key"
+            );
+            service_map.shift_remove(&key);
+        }
+    }
+}
@@ -5,7 +5,6 @@ pub mod provider;
 pub mod router;
 
 use std::{
-    collections::HashMap,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -18,10 +17,11 @@ pin_project! {
     /// Static service discovery based on a predetermined map of services.
     ///
     /// [`ServiceMap`] is created with an initial map of services. The discovery
-    /// process will yield this map once and do nothing after.
+    /// process will yield this map once, in its iteration order, and do
+    /// nothing after.
     #[derive(Debug)]
     pub(crate) struct ServiceMap<K, V> {
-        inner: std::collections::hash_map::IntoIter<K, V>,
+        inner: std::vec::IntoIter<(K, V)>,
     }
 }
 
@@ -29,12 +29,14 @@ impl<K, V> ServiceMap<K, V>
 where
     K: std::hash::Hash + Eq,
 {
-    pub fn new<Request>(services: HashMap<K, V>) -> ServiceMap<K, V>
+    pub fn new<Request>(
+        services: impl IntoIterator<Item = (K, V)>,
+    ) -> ServiceMap<K, V>
     where
         V: tower::Service<Request>,
     {
         ServiceMap {
-            inner: services.into_iter(),
+            inner: services.into_iter().collect::<Vec<_>>().into_iter(),
         }
     }
 }
@@ -1,10 +1,10 @@
 use std::{
-    collections::HashMap,
     sync::Arc,
     task::{Context, Poll},
 };
 
 use futures::future::BoxFuture;
+use indexmap::IndexMap;
 use tokio::sync::mpsc::Receiver;
 use tokio_stream::wrappers::ReceiverStream;
 use tower::{
@@ -18,7 +18,10 @@ use crate::{
     config::{balance::BalanceConfigInner, router::RouterConfig},
     discover::{
         ServiceMap,
-        dispatcher::{DispatcherDiscovery, factory::DispatcherDiscoverFactory},
+        dispatcher::{
+            DispatcherDiscovery, factory::DispatcherDiscoverFactory,
+            retain_keyed_providers,
+        },
     },
     dispatcher::{Dispatcher, DispatcherService},
     endpoints::EndpointType,
@@ -59,7 +62,7 @@ impl DispatcherDiscovery<Key> {
         rx: Receiver<Change<Key, DispatcherService>>,
     ) -> Result<Self, InitError> {
         let events = ReceiverStream::new(rx);
-        let mut service_map: HashMap<Key, DispatcherService> = HashMap::new();
+        let mut service_map: IndexMap<Key, DispatcherService> = IndexMap::new();
         for (endpoint_type, balance_config) in
             router_config.load_balance.as_ref()
         {
@@ -88,6 +91,10 @@ impl DispatcherDiscovery<Key> {
             }
         }
 
+        retain_keyed_providers(app_state, &mut service_map, |key| {
+            (key.endpoint_type, key.model_id.inference_provider())
+        })
+        .await;
         Ok(Self {
             initial: ServiceMap::new(service_map),
             events,
@@ -1,10 +1,10 @@
 use std::{
-    collections::HashMap,
     sync::Arc,
     task::{Context, Poll},
 };
 
 use futures::future::BoxFuture;
+use indexmap::IndexMap;
 use rust_decimal::prelude::ToPrimitive;
 use tokio::sync::mpsc::Receiver;
 use tokio_stream::wrappers::ReceiverStream;
@@ -16,7 +16,10 @@ use crate::{
     config::{balance::BalanceConfigInner, router::RouterConfig},
     discover::{
         ServiceMap,
-        dispatcher::{DispatcherDiscovery, factory::DispatcherDiscoverFactory},
+        dispatcher::{
+            DispatcherDiscovery, factory::DispatcherDiscoverFactory,
+            retain_keyed_providers,
+        },
     },
     dispatcher::{Dispatcher, DispatcherService},
     endpoints::EndpointType,
@@ -53,12 +56,12 @@ impl DispatcherDiscovery<WeightedKey> {
         router_config: &Arc<RouterConfig>,
         rx: Receiver<Change<WeightedKey, DispatcherService>>,
     ) -> Result<Self, InitError> {
-        let mut service_map = HashMap::new();
+        let mut service_map = IndexMap::new();
         for (endpoint_type, balance_config) in
             router_config.load_balance.as_ref()
         {
             let weighted_balance_targets = match balance_config {
-                BalanceConfigInner::ModelWeighted { models } => models,
+                BalanceConfigInner::ModelWeighted { models, .. } => models,
                 BalanceConfigInner::ModelLatency { .. } => {
                     return Err(InitError::InvalidBalancer(
                         "Model latency balancer not supported for model \
@@ -66,7 +69,30 @@ impl DispatcherDiscovery<WeightedKey> {
                             .to_string(),
                     ));
                 }
-                BalanceConfigInner::ProviderWeighted { .. } => {
+                BalanceConfigInner::CostWeighted { .. } => {
+                    return Err(InitError::InvalidBalancer(
+                        "Cost weighted balancer not supported for model \
+                         weighted discovery"
+                            .to_string(),
+                    ));
+                }
+                BalanceConfigInner::RoundRobin { .. } => {
+                    return Err(InitError::InvalidBalancer(
+                        "Round robin balancer not supported for model \
+                         weighted discovery"
+                            .to_string(),
+                    ));
+                }
+                BalanceConfigInner::Complexity { .. } => {
+                    return Err(InitError::InvalidBalancer(
+                        "Complexity balancer not supported for model weighted \
+                         discovery"
+                            .to_string(),
+                    ));
+                }
+                BalanceConfigInner::ProviderWeighted { .. }
+                | BalanceConfigInner::WeightedLatency { .. }
+                | BalanceConfigInner::LeastInFlight { .. } => {
                     return Err(InitError::InvalidBalancer(
                         "Provider weighted balancer not supported for model \
                          weighted discovery"
@@ -111,6 +137,10 @@ impl DispatcherDiscovery<WeightedKey> {
         }
         let events = ReceiverStream::new(rx);
 
+        retain_keyed_providers(app_state, &mut service_map, |key| {
+            (key.endpoint_type, key.model_id.inference_provider())
+        })
+        .await;
         Ok(Self {
             initial: ServiceMap::new(service_map),
             events,
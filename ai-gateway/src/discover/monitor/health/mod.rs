@@ -1,2 +1,6 @@
+pub mod probe;
 pub mod provider;
-pub use self::provider::HealthMonitor;
+pub mod store;
+pub use self::{
+    probe::HealthProbe, provider::HealthMonitor, store::HealthStore,
+};
@@ -0,0 +1,287 @@
+//! Actively probe inference providers with synthetic requests so that
+//! outages are detected even when a provider receives no live traffic.
+use bytes::Bytes;
+use futures::future::{self, BoxFuture};
+use indexmap::IndexSet;
+use meltdown::Token;
+use serde_json::json;
+use tokio::time;
+use tracing::{debug, error, trace, warn};
+
+use crate::{
+    app_state::AppState,
+    config::monitor::{ProbeConfig, ProbeEndpoint},
+    dispatcher::client::{Client, ProviderClient},
+    endpoints::{ApiEndpoint, openai::OpenAI},
+    error::{
+        api::ApiError, init::InitError, internal::InternalError,
+        runtime::RuntimeError,
+    },
+    middleware::mapper::{
+        model::ModelMapper, registry::EndpointConverterRegistry,
+    },
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+/// Periodically sends a cheap request, by default a chat completion, to each
+/// probed provider and records the result in the provider's
+/// [`EndpointMetrics`](crate::discover::monitor::metrics::EndpointMetrics),
+/// which the [`HealthMonitor`](super::HealthMonitor) uses to add and remove
+/// providers from the load balancer.
+///
+/// Probes are sent directly with the provider's client rather than through
+/// the dispatcher, so they are never logged as requests.
+#[derive(Debug, Clone)]
+pub struct HealthProbe {
+    app_state: AppState,
+    config: ProbeConfig,
+    converter_registry: EndpointConverterRegistry,
+    clients: Vec<(InferenceProvider, Client)>,
+}
+
+impl HealthProbe {
+    /// Returns `None` if probing is not enabled.
+    pub async fn new(app_state: AppState) -> Result<Option<Self>, InitError> {
+        let Some(config) = app_state.config().discover.monitor.probe.clone()
+        else {
+            return Ok(None);
+        };
+        let providers: IndexSet<InferenceProvider> =
+            if config.providers.is_empty() {
+                app_state
+                    .config()
+                    .routers
+                    .values()
+                    .flat_map(|router| router.load_balance.providers())
+                    .collect()
+            } else {
+                config.providers.iter().cloned().collect()
+            };
+        let mut clients = Vec::with_capacity(providers.len());
+        for provider in providers {
+            let client = Client::new(&app_state, provider.clone()).await?;
+            clients.push((provider, client));
+        }
+        let model_mapper = ModelMapper::new(app_state.clone());
+        let converter_registry = EndpointConverterRegistry::new(&model_mapper);
+
+        Ok(Some(Self {
+            app_state,
+            config,
+            converter_registry,
+            clients,
+        }))
+    }
+
+    pub async fn run_forever(self) -> Result<(), RuntimeError> {
+        tracing::info!(
+            providers = ?self.clients.iter().map(|(p, _)| p).collect::<Vec<_>>(),
+            "starting provider health probes"
+        );
+        let mut interval = time::interval(self.config.interval);
+        loop {
+            interval.tick().await;
+            self.probe_all().await;
+        }
+    }
+
+    /// Probes every provider once, concurrently.
+    pub async fn probe_all(&self) {
+        let probes = self.clients.iter().map(|(provider, client)| async move {
+            if let Err(e) = self.probe(provider, client).await {
+                warn!(provider = %provider, error = %e, "failed to probe provider");
+            }
+        });
+        future::join_all(probes).await;
+    }
+
+    /// Returns an error if the probe could not be sent, in which case the
+    /// provider's health metrics are left untouched.
+    async fn probe(
+        &self,
+        provider: &InferenceProvider,
+        client: &Client,
+    ) -> Result<(), ApiError> {
+        let config = self.app_state.config();
+        let provider_config =
+            config.providers.get(provider).ok_or_else(|| {
+                InternalError::ProviderNotConfigured(provider.clone())
+            })?;
+        let source_endpoint = ApiEndpoint::OpenAI(OpenAI::chat_completions());
+        let target_endpoint =
+            ApiEndpoint::mapped(source_endpoint.clone(), provider)?;
+
+        let models_path = match self.config.endpoint {
+            ProbeEndpoint::ChatCompletion => None,
+            ProbeEndpoint::Models => models_path(&target_endpoint),
+        };
+        let (method, path, body) = if let Some(path) = models_path {
+            (http::Method::GET, path, Bytes::new())
+        } else {
+            let Some(model) = provider_config.models.first() else {
+                debug!(provider = %provider, "no models configured, skipping probe");
+                return Ok(());
+            };
+            let (path, body) = self.chat_completion(
+                provider,
+                model,
+                &source_endpoint,
+                &target_endpoint,
+            )?;
+            (http::Method::POST, path, body)
+        };
+        let target_url = provider_config
+            .base_url
+            .join(&path)
+            .expect("PathAndQuery joined with valid url will always succeed");
+
+        let request_builder = client
+            .as_ref()
+            .request(method, target_url)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .timeout(self.config.timeout);
+        let request_builder = client
+            .authenticate(
+                &self.app_state,
+                request_builder,
+                &body,
+                None,
+                provider.clone(),
+            )
+            .await?;
+
+        // probe results are recorded against the chat endpoint, whose
+        // health decides whether the provider is load balanced
+        let endpoint_metrics = self
+            .app_state
+            .0
+            .endpoint_metrics
+            .health_metrics(target_endpoint)?;
+        endpoint_metrics.incr_req_count();
+        match request_builder.body(body).send().await {
+            Ok(response) if response.status().is_server_error() => {
+                warn!(provider = %provider, status = %response.status(), "provider probe failed");
+                endpoint_metrics.incr_remote_internal_error_count();
+            }
+            Ok(response) => {
+                trace!(provider = %provider, status = %response.status(), "provider probe succeeded");
+            }
+            Err(e) => {
+                // unlike live traffic, a probe that can't reach the provider
+                // is exactly the outage we're looking for
+                warn!(provider = %provider, error = %e, "provider probe failed");
+                endpoint_metrics.incr_remote_internal_error_count();
+            }
+        }
+        Ok(())
+    }
+
+    /// The path and body of a chat completion of `model`, in the provider's
+    /// request format.
+    fn chat_completion(
+        &self,
+        provider: &InferenceProvider,
+        model: &ModelId,
+        source_endpoint: &ApiEndpoint,
+        target_endpoint: &ApiEndpoint,
+    ) -> Result<(String, Bytes), ApiError> {
+        let converter = self
+            .converter_registry
+            .get_converter(source_endpoint, target_endpoint)
+            .ok_or_else(|| {
+                InternalError::InvalidConverter(
+                    source_endpoint.clone(),
+                    target_endpoint.clone(),
+                )
+            })?;
+        let body = serde_json::to_vec(&json!({
+            "model": format!("{provider}/{model}"),
+            "messages": [
+                {
+                    "role": "user",
+                    "content": self.config.prompt,
+                }
+            ],
+            "max_tokens": self.config.max_tokens,
+        }))
+        .map_err(|error| InternalError::Serialize {
+            ty: "serde_json::Value",
+            error,
+        })?;
+        let (body, mapper_ctx) =
+            converter.convert_req_body(Bytes::from(body))?;
+        let path = target_endpoint.path(mapper_ctx.model.as_ref(), false)?;
+        Ok((path, body))
+    }
+}
+
+/// The path of the endpoint listing a provider's models, which sits next to
+/// its chat endpoint, or `None` if the provider doesn't have one.
+fn models_path(chat_endpoint: &ApiEndpoint) -> Option<String> {
+    if matches!(chat_endpoint, ApiEndpoint::Bedrock(_)) {
+        return None;
+    }
+    let path = chat_endpoint.path(None, false).ok()?;
+    let prefix = path
+        .strip_suffix("chat/completions")
+        .or_else(|| path.strip_suffix("messages"))?;
+    Some(format!("{prefix}models"))
+}
+
+impl meltdown::Service for HealthProbe {
+    type Future = BoxFuture<'static, Result<(), RuntimeError>>;
+
+    fn run(self, mut token: Token) -> Self::Future {
+        Box::pin(async move {
+            tokio::select! {
+                result = self.run_forever() => {
+                    if let Err(e) = result {
+                        error!(name = "provider-health-probe-task", error = ?e, "Probe encountered error, shutting down");
+                    } else {
+                        debug!(name = "provider-health-probe-task", "Probe shut down successfully");
+                    }
+                    token.trigger();
+                }
+                () = &mut token => {
+                    debug!(name = "provider-health-probe-task", "task shut down successfully");
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider_models_path(provider: &InferenceProvider) -> Option<String> {
+        let chat_endpoint = ApiEndpoint::mapped(
+            ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+            provider,
+        )
+        .unwrap();
+        models_path(&chat_endpoint)
+    }
+
+    #[test]
+    fn models_path_sits_next_to_chat_endpoint() {
+        assert_eq!(
+            provider_models_path(&InferenceProvider::OpenAI).as_deref(),
+            Some("v1/models")
+        );
+        assert_eq!(
+            provider_models_path(&InferenceProvider::Anthropic).as_deref(),
+            Some("v1/models")
+        );
+        assert_eq!(
+            provider_models_path(&InferenceProvider::GoogleGemini).as_deref(),
+            Some("v1beta/openai/models")
+        );
+    }
+
+    #[test]
+    fn bedrock_has_no_models_path() {
+        assert_eq!(provider_models_path(&InferenceProvider::Bedrock), None);
+    }
+}
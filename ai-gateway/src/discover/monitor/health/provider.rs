@@ -106,6 +106,31 @@ impl ProviderHealthMonitor {
         ))
     }
 
+    /// Whether the monitor removed the provider from the load balancer.
+    #[must_use]
+    pub fn is_removed(&self, provider: &InferenceProvider) -> bool {
+        match self {
+            ProviderHealthMonitor::ProviderWeighted(inner) => inner
+                .unhealthy_keys
+                .iter()
+                .any(|key| &key.provider == provider),
+            ProviderHealthMonitor::ProviderLatency(inner) => inner
+                .unhealthy_keys
+                .iter()
+                .any(|key| &key.provider == provider),
+            ProviderHealthMonitor::ModelWeighted(inner) => {
+                inner.unhealthy_keys.iter().any(|key| {
+                    key.model_id.inference_provider().as_ref() == Some(provider)
+                })
+            }
+            ProviderHealthMonitor::ModelLatency(inner) => {
+                inner.unhealthy_keys.iter().any(|key| {
+                    key.model_id.inference_provider().as_ref() == Some(provider)
+                })
+            }
+        }
+    }
+
     async fn check_monitor(&mut self) -> Result<(), runtime::RuntimeError> {
         match self {
             ProviderHealthMonitor::ProviderWeighted(inner) => {
@@ -131,7 +156,9 @@ async fn check_provider_weighted_monitor(
         inner.router_config.load_balance.as_ref()
     {
         match balance_config {
-            BalanceConfigInner::ProviderWeighted { providers } => {
+            BalanceConfigInner::ProviderWeighted { providers }
+            | BalanceConfigInner::WeightedLatency { providers, .. }
+            | BalanceConfigInner::LeastInFlight { providers } => {
                 for target in providers {
                     let provider = &target.provider;
                     let weight = Weight::from(
@@ -144,6 +171,11 @@ async fn check_provider_weighted_monitor(
                         provider.clone(),
                         *endpoint_type,
                         weight,
+                    )
+                    .with_schedule(inner.router_config.weight_schedule.as_ref())
+                    .with_latency_bias(
+                        balance_config.latency_bias(),
+                        &inner.app_state.0.endpoint_metrics,
                     );
                     let is_healthy = inner.check_health(provider)?;
                     let was_unhealthy = inner.unhealthy_keys.contains(&key);
@@ -210,6 +242,24 @@ async fn check_provider_weighted_monitor(
                 );
                 return Err(InternalError::Internal.into());
             }
+            BalanceConfigInner::CostWeighted { .. } => {
+                tracing::error!(
+                    "Cost weighted entries in a provider weighted monitor"
+                );
+                return Err(InternalError::Internal.into());
+            }
+            BalanceConfigInner::Complexity { .. } => {
+                tracing::error!(
+                    "Complexity entries in a provider weighted monitor"
+                );
+                return Err(InternalError::Internal.into());
+            }
+            BalanceConfigInner::RoundRobin { .. } => {
+                tracing::error!(
+                    "Round robin entries in a provider weighted monitor"
+                );
+                return Err(InternalError::Internal.into());
+            }
         }
     }
 
@@ -224,7 +274,7 @@ async fn check_model_weighted_monitor(
         inner.router_config.load_balance.as_ref()
     {
         match balance_config {
-            BalanceConfigInner::ModelWeighted { models } => {
+            BalanceConfigInner::ModelWeighted { models, .. } => {
                 for target in models {
                     let model = &target.model;
                     let provider =
@@ -359,7 +409,9 @@ async fn check_model_weighted_monitor(
                     }
                 }
             }
-            BalanceConfigInner::ProviderWeighted { .. } => {
+            BalanceConfigInner::ProviderWeighted { .. }
+            | BalanceConfigInner::WeightedLatency { .. }
+            | BalanceConfigInner::LeastInFlight { .. } => {
                 tracing::error!(
                     "Provider weighted entries in a model weighted monitor"
                 );
@@ -375,6 +427,24 @@ async fn check_model_weighted_monitor(
                 );
                 return Err(InternalError::Internal.into());
             }
+            BalanceConfigInner::CostWeighted { .. } => {
+                tracing::error!(
+                    "Cost weighted entries in a model weighted monitor"
+                );
+                return Err(InternalError::Internal.into());
+            }
+            BalanceConfigInner::Complexity { .. } => {
+                tracing::error!(
+                    "Complexity entries in a model weighted monitor"
+                );
+                return Err(InternalError::Internal.into());
+            }
+            BalanceConfigInner::RoundRobin { .. } => {
+                tracing::error!(
+                    "Round robin entries in a model weighted monitor"
+                );
+                return Err(InternalError::Internal.into());
+            }
         }
     }
 
@@ -388,8 +458,9 @@ async fn check_provider_latency_monitor(
         inner.router_config.load_balance.as_ref()
     {
         match balance_config {
-            BalanceConfigInner::BalancedLatency { providers } => {
-                for provider in providers {
+            BalanceConfigInner::BalancedLatency { .. }
+            | BalanceConfigInner::RoundRobin { .. } => {
+                for provider in &balance_config.providers() {
                     let key =
                         ProviderKey::new(provider.clone(), *endpoint_type);
                     let is_healthy = inner.check_health(provider)?;
@@ -445,7 +516,9 @@ async fn check_provider_latency_monitor(
                 tracing::error!("Model weighted entries in a P2C monitor");
                 return Err(InternalError::Internal.into());
             }
-            BalanceConfigInner::ProviderWeighted { .. } => {
+            BalanceConfigInner::ProviderWeighted { .. }
+            | BalanceConfigInner::WeightedLatency { .. }
+            | BalanceConfigInner::LeastInFlight { .. } => {
                 tracing::error!("Weighted entries in a P2C monitor");
                 return Err(InternalError::Internal.into());
             }
@@ -453,6 +526,14 @@ async fn check_provider_latency_monitor(
                 tracing::error!("Model latency entries in a P2C monitor");
                 return Err(InternalError::Internal.into());
             }
+            BalanceConfigInner::CostWeighted { .. } => {
+                tracing::error!("Cost weighted entries in a P2C monitor");
+                return Err(InternalError::Internal.into());
+            }
+            BalanceConfigInner::Complexity { .. } => {
+                tracing::error!("Complexity entries in a P2C monitor");
+                return Err(InternalError::Internal.into());
+            }
         }
     }
 
@@ -573,7 +654,9 @@ async fn check_model_latency_monitor(
                 tracing::error!("Model weighted entries in a P2C monitor");
                 return Err(InternalError::Internal.into());
             }
-            BalanceConfigInner::ProviderWeighted { .. } => {
+            BalanceConfigInner::ProviderWeighted { .. }
+            | BalanceConfigInner::WeightedLatency { .. }
+            | BalanceConfigInner::LeastInFlight { .. } => {
                 tracing::error!("Weighted entries in a P2C monitor");
                 return Err(InternalError::Internal.into());
             }
@@ -583,6 +666,24 @@ async fn check_model_latency_monitor(
                 );
                 return Err(InternalError::Internal.into());
             }
+            BalanceConfigInner::CostWeighted { .. } => {
+                tracing::error!(
+                    "Cost weighted entries in a model latency monitor"
+                );
+                return Err(InternalError::Internal.into());
+            }
+            BalanceConfigInner::Complexity { .. } => {
+                tracing::error!(
+                    "Complexity entries in a model latency monitor"
+                );
+                return Err(InternalError::Internal.into());
+            }
+            BalanceConfigInner::RoundRobin { .. } => {
+                tracing::error!(
+                    "Round robin entries in a model latency monitor"
+                );
+                return Err(InternalError::Internal.into());
+            }
         }
     }
 
@@ -620,32 +721,74 @@ impl<K> ProviderMonitorInner<K> {
         &self,
         provider: &InferenceProvider,
     ) -> Result<bool, InternalError> {
-        let provider_endpoints = provider.endpoints();
-        let config = self.app_state.config();
-        let grace_period = config.discover.monitor.grace_period();
-        let mut all_healthy = true;
-        for endpoint in provider_endpoints {
-            let endpoint_metrics =
-                self.app_state.0.endpoint_metrics.health_metrics(endpoint)?;
-            let requests = endpoint_metrics.request_count.total();
-            match grace_period {
-                GracePeriod::Requests { min_requests } => {
-                    if requests < *min_requests {
-                        continue;
-                    }
+        let health = observed_health(&self.app_state, provider)?;
+        let Some(store) = &self.app_state.0.health_store else {
+            return Ok(health.unwrap_or(true));
+        };
+        // persistence is best effort, if the store is unavailable the
+        // health observed by this replica is used
+        match health {
+            Some(false) => {
+                if let Err(e) = store.mark_unhealthy(provider) {
+                    error!(error = ?e, provider = ?provider, "Failed to persist unhealthy provider");
                 }
+                Ok(false)
             }
+            Some(true) => Ok(true),
+            // this replica hasn't seen enough requests to judge, so rely on
+            // what other replicas, or this one before a restart, have seen
+            None => match store.is_unhealthy(provider) {
+                Ok(is_unhealthy) => Ok(!is_unhealthy),
+                Err(e) => {
+                    error!(error = ?e, provider = ?provider, "Failed to read persisted provider health");
+                    Ok(true)
+                }
+            },
+        }
+    }
+}
 
-            let errors = endpoint_metrics.remote_internal_error_count.total();
-            let error_ratio = f64::from(errors) / f64::from(requests);
+/// Whether the error ratio of every endpoint of the `provider` is within the
+/// configured error threshold. Endpoints that haven't served enough requests
+/// to leave the grace period are considered healthy.
+pub(crate) fn is_provider_healthy(
+    app_state: &AppState,
+    provider: &InferenceProvider,
+) -> Result<bool, InternalError> {
+    Ok(observed_health(app_state, provider)?.unwrap_or(true))
+}
 
-            if error_ratio > config.discover.monitor.error_threshold() {
-                all_healthy = false;
+/// Whether the error ratio of every endpoint of the `provider` that has left
+/// the grace period is within the configured error threshold, or `None` if
+/// none of them has.
+fn observed_health(
+    app_state: &AppState,
+    provider: &InferenceProvider,
+) -> Result<Option<bool>, InternalError> {
+    let provider_endpoints = provider.endpoints();
+    let config = app_state.config();
+    let grace_period = config.discover.monitor.grace_period();
+    let mut health = None;
+    for endpoint in provider_endpoints {
+        let endpoint_metrics =
+            app_state.0.endpoint_metrics.health_metrics(endpoint)?;
+        let requests = endpoint_metrics.request_count.total();
+        match grace_period {
+            GracePeriod::Requests { min_requests } => {
+                if requests < *min_requests {
+                    continue;
+                }
             }
         }
 
-        Ok(all_healthy)
+        let errors = endpoint_metrics.remote_internal_error_count.total();
+        let error_ratio = f64::from(errors) / f64::from(requests);
+
+        let healthy = error_ratio <= config.discover.monitor.error_threshold();
+        health = Some(health.unwrap_or(true) && healthy);
     }
+
+    Ok(health)
 }
 
 #[derive(Debug, Clone)]
@@ -668,27 +811,36 @@ impl HealthMonitor {
 
         loop {
             interval.tick().await;
-            let mut monitors = self.app_state.0.health_monitors.write().await;
-            let mut check_futures = Vec::new();
-            for (router_id, monitor) in monitors.iter_mut() {
-                let span = tracing::info_span!("health_monitor", router_id = ?router_id);
-                let check_future = async move {
-                    let result = monitor.check_monitor().await;
-                    if let Err(e) = &result {
-                        error!(router_id = ?router_id, error = ?e, "Provider health monitor check failed");
-                    }
-                    result
-                }.instrument(span);
-
-                check_futures.push(check_future);
-            }
-
-            if let Err(e) = future::try_join_all(check_futures).await {
+            if let Err(e) = self.check_all().await {
                 error!(error = ?e, "Provider health monitor encountered an error");
                 return Err(e);
             }
         }
     }
+
+    /// Checks the providers of every router once, concurrently, removing
+    /// unhealthy providers from the load balancers and adding recovered
+    /// providers back.
+    pub async fn check_all(&self) -> Result<(), runtime::RuntimeError> {
+        let mut monitors = self.app_state.0.health_monitors.write().await;
+        let mut check_futures = Vec::new();
+        for (router_id, monitor) in monitors.iter_mut() {
+            let span =
+                tracing::info_span!("health_monitor", router_id = ?router_id);
+            let check_future = async move {
+                let result = monitor.check_monitor().await;
+                if let Err(e) = &result {
+                    error!(router_id = ?router_id, error = ?e, "Provider health monitor check failed");
+                }
+                result
+            }
+            .instrument(span);
+
+            check_futures.push(check_future);
+        }
+        future::try_join_all(check_futures).await?;
+        Ok(())
+    }
 }
 
 impl meltdown::Service for HealthMonitor {
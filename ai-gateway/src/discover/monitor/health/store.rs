@@ -0,0 +1,59 @@
+//! Persists which providers are unhealthy, so that health signals outlive
+//! the replica that observed them.
+use std::time::Duration;
+
+use r2d2::Pool;
+use redis::{Client, Commands};
+
+use crate::{
+    config::monitor::HealthPersistenceConfig,
+    error::{init::InitError, internal::InternalError},
+    types::provider::InferenceProvider,
+};
+
+/// Records providers found unhealthy by any replica in Redis, where they
+/// expire `ttl` after they were last seen unhealthy.
+#[derive(Debug, Clone)]
+pub struct HealthStore {
+    pool: Pool<Client>,
+    ttl: Duration,
+}
+
+impl HealthStore {
+    pub fn new(config: &HealthPersistenceConfig) -> Result<Self, InitError> {
+        let client = Client::open(config.redis.host_url.expose().clone())?;
+        let pool = Pool::builder().build(client)?;
+        Ok(Self {
+            pool,
+            ttl: config.ttl,
+        })
+    }
+
+    /// Records that `provider` is unhealthy, extending the time it is
+    /// considered unhealthy if it already was.
+    pub fn mark_unhealthy(
+        &self,
+        provider: &InferenceProvider,
+    ) -> Result<(), InternalError> {
+        let mut conn = self.pool.get().map_err(InternalError::PoolError)?;
+        let ttl = self.ttl.as_secs().max(1);
+        let _: () = conn
+            .set_ex(redis_key(provider), 1, ttl)
+            .map_err(InternalError::RedisError)?;
+        Ok(())
+    }
+
+    /// Whether `provider` was recently found unhealthy.
+    pub fn is_unhealthy(
+        &self,
+        provider: &InferenceProvider,
+    ) -> Result<bool, InternalError> {
+        let mut conn = self.pool.get().map_err(InternalError::PoolError)?;
+        conn.exists(redis_key(provider))
+            .map_err(InternalError::RedisError)
+    }
+}
+
+fn redis_key(provider: &InferenceProvider) -> String {
+    format!("health:unhealthy:{provider}")
+}
@@ -56,6 +56,10 @@ pub struct EndpointMetrics {
     pub(crate) request_count: RollingCounter,
     /// Count of upstream remote internal errors
     pub(crate) remote_internal_error_count: RollingCounter,
+    /// Count of requests with a recorded latency
+    pub(crate) latency_count: RollingCounter,
+    /// Sum of recorded latencies, in milliseconds
+    pub(crate) latency_ms_total: RollingCounter,
 }
 
 impl EndpointMetrics {
@@ -64,6 +68,8 @@ impl EndpointMetrics {
         Self {
             request_count: RollingCounter::new(window, buckets),
             remote_internal_error_count: RollingCounter::new(window, buckets),
+            latency_count: RollingCounter::new(window, buckets),
+            latency_ms_total: RollingCounter::new(window, buckets),
         }
     }
 
@@ -75,6 +81,24 @@ impl EndpointMetrics {
         self.remote_internal_error_count.incr();
     }
 
+    /// Records the time it took the upstream to respond to a request.
+    pub fn record_latency(&self, latency: Duration) {
+        let millis = u32::try_from(latency.as_millis()).unwrap_or(u32::MAX);
+        self.latency_count.incr();
+        self.latency_ms_total.add(millis);
+    }
+
+    /// Mean latency of the requests in the rolling window, if any.
+    #[must_use]
+    pub fn mean_latency(&self) -> Option<Duration> {
+        let count = self.latency_count.total();
+        if count == 0 {
+            return None;
+        }
+        let total = self.latency_ms_total.total();
+        Some(Duration::from_millis(u64::from(total / count)))
+    }
+
     pub fn incr_for_stream_error(
         &self,
         stream_error: &reqwest_eventsource::Error,
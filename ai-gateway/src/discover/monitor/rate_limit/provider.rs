@@ -272,7 +272,9 @@ impl ProviderMonitorInner<ProviderWeightedKey> {
         };
 
         match balance_config {
-            BalanceConfigInner::ProviderWeighted { providers } => {
+            BalanceConfigInner::ProviderWeighted { providers }
+            | BalanceConfigInner::WeightedLatency { providers, .. }
+            | BalanceConfigInner::LeastInFlight { providers } => {
                 for target in providers {
                     if target.provider == provider {
                         let weight = Weight::from(
@@ -285,6 +287,13 @@ impl ProviderMonitorInner<ProviderWeightedKey> {
                             provider,
                             endpoint_type,
                             weight,
+                        )
+                        .with_schedule(
+                            self.router_config.weight_schedule.as_ref(),
+                        )
+                        .with_latency_bias(
+                            balance_config.latency_bias(),
+                            &self.app_state.0.endpoint_metrics,
                         ));
                     }
                 }
@@ -416,7 +425,7 @@ impl ProviderMonitorInner<ModelWeightedKey> {
         };
         let endpoint_type = event.api_endpoint.endpoint_type();
         let model_config =
-            if let Some(BalanceConfigInner::ModelWeighted { models }) =
+            if let Some(BalanceConfigInner::ModelWeighted { models, .. }) =
                 self.router_config.load_balance.0.get(&endpoint_type)
             {
                 models.iter().find(|m| m.model == model_id)
@@ -1,10 +1,10 @@
 use std::{
-    collections::HashMap,
     sync::Arc,
     task::{Context, Poll},
 };
 
 use futures::future::BoxFuture;
+use indexmap::IndexMap;
 use tokio::sync::mpsc::Receiver;
 use tokio_stream::wrappers::ReceiverStream;
 use tower::{
@@ -18,12 +18,15 @@ use crate::{
     config::router::RouterConfig,
     discover::{
         ServiceMap,
-        dispatcher::{DispatcherDiscovery, factory::DispatcherDiscoverFactory},
+        dispatcher::{
+            DispatcherDiscovery, factory::DispatcherDiscoverFactory,
+            retain_keyed_providers,
+        },
     },
     dispatcher::{Dispatcher, DispatcherService},
-    endpoints::EndpointType,
+    endpoints::{ApiEndpoint, EndpointType},
     error::init::InitError,
-    types::{provider::InferenceProvider, router::RouterId},
+    types::{provider::InferenceProvider, request::Request, router::RouterId},
 };
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -43,6 +46,24 @@ impl Key {
             endpoint_type,
         }
     }
+
+    /// Whether the dispatcher for this key serves the endpoint type the
+    /// request is for.
+    #[must_use]
+    pub fn serves(&self, request: &Request) -> bool {
+        request
+            .extensions()
+            .get::<ApiEndpoint>()
+            .is_some_and(|endpoint| {
+                endpoint.endpoint_type() == self.endpoint_type
+            })
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.provider, self.endpoint_type.as_ref())
+    }
 }
 
 impl DispatcherDiscovery<Key> {
@@ -53,7 +74,7 @@ impl DispatcherDiscovery<Key> {
         rx: Receiver<Change<Key, DispatcherService>>,
     ) -> Result<Self, InitError> {
         let events = ReceiverStream::new(rx);
-        let mut service_map: HashMap<Key, DispatcherService> = HashMap::new();
+        let mut service_map: IndexMap<Key, DispatcherService> = IndexMap::new();
         for (endpoint_type, balance_config) in
             router_config.load_balance.as_ref()
         {
@@ -71,6 +92,10 @@ impl DispatcherDiscovery<Key> {
             }
         }
 
+        retain_keyed_providers(app_state, &mut service_map, |key| {
+            (key.endpoint_type, Some(key.provider.clone()))
+        })
+        .await;
         Ok(Self {
             initial: ServiceMap::new(service_map),
             events,
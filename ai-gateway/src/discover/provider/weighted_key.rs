@@ -1,11 +1,13 @@
 use std::{
-    collections::HashMap,
     sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
+use chrono::{DateTime, FixedOffset, NaiveTime, Utc};
 use futures::future::BoxFuture;
-use rust_decimal::prelude::ToPrimitive;
+use indexmap::IndexMap;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
 use tokio::sync::mpsc::Receiver;
 use tokio_stream::wrappers::ReceiverStream;
 use tower::{Service, discover::Change};
@@ -13,15 +15,23 @@ use weighted_balance::weight::{HasWeight, Weight, WeightedDiscover};
 
 use crate::{
     app_state::AppState,
-    config::{balance::BalanceConfigInner, router::RouterConfig},
+    config::{
+        balance::BalanceConfigInner,
+        router::RouterConfig,
+        weight_schedule::{WeightScheduleConfig, in_window},
+    },
     discover::{
         ServiceMap,
-        dispatcher::{DispatcherDiscovery, factory::DispatcherDiscoverFactory},
+        dispatcher::{
+            DispatcherDiscovery, factory::DispatcherDiscoverFactory,
+            retain_keyed_providers,
+        },
+        monitor::metrics::{EndpointMetrics, EndpointMetricsRegistry},
     },
     dispatcher::{Dispatcher, DispatcherService},
-    endpoints::EndpointType,
+    endpoints::{ApiEndpoint, EndpointType},
     error::init::InitError,
-    types::{provider::InferenceProvider, router::RouterId},
+    types::{provider::InferenceProvider, request::Request, router::RouterId},
 };
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -29,6 +39,12 @@ pub struct WeightedKey {
     pub provider: InferenceProvider,
     pub endpoint_type: EndpointType,
     pub weight: Weight,
+    /// Overrides `weight` during the windows of the router's weight
+    /// schedule.
+    pub schedule: Option<WeightSchedule>,
+    /// Scales the weight by the provider's observed latency for
+    /// `weighted-latency` routers.
+    pub latency: Option<LatencyBias>,
 }
 
 impl WeightedKey {
@@ -42,8 +58,163 @@ impl WeightedKey {
             provider,
             endpoint_type,
             weight,
+            schedule: None,
+            latency: None,
         }
     }
+
+    /// Applies the weights of the router's weight schedule, if any, to this
+    /// key's provider.
+    #[must_use]
+    pub fn with_schedule(
+        mut self,
+        schedule: Option<&WeightScheduleConfig>,
+    ) -> Self {
+        self.schedule =
+            schedule.and_then(|schedule| WeightSchedule::new(schedule, &self));
+        self
+    }
+
+    /// Scales this key's weight by the latency of its provider, as recorded
+    /// in `metrics`, if the router's balancer has a latency bias.
+    #[must_use]
+    pub fn with_latency_bias(
+        mut self,
+        latency_bias: Option<Decimal>,
+        metrics: &EndpointMetricsRegistry,
+    ) -> Self {
+        self.latency = latency_bias.and_then(|bias| {
+            let endpoint =
+                self.provider.endpoints().into_iter().find(|endpoint| {
+                    endpoint.endpoint_type() == self.endpoint_type
+                })?;
+            Some(LatencyBias {
+                bias: Weight::from(bias.to_f64()?),
+                endpoint,
+                metrics: metrics.clone(),
+            })
+        });
+        self
+    }
+
+    /// Whether the dispatcher for this key serves the endpoint type the
+    /// request is for.
+    #[must_use]
+    pub fn serves(&self, request: &Request) -> bool {
+        request
+            .extensions()
+            .get::<ApiEndpoint>()
+            .is_some_and(|endpoint| {
+                endpoint.endpoint_type() == self.endpoint_type
+            })
+    }
+}
+
+impl std::fmt::Display for WeightedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.provider, self.endpoint_type.as_ref())
+    }
+}
+
+/// The latency bias of a provider in a `weighted-latency` router, along with
+/// where to read the provider's rolling latency from.
+#[derive(Debug, Clone)]
+pub struct LatencyBias {
+    bias: Weight,
+    endpoint: ApiEndpoint,
+    metrics: EndpointMetricsRegistry,
+}
+
+impl LatencyBias {
+    fn weight(&self, weight: Weight) -> Weight {
+        let latency = self
+            .metrics
+            .health_metrics(self.endpoint.clone())
+            .ok()
+            .and_then(EndpointMetrics::mean_latency);
+        latency_weighted(weight, self.bias, latency)
+    }
+}
+
+// The metrics registry is shared by every key, so it doesn't distinguish them.
+impl PartialEq for LatencyBias {
+    fn eq(&self, other: &Self) -> bool {
+        self.bias == other.bias && self.endpoint == other.endpoint
+    }
+}
+
+impl Eq for LatencyBias {}
+
+impl std::hash::Hash for LatencyBias {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.bias.hash(state);
+        self.endpoint.hash(state);
+    }
+}
+
+/// Divides `weight` by `latency`, in seconds, raised to the power of `bias`.
+///
+/// Weights are relative, so a bias of `1.0` makes the probability of
+/// selecting a provider proportional to its weight over its latency, while a
+/// bias of `0.0` ignores latency entirely. Providers without an observed
+/// latency keep their weight.
+#[must_use]
+pub fn latency_weighted(
+    weight: Weight,
+    bias: Weight,
+    latency: Option<Duration>,
+) -> Weight {
+    let Some(latency) = latency else {
+        return weight;
+    };
+    // avoid dividing by zero for sub-millisecond latencies
+    let secs = latency.as_secs_f64().max(0.001);
+    Weight::from(f64::from(weight) * secs.powf(-f64::from(bias)))
+}
+
+/// The weights of a single provider during the windows of a
+/// [`WeightScheduleConfig`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct WeightSchedule {
+    utc_offset: FixedOffset,
+    /// The start, end, and weight override of each window, in order.
+    windows: Arc<[(NaiveTime, NaiveTime, Option<Weight>)]>,
+}
+
+impl WeightSchedule {
+    fn new(config: &WeightScheduleConfig, key: &WeightedKey) -> Option<Self> {
+        let windows = config
+            .windows
+            .iter()
+            .map(|window| {
+                let weight = window
+                    .weights
+                    .get(&key.provider)
+                    .and_then(ToPrimitive::to_f64)
+                    .map(Weight::from);
+                (window.start, window.end, weight)
+            })
+            .collect::<Arc<[_]>>();
+        windows
+            .iter()
+            .any(|(_, _, weight)| weight.is_some())
+            .then_some(Self {
+                utc_offset: config.utc_offset,
+                windows,
+            })
+    }
+
+    /// Returns the weight at `now`, or `weight` if the window containing
+    /// `now` doesn't override it.
+    #[must_use]
+    pub fn weight_at(&self, weight: Weight, now: DateTime<Utc>) -> Weight {
+        let time = now.with_timezone(&self.utc_offset).time();
+        self.windows
+            .iter()
+            .find(|(start, end, _)| in_window(*start, *end, time))
+            .and_then(|(_, _, weight)| *weight)
+            .unwrap_or(weight)
+    }
 }
 
 impl DispatcherDiscovery<WeightedKey> {
@@ -53,12 +224,14 @@ impl DispatcherDiscovery<WeightedKey> {
         router_config: &Arc<RouterConfig>,
         rx: Receiver<Change<WeightedKey, DispatcherService>>,
     ) -> Result<Self, InitError> {
-        let mut service_map = HashMap::new();
+        let mut service_map = IndexMap::new();
         for (endpoint_type, balance_config) in
             router_config.load_balance.as_ref()
         {
             let weighted_balance_targets = match balance_config {
-                BalanceConfigInner::ProviderWeighted { providers } => providers,
+                BalanceConfigInner::ProviderWeighted { providers }
+                | BalanceConfigInner::WeightedLatency { providers, .. }
+                | BalanceConfigInner::LeastInFlight { providers } => providers,
                 BalanceConfigInner::ModelWeighted { .. } => {
                     return Err(InitError::InvalidBalancer(
                         "Model weighted balancer not supported for provider \
@@ -79,6 +252,27 @@ impl DispatcherDiscovery<WeightedKey> {
                             .to_string(),
                     ));
                 }
+                BalanceConfigInner::CostWeighted { .. } => {
+                    return Err(InitError::InvalidBalancer(
+                        "Cost weighted balancer not supported for provider \
+                         weighted discovery"
+                            .to_string(),
+                    ));
+                }
+                BalanceConfigInner::RoundRobin { .. } => {
+                    return Err(InitError::InvalidBalancer(
+                        "Round robin balancer not supported for provider \
+                         weighted discovery"
+                            .to_string(),
+                    ));
+                }
+                BalanceConfigInner::Complexity { .. } => {
+                    return Err(InitError::InvalidBalancer(
+                        "Complexity balancer not supported for provider \
+                         weighted discovery"
+                            .to_string(),
+                    ));
+                }
             };
             for target in weighted_balance_targets {
                 let weight =
@@ -89,6 +283,11 @@ impl DispatcherDiscovery<WeightedKey> {
                     target.provider.clone(),
                     *endpoint_type,
                     weight,
+                )
+                .with_schedule(router_config.weight_schedule.as_ref())
+                .with_latency_bias(
+                    balance_config.latency_bias(),
+                    &app_state.0.endpoint_metrics,
                 );
                 let dispatcher = Dispatcher::new(
                     app_state.clone(),
@@ -102,6 +301,10 @@ impl DispatcherDiscovery<WeightedKey> {
         }
         let events = ReceiverStream::new(rx);
 
+        retain_keyed_providers(app_state, &mut service_map, |key| {
+            (key.endpoint_type, Some(key.provider.clone()))
+        })
+        .await;
         Ok(Self {
             initial: ServiceMap::new(service_map),
             events,
@@ -111,7 +314,14 @@ impl DispatcherDiscovery<WeightedKey> {
 
 impl HasWeight for WeightedKey {
     fn weight(&self) -> Weight {
-        self.weight
+        let weight = match &self.schedule {
+            Some(schedule) => schedule.weight_at(self.weight, Utc::now()),
+            None => self.weight,
+        };
+        match &self.latency {
+            Some(latency) => latency.weight(weight),
+            None => weight,
+        }
     }
 }
 
@@ -149,3 +359,109 @@ impl Service<Receiver<Change<WeightedKey, DispatcherService>>>
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::TimeZone;
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::config::weight_schedule::ScheduleWindow;
+
+    /// Prefers `OpenAI` from 09:00 to 17:00 at -05:00, and `Anthropic`
+    /// otherwise.
+    fn schedule() -> WeightScheduleConfig {
+        let window = |start, end, openai, anthropic| ScheduleWindow {
+            start: NaiveTime::from_hms_opt(start, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end, 0, 0).unwrap(),
+            weights: HashMap::from([
+                (InferenceProvider::OpenAI, Decimal::new(openai, 1)),
+                (InferenceProvider::Anthropic, Decimal::new(anthropic, 1)),
+            ]),
+        };
+        WeightScheduleConfig {
+            utc_offset: FixedOffset::west_opt(5 * 3600).unwrap(),
+            windows: vec![window(9, 17, 9, 1), window(17, 9, 1, 9)],
+        }
+    }
+
+    fn key(provider: InferenceProvider) -> WeightedKey {
+        WeightedKey::new(provider, EndpointType::Chat, Weight::from(0.5))
+            .with_schedule(Some(&schedule()))
+    }
+
+    fn preferred(now: DateTime<Utc>) -> InferenceProvider {
+        [InferenceProvider::OpenAI, InferenceProvider::Anthropic]
+            .into_iter()
+            .max_by_key(|provider| {
+                let key = key(provider.clone());
+                key.schedule.unwrap().weight_at(key.weight, now)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn preferred_provider_follows_schedule() {
+        // 10:00 at -05:00
+        let business_hours =
+            Utc.with_ymd_and_hms(2025, 6, 2, 15, 0, 0).unwrap();
+        assert_eq!(preferred(business_hours), InferenceProvider::OpenAI);
+
+        // 20:00 and 03:00 at -05:00
+        let evening = Utc.with_ymd_and_hms(2025, 6, 3, 1, 0, 0).unwrap();
+        assert_eq!(preferred(evening), InferenceProvider::Anthropic);
+        let night = Utc.with_ymd_and_hms(2025, 6, 2, 8, 0, 0).unwrap();
+        assert_eq!(preferred(night), InferenceProvider::Anthropic);
+    }
+
+    #[test]
+    fn providers_without_overrides_keep_configured_weight() {
+        let mut config = schedule();
+        config.windows.truncate(1);
+        let key = key(InferenceProvider::OpenAI).with_schedule(Some(&config));
+        let schedule = key.schedule.clone().unwrap();
+        let evening = Utc.with_ymd_and_hms(2025, 6, 3, 1, 0, 0).unwrap();
+        assert_eq!(schedule.weight_at(key.weight, evening), key.weight);
+
+        let key = key.with_schedule(None);
+        assert!(key.schedule.is_none());
+        let gemini = WeightedKey::new(
+            InferenceProvider::GoogleGemini,
+            EndpointType::Chat,
+            Weight::from(0.5),
+        )
+        .with_schedule(Some(&config));
+        assert!(gemini.schedule.is_none());
+    }
+
+    #[test]
+    fn latency_bias_favors_faster_providers() {
+        let weight = Weight::from(0.5);
+        let fast = Some(Duration::from_millis(500));
+        let slow = Some(Duration::from_secs(2));
+
+        let full = Weight::from(1.0);
+        assert_eq!(latency_weighted(weight, full, fast), Weight::from(1.0));
+        assert_eq!(latency_weighted(weight, full, slow), Weight::from(0.25));
+
+        let half = Weight::from(0.5);
+        assert!(
+            latency_weighted(weight, half, fast)
+                > latency_weighted(weight, half, slow)
+        );
+        assert!(
+            latency_weighted(weight, half, fast)
+                < latency_weighted(weight, full, fast)
+        );
+    }
+
+    #[test]
+    fn latency_is_ignored_without_bias_or_samples() {
+        let weight = Weight::from(0.5);
+        let slow = Some(Duration::from_secs(2));
+        assert_eq!(latency_weighted(weight, Weight::MIN, slow), weight);
+        assert_eq!(latency_weighted(weight, Weight::UNIT, None), weight);
+    }
+}
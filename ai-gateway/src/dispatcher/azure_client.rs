@@ -0,0 +1,186 @@
+use http::{HeaderMap, HeaderName, HeaderValue};
+use indexmap::IndexMap;
+use reqwest::ClientBuilder;
+use url::Url;
+
+use crate::{
+    app_state::AppState,
+    error::{
+        api::ApiError, init::InitError, internal::InternalError,
+        invalid_req::InvalidRequestError, provider::ProviderError,
+    },
+    types::{
+        model_id::ModelId,
+        provider::{InferenceProvider, ProviderKey},
+        secret::Secret,
+    },
+    utils::host_header,
+};
+
+/// The name Azure `OpenAI` is configured with as a named provider.
+pub const PROVIDER_NAME: &str = "azure";
+/// The `api-version` sent if none is configured for the provider.
+pub(crate) const DEFAULT_API_VERSION: &str = "2024-10-21";
+const API_KEY_HEADER: HeaderName = HeaderName::from_static("api-key");
+
+/// Client for Azure `OpenAI`.
+///
+/// Request bodies are the same as `OpenAI`'s, but requests are sent to the
+/// deployment serving the model, authenticated with an `api-key` header
+/// and versioned by an `api-version` query parameter.
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub(super) inner: reqwest::Client,
+    base_url: Url,
+    api_version: String,
+    deployments: IndexMap<ModelId, String>,
+}
+
+impl Client {
+    pub fn new(
+        app_state: &AppState,
+        client_builder: ClientBuilder,
+        provider_key: Option<&ProviderKey>,
+    ) -> Result<Self, InitError> {
+        let provider = InferenceProvider::Named(PROVIDER_NAME.into());
+        let provider_config =
+            app_state.0.config.providers.get(&provider).ok_or_else(|| {
+                ProviderError::ProviderNotConfigured(provider)
+            })?;
+        let base_url = provider_config.base_url.clone();
+        let api_version = provider_config
+            .version
+            .clone()
+            .unwrap_or_else(|| DEFAULT_API_VERSION.to_string());
+
+        let mut default_headers = HeaderMap::new();
+        if let Some(ProviderKey::Secret(key)) = provider_key {
+            default_headers.insert(
+                API_KEY_HEADER,
+                HeaderValue::from_str(key.expose()).unwrap(),
+            );
+        }
+        default_headers.insert(http::header::HOST, host_header(&base_url));
+        default_headers.insert(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_str(mime::APPLICATION_JSON.essence_str())
+                .unwrap(),
+        );
+        let inner = client_builder
+            .default_headers(default_headers)
+            .build()
+            .map_err(InitError::CreateReqwestClient)?;
+        Ok(Self {
+            inner,
+            base_url,
+            api_version,
+            deployments: provider_config.deployments.clone(),
+        })
+    }
+
+    pub fn set_auth_header(
+        request_builder: reqwest::RequestBuilder,
+        key: &Secret<String>,
+    ) -> reqwest::RequestBuilder {
+        request_builder.header(
+            API_KEY_HEADER,
+            HeaderValue::from_str(key.expose()).unwrap(),
+        )
+    }
+
+    /// The URL of the deployment serving `model` at the `OpenAI` path
+    /// `path_and_query`, e.g. `v1/chat/completions`.
+    ///
+    /// Deployments are versioned by the `api-version` query parameter, so
+    /// the version of the `OpenAI` path is dropped.
+    pub(crate) fn target_url(
+        &self,
+        path_and_query: &str,
+        model: Option<&ModelId>,
+    ) -> Result<Url, ApiError> {
+        let model = model.ok_or(InvalidRequestError::MissingModelId)?;
+        let deployment = self
+            .deployments
+            .get(model)
+            .cloned()
+            .unwrap_or_else(|| model.to_string());
+        let path_and_query = path_and_query.trim_start_matches('/');
+        let path_and_query =
+            path_and_query.strip_prefix("v1/").unwrap_or(path_and_query);
+        let mut url = self
+            .base_url
+            .join(&format!("openai/deployments/{deployment}/"))
+            .and_then(|url| url.join(path_and_query))
+            .map_err(|e| {
+                tracing::error!(error = %e, "failed to build azure openai url");
+                InternalError::Internal
+            })?;
+        url.query_pairs_mut()
+            .append_pair("api-version", &self.api_version);
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(deployments: &[(&str, &str)]) -> Client {
+        let provider = InferenceProvider::Named(PROVIDER_NAME.into());
+        Client {
+            inner: reqwest::Client::new(),
+            base_url: Url::parse("https://my-resource.openai.azure.com/")
+                .unwrap(),
+            api_version: DEFAULT_API_VERSION.to_string(),
+            deployments: deployments
+                .iter()
+                .map(|(model, deployment)| {
+                    let model =
+                        ModelId::from_str_and_provider(provider.clone(), model)
+                            .unwrap();
+                    (model, (*deployment).to_string())
+                })
+                .collect(),
+        }
+    }
+
+    fn model(name: &str) -> ModelId {
+        ModelId::from_str_and_provider(
+            InferenceProvider::Named(PROVIDER_NAME.into()),
+            name,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn target_url_uses_the_models_deployment() {
+        let client = client(&[("gpt-4o", "my-gpt-4o")]);
+        let url = client
+            .target_url("/v1/chat/completions", Some(&model("gpt-4o")))
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://my-resource.openai.azure.com/openai/deployments/\
+             my-gpt-4o/chat/completions?api-version=2024-10-21"
+        );
+    }
+
+    #[test]
+    fn models_without_deployment_use_their_name() {
+        let client = client(&[]);
+        let url = client
+            .target_url("v1/chat/completions", Some(&model("gpt-4o-mini")))
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://my-resource.openai.azure.com/openai/deployments/\
+             gpt-4o-mini/chat/completions?api-version=2024-10-21"
+        );
+    }
+
+    #[test]
+    fn target_url_requires_a_model() {
+        let client = client(&[]);
+        assert!(client.target_url("v1/chat/completions", None).is_err());
+    }
+}
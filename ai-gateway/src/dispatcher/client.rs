@@ -1,6 +1,10 @@
+use std::sync::Arc;
+
 use bytes::Bytes;
 use futures::StreamExt;
+use http::{HeaderMap, HeaderName, HeaderValue};
 use http_body_util::BodyExt;
+use indexmap::IndexMap;
 use reqwest::RequestBuilder;
 use reqwest_eventsource::{Event, EventSource, RequestBuilderExt};
 use tracing::{Instrument, info_span};
@@ -9,10 +13,14 @@ use crate::{
     app_state::AppState,
     discover::monitor::metrics::EndpointMetricsRegistry,
     dispatcher::{
-        SSEStream, anthropic_client::Client as AnthropicClient,
+        SSEStream,
+        anthropic_client::Client as AnthropicClient,
+        azure_client::{self, Client as AzureClient},
         bedrock_client::Client as BedrockClient,
+        dns::DnsResolver,
         ollama_client::Client as OllamaClient,
         openai_compatible_client::Client as OpenAICompatibleClient,
+        vertex_client::{self, Client as VertexClient},
     },
     endpoints::ApiEndpoint,
     error::{
@@ -22,9 +30,24 @@ use crate::{
     types::{
         extensions::AuthContext,
         provider::{InferenceProvider, ProviderKey},
+        secret::Secret,
     },
 };
 
+/// The `User-Agent` sent to providers that don't have one configured.
+pub(crate) const DEFAULT_USER_AGENT: &str =
+    concat!("helicone-ai-gateway/", env!("CARGO_PKG_VERSION"));
+/// Headers set by the gateway itself, which custom provider headers can't
+/// override.
+const RESERVED_HEADERS: [HeaderName; 6] = [
+    http::header::AUTHORIZATION,
+    http::header::CONTENT_TYPE,
+    http::header::HOST,
+    http::header::USER_AGENT,
+    HeaderName::from_static("x-api-key"),
+    HeaderName::from_static("api-key"),
+];
+
 pub trait ProviderClient {
     async fn authenticate(
         &self,
@@ -46,9 +69,17 @@ impl ProviderClient for Client {
         provider: InferenceProvider,
     ) -> Result<reqwest::RequestBuilder, ApiError> {
         match self {
-            Client::Bedrock(inner) => inner
-                .extract_and_sign_aws_headers(request_builder, req_body_bytes),
-            Client::OpenAICompatible(_) | Client::Anthropic(_) => {
+            Client::Bedrock(inner) => {
+                inner
+                    .extract_and_sign_aws_headers(
+                        request_builder,
+                        req_body_bytes,
+                    )
+                    .await
+            }
+            Client::OpenAICompatible(_)
+            | Client::Anthropic(_)
+            | Client::Azure(_) => {
                 self.authenticate_inner(
                     app_state,
                     request_builder,
@@ -57,6 +88,11 @@ impl ProviderClient for Client {
                 )
                 .await
             }
+            Client::Vertex(inner) => {
+                inner
+                    .authenticate(app_state, request_builder, auth_ctx)
+                    .await
+            }
             Client::Ollama(_) => Ok(request_builder),
         }
     }
@@ -68,6 +104,8 @@ pub enum Client {
     Anthropic(AnthropicClient),
     Ollama(OllamaClient),
     Bedrock(BedrockClient),
+    Vertex(VertexClient),
+    Azure(AzureClient),
 }
 
 impl Client {
@@ -91,23 +129,7 @@ impl Client {
                 if let Some(ProviderKey::Secret(key)) = provider_key
                     && key.expose() != ""
                 {
-                    let request_builder = match self {
-                        Client::OpenAICompatible(_) => {
-                            OpenAICompatibleClient::set_auth_header(
-                                request_builder,
-                                &key,
-                            )
-                        }
-                        Client::Anthropic(_) => {
-                            AnthropicClient::set_auth_header(
-                                request_builder,
-                                &key,
-                            )
-                        }
-                        _ => request_builder,
-                    };
-
-                    return Ok(request_builder);
+                    return Ok(self.set_auth_header(request_builder, &key));
                 }
 
                 let refetched_org_provider_keys = app_state
@@ -129,23 +151,7 @@ impl Client {
                     .await;
 
                 if let Some(ProviderKey::Secret(key)) = provider_key {
-                    let request_builder = match self {
-                        Client::OpenAICompatible(_) => {
-                            OpenAICompatibleClient::set_auth_header(
-                                request_builder,
-                                key,
-                            )
-                        }
-                        Client::Anthropic(_) => {
-                            AnthropicClient::set_auth_header(
-                                request_builder,
-                                key,
-                            )
-                        }
-                        _ => request_builder,
-                    };
-
-                    return Ok(request_builder);
+                    return Ok(self.set_auth_header(request_builder, key));
                 }
 
                 return Err(ApiError::Authentication(
@@ -158,6 +164,30 @@ impl Client {
         }
     }
 
+    /// Sets `key` in the header the provider expects its API key in.
+    pub(crate) fn set_auth_header(
+        &self,
+        request_builder: reqwest::RequestBuilder,
+        key: &Secret<String>,
+    ) -> reqwest::RequestBuilder {
+        match self {
+            Client::OpenAICompatible(_) => {
+                OpenAICompatibleClient::set_auth_header(request_builder, key)
+            }
+            Client::Anthropic(_) => {
+                AnthropicClient::set_auth_header(request_builder, key)
+            }
+            Client::Azure(_) => {
+                AzureClient::set_auth_header(request_builder, key)
+            }
+            // bedrock requests are signed, and vertex requests use short
+            // lived access tokens rather than keys
+            Client::Ollama(_) | Client::Bedrock(_) | Client::Vertex(_) => {
+                request_builder
+            }
+        }
+    }
+
     pub(crate) async fn sse_stream<B>(
         request_builder: RequestBuilder,
         body: B,
@@ -198,13 +228,56 @@ impl Client {
         inference_provider: InferenceProvider,
         api_key: Option<&ProviderKey>,
     ) -> Result<Self, InitError> {
+        let provider_config =
+            app_state.0.config.providers.get(&inference_provider);
+        let user_agent = provider_config
+            .and_then(|config| config.user_agent.as_deref())
+            .unwrap_or(DEFAULT_USER_AGENT);
+        let tcp = provider_config
+            .map(|config| config.tcp.clone())
+            .unwrap_or_default();
         // connection timeout, timeout, etc.
-        let base_client = reqwest::Client::builder()
+        let mut base_client = reqwest::Client::builder()
             .connect_timeout(app_state.0.config.dispatcher.connection_timeout)
             .timeout(app_state.0.config.dispatcher.timeout)
-            .tcp_nodelay(true);
+            .user_agent(user_agent)
+            .tcp_nodelay(tcp.nodelay)
+            .tcp_keepalive(tcp.keepalive);
+        if let Some(resolver) =
+            DnsResolver::from_config(&app_state.0.config.dispatcher.dns)
+        {
+            base_client = base_client.dns_resolver(Arc::new(resolver));
+        }
+        // the clients' own default headers are added after these, so they
+        // take precedence
+        if let Some(config) = provider_config
+            && !config.headers.is_empty()
+        {
+            base_client = base_client.default_headers(custom_headers(
+                &inference_provider,
+                &config.headers,
+            )?);
+        }
 
         match inference_provider {
+            InferenceProvider::Named(name)
+                if name.as_str() == vertex_client::PROVIDER_NAME =>
+            {
+                Ok(Self::Vertex(VertexClient::new(
+                    app_state,
+                    base_client,
+                    api_key,
+                )?))
+            }
+            InferenceProvider::Named(name)
+                if name.as_str() == azure_client::PROVIDER_NAME =>
+            {
+                Ok(Self::Azure(AzureClient::new(
+                    app_state,
+                    base_client,
+                    api_key,
+                )?))
+            }
             InferenceProvider::OpenAI
             | InferenceProvider::GoogleGemini
             | InferenceProvider::Named(_) => {
@@ -229,6 +302,53 @@ impl Client {
     }
 }
 
+/// Builds the custom headers configured for `provider`, resolving the
+/// environment variables referenced in their values.
+fn custom_headers(
+    provider: &InferenceProvider,
+    headers: &IndexMap<String, String>,
+) -> Result<HeaderMap, InitError> {
+    let mut header_map = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+            InitError::InvalidProviderHeader(provider.clone(), name.clone())
+        })?;
+        if RESERVED_HEADERS.contains(&name) {
+            tracing::warn!(provider = %provider, header = %name, "ignoring custom header set by the gateway");
+            continue;
+        }
+        let value =
+            HeaderValue::from_str(&interpolate_env(value)?).map_err(|_| {
+                InitError::InvalidProviderHeader(
+                    provider.clone(),
+                    name.to_string(),
+                )
+            })?;
+        header_map.insert(name, value);
+    }
+    Ok(header_map)
+}
+
+/// Replaces each `${NAME}` in `value` with the value of the environment
+/// variable `NAME`.
+fn interpolate_env(value: &str) -> Result<String, InitError> {
+    let mut resolved = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let var = std::env::var(name)
+            .map_err(|_| InitError::MissingHeaderEnvVar(name.to_string()))?;
+        resolved.push_str(&rest[..start]);
+        resolved.push_str(&var);
+        rest = &rest[start + 2 + len + 1..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
 impl AsRef<reqwest::Client> for Client {
     fn as_ref(&self) -> &reqwest::Client {
         match self {
@@ -236,6 +356,8 @@ impl AsRef<reqwest::Client> for Client {
             Client::Anthropic(client) => &client.0,
             Client::Ollama(client) => &client.0,
             Client::Bedrock(client) => &client.inner,
+            Client::Vertex(client) => &client.inner,
+            Client::Azure(client) => &client.inner,
         }
     }
 }
@@ -426,3 +548,120 @@ fn record_stream_err_metrics(
         }).ok();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        app::App,
+        config::{Config, deployment_target::DeploymentTarget},
+        types::{org::OrgId, provider::ProviderKeyMap, user::UserId},
+    };
+
+    fn org_keys(key: &str) -> ProviderKeyMap {
+        ProviderKeyMap::from_db(HashMap::from([(
+            InferenceProvider::OpenAI,
+            ProviderKey::Secret(Secret::from(key.to_string())),
+        )]))
+    }
+
+    #[tokio::test]
+    async fn requests_from_each_org_use_that_orgs_provider_key() {
+        let mut config = Config::default();
+        config.deployment_target = DeploymentTarget::Cloud {
+            db_poll_interval: Duration::from_secs(60),
+            listener_reconnect_interval: Duration::from_secs(5),
+        };
+        let app_state =
+            App::build_app_state_with_store(config, None).await.unwrap();
+        let org_a = OrgId::new(Uuid::new_v4());
+        let org_b = OrgId::new(Uuid::new_v4());
+        app_state
+            .set_all_provider_keys(HashMap::from([
+                (org_a, org_keys("sk-org-a")),
+                (org_b, org_keys("sk-org-b")),
+            ]))
+            .await;
+        // the same client is shared by every request a router sends to a
+        // provider, whichever org it's for
+        let client = Client::new(&app_state, InferenceProvider::OpenAI)
+            .await
+            .unwrap();
+
+        for (org_id, expected) in
+            [(org_a, "Bearer sk-org-a"), (org_b, "Bearer sk-org-b")]
+        {
+            let auth_ctx = AuthContext {
+                api_key: Secret::from("sk-helicone-test".to_string()),
+                user_id: UserId::new(Uuid::new_v4()),
+                org_id,
+            };
+            let request = client
+                .authenticate(
+                    &app_state,
+                    reqwest::Client::new()
+                        .post("http://localhost/v1/chat/completions"),
+                    &Bytes::new(),
+                    Some(&auth_ctx),
+                    InferenceProvider::OpenAI,
+                )
+                .await
+                .unwrap()
+                .build()
+                .unwrap();
+            assert_eq!(
+                request.headers().get(http::header::AUTHORIZATION).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn env_vars_are_interpolated() {
+        // SAFETY: no other test reads or writes this variable
+        unsafe {
+            std::env::set_var("AI_GATEWAY_TEST_TEAM_ID", "team-123");
+        }
+        assert_eq!(
+            interpolate_env("${AI_GATEWAY_TEST_TEAM_ID}").unwrap(),
+            "team-123"
+        );
+        assert_eq!(
+            interpolate_env("team=${AI_GATEWAY_TEST_TEAM_ID};").unwrap(),
+            "team=team-123;"
+        );
+        assert_eq!(interpolate_env("$literal ${").unwrap(), "$literal ${");
+        assert!(matches!(
+            interpolate_env("${AI_GATEWAY_TEST_UNSET_VAR}"),
+            Err(InitError::MissingHeaderEnvVar(name))
+                if name == "AI_GATEWAY_TEST_UNSET_VAR"
+        ));
+    }
+
+    #[test]
+    fn reserved_headers_are_not_overridden() {
+        let headers = IndexMap::from([
+            ("x-team-id".to_string(), "team-123".to_string()),
+            ("Authorization".to_string(), "Bearer other".to_string()),
+            ("content-type".to_string(), "text/plain".to_string()),
+        ]);
+        let headers =
+            custom_headers(&InferenceProvider::OpenAI, &headers).unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers.get("x-team-id").unwrap(), "team-123");
+    }
+
+    #[test]
+    fn invalid_header_names_are_an_error() {
+        let headers =
+            IndexMap::from([("x team".to_string(), "team-123".to_string())]);
+        assert!(matches!(
+            custom_headers(&InferenceProvider::OpenAI, &headers),
+            Err(InitError::InvalidProviderHeader(..))
+        ));
+    }
+}
@@ -0,0 +1,114 @@
+use std::{collections::HashMap, io, net::SocketAddr, sync::Arc};
+
+use moka::future::Cache;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+use crate::config::dispatcher::DnsConfig;
+
+const DNS_CACHE_CAPACITY: u64 = 1024;
+
+/// Resolver for provider hosts that applies the static overrides and
+/// caching from [`DnsConfig`].
+#[derive(Debug, Clone)]
+pub(crate) struct DnsResolver {
+    overrides: Arc<HashMap<String, Arc<[SocketAddr]>>>,
+    cache: Option<Cache<String, Arc<[SocketAddr]>>>,
+}
+
+impl DnsResolver {
+    /// Returns `None` if no overrides or caching are configured, in which
+    /// case reqwest's default resolver should be used.
+    pub(crate) fn from_config(config: &DnsConfig) -> Option<Self> {
+        if config.cache_ttl.is_none() && config.overrides.is_empty() {
+            return None;
+        }
+        let overrides = config
+            .overrides
+            .iter()
+            .map(|(host, ips)| {
+                // port 0 makes reqwest use the conventional port for the
+                // request scheme
+                let addrs = ips
+                    .iter()
+                    .map(|ip| SocketAddr::new(*ip, 0))
+                    .collect::<Arc<[SocketAddr]>>();
+                (host.to_ascii_lowercase(), addrs)
+            })
+            .collect();
+        let cache = config.cache_ttl.map(|ttl| {
+            Cache::builder()
+                .max_capacity(DNS_CACHE_CAPACITY)
+                .time_to_live(ttl)
+                .build()
+        });
+        Some(Self {
+            overrides: Arc::new(overrides),
+            cache,
+        })
+    }
+
+    pub(crate) async fn lookup(
+        &self,
+        host: &str,
+    ) -> io::Result<Arc<[SocketAddr]>> {
+        let host = host.to_ascii_lowercase();
+        if let Some(addrs) = self.overrides.get(&host) {
+            return Ok(addrs.clone());
+        }
+        let Some(cache) = &self.cache else {
+            return system_lookup(&host).await;
+        };
+        cache
+            .try_get_with(host.clone(), system_lookup(&host))
+            .await
+            .map_err(|e| io::Error::new(e.kind(), e.to_string()))
+    }
+}
+
+async fn system_lookup(host: &str) -> io::Result<Arc<[SocketAddr]>> {
+    let addrs = tokio::net::lookup_host((host, 0)).await?.collect();
+    Ok(addrs)
+}
+
+impl Resolve for DnsResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let addrs = resolver.lookup(name.as_str()).await?;
+            let addrs: Addrs = Box::new(addrs.to_vec().into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, net::IpAddr, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn default_config_uses_default_resolver() {
+        assert!(DnsResolver::from_config(&DnsConfig::default()).is_none());
+    }
+
+    #[tokio::test]
+    async fn override_resolves_to_pinned_address() {
+        let pinned: IpAddr = "10.1.2.3".parse().unwrap();
+        let config = DnsConfig {
+            cache_ttl: Some(Duration::from_secs(60)),
+            overrides: BTreeMap::from([(
+                "api.openai.com".to_string(),
+                vec![pinned],
+            )]),
+        };
+        let resolver = DnsResolver::from_config(&config).unwrap();
+
+        let addrs = resolver.lookup("api.openai.com").await.unwrap();
+        assert_eq!(&*addrs, &[SocketAddr::new(pinned, 0)]);
+
+        // host names are case insensitive
+        let addrs = resolver.lookup("API.OpenAI.com").await.unwrap();
+        assert_eq!(&*addrs, &[SocketAddr::new(pinned, 0)]);
+    }
+}
@@ -2,7 +2,9 @@ use http::Extensions;
 use typed_builder::TypedBuilder;
 
 use crate::types::{
-    extensions::{AuthContext, MapperContext, ProviderRequestId},
+    extensions::{
+        AuthContext, EffectiveRequest, MapperContext, ProviderRequestId,
+    },
     provider::InferenceProvider,
     router::RouterId,
 };
@@ -13,6 +15,7 @@ pub struct ExtensionsCopier {
     router_id: Option<RouterId>,
     auth_context: Option<AuthContext>,
     provider_request_id: Option<http::HeaderValue>,
+    effective_request: Option<EffectiveRequest>,
     mapper_ctx: MapperContext,
 }
 
@@ -32,6 +35,9 @@ impl ExtensionsCopier {
         if let Some(provider_request_id) = self.provider_request_id {
             resp_extensions.insert(ProviderRequestId(provider_request_id));
         }
+        if let Some(effective_request) = self.effective_request {
+            resp_extensions.insert(effective_request);
+        }
         resp_extensions.insert(self.mapper_ctx);
     }
 }
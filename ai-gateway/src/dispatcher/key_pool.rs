@@ -0,0 +1,214 @@
+use std::{
+    hash::{Hash, Hasher},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use rustc_hash::{FxHashMap as HashMap, FxHasher};
+use tokio::time::Instant;
+
+use crate::{
+    config::providers::ProvidersConfig,
+    dispatcher::vertex_client,
+    types::{org::OrgId, provider::InferenceProvider, secret::Secret},
+};
+
+/// How long a key is avoided after the provider rate limited it, if the
+/// provider didn't say when to retry.
+const DEFAULT_RATE_LIMIT_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// The API keys of a provider, if several are configured with the
+/// `{PROVIDER}_API_KEYS` environment variable as a comma separated list.
+#[derive(Debug)]
+pub struct KeyPool {
+    keys: Vec<Secret<String>>,
+    /// When each key may be used again after the provider rate limited it.
+    rate_limited_until: Mutex<Vec<Option<Instant>>>,
+    next: AtomicUsize,
+}
+
+impl KeyPool {
+    /// Returns `None` if fewer than two keys are given, since there is
+    /// nothing to choose between.
+    #[must_use]
+    pub fn new(keys: Vec<Secret<String>>) -> Option<Self> {
+        if keys.len() < 2 {
+            return None;
+        }
+        Some(Self {
+            rate_limited_until: Mutex::new(vec![None; keys.len()]),
+            keys,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    fn from_env(provider: &InferenceProvider) -> Option<Self> {
+        let env_var =
+            format!("{}_API_KEYS", provider.to_string().to_uppercase());
+        let keys = std::env::var(env_var).ok()?;
+        Self::new(
+            keys.split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(|key| Secret::from(key.to_string()))
+                .collect(),
+        )
+    }
+
+    /// Selects the key for a request, returning its index in the pool along
+    /// with the key.
+    ///
+    /// With `affinity`, the requests of an organization always prefer the
+    /// same key, otherwise keys are used in turn. Keys that are rate limited
+    /// are skipped, unless every key is, in which case the key that is
+    /// available again first is used.
+    pub fn select(&self, affinity: Option<&OrgId>) -> (usize, &Secret<String>) {
+        let len = self.keys.len();
+        let preferred = match affinity {
+            Some(org_id) => {
+                let mut hasher = FxHasher::default();
+                org_id.hash(&mut hasher);
+                usize::try_from(hasher.finish() % len as u64).unwrap_or(0)
+            }
+            None => self.next.fetch_add(1, Ordering::Relaxed) % len,
+        };
+        let now = Instant::now();
+        let rate_limited_until = self
+            .rate_limited_until
+            .lock()
+            .expect("key pool lock poisoned");
+        let candidates = (0..len).map(|offset| (preferred + offset) % len);
+        let index = candidates
+            .clone()
+            .find(|index| {
+                rate_limited_until[*index].is_none_or(|until| until <= now)
+            })
+            .or_else(|| {
+                candidates.min_by_key(|index| rate_limited_until[*index])
+            })
+            .unwrap_or(preferred);
+        (index, &self.keys[index])
+    }
+
+    /// Avoids the key at `index` until `retry_after` has passed.
+    ///
+    /// Returns whether another key can still be used.
+    pub fn rate_limited(
+        &self,
+        index: usize,
+        retry_after: Option<Duration>,
+    ) -> bool {
+        let now = Instant::now();
+        let until = now + retry_after.unwrap_or(DEFAULT_RATE_LIMIT_COOLDOWN);
+        let mut rate_limited_until = self
+            .rate_limited_until
+            .lock()
+            .expect("key pool lock poisoned");
+        if let Some(key_rate_limited_until) = rate_limited_until.get_mut(index)
+        {
+            *key_rate_limited_until = Some(until);
+        }
+        rate_limited_until
+            .iter()
+            .any(|until| until.is_none_or(|until| until <= now))
+    }
+}
+
+/// The [`KeyPool`]s of the providers that have several keys.
+///
+/// Only used by sidecar deployments, cloud deployments have a single key
+/// per provider for each organization.
+#[derive(Debug, Default)]
+pub struct KeyPools(HashMap<InferenceProvider, KeyPool>);
+
+impl KeyPools {
+    #[must_use]
+    pub fn from_env(providers_config: &ProvidersConfig) -> Self {
+        let pools = providers_config
+            .iter()
+            .filter(|(provider, _)| {
+                !matches!(
+                    provider,
+                    InferenceProvider::Ollama | InferenceProvider::Bedrock
+                ) && provider.as_ref() != vertex_client::PROVIDER_NAME
+            })
+            .filter_map(|(provider, _)| {
+                let pool = KeyPool::from_env(provider)?;
+                tracing::debug!(provider = %provider, keys = pool.keys.len(), "discovered provider key pool");
+                Some((provider.clone(), pool))
+            })
+            .collect();
+        Self(pools)
+    }
+
+    #[must_use]
+    pub fn get(&self, provider: &InferenceProvider) -> Option<&KeyPool> {
+        self.0.get(provider)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> KeyPool {
+        KeyPool::new(
+            ["sk-1", "sk-2", "sk-3"]
+                .map(|key| Secret::from(key.to_string()))
+                .to_vec(),
+        )
+        .unwrap()
+    }
+
+    fn org() -> OrgId {
+        OrgId::new(uuid::Uuid::new_v4())
+    }
+
+    #[test]
+    fn single_keys_are_not_pooled() {
+        assert!(KeyPool::new(vec![Secret::from("sk-1".to_string())]).is_none());
+    }
+
+    #[test]
+    fn keys_are_used_in_turn_without_affinity() {
+        let pool = pool();
+        let selected = (0..3).map(|_| pool.select(None).0).collect::<Vec<_>>();
+        assert_eq!(selected, [0, 1, 2]);
+    }
+
+    #[test]
+    fn organizations_consistently_use_the_same_key() {
+        let pool = pool();
+        for _ in 0..10 {
+            let org_id = org();
+            let (index, _) = pool.select(Some(&org_id));
+            for _ in 0..5 {
+                assert_eq!(pool.select(Some(&org_id)).0, index);
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limited_keys_are_rotated_away_from() {
+        let pool = pool();
+        let org_id = org();
+        let (preferred, _) = pool.select(Some(&org_id));
+
+        assert!(pool.rate_limited(preferred, Some(Duration::from_secs(10))));
+        let (rotated, _) = pool.select(Some(&org_id));
+        assert_ne!(rotated, preferred);
+        assert_eq!(pool.select(Some(&org_id)).0, rotated);
+
+        // once every key is rate limited, the one available first is used
+        assert!(pool.rate_limited(rotated, Some(Duration::from_secs(30))));
+        let (last, _) = pool.select(Some(&org_id));
+        assert!(!pool.rate_limited(last, Some(Duration::from_secs(20))));
+        assert_eq!(pool.select(Some(&org_id)).0, preferred);
+
+        tokio::time::advance(Duration::from_secs(11)).await;
+        assert_eq!(pool.select(Some(&org_id)).0, preferred);
+    }
+}
@@ -1,10 +1,15 @@
 pub mod anthropic_client;
+pub mod azure_client;
 mod bedrock_client;
 pub mod client;
+mod dns;
 mod extensions;
+pub mod key_pool;
 pub mod ollama_client;
 pub mod openai_compatible_client;
 pub mod service;
+pub mod vertex_client;
+pub mod warm_pool;
 
 use std::pin::Pin;
 
@@ -29,27 +29,38 @@ use crate::{
     dispatcher::{
         client::{Client, ProviderClient},
         extensions::ExtensionsCopier,
+        warm_pool::WarmClients,
     },
     endpoints::ApiEndpoint,
-    error::{api::ApiError, init::InitError, internal::InternalError},
-    logger::service::LoggerService,
+    error::{
+        api::ApiError, auth::AuthError, init::InitError,
+        internal::InternalError, invalid_req::InvalidRequestError,
+    },
+    logger::{service::LoggerService, usage::ReportedUsage},
     metrics::tfft::TFFTFuture,
     middleware::{
         add_extension::{AddExtensions, AddExtensionsLayer},
-        mapper::{model::ModelMapper, registry::EndpointConverterRegistry},
+        mapper::{
+            fingerprint::ConfigDigest, model::ModelMapper,
+            registry::EndpointConverterRegistry,
+        },
     },
     types::{
         body::BodyReader,
         extensions::{
+            AppliedTransformations, AuthContext, EffectiveRequest,
             MapperContext, PromptContext, RequestContext, RequestKind,
         },
         model_id::ModelId,
-        provider::InferenceProvider,
+        provider::{InferenceProvider, ProviderKey},
         rate_limit::RateLimitEvent,
         request::Request,
         router::RouterId,
     },
-    utils::handle_error::{ErrorHandler, ErrorHandlerLayer},
+    utils::{
+        handle_error::{ErrorHandler, ErrorHandlerLayer},
+        host_header,
+    },
 };
 
 pub type DispatcherFuture = BoxFuture<
@@ -58,8 +69,15 @@ pub type DispatcherFuture = BoxFuture<
 >;
 pub type DispatcherService =
     AddExtensions<ErrorHandler<crate::middleware::mapper::Service<Dispatcher>>>;
-pub type DispatcherServiceWithoutMapper =
-    AddExtensions<ErrorHandler<Dispatcher>>;
+pub type DispatcherServiceWithoutMapper = AddExtensions<
+    ErrorHandler<crate::middleware::embeddings::Service<Dispatcher>>,
+>;
+
+const IDEMPOTENCY_KEY_HEADER: HeaderName =
+    HeaderName::from_static("idempotency-key");
+
+/// Maximum number of bytes of a non-JSON provider response to log.
+const NON_JSON_SNIPPET_LEN: usize = 512;
 
 /// Leaf service that dispatches requests to the correct provider.
 #[derive(Debug, Clone)]
@@ -69,6 +87,9 @@ pub struct Dispatcher {
     provider: InferenceProvider,
     /// Is `Some` for load balanced routers, `None` for direct proxies.
     rate_limit_tx: Option<Sender<RateLimitEvent>>,
+    /// Keeps the client warmed by the warm pool for as long as this
+    /// dispatcher is alive. `None` if the warm pool is not enabled.
+    _warm_handle: Option<Arc<reqwest::Client>>,
 }
 
 impl Dispatcher {
@@ -80,14 +101,35 @@ impl Dispatcher {
     ) -> Result<DispatcherService, InitError> {
         let client = Client::new(&app_state, provider.clone()).await?;
         let rate_limit_tx = app_state.get_rate_limit_tx(router_id).await?;
+        let warm_handle =
+            WarmClients::register(&app_state, &provider, &client).await;
 
         let dispatcher = Self {
             client,
             app_state: app_state.clone(),
             provider: provider.clone(),
             rate_limit_tx: Some(rate_limit_tx),
+            _warm_handle: warm_handle,
         };
         let converter_registry = EndpointConverterRegistry::new(&model_mapper);
+        let mapper_config = app_state.config().mapper;
+        let config_digest = if mapper_config.synthesize_system_fingerprint {
+            ConfigDigest::new(app_state.config())
+        } else {
+            ConfigDigest::default()
+        };
+        let (streaming, mapping_limits, length_finish_reasons) = app_state
+            .config()
+            .providers
+            .get(&provider)
+            .map(|config| {
+                (
+                    config.streaming,
+                    config.mapping_limits,
+                    config.length_finish_reasons.clone(),
+                )
+            })
+            .unwrap_or_default();
 
         let extensions_layer = AddExtensionsLayer::builder()
             .inference_provider(provider.clone())
@@ -97,7 +139,14 @@ impl Dispatcher {
         Ok(ServiceBuilder::new()
             .layer(extensions_layer)
             .layer(ErrorHandlerLayer::new(app_state))
-            .layer(crate::middleware::mapper::Layer::new(converter_registry))
+            .layer(crate::middleware::mapper::Layer::new(
+                converter_registry,
+                mapper_config,
+                streaming,
+                mapping_limits,
+                length_finish_reasons,
+                config_digest,
+            ))
             // other middleware: rate limiting, logging, etc, etc
             // will be added here as well
             .service(dispatcher))
@@ -136,15 +185,36 @@ impl Dispatcher {
         provider: &InferenceProvider,
     ) -> Result<DispatcherService, InitError> {
         let client = Client::new(&app_state, provider.clone()).await?;
+        let warm_handle =
+            WarmClients::register(&app_state, provider, &client).await;
 
         let dispatcher = Self {
             client,
             app_state: app_state.clone(),
             provider: provider.clone(),
             rate_limit_tx: None,
+            _warm_handle: warm_handle,
         };
         let model_mapper = ModelMapper::new(app_state.clone());
         let converter_registry = EndpointConverterRegistry::new(&model_mapper);
+        let mapper_config = app_state.config().mapper;
+        let config_digest = if mapper_config.synthesize_system_fingerprint {
+            ConfigDigest::new(app_state.config())
+        } else {
+            ConfigDigest::default()
+        };
+        let (streaming, mapping_limits, length_finish_reasons) = app_state
+            .config()
+            .providers
+            .get(provider)
+            .map(|config| {
+                (
+                    config.streaming,
+                    config.mapping_limits,
+                    config.length_finish_reasons.clone(),
+                )
+            })
+            .unwrap_or_default();
 
         let extensions_layer = AddExtensionsLayer::builder()
             .inference_provider(provider.clone())
@@ -154,7 +224,14 @@ impl Dispatcher {
         Ok(ServiceBuilder::new()
             .layer(extensions_layer)
             .layer(ErrorHandlerLayer::new(app_state))
-            .layer(crate::middleware::mapper::Layer::new(converter_registry))
+            .layer(crate::middleware::mapper::Layer::new(
+                converter_registry,
+                mapper_config,
+                streaming,
+                mapping_limits,
+                length_finish_reasons,
+                config_digest,
+            ))
             // other middleware: rate limiting, logging, etc, etc
             // will be added here as well
             .service(dispatcher))
@@ -165,13 +242,22 @@ impl Dispatcher {
         provider: &InferenceProvider,
     ) -> Result<DispatcherServiceWithoutMapper, InitError> {
         let client = Client::new(&app_state, provider.clone()).await?;
+        let warm_handle =
+            WarmClients::register(&app_state, provider, &client).await;
 
         let dispatcher = Self {
             client,
             app_state: app_state.clone(),
             provider: provider.clone(),
             rate_limit_tx: None,
+            _warm_handle: warm_handle,
         };
+        let embeddings_config = app_state
+            .config()
+            .providers
+            .get(provider)
+            .map(|config| config.embeddings)
+            .unwrap_or_default();
 
         let extensions_layer = AddExtensionsLayer::builder()
             .inference_provider(provider.clone())
@@ -181,6 +267,7 @@ impl Dispatcher {
         Ok(ServiceBuilder::new()
             .layer(extensions_layer)
             .layer(ErrorHandlerLayer::new(app_state))
+            .layer(crate::middleware::embeddings::Layer::new(embeddings_config))
             // other middleware: rate limiting, logging, etc, etc
             // will be added here as well
             .service(dispatcher))
@@ -209,12 +296,93 @@ impl Service<Request> for Dispatcher {
     }
 }
 
+/// Whether `require_provider_keys` is enabled and `provider` has no
+/// configured API key, in which case its requests are rejected.
+///
+/// Only applies to sidecar deployments, since cloud deployments fetch
+/// provider keys per organization when authenticating the request.
+pub(crate) async fn provider_key_missing(
+    app_state: &AppState,
+    provider: &InferenceProvider,
+) -> bool {
+    let config = &app_state.0.config;
+    if !config.dispatcher.require_provider_keys
+        || config.deployment_target.is_cloud()
+        || *provider == InferenceProvider::Ollama
+    {
+        return false;
+    }
+    !app_state
+        .0
+        .provider_keys
+        .get_provider_key(provider, None)
+        .await
+        .as_ref()
+        .is_some_and(ProviderKey::is_configured)
+}
+
 impl Dispatcher {
+    /// Rejects requests for providers without a configured API key before
+    /// any work is done to dispatch them.
+    ///
+    /// Keyless providers are left out of balanced routers, so this only
+    /// rejects requests when no provider of the router has a key, or when
+    /// the provider was selected without balancing.
+    async fn check_provider_key(&self) -> Result<(), ApiError> {
+        if provider_key_missing(&self.app_state, &self.provider).await {
+            tracing::warn!(
+                provider = %self.provider,
+                "rejecting request for provider without a configured key"
+            );
+            Err(AuthError::ProviderKeyNotConfigured(self.provider.clone())
+                .into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// If the provider has a pool of keys, authenticates the request with
+    /// one of them, returning the index of the selected key.
+    fn select_pooled_key(
+        &self,
+        request_builder: RequestBuilder,
+        auth_ctx: Option<&AuthContext>,
+    ) -> (RequestBuilder, Option<usize>) {
+        let Some(pool) =
+            self.app_state.0.provider_key_pools.get(&self.provider)
+        else {
+            return (request_builder, None);
+        };
+        let affinity = auth_ctx
+            .filter(|_| {
+                self.app_state.config().dispatcher.provider_key_affinity
+            })
+            .map(|auth_ctx| &auth_ctx.org_id);
+        let (index, key) = pool.select(affinity);
+        tracing::trace!(provider = %self.provider, key_index = index, "selected pooled provider key");
+        (
+            self.client.set_auth_header(request_builder, key),
+            Some(index),
+        )
+    }
+
+    fn supports_idempotency_keys(&self) -> bool {
+        self.app_state
+            .config()
+            .providers
+            .get(&self.provider)
+            .is_some_and(|config| config.idempotency_keys)
+    }
+
     #[allow(clippy::too_many_lines)]
     async fn dispatch(
         &self,
         mut req: Request,
     ) -> Result<http::Response<crate::types::body::Body>, ApiError> {
+        self.check_provider_key().await?;
+        // every attempt is logged separately, the client facing id is only
+        // returned in the response header and recorded on the trace span
+        let helicone_request_id = Uuid::new_v4();
         // Extract request context and extensions
         let (
             mapper_ctx,
@@ -228,6 +396,21 @@ impl Dispatcher {
             request_kind,
             prompt_ctx,
         ) = Self::extract_request_context(&mut req)?;
+        if let (Some(router_id), Some(model)) =
+            (router_id.as_ref(), mapper_ctx.model.as_ref())
+        {
+            self.app_state
+                .0
+                .model_rate_limits
+                .read()
+                .await
+                .check(router_id, model)
+                .map_err(InvalidRequestError::TooManyRequests)?;
+        }
+        let transformations = req
+            .extensions_mut()
+            .remove::<AppliedTransformations>()
+            .unwrap_or_default();
 
         let auth_ctx = req_ctx.auth_context.as_ref();
         let target_provider = &self.provider;
@@ -236,6 +419,8 @@ impl Dispatcher {
             h.remove(http::header::HOST);
             h.remove(http::header::AUTHORIZATION);
             h.remove(http::header::CONTENT_LENGTH);
+            // the user agent configured for the provider is sent instead
+            h.remove(http::header::USER_AGENT);
             h.remove(HeaderName::from_str("helicone-api-key").unwrap());
             // TODO: properly support accept encoding
             h.remove(http::header::ACCEPT_ENCODING);
@@ -243,14 +428,37 @@ impl Dispatcher {
                 http::header::ACCEPT_ENCODING,
                 HeaderValue::from_static("identity"),
             );
+            // the request builder is reused for retries, so they all carry
+            // the same key
+            if self.supports_idempotency_keys()
+                && !h.contains_key(IDEMPOTENCY_KEY_HEADER)
+            {
+                h.insert(
+                    IDEMPOTENCY_KEY_HEADER,
+                    HeaderValue::from_str(&Uuid::new_v4().to_string())
+                        .expect("a uuid is always a valid header value"),
+                );
+            }
         }
         let method = req.method().clone();
-        let headers = req.headers().clone();
-        let target_url = self.build_target_url(
-            &req_ctx,
-            target_provider,
-            extracted_path_and_query.as_str(),
-        )?;
+        let mut headers = req.headers().clone();
+        let target_url = if let Some(base_url) =
+            self.model_base_url(target_provider, mapper_ctx.model.as_ref())
+        {
+            // the client's default host header is that of the provider's
+            // base url
+            headers.insert(http::header::HOST, host_header(base_url));
+            base_url.join(extracted_path_and_query.as_str()).expect(
+                "PathAndQuery joined with valid url will always succeed",
+            )
+        } else {
+            self.build_target_url(
+                &req_ctx,
+                target_provider,
+                mapper_ctx.model.as_ref(),
+                extracted_path_and_query.as_str(),
+            )?
+        };
         // TODO: could change request type of dispatcher to
         // http::Request<reqwest::Body>
         // to avoid collecting the body twice
@@ -277,6 +485,8 @@ impl Dispatcher {
                 self.provider.clone(),
             )
             .await?;
+        let (request_builder, pooled_key) =
+            self.select_pooled_key(request_builder, auth_ctx);
 
         let metrics_for_stream = self.app_state.0.endpoint_metrics.clone();
         if let Some(ref api_endpoint) = api_endpoint {
@@ -288,31 +498,63 @@ impl Dispatcher {
             endpoint_metrics.incr_req_count();
         }
 
+        let dispatch_start = Instant::now();
+        let upstream = async {
+            if mapper_ctx.is_stream {
+                dispatch_stream_with_retry(
+                    &self.app_state,
+                    request_builder,
+                    req_body_bytes.clone(),
+                    api_endpoint.clone(),
+                    metrics_for_stream,
+                    &req_ctx,
+                    request_kind,
+                )
+                .await
+            } else {
+                self.dispatch_sync_with_retry(
+                    request_builder,
+                    req_body_bytes.clone(),
+                    &req_ctx,
+                    request_kind,
+                )
+                .instrument(info_span!("dispatch_sync"))
+                .await
+            }
+        };
+        let request_timeout =
+            req_ctx.router_config.as_ref().and_then(|router_config| {
+                router_config.timeout_for(
+                    mapper_ctx.model.as_ref(),
+                    mapper_ctx.is_stream,
+                )
+            });
+        // streaming dispatch resolves once the stream has started, so only
+        // the time to the first byte is limited for streams
         let (mut client_response, response_body_for_logger, tfft_rx): (
             http::Response<crate::types::body::Body>,
             crate::types::body::BodyReader,
             oneshot::Receiver<()>,
-        ) = if mapper_ctx.is_stream {
-            dispatch_stream_with_retry(
-                &self.app_state,
-                request_builder,
-                req_body_bytes.clone(),
-                api_endpoint.clone(),
-                metrics_for_stream,
-                &req_ctx,
-                request_kind,
-            )
-            .await?
-        } else {
-            self.dispatch_sync_with_retry(
-                request_builder,
-                req_body_bytes.clone(),
-                &req_ctx,
-                request_kind,
-            )
-            .instrument(info_span!("dispatch_sync"))
-            .await?
+        ) = match request_timeout {
+            Some(request_timeout) => {
+                tokio::time::timeout(request_timeout, upstream)
+                    .await
+                    .map_err(|_| {
+                        self.handle_timeout(
+                            request_timeout,
+                            api_endpoint.as_ref(),
+                        )
+                    })??
+            }
+            None => upstream.await?,
         };
+        if let Some(ref api_endpoint) = api_endpoint {
+            self.app_state
+                .0
+                .endpoint_metrics
+                .health_metrics(api_endpoint.clone())?
+                .record_latency(dispatch_start.elapsed());
+        }
         tracing::info!(
             method = %method,
             target_url = %target_url,
@@ -320,7 +562,20 @@ impl Dispatcher {
             response_status = %client_response.status(),
             "proxied request"
         );
-        let helicone_request_id = Uuid::new_v4();
+        if !mapper_ctx.is_stream
+            && client_response.status().is_success()
+            && let Some(api_endpoint) = api_endpoint.as_ref()
+            && let Some(content_type) =
+                non_json_content_type(client_response.headers())
+        {
+            return Err(self
+                .handle_non_json_response(
+                    api_endpoint,
+                    content_type,
+                    client_response,
+                )
+                .await);
+        }
         let provider_request_id = {
             let headers = client_response.headers_mut();
             headers.insert(
@@ -331,12 +586,24 @@ impl Dispatcher {
             headers.remove(http::header::CONTENT_LENGTH);
             headers.remove("x-request-id")
         };
-        tracing::debug!(provider_req_id = ?provider_request_id, status = %client_response.status(), "received response");
+        tracing::debug!(
+            helicone_id = %helicone_request_id,
+            provider_req_id = ?provider_request_id,
+            status = %client_response.status(),
+            "received response"
+        );
         let extensions_copier = ExtensionsCopier::builder()
             .inference_provider(inference_provider)
             .router_id(router_id.clone())
             .auth_context(auth_ctx.cloned())
             .provider_request_id(provider_request_id)
+            .effective_request(
+                self.app_state
+                    .config()
+                    .response_headers
+                    .effective_request
+                    .then(|| EffectiveRequest::new(&req_body_bytes)),
+            )
             .mapper_ctx(mapper_ctx.clone())
             .build();
         extensions_copier.copy_extensions(client_response.extensions_mut());
@@ -354,6 +621,7 @@ impl Dispatcher {
             response_status,
             response_headers,
             api_endpoint.clone(),
+            pooled_key,
         )
         .await?;
 
@@ -372,6 +640,8 @@ impl Dispatcher {
             router_id,
             helicone_request_id,
             prompt_ctx,
+            api_endpoint.as_ref(),
+            transformations,
         );
 
         Ok(client_response)
@@ -458,14 +728,90 @@ impl Dispatcher {
         ))
     }
 
+    /// Logs a snippet of a successful response that isn't JSON, e.g. an HTML
+    /// error page from a misbehaving proxy, and counts it against the
+    /// provider's health since the response can't be used.
+    async fn handle_non_json_response(
+        &self,
+        api_endpoint: &ApiEndpoint,
+        content_type: String,
+        response: http::Response<crate::types::body::Body>,
+    ) -> ApiError {
+        let body = match response.into_body().collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(e) => return InternalError::CollectBodyError(e).into(),
+        };
+        let snippet = String::from_utf8_lossy(
+            &body[..body.len().min(NON_JSON_SNIPPET_LEN)],
+        );
+        tracing::warn!(
+            provider = %self.provider,
+            content_type = %content_type,
+            snippet = %snippet,
+            "provider returned a non-JSON response"
+        );
+        match self
+            .app_state
+            .0
+            .endpoint_metrics
+            .health_metrics(api_endpoint.clone())
+        {
+            Ok(endpoint_metrics) => {
+                endpoint_metrics.incr_remote_internal_error_count();
+            }
+            Err(e) => return e.into(),
+        }
+        InternalError::NonJsonProviderResponse(content_type).into()
+    }
+
+    /// Counts a request that timed out against the provider's health, since
+    /// a provider that stops responding should be treated like one that
+    /// fails.
+    fn handle_timeout(
+        &self,
+        request_timeout: Duration,
+        api_endpoint: Option<&ApiEndpoint>,
+    ) -> ApiError {
+        tracing::warn!(
+            provider = %self.provider,
+            timeout = ?request_timeout,
+            "provider did not respond before the request timeout"
+        );
+        if let Some(api_endpoint) = api_endpoint {
+            match self
+                .app_state
+                .0
+                .endpoint_metrics
+                .health_metrics(api_endpoint.clone())
+            {
+                Ok(endpoint_metrics) => {
+                    endpoint_metrics.incr_remote_internal_error_count();
+                }
+                Err(e) => return e.into(),
+            }
+        }
+        InternalError::ProviderTimeout(request_timeout).into()
+    }
+
     /// Handles error responses and rate limiting
     async fn handle_error_and_rate_limiting(
         &self,
         response_status: StatusCode,
         response_headers: &HeaderMap,
         api_endpoint: Option<ApiEndpoint>,
+        pooled_key: Option<usize>,
     ) -> Result<(), ApiError> {
-        if response_status.is_server_error() {
+        if response_status == StatusCode::UNAUTHORIZED
+            && let Client::Vertex(vertex) = &self.client
+        {
+            // the access token may have been revoked before it expired
+            vertex.invalidate_tokens().await;
+        } else if response_status == StatusCode::FORBIDDEN
+            && let Client::Bedrock(bedrock) = &self.client
+        {
+            // the credentials of an assumed role may have expired in flight
+            bedrock.invalidate_credentials().await;
+        } else if response_status.is_server_error() {
             if let Some(api_endpoint) = api_endpoint {
                 let endpoint_metrics = self
                     .app_state
@@ -475,8 +821,24 @@ impl Dispatcher {
                 endpoint_metrics.incr_remote_internal_error_count();
             }
         } else if response_status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = extract_retry_after(response_headers);
+            if let Some(index) = pooled_key
+                && let Some(pool) =
+                    self.app_state.0.provider_key_pools.get(&self.provider)
+                && pool
+                    .rate_limited(index, retry_after.map(Duration::from_secs))
+            {
+                // the provider is only taken out of rotation once every key
+                // is rate limited
+                tracing::info!(
+                    provider = %self.provider,
+                    key_index = index,
+                    retry_after = ?retry_after,
+                    "provider key rate limited, rotating to another key"
+                );
+                return Ok(());
+            }
             if let Some(ref api_endpoint) = api_endpoint {
-                let retry_after = extract_retry_after(response_headers);
                 tracing::info!(
                     provider = ?self.provider,
                     api_endpoint = ?api_endpoint,
@@ -517,9 +879,26 @@ impl Dispatcher {
         router_id: Option<RouterId>,
         helicone_request_id: Uuid,
         prompt_ctx: Option<PromptContext>,
+        api_endpoint: Option<&ApiEndpoint>,
+        transformations: AppliedTransformations,
     ) {
         let deployment_target =
             self.app_state.config().deployment_target.clone();
+        let balance_strategy = req_ctx
+            .router_config
+            .as_ref()
+            .zip(api_endpoint)
+            .and_then(|(router_config, api_endpoint)| {
+                router_config
+                    .load_balance
+                    .strategy(api_endpoint.endpoint_type())
+            })
+            .map(ToString::to_string);
+        let applied_transformations = req_ctx
+            .router_config
+            .as_ref()
+            .is_some_and(|router_config| router_config.audit_transformations)
+            .then_some(transformations.0);
         if self.app_state.config().helicone.is_observability_enabled() {
             if let Some(auth_ctx) = req_ctx.auth_context.clone() {
                 let response_logger = LoggerService::builder()
@@ -539,6 +918,8 @@ impl Dispatcher {
                     .deployment_target(deployment_target)
                     .request_id(helicone_request_id)
                     .prompt_ctx(prompt_ctx)
+                    .balance_strategy(balance_strategy)
+                    .applied_transformations(applied_transformations)
                     .build();
 
                 let app_state = self.app_state.clone();
@@ -564,33 +945,66 @@ impl Dispatcher {
             );
             let path = target_url.path().to_string();
             let provider_string = self.provider.to_string();
+            let is_success = client_response.status().is_success();
+            let is_stream = mapper_ctx.is_stream;
             tokio::spawn(
                     async move {
                         let tfft_future = TFFTFuture::new(start_instant, tfft_rx);
                         let collect_future = response_body_for_logger.collect();
-                        let (_response_body, tfft_duration) = tokio::join!(collect_future, tfft_future);
+                        let (response_body, tfft_duration) = tokio::join!(collect_future, tfft_future);
+                        let attributes = [
+                            KeyValue::new("provider", provider_string),
+                            KeyValue::new("model", model),
+                            KeyValue::new("path", path),
+                        ];
                         if let Ok(tfft_duration) = tfft_duration {
                             tracing::trace!(tfft_duration = ?tfft_duration, "tfft_duration");
-                            let attributes = [
-                                KeyValue::new("provider", provider_string),
-                                KeyValue::new("model", model),
-                                KeyValue::new("path", path),
-                            ];
                             #[allow(clippy::cast_precision_loss)]
                             app_state.0.metrics.tfft_duration.record(tfft_duration.as_millis() as f64, &attributes);
                         } else { tracing::error!("Failed to get TFFT signal") }
+                        if let Ok(response_body) = response_body
+                            && is_success
+                            && let Some(usage) = ReportedUsage::from_response(&response_body.to_bytes(), is_stream)
+                        {
+                            usage.record(&app_state.0.metrics, &attributes[..2]);
+                        }
                     }
                     .instrument(tracing::Span::current()),
                 );
         }
     }
 
+    /// The base url configured for `model` if it isn't served from the
+    /// provider's base url.
+    fn model_base_url(
+        &self,
+        target_provider: &InferenceProvider,
+        model: Option<&ModelId>,
+    ) -> Option<&url::Url> {
+        let model = model?;
+        self.app_state
+            .config()
+            .providers
+            .get(target_provider)?
+            .model_base_urls
+            .get(model)
+    }
+
     fn build_target_url(
         &self,
         req_ctx: &RequestContext,
         target_provider: &InferenceProvider,
+        model: Option<&ModelId>,
         extracted_path_and_query: &str,
     ) -> Result<url::Url, ApiError> {
+        // the vertex ai url depends on the configured project and region
+        if let Client::Vertex(vertex) = &self.client {
+            return vertex.target_url(extracted_path_and_query);
+        }
+        // azure openai urls contain the deployment serving the model
+        if let Client::Azure(azure) = &self.client {
+            return azure.target_url(extracted_path_and_query, model);
+        }
         let config = self.app_state.config();
         if let Some(router_config) = req_ctx.router_config.as_ref()
             && let Some(router_provider_config) =
@@ -719,23 +1133,44 @@ impl Dispatcher {
         Ok((response, body_reader, tfft_rx))
     }
 
-    #[allow(clippy::too_many_lines)]
     async fn dispatch_sync_with_retry(
         &self,
         request_builder: RequestBuilder,
         req_body_bytes: Bytes,
         req_ctx: &RequestContext,
         request_kind: RequestKind,
-    ) -> Result<
-        (
-            http::Response<crate::types::body::Body>,
-            crate::types::body::BodyReader,
-            oneshot::Receiver<()>,
-        ),
-        ApiError,
-    > {
+    ) -> SyncDispatchResult {
         let retry_config =
             get_retry_config(&self.app_state, request_kind, req_ctx);
+        let retry_status_codes = req_ctx
+            .router_config
+            .as_ref()
+            .map_or(&[][..], |config| config.retry_status_codes.as_slice());
+        let future_fn = || async {
+            Self::dispatch_sync(&request_builder, req_body_bytes.clone()).await
+        };
+        let notify_fn = |result: &SyncDispatchResult, dur: Duration| {
+            self.app_state.0.metrics.retry_count.add(
+                1,
+                &[KeyValue::new("provider", self.provider.to_string())],
+            );
+            match result {
+                Ok(result) => {
+                    tracing::warn!(
+                        error = %result.0.status(),
+                        retry_in = ?dur,
+                        "got error dispatching sync request, retrying...",
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        error = %e,
+                        retry_in = ?dur,
+                        "got error dispatching sync request, retrying...",
+                    );
+                }
+            }
+        };
         if let Some(retry_config) = retry_config {
             match retry_config {
                 RetryConfig::Exponential {
@@ -753,45 +1188,15 @@ impl Dispatcher {
                         ))
                         .with_jitter()
                         .build();
-                    let future_fn = || async {
-                        let result = Self::dispatch_sync(
-                            &request_builder,
-                            req_body_bytes.clone(),
-                        )
-                        .await?;
-
-                        Ok(result)
-                    };
-
-                    crate::utils::retry::RetryWithResult::new(future_fn, retry_strategy)
-                    .when(|result: &Result<_, _>| match result {
-                        Ok(response) => response.0.status().is_server_error(),
-                        Err(e) => match e {
-                            ApiError::Internal(InternalError::ReqwestError(
-                                reqwest_error,
-                            )) => reqwest_error.is_connect() || reqwest_error.status().is_some_and(|s| s.is_server_error()),
-                            _ => false,
-                        },
-                    })
-                    .notify(|result: &Result<_, _>, dur: Duration| match result {
-                        Ok(result) if result.0.status().is_server_error() => {
-                                tracing::warn!(
-                                    error = %result.0.status(),
-                                    retry_in = ?dur,
-                                    "got error dispatching sync request, retrying...",
-                                );
-                        }
-                        Err(ApiError::Internal(InternalError::ReqwestError(
-                            reqwest_error,
-                        ))) if reqwest_error.is_connect() || reqwest_error.status().is_some_and(|s| s.is_server_error()) => {
-                                tracing::warn!(
-                                    error = %reqwest_error,
-                                    retry_in = ?dur,
-                                    "got error dispatching sync request, retrying...",
-                                );
-                            }
-                        _ => {}
+                    crate::utils::retry::RetryWithResult::new(
+                        future_fn,
+                        retry_strategy,
+                    )
+                    .when(|result: &SyncDispatchResult| {
+                        should_retry_sync(result, retry_status_codes)
                     })
+                    .adjust(retry_after_backoff(retry_config.max_delay()))
+                    .notify(notify_fn)
                     .await
                 }
                 RetryConfig::Constant { delay, max_retries } => {
@@ -800,48 +1205,79 @@ impl Dispatcher {
                         .with_max_times(usize::from(*max_retries))
                         .with_jitter()
                         .build();
-                    let future_fn = || async {
-                        Self::dispatch_sync(
-                            &request_builder,
-                            req_body_bytes.clone(),
-                        )
-                        .await
-                    };
-
-                    crate::utils::retry::RetryWithResult::new(future_fn, retry_strategy)
-                    .when(|result: &Result<_, _>| match result {
-                        Ok(response) => response.0.status().is_server_error(),
-                        Err(e) => match e {
-                            ApiError::Internal(InternalError::ReqwestError(
-                                reqwest_error,
-                            )) => reqwest_error.is_connect() || reqwest_error.status().is_some_and(|s| s.is_server_error()),
-                            _ => false,
-                        },
-                    })
-                    .notify(|result: &Result<_, _>, dur: Duration| match result {
-                        Ok(result) if result.0.status().is_server_error() => {
-                                tracing::warn!(
-                                    error = %result.0.status(),
-                                    retry_in = ?dur,
-                                    "got error dispatching sync request, retrying...",
-                                );
-                        }
-                        Err(ApiError::Internal(InternalError::ReqwestError(
-                            reqwest_error,
-                        ))) if reqwest_error.is_connect() || reqwest_error.status().is_some_and(|s| s.is_server_error()) => {
-                                tracing::warn!(
-                                    error = %reqwest_error,
-                                    retry_in = ?dur,
-                                    "got error dispatching sync request, retrying...",
-                                );
-                            }
-                        _ => {}
+                    crate::utils::retry::RetryWithResult::new(
+                        future_fn,
+                        retry_strategy,
+                    )
+                    .when(|result: &SyncDispatchResult| {
+                        should_retry_sync(result, retry_status_codes)
                     })
+                    .adjust(retry_after_backoff(retry_config.max_delay()))
+                    .notify(notify_fn)
                     .await
                 }
             }
         } else {
-            Self::dispatch_sync(&request_builder, req_body_bytes.clone()).await
+            future_fn().await
+        }
+    }
+}
+
+type SyncDispatchResult = Result<
+    (
+        http::Response<crate::types::body::Body>,
+        crate::types::body::BodyReader,
+        oneshot::Receiver<()>,
+    ),
+    ApiError,
+>;
+
+/// Whether a non-streaming request should be retried: on connection errors,
+/// server errors, and the router's additional retryable status codes, e.g.
+/// `429`.
+fn should_retry_sync(
+    result: &SyncDispatchResult,
+    status_codes: &[u16],
+) -> bool {
+    match result {
+        Ok(response) => {
+            let status = response.0.status();
+            status.is_server_error() || status_codes.contains(&status.as_u16())
+        }
+        Err(ApiError::Internal(InternalError::ReqwestError(reqwest_error))) => {
+            reqwest_error.is_connect()
+                || reqwest_error.status().is_some_and(|s| s.is_server_error())
+        }
+        Err(_) => false,
+    }
+}
+
+/// Waits for as long as the provider asked to with a `Retry-After` header,
+/// instead of the computed backoff, as long as retries remain.
+///
+/// Gives up if the provider asked to wait for longer than `max_delay`, in
+/// which case its response is returned as is.
+fn retry_after_backoff(
+    max_delay: Duration,
+) -> impl FnMut(&SyncDispatchResult, Option<Duration>) -> Option<Duration> {
+    move |result, backoff| {
+        let backoff = backoff?;
+        let retry_after = result
+            .as_ref()
+            .ok()
+            .and_then(|response| extract_retry_after(response.0.headers()))
+            .map(Duration::from_secs);
+        match retry_after {
+            Some(retry_after) if retry_after > max_delay => {
+                tracing::debug!(
+                    retry_after = ?retry_after,
+                    max_delay = ?max_delay,
+                    "provider asked to retry after the max delay, giving up"
+                );
+                None
+            }
+            Some(retry_after) => Some(retry_after),
+            None => Some(backoff),
         }
     }
 }
@@ -981,6 +1417,22 @@ fn extract_retry_after(headers: &HeaderMap) -> Option<u64> {
     None
 }
 
+/// Returns the content type of a response whose body is declared as
+/// something other than JSON, e.g. an HTML error page served by a proxy in
+/// front of the provider.
+///
+/// Responses without a content type are assumed to be JSON.
+fn non_json_content_type(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())?;
+    if content_type.to_ascii_lowercase().contains("json") {
+        None
+    } else {
+        Some(content_type.to_string())
+    }
+}
+
 fn stream_response_headers() -> HeaderMap {
     HeaderMap::from_iter([
         (
@@ -0,0 +1,401 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use http::{HeaderMap, HeaderValue};
+use reqwest::ClientBuilder;
+use ring::{rand::SystemRandom, signature};
+use rustc_hash::FxHashMap as HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::{sync::Mutex, time::Instant};
+use url::Url;
+
+use crate::{
+    app_state::AppState,
+    error::{
+        api::ApiError, auth::AuthError, init::InitError,
+        internal::InternalError, provider::ProviderError,
+    },
+    types::{
+        extensions::AuthContext,
+        provider::{InferenceProvider, ProviderKey},
+        secret::Secret,
+    },
+    utils::host_header,
+};
+
+/// The name Vertex AI is configured with as a named provider.
+pub const PROVIDER_NAME: &str = "vertex";
+
+const TOKEN_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const JWT_BEARER_GRANT_TYPE: &str =
+    "urn:ietf:params:oauth:grant-type:jwt-bearer";
+/// The lifetime requested for the signed assertion, the maximum Google
+/// accepts.
+const ASSERTION_LIFETIME: Duration = Duration::from_secs(60 * 60);
+/// Access tokens are refreshed this long before they expire, so that they
+/// don't expire while a request is in flight.
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+const GLOBAL_HOST: &str = "aiplatform.googleapis.com";
+const GLOBAL_REGION: &str = "global";
+
+/// Client for Google Vertex AI's `OpenAI` compatible endpoint.
+///
+/// Requests are authenticated with an OAuth2 access token obtained by
+/// exchanging a JWT signed with the service account's key, which is
+/// configured as the provider's key in the service account JSON format.
+/// Tokens are cached per service account until shortly before they expire.
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub(super) inner: reqwest::Client,
+    /// `None` if no project is configured, in which case requests fail.
+    base_url: Option<Url>,
+    /// Client for the token endpoint, without the default headers sent to
+    /// Vertex AI.
+    token_client: reqwest::Client,
+    service_account: Option<Secret<String>>,
+    tokens: Arc<Mutex<HashMap<String, AccessToken>>>,
+}
+
+#[derive(Debug, Clone)]
+struct AccessToken {
+    token: Secret<String>,
+    expires_at: Instant,
+}
+
+/// The fields of a service account key file needed to obtain access
+/// tokens.
+#[derive(Deserialize)]
+struct ServiceAccount {
+    client_email: String,
+    private_key: Secret<String>,
+    token_uri: Url,
+    #[serde(default)]
+    project_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Secret<String>,
+    expires_in: u64,
+}
+
+impl Client {
+    pub fn new(
+        app_state: &AppState,
+        client_builder: ClientBuilder,
+        provider_key: Option<&ProviderKey>,
+    ) -> Result<Self, InitError> {
+        let provider = InferenceProvider::Named(PROVIDER_NAME.into());
+        let provider_config =
+            app_state.0.config.providers.get(&provider).ok_or_else(|| {
+                ProviderError::ProviderNotConfigured(provider)
+            })?;
+        let service_account = match provider_key {
+            Some(ProviderKey::Secret(key)) => Some(key.clone()),
+            _ => None,
+        };
+        let project = provider_config.project.clone().or_else(|| {
+            service_account
+                .as_ref()
+                .and_then(|key| ServiceAccount::parse(key).ok())
+                .and_then(|service_account| service_account.project_id)
+        });
+        let base_url = project.map(|project| {
+            regional_base_url(
+                &provider_config.base_url,
+                &project,
+                provider_config.region.as_deref().unwrap_or(GLOBAL_REGION),
+            )
+        });
+        if base_url.is_none() {
+            tracing::debug!("no project configured for vertex ai");
+        }
+
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(
+            http::header::HOST,
+            host_header(base_url.as_ref().unwrap_or(&provider_config.base_url)),
+        );
+        default_headers.insert(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_str(mime::APPLICATION_JSON.essence_str())
+                .expect("application/json is always a valid header value"),
+        );
+        let inner = client_builder
+            .default_headers(default_headers)
+            .build()
+            .map_err(InitError::CreateReqwestClient)?;
+        let token_client = reqwest::Client::builder()
+            .timeout(app_state.0.config.dispatcher.connection_timeout)
+            .build()
+            .map_err(InitError::CreateReqwestClient)?;
+        Ok(Self {
+            inner,
+            base_url,
+            token_client,
+            service_account,
+            tokens: Arc::default(),
+        })
+    }
+
+    /// The URL of the `OpenAI` compatible endpoint at `path`, e.g.
+    /// `v1/chat/completions`.
+    ///
+    /// The endpoint is versioned by the base URL, so the version of the
+    /// `OpenAI` path is dropped.
+    pub(crate) fn target_url(&self, path: &str) -> Result<Url, ApiError> {
+        let base_url = self.base_url.as_ref().ok_or_else(|| {
+            InternalError::ProviderNotConfigured(InferenceProvider::Named(
+                PROVIDER_NAME.into(),
+            ))
+        })?;
+        let path = path.trim_start_matches('/');
+        let path = path.strip_prefix("v1/").unwrap_or(path);
+        base_url.join(path).map_err(|e| {
+            tracing::error!(error = %e, "failed to build vertex ai url");
+            InternalError::Internal.into()
+        })
+    }
+
+    /// Sets the access token of the service account in the `Authorization`
+    /// header.
+    ///
+    /// Cloud deployments use the service account of the request's
+    /// organization, sidecar deployments the one configured for the
+    /// provider.
+    pub(crate) async fn authenticate(
+        &self,
+        app_state: &AppState,
+        request_builder: reqwest::RequestBuilder,
+        auth_ctx: Option<&AuthContext>,
+    ) -> Result<reqwest::RequestBuilder, ApiError> {
+        let key = if app_state.0.config.deployment_target.is_cloud() {
+            let org_id = auth_ctx
+                .ok_or(ApiError::Authentication(
+                    AuthError::ProviderKeyNotFound,
+                ))?
+                .org_id;
+            match app_state
+                .0
+                .provider_keys
+                .get_provider_key(
+                    &InferenceProvider::Named(PROVIDER_NAME.into()),
+                    Some(&org_id),
+                )
+                .await
+            {
+                Some(ProviderKey::Secret(key)) => key,
+                _ => {
+                    return Err(ApiError::Authentication(
+                        AuthError::ProviderKeyNotFound,
+                    ));
+                }
+            }
+        } else {
+            self.service_account
+                .clone()
+                .ok_or(ApiError::Authentication(
+                    AuthError::ProviderKeyNotFound,
+                ))?
+        };
+        let service_account = ServiceAccount::parse(&key)?;
+        let token = self.access_token(&service_account).await?;
+        Ok(request_builder.bearer_auth(token.expose()))
+    }
+
+    /// Forgets all cached access tokens, e.g. after Vertex AI rejected one,
+    /// so that new tokens are obtained for subsequent requests.
+    pub(crate) async fn invalidate_tokens(&self) {
+        tracing::debug!("invalidating cached vertex ai access tokens");
+        self.tokens.lock().await.clear();
+    }
+
+    async fn access_token(
+        &self,
+        service_account: &ServiceAccount,
+    ) -> Result<Secret<String>, InternalError> {
+        // the lock is held while a token is fetched, so that concurrent
+        // requests don't all fetch one
+        let mut tokens = self.tokens.lock().await;
+        if let Some(token) = tokens.get(&service_account.client_email)
+            && token.expires_at > Instant::now() + TOKEN_EXPIRY_MARGIN
+        {
+            return Ok(token.token.clone());
+        }
+
+        let assertion = service_account.signed_assertion()?;
+        let response = self
+            .token_client
+            .post(service_account.token_uri.clone())
+            .form(&[
+                ("grant_type", JWT_BEARER_GRANT_TYPE),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| InternalError::GcpAuthError(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(InternalError::GcpAuthError(format!(
+                "token endpoint returned {status}: {body}"
+            )));
+        }
+        let token = response
+            .json::<TokenResponse>()
+            .await
+            .map_err(|e| InternalError::GcpAuthError(e.to_string()))?;
+        tracing::debug!(
+            client_email = %service_account.client_email,
+            expires_in = token.expires_in,
+            "obtained vertex ai access token"
+        );
+        tokens.insert(
+            service_account.client_email.clone(),
+            AccessToken {
+                token: token.access_token.clone(),
+                expires_at: Instant::now()
+                    + Duration::from_secs(token.expires_in),
+            },
+        );
+        Ok(token.access_token)
+    }
+}
+
+impl ServiceAccount {
+    fn parse(key: &Secret<String>) -> Result<Self, InternalError> {
+        serde_json::from_str(key.expose()).map_err(|e| {
+            InternalError::GcpAuthError(format!(
+                "invalid service account key: {e}"
+            ))
+        })
+    }
+
+    /// A JWT asserting the service account's identity, signed with its
+    /// private key, to be exchanged for an access token.
+    fn signed_assertion(&self) -> Result<String, InternalError> {
+        let iat = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let claims = Claims {
+            iss: &self.client_email,
+            scope: TOKEN_SCOPE,
+            aud: self.token_uri.as_str(),
+            iat,
+            exp: iat + ASSERTION_LIFETIME.as_secs(),
+        };
+        let header = json!({ "alg": "RS256", "typ": "JWT" });
+        let claims = serde_json::to_vec(&claims)
+            .map_err(|e| InternalError::GcpAuthError(e.to_string()))?;
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims)
+        );
+
+        let key_pair = self.key_pair()?;
+        let mut signature = vec![0; key_pair.public().modulus_len()];
+        key_pair
+            .sign(
+                &signature::RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                message.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| {
+                InternalError::GcpAuthError(
+                    "failed to sign assertion".to_string(),
+                )
+            })?;
+        Ok(format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    /// Decodes the service account's PEM encoded private key, which is
+    /// PKCS#8 for keys created by Google Cloud.
+    fn key_pair(&self) -> Result<signature::RsaKeyPair, InternalError> {
+        let pem = self.private_key.expose();
+        let der = pem
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with("-----"))
+            .collect::<String>();
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(der)
+            .map_err(|e| {
+                InternalError::GcpAuthError(format!("invalid private key: {e}"))
+            })?;
+        let key_pair = if pem.contains("BEGIN RSA PRIVATE KEY") {
+            signature::RsaKeyPair::from_der(&der)
+        } else {
+            signature::RsaKeyPair::from_pkcs8(&der)
+        };
+        key_pair.map_err(|e| {
+            InternalError::GcpAuthError(format!("invalid private key: {e}"))
+        })
+    }
+}
+
+/// Builds the base URL of the `OpenAI` compatible endpoint for `project` in
+/// `region`.
+///
+/// Regional requests are sent to the region's host, unless a custom host
+/// is configured.
+fn regional_base_url(base_url: &Url, project: &str, region: &str) -> Url {
+    let mut base_url = base_url.clone();
+    if region != GLOBAL_REGION && base_url.host_str() == Some(GLOBAL_HOST) {
+        let regional_host = format!("{region}-{GLOBAL_HOST}");
+        if base_url.set_host(Some(&regional_host)).is_err() {
+            tracing::warn!(region = %region, "invalid vertex ai region");
+        }
+    }
+    base_url
+        .join(&format!(
+            "v1/projects/{project}/locations/{region}/endpoints/openapi/"
+        ))
+        .unwrap_or(base_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regional_base_url_uses_regional_host() {
+        let base_url =
+            Url::parse("https://aiplatform.googleapis.com/").unwrap();
+        assert_eq!(
+            regional_base_url(&base_url, "my-project", "us-central1").as_str(),
+            "https://us-central1-aiplatform.googleapis.com/v1/projects/\
+             my-project/locations/us-central1/endpoints/openapi/"
+        );
+        assert_eq!(
+            regional_base_url(&base_url, "my-project", "global").as_str(),
+            "https://aiplatform.googleapis.com/v1/projects/my-project/\
+             locations/global/endpoints/openapi/"
+        );
+    }
+
+    #[test]
+    fn custom_host_is_kept() {
+        let base_url = Url::parse("http://localhost:8080/").unwrap();
+        assert_eq!(
+            regional_base_url(&base_url, "my-project", "europe-west4").as_str(),
+            "http://localhost:8080/v1/projects/my-project/locations/\
+             europe-west4/endpoints/openapi/"
+        );
+    }
+}
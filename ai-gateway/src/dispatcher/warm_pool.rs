@@ -0,0 +1,300 @@
+//! Keep idle connections to providers open ahead of traffic, so that bursts
+//! don't pay for TCP and TLS handshakes on the request path.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError, Weak},
+    time::{Duration, Instant},
+};
+
+use futures::future::{self, BoxFuture};
+use meltdown::Token;
+use tokio::{sync::RwLock, time};
+use tracing::{debug, error, trace};
+use url::Url;
+
+use crate::{
+    app_state::AppState, config::dispatcher::WarmPoolConfig,
+    discover::monitor::metrics::EndpointMetricsRegistry,
+    dispatcher::client::Client, error::runtime::RuntimeError,
+    types::provider::InferenceProvider,
+};
+
+/// How long reqwest keeps idle connections in a client's pool, which the
+/// provider clients don't override.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// The clients of live dispatchers, by provider.
+///
+/// Every dispatcher has its own connection pool, so a provider's warm
+/// connections are split between its dispatchers' clients. Clients are held
+/// weakly so that the clients of dropped dispatchers, e.g. ones replaced after
+/// a rate limit, are not kept alive.
+#[derive(Debug, Default)]
+pub struct WarmClients(
+    RwLock<HashMap<InferenceProvider, Vec<Arc<WarmClient>>>>,
+);
+
+#[derive(Debug)]
+struct WarmClient {
+    client: Weak<reqwest::Client>,
+    last_warmup: Mutex<Option<Warmup>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Warmup {
+    at: Instant,
+    connections: usize,
+    /// Whether the provider answered over HTTP/2, in which case every
+    /// request shares a single connection.
+    multiplexed: bool,
+}
+
+impl WarmClients {
+    /// Registers the `client` of a dispatcher if the warm pool is enabled.
+    /// The client is warmed for as long as the returned handle is alive.
+    pub(crate) async fn register(
+        app_state: &AppState,
+        provider: &InferenceProvider,
+        client: &Client,
+    ) -> Option<Arc<reqwest::Client>> {
+        app_state.config().dispatcher.warm_pool.as_ref()?;
+        let handle = Arc::new(client.as_ref().clone());
+        app_state
+            .0
+            .warm_clients
+            .0
+            .write()
+            .await
+            .entry(provider.clone())
+            .or_default()
+            .push(Arc::new(WarmClient {
+                client: Arc::downgrade(&handle),
+                last_warmup: Mutex::new(None),
+            }));
+        Some(handle)
+    }
+
+    /// Returns the live clients of each provider, forgetting dropped ones.
+    async fn live(
+        &self,
+    ) -> Vec<(
+        InferenceProvider,
+        Vec<(Arc<reqwest::Client>, Arc<WarmClient>)>,
+    )> {
+        let mut clients = self.0.write().await;
+        clients.retain(|_, clients| {
+            clients.retain(|warm| warm.client.strong_count() > 0);
+            !clients.is_empty()
+        });
+        clients
+            .iter()
+            .map(|(provider, clients)| {
+                let clients = clients
+                    .iter()
+                    .filter_map(|warm| {
+                        Some((warm.client.upgrade()?, warm.clone()))
+                    })
+                    .collect();
+                (provider.clone(), clients)
+            })
+            .collect()
+    }
+}
+
+impl WarmClient {
+    /// Whether the client's pool should be topped up to `connections`.
+    ///
+    /// Idle connections stay in the pool until they time out, so pools are
+    /// only topped up when they should grow, or before the connections
+    /// opened by the last warmup would expire.
+    fn needs_warmup(&self, connections: usize, interval: Duration) -> bool {
+        let last_warmup = *self
+            .last_warmup
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        last_warmup.is_none_or(|warmup| {
+            (!warmup.multiplexed && connections > warmup.connections)
+                || warmup.at.elapsed() + interval >= POOL_IDLE_TIMEOUT
+        })
+    }
+
+    fn warmed(&self, warmup: Warmup) {
+        *self
+            .last_warmup
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(warmup);
+    }
+
+    fn is_multiplexed(&self) -> bool {
+        self.last_warmup
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_some_and(|warmup| warmup.multiplexed)
+    }
+}
+
+/// Periodically sizes the pool of warm connections of each provider to the
+/// provider's recent traffic, and opens connections until the pool is full.
+#[derive(Debug, Clone)]
+pub struct WarmPool {
+    app_state: AppState,
+    config: WarmPoolConfig,
+}
+
+impl WarmPool {
+    /// Returns `None` if the warm pool is not enabled.
+    #[must_use]
+    pub fn new(app_state: AppState) -> Option<Self> {
+        let config = app_state.config().dispatcher.warm_pool.clone()?;
+        Some(Self { app_state, config })
+    }
+
+    /// The number of connections to keep warm for the `provider`.
+    #[must_use]
+    pub fn pool_size(&self, provider: &InferenceProvider) -> usize {
+        let recent_requests =
+            recent_requests(&self.app_state.0.endpoint_metrics, provider);
+        self.config.pool_size(recent_requests)
+    }
+
+    pub async fn run_forever(self) -> Result<(), RuntimeError> {
+        tracing::info!("starting provider warm pool");
+        let mut interval = time::interval(self.config.interval);
+        loop {
+            interval.tick().await;
+            self.warm_all().await;
+        }
+    }
+
+    /// Tops up the pools of all providers once, concurrently.
+    ///
+    /// A provider's connections are split between the pools of its
+    /// dispatchers, so that a provider served by several dispatchers isn't
+    /// sent more than its pool size of connections.
+    pub async fn warm_all(&self) {
+        let config = self.app_state.config();
+        let timeout = config.dispatcher.connection_timeout;
+        let mut warmups = Vec::new();
+        for (provider, clients) in self.app_state.0.warm_clients.live().await {
+            let Some(provider_config) = config.providers.get(&provider) else {
+                continue;
+            };
+            // clients can be dropped after `live` checked them
+            if clients.is_empty() {
+                continue;
+            }
+            let size = self.pool_size(&provider);
+            trace!(provider = %provider, size, clients = clients.len(), "warming provider connections");
+            let share = size / clients.len();
+            let remainder = size % clients.len();
+            for (i, (client, warm_client)) in clients.into_iter().enumerate() {
+                let connections = share + usize::from(i < remainder);
+                if connections == 0
+                    || !warm_client
+                        .needs_warmup(connections, self.config.interval)
+                {
+                    continue;
+                }
+                warmups.push(top_up(
+                    client,
+                    warm_client,
+                    provider_config.base_url.clone(),
+                    timeout,
+                    connections,
+                ));
+            }
+        }
+        future::join_all(warmups).await;
+    }
+}
+
+/// Opens `connections` connections in the client's pool, or a single one if
+/// the provider multiplexes requests over HTTP/2.
+async fn top_up(
+    client: Arc<reqwest::Client>,
+    warm_client: Arc<WarmClient>,
+    base_url: Url,
+    timeout: Duration,
+    connections: usize,
+) {
+    let connections = if warm_client.is_multiplexed() {
+        1
+    } else {
+        connections
+    };
+    // the requests are sent concurrently so that each of them needs its
+    // own connection, leaving `connections` idle connections in the pool
+    let versions = future::join_all(
+        (0..connections)
+            .map(|_| warm(client.clone(), base_url.clone(), timeout)),
+    )
+    .await;
+    // failed warmups are retried on the next tick
+    if versions.iter().all(Option::is_none) {
+        return;
+    }
+    warm_client.warmed(Warmup {
+        at: Instant::now(),
+        connections,
+        multiplexed: versions
+            .iter()
+            .flatten()
+            .any(|version| *version >= http::Version::HTTP_2),
+    });
+}
+
+/// Requests the `provider` served within the health monitor window.
+fn recent_requests(
+    metrics: &EndpointMetricsRegistry,
+    provider: &InferenceProvider,
+) -> u32 {
+    provider
+        .endpoints()
+        .into_iter()
+        .filter_map(|endpoint| metrics.health_metrics(endpoint).ok())
+        .map(|metrics| metrics.request_count.total())
+        .sum()
+}
+
+/// Opens a connection with a cheap request, returning it to the client's
+/// pool once the response is dropped. Returns the HTTP version the provider
+/// answered with, or `None` if the request failed.
+async fn warm(
+    client: Arc<reqwest::Client>,
+    base_url: Url,
+    timeout: Duration,
+) -> Option<http::Version> {
+    match client.head(base_url.clone()).timeout(timeout).send().await {
+        Ok(response) => {
+            trace!(url = %base_url, status = %response.status(), version = ?response.version(), "warmed provider connection");
+            Some(response.version())
+        }
+        Err(e) => {
+            debug!(url = %base_url, error = %e, "failed to warm provider connection");
+            None
+        }
+    }
+}
+
+impl meltdown::Service for WarmPool {
+    type Future = BoxFuture<'static, Result<(), RuntimeError>>;
+
+    fn run(self, mut token: Token) -> Self::Future {
+        Box::pin(async move {
+            tokio::select! {
+                result = self.run_forever() => {
+                    if let Err(e) = result {
+                        error!(name = "provider-warm-pool-task", error = ?e, "Warm pool encountered error, shutting down");
+                    } else {
+                        debug!(name = "provider-warm-pool-task", "Warm pool shut down successfully");
+                    }
+                    token.trigger();
+                }
+                () = &mut token => {
+                    debug!(name = "provider-warm-pool-task", "task shut down successfully");
+                }
+            }
+            Ok(())
+        })
+    }
+}
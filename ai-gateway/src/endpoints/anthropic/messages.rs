@@ -31,6 +31,10 @@ impl AiRequest for CreateMessageParams {
             &self.model,
         )
     }
+
+    fn reasoning_requested(&self) -> bool {
+        self.thinking.is_some()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
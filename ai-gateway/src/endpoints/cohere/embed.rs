@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    endpoints::{AiRequest, Endpoint, cohere::PROVIDER_NAME},
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Embed;
+
+impl Endpoint for Embed {
+    const PATH: &'static str = "v1/embed";
+    type RequestBody = EmbedRequest;
+    type ResponseBody = EmbedResponse;
+    /// Embeddings are never streamed.
+    type StreamResponseBody = EmbedResponse;
+    type ErrorResponseBody = CohereApiError;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbedRequest {
+    pub model: String,
+    pub texts: Vec<String>,
+    pub input_type: InputType,
+}
+
+impl AiRequest for EmbedRequest {
+    fn is_stream(&self) -> bool {
+        false
+    }
+
+    fn model(&self) -> Result<ModelId, MapperError> {
+        ModelId::from_str_and_provider(
+            InferenceProvider::Named(PROVIDER_NAME.into()),
+            &self.model,
+        )
+    }
+}
+
+/// What the embeddings will be used for, required by Cohere's v3 and newer
+/// embedding models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputType {
+    SearchDocument,
+    SearchQuery,
+    Classification,
+    Clustering,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbedResponse {
+    pub id: String,
+    /// One embedding per text, in the order of the request's texts.
+    pub embeddings: Vec<Vec<f32>>,
+    #[serde(default)]
+    pub meta: Option<Meta>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Meta {
+    #[serde(default)]
+    pub billed_units: Option<BilledUnits>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BilledUnits {
+    #[serde(default)]
+    pub input_tokens: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CohereApiError {
+    pub message: String,
+}
@@ -0,0 +1,33 @@
+pub mod embed;
+
+use super::{Endpoint, EndpointType};
+pub use crate::endpoints::cohere::embed::Embed;
+
+/// The name of the `Cohere` provider in the providers config.
+pub(crate) const PROVIDER_NAME: &str = "cohere";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumIter)]
+pub enum Cohere {
+    Embed(Embed),
+}
+
+impl Cohere {
+    #[must_use]
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Embed(_) => Embed::PATH,
+        }
+    }
+
+    #[must_use]
+    pub fn embed() -> Self {
+        Self::Embed(Embed)
+    }
+
+    #[must_use]
+    pub fn endpoint_type(&self) -> EndpointType {
+        match self {
+            Self::Embed(_) => EndpointType::Embeddings,
+        }
+    }
+}
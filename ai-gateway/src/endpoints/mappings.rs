@@ -1,6 +1,10 @@
-use crate::endpoints::{
-    anthropic::Anthropic, bedrock::Bedrock, google::Google, ollama::Ollama,
-    openai::OpenAI,
+use crate::{
+    endpoints::{
+        anthropic::Anthropic, bedrock::Bedrock, cohere::Cohere, google::Google,
+        ollama::Ollama, openai::OpenAI, voyage::Voyage,
+    },
+    error::invalid_req::InvalidRequestError,
+    types::provider::InferenceProvider,
 };
 
 impl From<Anthropic> for OpenAI {
@@ -11,10 +15,17 @@ impl From<Anthropic> for OpenAI {
     }
 }
 
-impl From<OpenAI> for Anthropic {
-    fn from(value: OpenAI) -> Self {
+impl TryFrom<OpenAI> for Anthropic {
+    type Error = InvalidRequestError;
+
+    fn try_from(value: OpenAI) -> Result<Self, Self::Error> {
         match value {
-            OpenAI::ChatCompletions(_) => Self::messages(),
+            OpenAI::ChatCompletions(_) => Ok(Self::messages()),
+            OpenAI::Embeddings(_) => {
+                Err(InvalidRequestError::UnsupportedProvider(
+                    InferenceProvider::Anthropic,
+                ))
+            }
         }
     }
 }
@@ -27,18 +38,32 @@ impl From<Google> for OpenAI {
     }
 }
 
-impl From<OpenAI> for Google {
-    fn from(value: OpenAI) -> Self {
+impl TryFrom<OpenAI> for Google {
+    type Error = InvalidRequestError;
+
+    fn try_from(value: OpenAI) -> Result<Self, Self::Error> {
         match value {
-            OpenAI::ChatCompletions(_) => Self::generate_contents(),
+            OpenAI::ChatCompletions(_) => Ok(Self::generate_contents()),
+            OpenAI::Embeddings(_) => {
+                Err(InvalidRequestError::UnsupportedProvider(
+                    InferenceProvider::GoogleGemini,
+                ))
+            }
         }
     }
 }
 
-impl From<OpenAI> for Ollama {
-    fn from(value: OpenAI) -> Self {
+impl TryFrom<OpenAI> for Ollama {
+    type Error = InvalidRequestError;
+
+    fn try_from(value: OpenAI) -> Result<Self, Self::Error> {
         match value {
-            OpenAI::ChatCompletions(_) => Self::chat_completions(),
+            OpenAI::ChatCompletions(_) => Ok(Self::chat_completions()),
+            OpenAI::Embeddings(_) => {
+                Err(InvalidRequestError::UnsupportedProvider(
+                    InferenceProvider::Ollama,
+                ))
+            }
         }
     }
 }
@@ -50,10 +75,33 @@ impl From<Ollama> for OpenAI {
         }
     }
 }
-impl From<OpenAI> for Bedrock {
-    fn from(value: OpenAI) -> Self {
+impl TryFrom<OpenAI> for Bedrock {
+    type Error = InvalidRequestError;
+
+    fn try_from(value: OpenAI) -> Result<Self, Self::Error> {
+        match value {
+            OpenAI::ChatCompletions(_) => Ok(Self::converse()),
+            OpenAI::Embeddings(_) => {
+                Err(InvalidRequestError::UnsupportedProvider(
+                    InferenceProvider::Bedrock,
+                ))
+            }
+        }
+    }
+}
+
+impl From<Cohere> for OpenAI {
+    fn from(value: Cohere) -> Self {
+        match value {
+            Cohere::Embed(_) => Self::embeddings(),
+        }
+    }
+}
+
+impl From<Voyage> for OpenAI {
+    fn from(value: Voyage) -> Self {
         match value {
-            OpenAI::ChatCompletions(_) => Self::converse(),
+            Voyage::Embeddings(_) => Self::embeddings(),
         }
     }
 }
@@ -0,0 +1,318 @@
+use async_openai::types::{
+    ChatCompletionNamedToolChoice, ChatCompletionTool,
+    CreateChatCompletionRequest, CreateChatCompletionResponse,
+    CreateChatCompletionStreamResponse, ResponseFormat, Stop,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::{
+    endpoints::{AiRequest, Endpoint},
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+/// Mistral's native chat completions endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ChatCompletions;
+
+impl Endpoint for ChatCompletions {
+    // https://docs.mistral.ai/api/#tag/chat/operation/chat_completion_v1_chat_completions_post
+    const PATH: &'static str = "v1/chat/completions";
+    type RequestBody = ChatCompletionRequest;
+    type ResponseBody = ChatCompletionResponse;
+    type StreamResponseBody = ChatCompletionChunk;
+    type ErrorResponseBody = MistralApiError;
+}
+
+/// The `OpenAI` chat completions endpoint, for requests that are routed to
+/// Mistral.
+///
+/// Responses are the same as for
+/// [`crate::endpoints::openai::ChatCompletions`], but the request body keeps
+/// the Mistral specific fields a client may set, which would otherwise be
+/// dropped when deserializing an `OpenAI` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct OpenAIChatCompletions;
+
+impl Endpoint for OpenAIChatCompletions {
+    const PATH: &'static str = "v1/chat/completions";
+    type RequestBody = OpenAIChatCompletionRequest;
+    type ResponseBody = CreateChatCompletionResponse;
+    type StreamResponseBody = CreateChatCompletionStreamResponse;
+    type ErrorResponseBody = async_openai::error::WrappedError;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OpenAIChatCompletionRequest {
+    pub(crate) inner: CreateChatCompletionRequest,
+    /// Whether to have Mistral inject its safety prompt before the messages.
+    pub(crate) safe_prompt: Option<bool>,
+    /// The indices of the assistant messages marked with `prefix: true`,
+    /// whose content the response must start with.
+    pub(crate) prefix_messages: Vec<usize>,
+}
+
+impl<'de> Deserialize<'de> for OpenAIChatCompletionRequest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        let safe_prompt = value.get("safe_prompt").and_then(Value::as_bool);
+        let prefix_messages = value
+            .get("messages")
+            .and_then(Value::as_array)
+            .map(|messages| {
+                messages
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, message)| {
+                        message.get("prefix").and_then(Value::as_bool)
+                            == Some(true)
+                    })
+                    .map(|(index, _)| index)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let inner =
+            serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+        Ok(Self {
+            inner,
+            safe_prompt,
+            prefix_messages,
+        })
+    }
+}
+
+impl AiRequest for OpenAIChatCompletionRequest {
+    fn is_stream(&self) -> bool {
+        self.inner.is_stream()
+    }
+
+    fn model(&self) -> Result<ModelId, MapperError> {
+        self.inner.model()
+    }
+
+    fn reasoning_requested(&self) -> bool {
+        self.inner.reasoning_requested()
+    }
+
+    fn seed(&self) -> Option<i64> {
+        self.inner.seed()
+    }
+}
+
+/// Fields that have the same shape as in the `OpenAI` API reuse the
+/// `OpenAI` types.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Stop>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub random_seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<ChatCompletionTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safe_prompt: Option<bool>,
+}
+
+impl AiRequest for ChatCompletionRequest {
+    fn is_stream(&self) -> bool {
+        self.stream.unwrap_or(false)
+    }
+
+    fn model(&self) -> Result<ModelId, MapperError> {
+        ModelId::from_str_and_provider(
+            InferenceProvider::Named("mistral".into()),
+            &self.model,
+        )
+    }
+
+    fn seed(&self) -> Option<i64> {
+        self.random_seed
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "role", rename_all = "lowercase")]
+pub enum Message {
+    System {
+        content: Content,
+    },
+    User {
+        content: Content,
+    },
+    Assistant {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<Content>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tool_calls: Option<Vec<ToolCall>>,
+        /// The response continues this message rather than starting a new
+        /// one. Only allowed on the final message.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        prefix: bool,
+    },
+    Tool {
+        content: Content,
+        tool_call_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Content {
+    Text(String),
+    Chunks(Vec<ContentChunk>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentChunk {
+    Text {
+        text: String,
+    },
+    ImageUrl {
+        image_url: String,
+    },
+    /// Chunks without an `OpenAI` equivalent, e.g. references or thinking.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Not returned by every Mistral model.
+    #[serde(default)]
+    pub id: Option<String>,
+    pub function: FunctionCall,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    /// Either the JSON encoded arguments or the arguments object itself.
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ToolChoice {
+    Mode(ToolChoiceMode),
+    Named(ChatCompletionNamedToolChoice),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolChoiceMode {
+    Auto,
+    None,
+    /// Mistral's equivalent of `required`.
+    Any,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionResponse {
+    pub id: String,
+    pub created: u32,
+    pub model: String,
+    pub choices: Vec<Choice>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Choice {
+    pub index: u32,
+    pub message: ResponseMessage,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseMessage {
+    #[serde(default)]
+    pub content: Option<Content>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ToolCall>>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub created: u32,
+    pub model: String,
+    pub choices: Vec<ChunkChoice>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkChoice {
+    pub index: u32,
+    pub delta: Delta,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delta {
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub content: Option<Content>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ToolCall>>,
+}
+
+/// Mistral errors are either flat `OpenAI` style errors, or validation
+/// errors with a `detail` list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MistralApiError {
+    Error {
+        /// A string, or the validation details for some errors.
+        message: Value,
+        #[serde(rename = "type", default)]
+        kind: Option<String>,
+        #[serde(default)]
+        code: Option<Value>,
+    },
+    Validation {
+        detail: Value,
+    },
+    Other(Value),
+}
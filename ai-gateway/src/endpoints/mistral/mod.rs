@@ -0,0 +1,5 @@
+pub mod chat_completions;
+
+pub use crate::endpoints::mistral::chat_completions::{
+    ChatCompletions, OpenAIChatCompletions,
+};
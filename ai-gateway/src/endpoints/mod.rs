@@ -1,16 +1,19 @@
 pub mod anthropic;
 pub(crate) mod bedrock;
+pub mod cohere;
 pub mod google;
 pub mod mappings;
+pub mod mistral;
 pub mod ollama;
 pub mod openai;
+pub mod voyage;
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
     endpoints::{
-        anthropic::Anthropic, bedrock::Bedrock, google::Google, ollama::Ollama,
-        openai::OpenAI,
+        anthropic::Anthropic, bedrock::Bedrock, cohere::Cohere, google::Google,
+        ollama::Ollama, openai::OpenAI, voyage::Voyage,
     },
     error::{
         internal::InternalError, invalid_req::InvalidRequestError,
@@ -57,11 +60,30 @@ macro_rules! define_endpoints {
 
 define_endpoints! {
     (ChatCompletions, "chat/completions"),
+    (Embeddings, "embeddings"),
 }
 
 pub trait AiRequest {
     fn is_stream(&self) -> bool;
     fn model(&self) -> Result<ModelId, MapperError>;
+    /// Whether the client asked the model to reason before responding.
+    fn reasoning_requested(&self) -> bool {
+        false
+    }
+    /// Whether the client asked for base64 encoded embeddings.
+    fn base64_embeddings_requested(&self) -> bool {
+        false
+    }
+    /// The seed for deterministic sampling, if the request has one. Request
+    /// formats without an equivalent never have one.
+    fn seed(&self) -> Option<i64> {
+        None
+    }
+    /// Whether the client asked for the token usage of a streamed response,
+    /// which is sent in a final chunk without any choices.
+    fn usage_requested(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -71,6 +93,8 @@ pub enum ApiEndpoint {
     Google(Google),
     Ollama(Ollama),
     Bedrock(Bedrock),
+    Cohere(Cohere),
+    Voyage(Voyage),
     OpenAICompatible {
         provider: InferenceProvider,
         openai_endpoint: OpenAI,
@@ -90,19 +114,31 @@ impl ApiEndpoint {
     ) -> Result<Self, InvalidRequestError> {
         match (source_endpoint, target_provider) {
             (Self::OpenAI(source), InferenceProvider::Anthropic) => {
-                Ok(Self::Anthropic(Anthropic::from(source)))
+                Ok(Self::Anthropic(Anthropic::try_from(source)?))
             }
             (Self::OpenAI(source), InferenceProvider::OpenAI) => {
                 Ok(Self::OpenAI(source))
             }
             (Self::OpenAI(source), InferenceProvider::GoogleGemini) => {
-                Ok(Self::Google(Google::from(source)))
+                Ok(Self::Google(Google::try_from(source)?))
             }
             (Self::OpenAI(source), InferenceProvider::Ollama) => {
-                Ok(Self::Ollama(Ollama::from(source)))
+                Ok(Self::Ollama(Ollama::try_from(source)?))
             }
             (Self::OpenAI(source), InferenceProvider::Bedrock) => {
-                Ok(Self::Bedrock(Bedrock::from(source)))
+                Ok(Self::Bedrock(Bedrock::try_from(source)?))
+            }
+            (
+                Self::OpenAI(OpenAI::Embeddings(_)),
+                InferenceProvider::Named(name),
+            ) if name.as_str() == cohere::PROVIDER_NAME => {
+                Ok(Self::Cohere(Cohere::embed()))
+            }
+            (
+                Self::OpenAI(OpenAI::Embeddings(_)),
+                InferenceProvider::Named(name),
+            ) if name.as_str() == voyage::PROVIDER_NAME => {
+                Ok(Self::Voyage(Voyage::embeddings()))
             }
             (Self::OpenAI(source), InferenceProvider::Named(name)) => {
                 Ok(Self::OpenAICompatible {
@@ -124,6 +160,12 @@ impl ApiEndpoint {
             Self::Google(_) => InferenceProvider::GoogleGemini,
             Self::Ollama(_) => InferenceProvider::Ollama,
             Self::Bedrock(_) => InferenceProvider::Bedrock,
+            Self::Cohere(_) => {
+                InferenceProvider::Named(cohere::PROVIDER_NAME.into())
+            }
+            Self::Voyage(_) => {
+                InferenceProvider::Named(voyage::PROVIDER_NAME.into())
+            }
             Self::OpenAICompatible { provider, .. } => provider.clone(),
         }
     }
@@ -141,6 +183,8 @@ impl ApiEndpoint {
             Self::Anthropic(anthropic) => Ok(anthropic.path().to_string()),
             Self::Google(google) => Ok(google.path().to_string()),
             Self::Ollama(ollama) => Ok(ollama.path().to_string()),
+            Self::Cohere(cohere) => Ok(cohere.path().to_string()),
+            Self::Voyage(voyage) => Ok(voyage.path().to_string()),
             Self::Bedrock(bedrock) => {
                 if let Some(model_id) = model_id {
                     Ok(bedrock.path(model_id, is_stream))
@@ -163,10 +207,31 @@ impl ApiEndpoint {
             Self::Google(google) => google.endpoint_type(),
             Self::Ollama(ollama) => ollama.endpoint_type(),
             Self::Bedrock(bedrock) => bedrock.endpoint_type(),
+            Self::Cohere(cohere) => cohere.endpoint_type(),
+            Self::Voyage(voyage) => voyage.endpoint_type(),
         }
     }
 }
 
+impl std::fmt::Display for ApiEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = match self {
+            Self::OpenAI(openai) => openai.path(),
+            Self::OpenAICompatible {
+                openai_endpoint, ..
+            } => openai_endpoint.path(),
+            Self::Anthropic(anthropic) => anthropic.path(),
+            Self::Google(google) => google.path(),
+            Self::Ollama(ollama) => ollama.path(),
+            Self::Cohere(cohere) => cohere.path(),
+            Self::Voyage(voyage) => voyage.path(),
+            // the bedrock path depends on the model
+            Self::Bedrock(_) => "converse",
+        };
+        write!(f, "{} {path}", self.provider())
+    }
+}
+
 #[derive(
     Debug,
     Clone,
@@ -182,6 +247,7 @@ impl ApiEndpoint {
 #[strum(serialize_all = "kebab-case")]
 pub enum EndpointType {
     Chat,
+    Embeddings,
     Image,
     Audio,
 }
@@ -35,4 +35,8 @@ impl AiRequest for CreateChatCompletionRequestOllama {
     fn model(&self) -> Result<ModelId, MapperError> {
         ModelId::from_str_and_provider(InferenceProvider::Ollama, &self.0.model)
     }
+
+    fn seed(&self) -> Option<i64> {
+        self.0.seed
+    }
 }
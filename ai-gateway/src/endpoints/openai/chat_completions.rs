@@ -30,6 +30,20 @@ impl AiRequest for CreateChatCompletionRequest {
     fn model(&self) -> Result<ModelId, MapperError> {
         ModelId::from_str_and_provider(InferenceProvider::OpenAI, &self.model)
     }
+
+    fn reasoning_requested(&self) -> bool {
+        self.reasoning_effort.is_some()
+    }
+
+    fn seed(&self) -> Option<i64> {
+        self.seed
+    }
+
+    fn usage_requested(&self) -> bool {
+        self.stream_options
+            .as_ref()
+            .is_some_and(|options| options.include_usage)
+    }
 }
 
 pub(crate) fn system_prompt(
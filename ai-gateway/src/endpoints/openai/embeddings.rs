@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    endpoints::{AiRequest, Endpoint},
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Embeddings;
+
+impl Endpoint for Embeddings {
+    const PATH: &'static str = "v1/embeddings";
+    type RequestBody = CreateEmbeddingRequest;
+    type ResponseBody = CreateEmbeddingResponse;
+    /// Embeddings are never streamed.
+    type StreamResponseBody = CreateEmbeddingResponse;
+    type ErrorResponseBody = async_openai::error::WrappedError;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateEmbeddingRequest {
+    pub model: String,
+    pub input: EmbeddingInput,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding_format: Option<EncodingFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+impl AiRequest for CreateEmbeddingRequest {
+    fn is_stream(&self) -> bool {
+        false
+    }
+
+    fn model(&self) -> Result<ModelId, MapperError> {
+        ModelId::from_str_and_provider(InferenceProvider::OpenAI, &self.model)
+    }
+
+    fn base64_embeddings_requested(&self) -> bool {
+        self.encoding_format == Some(EncodingFormat::Base64)
+    }
+}
+
+/// The text, or token ids, to embed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    String(String),
+    StringArray(Vec<String>),
+    Tokens(Vec<u32>),
+    TokensArray(Vec<Vec<u32>>),
+}
+
+impl EmbeddingInput {
+    /// The texts to embed, one per embedding.
+    ///
+    /// Returns `None` for token id inputs, which are specific to `OpenAI`
+    /// tokenizers.
+    #[must_use]
+    pub fn into_texts(self) -> Option<Vec<String>> {
+        match self {
+            Self::String(text) => Some(vec![text]),
+            Self::StringArray(texts) => Some(texts),
+            Self::Tokens(_) | Self::TokensArray(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodingFormat {
+    Float,
+    Base64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateEmbeddingResponse {
+    pub object: String,
+    pub data: Vec<Embedding>,
+    pub model: String,
+    pub usage: EmbeddingUsage,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Embedding {
+    pub object: String,
+    pub embedding: EmbeddingVector,
+    pub index: u32,
+}
+
+/// An embedding, as an array of floats or, if the client asked for
+/// `encoding_format: base64`, as the base64 encoded little endian bytes of
+/// those floats.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingVector {
+    Float(Vec<f32>),
+    Base64(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingUsage {
+    pub prompt_tokens: u32,
+    pub total_tokens: u32,
+}
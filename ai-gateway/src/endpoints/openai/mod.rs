@@ -1,7 +1,10 @@
 pub mod chat_completions;
+pub mod embeddings;
 
 use super::EndpointType;
-pub use crate::endpoints::openai::chat_completions::ChatCompletions;
+pub use crate::endpoints::openai::{
+    chat_completions::ChatCompletions, embeddings::Embeddings,
+};
 use crate::{
     endpoints::{Endpoint, EndpointRoute},
     error::invalid_req::InvalidRequestError,
@@ -10,6 +13,7 @@ use crate::{
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumIter)]
 pub enum OpenAI {
     ChatCompletions(ChatCompletions),
+    Embeddings(Embeddings),
 }
 
 impl OpenAI {
@@ -17,6 +21,7 @@ impl OpenAI {
     pub fn path(&self) -> &str {
         match self {
             Self::ChatCompletions(_) => ChatCompletions::PATH,
+            Self::Embeddings(_) => Embeddings::PATH,
         }
     }
 
@@ -25,10 +30,16 @@ impl OpenAI {
         Self::ChatCompletions(ChatCompletions)
     }
 
+    #[must_use]
+    pub fn embeddings() -> Self {
+        Self::Embeddings(Embeddings)
+    }
+
     #[must_use]
     pub fn endpoint_type(&self) -> EndpointType {
         match self {
             Self::ChatCompletions(_) => EndpointType::Chat,
+            Self::Embeddings(_) => EndpointType::Embeddings,
         }
     }
 }
@@ -41,6 +52,7 @@ impl TryFrom<&EndpointRoute> for OpenAI {
             EndpointRoute::ChatCompletions => {
                 Ok(Self::ChatCompletions(ChatCompletions))
             }
+            EndpointRoute::Embeddings => Ok(Self::Embeddings(Embeddings)),
         }
     }
 }
@@ -83,4 +95,8 @@ impl super::AiRequest for OpenAICompatibleChatCompletionRequest {
             &self.inner.model,
         )
     }
+
+    fn seed(&self) -> Option<i64> {
+        self.inner.seed
+    }
 }
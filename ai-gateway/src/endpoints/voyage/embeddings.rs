@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    endpoints::{
+        AiRequest, Endpoint, openai::embeddings::EmbeddingVector,
+        voyage::PROVIDER_NAME,
+    },
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Embeddings;
+
+impl Endpoint for Embeddings {
+    const PATH: &'static str = "v1/embeddings";
+    type RequestBody = EmbeddingsRequest;
+    type ResponseBody = EmbeddingsResponse;
+    /// Embeddings are never streamed.
+    type StreamResponseBody = EmbeddingsResponse;
+    type ErrorResponseBody = VoyageApiError;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingsRequest {
+    pub model: String,
+    pub input: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dimension: Option<u32>,
+}
+
+impl AiRequest for EmbeddingsRequest {
+    fn is_stream(&self) -> bool {
+        false
+    }
+
+    fn model(&self) -> Result<ModelId, MapperError> {
+        ModelId::from_str_and_provider(
+            InferenceProvider::Named(PROVIDER_NAME.into()),
+            &self.model,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingsResponse {
+    pub data: Vec<Embedding>,
+    pub model: String,
+    pub usage: Usage,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Embedding {
+    /// Floats, unless the request set `encoding_format: base64`.
+    pub embedding: EmbeddingVector,
+    pub index: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub total_tokens: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VoyageApiError {
+    pub detail: String,
+}
@@ -0,0 +1,33 @@
+pub mod embeddings;
+
+use super::{Endpoint, EndpointType};
+pub use crate::endpoints::voyage::embeddings::Embeddings;
+
+/// The name of the `Voyage` provider in the providers config.
+pub(crate) const PROVIDER_NAME: &str = "voyage";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumIter)]
+pub enum Voyage {
+    Embeddings(Embeddings),
+}
+
+impl Voyage {
+    #[must_use]
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Embeddings(_) => Embeddings::PATH,
+        }
+    }
+
+    #[must_use]
+    pub fn embeddings() -> Self {
+        Self::Embeddings(Embeddings)
+    }
+
+    #[must_use]
+    pub fn endpoint_type(&self) -> EndpointType {
+        match self {
+            Self::Embeddings(_) => EndpointType::Embeddings,
+        }
+    }
+}
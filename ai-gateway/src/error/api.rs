@@ -44,6 +44,12 @@ impl From<dynamic_router::router::Error> for ApiError {
             dynamic_router::router::Error::RouterNotFound(key) => {
                 Self::InvalidRequest(InvalidRequestError::RouterIdNotFound(key))
             }
+            dynamic_router::router::Error::NoReadyService => {
+                Self::Internal(InternalError::ProviderNotFound)
+            }
+            dynamic_router::router::Error::SampleFailed(error) => {
+                Self::Internal(InternalError::LoadBalancerError(error.into()))
+            }
         }
     }
 }
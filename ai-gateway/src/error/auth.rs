@@ -1,12 +1,15 @@
 use axum_core::response::{IntoResponse, Response};
 use displaydoc::Display;
-use http::StatusCode;
+use http::{HeaderMap, StatusCode};
 use thiserror::Error;
 
 use super::api::ErrorResponse;
 use crate::{
     error::api::ErrorDetails,
-    middleware::mapper::openai::INVALID_REQUEST_ERROR_TYPE, types::json::Json,
+    middleware::mapper::openai::{
+        INVALID_REQUEST_ERROR_TYPE, SERVER_ERROR_TYPE,
+    },
+    types::{json::Json, provider::InferenceProvider},
 };
 
 #[derive(Debug, strum::AsRefStr, Error, Display)]
@@ -17,6 +20,11 @@ pub enum AuthError {
     InvalidCredentials,
     /// Provider key not found
     ProviderKeyNotFound,
+    /// No API key is configured for provider: {0}
+    ProviderKeyNotConfigured(InferenceProvider),
+    /// The gateway is still syncing with the control plane, retry after {0}
+    /// seconds
+    AuthDataNotReady(u64),
 }
 
 impl IntoResponse for AuthError {
@@ -58,6 +66,40 @@ impl IntoResponse for AuthError {
                 }),
             )
                 .into_response(),
+            Self::ProviderKeyNotConfigured(provider) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse {
+                    error: ErrorDetails {
+                        message: Self::ProviderKeyNotConfigured(provider)
+                            .to_string(),
+                        r#type: Some(SERVER_ERROR_TYPE.to_string()),
+                        param: None,
+                        code: Some("provider_key_not_configured".to_string()),
+                    },
+                }),
+            )
+                .into_response(),
+            Self::AuthDataNotReady(retry_after) => {
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    "retry-after",
+                    retry_after.to_string().parse().unwrap(),
+                );
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    headers,
+                    Json(ErrorResponse {
+                        error: ErrorDetails {
+                            message: Self::AuthDataNotReady(retry_after)
+                                .to_string(),
+                            r#type: Some(SERVER_ERROR_TYPE.to_string()),
+                            param: None,
+                            code: Some("auth_data_not_ready".to_string()),
+                        },
+                    }),
+                )
+                    .into_response()
+            }
         }
     }
 }
@@ -73,6 +115,10 @@ pub enum AuthErrorMetric {
     InvalidCredentials,
     /// Provider key not found
     ProviderKeyNotFound,
+    /// Provider key not configured
+    ProviderKeyNotConfigured,
+    /// Auth data not ready
+    AuthDataNotReady,
 }
 
 impl From<&AuthError> for AuthErrorMetric {
@@ -83,6 +129,10 @@ impl From<&AuthError> for AuthErrorMetric {
             }
             AuthError::InvalidCredentials => Self::InvalidCredentials,
             AuthError::ProviderKeyNotFound => Self::ProviderKeyNotFound,
+            AuthError::ProviderKeyNotConfigured(_) => {
+                Self::ProviderKeyNotConfigured
+            }
+            AuthError::AuthDataNotReady(_) => Self::AuthDataNotReady,
         }
     }
 }
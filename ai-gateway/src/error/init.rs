@@ -56,6 +56,26 @@ pub enum InitError {
     WebsocketRequestBuild(#[from] http::Error),
     /// Invalid router id: {0}
     InvalidRouterId(String),
+    /// Invalid required header name: {0}
+    InvalidRequiredHeader(String),
+    /// Invalid header configured for provider {0}: {1}
+    InvalidProviderHeader(InferenceProvider, String),
+    /// Environment variable {0} referenced by a provider header is not set
+    MissingHeaderEnvVar(String),
+    /// Retry status code {0} is not an error status
+    InvalidRetryStatusCode(u16),
+    /// Invalid stream moderation config: {0}
+    InvalidStreamModeration(String),
+    /// Invalid moderation config: {0}
+    InvalidModeration(String),
+    /// Invalid sampling bounds: {0}
+    InvalidSamplingBounds(String),
+    /// Invalid stickiness config: {0}
+    InvalidStickiness(String),
+    /// Status code {0} is not a cacheable client error
+    InvalidCacheableStatusCode(u16),
+    /// Semantic cache threshold must be in (0, 1]: {0}
+    InvalidSemanticCacheThreshold(rust_decimal::Decimal),
     /// Cache not configured
     CacheNotConfigured,
     /// Minio not configured
@@ -82,4 +102,9 @@ pub enum InitError {
     InitHeliconeKeys(String),
     /// Failed to load initial routers from db: {0}
     InitRouters(String),
+    /// Failed to load WASM plugin: {0}
+    #[cfg(feature = "wasm-plugins")]
+    WasmPlugin(wasmtime::Error),
+    /// Invalid redaction pattern {0}: {1}
+    InvalidRedactionPattern(String, regex::Error),
 }
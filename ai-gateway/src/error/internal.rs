@@ -10,7 +10,7 @@ use crate::{
     endpoints::ApiEndpoint,
     error::{
         api::{ErrorDetails, ErrorResponse},
-        mapper::MapperErrorMetric,
+        mapper::{MapperError, MapperErrorMetric},
     },
     middleware::mapper::openai::SERVER_ERROR_TYPE,
     types::{json::Json, provider::InferenceProvider},
@@ -63,10 +63,18 @@ pub enum InternalError {
     InvalidConverter(ApiEndpoint, ApiEndpoint),
     /// Upstream 5xx error: {0}
     Provider5xxError(StatusCode),
+    /// Provider returned a non-JSON response with content type: {0}
+    NonJsonProviderResponse(String),
+    /// Provider did not respond within {0:?}
+    ProviderTimeout(std::time::Duration),
     /// Metrics not configured for: {0:?}
     MetricsNotConfigured(ApiEndpoint),
     /// Failed to sign AWS request: {0}
     AwsRequestSigningError(String),
+    /// Failed to assume AWS role: {0}
+    AwsAssumeRoleError(String),
+    /// Failed to authenticate with Google Cloud: {0}
+    GcpAuthError(String),
     /// Dynamic router discovery error: {0}
     DynamicRouterDiscoveryError(BoxError),
     /// Cache error: {0}
@@ -79,17 +87,31 @@ pub enum InternalError {
     PromptError(#[from] crate::error::prompts::PromptError),
     /// Failed to complete prompt task: {0}
     PromptTaskError(tokio::task::JoinError),
-    /// Auth data not ready
-    AuthDataNotReady,
     /// Database error: {0}
     DatabaseError(#[from] sqlx::Error),
+    /// WASM plugin error: {0}
+    #[cfg(feature = "wasm-plugins")]
+    WasmPluginError(wasmtime::Error),
+    /// Failed to complete WASM plugin task: {0}
+    #[cfg(feature = "wasm-plugins")]
+    WasmPluginTaskError(tokio::task::JoinError),
 }
 
 impl IntoResponse for InternalError {
     fn into_response(self) -> Response {
         error!(error = %self, "internal error");
+        let status = match self {
+            Self::NonJsonProviderResponse(_)
+            | Self::MapperError(
+                MapperError::RequiredToolCallMissing(_)
+                | MapperError::ResponseSchemaViolation(..)
+                | MapperError::MappedResponseTooLarge(_),
+            ) => StatusCode::BAD_GATEWAY,
+            Self::ProviderTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            status,
             Json(ErrorResponse {
                 error: ErrorDetails {
                     message: self.to_string(),
@@ -148,10 +170,18 @@ pub enum InternalErrorMetric {
     StreamError,
     /// Upstream 5xx error
     Provider5xxError,
+    /// Provider returned a non-JSON response
+    NonJsonProviderResponse,
+    /// Provider timed out
+    ProviderTimeout,
     /// Metrics not configured
     MetricsNotConfigured,
     /// Failed to sign AWS request
     AwsRequestSigningError,
+    /// Failed to assume AWS role
+    AwsAssumeRoleError,
+    /// Failed to authenticate with Google Cloud
+    GcpAuthError,
     /// Cache error
     CacheError,
     /// Dynamic router discovery error
@@ -162,10 +192,11 @@ pub enum InternalErrorMetric {
     PoolError,
     /// Prompt error
     PromptError,
-    /// Auth data not ready
-    AuthDataNotReady,
     /// Database error
     DatabaseError,
+    /// WASM plugin error
+    #[cfg(feature = "wasm-plugins")]
+    WasmPluginError,
 }
 
 impl From<&InternalError> for InternalErrorMetric {
@@ -193,14 +224,22 @@ impl From<&InternalError> for InternalErrorMetric {
             InternalError::InvalidHeader(_) => Self::InvalidHeader,
             InternalError::MappingTaskError(_)
             | InternalError::PromptTaskError(_) => Self::TokioTaskError,
+            #[cfg(feature = "wasm-plugins")]
+            InternalError::WasmPluginTaskError(_) => Self::TokioTaskError,
             InternalError::InvalidConverter(_, _) => Self::InvalidConverter,
             InternalError::Provider5xxError(_) => Self::Provider5xxError,
+            InternalError::NonJsonProviderResponse(_) => {
+                Self::NonJsonProviderResponse
+            }
+            InternalError::ProviderTimeout(_) => Self::ProviderTimeout,
             InternalError::MetricsNotConfigured(_) => {
                 Self::MetricsNotConfigured
             }
             InternalError::AwsRequestSigningError(_) => {
                 Self::AwsRequestSigningError
             }
+            InternalError::AwsAssumeRoleError(_) => Self::AwsAssumeRoleError,
+            InternalError::GcpAuthError(_) => Self::GcpAuthError,
             InternalError::CacheError(_) => Self::CacheError,
             InternalError::RedisError(_) => Self::RedisError,
             InternalError::PoolError(_) => Self::PoolError,
@@ -208,8 +247,9 @@ impl From<&InternalError> for InternalErrorMetric {
             InternalError::DynamicRouterDiscoveryError(_) => {
                 Self::DynamicRouterDiscoveryError
             }
-            InternalError::AuthDataNotReady => Self::AuthDataNotReady,
             InternalError::DatabaseError(_) => Self::DatabaseError,
+            #[cfg(feature = "wasm-plugins")]
+            InternalError::WasmPluginError(_) => Self::WasmPluginError,
         }
     }
 }
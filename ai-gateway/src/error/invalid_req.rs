@@ -5,6 +5,8 @@ use thiserror::Error;
 use tracing::debug;
 
 use crate::{
+    config::spend_limit::SpendWindow,
+    endpoints::ApiEndpoint,
     error::api::{ErrorDetails, ErrorResponse},
     middleware::mapper::openai::INVALID_REQUEST_ERROR_TYPE,
     types::{json::Json, provider::InferenceProvider},
@@ -22,6 +24,17 @@ pub struct TooManyRequestsError {
     pub retry_after: u64,
 }
 
+#[derive(Debug, Display)]
+#[displaydoc("{window} limit of ${limit} reached. Retry after {retry_after}s.")]
+pub struct SpendLimitExceededError {
+    /// The window whose spend limit was reached
+    pub window: SpendWindow,
+    /// Spend limit in USD
+    pub limit: rust_decimal::Decimal,
+    /// Number of seconds until the window resets
+    pub retry_after: u64,
+}
+
 /// User errors
 #[derive(Debug, Error, Display, strum::AsRefStr)]
 pub enum InvalidRequestError {
@@ -49,12 +62,52 @@ pub enum InvalidRequestError {
     Provider4xxError(StatusCode),
     /// Invalid cache config
     InvalidCacheConfig,
+    /// Invalid cache ttl, expected a positive number of seconds: {0}
+    InvalidCacheTtl(String),
     /// Too many requests: {0}
     TooManyRequests(TooManyRequestsError),
+    /// Request is estimated at {0} tokens, over the token limit capacity of {1}
+    TokenEstimateOverCapacity(u64, u64),
+    /// Spend limit exceeded: {0}
+    SpendLimitExceeded(SpendLimitExceededError),
+    /// Too many concurrent streams, at most {0} are allowed
+    ConcurrentStreamLimitExceeded(std::num::NonZeroU32),
     /// Invalid request header: {0}
     InvalidRequestHeader(http::header::ToStrError),
+    /// Missing required header: {0}
+    MissingRequiredHeader(String),
     /// Invalid prompt inputs: {0}
     InvalidPromptInputs(String),
+    /// Malformed prompt parameters: {0}
+    MalformedPromptParams(serde_json::Error),
+    /// Prompt schema exceeds the maximum nesting depth of {0}
+    PromptSchemaTooDeep(usize),
+    /// Request must contain at least one message
+    EmptyMessages,
+    /// Request body contains unknown fields: {0}
+    UnknownFields(String),
+    /// Request must contain at least one non-system message for provider: {0}
+    SystemOnlyMessages(InferenceProvider),
+    /// Messages contain {0} tool call turns, at most {1} are allowed
+    TooManyToolCallTurns(u32, std::num::NonZeroU32),
+    /// Streaming is not supported when returning all fan out responses
+    StreamingFanOut,
+    /// Mapping {0} requests to {1} is not supported
+    UnsupportedMapping(ApiEndpoint, ApiEndpoint),
+    /// Request body exceeds the {0} byte limit for mapping
+    MappedRequestTooLarge(usize),
+    /// Request body exceeds the {0} byte limit
+    RequestTooLarge(usize),
+    /// Request contains more than the {0} images allowed by provider: {1}
+    TooManyImages(usize, InferenceProvider),
+    /// Image exceeds the {0} byte limit of provider: {1}
+    ImageTooLarge(usize, InferenceProvider),
+    /// Response was blocked by content moderation
+    BlockedByModeration,
+    /// Request was flagged by content moderation for: {0}
+    FlaggedByModeration(String),
+    /// Provider does not support deterministic sampling with `seed`: {0}
+    UnsupportedSeed(InferenceProvider),
 }
 
 impl IntoResponse for InvalidRequestError {
@@ -120,6 +173,64 @@ impl IntoResponse for InvalidRequestError {
                 )
                     .into_response()
             }
+            Self::SpendLimitExceeded(error) => {
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    "retry-after",
+                    error.retry_after.to_string().parse().unwrap(),
+                );
+                (
+                    StatusCode::PAYMENT_REQUIRED,
+                    headers,
+                    Json(ErrorResponse {
+                        error: ErrorDetails {
+                            message,
+                            r#type: Some(
+                                INVALID_REQUEST_ERROR_TYPE.to_string(),
+                            ),
+                            param: None,
+                            code: None,
+                        },
+                    }),
+                )
+                    .into_response()
+            }
+            Self::ConcurrentStreamLimitExceeded(_) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(ErrorResponse {
+                    error: ErrorDetails {
+                        message,
+                        r#type: Some(INVALID_REQUEST_ERROR_TYPE.to_string()),
+                        param: None,
+                        code: None,
+                    },
+                }),
+            )
+                .into_response(),
+            Self::MappedRequestTooLarge(_) | Self::RequestTooLarge(_) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(ErrorResponse {
+                    error: ErrorDetails {
+                        message,
+                        r#type: Some(INVALID_REQUEST_ERROR_TYPE.to_string()),
+                        param: None,
+                        code: None,
+                    },
+                }),
+            )
+                .into_response(),
+            Self::UnsupportedMapping(..) => (
+                StatusCode::NOT_IMPLEMENTED,
+                Json(ErrorResponse {
+                    error: ErrorDetails {
+                        message,
+                        r#type: Some(INVALID_REQUEST_ERROR_TYPE.to_string()),
+                        param: None,
+                        code: None,
+                    },
+                }),
+            )
+                .into_response(),
             _ => (
                 StatusCode::BAD_REQUEST,
                 Json(ErrorResponse {
@@ -155,6 +266,16 @@ pub enum InvalidRequestErrorMetric {
     Provider4xxError,
     /// Too many requests
     TooManyRequests,
+    /// Spend limit exceeded
+    SpendLimitExceeded,
+    /// Concurrent stream limit exceeded
+    ConcurrentStreamLimitExceeded,
+    /// Unsupported mapping
+    UnsupportedMapping,
+    /// Mapped request body too large
+    MappedBodyTooLarge,
+    /// Request body too large
+    RequestBodyTooLarge,
 }
 
 impl From<&InvalidRequestError> for InvalidRequestErrorMetric {
@@ -170,7 +291,22 @@ impl From<&InvalidRequestError> for InvalidRequestErrorMetric {
             InvalidRequestError::InvalidRequest(_)
             | InvalidRequestError::UnsupportedEndpoint(_)
             | InvalidRequestError::InvalidCacheConfig
+            | InvalidRequestError::InvalidCacheTtl(_)
             | InvalidRequestError::InvalidPromptInputs(_)
+            | InvalidRequestError::MalformedPromptParams(_)
+            | InvalidRequestError::PromptSchemaTooDeep(_)
+            | InvalidRequestError::EmptyMessages
+            | InvalidRequestError::UnknownFields(_)
+            | InvalidRequestError::SystemOnlyMessages(_)
+            | InvalidRequestError::TooManyToolCallTurns(..)
+            | InvalidRequestError::TooManyImages(..)
+            | InvalidRequestError::ImageTooLarge(..)
+            | InvalidRequestError::StreamingFanOut
+            | InvalidRequestError::BlockedByModeration
+            | InvalidRequestError::FlaggedByModeration(_)
+            | InvalidRequestError::UnsupportedSeed(_)
+            | InvalidRequestError::TokenEstimateOverCapacity(..)
+            | InvalidRequestError::MissingRequiredHeader(_)
             | InvalidRequestError::MissingModelId
             | InvalidRequestError::InvalidModelId => Self::InvalidRequest,
             InvalidRequestError::InvalidUrl(_) => Self::InvalidUrl,
@@ -179,6 +315,21 @@ impl From<&InvalidRequestError> for InvalidRequestErrorMetric {
             }
             InvalidRequestError::Provider4xxError(_) => Self::Provider4xxError,
             InvalidRequestError::TooManyRequests(_) => Self::TooManyRequests,
+            InvalidRequestError::SpendLimitExceeded(_) => {
+                Self::SpendLimitExceeded
+            }
+            InvalidRequestError::ConcurrentStreamLimitExceeded(_) => {
+                Self::ConcurrentStreamLimitExceeded
+            }
+            InvalidRequestError::UnsupportedMapping(..) => {
+                Self::UnsupportedMapping
+            }
+            InvalidRequestError::MappedRequestTooLarge(_) => {
+                Self::MappedBodyTooLarge
+            }
+            InvalidRequestError::RequestTooLarge(_) => {
+                Self::RequestBodyTooLarge
+            }
         }
     }
 }
@@ -21,3 +21,17 @@ pub enum LoggerError {
     /// Unexpected response: {0}
     UnexpectedResponse(String),
 }
+
+impl LoggerError {
+    /// Whether the log failed because Helicone couldn't be reached or
+    /// rejected it, rather than because the log itself was invalid.
+    #[must_use]
+    pub fn is_backend_unavailable(&self) -> bool {
+        matches!(
+            self,
+            Self::FailedToSendRequest(_)
+                | Self::ResponseError(_)
+                | Self::UnexpectedResponse(_)
+        )
+    }
+}
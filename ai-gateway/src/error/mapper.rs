@@ -30,8 +30,16 @@ pub enum MapperError {
     ToolMappingInvalid(String),
     /// Image mapping invalid: {0}
     ImageMappingInvalid(String),
+    /// Embeddings mapping invalid: {0}
+    EmbeddingsMappingInvalid(String),
     /// Failed to map Bedrock message: {0}
     FailedToMapBedrockMessage(BoxError),
+    /// Provider {0} did not call a tool despite `tool_choice: required`
+    RequiredToolCallMissing(InferenceProvider),
+    /// Provider {0} response does not match the requested JSON schema: {1}
+    ResponseSchemaViolation(InferenceProvider, String),
+    /// Response body exceeds the {0} byte limit for mapping
+    MappedResponseTooLarge(usize),
 }
 
 /// Error types that can occur when mapping requests between providers.
@@ -59,8 +67,16 @@ pub enum MapperErrorMetric {
     ToolMappingInvalid,
     /// Image mapping invalid
     ImageMappingInvalid,
+    /// Embeddings mapping invalid
+    EmbeddingsMappingInvalid,
     /// Failed to map Bedrock message
     FailedToMapBedrockMessage,
+    /// Required tool call missing
+    RequiredToolCallMissing,
+    /// Response schema violation
+    ResponseSchemaViolation,
+    /// Mapped response body too large
+    MappedResponseTooLarge,
 }
 
 impl From<&MapperError> for MapperErrorMetric {
@@ -77,9 +93,21 @@ impl From<&MapperError> for MapperErrorMetric {
             MapperError::ProviderNotSupported(_) => Self::ProviderNotSupported,
             MapperError::ToolMappingInvalid(_) => Self::ToolMappingInvalid,
             MapperError::ImageMappingInvalid(_) => Self::ImageMappingInvalid,
+            MapperError::EmbeddingsMappingInvalid(_) => {
+                Self::EmbeddingsMappingInvalid
+            }
             MapperError::FailedToMapBedrockMessage(_) => {
                 Self::FailedToMapBedrockMessage
             }
+            MapperError::RequiredToolCallMissing(_) => {
+                Self::RequiredToolCallMissing
+            }
+            MapperError::ResponseSchemaViolation(..) => {
+                Self::ResponseSchemaViolation
+            }
+            MapperError::MappedResponseTooLarge(_) => {
+                Self::MappedResponseTooLarge
+            }
         }
     }
 }
@@ -0,0 +1,163 @@
+use std::{
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    },
+    time::Instant,
+};
+
+use crate::{
+    config::helicone::LoggingDegradationConfig, error::logger::LoggerError,
+    metrics::Metrics,
+};
+
+/// Health of the logging pipeline, i.e. whether request logs are reaching
+/// Helicone.
+///
+/// Logging is best effort, so requests are served regardless, but once
+/// enough logs in a row have failed because the backend is unavailable the
+/// pipeline is considered degraded until a log succeeds again.
+#[derive(Debug)]
+pub struct LoggingHealth {
+    config: LoggingDegradationConfig,
+    consecutive_failures: AtomicU32,
+    /// Logs dropped since the last warning was logged.
+    dropped_since_warning: AtomicU64,
+    degraded: AtomicBool,
+    last_warning: Mutex<Option<Instant>>,
+}
+
+impl LoggingHealth {
+    #[must_use]
+    pub fn new(config: LoggingDegradationConfig) -> Self {
+        Self {
+            config,
+            consecutive_failures: AtomicU32::new(0),
+            dropped_since_warning: AtomicU64::new(0),
+            degraded: AtomicBool::new(false),
+            last_warning: Mutex::new(None),
+        }
+    }
+
+    /// Whether logs are currently failing to reach Helicone.
+    #[must_use]
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    /// Records the outcome of sending a request log.
+    ///
+    /// Only failures of the backend count towards the degraded mode, not
+    /// logs that were invalid to begin with.
+    pub fn record(&self, metrics: &Metrics, result: &Result<(), LoggerError>) {
+        match result {
+            Ok(()) => self.record_success(metrics),
+            Err(e) if e.is_backend_unavailable() => {
+                self.record_failure(metrics, e);
+            }
+            Err(_) => {}
+        }
+    }
+
+    fn record_success(&self, metrics: &Metrics) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        if self.degraded.swap(false, Ordering::Relaxed) {
+            metrics.logging_degraded.record(0, &[]);
+            let dropped = self.dropped_since_warning.swap(0, Ordering::Relaxed);
+            tracing::info!(dropped, "logging backend recovered");
+        }
+    }
+
+    fn record_failure(&self, metrics: &Metrics, error: &LoggerError) {
+        metrics.dropped_logs.add(1, &[]);
+        self.dropped_since_warning.fetch_add(1, Ordering::Relaxed);
+        let failures =
+            self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures < self.config.failure_threshold.get() {
+            return;
+        }
+        let now = Instant::now();
+        let mut last_warning = self
+            .last_warning
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if !self.degraded.swap(true, Ordering::Relaxed) {
+            metrics.logging_degraded.record(1, &[]);
+            *last_warning = Some(now);
+            tracing::warn!(
+                error = %error,
+                failures,
+                "logging backend unavailable, entering degraded mode"
+            );
+        } else if last_warning.is_none_or(|last| {
+            now.duration_since(last) >= self.config.warning_interval
+        }) {
+            *last_warning = Some(now);
+            let dropped = self.dropped_since_warning.swap(0, Ordering::Relaxed);
+            tracing::warn!(
+                error = %error,
+                dropped,
+                "logging backend still unavailable, logs are being dropped"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroU32, time::Duration};
+
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+
+    use super::*;
+
+    fn health(failure_threshold: u32) -> LoggingHealth {
+        LoggingHealth::new(LoggingDegradationConfig {
+            failure_threshold: NonZeroU32::new(failure_threshold).unwrap(),
+            warning_interval: Duration::from_secs(60),
+        })
+    }
+
+    fn unavailable() -> Result<(), LoggerError> {
+        Err(LoggerError::UnexpectedResponse("503".to_string()))
+    }
+
+    #[test]
+    fn degraded_after_consecutive_failures() {
+        let metrics = Metrics::new(&SdkMeterProvider::default().meter("test"));
+        let health = health(2);
+        health.record(&metrics, &unavailable());
+        assert!(!health.is_degraded());
+        health.record(&metrics, &unavailable());
+        assert!(health.is_degraded());
+    }
+
+    #[test]
+    fn success_recovers_from_degraded_mode() {
+        let metrics = Metrics::new(&SdkMeterProvider::default().meter("test"));
+        let health = health(1);
+        health.record(&metrics, &unavailable());
+        assert!(health.is_degraded());
+        health.record(&metrics, &Ok(()));
+        assert!(!health.is_degraded());
+    }
+
+    #[test]
+    fn success_resets_consecutive_failures() {
+        let metrics = Metrics::new(&SdkMeterProvider::default().meter("test"));
+        let health = health(2);
+        health.record(&metrics, &unavailable());
+        health.record(&metrics, &Ok(()));
+        health.record(&metrics, &unavailable());
+        assert!(!health.is_degraded());
+    }
+
+    #[test]
+    fn invalid_logs_do_not_count_as_failures() {
+        let metrics = Metrics::new(&SdkMeterProvider::default().meter("test"));
+        let health = health(1);
+        health.record(&metrics, &Err(LoggerError::InvalidLogMessage));
+        assert!(!health.is_degraded());
+    }
+}
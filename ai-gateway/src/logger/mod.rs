@@ -1 +1,4 @@
+pub mod health;
+pub mod redaction;
 pub mod service;
+pub mod usage;
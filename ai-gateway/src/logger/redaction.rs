@@ -0,0 +1,250 @@
+use std::borrow::Cow;
+
+use bytes::Bytes;
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use crate::{
+    config::redaction::{RedactionConfig, RedactionPattern},
+    error::init::InitError,
+};
+
+const EMAIL: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}";
+const PHONE: &str = r"\+?\d[\d.-]{6,}\d";
+const CREDIT_CARD: &str = r"\b\d(?:[ -]?\d){12,18}\b";
+
+/// Redacts personal data from the bodies logged to Helicone.
+#[derive(Debug, Clone)]
+pub struct BodyRedactor {
+    /// Patterns in the order they are applied. Credit card numbers come
+    /// first, so that they aren't partially redacted as phone numbers.
+    patterns: Vec<(Regex, bool)>,
+    placeholder: String,
+}
+
+impl BodyRedactor {
+    pub fn new(config: &RedactionConfig) -> Result<Self, InitError> {
+        let mut builtin = config.patterns.clone();
+        builtin.sort_by_key(|pattern| *pattern != RedactionPattern::CreditCard);
+        let mut patterns = builtin
+            .into_iter()
+            .map(|pattern| {
+                let (regex, luhn) = match pattern {
+                    RedactionPattern::Email => (EMAIL, false),
+                    RedactionPattern::Phone => (PHONE, false),
+                    RedactionPattern::CreditCard => (CREDIT_CARD, true),
+                };
+                let regex =
+                    Regex::new(regex).expect("always valid if tests pass");
+                (regex, luhn)
+            })
+            .collect::<Vec<_>>();
+        for pattern in &config.custom_patterns {
+            let regex = Regex::new(pattern).map_err(|e| {
+                InitError::InvalidRedactionPattern(pattern.clone(), e)
+            })?;
+            patterns.push((regex, false));
+        }
+        Ok(Self {
+            patterns,
+            placeholder: config.placeholder.clone(),
+        })
+    }
+
+    /// Returns a copy of `body` with every match replaced by the
+    /// placeholder.
+    ///
+    /// Only the string values of JSON bodies, and of the JSON events of
+    /// streamed bodies, are redacted, so that numbers such as timestamps
+    /// aren't mistaken for personal data. Other text is redacted as a whole,
+    /// and bodies that aren't UTF-8 are returned unchanged, since they can't
+    /// be logged anyway.
+    #[must_use]
+    pub fn redact(&self, body: Bytes) -> Bytes {
+        if let Ok(mut json) = serde_json::from_slice::<Value>(&body) {
+            return if self.redact_json(&mut json) {
+                serde_json::to_vec(&json).map_or(body, Bytes::from)
+            } else {
+                body
+            };
+        }
+        let Ok(text) = std::str::from_utf8(&body) else {
+            return body;
+        };
+        let mut changed = false;
+        let lines = text
+            .split('\n')
+            .map(|line| {
+                let redacted = match line.strip_prefix("data:").map(|data| {
+                    serde_json::from_str::<Value>(data.trim_start())
+                }) {
+                    Some(Ok(mut json)) => self
+                        .redact_json(&mut json)
+                        .then(|| format!("data: {json}")),
+                    _ => self.redact_text(line),
+                };
+                changed |= redacted.is_some();
+                redacted.map_or(Cow::Borrowed(line), Cow::Owned)
+            })
+            .collect::<Vec<_>>();
+        if changed {
+            Bytes::from(lines.join("\n"))
+        } else {
+            body
+        }
+    }
+
+    /// Redacts the string values of `json`, returning whether any were
+    /// changed.
+    fn redact_json(&self, json: &mut Value) -> bool {
+        match json {
+            Value::String(text) => match self.redact_text(text) {
+                Some(redacted) => {
+                    *text = redacted;
+                    true
+                }
+                None => false,
+            },
+            Value::Array(values) => {
+                values.iter_mut().fold(false, |changed, value| {
+                    self.redact_json(value) || changed
+                })
+            }
+            Value::Object(values) => {
+                values.values_mut().fold(false, |changed, value| {
+                    self.redact_json(value) || changed
+                })
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `text` with every match replaced, or `None` if nothing
+    /// matched.
+    fn redact_text(&self, text: &str) -> Option<String> {
+        let mut redacted = Cow::Borrowed(text);
+        for (regex, luhn) in &self.patterns {
+            let replaced =
+                regex.replace_all(&redacted, |captures: &Captures| {
+                    let matched = &captures[0];
+                    if !luhn || luhn_valid(matched) {
+                        self.placeholder.clone()
+                    } else {
+                        matched.to_string()
+                    }
+                });
+            if let Cow::Owned(replaced) = replaced {
+                redacted = Cow::Owned(replaced);
+            }
+        }
+        match redacted {
+            Cow::Owned(redacted) if redacted != text => Some(redacted),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the digits of `number` have a valid Luhn checksum, to tell credit
+/// card numbers apart from other long numbers.
+fn luhn_valid(number: &str) -> bool {
+    let mut sum = 0;
+    let digits = number.chars().rev().filter_map(|c| c.to_digit(10));
+    for (i, digit) in digits.enumerate() {
+        sum += if i % 2 == 1 {
+            let doubled = digit * 2;
+            if doubled > 9 { doubled - 9 } else { doubled }
+        } else {
+            digit
+        };
+    }
+    sum % 10 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor() -> BodyRedactor {
+        BodyRedactor::new(&RedactionConfig::default()).unwrap()
+    }
+
+    fn redact(redactor: &BodyRedactor, body: &str) -> String {
+        let redacted = redactor.redact(Bytes::from(body.to_string()));
+        String::from_utf8(redacted.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn builtin_patterns_are_redacted() {
+        let redactor = redactor();
+        assert_eq!(
+            redact(&redactor, r#"{"content":"mail jane.doe@example.com"}"#),
+            r#"{"content":"mail [REDACTED]"}"#
+        );
+        assert_eq!(
+            redact(&redactor, "call +1-555-123-4567 now"),
+            "call [REDACTED] now"
+        );
+        assert_eq!(
+            redact(&redactor, "card 4111 1111 1111 1111 expires"),
+            "card [REDACTED] expires"
+        );
+    }
+
+    #[test]
+    fn only_json_strings_are_redacted() {
+        let redactor = redactor();
+        let body = r#"{"created":1741569952123,"content":"jane@example.com"}"#;
+        let redacted: Value =
+            serde_json::from_str(&redact(&redactor, body)).unwrap();
+        assert_eq!(redacted["created"], 1_741_569_952_123_u64);
+        assert_eq!(redacted["content"], "[REDACTED]");
+    }
+
+    #[test]
+    fn streamed_events_are_redacted() {
+        let redactor = redactor();
+        let body =
+            "data: {\"content\":\"jane@example.com\"}\n\ndata: [DONE]\n\n";
+        assert_eq!(
+            redact(&redactor, body),
+            "data: {\"content\":\"[REDACTED]\"}\n\ndata: [DONE]\n\n"
+        );
+    }
+
+    #[test]
+    fn numbers_failing_luhn_are_not_redacted_as_cards() {
+        let redactor = BodyRedactor::new(&RedactionConfig {
+            patterns: vec![RedactionPattern::CreditCard],
+            ..Default::default()
+        })
+        .unwrap();
+        let body = "order 1234 5678 9012 3456";
+        assert_eq!(redact(&redactor, body), body);
+    }
+
+    #[test]
+    fn custom_patterns_are_redacted() {
+        let redactor = BodyRedactor::new(&RedactionConfig {
+            patterns: Vec::new(),
+            custom_patterns: vec![r"ACCT-\d+".to_string()],
+            placeholder: "***".to_string(),
+        })
+        .unwrap();
+        assert_eq!(
+            redact(&redactor, "account ACCT-42, mail a@b.co"),
+            "account ***, mail a@b.co"
+        );
+    }
+
+    #[test]
+    fn invalid_custom_pattern_is_rejected() {
+        let config = RedactionConfig {
+            custom_patterns: vec!["(unclosed".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            BodyRedactor::new(&config),
+            Err(InitError::InvalidRedactionPattern(..))
+        ));
+    }
+}
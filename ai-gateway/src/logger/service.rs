@@ -7,6 +7,7 @@ use http_body_util::BodyExt;
 use indexmap::IndexMap;
 use opentelemetry::KeyValue;
 use reqwest::Client;
+use rust_decimal::prelude::ToPrimitive;
 use tokio::{sync::oneshot, time::Instant};
 use typed_builder::TypedBuilder;
 use url::Url;
@@ -16,11 +17,14 @@ use crate::{
     app_state::AppState,
     config::deployment_target::DeploymentTarget,
     error::{init::InitError, logger::LoggerError},
+    logger::usage,
     metrics::tfft::TFFTFuture,
     store::minio::MinioClient,
     types::{
         body::BodyReader,
-        extensions::{AuthContext, MapperContext, PromptContext},
+        extensions::{
+            AuthContext, MapperContext, PromptContext, Transformation,
+        },
         logger::{
             HeliconeLogMetadata, Log, LogMessage, RequestLog, ResponseLog,
         },
@@ -75,12 +79,28 @@ pub struct LoggerService {
     cache_reference_id: Option<String>,
     #[builder(default)]
     prompt_ctx: Option<PromptContext>,
+    #[builder(default)]
+    balance_strategy: Option<String>,
+    #[builder(default)]
+    applied_transformations: Option<Vec<Transformation>>,
 }
 
 impl LoggerService {
+    /// Sends the request log to Helicone, and records whether the logging
+    /// backend could be reached.
     #[tracing::instrument(skip_all)]
+    pub async fn log(self) -> Result<(), LoggerError> {
+        let app_state = self.app_state.clone();
+        let result = self.send().await;
+        app_state
+            .0
+            .logging_health
+            .record(&app_state.0.metrics, &result);
+        result
+    }
+
     #[allow(clippy::cast_precision_loss, clippy::too_many_lines)]
-    pub async fn log(mut self) -> Result<(), LoggerError> {
+    async fn send(mut self) -> Result<(), LoggerError> {
         tracing::trace!("logging request");
         let tfft_future = TFFTFuture::new(self.start_instant, self.tfft_rx);
         let collect_future = self.response_body.collect();
@@ -97,18 +117,49 @@ impl LoggerService {
         tracing::trace!(tfft_duration = ?tfft_duration, "tfft_duration");
         let req_body_len = self.request_body.len();
         let resp_body_len = response_body.len();
+        let estimated_usage =
+            (self.app_state.config().helicone.estimate_missing_usage
+                && self.response_status.is_success())
+            .then(|| {
+                usage::estimate_if_missing(
+                    &self.request_body,
+                    &response_body,
+                    self.mapper_ctx.is_stream,
+                )
+            })
+            .flatten();
+        // cached responses didn't use any of the provider's tokens
+        let reported_usage = (self.cache_reference_id.is_none()
+            && self.response_status.is_success())
+        .then(|| {
+            usage::ReportedUsage::from_response(
+                &response_body,
+                self.mapper_ctx.is_stream,
+            )
+        })
+        .flatten();
         let s3_client = if self.app_state.config().deployment_target.is_cloud()
         {
             MinioClient::cloud(&self.app_state.0.minio)
         } else {
             MinioClient::sidecar(&self.app_state.0.jawn_http_client)
         };
+        // only the logged copies are redacted, the request has already been
+        // sent to the provider as is
+        let (request_body, response_body) =
+            match &self.app_state.0.body_redactor {
+                Some(redactor) => (
+                    redactor.redact(self.request_body),
+                    redactor.redact(response_body),
+                ),
+                None => (self.request_body, response_body),
+            };
         s3_client
             .log_bodies(
                 &self.app_state,
                 &self.auth_ctx,
                 self.request_id,
-                self.request_body,
+                request_body,
                 response_body,
             )
             .await?;
@@ -128,6 +179,33 @@ impl LoggerService {
             .metrics
             .tfft_duration
             .record(tfft_duration.as_millis() as f64, &attributes);
+        if let Some(usage) = reported_usage {
+            usage.record(&self.app_state.0.metrics, &attributes[..2]);
+        }
+        let pricing = &self.app_state.config().pricing;
+        let cost =
+            reported_usage.filter(|_| !pricing.is_empty()).map(|usage| {
+                let cost = self.mapper_ctx.model.as_ref().and_then(|model| {
+                    pricing.cost(
+                        model,
+                        usage.prompt_tokens,
+                        usage.completion_tokens,
+                    )
+                });
+                if cost.is_none() {
+                    tracing::warn!(
+                        model = ?self.mapper_ctx.model,
+                        "no price configured for model, recording zero cost"
+                    );
+                }
+                cost
+            });
+        if let Some(cost) = cost {
+            self.app_state.0.metrics.cost.add(
+                cost.unwrap_or_default().to_f64().unwrap_or_default(),
+                &attributes[..2],
+            );
+        }
 
         let helicone_metadata = HeliconeLogMetadata::from_headers(
             &mut self.request_headers,
@@ -136,6 +214,9 @@ impl LoggerService {
             self.prompt_ctx,
         )?;
         let req_path = self.target_url.path().to_string();
+        let selected_provider = self.provider.to_string();
+        let selected_model =
+            self.mapper_ctx.model.as_ref().map(ToString::to_string);
         let provider = match self.provider {
             InferenceProvider::Ollama => "CUSTOM".to_string(),
             InferenceProvider::GoogleGemini => "GOOGLE".to_string(),
@@ -166,6 +247,10 @@ impl LoggerService {
             .cache_bucket_max_size(self.cache_bucket_max_size)
             .cache_control(self.cache_control)
             .cache_reference_id(self.cache_reference_id)
+            .balance_strategy(self.balance_strategy)
+            .selected_provider(Some(selected_provider))
+            .selected_model(selected_model)
+            .applied_transformations(self.applied_transformations)
             .build();
         let response_log = ResponseLog::builder()
             .id(self.request_id)
@@ -173,6 +258,24 @@ impl LoggerService {
             .body_size(resp_body_len as f64)
             .response_created_at(Utc::now())
             .delay_ms(tfft_duration.as_millis() as f64)
+            .prompt_tokens(
+                estimated_usage.map(|usage| usage.prompt_tokens as f64),
+            )
+            .completion_tokens(
+                estimated_usage.map(|usage| usage.completion_tokens as f64),
+            )
+            .usage_estimated(estimated_usage.map(|_| true))
+            .prompt_cache_write_tokens(
+                reported_usage
+                    .and_then(|usage| usage.cache_creation_tokens)
+                    .map(|tokens| tokens as f64),
+            )
+            .prompt_cache_read_tokens(
+                reported_usage
+                    .and_then(|usage| usage.cache_read_tokens)
+                    .map(|tokens| tokens as f64),
+            )
+            .cost(cost.flatten().and_then(|cost| cost.to_f64()))
             .build();
         let log = Log::new(request_log, response_log);
         let log_message = LogMessage::builder()
@@ -0,0 +1,501 @@
+use opentelemetry::KeyValue;
+use serde_json::Value;
+
+use crate::metrics::Metrics;
+
+/// Rough number of characters per token, used to approximate token counts
+/// when the provider doesn't report them.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Fields of request bodies whose text counts towards the prompt.
+const PROMPT_FIELDS: &[&str] =
+    &["content", "text", "system", "prompt", "input"];
+/// Fields of response bodies whose text counts towards the completion.
+const COMPLETION_FIELDS: &[&str] =
+    &["content", "text", "arguments", "thinking", "output"];
+
+/// Token usage estimated from the request and response bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EstimatedUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// Token usage reported by the provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportedUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    /// Prompt tokens read from the provider's prompt cache.
+    pub cache_read_tokens: Option<u64>,
+    /// Prompt tokens written to the provider's prompt cache.
+    pub cache_creation_tokens: Option<u64>,
+}
+
+impl ReportedUsage {
+    /// Reads the token usage reported in a response body, in either the
+    /// `OpenAI`, `Anthropic`, Bedrock or Gemini format, including the
+    /// tokens read from or written to the provider's prompt cache.
+    ///
+    /// Streamed responses report their usage in their final chunks, which
+    /// take precedence over earlier ones. Returns `None` if the provider
+    /// didn't report any usage.
+    #[must_use]
+    pub fn from_response(
+        response_body: &[u8],
+        is_stream: bool,
+    ) -> Option<Self> {
+        let responses = if is_stream {
+            stream_events(response_body)
+        } else {
+            serde_json::from_slice::<Value>(response_body)
+                .map(|value| vec![value])
+                .unwrap_or_default()
+        };
+        let tokens = |usage: &Value, fields: &[&str]| {
+            fields
+                .iter()
+                .find_map(|field| usage.get(field).and_then(Value::as_u64))
+        };
+        let nested = |usage: &Value, pointer: &str| {
+            usage.pointer(pointer).and_then(Value::as_u64)
+        };
+        let (mut prompt, mut completion, mut total) = (None, None, None);
+        let (mut cache_read, mut cache_creation) = (None, None);
+        for usage in responses.iter().filter_map(usage) {
+            cache_read = tokens(
+                usage,
+                &[
+                    "cache_read_input_tokens",
+                    "cacheReadInputTokens",
+                    "cachedContentTokenCount",
+                ],
+            )
+            .or_else(|| nested(usage, "/prompt_tokens_details/cached_tokens"))
+            .or(cache_read);
+            cache_creation = tokens(
+                usage,
+                &["cache_creation_input_tokens", "cacheWriteInputTokens"],
+            )
+            .or_else(|| {
+                nested(usage, "/prompt_tokens_details/cache_creation_tokens")
+            })
+            .or(cache_creation);
+            prompt = tokens(
+                usage,
+                &["prompt_tokens", "input_tokens", "promptTokenCount"],
+            )
+            .or(prompt);
+            completion = tokens(
+                usage,
+                &["completion_tokens", "output_tokens", "candidatesTokenCount"],
+            )
+            .or(completion);
+            total =
+                tokens(usage, &["total_tokens", "totalTokenCount"]).or(total);
+        }
+        if prompt.is_none() && completion.is_none() && total.is_none() {
+            return None;
+        }
+        let prompt_tokens = prompt.unwrap_or_default();
+        let completion_tokens = completion.unwrap_or_default();
+        Some(Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: total.unwrap_or(prompt_tokens + completion_tokens),
+            cache_read_tokens: cache_read,
+            cache_creation_tokens: cache_creation,
+        })
+    }
+
+    /// Adds the usage to the token counters, labeled with `attributes`.
+    pub fn record(&self, metrics: &Metrics, attributes: &[KeyValue]) {
+        metrics.prompt_tokens.add(self.prompt_tokens, attributes);
+        metrics
+            .completion_tokens
+            .add(self.completion_tokens, attributes);
+        metrics.total_tokens.add(self.total_tokens, attributes);
+    }
+}
+
+/// Estimates the token usage of a request if the provider's response
+/// doesn't report it.
+///
+/// Returns `None` if the response reports its usage.
+#[must_use]
+pub fn estimate_if_missing(
+    request_body: &[u8],
+    response_body: &[u8],
+    is_stream: bool,
+) -> Option<EstimatedUsage> {
+    let responses = if is_stream {
+        stream_events(response_body)
+    } else {
+        serde_json::from_slice::<Value>(response_body)
+            .map(|value| vec![value])
+            .unwrap_or_default()
+    };
+    if responses.iter().any(has_usage) {
+        return None;
+    }
+
+    let mut prompt = String::new();
+    if let Ok(request) = serde_json::from_slice::<Value>(request_body) {
+        collect_text(&request, PROMPT_FIELDS, &mut prompt);
+    }
+    let mut completion = String::new();
+    for response in &responses {
+        collect_text(response, COMPLETION_FIELDS, &mut completion);
+    }
+    Some(EstimatedUsage {
+        prompt_tokens: count_tokens(&prompt),
+        completion_tokens: count_tokens(&completion),
+    })
+}
+
+/// Parses the events of a streamed response body, with or without the SSE
+/// `data:` prefix.
+fn stream_events(body: &[u8]) -> Vec<Value> {
+    String::from_utf8_lossy(body)
+        .lines()
+        .map(|line| line.strip_prefix("data:").unwrap_or(line).trim())
+        .flat_map(|data| {
+            serde_json::Deserializer::from_str(data)
+                .into_iter::<Value>()
+                .map_while(Result::ok)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Whether a response body, or one event of a streamed response, reports
+/// its token usage.
+fn has_usage(value: &Value) -> bool {
+    usage(value).is_some()
+}
+
+/// The token usage reported by a response body, or one event of a streamed
+/// response, in either the `OpenAI`, `Anthropic`, or Gemini format.
+fn usage(value: &Value) -> Option<&Value> {
+    ["/usage", "/usageMetadata", "/message/usage"]
+        .iter()
+        .filter_map(|pointer| value.pointer(pointer))
+        .find(|usage| !usage.is_null())
+}
+
+/// Appends the string values of the given fields, at any depth, to `text`.
+fn collect_text(value: &Value, fields: &[&str], text: &mut String) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match value {
+                    Value::String(s) if fields.contains(&key.as_str()) => {
+                        text.push_str(s);
+                    }
+                    value => collect_text(value, fields, text),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                collect_text(value, fields, text);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Approximates the number of tokens in `text`.
+fn count_tokens(text: &str) -> u64 {
+    let tokens = text.chars().count().div_ceil(CHARS_PER_TOKEN);
+    u64::try_from(tokens).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{
+        InMemoryMetricExporter, PeriodicReader, SdkMeterProvider,
+        data::{ResourceMetrics, Sum},
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    fn request() -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{ "role": "user", "content": "Hello, world!" }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn usage_is_estimated_when_missing() {
+        let response = serde_json::to_vec(&json!({
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Hi there!" }
+            }]
+        }))
+        .unwrap();
+        let usage = estimate_if_missing(&request(), &response, false);
+        assert_eq!(
+            usage,
+            Some(EstimatedUsage {
+                prompt_tokens: 4,
+                completion_tokens: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn streamed_usage_is_estimated_when_missing() {
+        let response = [
+            json!({"choices": [{"index": 0, "delta": {"content": "Hi "}}]}),
+            json!({"choices": [{"index": 0, "delta": {"content": "there!"}}]}),
+        ]
+        .iter()
+        .map(|chunk| format!("data: {chunk}\n\n"))
+        .collect::<String>();
+        let usage = estimate_if_missing(&request(), response.as_bytes(), true);
+        assert_eq!(
+            usage,
+            Some(EstimatedUsage {
+                prompt_tokens: 4,
+                completion_tokens: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn reported_usage_is_not_estimated() {
+        let openai = json!({
+            "choices": [],
+            "usage": { "prompt_tokens": 19, "completion_tokens": 10 }
+        });
+        let anthropic_stream = json!({
+            "type": "message_start",
+            "message": { "usage": { "input_tokens": 19, "output_tokens": 1 } }
+        });
+        assert_eq!(
+            estimate_if_missing(
+                &request(),
+                &serde_json::to_vec(&openai).unwrap(),
+                false
+            ),
+            None
+        );
+        assert_eq!(
+            estimate_if_missing(
+                &request(),
+                format!("{anthropic_stream}").as_bytes(),
+                true
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn usage_is_read_from_responses() {
+        let openai = json!({
+            "choices": [],
+            "usage": {
+                "prompt_tokens": 19,
+                "completion_tokens": 10,
+                "total_tokens": 29
+            }
+        });
+        let anthropic = json!({
+            "content": [],
+            "usage": { "input_tokens": 19, "output_tokens": 10 }
+        });
+        let gemini = json!({
+            "candidates": [],
+            "usageMetadata": {
+                "promptTokenCount": 19,
+                "candidatesTokenCount": 10,
+                "totalTokenCount": 29
+            }
+        });
+        let expected = Some(ReportedUsage {
+            prompt_tokens: 19,
+            completion_tokens: 10,
+            total_tokens: 29,
+            cache_read_tokens: None,
+            cache_creation_tokens: None,
+        });
+        for response in [openai, anthropic, gemini] {
+            let body = serde_json::to_vec(&response).unwrap();
+            assert_eq!(ReportedUsage::from_response(&body, false), expected);
+        }
+
+        let missing = json!({ "choices": [] });
+        let body = serde_json::to_vec(&missing).unwrap();
+        assert_eq!(ReportedUsage::from_response(&body, false), None);
+    }
+
+    #[test]
+    fn streamed_usage_is_read_from_final_chunks() {
+        let openai = [
+            json!({"choices": [{"index": 0, "delta": {"content": "Hi"}}]}),
+            json!({"choices": [], "usage": {
+                "prompt_tokens": 19,
+                "completion_tokens": 10,
+                "total_tokens": 29
+            }}),
+        ];
+        let anthropic = [
+            json!({"type": "message_start", "message": {
+                "usage": { "input_tokens": 19, "output_tokens": 1 }
+            }}),
+            json!({"type": "message_delta", "usage": { "output_tokens": 10 }}),
+        ];
+        for chunks in [openai, anthropic] {
+            let body = chunks
+                .iter()
+                .map(|chunk| format!("data: {chunk}\n\n"))
+                .collect::<String>();
+            assert_eq!(
+                ReportedUsage::from_response(body.as_bytes(), true),
+                Some(ReportedUsage {
+                    prompt_tokens: 19,
+                    completion_tokens: 10,
+                    total_tokens: 29,
+                    cache_read_tokens: None,
+                    cache_creation_tokens: None,
+                })
+            );
+        }
+
+        let without_usage =
+            json!({"choices": [{"index": 0, "delta": {"content": "Hi"}}]});
+        let body = format!("data: {without_usage}\n\ndata: [DONE]\n\n");
+        assert_eq!(ReportedUsage::from_response(body.as_bytes(), true), None);
+    }
+
+    #[test]
+    fn prompt_cache_tokens_are_read_from_responses() {
+        let anthropic = json!({
+            "content": [],
+            "usage": {
+                "input_tokens": 19,
+                "output_tokens": 10,
+                "cache_read_input_tokens": 1024,
+                "cache_creation_input_tokens": 256
+            }
+        });
+        let openai = json!({
+            "choices": [],
+            "usage": {
+                "prompt_tokens": 19,
+                "completion_tokens": 10,
+                "total_tokens": 29,
+                "prompt_tokens_details": {
+                    "cached_tokens": 1024,
+                    "cache_creation_tokens": 256
+                }
+            }
+        });
+        for response in [anthropic, openai] {
+            let body = serde_json::to_vec(&response).unwrap();
+            let usage = ReportedUsage::from_response(&body, false).unwrap();
+            assert_eq!(usage.cache_read_tokens, Some(1024));
+            assert_eq!(usage.cache_creation_tokens, Some(256));
+        }
+
+        // anthropic streams report cache usage when the message starts
+        let chunks = [
+            json!({"type": "message_start", "message": {
+                "usage": {
+                    "input_tokens": 19,
+                    "output_tokens": 1,
+                    "cache_read_input_tokens": 1024
+                }
+            }}),
+            json!({"type": "message_delta", "usage": { "output_tokens": 10 }}),
+        ];
+        let body = chunks
+            .iter()
+            .map(|chunk| format!("data: {chunk}\n\n"))
+            .collect::<String>();
+        let usage =
+            ReportedUsage::from_response(body.as_bytes(), true).unwrap();
+        assert_eq!(usage.cache_read_tokens, Some(1024));
+        assert_eq!(usage.cache_creation_tokens, None);
+    }
+
+    /// The value of the `u64` counter `name` for the given attributes.
+    fn counter_value(
+        metrics: &[ResourceMetrics],
+        name: &str,
+        attributes: &[KeyValue],
+    ) -> Option<u64> {
+        metrics
+            .iter()
+            .flat_map(|resource| &resource.scope_metrics)
+            .flat_map(|scope| &scope.metrics)
+            .filter(|metric| metric.name == name)
+            .filter_map(|metric| {
+                metric.data.as_any().downcast_ref::<Sum<u64>>()
+            })
+            .flat_map(|sum| &sum.data_points)
+            .find(|point| {
+                attributes
+                    .iter()
+                    .all(|attribute| point.attributes.contains(attribute))
+            })
+            .map(|point| point.value)
+    }
+
+    #[test]
+    fn reported_usage_increments_token_counters() {
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .build();
+        let metrics = Metrics::new(&provider.meter("test"));
+        let attributes = [
+            KeyValue::new("provider", "openai"),
+            KeyValue::new("model", "gpt-4o-mini"),
+        ];
+        let response = serde_json::to_vec(&json!({
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Hi there!" }
+            }],
+            "usage": {
+                "prompt_tokens": 19,
+                "completion_tokens": 10,
+                "total_tokens": 29
+            }
+        }))
+        .unwrap();
+        let usage = ReportedUsage::from_response(&response, false).unwrap();
+        usage.record(&metrics, &attributes);
+        usage.record(&metrics, &attributes);
+
+        provider.force_flush().unwrap();
+        let exported = exporter.get_finished_metrics().unwrap();
+        for (name, value) in [
+            ("prompt_tokens", 38),
+            ("completion_tokens", 20),
+            ("total_tokens", 58),
+        ] {
+            assert_eq!(
+                counter_value(&exported, name, &attributes),
+                Some(value),
+                "{name}"
+            );
+        }
+        let other_model = [
+            KeyValue::new("provider", "openai"),
+            KeyValue::new("model", "gpt-4o"),
+        ];
+        assert_eq!(
+            counter_value(&exported, "total_tokens", &other_model),
+            None
+        );
+    }
+}
@@ -5,8 +5,10 @@ use ai_gateway::{
     config::Config,
     control_plane::websocket::ControlPlaneClient,
     discover::monitor::{
-        health::provider::HealthMonitor, rate_limit::RateLimitMonitor,
+        health::{HealthProbe, provider::HealthMonitor},
+        rate_limit::RateLimitMonitor,
     },
+    dispatcher::warm_pool::WarmPool,
     error::{init::InitError, runtime::RuntimeError},
     metrics::system::SystemMetrics,
     middleware::rate_limit,
@@ -112,10 +114,19 @@ async fn run_app(config: Config) -> Result<(), RuntimeError> {
     let config = app.state.config();
     let health_monitor = HealthMonitor::new(app.state.clone());
     let rate_limit_monitor = RateLimitMonitor::new(app.state.clone());
+    let health_probe = HealthProbe::new(app.state.clone()).await?;
+    let warm_pool = WarmPool::new(app.state.clone());
     let control_plane_state = app.state.0.control_plane_state.clone();
 
-    let rate_limiting_cleanup_service =
-        config.global.rate_limit.as_ref().map(|_| {
+    let has_model_rate_limits = config.routers.values().any(|router| {
+        router
+            .rate_limit
+            .as_ref()
+            .is_some_and(|rate_limit| !rate_limit.limits.per_model.is_empty())
+    });
+    let rate_limiting_cleanup_service = (config.global.rate_limit.is_some()
+        || has_model_rate_limits)
+        .then(|| {
             rate_limit::cleanup::GarbageCollector::new(
                 app.state.clone(),
                 CLEANUP_INTERVAL,
@@ -174,6 +185,20 @@ async fn run_app(config: Config) -> Result<(), RuntimeError> {
         ))
         .register(TaggedService::new("system-metrics", SystemMetrics));
 
+    if let Some(health_probe) = health_probe {
+        meltdown = meltdown.register(TaggedService::new(
+            "provider-health-probe",
+            health_probe,
+        ));
+        tasks.push("provider-health-probe");
+    }
+
+    if let Some(warm_pool) = warm_pool {
+        meltdown = meltdown
+            .register(TaggedService::new("provider-warm-pool", warm_pool));
+        tasks.push("provider-warm-pool");
+    }
+
     if let Some(rate_limiting_cleanup_service) = rate_limiting_cleanup_service {
         meltdown = meltdown.register(TaggedService::new(
             "rate-limiting-cleanup",
@@ -19,6 +19,40 @@ pub struct Metrics {
     pub request_count: Counter<u64>,
     pub response_count: Counter<u64>,
     pub tfft_duration: Histogram<f64>,
+    /// labels:
+    /// - `fingerprint_bucket`
+    pub request_fingerprints: Counter<u64>,
+    /// Number of assistant tool call turns in chat completion requests.
+    pub tool_call_turns: Histogram<u64>,
+    /// labels:
+    /// - `provider`
+    pub retry_count: Counter<u64>,
+    /// Tokens reported by providers, excluding cached responses.
+    ///
+    /// labels:
+    /// - `provider`
+    /// - `model`
+    pub prompt_tokens: Counter<u64>,
+    /// labels:
+    /// - `provider`
+    /// - `model`
+    pub completion_tokens: Counter<u64>,
+    /// labels:
+    /// - `provider`
+    /// - `model`
+    pub total_tokens: Counter<u64>,
+    /// Cost in USD of the tokens reported by providers, for models with a
+    /// configured price.
+    ///
+    /// labels:
+    /// - `provider`
+    /// - `model`
+    pub cost: Counter<f64>,
+    /// Whether the gateway is in the degraded mode because logs can't be
+    /// sent to Helicone, `1` if so and `0` otherwise.
+    pub logging_degraded: Gauge<u64>,
+    /// Number of request logs that couldn't be sent to Helicone.
+    pub dropped_logs: Counter<u64>,
     pub cache: CacheMetrics,
     pub routers: RouterMetrics,
 }
@@ -55,6 +89,51 @@ impl Metrics {
             .with_unit("ms")
             .with_description("Time to first token duration")
             .build();
+        let request_fingerprints = meter
+            .u64_counter("request_fingerprints")
+            .with_description("Number of requests by fingerprint bucket")
+            .build();
+        let tool_call_turns = meter
+            .u64_histogram("tool_call_turns")
+            .with_description(
+                "Number of assistant tool call turns in chat completion \
+                 requests",
+            )
+            .with_boundaries(vec![0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0])
+            .build();
+        let retry_count = meter
+            .u64_counter("retry_count")
+            .with_description("Number of requests retried against a provider")
+            .build();
+        let prompt_tokens = meter
+            .u64_counter("prompt_tokens")
+            .with_description("Number of prompt tokens reported by providers")
+            .build();
+        let completion_tokens = meter
+            .u64_counter("completion_tokens")
+            .with_description(
+                "Number of completion tokens reported by providers",
+            )
+            .build();
+        let total_tokens = meter
+            .u64_counter("total_tokens")
+            .with_description("Number of tokens reported by providers")
+            .build();
+        let cost = meter
+            .f64_counter("cost")
+            .with_unit("USD")
+            .with_description("Cost of the tokens reported by providers")
+            .build();
+        let logging_degraded = meter
+            .u64_gauge("logging_degraded")
+            .with_description(
+                "Whether logs can't be sent to Helicone and are being dropped",
+            )
+            .build();
+        let dropped_logs = meter
+            .u64_counter("dropped_logs")
+            .with_description("Number of request logs that failed to send")
+            .build();
         let cache = CacheMetrics::new(meter);
         let routers = RouterMetrics::new(meter);
         Self {
@@ -65,6 +144,15 @@ impl Metrics {
             request_count,
             response_count,
             tfft_duration,
+            request_fingerprints,
+            tool_call_turns,
+            retry_count,
+            prompt_tokens,
+            completion_tokens,
+            total_tokens,
+            cost,
+            logging_degraded,
+            dropped_logs,
             cache,
             routers,
         }
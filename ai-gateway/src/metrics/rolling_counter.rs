@@ -42,6 +42,10 @@ impl RollingCounter {
     }
 
     pub fn incr(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, amount: u32) {
         let now = Instant::now();
         let (idx, lap) = self.get_index_and_lap(now);
         let last_lap = self.laps[idx].load(Ordering::Acquire);
@@ -59,7 +63,7 @@ impl RollingCounter {
                 self.counters[idx].store(0, Ordering::Release);
             }
         }
-        self.counters[idx].fetch_add(1, Ordering::Relaxed);
+        self.counters[idx].fetch_add(amount, Ordering::Relaxed);
     }
 
     #[must_use]
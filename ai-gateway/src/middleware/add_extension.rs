@@ -1,6 +1,12 @@
-use std::task::{Context, Poll};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
+use futures::ready;
 use http::{Request, Response};
+use pin_project_lite::pin_project;
 use tower::{Layer, Service};
 use typed_builder::TypedBuilder;
 
@@ -38,7 +44,7 @@ where
 {
     type Response = S::Response;
     type Error = S::Error;
-    type Future = S::Future;
+    type Future = ResponseFuture<S::Future>;
 
     #[inline]
     fn poll_ready(
@@ -54,6 +60,43 @@ where
         if let Some(router_id) = self.router_id.clone() {
             extensions.insert(router_id);
         }
-        self.inner.call(req)
+        ResponseFuture {
+            inner: self.inner.call(req),
+            inference_provider: self.inference_provider.clone(),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`AddExtensions`].
+    ///
+    /// Responses converted from errors don't carry the provider the request
+    /// was sent to, so it's added to them in order for them to be attributed,
+    /// e.g. to fail them over to another provider.
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        inference_provider: InferenceProvider,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = match ready!(this.inner.poll(cx)) {
+            Ok(response) => response,
+            Err(e) => return Poll::Ready(Err(e)),
+        };
+        if response.extensions().get::<InferenceProvider>().is_none() {
+            response
+                .extensions_mut()
+                .insert(this.inference_provider.clone());
+        }
+        Poll::Ready(Ok(response))
     }
 }
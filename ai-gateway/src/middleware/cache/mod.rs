@@ -1,4 +1,5 @@
 pub mod optional;
+mod semantic;
 mod service;
 
 pub use optional::{Layer as CacheLayer, Service as CacheService};
@@ -0,0 +1,478 @@
+//! Serves responses cached for prompts that are similar to, rather than
+//! identical to, the prompt of a request, by comparing embeddings of the
+//! prompts.
+use std::{
+    collections::VecDeque,
+    hash::{Hash, Hasher},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use bytes::Bytes;
+use http::{StatusCode, request::Parts};
+use http_body_util::BodyExt;
+use http_cache::HttpResponse;
+use rust_decimal::prelude::ToPrimitive;
+use rustc_hash::FxHasher;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::{OnceCell, RwLock};
+
+use crate::{
+    app_state::AppState,
+    config::cache::SemanticCacheConfig,
+    dispatcher::client::{Client, ProviderClient},
+    endpoints::{ApiEndpoint, openai::OpenAI},
+    error::{api::ApiError, internal::InternalError},
+    middleware::mapper::{
+        model::ModelMapper, registry::EndpointConverterRegistry,
+    },
+    types::{extensions::AuthContext, model_id::ModelId},
+};
+
+/// The most cached prompts kept per cache, beyond which the oldest are
+/// evicted.
+const MAX_ENTRIES: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub(crate) struct SemanticCache {
+    threshold: f32,
+    embedder: Arc<Embedder>,
+    entries: Arc<RwLock<VecDeque<Entry>>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: SemanticKey,
+    response: HttpResponse,
+    status: StatusCode,
+    expires_at: SystemTime,
+}
+
+/// Identifies the prompt of a request. Prompts are only compared to prompts
+/// with the same scope, i.e. sent to the same path by the same tenant with
+/// the same parameters, such as the model and tools.
+#[derive(Debug, Clone)]
+pub(crate) struct SemanticKey {
+    scope: u64,
+    embedding: Vec<f32>,
+}
+
+impl SemanticCache {
+    pub(crate) fn new(
+        app_state: AppState,
+        config: &SemanticCacheConfig,
+    ) -> Self {
+        Self {
+            threshold: config.threshold.to_f32().unwrap_or(1.0),
+            embedder: Arc::new(Embedder::new(
+                app_state,
+                config.embedding_model.clone(),
+            )),
+            entries: Arc::default(),
+        }
+    }
+
+    /// Embeds the prompt of the request. Returns `None` if the request has no
+    /// prompt or it could not be embedded, in which case the request is
+    /// only looked up in the exact match cache.
+    pub(crate) async fn key(
+        &self,
+        parts: &Parts,
+        body: &Bytes,
+        seed: Option<&str>,
+        tenant: Option<&str>,
+    ) -> Option<SemanticKey> {
+        let mut body = serde_json::from_slice::<Value>(body).ok()?;
+        let prompt = prompt(&body)?;
+        let scope = scope(parts, &mut body, seed, tenant);
+        let auth_ctx = parts.extensions.get::<AuthContext>();
+        match self.embedder.embed(&prompt, auth_ctx).await {
+            Ok(embedding) => Some(SemanticKey { scope, embedding }),
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to embed prompt for semantic cache");
+                None
+            }
+        }
+    }
+
+    /// The fresh cached response whose prompt is most similar to the key's,
+    /// if it is at least as similar as the threshold.
+    pub(crate) async fn get(
+        &self,
+        key: &SemanticKey,
+        now: SystemTime,
+    ) -> Option<(HttpResponse, StatusCode)> {
+        let entries = self.entries.read().await;
+        let (similarity, entry) = entries
+            .iter()
+            .filter(|entry| {
+                entry.key.scope == key.scope && entry.expires_at > now
+            })
+            .map(|entry| {
+                (
+                    cosine_similarity(&entry.key.embedding, &key.embedding),
+                    entry,
+                )
+            })
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))?;
+        tracing::trace!(
+            similarity,
+            threshold = self.threshold,
+            "closest semantic cache entry"
+        );
+        (similarity >= self.threshold)
+            .then(|| (entry.response.clone(), entry.status))
+    }
+
+    /// Evicts the cached responses that would be served for the key.
+    pub(crate) async fn evict(&self, key: &SemanticKey) {
+        let mut entries = self.entries.write().await;
+        entries.retain(|entry| {
+            entry.key.scope != key.scope
+                || cosine_similarity(&entry.key.embedding, &key.embedding)
+                    < self.threshold
+        });
+    }
+
+    pub(crate) async fn put(
+        &self,
+        key: SemanticKey,
+        response: HttpResponse,
+        status: StatusCode,
+        expires_at: SystemTime,
+    ) {
+        let now = SystemTime::now();
+        let mut entries = self.entries.write().await;
+        entries.retain(|entry| entry.expires_at > now);
+        entries.push_back(Entry {
+            key,
+            response,
+            status,
+            expires_at,
+        });
+        while entries.len() > MAX_ENTRIES {
+            entries.pop_front();
+        }
+    }
+}
+
+/// Embeds prompts by sending them to the embeddings endpoint of the
+/// configured model's provider.
+///
+/// Like health probes, these requests are sent directly with the provider's
+/// client rather than through the dispatcher, so they are never logged as
+/// requests.
+#[derive(Debug)]
+struct Embedder {
+    app_state: AppState,
+    model: ModelId,
+    converter_registry: EndpointConverterRegistry,
+    /// Created on first use, since creating a client may need to fetch the
+    /// provider's key.
+    client: OnceCell<Client>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<Embedding>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    embedding: Vec<f32>,
+}
+
+impl Embedder {
+    fn new(app_state: AppState, model: ModelId) -> Self {
+        let model_mapper = ModelMapper::new(app_state.clone());
+        let converter_registry = EndpointConverterRegistry::new(&model_mapper);
+        Self {
+            app_state,
+            model,
+            converter_registry,
+            client: OnceCell::new(),
+        }
+    }
+
+    async fn embed(
+        &self,
+        text: &str,
+        auth_ctx: Option<&AuthContext>,
+    ) -> Result<Vec<f32>, ApiError> {
+        // checked when the cache config is validated
+        let provider = self
+            .model
+            .inference_provider()
+            .ok_or(InternalError::Internal)?;
+        let config = self.app_state.config();
+        let provider_config =
+            config.providers.get(&provider).ok_or_else(|| {
+                InternalError::ProviderNotConfigured(provider.clone())
+            })?;
+        let client = self
+            .client
+            .get_or_try_init(|| Client::new(&self.app_state, provider.clone()))
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, "failed to create embeddings client");
+                InternalError::Internal
+            })?;
+
+        let source_endpoint = ApiEndpoint::OpenAI(OpenAI::embeddings());
+        let target_endpoint =
+            ApiEndpoint::mapped(source_endpoint.clone(), &provider)?;
+        let converter = self
+            .converter_registry
+            .get_converter(&source_endpoint, &target_endpoint)
+            .ok_or_else(|| {
+                InternalError::InvalidConverter(
+                    source_endpoint.clone(),
+                    target_endpoint.clone(),
+                )
+            })?;
+        let body = serde_json::to_vec(&json!({
+            "model": format!("{provider}/{}", self.model),
+            "input": text,
+        }))
+        .map_err(|error| InternalError::Serialize {
+            ty: "serde_json::Value",
+            error,
+        })?;
+        let (body, mapper_ctx) =
+            converter.convert_req_body(Bytes::from(body))?;
+        let path = target_endpoint.path(mapper_ctx.model.as_ref(), false)?;
+        let target_url = provider_config
+            .base_url
+            .join(&path)
+            .expect("PathAndQuery joined with valid url will always succeed");
+
+        let request_builder = client
+            .as_ref()
+            .post(target_url)
+            .header(http::header::CONTENT_TYPE, "application/json");
+        let request_builder = client
+            .authenticate(
+                &self.app_state,
+                request_builder,
+                &body,
+                auth_ctx,
+                provider,
+            )
+            .await?;
+        let response: http::Response<reqwest::Body> = request_builder
+            .body(body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(InternalError::ReqwestError)?
+            .into();
+        let (parts, body) = response.into_parts();
+        let body = body
+            .collect()
+            .await
+            .map_err(InternalError::ReqwestError)?
+            .to_bytes();
+        let body = converter
+            .convert_resp_body(parts, body, false)?
+            .ok_or(InternalError::Internal)?;
+        let response: EmbeddingResponse = serde_json::from_slice(&body)
+            .map_err(|error| InternalError::Deserialize {
+                ty: "EmbeddingResponse",
+                error,
+            })?;
+        response
+            .data
+            .into_iter()
+            .next()
+            .map(|embedding| embedding.embedding)
+            .ok_or_else(|| InternalError::Internal.into())
+    }
+}
+
+/// Hashes everything about a request except its messages, so that only
+/// prompts sent with identical parameters are compared.
+fn scope(
+    parts: &Parts,
+    body: &mut Value,
+    seed: Option<&str>,
+    tenant: Option<&str>,
+) -> u64 {
+    let mut hasher = FxHasher::default();
+    seed.hash(&mut hasher);
+    tenant.hash(&mut hasher);
+    parts.uri.path().hash(&mut hasher);
+    if let Some(body) = body.as_object_mut() {
+        body.remove("messages");
+    }
+    body.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The messages of a chat completion request, one `role: content` line per
+/// message, or `None` if the request has no text content.
+///
+/// Tool calls are included as `name(arguments)` after the content of the
+/// assistant message that made them.
+fn prompt(body: &Value) -> Option<String> {
+    let messages = body.get("messages").and_then(Value::as_array)?;
+    let mut prompt = String::new();
+    for message in messages {
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let mut content = match message.get("content") {
+            Some(Value::String(content)) => content.clone(),
+            Some(Value::Array(parts)) => parts
+                .iter()
+                .filter_map(|part| part.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => String::new(),
+        };
+        let tool_calls = message
+            .get("tool_calls")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for tool_call in tool_calls {
+            let Some(function) = tool_call.get("function") else {
+                continue;
+            };
+            if !content.is_empty() {
+                content.push(' ');
+            }
+            let name = function.get("name").and_then(Value::as_str);
+            let arguments = function.get("arguments").and_then(Value::as_str);
+            content.push_str(name.unwrap_or_default());
+            content.push('(');
+            content.push_str(arguments.unwrap_or_default());
+            content.push(')');
+        }
+        if content.is_empty() {
+            continue;
+        }
+        prompt.push_str(role);
+        prompt.push_str(": ");
+        prompt.push_str(&content);
+        prompt.push('\n');
+    }
+    (!prompt.is_empty()).then_some(prompt)
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a = a.iter().map(|a| a * a).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|b| b * b).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cosine_similarity_of_embeddings() {
+        assert!(
+            (cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6
+        );
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0], &[1.0, 0.0]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn prompt_includes_every_message() {
+        let body = json!({
+            "messages": [
+                { "role": "system", "content": "Be brief." },
+                {
+                    "role": "user",
+                    "content": [{ "type": "text", "text": "Hello" }]
+                }
+            ]
+        });
+        assert_eq!(
+            prompt(&body).as_deref(),
+            Some("system: Be brief.\nuser: Hello\n")
+        );
+        assert_eq!(prompt(&json!({ "messages": [] })), None);
+        assert_eq!(prompt(&json!({ "input": "Hello" })), None);
+    }
+
+    #[test]
+    fn prompt_includes_tool_calls_and_results() {
+        let body = json!({
+            "messages": [
+                { "role": "user", "content": "Weather in Paris?" },
+                {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {
+                            "name": "get_weather",
+                            "arguments": "{\"city\":\"Paris\"}"
+                        }
+                    }]
+                },
+                {
+                    "role": "tool",
+                    "tool_call_id": "call_1",
+                    "content": "Sunny"
+                }
+            ]
+        });
+        assert_eq!(
+            prompt(&body).as_deref(),
+            Some(
+                "user: Weather in Paris?\nassistant: \
+                 get_weather({\"city\":\"Paris\"})\ntool: Sunny\n"
+            )
+        );
+    }
+
+    #[test]
+    fn scope_covers_everything_but_messages() {
+        let (parts, ()) = http::Request::builder()
+            .uri("/v1/chat/completions")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let scope_of = |mut body: Value| scope(&parts, &mut body, None, None);
+        let messages = json!([{ "role": "user", "content": "Hello" }]);
+        let weather_tool = json!([{
+            "type": "function",
+            "function": { "name": "get_weather" }
+        }]);
+        let time_tool = json!([{
+            "type": "function",
+            "function": { "name": "get_time" }
+        }]);
+        let with_weather = scope_of(json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": messages,
+            "tools": weather_tool,
+        }));
+        let with_time = scope_of(json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": messages,
+            "tools": time_tool,
+        }));
+        let other_messages = scope_of(json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [{ "role": "user", "content": "Goodbye" }],
+            "tools": weather_tool,
+        }));
+        assert_ne!(with_weather, with_time);
+        assert_eq!(with_weather, other_messages);
+    }
+}
@@ -22,11 +22,15 @@ use tracing::Instrument;
 use url::Url;
 use uuid::Uuid;
 
+use super::semantic::{SemanticCache, SemanticKey};
 use crate::{
     app_state::AppState,
     cache::CacheClient,
     config::{
-        cache::{CacheConfig, DEFAULT_BUCKETS, MAX_BUCKET_SIZE},
+        cache::{
+            CacheConfig, DEFAULT_BUCKETS, ErrorResponseCacheConfig,
+            MAX_BUCKET_SIZE,
+        },
         router::RouterConfig,
     },
     error::{
@@ -61,7 +65,19 @@ struct CacheContext {
     directive: Option<String>,
     buckets: Option<u8>,
     seed: Option<String>,
+    /// Set from the `helicone-cache-ttl` header, and reflected in
+    /// `directive`.
+    ttl: Option<u64>,
+    /// Skip the cache lookup but still cache the response, replacing any
+    /// cached one.
+    bypass: Option<bool>,
     options: Option<CacheOptions>,
+    cache_streams: Option<bool>,
+    /// Only set from config, so that clients can't opt out of isolation.
+    share_across_tenants: Option<bool>,
+    tenant_header: Option<String>,
+    /// Only set from config.
+    error_responses: Option<ErrorResponseCacheConfig>,
 }
 
 impl CacheContext {
@@ -85,7 +101,21 @@ impl CacheContext {
                 .or_else(|| self.directive.clone()),
             buckets: other.buckets.or(self.buckets),
             seed: other.seed.clone().or_else(|| self.seed.clone()),
+            ttl: other.ttl.or(self.ttl),
+            bypass: other.bypass.or(self.bypass),
             options: other.options.or(self.options),
+            cache_streams: other.cache_streams.or(self.cache_streams),
+            share_across_tenants: other
+                .share_across_tenants
+                .or(self.share_across_tenants),
+            tenant_header: other
+                .tenant_header
+                .clone()
+                .or_else(|| self.tenant_header.clone()),
+            error_responses: other
+                .error_responses
+                .clone()
+                .or_else(|| self.error_responses.clone()),
         }
     }
 }
@@ -95,6 +125,7 @@ pub struct CacheLayer {
     app_state: AppState,
     backend: CacheClient,
     context: Arc<CacheContext>,
+    semantic: Option<SemanticCache>,
 }
 
 impl CacheLayer {
@@ -102,6 +133,7 @@ impl CacheLayer {
         app_state: AppState,
         config: CacheConfig,
     ) -> Result<Self, InitError> {
+        config.validate()?;
         let backend = app_state
             .0
             .cache_manager
@@ -112,15 +144,26 @@ impl CacheLayer {
             directive: config.directive,
             buckets: Some(config.buckets),
             seed: config.seed,
+            ttl: None,
+            bypass: None,
             options: Some(CacheOptions {
                 shared: false,
                 ..Default::default()
             }),
+            cache_streams: Some(config.cache_streams),
+            share_across_tenants: Some(config.share_across_tenants),
+            tenant_header: config.tenant_header,
+            error_responses: config.error_responses,
         };
+        let semantic = config
+            .semantic
+            .as_ref()
+            .map(|semantic| SemanticCache::new(app_state.clone(), semantic));
         Ok(Self {
             app_state,
             backend,
             context: Arc::new(context),
+            semantic,
         })
     }
 
@@ -165,6 +208,7 @@ impl<S> tower::Layer<S> for CacheLayer {
             app_state: self.app_state.clone(),
             backend: self.backend.clone(),
             context: Arc::clone(&self.context),
+            semantic: self.semantic.clone(),
         }
     }
 }
@@ -175,6 +219,7 @@ pub struct CacheService<S> {
     app_state: AppState,
     backend: CacheClient,
     context: Arc<CacheContext>,
+    semantic: Option<SemanticCache>,
 }
 
 impl<S> tower::Service<Request> for CacheService<S>
@@ -212,6 +257,7 @@ where
                 &this.app_state,
                 req,
                 &backend,
+                this.semantic.as_ref(),
                 merged_ctx,
             )
             .await
@@ -219,7 +265,6 @@ where
     }
 }
 
-#[allow(clippy::too_many_lines)]
 async fn check_cache(
     app_state: AppState,
     cache: &CacheClient,
@@ -237,160 +282,20 @@ async fn check_cache(
 
     match policy.before_request(&req, now) {
         BeforeRequest::Fresh(parts) => {
+            // cached error responses have a success status in their policy,
+            // see `CacheableResponse::error`
+            let status =
+                StatusCode::from_u16(http_resp.status).unwrap_or(parts.status);
             let additional_headers = vec![
                 (CACHE_HIT_HEADER, CACHE_HIT_HEADER_VALUE),
                 (CACHE_BUCKET_IDX, bucket_header_value(bucket)),
             ];
             let response =
-                build_response(http_resp, parts.status, additional_headers)?;
-
-            let start_instant = req
-                .extensions()
-                .get::<tokio::time::Instant>()
-                .copied()
-                .ok_or(InternalError::ExtensionNotFound("Instant"))?;
-            let start_time =
-                req.extensions()
-                    .get::<DateTime<Utc>>()
-                    .copied()
-                    .ok_or(InternalError::ExtensionNotFound("DateTime<Utc>"))?;
-
-            let target_url = get_url(&req)?;
-            let req_headers = req.headers().clone();
-
-            let (req_parts, req_body) = req.into_parts();
-            let req_body_bytes = req_body
-                .collect()
-                .await
-                .map_err(InternalError::CollectBodyError)?
-                .to_bytes();
-            let (resp_parts, resp_body) = response.into_parts();
-            let stream = futures::TryStreamExt::map_err(
-                resp_body.into_data_stream(),
-                |e| InternalError::CollectBodyError(e).into(),
-            );
-
-            let (user_resp_body, body_reader, tfft_rx) =
-                BodyReader::wrap_stream(stream, false);
-            let response = Response::from_parts(resp_parts, user_resp_body);
-
-            if app_state.config().helicone.is_observability_enabled() {
-                let auth_ctx =
-                    req_parts.extensions.get::<AuthContext>().cloned().ok_or(
-                        InternalError::ExtensionNotFound("AuthContext"),
-                    )?;
-
-                let app_state_cloned = app_state.clone();
-                // TODO(eng-2160): make cache service agnostic to which endpoint
-                // is used
-                let deserialized_body = serde_json::from_slice::<
-                    async_openai::types::CreateChatCompletionRequest,
-                >(&req_body_bytes)
-                .map_err(|e| InternalError::Deserialize {
-                    ty: "async_openai::types::CreateChatCompletionRequest",
-                    error: e,
-                });
-                let max_buckets = ctx.buckets;
-                let cache_control = ctx.directive.clone();
-                let helicone_request_id = response
-                    .headers()
-                    .get("helicone-id")
-                    .and_then(|hv| Uuid::parse_str(hv.to_str().unwrap()).ok())
-                    .unwrap_or(DEFAULT_UUID);
-                tokio::spawn(
-                    async move {
-                        let Ok(deserialized_body) = deserialized_body else {
-                            tracing::error!(
-                                "Could not deserialize request body"
-                            );
-                            return;
-                        };
-                        let Ok(model) =
-                            ModelId::from_str(&deserialized_body.model)
-                        else {
-                            tracing::error!(
-                                "Could not parse model id from request body"
-                            );
-                            return;
-                        };
-                        let provider =
-                            model.inference_provider().unwrap_or_else(|| {
-                                // this should never happen in practice, but we
-                                // need to handle it, so we
-                                // default to OpenAI
-                                tracing::error!(
-                                    "Could not parse inference provider from \
-                                     request body"
-                                );
-                                InferenceProvider::OpenAI
-                            });
-                        let is_stream = deserialized_body
-                            .stream
-                            .is_some_and(|stream| stream);
-                        let mapper_ctx = MapperContext {
-                            is_stream,
-                            model: Some(model),
-                        };
-                        let router_id =
-                            req_parts.extensions.get::<RouterId>().cloned();
-                        let deployment_target =
-                            app_state.config().deployment_target.clone();
-
-                        let response_logger = LoggerService::builder()
-                            .app_state(app_state.clone())
-                            .auth_ctx(auth_ctx)
-                            .start_time(start_time)
-                            .start_instant(start_instant)
-                            .target_url(target_url)
-                            .request_headers(req_headers)
-                            .request_body(req_body_bytes)
-                            .response_status(parts.status)
-                            .response_body(body_reader)
-                            .provider(provider)
-                            .tfft_rx(tfft_rx)
-                            .mapper_ctx(mapper_ctx)
-                            .router_id(router_id)
-                            .deployment_target(deployment_target)
-                            .cache_enabled(Some(true))
-                            .cache_bucket_max_size(max_buckets)
-                            .cache_control(cache_control)
-                            .cache_reference_id(Some(
-                                helicone_request_id.to_string(),
-                            ))
-                            .request_id(helicone_request_id)
-                            .build();
-                        if let Err(e) = response_logger.log().await {
-                            let error_str = e.as_ref().to_string();
-                            app_state_cloned
-                                .0
-                                .metrics
-                                .error_count
-                                .add(1, &[KeyValue::new("type", error_str)]);
-                        }
-                    }
-                    .instrument(tracing::Span::current()),
-                );
-                Ok(CacheCheckResult::Fresh(response))
-            } else {
-                tokio::spawn(
-                    async move {
-                        let tfft_future = TFFTFuture::new(start_instant, tfft_rx);
-                        let collect_future = body_reader.collect();
-                        let (_response_body, tfft_duration) = tokio::join!(collect_future, tfft_future);
-                        if let Ok(tfft_duration) = tfft_duration {
-                            tracing::trace!(tfft_duration = ?tfft_duration, "tfft_duration");
-                            let attributes = [
-                                KeyValue::new("path", target_url.path().to_string()),
-                            ];
-                            #[allow(clippy::cast_precision_loss)]
-                            app_state.0.metrics.tfft_duration.record(tfft_duration.as_millis() as f64, &attributes);
-                        } else { tracing::error!("Failed to get TFFT signal") }
-                    }
-                    .instrument(tracing::Span::current()),
-                );
+                build_response(http_resp, status, additional_headers)?;
 
-                Ok(CacheCheckResult::Fresh(response))
-            }
+            serve_cached(app_state, req, response, status, ctx)
+                .await
+                .map(CacheCheckResult::Fresh)
         }
         BeforeRequest::Stale {
             request: _,
@@ -400,6 +305,163 @@ async fn check_cache(
     }
 }
 
+/// Serves a cached response, logging the request as a cache hit if
+/// observability is enabled.
+#[allow(clippy::too_many_lines)]
+async fn serve_cached(
+    app_state: AppState,
+    req: Request,
+    response: Response,
+    status: StatusCode,
+    ctx: &CacheContext,
+) -> Result<Response, ApiError> {
+    let start_instant = req
+        .extensions()
+        .get::<tokio::time::Instant>()
+        .copied()
+        .ok_or(InternalError::ExtensionNotFound("Instant"))?;
+    let start_time = req
+        .extensions()
+        .get::<DateTime<Utc>>()
+        .copied()
+        .ok_or(InternalError::ExtensionNotFound("DateTime<Utc>"))?;
+
+    let target_url = get_url(&req)?;
+    let req_headers = req.headers().clone();
+
+    let (req_parts, req_body) = req.into_parts();
+    let req_body_bytes = req_body
+        .collect()
+        .await
+        .map_err(InternalError::CollectBodyError)?
+        .to_bytes();
+    let (resp_parts, resp_body) = response.into_parts();
+    let stream =
+        futures::TryStreamExt::map_err(resp_body.into_data_stream(), |e| {
+            InternalError::CollectBodyError(e).into()
+        });
+
+    let (user_resp_body, body_reader, tfft_rx) =
+        BodyReader::wrap_stream(stream, false);
+    let response = Response::from_parts(resp_parts, user_resp_body);
+
+    if app_state.config().helicone.is_observability_enabled() {
+        let auth_ctx = req_parts
+            .extensions
+            .get::<AuthContext>()
+            .cloned()
+            .ok_or(InternalError::ExtensionNotFound("AuthContext"))?;
+
+        let app_state_cloned = app_state.clone();
+        // TODO(eng-2160): make cache service agnostic to which endpoint
+        // is used
+        let deserialized_body = serde_json::from_slice::<
+            async_openai::types::CreateChatCompletionRequest,
+        >(&req_body_bytes)
+        .map_err(|e| InternalError::Deserialize {
+            ty: "async_openai::types::CreateChatCompletionRequest",
+            error: e,
+        });
+        let max_buckets = ctx.buckets;
+        let cache_control = ctx.directive.clone();
+        let helicone_request_id = response
+            .headers()
+            .get("helicone-id")
+            .and_then(|hv| Uuid::parse_str(hv.to_str().unwrap()).ok())
+            .unwrap_or(DEFAULT_UUID);
+        tokio::spawn(
+            async move {
+                let Ok(deserialized_body) = deserialized_body else {
+                    tracing::error!("Could not deserialize request body");
+                    return;
+                };
+                let Ok(model) = ModelId::from_str(&deserialized_body.model)
+                else {
+                    tracing::error!(
+                        "Could not parse model id from request body"
+                    );
+                    return;
+                };
+                let provider =
+                    model.inference_provider().unwrap_or_else(|| {
+                        // this should never happen in practice, but we
+                        // need to handle it, so we
+                        // default to OpenAI
+                        tracing::error!(
+                            "Could not parse inference provider from request \
+                             body"
+                        );
+                        InferenceProvider::OpenAI
+                    });
+                let is_stream =
+                    deserialized_body.stream.is_some_and(|stream| stream);
+                let mapper_ctx = MapperContext {
+                    is_stream,
+                    model: Some(model),
+                    reasoning: false,
+                    base64_embeddings: false,
+                    seed_dropped: false,
+                    include_usage: false,
+                };
+                let router_id = req_parts.extensions.get::<RouterId>().cloned();
+                let deployment_target =
+                    app_state.config().deployment_target.clone();
+
+                let response_logger = LoggerService::builder()
+                    .app_state(app_state.clone())
+                    .auth_ctx(auth_ctx)
+                    .start_time(start_time)
+                    .start_instant(start_instant)
+                    .target_url(target_url)
+                    .request_headers(req_headers)
+                    .request_body(req_body_bytes)
+                    .response_status(status)
+                    .response_body(body_reader)
+                    .provider(provider)
+                    .tfft_rx(tfft_rx)
+                    .mapper_ctx(mapper_ctx)
+                    .router_id(router_id)
+                    .deployment_target(deployment_target)
+                    .cache_enabled(Some(true))
+                    .cache_bucket_max_size(max_buckets)
+                    .cache_control(cache_control)
+                    .cache_reference_id(Some(helicone_request_id.to_string()))
+                    .request_id(helicone_request_id)
+                    .build();
+                if let Err(e) = response_logger.log().await {
+                    let error_str = e.as_ref().to_string();
+                    app_state_cloned
+                        .0
+                        .metrics
+                        .error_count
+                        .add(1, &[KeyValue::new("type", error_str)]);
+                }
+            }
+            .instrument(tracing::Span::current()),
+        );
+        Ok(response)
+    } else {
+        tokio::spawn(
+            async move {
+                let tfft_future = TFFTFuture::new(start_instant, tfft_rx);
+                let collect_future = body_reader.collect();
+                let (_response_body, tfft_duration) = tokio::join!(collect_future, tfft_future);
+                if let Ok(tfft_duration) = tfft_duration {
+                    tracing::trace!(tfft_duration = ?tfft_duration, "tfft_duration");
+                    let attributes = [
+                        KeyValue::new("path", target_url.path().to_string()),
+                    ];
+                    #[allow(clippy::cast_precision_loss)]
+                    app_state.0.metrics.tfft_duration.record(tfft_duration.as_millis() as f64, &attributes);
+                } else { tracing::error!("Failed to get TFFT signal") }
+            }
+            .instrument(tracing::Span::current()),
+        );
+
+        Ok(response)
+    }
+}
+
 enum CacheCheckResult {
     Fresh(Response),
     Stale,
@@ -411,6 +473,7 @@ fn bucket_header_value(bucket: u8) -> HeaderValue {
         .unwrap_or_else(|_| HeaderValue::from_static("0"))
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_response_for_cache_miss(
     cache: &CacheClient,
     ctx: &CacheContext,
@@ -419,14 +482,32 @@ async fn handle_response_for_cache_miss(
     resp: Response,
     bucket: u8,
     now: std::time::SystemTime,
+    semantic: Option<(&SemanticCache, SemanticKey)>,
 ) -> Result<Response, ApiError> {
-    let cacheable_resp =
-        CacheableResponse::new(ctx, resp.headers(), resp.status());
+    let error_ttl = ctx
+        .error_responses
+        .as_ref()
+        .filter(|config| config.is_cacheable(resp.status()))
+        .map(|config| config.ttl);
+    let cacheable_resp = match error_ttl {
+        Some(ttl) => CacheableResponse::error(resp.headers(), ttl),
+        None => CacheableResponse::new(ctx, resp.headers(), resp.status()),
+    };
     let cache_options = ctx.options.unwrap_or_default();
     let policy =
         CachePolicy::new_options(&req, &cacheable_resp, now, cache_options);
 
-    if !policy.is_storable() || !resp.status().is_success() {
+    let is_stream = resp
+        .extensions()
+        .get::<MapperContext>()
+        .is_some_and(|mapper_ctx| mapper_ctx.is_stream);
+    if is_stream && !ctx.cache_streams.unwrap_or(false) {
+        tracing::trace!("got streaming response, not caching");
+        return Ok(resp);
+    }
+    if !policy.is_storable()
+        || !(resp.status().is_success() || error_ttl.is_some())
+    {
         tracing::trace!(
             status = ?resp.status(),
             is_storable = policy.is_storable(),
@@ -451,6 +532,13 @@ async fn handle_response_for_cache_miss(
         version: get_version(parts.version),
     };
 
+    if let Some((semantic, semantic_key)) = semantic {
+        let expires_at = now + policy.time_to_live(now);
+        semantic
+            .put(semantic_key, http_resp.clone(), parts.status, expires_at)
+            .await;
+    }
+
     let cached = cache
         .put(key, http_resp, policy)
         .await
@@ -473,6 +561,7 @@ async fn make_request<S>(
     app_state: &AppState,
     mut req: Request,
     cache: &CacheClient,
+    semantic: Option<&SemanticCache>,
     ctx: CacheContext,
 ) -> Result<Response, ApiError>
 where
@@ -489,7 +578,10 @@ where
     }
 
     if let Some(directive) = &ctx.directive {
-        if req.headers().get(http::header::CACHE_CONTROL).is_none() {
+        // a ttl override replaces the client's own cache-control
+        if ctx.ttl.is_some()
+            || req.headers().get(http::header::CACHE_CONTROL).is_none()
+        {
             req.headers_mut().insert(
                 http::header::CACHE_CONTROL,
                 HeaderValue::from_str(directive)
@@ -504,12 +596,73 @@ where
         .await
         .map_err(InternalError::CollectBodyError)?
         .to_bytes();
+    if !ctx.cache_streams.unwrap_or(false) && is_stream_request(&body_bytes) {
+        tracing::trace!("streaming request, skipping cache");
+        let req = Request::from_parts(parts, body_bytes.into());
+        return inner.call(req).await.map_err(|e| {
+            tracing::error!(error = %e, "encountered infallible error");
+            ApiError::Internal(InternalError::Internal)
+        });
+    }
     let buckets = ctx.buckets.unwrap_or(DEFAULT_BUCKETS);
     let now = std::time::SystemTime::now();
 
     // Try each bucket in parallel
     let mut futures = FuturesUnordered::new();
-    let hasher = get_hasher(&parts, &body_bytes, ctx.seed.as_deref());
+    let tenant = tenant(&ctx, &parts);
+    let hasher =
+        get_hasher(&parts, &body_bytes, ctx.seed.as_deref(), tenant.as_deref());
+    if ctx.bypass.unwrap_or(false) {
+        tracing::trace!("cache bypassed, refreshing entry");
+        // evict the entries in every bucket, so that none of them is served
+        // in place of the refreshed response
+        for bucket in 0..buckets {
+            let mut cloned_hasher = hasher.clone();
+            bucket.hash(&mut cloned_hasher);
+            let key = cloned_hasher.finish().to_string();
+            cache
+                .delete(&key)
+                .await
+                .map_err(InternalError::CacheError)?;
+        }
+        let semantic_key = match semantic {
+            Some(semantic) => semantic
+                .key(
+                    &parts,
+                    &body_bytes,
+                    ctx.seed.as_deref(),
+                    tenant.as_deref(),
+                )
+                .await
+                .map(|key| (semantic, key)),
+            None => None,
+        };
+        if let Some((semantic, key)) = &semantic_key {
+            semantic.evict(key).await;
+        }
+        let bucket = rand::random::<u8>() % buckets;
+        let mut cloned_hasher = hasher.clone();
+        bucket.hash(&mut cloned_hasher);
+        let key = cloned_hasher.finish().to_string();
+        record_cache_miss(app_state, &parts.uri, bucket);
+        let req = Request::from_parts(parts.clone(), body_bytes.clone().into());
+        let resp = inner.call(req).await.map_err(|e| {
+            tracing::error!(error = %e, "encountered infallible error");
+            ApiError::Internal(InternalError::Internal)
+        })?;
+        let req_for_cache = Request::from_parts(parts, body_bytes.into());
+        return handle_response_for_cache_miss(
+            cache,
+            &ctx,
+            key,
+            req_for_cache,
+            resp,
+            bucket,
+            now,
+            semantic_key,
+        )
+        .await;
+    }
     // fairly sample different buckets
     let mut bucket_indices: Vec<u8> = (0..buckets).collect();
     {
@@ -564,6 +717,28 @@ where
         }
     }
 
+    // No exact match, so look for a response to a similar prompt
+    let semantic_key = match semantic {
+        Some(semantic) => semantic
+            .key(&parts, &body_bytes, ctx.seed.as_deref(), tenant.as_deref())
+            .await
+            .map(|key| (semantic, key)),
+        None => None,
+    };
+    if let Some((semantic, key)) = &semantic_key
+        && let Some((cached, status)) = semantic.get(key, now).await
+    {
+        record_semantic_cache_hit(app_state, &parts.uri);
+        let response = build_response(
+            cached,
+            status,
+            [(CACHE_HIT_HEADER, CACHE_HIT_HEADER_VALUE)],
+        )?;
+        let req = Request::from_parts(parts, body_bytes.into());
+        return serve_cached(app_state.clone(), req, response, status, &ctx)
+            .await;
+    }
+
     // Try stale hits
     if let Some((bucket, key)) = stale_hits.into_iter().next() {
         let req = Request::from_parts(parts.clone(), body_bytes.clone().into());
@@ -581,6 +756,7 @@ where
             resp,
             bucket,
             now,
+            semantic_key,
         )
         .await;
     }
@@ -610,15 +786,53 @@ where
         resp,
         bucket,
         now,
+        semantic_key,
     )
     .await
 }
 
-fn get_hasher(parts: &Parts, body: &Bytes, seed: Option<&str>) -> FxHasher {
+/// Whether the request body asks for a streaming response.
+///
+/// Bodies that can't be parsed are treated as non-streaming requests.
+fn is_stream_request(body: &Bytes) -> bool {
+    #[derive(serde::Deserialize)]
+    struct StreamField {
+        #[serde(default)]
+        stream: Option<bool>,
+    }
+    serde_json::from_slice::<StreamField>(body)
+        .is_ok_and(|body| body.stream.unwrap_or(false))
+}
+
+/// The tenant that cache entries for a request are scoped to, if any.
+fn tenant(ctx: &CacheContext, parts: &Parts) -> Option<String> {
+    if ctx.share_across_tenants.unwrap_or(false) {
+        return None;
+    }
+    if let Some(auth_ctx) = parts.extensions.get::<AuthContext>() {
+        return Some(auth_ctx.org_id.to_string());
+    }
+    let header = ctx.tenant_header.as_deref()?;
+    parts
+        .headers
+        .get(header)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| format!("header:{value}"))
+}
+
+fn get_hasher(
+    parts: &Parts,
+    body: &Bytes,
+    seed: Option<&str>,
+    tenant: Option<&str>,
+) -> FxHasher {
     let mut hasher = FxHasher::default();
     if let Some(s) = seed {
         s.hash(&mut hasher);
     }
+    if let Some(tenant) = tenant {
+        tenant.hash(&mut hasher);
+    }
     if let Some(pq) = parts.uri.path_and_query() {
         pq.hash(&mut hasher);
     }
@@ -635,6 +849,15 @@ fn record_cache_hit(app_state: &AppState, bucket: u8, uri: &http::Uri) {
     app_state.0.metrics.cache.hits.add(1, attributes);
 }
 
+fn record_semantic_cache_hit(app_state: &AppState, uri: &http::Uri) {
+    let attributes = &[
+        KeyValue::new("bucket", "semantic"),
+        KeyValue::new("path", uri.path().to_string()),
+    ];
+    tracing::trace!(path = uri.path(), "semantic cache hit");
+    app_state.0.metrics.cache.hits.add(1, attributes);
+}
+
 fn record_cache_miss(app_state: &AppState, uri: &http::Uri, bucket: u8) {
     let attributes = &[
         KeyValue::new("bucket", bucket.to_string()),
@@ -658,15 +881,37 @@ fn get_cache_ctx(req: &Request) -> Result<CacheContext, InvalidRequestError> {
     let seed = headers
         .get("helicone-cache-seed")
         .and_then(|v| v.to_str().ok().map(String::from));
-    let directive = headers
-        .get(http::header::CACHE_CONTROL)
-        .and_then(|v| v.to_str().ok().map(String::from));
+    let ttl = headers
+        .get("helicone-cache-ttl")
+        .map(|v| {
+            let v = v.to_str().unwrap_or_default();
+            v.parse::<u64>()
+                .ok()
+                .filter(|ttl| *ttl > 0)
+                .ok_or_else(|| InvalidRequestError::InvalidCacheTtl(v.into()))
+        })
+        .transpose()?;
+    let bypass = headers
+        .get("helicone-cache-bypass")
+        .and_then(|v| v.to_str().map_or(None, |v| v.parse::<bool>().ok()));
+    let directive = match ttl {
+        Some(ttl) => Some(format!("max-age={ttl}")),
+        None => headers
+            .get(http::header::CACHE_CONTROL)
+            .and_then(|v| v.to_str().ok().map(String::from)),
+    };
     Ok(CacheContext {
         enabled,
         directive,
         buckets,
         seed,
+        ttl,
+        bypass,
         options: None,
+        cache_streams: None,
+        share_across_tenants: None,
+        tenant_header: None,
+        error_responses: None,
     })
 }
 
@@ -802,6 +1047,28 @@ impl CacheableResponse {
     }
 }
 
+impl CacheableResponse {
+    /// A cacheable error response that is fresh for `ttl`.
+    ///
+    /// The cache policy only stores responses with statuses it understands,
+    /// so the policy is built with a success status, and the actual status
+    /// is restored from the cached response on hits.
+    fn error(resp: &HeaderMap, ttl: std::time::Duration) -> Self {
+        let mut resp_headers = resp.clone();
+        resp_headers.remove(http::header::SET_COOKIE);
+        resp_headers.remove(http::header::EXPIRES);
+        resp_headers.insert(
+            http::header::CACHE_CONTROL,
+            HeaderValue::from_str(&format!("max-age={}", ttl.as_secs()))
+                .expect("max-age is always a valid header value"),
+        );
+        Self {
+            resp_headers,
+            status: StatusCode::OK,
+        }
+    }
+}
+
 impl ResponseLike for CacheableResponse {
     fn status(&self) -> StatusCode {
         self.status
@@ -0,0 +1,334 @@
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::future::{BoxFuture, join_all};
+use http::{
+    HeaderName, HeaderValue, header::CONTENT_LENGTH, uri::PathAndQuery,
+};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+use crate::{
+    config::providers::{EmbeddingsConfig, PartialFailure},
+    error::{api::ApiError, internal::InternalError},
+    types::{request::Request, response::Response},
+};
+
+/// Set on responses that are missing the embeddings of some inputs because
+/// one or more of the split requests failed.
+const PARTIAL_RESPONSE_HEADER: HeaderName =
+    HeaderName::from_static("helicone-partial-response");
+
+#[derive(Debug, Clone, Copy)]
+pub struct Layer {
+    config: EmbeddingsConfig,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn new(config: EmbeddingsConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            config: self.config,
+        }
+    }
+}
+
+/// Splits embeddings requests with more inputs than the provider accepts
+/// into multiple requests, and reassembles their embeddings in order.
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    config: EmbeddingsConfig,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = ApiError>
+        + Send
+        + Clone
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "embeddings", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut this = self.clone();
+        std::mem::swap(self, &mut this);
+        Box::pin(async move {
+            let Some(max_inputs) = this.config.max_inputs else {
+                return this.inner.call(req).await;
+            };
+            if !is_embeddings(&req) {
+                return this.inner.call(req).await;
+            }
+            let (parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(|e| InternalError::RequestBodyError(Box::new(e)))?
+                .to_bytes();
+            let Some(chunks) = split(&body, max_inputs.get()) else {
+                let req = Request::from_parts(
+                    parts,
+                    axum_core::body::Body::from(body),
+                );
+                return this.inner.call(req).await;
+            };
+            tracing::debug!(
+                requests = chunks.len(),
+                max_inputs,
+                "splitting embeddings request"
+            );
+
+            let responses = chunks.into_iter().map(|(offset, chunk)| {
+                let mut parts = parts.clone();
+                parts.headers.remove(CONTENT_LENGTH);
+                let req = Request::from_parts(
+                    parts,
+                    axum_core::body::Body::from(chunk),
+                );
+                let inner = this.inner.clone();
+                async move { (offset, inner.oneshot(req).await) }
+            });
+            let responses = join_all(responses).await;
+            merge(responses, this.config.on_partial_failure).await
+        })
+    }
+}
+
+fn is_embeddings(req: &Request) -> bool {
+    req.extensions()
+        .get::<PathAndQuery>()
+        .map_or_else(|| req.uri().path(), PathAndQuery::path)
+        .ends_with("embeddings")
+}
+
+/// Splits the `input` array of an embeddings request body into chunks of
+/// at most `max_inputs` items, returning each chunk's body along with the
+/// index of its first input.
+///
+/// Returns `None` if the request doesn't need to be split.
+fn split(body: &[u8], max_inputs: usize) -> Option<Vec<(usize, Bytes)>> {
+    let Ok(Value::Object(request)) = serde_json::from_slice::<Value>(body)
+    else {
+        return None;
+    };
+    let inputs = request.get("input")?.as_array()?;
+    if inputs.len() <= max_inputs {
+        return None;
+    }
+    let chunks = inputs
+        .chunks(max_inputs)
+        .enumerate()
+        .map(|(idx, inputs)| {
+            let mut request = request.clone();
+            request.insert("input".to_string(), Value::Array(inputs.to_vec()));
+            let body = serde_json::to_vec(&request)
+                .expect("json value is always serializable");
+            (idx * max_inputs, Bytes::from(body))
+        })
+        .collect();
+    Some(chunks)
+}
+
+/// Reassembles the responses of split requests into a single response.
+///
+/// `responses` must be in the order of the original inputs.
+async fn merge(
+    responses: Vec<(usize, Result<Response, ApiError>)>,
+    on_partial_failure: PartialFailure,
+) -> Result<Response, ApiError> {
+    let mut successes = Vec::with_capacity(responses.len());
+    let mut last_failure = None;
+    for (offset, response) in responses {
+        match response {
+            Ok(response) if response.status().is_success() => {
+                let (parts, body) = response.into_parts();
+                let body = body
+                    .collect()
+                    .await
+                    .map_err(InternalError::CollectBodyError)?
+                    .to_bytes();
+                let body = serde_json::from_slice::<Value>(&body).map_err(
+                    |error| InternalError::Deserialize {
+                        ty: "embeddings response",
+                        error,
+                    },
+                )?;
+                successes.push((parts, offset, body));
+            }
+            failure => {
+                if on_partial_failure == PartialFailure::Fail {
+                    return failure;
+                }
+                tracing::warn!(offset, "split embeddings request failed");
+                last_failure = Some(failure);
+            }
+        }
+    }
+
+    let partial = last_failure.is_some();
+    let mut successes = successes.into_iter();
+    let Some((mut parts, offset, first)) = successes.next() else {
+        return last_failure
+            .unwrap_or_else(|| Err(InternalError::Internal.into()));
+    };
+    let body = successes
+        .fold(offset_indices(first, offset), |acc, (_, offset, body)| {
+            concat(acc, offset_indices(body, offset))
+        });
+
+    parts.headers.remove(CONTENT_LENGTH);
+    if partial {
+        parts
+            .headers
+            .insert(PARTIAL_RESPONSE_HEADER, HeaderValue::from_static("true"));
+    }
+    let body =
+        serde_json::to_vec(&body).expect("json value is always serializable");
+    Ok(Response::from_parts(
+        parts,
+        axum_core::body::Body::from(body),
+    ))
+}
+
+/// Shifts the `index` of each embedding so that it refers to the input's
+/// position in the original request.
+fn offset_indices(mut body: Value, offset: usize) -> Value {
+    if let Some(data) = body.get_mut("data").and_then(Value::as_array_mut) {
+        for embedding in data {
+            if let Some(index) = embedding.get("index").and_then(Value::as_u64)
+            {
+                embedding["index"] = json!(index + offset as u64);
+            }
+        }
+    }
+    body
+}
+
+/// Appends the embeddings of `next` to `acc` and sums their token usage.
+fn concat(mut acc: Value, mut next: Value) -> Value {
+    if let (Some(data), Some(Value::Array(next_data))) = (
+        acc.get_mut("data").and_then(Value::as_array_mut),
+        next.get_mut("data").map(Value::take),
+    ) {
+        data.extend(next_data);
+    }
+    if let (Some(usage), Some(next_usage)) = (
+        acc.get_mut("usage").and_then(Value::as_object_mut),
+        next.get("usage"),
+    ) {
+        for (key, value) in usage.iter_mut() {
+            if let (Some(a), Some(b)) =
+                (value.as_u64(), next_usage.get(key).and_then(Value::as_u64))
+            {
+                *value = json!(a + b);
+            }
+        }
+    }
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embeddings(count: usize, prompt_tokens: u64) -> Value {
+        let data = (0..count)
+            .map(|index| {
+                json!({
+                    "object": "embedding",
+                    "index": index,
+                    "embedding": [index],
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "object": "list",
+            "data": data,
+            "model": "text-embedding-3-small",
+            "usage": {
+                "prompt_tokens": prompt_tokens,
+                "total_tokens": prompt_tokens,
+            },
+        })
+    }
+
+    #[test]
+    fn split_chunks_inputs_in_order() {
+        let body = json!({
+            "model": "text-embedding-3-small",
+            "input": ["one", "two", "three", "four", "five"],
+        });
+        let body = serde_json::to_vec(&body).unwrap();
+        let chunks = split(&body, 2).unwrap();
+
+        let inputs = chunks
+            .iter()
+            .map(|(offset, chunk)| {
+                let chunk = serde_json::from_slice::<Value>(chunk).unwrap();
+                assert_eq!(chunk["model"], "text-embedding-3-small");
+                (*offset, chunk["input"].clone())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            inputs,
+            vec![
+                (0, json!(["one", "two"])),
+                (2, json!(["three", "four"])),
+                (4, json!(["five"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_skips_requests_within_limit() {
+        let body = json!({ "model": "m", "input": ["one", "two"] });
+        let body = serde_json::to_vec(&body).unwrap();
+        assert!(split(&body, 2).is_none());
+
+        let body = json!({ "model": "m", "input": "one" });
+        let body = serde_json::to_vec(&body).unwrap();
+        assert!(split(&body, 1).is_none());
+    }
+
+    #[test]
+    fn concat_reassembles_indices_and_usage() {
+        let merged = concat(
+            offset_indices(embeddings(2, 3), 0),
+            offset_indices(embeddings(1, 4), 2),
+        );
+        let indices = merged["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|embedding| embedding["index"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert_eq!(merged["usage"]["prompt_tokens"], 7);
+        assert_eq!(merged["usage"]["total_tokens"], 7);
+        assert_eq!(merged["object"], "list");
+    }
+}
@@ -7,8 +7,8 @@ use crate::{
     endpoints::openai::chat_completions::system_prompt,
     error::mapper::MapperError,
     middleware::mapper::{
-        DEFAULT_MAX_TOKENS, TryConvertError, mime_from_data_uri,
-        model::ModelMapper,
+        DEFAULT_MAX_TOKENS, TryConvertError, drop_prediction,
+        mime_from_data_uri, model::ModelMapper, requested_max_tokens,
     },
     types::{
         model_id::{ModelId, Version},
@@ -40,13 +40,14 @@ impl
     #[allow(clippy::too_many_lines)]
     fn try_convert(
         &self,
-        value: async_openai::types::CreateChatCompletionRequest,
+        mut value: async_openai::types::CreateChatCompletionRequest,
     ) -> std::result::Result<
         anthropic_ai_sdk::types::message::CreateMessageParams,
         Self::Error,
     > {
         use anthropic_ai_sdk::types::message as anthropic;
         use async_openai::types as openai;
+        drop_prediction(&mut value, &InferenceProvider::Anthropic);
         let source_model = ModelId::from_str(&value.model)?;
         let mut target_model = self
             .model_mapper
@@ -70,18 +71,28 @@ impl
         }
 
         let system_prompt = system_prompt(&value);
-        #[allow(deprecated)]
-        let max_tokens = value
-            .max_completion_tokens
-            .unwrap_or_else(|| value.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS));
-        let temperature = value.temperature;
+        let max_tokens =
+            requested_max_tokens(&value).unwrap_or(DEFAULT_MAX_TOKENS);
+        let thinking = value.reasoning_effort.as_ref().and_then(|effort| {
+            let budget_tokens = thinking_budget(effort, max_tokens)?;
+            Some(anthropic::Thinking {
+                type_: anthropic::ThinkingType::Enabled,
+                budget_tokens: budget_tokens.try_into().ok()?,
+            })
+        });
+        // anthropic rejects sampling parameters other than the defaults when
+        // thinking is enabled
+        let (temperature, top_p) = if thinking.is_some() {
+            (None, None)
+        } else {
+            (value.temperature, value.top_p)
+        };
         let stop_sequences = match value.stop {
             Some(openai::Stop::String(stop)) => Some(vec![stop]),
             Some(openai::Stop::StringArray(stops)) => Some(stops),
             None => None,
         };
         let stream = value.stream;
-        let top_p = value.top_p;
         let tools = if let Some(tools) = value.tools {
             let mapped_tools: Vec<_> = tools
                 .iter()
@@ -300,11 +311,36 @@ impl
             tools,
             tool_choice,
             metadata,
-            thinking: None,
+            thinking,
         })
     }
 }
 
+/// Minimum thinking budget accepted by Anthropic.
+const MIN_THINKING_BUDGET_TOKENS: u32 = 1024;
+
+/// Maps an `OpenAI` reasoning effort to an Anthropic thinking budget, the
+/// inverse of the mapping used when converting Anthropic requests to
+/// `OpenAI`.
+///
+/// Returns `None` if `max_tokens` is too small to fit the minimum budget,
+/// since Anthropic requires the budget to be less than `max_tokens`.
+fn thinking_budget(
+    effort: &async_openai::types::ReasoningEffort,
+    max_tokens: u32,
+) -> Option<u32> {
+    use async_openai::types::ReasoningEffort;
+    if max_tokens <= MIN_THINKING_BUDGET_TOKENS {
+        return None;
+    }
+    let budget = match effort {
+        ReasoningEffort::Low => max_tokens / 4,
+        ReasoningEffort::Medium => max_tokens / 2,
+        ReasoningEffort::High => max_tokens / 5 * 4,
+    };
+    Some(budget.clamp(MIN_THINKING_BUDGET_TOKENS, max_tokens - 1))
+}
+
 impl
     TryConvert<
         anthropic_ai_sdk::types::message::CreateMessageResponse,
@@ -593,7 +629,7 @@ impl
                 match delta {
                     anthropic::ContentBlockDelta::TextDelta { text } => {
                         let choice = openai::ChatChoiceStream {
-                            index: u32::try_from(index).unwrap_or(0),
+                            index: 0,
                             delta: openai::ChatCompletionStreamResponseDelta {
                                 role: None,
                                 content: Some(text),
@@ -632,7 +668,7 @@ impl
                                 }),
                             };
                         let choice = openai::ChatChoiceStream {
-                            index: u32::try_from(index).unwrap_or(0),
+                            index: 0,
                             delta: openai::ChatCompletionStreamResponseDelta {
                                 role: None,
                                 content: None,
@@ -654,10 +690,13 @@ impl
                             usage: None,
                         }))
                     }
+                    // thinking is surfaced as `reasoning_content` by the
+                    // mapper if the client asked for reasoning, it must not
+                    // be sent as content
                     anthropic::ContentBlockDelta::ThinkingDelta { .. }
                     | anthropic::ContentBlockDelta::SignatureDelta { .. } => {
                         Ok(None)
-                    } // No direct OpenAI mapping for these deltas
+                    }
                 }
             }
             anthropic::StreamEvent::ContentBlockStop { index: _ }
@@ -10,7 +10,10 @@ use super::{
     MapperError, TryConvert, TryConvertStreamData, model::ModelMapper,
 };
 use crate::{
-    middleware::mapper::{DEFAULT_MAX_TOKENS, TryConvertError},
+    middleware::mapper::{
+        DEFAULT_MAX_TOKENS, TryConvertError, drop_prediction,
+        requested_max_tokens,
+    },
     types::{model_id::ModelId, provider::InferenceProvider},
 };
 
@@ -35,13 +38,14 @@ impl
     #[allow(clippy::too_many_lines)]
     fn try_convert(
         &self,
-        value: async_openai::types::CreateChatCompletionRequest,
+        mut value: async_openai::types::CreateChatCompletionRequest,
     ) -> Result<
         aws_sdk_bedrockruntime::operation::converse::ConverseInput,
         Self::Error,
     > {
         use async_openai::types as openai;
         use aws_sdk_bedrockruntime::types as bedrock;
+        drop_prediction(&mut value, &InferenceProvider::Bedrock);
         let source_model = ModelId::from_str(&value.model)?;
 
         let target_model = self
@@ -51,7 +55,7 @@ impl
         tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
 
         let max_tokens =
-            value.max_completion_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+            requested_max_tokens(&value).unwrap_or(DEFAULT_MAX_TOKENS);
         let stop_sequences = match value.stop {
             Some(openai::Stop::String(stop)) => Some(vec![stop]),
             Some(openai::Stop::StringArray(stops)) => Some(stops),
@@ -327,6 +331,7 @@ impl
             .unwrap_or_default();
 
         let created = 0;
+        let finish_reason = finish_reason(&value.stop_reason);
         let usage = if let Some(usage) = value.usage {
             usage
         } else {
@@ -428,7 +433,7 @@ impl
         let choice = openai::ChatChoice {
             index: 0,
             message,
-            finish_reason: None,
+            finish_reason,
             logprobs: None,
         };
 
@@ -474,14 +479,9 @@ impl
 
         #[allow(deprecated)]
         let mut choices = Vec::new();
-        let mut completion_usage: openai::CompletionUsage =
-            openai::CompletionUsage {
-                prompt_tokens: 0,
-                completion_tokens: 0,
-                total_tokens: 0,
-                prompt_tokens_details: None,
-                completion_tokens_details: None,
-            };
+        // like OpenAI, usage is only sent in its own chunk at the end of the
+        // stream, converted from Bedrock's final metadata event
+        let mut completion_usage = None;
         match value {
             bedrock::ConverseStreamOutput::MessageStart(message) => {
                 let choice = openai::ChatChoiceStream {
@@ -608,18 +608,38 @@ impl
             }
 
             bedrock::ConverseStreamOutput::Metadata(metadata) => {
-                if let Some(usage) = metadata.usage {
-                    completion_usage.prompt_tokens =
-                        u32::try_from(usage.input_tokens).unwrap_or(0);
-                    completion_usage.completion_tokens =
-                        u32::try_from(usage.output_tokens).unwrap_or(0);
-                    completion_usage.total_tokens =
-                        u32::try_from(usage.total_tokens).unwrap_or(0);
-                }
+                completion_usage =
+                    metadata.usage.map(|usage| openai::CompletionUsage {
+                        prompt_tokens: u32::try_from(usage.input_tokens)
+                            .unwrap_or(0),
+                        completion_tokens: u32::try_from(usage.output_tokens)
+                            .unwrap_or(0),
+                        total_tokens: u32::try_from(usage.total_tokens)
+                            .unwrap_or(0),
+                        prompt_tokens_details: None,
+                        completion_tokens_details: None,
+                    });
+            }
+            bedrock::ConverseStreamOutput::MessageStop(message_stop) => {
+                let choice = openai::ChatChoiceStream {
+                    index: 0,
+                    delta: openai::ChatCompletionStreamResponseDelta {
+                        role: None,
+                        content: None,
+                        tool_calls: None,
+                        refusal: None,
+                        #[allow(deprecated)]
+                        function_call: None,
+                    },
+                    finish_reason: finish_reason(&message_stop.stop_reason),
+                    logprobs: None,
+                };
+                choices.push(choice);
             }
-            bedrock::ConverseStreamOutput::ContentBlockStop(_)
-            | bedrock::ConverseStreamOutput::MessageStop(_)
-            | _ => {}
+            bedrock::ConverseStreamOutput::ContentBlockStop(_) | _ => {}
+        }
+        if choices.is_empty() && completion_usage.is_none() {
+            return Ok(None);
         }
 
         Ok(Some(CreateChatCompletionStreamResponse {
@@ -635,11 +655,30 @@ impl
             object: CHAT_COMPLETION_CHUNK_OBJECT.to_string(),
             system_fingerprint: None,
             service_tier: None,
-            usage: Some(completion_usage),
+            usage: completion_usage,
         }))
     }
 }
 
+/// Maps the reason Bedrock stopped generating to an `OpenAI` finish reason.
+fn finish_reason(
+    stop_reason: &aws_sdk_bedrockruntime::types::StopReason,
+) -> Option<async_openai::types::FinishReason> {
+    use async_openai::types::FinishReason;
+    use aws_sdk_bedrockruntime::types::StopReason;
+    match stop_reason {
+        StopReason::EndTurn | StopReason::StopSequence => {
+            Some(FinishReason::Stop)
+        }
+        StopReason::MaxTokens => Some(FinishReason::Length),
+        StopReason::ToolUse => Some(FinishReason::ToolCalls),
+        StopReason::ContentFiltered | StopReason::GuardrailIntervened => {
+            Some(FinishReason::ContentFilter)
+        }
+        _ => None,
+    }
+}
+
 impl
     TryConvertError<
         crate::endpoints::bedrock::converse::ConverseError,
@@ -0,0 +1,94 @@
+use std::num::NonZeroUsize;
+
+use serde_json::Value;
+
+/// Drops the choices of an `OpenAI` formatted response body or stream chunk
+/// whose index is at or past `max`.
+///
+/// Choices without an index are kept or dropped by their position instead.
+/// Bodies without choices, e.g. errors, are returned unchanged.
+pub(super) fn truncate(body: &mut Value, max: NonZeroUsize) {
+    let Some(choices) = body.get_mut("choices").and_then(Value::as_array_mut)
+    else {
+        return;
+    };
+    let mut position = 0;
+    choices.retain(|choice| {
+        let index = choice
+            .get("index")
+            .and_then(Value::as_u64)
+            .and_then(|index| usize::try_from(index).ok())
+            .unwrap_or(position);
+        position += 1;
+        index < max.get()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn truncate_value(body: &Value, max: usize) -> Value {
+        let mut body = body.clone();
+        truncate(&mut body, NonZeroUsize::new(max).unwrap());
+        body
+    }
+
+    fn choice(index: usize) -> Value {
+        json!({
+            "index": index,
+            "message": { "role": "assistant", "content": format!("choice {index}") },
+            "finish_reason": "stop"
+        })
+    }
+
+    #[test]
+    fn choices_are_capped() {
+        let body = json!({
+            "object": "chat.completion",
+            "choices": [choice(0), choice(1), choice(2)]
+        });
+        let truncated = truncate_value(&body, 2);
+        assert_eq!(truncated["choices"], json!([choice(0), choice(1)]));
+        assert_eq!(truncated["object"], "chat.completion");
+    }
+
+    #[test]
+    fn fewer_choices_than_max_are_unchanged() {
+        let body = json!({ "choices": [choice(0)] });
+        assert_eq!(truncate_value(&body, 2), body);
+    }
+
+    #[test]
+    fn stream_chunks_past_max_lose_their_choices() {
+        let chunk = json!({
+            "object": "chat.completion.chunk",
+            "choices": [{ "index": 3, "delta": { "content": "hi" } }]
+        });
+        let truncated = truncate_value(&chunk, 1);
+        assert_eq!(truncated["choices"], json!([]));
+
+        let chunk = json!({
+            "object": "chat.completion.chunk",
+            "choices": [{ "index": 0, "delta": { "content": "hi" } }]
+        });
+        assert_eq!(truncate_value(&chunk, 1), chunk);
+    }
+
+    #[test]
+    fn choices_without_index_are_capped_by_position() {
+        let body = json!({
+            "choices": [{ "text": "a" }, { "text": "b" }, { "text": "c" }]
+        });
+        let truncated = truncate_value(&body, 1);
+        assert_eq!(truncated["choices"], json!([{ "text": "a" }]));
+    }
+
+    #[test]
+    fn bodies_without_choices_are_unchanged() {
+        let body = json!({ "error": { "message": "oops" } });
+        assert_eq!(truncate_value(&body, 1), body);
+    }
+}
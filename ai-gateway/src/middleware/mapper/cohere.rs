@@ -0,0 +1,128 @@
+use std::str::FromStr;
+
+use http::response::Parts;
+
+use super::{
+    TryConvert, TryConvertError, TryConvertStreamData, model::ModelMapper,
+};
+use crate::{
+    endpoints::{
+        cohere::{
+            PROVIDER_NAME,
+            embed::{CohereApiError, EmbedRequest, EmbedResponse, InputType},
+        },
+        openai::embeddings::{
+            CreateEmbeddingRequest, CreateEmbeddingResponse, Embedding,
+            EmbeddingUsage, EmbeddingVector,
+        },
+    },
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+/// Cohere's v3 and newer models require the intended use of the embeddings,
+/// which `OpenAI` requests don't carry, so we assume they're for retrieval.
+const DEFAULT_INPUT_TYPE: InputType = InputType::SearchDocument;
+
+pub struct CohereConverter {
+    model_mapper: ModelMapper,
+}
+
+impl CohereConverter {
+    #[must_use]
+    pub fn new(model_mapper: ModelMapper) -> Self {
+        Self { model_mapper }
+    }
+}
+
+impl TryConvert<CreateEmbeddingRequest, EmbedRequest> for CohereConverter {
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: CreateEmbeddingRequest,
+    ) -> Result<EmbedRequest, Self::Error> {
+        let source_model = ModelId::from_str(&value.model)?;
+        let target_model = self.model_mapper.map_model(
+            &source_model,
+            &InferenceProvider::Named(PROVIDER_NAME.into()),
+        )?;
+        tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
+        let texts = value.input.into_texts().ok_or_else(|| {
+            MapperError::EmbeddingsMappingInvalid(
+                "token id inputs are not supported by Cohere".to_string(),
+            )
+        })?;
+
+        Ok(EmbedRequest {
+            model: target_model.to_string(),
+            texts,
+            input_type: DEFAULT_INPUT_TYPE,
+        })
+    }
+}
+
+impl TryConvert<EmbedResponse, CreateEmbeddingResponse> for CohereConverter {
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: EmbedResponse,
+    ) -> Result<CreateEmbeddingResponse, Self::Error> {
+        let data = value
+            .embeddings
+            .into_iter()
+            .zip(0u32..)
+            .map(|(embedding, index)| Embedding {
+                object: "embedding".to_string(),
+                embedding: EmbeddingVector::Float(embedding),
+                index,
+            })
+            .collect();
+        let tokens = value
+            .meta
+            .and_then(|meta| meta.billed_units)
+            .map_or(0, |billed_units| billed_units.input_tokens);
+
+        Ok(CreateEmbeddingResponse {
+            object: "list".to_string(),
+            data,
+            // cohere doesn't return the model that embedded the texts
+            model: String::new(),
+            usage: EmbeddingUsage {
+                prompt_tokens: tokens,
+                total_tokens: tokens,
+            },
+        })
+    }
+}
+
+impl TryConvertStreamData<EmbedResponse, CreateEmbeddingResponse>
+    for CohereConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_chunk(
+        &self,
+        value: EmbedResponse,
+    ) -> Result<Option<CreateEmbeddingResponse>, Self::Error> {
+        self.try_convert(value).map(Some)
+    }
+}
+
+impl TryConvertError<CohereApiError, async_openai::error::WrappedError>
+    for CohereConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        resp_parts: &Parts,
+        value: CohereApiError,
+    ) -> Result<async_openai::error::WrappedError, Self::Error> {
+        Ok(super::openai_error_from_status(
+            resp_parts.status,
+            Some(value.message),
+        ))
+    }
+}
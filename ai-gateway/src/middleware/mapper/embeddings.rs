@@ -0,0 +1,123 @@
+use base64::Engine;
+use bytes::Bytes;
+use serde_json::Value;
+
+use crate::{
+    endpoints::openai::embeddings::EmbeddingVector, error::mapper::MapperError,
+};
+
+/// Encodes an embedding the way `OpenAI` does for `encoding_format: base64`,
+/// as the base64 encoded little endian bytes of its floats.
+pub(super) fn encode_base64(embedding: &[f32]) -> String {
+    let bytes = embedding
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect::<Vec<_>>();
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Decodes an embedding encoded with [`encode_base64`].
+pub(super) fn decode_base64(encoded: &str) -> Result<Vec<f32>, MapperError> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| MapperError::EmbeddingsMappingInvalid(e.to_string()))?;
+    if bytes.len() % 4 != 0 {
+        return Err(MapperError::EmbeddingsMappingInvalid(format!(
+            "base64 embedding of {} bytes is not an array of floats",
+            bytes.len()
+        )));
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|chunk| {
+            f32::from_le_bytes(chunk.try_into().expect("chunks are 4 bytes"))
+        })
+        .collect())
+}
+
+/// Returns the floats of an embedding, decoding it if it is base64 encoded.
+pub(super) fn into_floats(
+    embedding: EmbeddingVector,
+) -> Result<Vec<f32>, MapperError> {
+    match embedding {
+        EmbeddingVector::Float(embedding) => Ok(embedding),
+        EmbeddingVector::Base64(encoded) => decode_base64(&encoded),
+    }
+}
+
+/// Base64 encodes the float embeddings of a mapped `OpenAI` embeddings
+/// response, for clients that asked for `encoding_format: base64` from a
+/// provider that only returns floats.
+///
+/// Embeddings that are already encoded, and bodies that can't be parsed, are
+/// left unchanged.
+#[allow(clippy::cast_possible_truncation)]
+pub(super) fn encode_response(body: Bytes) -> Bytes {
+    let Ok(mut value) = serde_json::from_slice::<Value>(&body) else {
+        return body;
+    };
+    let Some(data) = value.get_mut("data").and_then(Value::as_array_mut) else {
+        return body;
+    };
+    for item in data {
+        let Some(embedding) = item.get_mut("embedding") else {
+            continue;
+        };
+        let Some(floats) = embedding.as_array().and_then(|values| {
+            values
+                .iter()
+                .map(|value| value.as_f64().map(|value| value as f32))
+                .collect::<Option<Vec<_>>>()
+        }) else {
+            continue;
+        };
+        *embedding = Value::String(encode_base64(&floats));
+    }
+    serde_json::to_vec(&value).map_or(body, Bytes::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn base64_round_trips_floats() {
+        let embedding = vec![0.25, -1.5, 3.0];
+        let encoded = encode_base64(&embedding);
+        assert_eq!(decode_base64(&encoded).unwrap(), embedding);
+    }
+
+    #[test]
+    fn base64_of_partial_floats_is_rejected() {
+        let encoded =
+            base64::engine::general_purpose::STANDARD.encode([0u8; 6]);
+        assert!(matches!(
+            decode_base64(&encoded),
+            Err(MapperError::EmbeddingsMappingInvalid(_))
+        ));
+    }
+
+    #[test]
+    fn response_float_embeddings_are_encoded() {
+        let body = json!({
+            "object": "list",
+            "data": [
+                { "object": "embedding", "embedding": [0.25, -1.5], "index": 0 },
+                { "object": "embedding", "embedding": "AACAPg==", "index": 1 }
+            ],
+            "model": "embed-english-v3.0",
+            "usage": { "prompt_tokens": 2, "total_tokens": 2 }
+        });
+        let body = Bytes::from(serde_json::to_vec(&body).unwrap());
+        let encoded: Value =
+            serde_json::from_slice(&encode_response(body)).unwrap();
+        assert_eq!(
+            encoded["data"][0]["embedding"],
+            json!(encode_base64(&[0.25, -1.5]))
+        );
+        assert_eq!(encoded["data"][1]["embedding"], json!("AACAPg=="));
+        assert_eq!(encoded["usage"]["prompt_tokens"], 2);
+    }
+}
@@ -0,0 +1,65 @@
+use http::HeaderMap;
+
+/// Selects the format of provider errors returned to the client, either
+/// `openai` or `raw`.
+pub(crate) const ERROR_FORMAT_HEADER: &str = "x-helicone-error-format";
+
+/// How error responses from a provider are returned to the client.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ErrorFormat {
+    /// Errors are mapped to the shape of the endpoint the client called,
+    /// e.g. an `OpenAI` error for `/chat/completions`.
+    #[default]
+    OpenAI,
+    /// Errors are returned as the provider sent them, for clients that
+    /// handle provider native errors.
+    Raw,
+}
+
+impl ErrorFormat {
+    /// Returns the format requested with the [`ERROR_FORMAT_HEADER`], or the
+    /// default if it's missing or not recognized.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+        let Some(value) = headers
+            .get(ERROR_FORMAT_HEADER)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return Self::default();
+        };
+        match value.trim().to_ascii_lowercase().as_str() {
+            "openai" => Self::OpenAI,
+            "raw" => Self::Raw,
+            _ => {
+                tracing::warn!(format = %value, "unknown error format");
+                Self::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(value: &'static str) -> ErrorFormat {
+        let mut headers = HeaderMap::new();
+        headers.insert(ERROR_FORMAT_HEADER, value.parse().unwrap());
+        ErrorFormat::from_headers(&headers)
+    }
+
+    #[test]
+    fn parses_requested_format() {
+        assert_eq!(format("raw"), ErrorFormat::Raw);
+        assert_eq!(format(" RAW "), ErrorFormat::Raw);
+        assert_eq!(format("openai"), ErrorFormat::OpenAI);
+    }
+
+    #[test]
+    fn defaults_to_openai() {
+        assert_eq!(format("xml"), ErrorFormat::OpenAI);
+        assert_eq!(
+            ErrorFormat::from_headers(&HeaderMap::new()),
+            ErrorFormat::OpenAI
+        );
+    }
+}
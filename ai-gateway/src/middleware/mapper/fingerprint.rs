@@ -0,0 +1,172 @@
+use std::fmt::Write;
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::Config,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+const FINGERPRINT_PREFIX: &str = "fp_";
+/// Number of hex characters kept from the digest, matching the length of
+/// fingerprints returned by `OpenAI`.
+const FINGERPRINT_HEX_LEN: usize = 10;
+
+/// Digest of the serialized gateway config, computed once so that
+/// fingerprints can be synthesized from it without serializing the config
+/// for every response.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigDigest([u8; 32]);
+
+impl ConfigDigest {
+    #[must_use]
+    pub fn new(config: &Config) -> Self {
+        // serialized through a `Value` so that map keys are in a stable order
+        let serialized = serde_json::to_value(config)
+            .and_then(|value| serde_json::to_vec(&value))
+            .unwrap_or_default();
+        Self(Sha256::digest(&serialized).into())
+    }
+}
+
+/// Synthesizes a `system_fingerprint` for providers that don't return one.
+///
+/// The fingerprint only depends on the provider, the model, and the config
+/// of the gateway, so identical requests against an identically configured
+/// gateway always receive the same fingerprint.
+#[must_use]
+pub fn synthesize(
+    provider: &InferenceProvider,
+    model: Option<&ModelId>,
+    config_digest: ConfigDigest,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(provider.to_string().as_bytes());
+    hasher.update(b"\0");
+    if let Some(model) = model {
+        hasher.update(model.to_string().as_bytes());
+    }
+    hasher.update(b"\0");
+    hasher.update(config_digest.0);
+    let digest = hasher.finalize();
+
+    let mut fingerprint =
+        String::with_capacity(FINGERPRINT_PREFIX.len() + FINGERPRINT_HEX_LEN);
+    fingerprint.push_str(FINGERPRINT_PREFIX);
+    for byte in digest.iter().take(FINGERPRINT_HEX_LEN / 2) {
+        let _ = write!(fingerprint, "{byte:02x}");
+    }
+    fingerprint
+}
+
+/// Sets `system_fingerprint` on an OpenAI-format response body or stream
+/// chunk if the provider did not already return one.
+///
+/// Bodies that are not JSON objects are left unchanged.
+pub fn apply(body: &mut serde_json::Value, fingerprint: &str) {
+    let Some(object) = body.as_object_mut() else {
+        return;
+    };
+    if object
+        .get("system_fingerprint")
+        .is_some_and(|value| !value.is_null())
+    {
+        return;
+    }
+    object.insert(
+        "system_fingerprint".to_string(),
+        serde_json::Value::String(fingerprint.to_string()),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn model(name: &str) -> ModelId {
+        ModelId::from_str(name).unwrap()
+    }
+
+    #[test]
+    fn synthesized_fingerprint_is_stable() {
+        let model = model("anthropic/claude-3-5-sonnet-latest");
+        let digest = ConfigDigest::default();
+        let first =
+            synthesize(&InferenceProvider::Anthropic, Some(&model), digest);
+        let second =
+            synthesize(&InferenceProvider::Anthropic, Some(&model), digest);
+        assert_eq!(first, second);
+        assert!(first.starts_with(FINGERPRINT_PREFIX));
+        assert_eq!(first.len(), FINGERPRINT_PREFIX.len() + FINGERPRINT_HEX_LEN);
+    }
+
+    #[test]
+    fn synthesized_fingerprint_depends_on_provider_and_model() {
+        let sonnet = model("anthropic/claude-3-5-sonnet-latest");
+        let haiku = model("anthropic/claude-3-5-haiku-latest");
+        let digest = ConfigDigest::default();
+        assert_ne!(
+            synthesize(&InferenceProvider::Anthropic, Some(&sonnet), digest),
+            synthesize(&InferenceProvider::Anthropic, Some(&haiku), digest),
+        );
+        assert_ne!(
+            synthesize(&InferenceProvider::Anthropic, Some(&sonnet), digest),
+            synthesize(&InferenceProvider::Ollama, Some(&sonnet), digest),
+        );
+    }
+
+    #[test]
+    fn synthesized_fingerprint_depends_on_config() {
+        let model = model("anthropic/claude-3-5-sonnet-latest");
+        let config = Config::default();
+        let mut other_config = Config::default();
+        other_config.mapper.synthesize_system_fingerprint =
+            !config.mapper.synthesize_system_fingerprint;
+        assert_eq!(ConfigDigest::new(&config), ConfigDigest::new(&config));
+        assert_ne!(
+            synthesize(
+                &InferenceProvider::Anthropic,
+                Some(&model),
+                ConfigDigest::new(&config),
+            ),
+            synthesize(
+                &InferenceProvider::Anthropic,
+                Some(&model),
+                ConfigDigest::new(&other_config),
+            ),
+        );
+    }
+
+    #[test]
+    fn apply_inserts_missing_fingerprint() {
+        let mut body = json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "system_fingerprint": null,
+        });
+        apply(&mut body, "fp_0123456789");
+        assert_eq!(body["system_fingerprint"], "fp_0123456789");
+    }
+
+    #[test]
+    fn apply_passes_through_provider_fingerprint() {
+        let mut body = json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "system_fingerprint": "fp_from_openai",
+        });
+        apply(&mut body, "fp_0123456789");
+        assert_eq!(body["system_fingerprint"], "fp_from_openai");
+    }
+
+    #[test]
+    fn apply_ignores_non_object_bodies() {
+        let mut body = json!(["[DONE]"]);
+        apply(&mut body, "fp_0123456789");
+        assert_eq!(body, json!(["[DONE]"]));
+    }
+}
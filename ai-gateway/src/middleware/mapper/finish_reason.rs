@@ -0,0 +1,90 @@
+use serde_json::Value;
+
+const LENGTH: &str = "length";
+
+/// Rewrites the provider specific finish reasons of an `OpenAI` formatted
+/// response body or stream chunk that signal truncation at the maximum
+/// number of tokens to `length`.
+///
+/// This is done on the raw provider body, since finish reasons that aren't
+/// part of the `OpenAI` API would otherwise fail to deserialize. Bodies
+/// without choices, e.g. those of other formats, are left unchanged.
+///
+/// Returns whether any finish reason was rewritten.
+pub(super) fn normalize_length(
+    body: &mut Value,
+    length_reasons: &[String],
+) -> bool {
+    if length_reasons.is_empty() {
+        return false;
+    }
+    let Some(choices) = body.get_mut("choices").and_then(Value::as_array_mut)
+    else {
+        return false;
+    };
+    let mut changed = false;
+    for choice in choices {
+        let Some(reason) = choice.get_mut("finish_reason") else {
+            continue;
+        };
+        let is_length = reason.as_str().is_some_and(|reason| {
+            reason != LENGTH
+                && length_reasons
+                    .iter()
+                    .any(|length| length.eq_ignore_ascii_case(reason))
+        });
+        if is_length {
+            *reason = Value::String(LENGTH.to_string());
+            changed = true;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn normalize(body: &Value, length_reasons: &[&str]) -> Value {
+        let length_reasons = length_reasons
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let mut body = body.clone();
+        normalize_length(&mut body, &length_reasons);
+        body
+    }
+
+    #[test]
+    fn provider_truncation_reasons_become_length() {
+        let body = json!({
+            "choices": [
+                { "index": 0, "finish_reason": "model_length" },
+                { "index": 1, "finish_reason": "stop" },
+                { "index": 2, "finish_reason": null },
+            ]
+        });
+        let normalized = normalize(&body, &["model_length"]);
+        assert_eq!(normalized["choices"][0]["finish_reason"], "length");
+        assert_eq!(normalized["choices"][1]["finish_reason"], "stop");
+        assert!(normalized["choices"][2]["finish_reason"].is_null());
+    }
+
+    #[test]
+    fn reasons_are_matched_ignoring_case() {
+        let body = json!({ "choices": [{ "finish_reason": "MAX_TOKENS" }] });
+        let normalized = normalize(&body, &["max_tokens"]);
+        assert_eq!(normalized["choices"][0]["finish_reason"], "length");
+    }
+
+    #[test]
+    fn other_bodies_are_unchanged() {
+        let body = json!({ "stop_reason": "max_tokens" });
+        assert_eq!(normalize(&body, &["max_tokens"]), body);
+        let mut body = json!({ "choices": [{ "finish_reason": "stop" }] });
+        let reasons = vec!["max_tokens".to_string()];
+        assert!(!normalize_length(&mut body, &reasons));
+    }
+}
@@ -0,0 +1,294 @@
+use std::io::Cursor;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use image::{ImageFormat, imageops::FilterType};
+use serde_json::Value;
+
+use crate::{
+    config::providers::MappingLimits, error::invalid_req::InvalidRequestError,
+    types::provider::InferenceProvider,
+};
+
+/// Each downscaling step shrinks both dimensions by at least this factor.
+const MAX_DOWNSCALE_FACTOR: f64 = 0.9;
+/// Images that don't fit after this many steps are rejected.
+const MAX_DOWNSCALE_STEPS: usize = 8;
+
+/// Enforces the image limits of the target provider on an `OpenAI` chat
+/// completion request, downscaling oversized base64 encoded images if
+/// enabled.
+///
+/// The body is returned as is if no image limits are configured, or if it
+/// fails to deserialize, which is left for the converter to reject.
+pub(super) fn enforce_limits(
+    limits: MappingLimits,
+    provider: &InferenceProvider,
+    body: Bytes,
+) -> Result<Bytes, InvalidRequestError> {
+    if limits.max_images.is_none() && limits.max_image_bytes.is_none() {
+        return Ok(body);
+    }
+    let Ok(mut request) = serde_json::from_slice::<Value>(&body) else {
+        return Ok(body);
+    };
+
+    let mut image_urls = image_urls(&mut request);
+    if let Some(max_images) = limits.max_images
+        && image_urls.len() > max_images
+    {
+        return Err(InvalidRequestError::TooManyImages(
+            max_images,
+            provider.clone(),
+        ));
+    }
+    let Some(max_image_bytes) = limits.max_image_bytes else {
+        return Ok(body);
+    };
+
+    let mut downscaled = false;
+    for url in &mut image_urls {
+        let Some((media_type, data)) = parse_data_url(url) else {
+            continue;
+        };
+        if decoded_len(data) <= max_image_bytes {
+            continue;
+        }
+        let too_large = || {
+            InvalidRequestError::ImageTooLarge(
+                max_image_bytes,
+                provider.clone(),
+            )
+        };
+        if !limits.downscale_images {
+            return Err(too_large());
+        }
+        let downscaled_url =
+            downscale(data, max_image_bytes).ok_or_else(too_large)?;
+        tracing::debug!(
+            media_type,
+            original_bytes = decoded_len(data),
+            "downscaled oversized image"
+        );
+        **url = downscaled_url;
+        downscaled = true;
+    }
+
+    if !downscaled {
+        return Ok(body);
+    }
+    Ok(serde_json::to_vec(&request)?.into())
+}
+
+/// The URLs of all `image_url` content parts in the request's messages.
+fn image_urls(request: &mut Value) -> Vec<&mut String> {
+    let Some(messages) =
+        request.get_mut("messages").and_then(Value::as_array_mut)
+    else {
+        return Vec::new();
+    };
+    messages
+        .iter_mut()
+        .filter_map(|message| {
+            message.get_mut("content").and_then(Value::as_array_mut)
+        })
+        .flatten()
+        .filter(|part| {
+            part.get("type").and_then(Value::as_str) == Some("image_url")
+        })
+        .filter_map(|part| match part.pointer_mut("/image_url/url") {
+            Some(Value::String(url)) => Some(url),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Splits a base64 encoded data URL into its media type and data.
+fn parse_data_url(url: &str) -> Option<(&str, &str)> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let media_type = header.strip_suffix(";base64")?;
+    Some((media_type, data))
+}
+
+/// The size of base64 encoded data once decoded.
+fn decoded_len(data: &str) -> usize {
+    let padding = data.bytes().rev().take_while(|b| *b == b'=').count();
+    (data.len() / 4 * 3).saturating_sub(padding)
+}
+
+/// Downscales a base64 encoded image until it is at most `max_bytes` once
+/// encoded, returning it as a data URL.
+///
+/// JPEG images are re-encoded as JPEG, and other formats as PNG. Returns
+/// `None` if the image can't be decoded or doesn't fit in a few steps.
+fn downscale(data: &str, max_bytes: usize) -> Option<String> {
+    let bytes = STANDARD.decode(data).ok()?;
+    let (format, media_type) = match image::guess_format(&bytes).ok()? {
+        ImageFormat::Jpeg => (ImageFormat::Jpeg, "image/jpeg"),
+        _ => (ImageFormat::Png, "image/png"),
+    };
+    let mut image = image::load_from_memory(&bytes).ok()?;
+    let mut encoded_len = bytes.len();
+    for _ in 0..MAX_DOWNSCALE_STEPS {
+        // the encoded size is roughly proportional to the number of pixels
+        #[allow(clippy::cast_precision_loss)]
+        let factor = (max_bytes as f64 / encoded_len as f64)
+            .sqrt()
+            .min(MAX_DOWNSCALE_FACTOR);
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss
+        )]
+        let scale =
+            |dimension: u32| ((f64::from(dimension) * factor) as u32).max(1);
+        image = image.resize(
+            scale(image.width()),
+            scale(image.height()),
+            FilterType::Triangle,
+        );
+        let mut encoded = Cursor::new(Vec::new());
+        image.write_to(&mut encoded, format).ok()?;
+        let encoded = encoded.into_inner();
+        if encoded.len() <= max_bytes {
+            return Some(format!(
+                "data:{media_type};base64,{}",
+                STANDARD.encode(encoded)
+            ));
+        }
+        encoded_len = encoded.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, RgbImage};
+    use serde_json::json;
+
+    use super::*;
+
+    /// A PNG data URL of a noisy image, which doesn't compress well.
+    fn png_data_url(width: u32, height: u32) -> String {
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            let seed = x.wrapping_mul(31).wrapping_add(y.wrapping_mul(17));
+            let [r, g, b, _] = seed.wrapping_mul(2_654_435_761).to_le_bytes();
+            image::Rgb([r, g, b])
+        });
+        let mut encoded = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut encoded, ImageFormat::Png)
+            .unwrap();
+        format!(
+            "data:image/png;base64,{}",
+            STANDARD.encode(encoded.into_inner())
+        )
+    }
+
+    fn request(urls: &[&str]) -> Bytes {
+        let content = urls
+            .iter()
+            .map(|url| json!({ "type": "image_url", "image_url": { "url": url } }))
+            .chain([json!({ "type": "text", "text": "What's this?" })])
+            .collect::<Vec<_>>();
+        serde_json::to_vec(&json!({
+            "model": "anthropic/claude-3-5-sonnet-latest",
+            "messages": [{ "role": "user", "content": content }]
+        }))
+        .unwrap()
+        .into()
+    }
+
+    fn limits(
+        max_images: Option<usize>,
+        max_image_bytes: Option<usize>,
+        downscale_images: bool,
+    ) -> MappingLimits {
+        MappingLimits {
+            max_images,
+            max_image_bytes,
+            downscale_images,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn too_many_images_are_rejected() {
+        let body = request(&[
+            "https://example.com/a.png",
+            "https://example.com/b.png",
+            "https://example.com/c.png",
+        ]);
+        let result = enforce_limits(
+            limits(Some(2), None, false),
+            &InferenceProvider::Anthropic,
+            body.clone(),
+        );
+        assert!(matches!(
+            result,
+            Err(InvalidRequestError::TooManyImages(2, _))
+        ));
+
+        let result = enforce_limits(
+            limits(Some(3), None, false),
+            &InferenceProvider::Anthropic,
+            body.clone(),
+        );
+        assert_eq!(result.unwrap(), body);
+    }
+
+    #[test]
+    fn oversized_images_are_rejected_unless_downscaled() {
+        let url = png_data_url(256, 256);
+        let (_, data) = parse_data_url(&url).unwrap();
+        let max_image_bytes = decoded_len(data) / 2;
+        let body = request(&[&url, "https://example.com/remote.png"]);
+
+        let result = enforce_limits(
+            limits(None, Some(max_image_bytes), false),
+            &InferenceProvider::Anthropic,
+            body.clone(),
+        );
+        assert!(matches!(
+            result,
+            Err(InvalidRequestError::ImageTooLarge(_, _))
+        ));
+
+        let downscaled = enforce_limits(
+            limits(None, Some(max_image_bytes), true),
+            &InferenceProvider::Anthropic,
+            body,
+        )
+        .unwrap();
+        let mut downscaled =
+            serde_json::from_slice::<Value>(&downscaled).unwrap();
+        let urls = image_urls(&mut downscaled);
+        let (media_type, data) = parse_data_url(urls[0]).unwrap();
+        assert_eq!(media_type, "image/png");
+        assert!(decoded_len(data) <= max_image_bytes);
+        let image =
+            image::load_from_memory(&STANDARD.decode(data).unwrap()).unwrap();
+        assert!(image.width() < 256 && image.height() < 256);
+        // remote images are left as is
+        assert_eq!(*urls[1], "https://example.com/remote.png");
+    }
+
+    #[test]
+    fn images_within_limits_are_unchanged() {
+        let url = png_data_url(16, 16);
+        let body = request(&[&url]);
+        let result = enforce_limits(
+            limits(Some(1), Some(1024 * 1024), true),
+            &InferenceProvider::Anthropic,
+            body.clone(),
+        );
+        assert_eq!(result.unwrap(), body);
+    }
+
+    #[test]
+    fn decoded_len_accounts_for_padding() {
+        assert_eq!(decoded_len(&STANDARD.encode(b"a")), 1);
+        assert_eq!(decoded_len(&STANDARD.encode(b"ab")), 2);
+        assert_eq!(decoded_len(&STANDARD.encode(b"abc")), 3);
+    }
+}
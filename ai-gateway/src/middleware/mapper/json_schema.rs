@@ -0,0 +1,377 @@
+use serde_json::Value;
+
+/// How deeply schemas are followed, through nested values, references and
+/// `anyOf`, before validation gives up, so that neither a deeply nested
+/// schema nor a deeply nested response can exhaust the stack.
+const MAX_DEPTH: usize = 64;
+
+/// Returns the schema of an `OpenAI` chat completion request that asks for
+/// `json_schema` structured output.
+///
+/// Streaming requests are never considered, since their responses can't be
+/// inspected before they are sent to the client.
+pub(super) fn requested_schema(body: &[u8]) -> Option<Value> {
+    let mut value = serde_json::from_slice::<Value>(body).ok()?;
+    if value.get("stream").and_then(Value::as_bool) == Some(true) {
+        return None;
+    }
+    let response_format = value.get_mut("response_format")?;
+    if response_format.get("type").and_then(Value::as_str)
+        != Some("json_schema")
+    {
+        return None;
+    }
+    response_format
+        .pointer_mut("/json_schema/schema")
+        .map(Value::take)
+}
+
+/// Describes the first way in which the content of an `OpenAI` chat
+/// completion response doesn't match `schema`, if any.
+///
+/// Refusals and choices without content are not considered violations.
+pub(super) fn violation(body: &[u8], schema: &Value) -> Option<String> {
+    let Ok(value) = serde_json::from_slice::<Value>(body) else {
+        return Some("response is not valid JSON".to_string());
+    };
+    let choices = value.get("choices").and_then(Value::as_array)?;
+    choices.iter().enumerate().find_map(|(idx, choice)| {
+        let content = choice.pointer("/message/content")?.as_str()?;
+        match serde_json::from_str::<Value>(content) {
+            Ok(content) => validate(schema, schema, &content, "$")
+                .err()
+                .map(|error| format!("choice {idx}: {error}")),
+            Err(_) => Some(format!("choice {idx}: content is not valid JSON")),
+        }
+    })
+}
+
+/// Validates `value` against the subset of JSON schema supported by
+/// structured outputs.
+///
+/// Keywords outside of that subset are ignored.
+fn validate(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    path: &str,
+) -> Result<(), String> {
+    validate_at(root, schema, value, path, &mut Vec::new(), 0)
+}
+
+/// Validates `value` against `schema`, where `refs` are the references
+/// already followed for this same `value`. Following one of them again
+/// would never reach another value, so it's a cycle.
+fn validate_at<'a>(
+    root: &'a Value,
+    schema: &'a Value,
+    value: &Value,
+    path: &str,
+    refs: &mut Vec<&'a str>,
+    depth: usize,
+) -> Result<(), String> {
+    if depth > MAX_DEPTH {
+        return Err(format!("{path} is nested too deeply to validate"));
+    }
+    let depth = depth + 1;
+    let Some(schema) = schema.as_object() else {
+        // `true` and `{}` accept anything, `false` accepts nothing
+        return if schema.as_bool() == Some(false) {
+            Err(format!("{path} is not allowed"))
+        } else {
+            Ok(())
+        };
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        if refs.contains(&reference) {
+            return Err(format!("circular reference {reference}"));
+        }
+        let target = reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+            .ok_or_else(|| format!("unresolvable reference {reference}"))?;
+        refs.push(reference);
+        let result = validate_at(root, target, value, path, refs, depth);
+        refs.pop();
+        result?;
+    }
+
+    if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array)
+        && !any_of.iter().any(|schema| {
+            validate_at(root, schema, value, path, refs, depth).is_ok()
+        })
+    {
+        return Err(format!(
+            "{path} does not match any of the allowed schemas"
+        ));
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+        && !allowed.contains(value)
+    {
+        return Err(format!("{path} is not one of the allowed values"));
+    }
+
+    if let Some(constant) = schema.get("const")
+        && constant != value
+    {
+        return Err(format!("{path} does not equal {constant}"));
+    }
+
+    if let Some(types) = schema.get("type") {
+        let matches = match types {
+            Value::String(ty) => has_type(value, ty),
+            Value::Array(types) => types
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|ty| has_type(value, ty)),
+            _ => true,
+        };
+        if !matches {
+            return Err(format!("{path} is not of type {types}"));
+        }
+    }
+
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(required) = schema.get("required").and_then(Value::as_array)
+        {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    return Err(format!("{path} is missing property {key}"));
+                }
+            }
+        }
+        for (key, property) in object {
+            let property_path = format!("{path}.{key}");
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(property_schema) => validate_at(
+                    root,
+                    property_schema,
+                    property,
+                    &property_path,
+                    &mut Vec::new(),
+                    depth,
+                )?,
+                None => {
+                    if let Some(additional) = schema.get("additionalProperties")
+                    {
+                        validate_at(
+                            root,
+                            additional,
+                            property,
+                            &property_path,
+                            &mut Vec::new(),
+                            depth,
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) =
+        (value, schema.get("items"))
+    {
+        for (idx, item) in items.iter().enumerate() {
+            validate_at(
+                root,
+                item_schema,
+                item,
+                &format!("{path}[{idx}]"),
+                &mut Vec::new(),
+                depth,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64()
+                || value.is_u64()
+                || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "city": { "type": "string" },
+                "temperature": { "type": "number" },
+                "unit": { "$ref": "#/$defs/unit" },
+                "tags": { "type": "array", "items": { "type": "string" } },
+            },
+            "required": ["city", "temperature", "unit"],
+            "additionalProperties": false,
+            "$defs": {
+                "unit": { "type": "string", "enum": ["celsius", "fahrenheit"] },
+            },
+        })
+    }
+
+    fn response(content: &str) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content }
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn only_non_streaming_json_schema_requests_are_validated() {
+        let request = |response_format: Value, stream: bool| {
+            serde_json::to_vec(&json!({
+                "model": "openai/gpt-4o-mini",
+                "messages": [{ "role": "user", "content": "Weather?" }],
+                "response_format": response_format,
+                "stream": stream,
+            }))
+            .unwrap()
+        };
+        let json_schema = json!({
+            "type": "json_schema",
+            "json_schema": { "name": "weather", "schema": schema() },
+        });
+        assert_eq!(
+            requested_schema(&request(json_schema.clone(), false)),
+            Some(schema())
+        );
+        assert!(requested_schema(&request(json_schema, true)).is_none());
+        assert!(
+            requested_schema(&request(json!({ "type": "json_object" }), false))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn matching_content_is_valid() {
+        let content = r#"{"city":"Paris","temperature":21.5,"unit":"celsius","tags":["sunny"]}"#;
+        assert_eq!(violation(&response(content), &schema()), None);
+    }
+
+    #[test]
+    fn violations_are_described() {
+        let cases = [
+            (
+                "It's sunny in Paris.",
+                "choice 0: content is not valid JSON",
+            ),
+            (
+                r#"{"city":"Paris","unit":"celsius"}"#,
+                "choice 0: $ is missing property temperature",
+            ),
+            (
+                r#"{"city":"Paris","temperature":"warm","unit":"celsius"}"#,
+                r#"choice 0: $.temperature is not of type "number""#,
+            ),
+            (
+                r#"{"city":"Paris","temperature":21,"unit":"kelvin"}"#,
+                "choice 0: $.unit is not one of the allowed values",
+            ),
+            (
+                r#"{"city":"Paris","temperature":21,"unit":"celsius","tags":[1]}"#,
+                r#"choice 0: $.tags[0] is not of type "string""#,
+            ),
+            (
+                r#"{"city":"Paris","temperature":21,"unit":"celsius","wind":3}"#,
+                "choice 0: $.wind is not allowed",
+            ),
+        ];
+        for (content, expected) in cases {
+            assert_eq!(
+                violation(&response(content), &schema()).as_deref(),
+                Some(expected),
+                "{content}"
+            );
+        }
+    }
+
+    #[test]
+    fn any_of_accepts_any_matching_schema() {
+        let schema = json!({
+            "anyOf": [{ "type": "string" }, { "type": "null" }],
+        });
+        assert!(validate(&schema, &schema, &json!(null), "$").is_ok());
+        assert!(validate(&schema, &schema, &json!("a"), "$").is_ok());
+        assert!(validate(&schema, &schema, &json!(1), "$").is_err());
+    }
+
+    #[test]
+    fn circular_references_are_errors() {
+        let self_referencing = json!({ "$ref": "#" });
+        assert_eq!(
+            validate(&self_referencing, &self_referencing, &json!(1), "$"),
+            Err("circular reference #".to_string())
+        );
+
+        let mutually_referencing = json!({
+            "$ref": "#/$defs/a",
+            "$defs": {
+                "a": { "$ref": "#/$defs/b" },
+                "b": { "anyOf": [{ "$ref": "#/$defs/a" }] },
+            },
+        });
+        assert!(
+            validate(
+                &mutually_referencing,
+                &mutually_referencing,
+                &json!(1),
+                "$"
+            )
+            .is_err()
+        );
+        assert!(violation(&response("1"), &mutually_referencing).is_some());
+    }
+
+    #[test]
+    fn recursive_schemas_validate_nested_values() {
+        let schema = json!({
+            "$ref": "#/$defs/node",
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": {
+                        "children": {
+                            "type": "array",
+                            "items": { "$ref": "#/$defs/node" },
+                        },
+                    },
+                },
+            },
+        });
+        let tree = json!({ "children": [{ "children": [] }] });
+        assert!(validate(&schema, &schema, &tree, "$").is_ok());
+
+        let mut deep = json!({ "children": [] });
+        for _ in 0..MAX_DEPTH {
+            deep = json!({ "children": [deep] });
+        }
+        assert_eq!(
+            validate(&schema, &schema, &deep, "$")
+                .map_err(|e| e.contains("nested too deeply")),
+            Err(true)
+        );
+    }
+}
@@ -0,0 +1,508 @@
+use std::str::FromStr;
+
+use async_openai::types as openai;
+use http::response::Parts;
+use serde_json::Value;
+
+use super::{
+    TryConvert, TryConvertError, TryConvertStreamData,
+    anthropic::OPENAI_CHAT_COMPLETION_OBJECT, drop_prediction,
+    model::ModelMapper, openai_error_from_status, requested_max_tokens,
+};
+use crate::{
+    endpoints::mistral::chat_completions::{
+        ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse,
+        Content, ContentChunk, FunctionCall, Message, MistralApiError,
+        OpenAIChatCompletionRequest, ToolCall, ToolChoice, ToolChoiceMode,
+        Usage,
+    },
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+const CHAT_COMPLETION_CHUNK_OBJECT: &str = "chat.completion.chunk";
+
+pub struct MistralConverter {
+    provider: InferenceProvider,
+    model_mapper: ModelMapper,
+}
+
+impl MistralConverter {
+    #[must_use]
+    pub fn new(model_mapper: ModelMapper) -> Self {
+        Self {
+            provider: InferenceProvider::Named("mistral".into()),
+            model_mapper,
+        }
+    }
+}
+
+impl TryConvert<OpenAIChatCompletionRequest, ChatCompletionRequest>
+    for MistralConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: OpenAIChatCompletionRequest,
+    ) -> Result<ChatCompletionRequest, Self::Error> {
+        let OpenAIChatCompletionRequest {
+            inner: mut value,
+            safe_prompt,
+            prefix_messages,
+        } = value;
+        let source_model = ModelId::from_str(&value.model)?;
+        let target_model =
+            self.model_mapper.map_model(&source_model, &self.provider)?;
+        tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
+        drop_prediction(&mut value, &self.provider);
+        let max_tokens = requested_max_tokens(&value);
+
+        let messages = value
+            .messages
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, message)| {
+                map_message(message, prefix_messages.contains(&index))
+            })
+            .collect();
+        let tool_choice =
+            value.tool_choice.map(|tool_choice| match tool_choice {
+                openai::ChatCompletionToolChoiceOption::None => {
+                    ToolChoice::Mode(ToolChoiceMode::None)
+                }
+                openai::ChatCompletionToolChoiceOption::Auto => {
+                    ToolChoice::Mode(ToolChoiceMode::Auto)
+                }
+                openai::ChatCompletionToolChoiceOption::Required => {
+                    ToolChoice::Mode(ToolChoiceMode::Any)
+                }
+                openai::ChatCompletionToolChoiceOption::Named(tool) => {
+                    ToolChoice::Named(tool)
+                }
+            });
+
+        Ok(ChatCompletionRequest {
+            model: target_model.to_string(),
+            messages,
+            temperature: value.temperature,
+            top_p: value.top_p,
+            max_tokens,
+            stream: value.stream,
+            stop: value.stop,
+            random_seed: value.seed,
+            response_format: value.response_format,
+            tools: value.tools,
+            tool_choice,
+            presence_penalty: value.presence_penalty,
+            frequency_penalty: value.frequency_penalty,
+            n: value.n,
+            parallel_tool_calls: value.parallel_tool_calls,
+            safe_prompt,
+        })
+    }
+}
+
+/// Returns `None` for messages that Mistral has no equivalent for, i.e.
+/// the deprecated function messages.
+fn map_message(
+    message: openai::ChatCompletionRequestMessage,
+    prefix: bool,
+) -> Option<Message> {
+    let message = match message {
+        openai::ChatCompletionRequestMessage::Developer(message) => {
+            let content = match message.content {
+                openai::ChatCompletionRequestDeveloperMessageContent::Text(
+                    text,
+                ) => Content::Text(text),
+                openai::ChatCompletionRequestDeveloperMessageContent::Array(
+                    parts,
+                ) => Content::Chunks(
+                    parts
+                        .into_iter()
+                        .map(|part| ContentChunk::Text { text: part.text })
+                        .collect(),
+                ),
+            };
+            Message::System { content }
+        }
+        openai::ChatCompletionRequestMessage::System(message) => {
+            let content = match message.content {
+                openai::ChatCompletionRequestSystemMessageContent::Text(
+                    text,
+                ) => Content::Text(text),
+                openai::ChatCompletionRequestSystemMessageContent::Array(
+                    parts,
+                ) => Content::Chunks(
+                    parts
+                        .into_iter()
+                        .map(|part| match part {
+                            openai::ChatCompletionRequestSystemMessageContentPart::Text(text) => {
+                                ContentChunk::Text { text: text.text }
+                            }
+                        })
+                        .collect(),
+                ),
+            };
+            Message::System { content }
+        }
+        openai::ChatCompletionRequestMessage::User(message) => {
+            let content = match message.content {
+                openai::ChatCompletionRequestUserMessageContent::Text(text) => {
+                    Content::Text(text)
+                }
+                openai::ChatCompletionRequestUserMessageContent::Array(
+                    parts,
+                ) => Content::Chunks(
+                    parts
+                        .into_iter()
+                        .filter_map(|part| match part {
+                            openai::ChatCompletionRequestUserMessageContentPart::Text(text) => {
+                                Some(ContentChunk::Text { text: text.text })
+                            }
+                            openai::ChatCompletionRequestUserMessageContentPart::ImageUrl(image) => {
+                                Some(ContentChunk::ImageUrl {
+                                    image_url: image.image_url.url,
+                                })
+                            }
+                            // Mistral chat models don't accept audio
+                            openai::ChatCompletionRequestUserMessageContentPart::InputAudio(_) => None,
+                        })
+                        .collect(),
+                ),
+            };
+            Message::User { content }
+        }
+        openai::ChatCompletionRequestMessage::Assistant(message) => {
+            let content = message.content.map(|content| match content {
+                openai::ChatCompletionRequestAssistantMessageContent::Text(
+                    text,
+                ) => Content::Text(text),
+                openai::ChatCompletionRequestAssistantMessageContent::Array(
+                    parts,
+                ) => Content::Chunks(
+                    parts
+                        .into_iter()
+                        .map(|part| match part {
+                            openai::ChatCompletionRequestAssistantMessageContentPart::Text(text) => {
+                                ContentChunk::Text { text: text.text }
+                            }
+                            openai::ChatCompletionRequestAssistantMessageContentPart::Refusal(refusal) => {
+                                ContentChunk::Text { text: refusal.refusal }
+                            }
+                        })
+                        .collect(),
+                ),
+            });
+            let tool_calls = message.tool_calls.map(|tool_calls| {
+                tool_calls
+                    .into_iter()
+                    .map(|tool_call| ToolCall {
+                        id: Some(tool_call.id),
+                        function: FunctionCall {
+                            name: tool_call.function.name,
+                            arguments: Value::String(
+                                tool_call.function.arguments,
+                            ),
+                        },
+                        index: None,
+                    })
+                    .collect()
+            });
+            Message::Assistant {
+                content,
+                tool_calls,
+                prefix,
+            }
+        }
+        openai::ChatCompletionRequestMessage::Tool(message) => {
+            let content = match message.content {
+                openai::ChatCompletionRequestToolMessageContent::Text(text) => {
+                    Content::Text(text)
+                }
+                openai::ChatCompletionRequestToolMessageContent::Array(
+                    parts,
+                ) => Content::Chunks(
+                    parts
+                        .into_iter()
+                        .map(|part| match part {
+                            openai::ChatCompletionRequestToolMessageContentPart::Text(text) => {
+                                ContentChunk::Text { text: text.text }
+                            }
+                        })
+                        .collect(),
+                ),
+            };
+            Message::Tool {
+                content,
+                tool_call_id: message.tool_call_id,
+                name: None,
+            }
+        }
+        openai::ChatCompletionRequestMessage::Function(message) => {
+            tracing::debug!(name = %message.name, "dropping function message not supported by mistral");
+            return None;
+        }
+    };
+    Some(message)
+}
+
+/// The text of the content, without chunks that have no `OpenAI`
+/// equivalent.
+fn content_text(content: Content) -> Option<String> {
+    match content {
+        Content::Text(text) => Some(text),
+        Content::Chunks(chunks) => {
+            let text = chunks
+                .into_iter()
+                .filter_map(|chunk| match chunk {
+                    ContentChunk::Text { text } => Some(text),
+                    ContentChunk::ImageUrl { .. } | ContentChunk::Other => None,
+                })
+                .collect::<Vec<_>>();
+            (!text.is_empty()).then(|| text.concat())
+        }
+    }
+}
+
+fn arguments(arguments: Value) -> String {
+    match arguments {
+        Value::String(arguments) => arguments,
+        arguments => arguments.to_string(),
+    }
+}
+
+fn finish_reason(reason: Option<&str>) -> Option<openai::FinishReason> {
+    match reason? {
+        "stop" => Some(openai::FinishReason::Stop),
+        "length" | "model_length" => Some(openai::FinishReason::Length),
+        "tool_calls" => Some(openai::FinishReason::ToolCalls),
+        _ => None,
+    }
+}
+
+fn usage(usage: Usage) -> openai::CompletionUsage {
+    openai::CompletionUsage {
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        total_tokens: usage.total_tokens,
+        prompt_tokens_details: None,
+        completion_tokens_details: None,
+    }
+}
+
+impl TryConvert<ChatCompletionResponse, openai::CreateChatCompletionResponse>
+    for MistralConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: ChatCompletionResponse,
+    ) -> Result<openai::CreateChatCompletionResponse, Self::Error> {
+        let choices = value
+            .choices
+            .into_iter()
+            .map(|choice| {
+                let tool_calls = choice.message.tool_calls.map(|tool_calls| {
+                    tool_calls
+                        .into_iter()
+                        .map(|tool_call| {
+                            openai::ChatCompletionMessageToolCall {
+                                id: tool_call.id.unwrap_or_default(),
+                                r#type:
+                                    openai::ChatCompletionToolType::Function,
+                                function: openai::FunctionCall {
+                                    name: tool_call.function.name,
+                                    arguments: arguments(
+                                        tool_call.function.arguments,
+                                    ),
+                                },
+                            }
+                        })
+                        .collect()
+                });
+                #[allow(deprecated)]
+                let message = openai::ChatCompletionResponseMessage {
+                    content: choice.message.content.and_then(content_text),
+                    refusal: None,
+                    tool_calls,
+                    role: openai::Role::Assistant,
+                    function_call: None,
+                    audio: None,
+                };
+                openai::ChatChoice {
+                    index: choice.index,
+                    message,
+                    finish_reason: finish_reason(
+                        choice.finish_reason.as_deref(),
+                    ),
+                    logprobs: None,
+                }
+            })
+            .collect();
+
+        Ok(openai::CreateChatCompletionResponse {
+            id: value.id,
+            choices,
+            created: value.created,
+            model: value.model,
+            object: OPENAI_CHAT_COMPLETION_OBJECT.to_string(),
+            usage: value.usage.map(usage),
+            service_tier: None,
+            system_fingerprint: None,
+        })
+    }
+}
+
+impl
+    TryConvertStreamData<
+        ChatCompletionChunk,
+        openai::CreateChatCompletionStreamResponse,
+    > for MistralConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_chunk(
+        &self,
+        value: ChatCompletionChunk,
+    ) -> Result<Option<openai::CreateChatCompletionStreamResponse>, Self::Error>
+    {
+        let choices = value
+            .choices
+            .into_iter()
+            .map(|choice| {
+                let tool_calls = choice.delta.tool_calls.map(|tool_calls| {
+                    tool_calls
+                        .into_iter()
+                        .zip(0u32..)
+                        .map(|(tool_call, position)| {
+                            openai::ChatCompletionMessageToolCallChunk {
+                                index: tool_call.index.unwrap_or(position),
+                                id: tool_call.id,
+                                r#type: Some(
+                                    openai::ChatCompletionToolType::Function,
+                                ),
+                                function: Some(openai::FunctionCallStream {
+                                    name: Some(tool_call.function.name),
+                                    arguments: Some(arguments(
+                                        tool_call.function.arguments,
+                                    )),
+                                }),
+                            }
+                        })
+                        .collect()
+                });
+                #[allow(deprecated)]
+                let delta = openai::ChatCompletionStreamResponseDelta {
+                    role: choice
+                        .delta
+                        .role
+                        .filter(|role| role == "assistant")
+                        .map(|_| openai::Role::Assistant),
+                    content: choice.delta.content.and_then(content_text),
+                    tool_calls,
+                    refusal: None,
+                    function_call: None,
+                };
+                openai::ChatChoiceStream {
+                    index: choice.index,
+                    delta,
+                    finish_reason: finish_reason(
+                        choice.finish_reason.as_deref(),
+                    ),
+                    logprobs: None,
+                }
+            })
+            .collect();
+
+        Ok(Some(openai::CreateChatCompletionStreamResponse {
+            id: value.id,
+            choices,
+            created: value.created,
+            model: value.model,
+            object: CHAT_COMPLETION_CHUNK_OBJECT.to_string(),
+            system_fingerprint: None,
+            service_tier: None,
+            usage: value.usage.map(usage),
+        }))
+    }
+}
+
+/// Formats validation details, a list of `{ "loc": [..], "msg": ".." }`
+/// objects, as `loc: msg` pairs.
+fn validation_message(detail: &Value) -> String {
+    let Some(details) = detail.as_array() else {
+        return detail.to_string();
+    };
+    details
+        .iter()
+        .map(|detail| {
+            let msg = detail
+                .get("msg")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let loc = detail.get("loc").and_then(Value::as_array).map(|loc| {
+                loc.iter()
+                    .map(|part| match part {
+                        Value::String(part) => part.clone(),
+                        part => part.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(".")
+            });
+            match loc {
+                Some(loc) => format!("{loc}: {msg}"),
+                None => msg.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl TryConvertError<MistralApiError, async_openai::error::WrappedError>
+    for MistralConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        resp_parts: &Parts,
+        value: MistralApiError,
+    ) -> Result<async_openai::error::WrappedError, Self::Error> {
+        let (message, code) = match value {
+            MistralApiError::Error {
+                message,
+                kind,
+                code,
+            } => {
+                let message = match message {
+                    Value::String(message) => message,
+                    Value::Object(ref object) => {
+                        object.get("detail").map_or_else(
+                            || message.to_string(),
+                            validation_message,
+                        )
+                    }
+                    message => message.to_string(),
+                };
+                let code = match code {
+                    Some(Value::String(code)) => Some(code),
+                    Some(Value::Number(code)) => Some(code.to_string()),
+                    _ => kind,
+                };
+                (message, code)
+            }
+            MistralApiError::Validation { detail } => {
+                (validation_message(&detail), None)
+            }
+            MistralApiError::Other(value) => (value.to_string(), None),
+        };
+        let mut error =
+            openai_error_from_status(resp_parts.status, Some(message));
+        if code.is_some() {
+            error.error.code = code;
+        }
+        Ok(error)
+    }
+}
@@ -1,11 +1,33 @@
 pub mod anthropic;
 mod bedrock;
+mod choices;
+pub mod cohere;
+mod embeddings;
+mod error_format;
+pub mod fingerprint;
+mod finish_reason;
+mod images;
+mod json_schema;
+pub mod mistral;
 pub mod model;
 pub mod ollama;
 pub mod openai;
 pub mod openai_compatible;
+mod prompt_cache;
+mod reasoning;
+mod redaction;
 pub mod registry;
+mod role_alternation;
+mod seed;
 pub mod service;
+mod sse;
+mod streaming;
+mod tenant;
+mod tool_choice;
+mod usage;
+mod usage_chunk;
+mod validation;
+pub mod voyage;
 
 use async_openai::error::WrappedError;
 use base64::Engine;
@@ -20,11 +42,42 @@ use crate::{
         api::ApiError, internal::InternalError,
         invalid_req::InvalidRequestError, mapper::MapperError,
     },
-    types::extensions::MapperContext,
+    types::{extensions::MapperContext, provider::InferenceProvider},
 };
 
 pub(crate) const DEFAULT_MAX_TOKENS: u32 = 2000;
 
+/// Returns the output token limit requested by the client, which may be set
+/// with either `max_completion_tokens` or the deprecated `max_tokens`.
+#[allow(deprecated)]
+pub(crate) fn requested_max_tokens(
+    request: &async_openai::types::CreateChatCompletionRequest,
+) -> Option<u32> {
+    request.max_completion_tokens.or(request.max_tokens)
+}
+
+/// Moves the requested output token limit into `max_tokens`, since
+/// `max_completion_tokens` is not supported by most `OpenAI` compatible
+/// providers.
+#[allow(deprecated)]
+pub(crate) fn set_legacy_max_tokens(
+    request: &mut async_openai::types::CreateChatCompletionRequest,
+) {
+    request.max_tokens = requested_max_tokens(request);
+    request.max_completion_tokens = None;
+}
+
+/// Drops the `prediction` of a request for a provider that doesn't support
+/// predicted outputs, which is every provider but `OpenAI`.
+pub(crate) fn drop_prediction(
+    request: &mut async_openai::types::CreateChatCompletionRequest,
+    provider: &InferenceProvider,
+) {
+    if request.prediction.take().is_some() {
+        tracing::debug!(provider = %provider, "dropping predicted output not supported by provider");
+    }
+}
+
 /// `TryFrom` but allows us to implement it for foreign types, so we can
 /// maintain boundaries between our business logic and the provider types.
 pub trait TryConvert<Source, Target>: Sized {
@@ -86,6 +139,7 @@ where
         + TryConvert<T::ResponseBody, S::ResponseBody>,
 {
     converter: C,
+    passthrough_response: bool,
     _phantom: std::marker::PhantomData<(S, T)>,
 }
 
@@ -99,6 +153,18 @@ where
     pub fn new(converter: C) -> Self {
         Self {
             converter,
+            passthrough_response: false,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// For target endpoints that respond in the same format as the source
+    /// endpoint, successful non-streaming response bodies are forwarded as
+    /// is rather than being needlessly deserialized and serialized again.
+    pub fn passthrough(converter: C) -> Self {
+        Self {
+            converter,
+            passthrough_response: true,
             _phantom: std::marker::PhantomData,
         }
     }
@@ -133,6 +199,10 @@ where
         let source_request: S::RequestBody = serde_json::from_slice(&bytes)
             .map_err(InvalidRequestError::InvalidRequestBody)?;
         let is_stream = source_request.is_stream();
+        let reasoning = source_request.reasoning_requested();
+        let base64_embeddings = source_request.base64_embeddings_requested();
+        let seed = source_request.seed();
+        let include_usage = source_request.usage_requested();
         let target_request: T::RequestBody = self
             .converter
             .try_convert(source_request)
@@ -141,7 +211,14 @@ where
             tracing::error!(?e, "failed to get model from request");
         })?;
 
-        let mapper_ctx = MapperContext { is_stream, model: Some(model) };
+        let mapper_ctx = MapperContext {
+            is_stream,
+            model: Some(model),
+            reasoning,
+            base64_embeddings,
+            seed_dropped: seed.is_some() && target_request.seed().is_none(),
+            include_usage,
+        };
         let target_bytes =
             Bytes::from(serde_json::to_vec(&target_request).map_err(|e| {
                 InternalError::Serialize {
@@ -204,6 +281,8 @@ where
             })?;
 
             Ok(Some(Bytes::from(target_bytes)))
+        } else if self.passthrough_response {
+            Ok(Some(bytes))
         } else {
             let source_response: T::ResponseBody =
             serde_json::from_slice(&bytes)
@@ -9,7 +9,10 @@ use super::{TryConvert, TryConvertStreamData};
 use crate::{
     endpoints::ollama::chat_completions::CreateChatCompletionRequestOllama,
     error::mapper::MapperError,
-    middleware::mapper::{TryConvertError, model::ModelMapper},
+    middleware::mapper::{
+        TryConvertError, drop_prediction, model::ModelMapper,
+        set_legacy_max_tokens,
+    },
     types::{model_id::ModelId, provider::InferenceProvider},
 };
 
@@ -42,6 +45,8 @@ impl
         tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
 
         value.model = target_model.to_string();
+        set_legacy_max_tokens(&mut value);
+        drop_prediction(&mut value, &InferenceProvider::Ollama);
 
         Ok(CreateChatCompletionRequestOllama(value))
     }
@@ -4,6 +4,9 @@ use http::{StatusCode, response::Parts};
 
 use super::{TryConvertStreamData, model::ModelMapper};
 use crate::{
+    endpoints::openai::embeddings::{
+        CreateEmbeddingRequest, CreateEmbeddingResponse,
+    },
     error::mapper::MapperError,
     middleware::mapper::{TryConvert, TryConvertError},
     types::{model_id::ModelId, provider::InferenceProvider},
@@ -366,7 +369,20 @@ impl
 
         let choices = &value.choices;
         if choices.is_empty() {
-            return Ok(None);
+            // the last chunk of streams with `include_usage` only has the
+            // usage of the whole stream
+            return Ok(value.usage.map(|usage| {
+                anthropic::StreamEvent::MessageDelta {
+                    delta: anthropic::MessageDeltaContent {
+                        stop_reason: None,
+                        stop_sequence: None,
+                    },
+                    usage: Some(anthropic::StreamUsage {
+                        input_tokens: usage.prompt_tokens,
+                        output_tokens: usage.completion_tokens,
+                    }),
+                }
+            }));
         }
         let first_choice = &choices[0];
         let delta = &first_choice.delta;
@@ -650,6 +666,50 @@ impl
     }
 }
 
+impl TryConvert<CreateEmbeddingRequest, CreateEmbeddingRequest>
+    for OpenAIConverter
+{
+    type Error = MapperError;
+    fn try_convert(
+        &self,
+        mut value: CreateEmbeddingRequest,
+    ) -> Result<CreateEmbeddingRequest, Self::Error> {
+        let source_model = ModelId::from_str(&value.model)?;
+        let target_model = self
+            .model_mapper
+            .map_model(&source_model, &InferenceProvider::OpenAI)?;
+        tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
+        value.model = target_model.to_string();
+
+        Ok(value)
+    }
+}
+
+impl TryConvert<CreateEmbeddingResponse, CreateEmbeddingResponse>
+    for OpenAIConverter
+{
+    type Error = MapperError;
+    fn try_convert(
+        &self,
+        value: CreateEmbeddingResponse,
+    ) -> Result<CreateEmbeddingResponse, Self::Error> {
+        Ok(value)
+    }
+}
+
+impl TryConvertStreamData<CreateEmbeddingResponse, CreateEmbeddingResponse>
+    for OpenAIConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_chunk(
+        &self,
+        value: CreateEmbeddingResponse,
+    ) -> Result<Option<CreateEmbeddingResponse>, Self::Error> {
+        Ok(Some(value))
+    }
+}
+
 pub(super) fn get_error_type(status_code: StatusCode) -> String {
     if status_code == StatusCode::TOO_MANY_REQUESTS {
         "tokens".to_string()
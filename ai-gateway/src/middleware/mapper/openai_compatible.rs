@@ -6,7 +6,9 @@ use super::{TryConvertStreamData, model::ModelMapper};
 use crate::{
     endpoints::openai::OpenAICompatibleChatCompletionRequest,
     error::mapper::MapperError,
-    middleware::mapper::{TryConvert, TryConvertError},
+    middleware::mapper::{
+        TryConvert, TryConvertError, drop_prediction, set_legacy_max_tokens,
+    },
     types::{model_id::ModelId, provider::InferenceProvider},
 };
 
@@ -41,6 +43,8 @@ impl
             self.model_mapper.map_model(&source_model, &self.provider)?;
         tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
         value.model = target_model.to_string();
+        set_legacy_max_tokens(&mut value);
+        drop_prediction(&mut value, &self.provider);
 
         Ok(OpenAICompatibleChatCompletionRequest {
             provider: self.provider.clone(),
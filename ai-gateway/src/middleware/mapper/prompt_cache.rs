@@ -0,0 +1,188 @@
+use bytes::Bytes;
+use http::HeaderMap;
+use serde_json::{Value, json};
+
+/// Marks parts of requests mapped to `Anthropic` as cacheable, as a comma
+/// separated list of `system`, `tools` and `messages`, e.g. `system,tools`.
+pub(crate) const ANTHROPIC_CACHE_HEADER: &str = "helicone-anthropic-cache";
+
+/// The parts of a request that are marked with an ephemeral
+/// `cache_control`, so that `Anthropic` caches the prompt up to and
+/// including them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(super) struct CacheBreakpoints {
+    /// The system prompt.
+    system: bool,
+    /// The last tool definition, which caches all tool definitions.
+    tools: bool,
+    /// The last message, which caches the whole conversation so far.
+    messages: bool,
+}
+
+impl CacheBreakpoints {
+    /// The breakpoints requested with the `helicone-anthropic-cache`
+    /// header, if any.
+    pub(super) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let value = headers
+            .get(ANTHROPIC_CACHE_HEADER)
+            .and_then(|value| value.to_str().ok())?;
+        let mut breakpoints = Self::default();
+        for part in value.split(',') {
+            match part.trim().to_ascii_lowercase().as_str() {
+                "system" => breakpoints.system = true,
+                "tools" => breakpoints.tools = true,
+                "messages" => breakpoints.messages = true,
+                "" => {}
+                _ => {
+                    tracing::warn!(part = %part, "unknown anthropic cache breakpoint");
+                }
+            }
+        }
+        (breakpoints != Self::default()).then_some(breakpoints)
+    }
+
+    /// Adds the `cache_control` markers to a mapped `Anthropic` request
+    /// body, returning `None` if the body has none of the marked parts.
+    pub(super) fn apply(self, body: &Bytes) -> Option<Bytes> {
+        let mut value = serde_json::from_slice::<Value>(body).ok()?;
+        let mut marked = false;
+        if self.system
+            && let Some(system) = value.get_mut("system")
+        {
+            marked |= mark_content(system);
+        }
+        if self.tools
+            && let Some(tool) = value
+                .get_mut("tools")
+                .and_then(Value::as_array_mut)
+                .and_then(|tools| tools.last_mut())
+                .and_then(Value::as_object_mut)
+        {
+            tool.insert("cache_control".to_string(), ephemeral());
+            marked = true;
+        }
+        if self.messages
+            && let Some(content) = value
+                .get_mut("messages")
+                .and_then(Value::as_array_mut)
+                .and_then(|messages| messages.last_mut())
+                .and_then(|message| message.get_mut("content"))
+        {
+            marked |= mark_content(content);
+        }
+        if !marked {
+            return None;
+        }
+        serde_json::to_vec(&value).ok().map(Bytes::from)
+    }
+}
+
+fn ephemeral() -> Value {
+    json!({ "type": "ephemeral" })
+}
+
+/// Marks the last block of `content`, converting text content to a text
+/// block first since only blocks can be marked.
+fn mark_content(content: &mut Value) -> bool {
+    if let Value::String(text) = content {
+        if text.is_empty() {
+            return false;
+        }
+        let text = std::mem::take(text);
+        *content = json!([{ "type": "text", "text": text }]);
+    }
+    let Some(block) = content
+        .as_array_mut()
+        .and_then(|blocks| blocks.last_mut())
+        .and_then(Value::as_object_mut)
+    else {
+        return false;
+    };
+    block.insert("cache_control".to_string(), ephemeral());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn breakpoints(header: &str) -> Option<CacheBreakpoints> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            ANTHROPIC_CACHE_HEADER,
+            HeaderValue::from_str(header).unwrap(),
+        );
+        CacheBreakpoints::from_headers(&headers)
+    }
+
+    fn body() -> Bytes {
+        Bytes::from(
+            serde_json::to_vec(&json!({
+                "model": "claude-3-5-sonnet-latest",
+                "system": "You are a helpful assistant.",
+                "tools": [
+                    { "name": "a", "input_schema": {} },
+                    { "name": "b", "input_schema": {} }
+                ],
+                "messages": [
+                    { "role": "user", "content": "Hello" },
+                    {
+                        "role": "user",
+                        "content": [{ "type": "text", "text": "world" }]
+                    }
+                ]
+            }))
+            .unwrap(),
+        )
+    }
+
+    fn apply(header: &str) -> Value {
+        let marked = breakpoints(header).unwrap().apply(&body()).unwrap();
+        serde_json::from_slice(&marked).unwrap()
+    }
+
+    #[test]
+    fn system_prompt_becomes_a_marked_block() {
+        let marked = apply("system");
+        assert_eq!(
+            marked["system"],
+            json!([{
+                "type": "text",
+                "text": "You are a helpful assistant.",
+                "cache_control": { "type": "ephemeral" }
+            }])
+        );
+        assert!(marked["tools"][1].get("cache_control").is_none());
+        assert!(
+            marked["messages"][1]["content"][0]
+                .get("cache_control")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn last_tool_and_message_are_marked() {
+        let marked = apply(" Tools , messages ");
+        assert!(marked["tools"][0].get("cache_control").is_none());
+        assert_eq!(marked["tools"][1]["cache_control"], ephemeral());
+        assert_eq!(marked["system"], "You are a helpful assistant.");
+        assert_eq!(
+            marked["messages"][1]["content"][0]["cache_control"],
+            ephemeral()
+        );
+    }
+
+    #[test]
+    fn unknown_breakpoints_are_ignored() {
+        assert_eq!(breakpoints("everything"), None);
+        assert_eq!(breakpoints(""), None);
+    }
+
+    #[test]
+    fn bodies_without_marked_parts_are_unchanged() {
+        let body = Bytes::from_static(br#"{"messages":[]}"#);
+        assert_eq!(breakpoints("system,messages").unwrap().apply(&body), None);
+    }
+}
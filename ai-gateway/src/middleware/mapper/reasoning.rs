@@ -0,0 +1,195 @@
+use serde_json::{Value, json};
+
+use crate::endpoints::ApiEndpoint;
+
+/// The field reasoning is surfaced in on `OpenAI` formatted responses, as
+/// used by `OpenAI` compatible providers that return reasoning.
+const REASONING_CONTENT: &str = "reasoning_content";
+const CHAT_COMPLETION_CHUNK_OBJECT: &str = "chat.completion.chunk";
+
+/// Extracts reasoning or thinking content from a raw provider response body
+/// or stream chunk, before it is mapped to the `OpenAI` format and any
+/// reasoning is lost.
+pub(super) fn extract(
+    provider_endpoint: &ApiEndpoint,
+    value: &Value,
+    is_stream: bool,
+) -> Option<String> {
+    let reasoning = match (provider_endpoint, is_stream) {
+        (ApiEndpoint::Anthropic(_), false) => {
+            let blocks = value.get("content")?.as_array()?;
+            let thinking = blocks
+                .iter()
+                .filter(|block| {
+                    block.get("type").and_then(Value::as_str)
+                        == Some("thinking")
+                })
+                .filter_map(|block| block.get("thinking")?.as_str())
+                .collect::<Vec<_>>();
+            (!thinking.is_empty()).then(|| thinking.join("\n"))
+        }
+        (ApiEndpoint::Anthropic(_), true) => {
+            let delta = value.get("delta")?;
+            if delta.get("type").and_then(Value::as_str)
+                != Some("thinking_delta")
+            {
+                return None;
+            }
+            delta.get("thinking")?.as_str().map(ToString::to_string)
+        }
+        (ApiEndpoint::Bedrock(_), false) => {
+            let blocks =
+                value.pointer("/output/message/content")?.as_array()?;
+            let reasoning = blocks
+                .iter()
+                .filter_map(|block| {
+                    block
+                        .pointer("/reasoningContent/reasoningText/text")?
+                        .as_str()
+                })
+                .collect::<Vec<_>>();
+            (!reasoning.is_empty()).then(|| reasoning.join("\n"))
+        }
+        // bedrock streams are decoded from the AWS event stream format
+        // before reaching the mapper, so reasoning is not available here
+        (ApiEndpoint::Bedrock(_), true) => None,
+        (_, is_stream) => {
+            let field = if is_stream { "delta" } else { "message" };
+            let message = value.pointer(&format!("/choices/0/{field}"))?;
+            message
+                .get(REASONING_CONTENT)
+                .or_else(|| message.get("reasoning"))?
+                .as_str()
+                .map(ToString::to_string)
+        }
+    };
+    reasoning.filter(|reasoning| !reasoning.is_empty())
+}
+
+/// Sets `reasoning_content` on the first choice of a mapped `OpenAI`
+/// response body or stream chunk.
+///
+/// Stream chunks which only carried reasoning have no mapped equivalent, so
+/// a new chunk is created for them.
+pub(super) fn apply(
+    mapped: Option<Value>,
+    reasoning: String,
+    is_stream: bool,
+) -> Option<Value> {
+    let Some(mut mapped) = mapped else {
+        if !is_stream {
+            return None;
+        }
+        return Some(json!({
+            "id": "",
+            "object": CHAT_COMPLETION_CHUNK_OBJECT,
+            "created": 0,
+            "model": "",
+            "choices": [{
+                "index": 0,
+                "delta": { REASONING_CONTENT: reasoning },
+                "finish_reason": null,
+            }],
+        }));
+    };
+
+    let field = if is_stream { "delta" } else { "message" };
+    if let Some(message) = mapped
+        .pointer_mut(&format!("/choices/0/{field}"))
+        .and_then(Value::as_object_mut)
+        && message.get(REASONING_CONTENT).is_none_or(Value::is_null)
+    {
+        message.insert(REASONING_CONTENT.to_string(), Value::String(reasoning));
+    }
+    Some(mapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoints::{anthropic::Anthropic, openai::OpenAI};
+
+    fn anthropic() -> ApiEndpoint {
+        ApiEndpoint::Anthropic(Anthropic::messages())
+    }
+
+    #[test]
+    fn anthropic_thinking_is_surfaced_in_response() {
+        let provider_body = json!({
+            "id": "msg_123",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                { "type": "thinking", "thinking": "2 + 2 is 4", "signature": "sig" },
+                { "type": "text", "text": "The answer is 4." }
+            ]
+        });
+        let reasoning = extract(&anthropic(), &provider_body, false).unwrap();
+        assert_eq!(reasoning, "2 + 2 is 4");
+
+        let mapped = json!({
+            "id": "msg_123",
+            "object": "chat.completion",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "The answer is 4." }
+            }]
+        });
+        let mapped = apply(Some(mapped), reasoning, false).unwrap();
+        assert_eq!(
+            mapped["choices"][0]["message"]["reasoning_content"],
+            "2 + 2 is 4"
+        );
+        assert_eq!(
+            mapped["choices"][0]["message"]["content"],
+            "The answer is 4."
+        );
+    }
+
+    #[test]
+    fn anthropic_thinking_delta_becomes_reasoning_chunk() {
+        let provider_chunk = json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": { "type": "thinking_delta", "thinking": "Let me think" }
+        });
+        let reasoning = extract(&anthropic(), &provider_chunk, true).unwrap();
+
+        // thinking deltas have no mapped OpenAI chunk
+        let chunk = apply(None, reasoning, true).unwrap();
+        assert_eq!(chunk["object"], CHAT_COMPLETION_CHUNK_OBJECT);
+        assert_eq!(
+            chunk["choices"][0]["delta"]["reasoning_content"],
+            "Let me think"
+        );
+    }
+
+    #[test]
+    fn text_delta_has_no_reasoning() {
+        let provider_chunk = json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": { "type": "text_delta", "text": "Hello" }
+        });
+        assert!(extract(&anthropic(), &provider_chunk, true).is_none());
+    }
+
+    #[test]
+    fn openai_compatible_reasoning_is_preserved() {
+        let provider_body = json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "4",
+                    "reasoning_content": "2 + 2 is 4"
+                }
+            }]
+        });
+        let endpoint = ApiEndpoint::OpenAI(OpenAI::chat_completions());
+        let reasoning = extract(&endpoint, &provider_body, false).unwrap();
+        assert_eq!(reasoning, "2 + 2 is 4");
+    }
+}
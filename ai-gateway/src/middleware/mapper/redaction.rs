@@ -0,0 +1,303 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use http::HeaderMap;
+use regex::Regex;
+use serde_json::{Map, Value, json};
+
+/// Comma separated list of patterns to redact from a streaming response,
+/// e.g. `email,phone`.
+pub(crate) const STREAM_REDACT_HEADER: &str = "helicone-stream-redact";
+const REDACTED: &str = "[REDACTED]";
+/// Maximum length of text held back waiting for a pattern to complete.
+///
+/// None of the supported patterns can match more than this, so once the
+/// held back text grows past it there is nothing left to wait for.
+const MAX_HOLD_BACK: usize = 256;
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}")
+        .expect("always valid if tests pass")
+});
+static PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\+?\d[\d.-]{6,}\d").expect("always valid if tests pass")
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pattern {
+    Email,
+    Phone,
+}
+
+impl Pattern {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "email" => Some(Self::Email),
+            "phone" => Some(Self::Phone),
+            _ => None,
+        }
+    }
+
+    fn regex(self) -> &'static Regex {
+        match self {
+            Self::Email => &EMAIL,
+            Self::Phone => &PHONE,
+        }
+    }
+}
+
+/// Redacts patterns from the content of a stream of `OpenAI` chat
+/// completion chunks.
+///
+/// A pattern may be split across several chunks, so the trailing word of
+/// each choice's content is held back until it's complete, i.e. until more
+/// content is streamed after it, the choice finishes, or the stream ends and
+/// it is [flushed](Self::flush). None of the
+/// supported patterns span whitespace, so the text that is emitted can be
+/// redacted on its own.
+#[derive(Debug)]
+pub(crate) struct StreamRedactor {
+    patterns: Vec<Pattern>,
+    /// Content held back per choice index.
+    pending: HashMap<u64, String>,
+    /// Top level fields of the first chunk, reused for the flushed chunk.
+    template: Option<Map<String, Value>>,
+}
+
+impl StreamRedactor {
+    /// Returns a redactor for the patterns requested with the
+    /// [`STREAM_REDACT_HEADER`], if any.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let value = headers.get(STREAM_REDACT_HEADER)?.to_str().ok()?;
+        let mut patterns = Vec::new();
+        for name in value.split(',').filter(|name| !name.trim().is_empty()) {
+            match Pattern::parse(name) {
+                Some(pattern) if !patterns.contains(&pattern) => {
+                    patterns.push(pattern);
+                }
+                Some(_) => {}
+                None => {
+                    tracing::warn!(pattern = %name, "unknown redaction pattern");
+                }
+            }
+        }
+        if patterns.is_empty() {
+            return None;
+        }
+        Some(Self {
+            patterns,
+            pending: HashMap::new(),
+            template: None,
+        })
+    }
+
+    /// Redacts the content of a single stream chunk.
+    ///
+    /// Chunks without choices are left unchanged.
+    pub(crate) fn apply(&mut self, chunk: &mut Value) {
+        if self.template.is_none()
+            && let Some(fields) = chunk.as_object()
+            && fields.contains_key("choices")
+        {
+            self.template = Some(
+                fields
+                    .iter()
+                    .filter(|(key, _)| *key != "choices" && *key != "usage")
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            );
+        }
+        let Some(choices) =
+            chunk.get_mut("choices").and_then(Value::as_array_mut)
+        else {
+            return;
+        };
+        for choice in choices {
+            let index = choice.get("index").and_then(Value::as_u64);
+            let finished = choice
+                .get("finish_reason")
+                .is_some_and(|reason| !reason.is_null());
+            let Some(delta) = choice.get_mut("delta") else {
+                continue;
+            };
+            let content = delta.get("content").and_then(Value::as_str);
+            if content.is_none() && !finished {
+                continue;
+            }
+            let redacted = self.push(
+                index.unwrap_or_default(),
+                content.unwrap_or_default(),
+                finished,
+            );
+            if content.is_some() || !redacted.is_empty() {
+                delta["content"] = Value::String(redacted);
+            }
+        }
+    }
+
+    /// Appends `content` to the text held back for a choice, and returns the
+    /// redacted text that is ready to be emitted.
+    fn push(&mut self, index: u64, content: &str, finished: bool) -> String {
+        let pending = self.pending.entry(index).or_default();
+        pending.push_str(content);
+        let ready = if finished {
+            std::mem::take(pending)
+        } else {
+            let split = hold_back_start(pending);
+            pending.drain(..split).collect()
+        };
+        self.redact(ready)
+    }
+
+    /// Returns a final chunk with the redacted content that is still held
+    /// back, if any.
+    ///
+    /// Held back content is otherwise only emitted when its choice finishes,
+    /// so this must be called at the end of the stream in case the provider
+    /// ended it without a finish reason.
+    pub(crate) fn flush(&mut self) -> Option<Value> {
+        let mut pending = self
+            .pending
+            .drain()
+            .filter(|(_, content)| !content.is_empty())
+            .collect::<Vec<_>>();
+        if pending.is_empty() {
+            return None;
+        }
+        pending.sort_unstable_by_key(|(index, _)| *index);
+        let choices = pending
+            .into_iter()
+            .map(|(index, content)| {
+                json!({
+                    "index": index,
+                    "delta": { "content": self.redact(content) },
+                    "finish_reason": null,
+                })
+            })
+            .collect();
+        let mut chunk = self.template.take().unwrap_or_default();
+        chunk.insert("choices".to_string(), Value::Array(choices));
+        Some(Value::Object(chunk))
+    }
+
+    fn redact(&self, text: String) -> String {
+        self.patterns.iter().fold(text, |text, pattern| {
+            pattern.regex().replace_all(&text, REDACTED).into_owned()
+        })
+    }
+}
+
+/// Returns the start of the trailing word of `text`, which may still be
+/// part of a pattern that hasn't been fully streamed yet.
+fn hold_back_start(text: &str) -> usize {
+    let start = text
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(idx, c)| idx + c.len_utf8());
+    if text.len() - start > MAX_HOLD_BACK {
+        text.len()
+    } else {
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn redactor(patterns: &'static str) -> StreamRedactor {
+        let mut headers = HeaderMap::new();
+        headers.insert(STREAM_REDACT_HEADER, patterns.parse().unwrap());
+        StreamRedactor::from_headers(&headers).unwrap()
+    }
+
+    fn chunk(content: &str, finish_reason: Option<&str>) -> Value {
+        json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion.chunk",
+            "choices": [{
+                "index": 0,
+                "delta": { "content": content },
+                "finish_reason": finish_reason,
+            }],
+        })
+    }
+
+    /// Redacts the chunk, returning its redacted content.
+    fn redact(redactor: &mut StreamRedactor, mut chunk: Value) -> String {
+        redactor.apply(&mut chunk);
+        chunk["choices"][0]["delta"]["content"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn pattern_split_across_chunks_is_redacted() {
+        let mut redactor = redactor("email");
+        let streamed = [
+            chunk("Contact me at jane.doe@exa", None),
+            chunk("mple.com for details", None),
+            chunk(".", Some("stop")),
+        ]
+        .into_iter()
+        .map(|chunk| redact(&mut redactor, chunk))
+        .collect::<Vec<_>>();
+
+        assert_eq!(streamed.concat(), "Contact me at [REDACTED] for details.");
+        assert!(streamed.iter().all(|content| !content.contains("jane")));
+        assert_eq!(streamed[0], "Contact me at ");
+    }
+
+    #[test]
+    fn held_back_content_is_flushed_when_choice_finishes() {
+        let mut redactor = redactor("email, phone");
+        let first = redact(&mut redactor, chunk("Call +1-555-01", None));
+        let last = redact(&mut redactor, chunk("23-4567", Some("stop")));
+        assert_eq!(first, "Call ");
+        assert_eq!(last, "[REDACTED]");
+    }
+
+    #[test]
+    fn held_back_content_is_flushed_at_end_of_stream() {
+        let mut redactor = redactor("email");
+        let first = redact(&mut redactor, chunk("Mail jane.doe@exa", None));
+        let second = redact(&mut redactor, chunk("mple.com", None));
+        let flushed = redactor.flush().unwrap();
+        assert_eq!(first, "Mail ");
+        assert_eq!(second, "");
+        assert_eq!(flushed["id"], "chatcmpl-123");
+        assert_eq!(flushed["choices"][0]["delta"]["content"], "[REDACTED]");
+        assert!(flushed["choices"][0]["finish_reason"].is_null());
+        assert!(redactor.flush().is_none());
+    }
+
+    #[test]
+    fn long_words_are_not_held_back_indefinitely() {
+        let mut redactor = redactor("email");
+        let word = "a".repeat(MAX_HOLD_BACK + 1);
+        assert_eq!(redact(&mut redactor, chunk(&word, None)), word);
+    }
+
+    #[test]
+    fn unknown_patterns_are_ignored() {
+        let mut headers = HeaderMap::new();
+        headers.insert(STREAM_REDACT_HEADER, "ssn".parse().unwrap());
+        assert!(StreamRedactor::from_headers(&headers).is_none());
+        assert!(StreamRedactor::from_headers(&HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn chunks_without_choices_are_unchanged() {
+        let mut redactor = redactor("email");
+        let mut chunk =
+            json!({ "error": { "message": "jane.doe@example.com" } });
+        redactor.apply(&mut chunk);
+        assert_eq!(
+            chunk,
+            json!({ "error": { "message": "jane.doe@example.com" } })
+        );
+    }
+}
@@ -4,13 +4,16 @@ use rustc_hash::FxHashMap as HashMap;
 
 use super::{
     EndpointConverter, TypedEndpointConverter, anthropic::AnthropicConverter,
-    model::ModelMapper, openai::OpenAIConverter,
-    openai_compatible::OpenAICompatibleConverter,
+    cohere::CohereConverter, mistral::MistralConverter, model::ModelMapper,
+    openai::OpenAIConverter, openai_compatible::OpenAICompatibleConverter,
+    voyage::VoyageConverter,
 };
 use crate::{
+    dispatcher::{azure_client, vertex_client},
     endpoints::{
         self, ApiEndpoint, anthropic::Anthropic, bedrock::Bedrock,
-        google::Google, ollama::Ollama, openai::OpenAI,
+        cohere::Cohere, google::Google, ollama::Ollama, openai::OpenAI,
+        voyage::Voyage,
     },
     middleware::mapper::{bedrock::BedrockConverter, ollama::OllamaConverter},
     types::provider::InferenceProvider,
@@ -59,8 +62,6 @@ impl RegistryKey {
 
 #[derive(Default)]
 struct EndpointConverterRegistryInner {
-    /// In the future when we support other APIs beside just chat completion
-    /// we'll want to add another level here.
     converters: HashMap<
         RegistryKey,
         Box<dyn EndpointConverter + Send + Sync + 'static>,
@@ -102,34 +103,78 @@ impl EndpointConverterRegistryInner {
             endpoints::openai::ChatCompletions,
             endpoints::google::GenerateContents,
             OpenAICompatibleConverter,
-        >::new(OpenAICompatibleConverter::new(
+        >::passthrough(OpenAICompatibleConverter::new(
             InferenceProvider::GoogleGemini,
             model_mapper.clone(),
         ));
         registry.register_converter(key, converter);
 
+        // vertex serves gemini through the same `OpenAI` compatible API as
+        // the gemini api
         let key = RegistryKey::new(
             ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+            ApiEndpoint::OpenAICompatible {
+                provider: InferenceProvider::Named(
+                    vertex_client::PROVIDER_NAME.into(),
+                ),
+                openai_endpoint: OpenAI::chat_completions(),
+            },
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::openai::ChatCompletions,
+            endpoints::openai::OpenAICompatibleChatCompletions,
+            OpenAICompatibleConverter,
+        >::passthrough(OpenAICompatibleConverter::new(
+            InferenceProvider::Named(vertex_client::PROVIDER_NAME.into()),
+            model_mapper.clone(),
+        ));
+        registry.register_converter(key, converter);
+
+        // azure openai accepts the same request bodies as openai, only
+        // its urls and authentication differ
+        let key = RegistryKey::new(
             ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+            ApiEndpoint::OpenAICompatible {
+                provider: InferenceProvider::Named(
+                    azure_client::PROVIDER_NAME.into(),
+                ),
+                openai_endpoint: OpenAI::chat_completions(),
+            },
         );
-        let converter =
-            TypedEndpointConverter::<
-                endpoints::openai::ChatCompletions,
-                endpoints::openai::ChatCompletions,
-                OpenAIConverter,
-            >::new(OpenAIConverter::new(model_mapper.clone()));
+        let converter = TypedEndpointConverter::<
+            endpoints::openai::ChatCompletions,
+            endpoints::openai::OpenAICompatibleChatCompletions,
+            OpenAICompatibleConverter,
+        >::passthrough(OpenAICompatibleConverter::new(
+            InferenceProvider::Named(azure_client::PROVIDER_NAME.into()),
+            model_mapper.clone(),
+        ));
+        registry.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+            ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::openai::ChatCompletions,
+            endpoints::openai::ChatCompletions,
+            OpenAIConverter,
+        >::passthrough(OpenAIConverter::new(
+            model_mapper.clone(),
+        ));
         registry.register_converter(key, converter);
 
         let key = RegistryKey::new(
             ApiEndpoint::OpenAI(OpenAI::chat_completions()),
             ApiEndpoint::Ollama(Ollama::chat_completions()),
         );
-        let converter =
-            TypedEndpointConverter::<
-                endpoints::openai::ChatCompletions,
-                endpoints::ollama::chat_completions::ChatCompletions,
-                OllamaConverter,
-            >::new(OllamaConverter::new(model_mapper.clone()));
+        let converter = TypedEndpointConverter::<
+            endpoints::openai::ChatCompletions,
+            endpoints::ollama::chat_completions::ChatCompletions,
+            OllamaConverter,
+        >::passthrough(OllamaConverter::new(
+            model_mapper.clone(),
+        ));
         registry.register_converter(key, converter);
 
         let key = RegistryKey::new(
@@ -153,14 +198,12 @@ impl EndpointConverterRegistryInner {
                 openai_endpoint: OpenAI::chat_completions(),
             },
         );
-        let converter = TypedEndpointConverter::<
-            endpoints::openai::ChatCompletions,
-            endpoints::openai::OpenAICompatibleChatCompletions,
-            OpenAICompatibleConverter,
-        >::new(OpenAICompatibleConverter::new(
-            InferenceProvider::Named("mistral".into()),
-            model_mapper.clone(),
-        ));
+        let converter =
+            TypedEndpointConverter::<
+                endpoints::mistral::OpenAIChatCompletions,
+                endpoints::mistral::ChatCompletions,
+                MistralConverter,
+            >::new(MistralConverter::new(model_mapper.clone()));
         registry.register_converter(key, converter);
 
         let key = RegistryKey::new(
@@ -174,7 +217,7 @@ impl EndpointConverterRegistryInner {
             endpoints::openai::ChatCompletions,
             endpoints::openai::OpenAICompatibleChatCompletions,
             OpenAICompatibleConverter,
-        >::new(OpenAICompatibleConverter::new(
+        >::passthrough(OpenAICompatibleConverter::new(
             InferenceProvider::Named("groq".into()),
             model_mapper.clone(),
         ));
@@ -191,7 +234,7 @@ impl EndpointConverterRegistryInner {
             endpoints::openai::ChatCompletions,
             endpoints::openai::OpenAICompatibleChatCompletions,
             OpenAICompatibleConverter,
-        >::new(OpenAICompatibleConverter::new(
+        >::passthrough(OpenAICompatibleConverter::new(
             InferenceProvider::Named("deepseek".into()),
             model_mapper.clone(),
         ));
@@ -208,7 +251,7 @@ impl EndpointConverterRegistryInner {
             endpoints::openai::ChatCompletions,
             endpoints::openai::OpenAICompatibleChatCompletions,
             OpenAICompatibleConverter,
-        >::new(OpenAICompatibleConverter::new(
+        >::passthrough(OpenAICompatibleConverter::new(
             InferenceProvider::Named("xai".into()),
             model_mapper.clone(),
         ));
@@ -225,12 +268,49 @@ impl EndpointConverterRegistryInner {
             endpoints::openai::ChatCompletions,
             endpoints::openai::OpenAICompatibleChatCompletions,
             OpenAICompatibleConverter,
-        >::new(OpenAICompatibleConverter::new(
+        >::passthrough(OpenAICompatibleConverter::new(
             InferenceProvider::Named("hyperbolic".into()),
             model_mapper.clone(),
         ));
         registry.register_converter(key, converter);
 
+        let key = RegistryKey::new(
+            ApiEndpoint::OpenAI(OpenAI::embeddings()),
+            ApiEndpoint::OpenAI(OpenAI::embeddings()),
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::openai::Embeddings,
+            endpoints::openai::Embeddings,
+            OpenAIConverter,
+        >::passthrough(OpenAIConverter::new(
+            model_mapper.clone(),
+        ));
+        registry.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::OpenAI(OpenAI::embeddings()),
+            ApiEndpoint::Cohere(Cohere::embed()),
+        );
+        let converter =
+            TypedEndpointConverter::<
+                endpoints::openai::Embeddings,
+                endpoints::cohere::Embed,
+                CohereConverter,
+            >::new(CohereConverter::new(model_mapper.clone()));
+        registry.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::OpenAI(OpenAI::embeddings()),
+            ApiEndpoint::Voyage(Voyage::embeddings()),
+        );
+        let converter =
+            TypedEndpointConverter::<
+                endpoints::openai::Embeddings,
+                endpoints::voyage::Embeddings,
+                VoyageConverter,
+            >::new(VoyageConverter::new(model_mapper.clone()));
+        registry.register_converter(key, converter);
+
         registry
     }
 
@@ -0,0 +1,210 @@
+use bytes::Bytes;
+use serde_json::{Value, json};
+
+use crate::config::mapper::RoleAlternation;
+
+/// Text of the messages inserted between consecutive messages of the same
+/// role, since Anthropic rejects empty text content.
+const PLACEHOLDER_TEXT: &str = "...";
+
+/// Makes the messages of a mapped Anthropic request alternate between the
+/// `user` and `assistant` roles, as Anthropic requires.
+///
+/// Consecutive tool results are always merged into one message, since
+/// Anthropic expects the results of parallel tool calls to be sent together.
+///
+/// Returns `None` if the messages already alternate, or the body can't be
+/// parsed.
+pub(super) fn enforce(
+    handling: RoleAlternation,
+    body: &Bytes,
+) -> Option<Bytes> {
+    if handling == RoleAlternation::Passthrough {
+        return None;
+    }
+    let mut request = serde_json::from_slice::<Value>(body).ok()?;
+    let messages = request.get_mut("messages")?.as_array_mut()?;
+    let mut alternating: Vec<Value> = Vec::with_capacity(messages.len());
+    let mut changed = false;
+    for message in messages.drain(..) {
+        let Some(previous) = alternating.last_mut() else {
+            alternating.push(message);
+            continue;
+        };
+        let role = message.get("role").and_then(Value::as_str);
+        if role.is_none()
+            || role != previous.get("role").and_then(Value::as_str)
+        {
+            alternating.push(message);
+            continue;
+        }
+        changed = true;
+        if handling == RoleAlternation::Merge || is_tool_result(&message) {
+            merge(previous, message);
+        } else {
+            let other_role = if role == Some("user") {
+                "assistant"
+            } else {
+                "user"
+            };
+            alternating.push(json!({
+                "role": other_role,
+                "content": [{ "type": "text", "text": PLACEHOLDER_TEXT }],
+            }));
+            alternating.push(message);
+        }
+    }
+    if !changed {
+        return None;
+    }
+    *messages = alternating;
+    serde_json::to_vec(&request).ok().map(Bytes::from)
+}
+
+/// Whether the message only contains tool results.
+fn is_tool_result(message: &Value) -> bool {
+    message
+        .get("content")
+        .and_then(Value::as_array)
+        .is_some_and(|blocks| {
+            !blocks.is_empty()
+                && blocks.iter().all(|block| {
+                    block.get("type").and_then(Value::as_str)
+                        == Some("tool_result")
+                })
+        })
+}
+
+/// Appends the content blocks of `message` to those of `into`.
+fn merge(into: &mut Value, message: Value) {
+    let mut blocks = content_blocks(into.get_mut("content").map(Value::take));
+    blocks.extend(content_blocks(message.get("content").cloned()));
+    into["content"] = Value::Array(blocks);
+}
+
+/// The content of a message as content blocks, since it can also be a plain
+/// string.
+fn content_blocks(content: Option<Value>) -> Vec<Value> {
+    match content {
+        Some(Value::Array(blocks)) => blocks,
+        Some(Value::String(text)) => {
+            vec![json!({ "type": "text", "text": text })]
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(messages: &Value) -> Bytes {
+        Bytes::from(
+            serde_json::to_vec(&json!({
+                "model": "claude-3-5-sonnet-latest",
+                "max_tokens": 1024,
+                "messages": messages,
+            }))
+            .unwrap(),
+        )
+    }
+
+    fn messages(body: &Bytes) -> Value {
+        let request: Value = serde_json::from_slice(body).unwrap();
+        request["messages"].clone()
+    }
+
+    #[test]
+    fn consecutive_messages_are_merged() {
+        let body = request(&json!([
+            { "role": "user", "content": "Hello" },
+            { "role": "user", "content": [{ "type": "text", "text": "Hi" }] },
+            { "role": "assistant", "content": "Hey" },
+        ]));
+        let body = enforce(RoleAlternation::Merge, &body).unwrap();
+        assert_eq!(
+            messages(&body),
+            json!([
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": "Hello" },
+                        { "type": "text", "text": "Hi" },
+                    ]
+                },
+                { "role": "assistant", "content": "Hey" },
+            ])
+        );
+    }
+
+    #[test]
+    fn placeholder_is_inserted_between_consecutive_messages() {
+        let body = request(&json!([
+            { "role": "user", "content": "Hello" },
+            { "role": "user", "content": "Hi" },
+        ]));
+        let body = enforce(RoleAlternation::Insert, &body).unwrap();
+        assert_eq!(
+            messages(&body),
+            json!([
+                { "role": "user", "content": "Hello" },
+                {
+                    "role": "assistant",
+                    "content": [{ "type": "text", "text": PLACEHOLDER_TEXT }]
+                },
+                { "role": "user", "content": "Hi" },
+            ])
+        );
+    }
+
+    #[test]
+    fn tool_results_are_always_merged() {
+        let tool_result = |id: &str| {
+            json!({
+                "role": "user",
+                "content": [{
+                    "type": "tool_result",
+                    "tool_use_id": id,
+                    "content": "sunny",
+                }]
+            })
+        };
+        let body = request(&json!([
+            { "role": "user", "content": "What's the weather?" },
+            {
+                "role": "assistant",
+                "content": [
+                    { "type": "tool_use", "id": "a", "name": "weather", "input": {} },
+                    { "type": "tool_use", "id": "b", "name": "weather", "input": {} },
+                ]
+            },
+            tool_result("a"),
+            tool_result("b"),
+        ]));
+        let body = enforce(RoleAlternation::Insert, &body).unwrap();
+        let messages = messages(&body);
+        assert_eq!(messages.as_array().unwrap().len(), 3);
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "a");
+        assert_eq!(messages[2]["content"][1]["tool_use_id"], "b");
+    }
+
+    #[test]
+    fn alternating_messages_are_unchanged() {
+        let body = request(&json!([
+            { "role": "user", "content": "Hello" },
+            { "role": "assistant", "content": "Hi" },
+            { "role": "user", "content": "How are you?" },
+        ]));
+        assert!(enforce(RoleAlternation::Merge, &body).is_none());
+        assert!(enforce(RoleAlternation::Insert, &body).is_none());
+    }
+
+    #[test]
+    fn passthrough_leaves_messages_as_is() {
+        let body = request(&json!([
+            { "role": "user", "content": "Hello" },
+            { "role": "user", "content": "Hi" },
+        ]));
+        assert!(enforce(RoleAlternation::Passthrough, &body).is_none());
+    }
+}
@@ -0,0 +1,66 @@
+use http::HeaderName;
+
+use crate::{
+    config::mapper::UnsupportedSeed, error::invalid_req::InvalidRequestError,
+    types::provider::InferenceProvider,
+};
+
+/// Set on responses to requests whose `seed` the provider couldn't honor.
+pub(super) const NONDETERMINISTIC_HEADER: HeaderName =
+    HeaderName::from_static("helicone-nondeterministic");
+
+/// Applies the configured handling of a `seed` that was dropped when the
+/// request was mapped to `provider`'s format.
+///
+/// Returns whether the response should be marked as nondeterministic.
+pub(super) fn enforce(
+    handling: UnsupportedSeed,
+    provider: &InferenceProvider,
+    seed_dropped: bool,
+) -> Result<bool, InvalidRequestError> {
+    if !seed_dropped {
+        return Ok(false);
+    }
+    tracing::debug!(provider = %provider, "seed not supported by provider");
+    match handling {
+        UnsupportedSeed::Passthrough => Ok(false),
+        UnsupportedSeed::Reject => {
+            Err(InvalidRequestError::UnsupportedSeed(provider.clone()))
+        }
+        UnsupportedSeed::Header => Ok(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_without_dropped_seed_are_unaffected() {
+        for handling in [
+            UnsupportedSeed::Passthrough,
+            UnsupportedSeed::Reject,
+            UnsupportedSeed::Header,
+        ] {
+            let marked =
+                enforce(handling, &InferenceProvider::Anthropic, false)
+                    .unwrap();
+            assert!(!marked);
+        }
+    }
+
+    #[test]
+    fn dropped_seed_is_handled_as_configured() {
+        let provider = InferenceProvider::Anthropic;
+        assert!(
+            !enforce(UnsupportedSeed::Passthrough, &provider, true).unwrap()
+        );
+        assert!(enforce(UnsupportedSeed::Header, &provider, true).unwrap());
+        assert!(matches!(
+            enforce(UnsupportedSeed::Reject, &provider, true),
+            Err(InvalidRequestError::UnsupportedSeed(
+                InferenceProvider::Anthropic
+            ))
+        ));
+    }
+}
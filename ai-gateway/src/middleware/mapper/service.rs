@@ -1,23 +1,49 @@
 use std::{
     str::FromStr,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 
-use bytes::{BufMut, BytesMut};
-use futures::{TryStreamExt, future::BoxFuture};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::{StreamExt, TryStreamExt, future::BoxFuture};
 use http::uri::PathAndQuery;
+use serde_json::Value;
 use tracing::{Instrument, info_span};
 
 use crate::{
-    endpoints::ApiEndpoint,
+    config::{
+        mapper::{MapperConfig, RequiredToolChoice, ResponseSchemaValidation},
+        providers::{MappingLimits, StreamingSupport},
+    },
+    endpoints::{ApiEndpoint, openai::OpenAI},
     error::{
-        api::ApiError, internal::InternalError, mapper::MapperError,
+        api::ApiError, internal::InternalError,
+        invalid_req::InvalidRequestError, mapper::MapperError,
         stream::StreamError,
     },
-    middleware::mapper::registry::EndpointConverterRegistry,
+    middleware::mapper::{
+        choices, embeddings,
+        error_format::ErrorFormat,
+        fingerprint::{self, ConfigDigest},
+        finish_reason, images, json_schema,
+        prompt_cache::CacheBreakpoints,
+        reasoning,
+        redaction::StreamRedactor,
+        registry::EndpointConverterRegistry,
+        role_alternation, seed, sse,
+        streaming::{self, StreamConversion},
+        tenant, tool_choice, usage,
+        usage_chunk::UsageChunk,
+        validation::{validate_fields, validate_messages},
+    },
     types::{
-        extensions::MapperContext, provider::InferenceProvider,
-        request::Request, response::Response,
+        extensions::{
+            AppliedTransformations, MapperContext, ProviderErrorType,
+            RequestContext, Transformation,
+        },
+        provider::InferenceProvider,
+        request::Request,
+        response::Response,
     },
 };
 
@@ -25,16 +51,31 @@ use crate::{
 pub struct Service<S> {
     inner: S,
     endpoint_converter_registry: EndpointConverterRegistry,
+    config: MapperConfig,
+    streaming: StreamingSupport,
+    limits: MappingLimits,
+    length_finish_reasons: Arc<[String]>,
+    config_digest: ConfigDigest,
 }
 
 impl<S> Service<S> {
     pub fn new(
         inner: S,
         endpoint_converter_registry: EndpointConverterRegistry,
+        config: MapperConfig,
+        streaming: StreamingSupport,
+        limits: MappingLimits,
+        length_finish_reasons: Arc<[String]>,
+        config_digest: ConfigDigest,
     ) -> Self {
         Self {
             inner,
             endpoint_converter_registry,
+            config,
+            streaming,
+            limits,
+            length_finish_reasons,
+            config_digest,
         }
     }
 }
@@ -67,6 +108,11 @@ where
         // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
         let mut inner = self.inner.clone();
         let converter_registry = self.endpoint_converter_registry.clone();
+        let config = self.config;
+        let streaming = self.streaming;
+        let limits = self.limits;
+        let length_finish_reasons = Arc::clone(&self.length_finish_reasons);
+        let config_digest = self.config_digest;
         std::mem::swap(&mut self.inner, &mut inner);
         Box::pin(async move {
             let target_provider = req
@@ -89,72 +135,573 @@ where
             let source_endpoint = source_endpoint.ok_or(ApiError::Internal(
                 InternalError::ExtensionNotFound("ApiEndpoint"),
             ))?;
-            let source_endpoint_cloned = source_endpoint.clone();
             let target_endpoint =
-                ApiEndpoint::mapped(source_endpoint, &target_provider)?;
-            let target_endpoint_cloned = target_endpoint.clone();
-            // serialization/deserialization should be done on a dedicated
-            // thread
-            let converter_registry_cloned = converter_registry.clone();
-            let source_endpoint_for_req = source_endpoint_cloned.clone();
-            let target_endpoint_for_req = target_endpoint_cloned.clone();
-            let req = tokio::task::spawn_blocking(move || async move {
-                map_request(
-                    converter_registry_cloned,
-                    source_endpoint_for_req,
-                    target_endpoint_for_req,
-                    &extracted_path_and_query,
-                    req,
+                ApiEndpoint::mapped(source_endpoint.clone(), &target_provider)?;
+
+            let mapping_limits = effective_limits(&req, config, limits);
+            // keep a copy of the original request in case it needs to be
+            // retried because a required tool call was missing or the
+            // response didn't match the requested schema
+            let (req, original_req, requires_tool_call, schema) = if (config
+                .required_tool_choice
+                != RequiredToolChoice::Passthrough
+                || config.response_schema_validation
+                    != ResponseSchemaValidation::Passthrough)
+                && matches!(source_endpoint, ApiEndpoint::OpenAI(_))
+            {
+                let max_bytes = mapping_limits.max_request_bytes;
+                let (parts, body) = req.into_parts();
+                let body = collect_limited(body, max_bytes).await?.ok_or_else(
+                    || {
+                        InvalidRequestError::MappedRequestTooLarge(
+                            max_bytes.unwrap_or(0),
+                        )
+                    },
+                )?;
+                let requires_tool_call = config.required_tool_choice
+                    != RequiredToolChoice::Passthrough
+                    && tool_choice::requires_tool_call(&body);
+                let schema = (config.response_schema_validation
+                    != ResponseSchemaValidation::Passthrough)
+                    .then(|| json_schema::requested_schema(&body))
+                    .flatten();
+                let original_req = (requires_tool_call || schema.is_some())
+                    .then(|| (parts.clone(), body.clone()));
+                (
+                    Request::from_parts(parts, body.into()),
+                    original_req,
+                    requires_tool_call,
+                    schema,
                 )
-                .instrument(info_span!("map_request"))
-                .await
-            })
-            .await
-            .map_err(InternalError::MappingTaskError)?
+            } else {
+                (req, None, false, None)
+            };
+
+            let mut retry_inner = inner.clone();
+            let response = map_and_call(
+                &mut inner,
+                &converter_registry,
+                config,
+                streaming,
+                limits,
+                &length_finish_reasons,
+                config_digest,
+                &source_endpoint,
+                &target_endpoint,
+                &extracted_path_and_query,
+                req,
+            )
             .await?;
-            let response = inner.call(req).await?;
-            let response = tokio::task::spawn_blocking(move || async move {
-                map_response(
-                    converter_registry,
-                    target_endpoint_cloned,
-                    source_endpoint_cloned,
+            let Some((parts, body)) = original_req else {
+                return Ok(response);
+            };
+            let response = if requires_tool_call {
+                let (response, has_tool_call) = inspect_tool_calls(
                     response,
+                    mapping_limits.max_response_bytes,
                 )
-                .await
-            })
-            .instrument(info_span!("map_response"))
-            .await
-            .map_err(InternalError::MappingTaskError)?
+                .await?;
+                if has_tool_call || !response.status().is_success() {
+                    response
+                } else if config.required_tool_choice
+                    == RequiredToolChoice::Retry
+                {
+                    tracing::debug!(
+                        provider = %target_provider,
+                        "required tool call missing, retrying"
+                    );
+                    let mut parts = parts.clone();
+                    AppliedTransformations::record(
+                        &mut parts.extensions,
+                        Transformation::ToolInstruction,
+                    );
+                    let req = Request::from_parts(
+                        parts,
+                        tool_choice::with_tool_instruction(body.clone()).into(),
+                    );
+                    // the schema may still need to be retried as well
+                    let mut tool_retry_inner = retry_inner.clone();
+                    tower::ServiceExt::ready(&mut tool_retry_inner).await?;
+                    let response = map_and_call(
+                        &mut tool_retry_inner,
+                        &converter_registry,
+                        config,
+                        streaming,
+                        limits,
+                        &length_finish_reasons,
+                        config_digest,
+                        &source_endpoint,
+                        &target_endpoint,
+                        &extracted_path_and_query,
+                        req,
+                    )
+                    .await?;
+                    let (response, has_tool_call) = inspect_tool_calls(
+                        response,
+                        mapping_limits.max_response_bytes,
+                    )
+                    .await?;
+                    if has_tool_call || !response.status().is_success() {
+                        response
+                    } else {
+                        return Err(InternalError::MapperError(
+                            MapperError::RequiredToolCallMissing(
+                                target_provider,
+                            ),
+                        )
+                        .into());
+                    }
+                } else {
+                    return Err(InternalError::MapperError(
+                        MapperError::RequiredToolCallMissing(target_provider),
+                    )
+                    .into());
+                }
+            } else {
+                response
+            };
+
+            let Some(schema) = schema else {
+                return Ok(response);
+            };
+            let (response, violation) = inspect_schema(
+                response,
+                &schema,
+                mapping_limits.max_response_bytes,
+            )
             .await?;
-            Ok(response)
+            let Some(mut violation) = violation else {
+                return Ok(response);
+            };
+            if config.response_schema_validation
+                == ResponseSchemaValidation::Retry
+            {
+                tracing::debug!(
+                    provider = %target_provider,
+                    violation = %violation,
+                    "response does not match requested schema, retrying"
+                );
+                let req = Request::from_parts(parts, body.into());
+                tower::ServiceExt::ready(&mut retry_inner).await?;
+                let response = map_and_call(
+                    &mut retry_inner,
+                    &converter_registry,
+                    config,
+                    streaming,
+                    limits,
+                    &length_finish_reasons,
+                    config_digest,
+                    &source_endpoint,
+                    &target_endpoint,
+                    &extracted_path_and_query,
+                    req,
+                )
+                .await?;
+                let (response, retry_violation) = inspect_schema(
+                    response,
+                    &schema,
+                    mapping_limits.max_response_bytes,
+                )
+                .await?;
+                let Some(retry_violation) = retry_violation else {
+                    return Ok(response);
+                };
+                violation = retry_violation;
+            }
+            Err(InternalError::MapperError(
+                MapperError::ResponseSchemaViolation(
+                    target_provider,
+                    violation,
+                ),
+            )
+            .into())
         })
     }
 }
 
+/// The lowest of the global, mapper, and router limits on the size of bodies
+/// buffered for mapping.
+fn effective_limits(
+    req: &Request,
+    config: MapperConfig,
+    limits: MappingLimits,
+) -> MappingLimits {
+    let router_max_request_bytes = req
+        .extensions()
+        .get::<Arc<RequestContext>>()
+        .and_then(|ctx| ctx.router_config.as_ref())
+        .and_then(|router_config| router_config.max_request_body_bytes);
+    MappingLimits {
+        max_request_bytes: lowest_limit(&[
+            limits.max_request_bytes,
+            router_max_request_bytes,
+            config.max_request_body_bytes,
+        ]),
+        max_response_bytes: lowest_limit(&[
+            limits.max_response_bytes,
+            config.max_response_body_bytes,
+        ]),
+        ..limits
+    }
+}
+
+/// Maps the request to the target endpoint, calls the inner service, and
+/// maps the response back to the source endpoint.
+///
+/// `OpenAI` requests of a kind the provider doesn't support are sent as the
+/// other kind, and the response is converted back to the kind the client
+/// asked for.
+#[allow(clippy::too_many_arguments)]
+async fn map_and_call<S>(
+    inner: &mut S,
+    converter_registry: &EndpointConverterRegistry,
+    config: MapperConfig,
+    streaming: StreamingSupport,
+    limits: MappingLimits,
+    length_finish_reasons: &Arc<[String]>,
+    config_digest: ConfigDigest,
+    source_endpoint: &ApiEndpoint,
+    target_endpoint: &ApiEndpoint,
+    extracted_path_and_query: &PathAndQuery,
+    req: Request,
+) -> Result<Response, ApiError>
+where
+    S: tower::Service<
+            Request,
+            Response = http::Response<crate::types::body::Body>,
+            Error = ApiError,
+        >,
+{
+    let mut req = req;
+    let limits = effective_limits(&req, config, limits);
+    let redactor = StreamRedactor::from_headers(req.headers());
+    if redactor.is_some() {
+        AppliedTransformations::record(
+            req.extensions_mut(),
+            Transformation::Redaction,
+        );
+    }
+    let error_format = ErrorFormat::from_headers(req.headers());
+    let (req, conversion) = if streaming != StreamingSupport::Both
+        && matches!(
+            source_endpoint,
+            ApiEndpoint::OpenAI(OpenAI::ChatCompletions(_))
+        ) {
+        let (parts, body) = req.into_parts();
+        let max_bytes = limits.max_request_bytes;
+        let body =
+            collect_limited(body, max_bytes).await?.ok_or_else(|| {
+                InvalidRequestError::MappedRequestTooLarge(
+                    max_bytes.unwrap_or(0),
+                )
+            })?;
+        let (body, conversion) = streaming::convert_request(streaming, body);
+        let mut req = Request::from_parts(parts, body.into());
+        if conversion.is_some() {
+            AppliedTransformations::record(
+                req.extensions_mut(),
+                Transformation::StreamingConversion,
+            );
+        }
+        (req, conversion)
+    } else {
+        (req, None)
+    };
+    // serialization/deserialization should be done on a dedicated
+    // thread
+    let converter_registry_cloned = converter_registry.clone();
+    let source_endpoint_for_req = source_endpoint.clone();
+    let target_endpoint_for_req = target_endpoint.clone();
+    let extracted_path_and_query = extracted_path_and_query.clone();
+    let req = tokio::task::spawn_blocking(move || async move {
+        map_request(
+            converter_registry_cloned,
+            config,
+            limits,
+            source_endpoint_for_req,
+            target_endpoint_for_req,
+            &extracted_path_and_query,
+            req,
+        )
+        .instrument(info_span!("map_request"))
+        .await
+    })
+    .await
+    .map_err(InternalError::MappingTaskError)?
+    .await?;
+    let nondeterministic = seed::enforce(
+        config.unsupported_seed,
+        &target_endpoint.provider(),
+        req.extensions()
+            .get::<MapperContext>()
+            .is_some_and(|mapper_ctx| mapper_ctx.seed_dropped),
+    )?;
+    let response = inner.call(req).await?;
+    let converter_registry = converter_registry.clone();
+    let length_finish_reasons = Arc::clone(length_finish_reasons);
+    let source_endpoint = source_endpoint.clone();
+    let target_endpoint = target_endpoint.clone();
+    let response = tokio::task::spawn_blocking(move || async move {
+        map_response(
+            converter_registry,
+            config,
+            limits.max_response_bytes,
+            length_finish_reasons,
+            config_digest,
+            target_endpoint,
+            source_endpoint,
+            redactor,
+            error_format,
+            response,
+        )
+        .await
+    })
+    .instrument(info_span!("map_response"))
+    .await
+    .map_err(InternalError::MappingTaskError)?
+    .await?;
+    let mut response = match conversion {
+        Some(conversion) if response.status().is_success() => {
+            convert_response(response, conversion, limits.max_response_bytes)
+                .await?
+        }
+        _ => response,
+    };
+    if nondeterministic {
+        response.headers_mut().insert(
+            seed::NONDETERMINISTIC_HEADER,
+            http::HeaderValue::from_static("true"),
+        );
+    }
+    Ok(response)
+}
+
+/// Converts a successful mapped `OpenAI` response back to the kind of
+/// response the client asked for.
+async fn convert_response(
+    response: Response,
+    conversion: StreamConversion,
+    max_bytes: Option<usize>,
+) -> Result<Response, ApiError> {
+    let (mut parts, body) = response.into_parts();
+    let body = collect_limited(body, max_bytes).await?.ok_or_else(|| {
+        InternalError::MapperError(MapperError::MappedResponseTooLarge(
+            max_bytes.unwrap_or(0),
+        ))
+    })?;
+    parts.headers.remove(http::header::CONTENT_LENGTH);
+    let (body, content_type, is_stream) = match conversion {
+        StreamConversion::Aggregate => {
+            let body = streaming::aggregate(&body)
+                .ok_or(MapperError::EmptyResponseBody)
+                .map_err(InternalError::MapperError)?;
+            parts.headers.remove(http::header::TRANSFER_ENCODING);
+            (body, "application/json", false)
+        }
+        StreamConversion::Chunk { include_usage } => (
+            streaming::chunk(body, include_usage),
+            "text/event-stream; charset=utf-8",
+            true,
+        ),
+    };
+    parts.headers.insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static(content_type),
+    );
+    if let Some(mapper_ctx) = parts.extensions.get_mut::<MapperContext>() {
+        mapper_ctx.is_stream = is_stream;
+    }
+    Ok(Response::from_parts(parts, body.into()))
+}
+
+/// Buffers the body of a mapped `OpenAI` chat completion response and checks
+/// whether it contains a tool call.
+///
+/// Bodies larger than `max_bytes` are passed through uninspected and treated
+/// as containing a tool call.
+async fn inspect_tool_calls(
+    response: Response,
+    max_bytes: Option<usize>,
+) -> Result<(Response, bool), ApiError> {
+    let (parts, body) = response.into_parts();
+    let body = match collect_or_passthrough(body, max_bytes).await? {
+        Ok(body) => body,
+        Err(body) => {
+            tracing::debug!("response too large to inspect for tool calls");
+            return Ok((Response::from_parts(parts, body), true));
+        }
+    };
+    let has_tool_call = tool_choice::has_tool_call(&body);
+    Ok((Response::from_parts(parts, body.into()), has_tool_call))
+}
+
+/// Buffers the body of a mapped `OpenAI` chat completion response and checks
+/// whether its content matches `schema`.
+///
+/// Unsuccessful responses are never considered violations, and bodies larger
+/// than `max_bytes` are passed through uninspected.
+async fn inspect_schema(
+    response: Response,
+    schema: &Value,
+    max_bytes: Option<usize>,
+) -> Result<(Response, Option<String>), ApiError> {
+    let (parts, body) = response.into_parts();
+    let body = match collect_or_passthrough(body, max_bytes).await? {
+        Ok(body) => body,
+        Err(body) => {
+            tracing::debug!("response too large to validate against schema");
+            return Ok((Response::from_parts(parts, body), None));
+        }
+    };
+    let violation = if parts.status.is_success() {
+        json_schema::violation(&body, schema)
+    } else {
+        None
+    };
+    Ok((Response::from_parts(parts, body.into()), violation))
+}
+
+/// The lowest of the configured body size limits, if any is set.
+fn lowest_limit(limits: &[Option<usize>]) -> Option<usize> {
+    limits.iter().flatten().copied().min()
+}
+
+/// Buffers a body to be mapped.
+///
+/// Returns `None` if the body is larger than `max_bytes`, without buffering
+/// more than `max_bytes` of it.
+async fn collect_limited(
+    body: axum_core::body::Body,
+    max_bytes: Option<usize>,
+) -> Result<Option<Bytes>, InternalError> {
+    use http_body_util::{BodyExt, LengthLimitError, Limited};
+    let Some(max_bytes) = max_bytes else {
+        let body = body
+            .collect()
+            .await
+            .map_err(InternalError::CollectBodyError)?;
+        return Ok(Some(body.to_bytes()));
+    };
+    match Limited::new(body, max_bytes).collect().await {
+        Ok(body) => Ok(Some(body.to_bytes())),
+        Err(e) if e.is::<LengthLimitError>() => Ok(None),
+        Err(e) => match e.downcast::<axum_core::Error>() {
+            Ok(e) => Err(InternalError::CollectBodyError(*e)),
+            Err(e) => Err(InternalError::RequestBodyError(e)),
+        },
+    }
+}
+
+/// Buffers a body to be inspected.
+///
+/// If the body is larger than `max_bytes` it is returned unbuffered instead,
+/// with the part that was already read put back in front of it.
+async fn collect_or_passthrough(
+    body: axum_core::body::Body,
+    max_bytes: Option<usize>,
+) -> Result<Result<Bytes, axum_core::body::Body>, InternalError> {
+    let mut stream = body.into_data_stream();
+    let mut buffered = BytesMut::new();
+    while let Some(chunk) = stream.next().await {
+        buffered.extend_from_slice(
+            &chunk.map_err(InternalError::CollectBodyError)?,
+        );
+        if max_bytes.is_some_and(|max_bytes| buffered.len() > max_bytes) {
+            let buffered = buffered.freeze();
+            let body = futures::stream::once(async move {
+                Ok::<_, axum_core::Error>(buffered)
+            })
+            .chain(stream);
+            return Ok(Err(axum_core::body::Body::from_stream(body)));
+        }
+    }
+    Ok(Ok(buffered.freeze()))
+}
+
 async fn map_request(
     converter_registry: EndpointConverterRegistry,
+    config: MapperConfig,
+    limits: MappingLimits,
     source_endpoint: ApiEndpoint,
     target_endpoint: ApiEndpoint,
     target_path_and_query: &PathAndQuery,
     req: Request,
 ) -> Result<Request, ApiError> {
-    use http_body_util::BodyExt;
-    let (parts, body) = req.into_parts();
-    let body = body
-        .collect()
-        .await
-        .map_err(InternalError::CollectBodyError)?
-        .to_bytes();
+    let (mut parts, body) = req.into_parts();
+    let max_bytes = limits.max_request_bytes;
+    let body = collect_limited(body, max_bytes).await?.ok_or_else(|| {
+        InvalidRequestError::MappedRequestTooLarge(max_bytes.unwrap_or(0))
+    })?;
+    let body = if matches!(
+        source_endpoint,
+        ApiEndpoint::OpenAI(OpenAI::ChatCompletions(_))
+    ) {
+        validate_fields(config, &body)?;
+        validate_messages(config, &target_endpoint, &body)?;
+        images::enforce_limits(limits, &target_endpoint.provider(), body)?
+    } else {
+        body
+    };
     let converter = converter_registry
         .get_converter(&source_endpoint, &target_endpoint)
         .ok_or_else(|| {
-            InternalError::InvalidConverter(
+            InvalidRequestError::UnsupportedMapping(
                 source_endpoint.clone(),
                 target_endpoint.clone(),
             )
         })?;
 
     let (body, mapper_ctx) = converter.convert_req_body(body)?;
+    if source_endpoint != target_endpoint {
+        AppliedTransformations::record(
+            &mut parts.extensions,
+            Transformation::Mapping,
+        );
+    }
+    let body = if matches!(target_endpoint, ApiEndpoint::Anthropic(_))
+        && let Some(alternating) =
+            role_alternation::enforce(config.role_alternation, &body)
+    {
+        AppliedTransformations::record(
+            &mut parts.extensions,
+            Transformation::RoleAlternation,
+        );
+        alternating
+    } else {
+        body
+    };
+    let body = if matches!(target_endpoint, ApiEndpoint::Anthropic(_))
+        && let Some(marked) = CacheBreakpoints::from_headers(&parts.headers)
+            .and_then(|breakpoints| breakpoints.apply(&body))
+    {
+        AppliedTransformations::record(
+            &mut parts.extensions,
+            Transformation::PromptCaching,
+        );
+        marked
+    } else {
+        body
+    };
+    let auth_ctx = parts
+        .extensions
+        .get::<Arc<RequestContext>>()
+        .and_then(|req_ctx| req_ctx.auth_context.as_ref());
+    let tenant = match (config.tenant_attribution, auth_ctx) {
+        (Some(tenant_config), Some(auth_ctx)) => {
+            Some(tenant::identifier(tenant_config, auth_ctx))
+        }
+        _ => None,
+    };
+    let body = match tenant {
+        Some(tenant) => {
+            AppliedTransformations::record(
+                &mut parts.extensions,
+                Transformation::TenantAttribution,
+            );
+            tenant::inject(&target_endpoint, body, &tenant)
+        }
+        None => body,
+    };
     let base_path = target_endpoint
         .path(mapper_ctx.model.as_ref(), mapper_ctx.is_stream)?;
 
@@ -181,18 +728,57 @@ async fn map_request(
     Ok(req)
 }
 
+/// Maps a provider response back to the client's format.
+///
+/// Responses travel in the opposite direction to requests, so here
+/// `source_endpoint` is the provider's endpoint, which the raw response body
+/// is formatted for, and `target_endpoint` is the endpoint the client called.
+#[allow(clippy::too_many_arguments)]
 async fn map_response(
     converter_registry: EndpointConverterRegistry,
+    config: MapperConfig,
+    max_bytes: Option<usize>,
+    length_finish_reasons: Arc<[String]>,
+    config_digest: ConfigDigest,
     source_endpoint: ApiEndpoint,
     target_endpoint: ApiEndpoint,
+    redactor: Option<StreamRedactor>,
+    error_format: ErrorFormat,
     resp: http::Response<crate::types::body::Body>,
 ) -> Result<Response, ApiError> {
+    if error_format == ErrorFormat::Raw
+        && (resp.status().is_client_error() || resp.status().is_server_error())
+    {
+        tracing::trace!(
+            source_endpoint = ?target_endpoint,
+            status = %resp.status(),
+            "returning raw provider error"
+        );
+        return Ok(resp);
+    }
     let mapper_ctx = resp
         .extensions()
         .get::<MapperContext>()
         .ok_or(InternalError::ExtensionNotFound("MapperContext"))?;
     let is_stream = mapper_ctx.is_stream;
-    let (parts, body) = resp.into_parts();
+    let base64_embeddings = mapper_ctx.base64_embeddings;
+    let include_usage = mapper_ctx.include_usage;
+    // reasoning is only surfaced to clients that opted in to it
+    let surface_reasoning = mapper_ctx.reasoning
+        && matches!(target_endpoint, ApiEndpoint::OpenAI(_));
+    let normalize_usage = config.normalize_usage
+        && matches!(target_endpoint, ApiEndpoint::OpenAI(_));
+    let max_choices = config
+        .max_choices
+        .filter(|_| matches!(target_endpoint, ApiEndpoint::OpenAI(_)));
+    let system_fingerprint = synthesized_system_fingerprint(
+        config,
+        config_digest,
+        &target_endpoint,
+        resp.extensions().get::<InferenceProvider>(),
+        mapper_ctx,
+    );
+    let (mut parts, body) = resp.into_parts();
 
     let converter = converter_registry
         .get_converter(&target_endpoint, &source_endpoint)
@@ -209,67 +795,272 @@ async fn map_response(
             target_endpoint = ?source_endpoint,
             "mapped streaming response"
         );
-        // because we are using our custom body type, and we know it was
-        // constructed in the dispatcher from either an SSE stream or a
-        // stream of bytes, we can safely assume each frame is a single
-        // SSE event in this branch
-        let mapped_stream = body
-            .into_data_stream()
-            .map_err(|e| ApiError::StreamError(StreamError::BodyError(e)))
-            .try_filter_map({
-                let captured_registry = converter_registry.clone();
-                let resp_parts = parts.clone();
-                let target_endpoint_cloned = target_endpoint.clone();
-                let source_endpoint_cloned = source_endpoint.clone();
-                move |bytes| {
-                    let registry_for_future = captured_registry.clone();
-                    let resp_parts = resp_parts.clone();
-                    let target_endpoint = target_endpoint_cloned.clone();
-                    let source_endpoint = source_endpoint_cloned.clone();
-                    async move {
-                        let converter = registry_for_future
-                            .get_converter(&target_endpoint, &source_endpoint)
-                            .ok_or_else(|| {
-                                InternalError::InvalidConverter(
-                                    target_endpoint.clone(),
-                                    source_endpoint.clone(),
-                                )
-                            })?;
-
-                        let converted_data = converter
-                            .convert_resp_body(resp_parts, bytes, is_stream)?;
-
-                        // add the `data: ` prefix expected by the OpenAI SDK
-                        if let Some(converted_data) = converted_data {
-                            let mut new_bytes = BytesMut::new();
-                            new_bytes.put("data: ".as_bytes());
-                            new_bytes.put(converted_data);
-                            new_bytes.put("\n\n".as_bytes());
-                            let data = new_bytes.freeze();
-                            Ok(Some(data))
-                        } else {
-                            Ok(converted_data)
+        // redaction holds back content across events, so its state is
+        // shared by all of them
+        let redactor = redactor
+            .filter(|_| matches!(target_endpoint, ApiEndpoint::OpenAI(_)))
+            .map(|redactor| Arc::new(Mutex::new(redactor)));
+        // likewise, usage reported on any event is sent in a final chunk
+        let usage_chunk = (include_usage
+            && matches!(target_endpoint, ApiEndpoint::OpenAI(_)))
+        .then(|| Arc::new(Mutex::new(UsageChunk::default())));
+        // the body was constructed in the dispatcher from either an SSE
+        // stream, whose frames are single events, or a stream of bytes,
+        // whose frames may be any part of the raw event stream
+        let events = sse::events(
+            body.into_data_stream()
+                .map_err(|e| ApiError::StreamError(StreamError::BodyError(e))),
+        );
+        let mapped_stream = events.try_filter_map({
+            let captured_registry = converter_registry.clone();
+            let redactor = redactor.clone();
+            let usage_chunk = usage_chunk.clone();
+            let resp_parts = parts.clone();
+            let target_endpoint_cloned = target_endpoint.clone();
+            let source_endpoint_cloned = source_endpoint.clone();
+            move |bytes| {
+                let registry_for_future = captured_registry.clone();
+                let resp_parts = resp_parts.clone();
+                let target_endpoint = target_endpoint_cloned.clone();
+                let source_endpoint = source_endpoint_cloned.clone();
+                let system_fingerprint = system_fingerprint.clone();
+                let redactor = redactor.clone();
+                let usage_chunk = usage_chunk.clone();
+                let length_finish_reasons = Arc::clone(&length_finish_reasons);
+                async move {
+                    let converter = registry_for_future
+                        .get_converter(&target_endpoint, &source_endpoint)
+                        .ok_or_else(|| {
+                            InternalError::InvalidConverter(
+                                target_endpoint.clone(),
+                                source_endpoint.clone(),
+                            )
+                        })?;
+
+                    // each event is parsed at most once before and once
+                    // after it is converted, rather than by every step
+                    let mut provider_event = (surface_reasoning
+                        || normalize_usage
+                        || !length_finish_reasons.is_empty())
+                    .then(|| serde_json::from_slice::<Value>(&bytes).ok())
+                    .flatten();
+                    let reasoning = provider_event
+                        .as_ref()
+                        .filter(|_| surface_reasoning)
+                        .and_then(|event| {
+                            reasoning::extract(
+                                &source_endpoint,
+                                event,
+                                is_stream,
+                            )
+                        });
+                    let reported_usage = provider_event
+                        .as_ref()
+                        .filter(|_| normalize_usage)
+                        .and_then(|event| {
+                            usage::extract(&source_endpoint, event, is_stream)
+                        });
+                    let bytes = match &mut provider_event {
+                        Some(event)
+                            if finish_reason::normalize_length(
+                                event,
+                                &length_finish_reasons,
+                            ) =>
+                        {
+                            serialize(event)?
+                        }
+                        _ => bytes,
+                    };
+                    let converted_data = match converter
+                        .convert_resp_body(resp_parts, bytes, is_stream)
+                    {
+                        // a single malformed event shouldn't fail the
+                        // whole stream
+                        Err(ApiError::Internal(
+                            InternalError::Deserialize { ty, error },
+                        )) => {
+                            tracing::warn!(
+                                ty,
+                                error = %error,
+                                "dropping malformed stream event"
+                            );
+                            return Ok(None);
                         }
+                        result => result?,
+                    };
+                    let post_process = reasoning.is_some()
+                        || reported_usage.is_some()
+                        || system_fingerprint.is_some()
+                        || max_choices.is_some()
+                        || usage_chunk.is_some()
+                        || redactor.is_some();
+                    if !post_process {
+                        return Ok(converted_data.map(sse_event));
                     }
+                    let event = match converted_data {
+                        Some(data) => match serde_json::from_slice(&data) {
+                            Ok(event) => Some(event),
+                            // events that aren't JSON are sent as is
+                            Err(_) => return Ok(Some(sse_event(data))),
+                        },
+                        None => None,
+                    };
+                    let event = match reasoning {
+                        Some(reasoning) => {
+                            reasoning::apply(event, reasoning, is_stream)
+                        }
+                        None => event,
+                    };
+                    let Some(mut event) = event else {
+                        return Ok(None);
+                    };
+                    if let Some(reported) = reported_usage {
+                        usage::apply(&mut event, reported, is_stream);
+                    }
+                    if let Some(fp) = &system_fingerprint {
+                        fingerprint::apply(&mut event, fp);
+                    }
+                    if let Some(max) = max_choices {
+                        choices::truncate(&mut event, max);
+                    }
+                    if let Some(usage_chunk) = &usage_chunk
+                        && !usage_chunk
+                            .lock()
+                            .expect("usage chunk lock poisoned")
+                            .take(&mut event)
+                    {
+                        return Ok(None);
+                    }
+                    if let Some(redactor) = &redactor {
+                        redactor
+                            .lock()
+                            .expect("stream redactor lock poisoned")
+                            .apply(&mut event);
+                    }
+
+                    Ok(Some(sse_event(serialize(&event)?)))
                 }
+            }
+        });
+        // content still held back by the redactor when the stream ends
+        let redactor_stream =
+            futures::stream::iter(redactor).filter_map(|redactor| async move {
+                let chunk = redactor
+                    .lock()
+                    .expect("stream redactor lock poisoned")
+                    .flush()?;
+                Some(serialize(&chunk).map(sse_event).map_err(ApiError::from))
             });
-        let final_body = axum_core::body::Body::new(
-            reqwest::Body::wrap_stream(mapped_stream),
+        let usage_stream = futures::stream::iter(usage_chunk).filter_map(
+            |usage_chunk| async move {
+                let chunk = usage_chunk
+                    .lock()
+                    .expect("usage chunk lock poisoned")
+                    .finish()?;
+                Some(serialize(&chunk).map(sse_event).map_err(ApiError::from))
+            },
         );
+        let final_body =
+            axum_core::body::Body::new(reqwest::Body::wrap_stream(
+                mapped_stream.chain(redactor_stream).chain(usage_stream),
+            ));
         let new_resp = Response::from_parts(parts, final_body);
         Ok(new_resp)
     } else {
-        use http_body_util::BodyExt;
-        let body_bytes = body
-            .collect()
-            .await
-            .map_err(InternalError::CollectBodyError)?
-            .to_bytes();
+        let body_bytes =
+            collect_limited(body, max_bytes).await?.ok_or_else(|| {
+                InternalError::MapperError(MapperError::MappedResponseTooLarge(
+                    max_bytes.unwrap_or(0),
+                ))
+            })?;
+        // the error type may not survive mapping, so keep the provider's
+        if (parts.status.is_client_error() || parts.status.is_server_error())
+            && let Some(error_type) = ProviderErrorType::parse(&body_bytes)
+        {
+            parts.extensions.insert(error_type);
+        }
 
-        let mapped_body_bytes = converter
-            .convert_resp_body(parts.clone(), body_bytes, is_stream)?
-            .ok_or(MapperError::EmptyResponseBody)
-            .map_err(InternalError::MapperError)?;
+        let mut provider_body = (surface_reasoning
+            || normalize_usage
+            || !length_finish_reasons.is_empty())
+        .then(|| serde_json::from_slice::<Value>(&body_bytes).ok())
+        .flatten();
+        let reasoning = provider_body
+            .as_ref()
+            .filter(|_| surface_reasoning)
+            .and_then(|body| {
+                reasoning::extract(&source_endpoint, body, is_stream)
+            });
+        let reported_usage = provider_body
+            .as_ref()
+            .filter(|_| normalize_usage)
+            .and_then(|body| usage::extract(&source_endpoint, body, is_stream));
+        let body_bytes = match &mut provider_body {
+            Some(body)
+                if finish_reason::normalize_length(
+                    body,
+                    &length_finish_reasons,
+                ) =>
+            {
+                serialize(body)?
+            }
+            _ => body_bytes,
+        };
+        let mapped_body_bytes = converter.convert_resp_body(
+            parts.clone(),
+            body_bytes,
+            is_stream,
+        )?;
+        let post_process = reasoning.is_some()
+            || reported_usage.is_some()
+            || system_fingerprint.is_some()
+            || max_choices.is_some();
+        let mapped_body_bytes = match mapped_body_bytes {
+            Some(mapped) if post_process => {
+                match serde_json::from_slice::<Value>(&mapped) {
+                    Ok(mut body) => {
+                        if let Some(reasoning) = reasoning {
+                            body = reasoning::apply(
+                                Some(body),
+                                reasoning,
+                                is_stream,
+                            )
+                            .ok_or(MapperError::EmptyResponseBody)
+                            .map_err(InternalError::MapperError)?;
+                        }
+                        if let Some(reported) = reported_usage {
+                            usage::apply(&mut body, reported, is_stream);
+                        }
+                        if let Some(fp) = &system_fingerprint {
+                            fingerprint::apply(&mut body, fp);
+                        }
+                        if let Some(max) = max_choices {
+                            choices::truncate(&mut body, max);
+                        }
+                        serialize(&body)?
+                    }
+                    // bodies that aren't JSON are returned as is
+                    Err(_) => mapped,
+                }
+            }
+            Some(mapped) => mapped,
+            None => {
+                return Err(InternalError::MapperError(
+                    MapperError::EmptyResponseBody,
+                )
+                .into());
+            }
+        };
+        let mapped_body_bytes = if base64_embeddings {
+            embeddings::encode_response(mapped_body_bytes)
+        } else {
+            mapped_body_bytes
+        };
+        let mapped_body_bytes = if config.pretty_print_responses {
+            pretty_print(mapped_body_bytes)
+        } else {
+            mapped_body_bytes
+        };
         let final_body = axum_core::body::Body::from(mapped_body_bytes);
         let new_resp = Response::from_parts(parts, final_body);
         tracing::trace!(
@@ -281,16 +1072,82 @@ async fn map_response(
     }
 }
 
+fn serialize(value: &Value) -> Result<Bytes, InternalError> {
+    serde_json::to_vec(value).map(Bytes::from).map_err(|error| {
+        InternalError::Serialize {
+            ty: "serde_json::Value",
+            error,
+        }
+    })
+}
+
+/// Adds the `data: ` prefix expected by the `OpenAI` SDK to a mapped event.
+fn sse_event(data: Bytes) -> Bytes {
+    let mut new_bytes = BytesMut::new();
+    new_bytes.put("data: ".as_bytes());
+    new_bytes.put(data);
+    new_bytes.put("\n\n".as_bytes());
+    new_bytes.freeze()
+}
+
+/// Returns the fingerprint to set on mapped responses, if synthesis is
+/// enabled and the client expects an `OpenAI` formatted response.
+fn synthesized_system_fingerprint(
+    config: MapperConfig,
+    config_digest: ConfigDigest,
+    target_endpoint: &ApiEndpoint,
+    provider: Option<&InferenceProvider>,
+    mapper_ctx: &MapperContext,
+) -> Option<String> {
+    if !config.synthesize_system_fingerprint
+        || !matches!(target_endpoint, ApiEndpoint::OpenAI(_))
+    {
+        return None;
+    }
+    let provider = provider?;
+    Some(fingerprint::synthesize(
+        provider,
+        mapper_ctx.model.as_ref(),
+        config_digest,
+    ))
+}
+
+/// Re-serializes a JSON response body with indentation.
+///
+/// Bodies that are not JSON are returned unchanged.
+fn pretty_print(body: Bytes) -> Bytes {
+    serde_json::from_slice::<Value>(&body)
+        .and_then(|value| serde_json::to_vec_pretty(&value))
+        .map_or(body, Bytes::from)
+}
+
 #[derive(Debug, Clone)]
 pub struct Layer {
     endpoint_converter_registry: EndpointConverterRegistry,
+    config: MapperConfig,
+    streaming: StreamingSupport,
+    limits: MappingLimits,
+    length_finish_reasons: Arc<[String]>,
+    config_digest: ConfigDigest,
 }
 
 impl Layer {
     #[must_use]
-    pub fn new(endpoint_converter_registry: EndpointConverterRegistry) -> Self {
+    pub fn new(
+        endpoint_converter_registry: EndpointConverterRegistry,
+        config: MapperConfig,
+        streaming: StreamingSupport,
+        limits: MappingLimits,
+        length_finish_reasons: Vec<String>,
+        config_digest: ConfigDigest,
+    ) -> Self {
         Self {
             endpoint_converter_registry,
+            config,
+            streaming,
+            limits,
+            length_finish_reasons: length_finish_reasons.into(),
+            config_digest,
         }
     }
 }
@@ -299,6 +1156,91 @@ impl<S> tower::Layer<S> for Layer {
     type Service = Service<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        Service::new(inner, self.endpoint_converter_registry.clone())
+        Service::new(
+            inner,
+            self.endpoint_converter_registry.clone(),
+            self.config,
+            self.streaming,
+            self.limits,
+            Arc::clone(&self.length_finish_reasons),
+            self.config_digest,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum_core::response::IntoResponse;
+    use http_body_util::BodyExt;
+
+    use super::*;
+    use crate::endpoints::{anthropic::Anthropic, openai::OpenAI};
+
+    #[tokio::test]
+    async fn bodies_over_the_limit_are_not_collected() {
+        let body = || axum_core::body::Body::from("x".repeat(64));
+        assert_eq!(
+            collect_limited(body(), Some(64))
+                .await
+                .unwrap()
+                .unwrap()
+                .len(),
+            64
+        );
+        assert!(collect_limited(body(), Some(63)).await.unwrap().is_none());
+        assert!(collect_limited(body(), None).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn bodies_over_the_limit_are_passed_through() {
+        let body = || axum_core::body::Body::from("x".repeat(64));
+        assert_eq!(
+            collect_or_passthrough(body(), Some(64))
+                .await
+                .unwrap()
+                .unwrap()
+                .len(),
+            64
+        );
+        let passed_through = collect_or_passthrough(body(), Some(63))
+            .await
+            .unwrap()
+            .unwrap_err();
+        let passed_through = passed_through.collect().await.unwrap().to_bytes();
+        assert_eq!(passed_through, "x".repeat(64));
+    }
+
+    #[tokio::test]
+    async fn unsupported_mapping_is_not_implemented() {
+        let body = serde_json::json!({
+            "model": "claude-3-5-haiku",
+            "messages": [{ "role": "user", "content": "Hello, world!" }]
+        });
+        let req = http::Request::builder()
+            .uri("/v1/chat/completions")
+            .body(axum_core::body::Body::from(body.to_string()))
+            .unwrap();
+
+        let error = map_request(
+            EndpointConverterRegistry::default(),
+            MapperConfig::default(),
+            MappingLimits::default(),
+            ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            &PathAndQuery::from_static("/v1/messages"),
+            req,
+        )
+        .await
+        .unwrap_err();
+
+        let response = error.into_response();
+        assert_eq!(response.status(), http::StatusCode::NOT_IMPLEMENTED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["error"]["message"],
+            "Mapping openai v1/chat/completions requests to anthropic \
+             v1/messages is not supported"
+        );
     }
 }
@@ -0,0 +1,233 @@
+use std::{collections::VecDeque, pin::Pin};
+
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+
+/// Sent by `OpenAI` compatible providers as the data of the last event.
+const DONE: &[u8] = b"[DONE]";
+
+/// Decodes the data of the events in the frames of a streamed response body.
+///
+/// Frames of streams read with an event source already are the data of a
+/// single event and are passed through as is. Frames that are raw SSE, e.g.
+/// forwarded from a provider's byte stream, are decoded following the
+/// [event stream format]: comment lines and the `event`, `id`, and `retry`
+/// fields are skipped, multiple `data` lines are joined, and events that are
+/// split across frames are buffered until they are complete.
+///
+/// [event stream format]: https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation
+#[derive(Debug, Default)]
+pub(super) struct Decoder {
+    buffer: BytesMut,
+}
+
+impl Decoder {
+    /// Returns the data of the events completed by `frame`.
+    pub(super) fn decode(&mut self, frame: Bytes) -> Vec<Bytes> {
+        if self.buffer.is_empty() && !is_sse(&frame) {
+            return vec![frame];
+        }
+        self.buffer.extend_from_slice(&frame);
+        let mut events = Vec::new();
+        while let Some((end, delimiter_len)) = event_end(&self.buffer) {
+            let event = self.buffer.split_to(end + delimiter_len);
+            events.extend(event_data(&event[..end]));
+        }
+        events
+    }
+
+    /// Returns the data of an event that was still buffered when the stream
+    /// ended, since the blank line after the last event may be missing.
+    pub(super) fn finish(&mut self) -> Option<Bytes> {
+        let event = self.buffer.split();
+        event_data(&event)
+    }
+}
+
+/// Decodes the frames of `frames` with a [`Decoder`].
+pub(super) fn events<S, E>(frames: S) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    struct State<F> {
+        frames: Pin<Box<F>>,
+        decoder: Decoder,
+        pending: VecDeque<Bytes>,
+        ended: bool,
+    }
+
+    let state = State {
+        frames: Box::pin(frames),
+        decoder: Decoder::default(),
+        pending: VecDeque::new(),
+        ended: false,
+    };
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.pending.pop_front() {
+                return Some((Ok(event), state));
+            }
+            if state.ended {
+                return None;
+            }
+            match state.frames.next().await {
+                Some(Ok(frame)) => {
+                    state.pending.extend(state.decoder.decode(frame));
+                }
+                Some(Err(error)) => return Some((Err(error), state)),
+                None => {
+                    state.ended = true;
+                    state.pending.extend(state.decoder.finish());
+                }
+            }
+        }
+    })
+}
+
+/// Whether the frame starts with an SSE field or comment, rather than being
+/// the data of an event.
+fn is_sse(frame: &[u8]) -> bool {
+    let Some(line) = frame
+        .split(|byte| *byte == b'\n')
+        .map(trim_cr)
+        .find(|line| !line.is_empty())
+    else {
+        return false;
+    };
+    line.starts_with(b":")
+        || ["data", "event", "id", "retry"].iter().any(|field| {
+            line.strip_prefix(field.as_bytes())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(b":"))
+        })
+}
+
+/// The end of the first complete event in `buffer` and the length of the
+/// blank line terminating it.
+fn event_end(buffer: &[u8]) -> Option<(usize, usize)> {
+    [&b"\r\n\r\n"[..], b"\n\n", b"\r\r"]
+        .iter()
+        .filter_map(|delimiter| {
+            buffer
+                .windows(delimiter.len())
+                .position(|window| window == *delimiter)
+                .map(|end| (end, delimiter.len()))
+        })
+        .min_by_key(|(end, _)| *end)
+}
+
+/// The data of a single event, or `None` if it doesn't have any or signals
+/// the end of the stream.
+fn event_data(event: &[u8]) -> Option<Bytes> {
+    let mut data: Option<Vec<u8>> = None;
+    for line in event.split(|byte| *byte == b'\n' || *byte == b'\r') {
+        if line.is_empty() || line.starts_with(b":") {
+            continue;
+        }
+        let (field, value) = match line.iter().position(|byte| *byte == b':') {
+            Some(colon) => {
+                let value = &line[colon + 1..];
+                (&line[..colon], value.strip_prefix(b" ").unwrap_or(value))
+            }
+            None => (line, &b""[..]),
+        };
+        // `event`, `id` and `retry` don't affect the data, and unknown
+        // fields are ignored
+        if field == b"data" {
+            let data = data.get_or_insert_with(Vec::new);
+            if !data.is_empty() {
+                data.push(b'\n');
+            }
+            data.extend_from_slice(value);
+        }
+    }
+    data.filter(|data| !data.is_empty() && data.as_slice() != DONE)
+        .map(Bytes::from)
+}
+
+fn trim_cr(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    fn decode_all(frames: &[&'static str]) -> Vec<String> {
+        let mut decoder = Decoder::default();
+        let mut events = frames
+            .iter()
+            .flat_map(|frame| {
+                decoder.decode(Bytes::from_static(frame.as_bytes()))
+            })
+            .collect::<Vec<_>>();
+        events.extend(decoder.finish());
+        events
+            .into_iter()
+            .map(|event| String::from_utf8(event.to_vec()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn event_data_is_passed_through() {
+        let events = decode_all(&[r#"{"id":"1"}"#, r#"{"id":"2"}"#]);
+        assert_eq!(events, [r#"{"id":"1"}"#, r#"{"id":"2"}"#]);
+    }
+
+    #[test]
+    fn comments_and_other_fields_are_skipped() {
+        let events = decode_all(&[
+            ": keep-alive\n\n",
+            "event: message_start\nid: 1\nretry: 1000\ndata: \
+             {\"id\":\"1\"}\n\n",
+            ":comment\r\ndata:{\"id\":\"2\"}\r\n\r\n",
+            "data: [DONE]\n\n",
+        ]);
+        assert_eq!(events, [r#"{"id":"1"}"#, r#"{"id":"2"}"#]);
+    }
+
+    #[test]
+    fn multiple_data_lines_are_joined() {
+        let events = decode_all(&["data: {\"id\":\ndata: \"1\"}\n\n"]);
+        assert_eq!(events, ["{\"id\":\n\"1\"}"]);
+    }
+
+    #[test]
+    fn split_events_are_buffered() {
+        let events = decode_all(&[
+            "data: {\"id\"",
+            ":\"1\"}\n",
+            "\ndata: {\"id\":\"2\"}\n\nda",
+            "ta: {\"id\":\"3\"}",
+        ]);
+        assert_eq!(events, [r#"{"id":"1"}"#, r#"{"id":"2"}"#, r#"{"id":"3"}"#]);
+    }
+
+    #[test]
+    fn heartbeats_between_split_events_are_skipped() {
+        let events = decode_all(&[
+            "data: {\"id\":\"1\"}\n",
+            "\n: ping\n",
+            "\ndata: {\"id\"",
+            ":\"2\"}\r\n\r\n: ping\n\ndata: [DO",
+            "NE]\n\n",
+        ]);
+        assert_eq!(events, [r#"{"id":"1"}"#, r#"{"id":"2"}"#]);
+    }
+
+    #[tokio::test]
+    async fn streams_are_decoded() {
+        let frames = futures::stream::iter(
+            [
+                "event: ping\n\n",
+                "data: {\"id\":",
+                "\"1\"}\n\n",
+                "data: [DONE]\n\n",
+            ]
+            .map(|frame| Ok::<_, ()>(Bytes::from_static(frame.as_bytes()))),
+        );
+        let events = events(frames).try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(events, [Bytes::from_static(br#"{"id":"1"}"#)]);
+    }
+}
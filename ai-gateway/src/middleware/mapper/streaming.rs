@@ -0,0 +1,358 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use serde_json::{Map, Value, json};
+
+use crate::config::providers::StreamingSupport;
+
+/// How a response has to be converted for the client, when the kind of
+/// request the client sent isn't supported by the provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum StreamConversion {
+    /// The request was streamed from the provider, and the chunks are
+    /// aggregated into a single response.
+    Aggregate,
+    /// The request was sent without streaming, and the response is sent to
+    /// the client as a stream.
+    Chunk { include_usage: bool },
+}
+
+/// Rewrites an `OpenAI` chat completion request into a kind the provider
+/// supports, returning the conversion to apply to its response.
+///
+/// Bodies that can't be parsed are returned unchanged.
+pub(super) fn convert_request(
+    streaming: StreamingSupport,
+    body: Bytes,
+) -> (Bytes, Option<StreamConversion>) {
+    if streaming == StreamingSupport::Both {
+        return (body, None);
+    }
+    let Ok(mut value) = serde_json::from_slice::<Value>(&body) else {
+        return (body, None);
+    };
+    let Some(request) = value.as_object_mut() else {
+        return (body, None);
+    };
+    let is_stream =
+        request.get("stream").and_then(Value::as_bool) == Some(true);
+    let conversion = match (streaming, is_stream) {
+        (StreamingSupport::StreamOnly, false) => {
+            request.insert("stream".to_string(), Value::Bool(true));
+            request.insert(
+                "stream_options".to_string(),
+                json!({ "include_usage": true }),
+            );
+            StreamConversion::Aggregate
+        }
+        (StreamingSupport::NonStreamOnly, true) => {
+            let include_usage = request
+                .remove("stream_options")
+                .and_then(|options| {
+                    options.get("include_usage").and_then(Value::as_bool)
+                })
+                .unwrap_or(false);
+            request.insert("stream".to_string(), Value::Bool(false));
+            StreamConversion::Chunk { include_usage }
+        }
+        _ => return (body, None),
+    };
+    match serde_json::to_vec(&value) {
+        Ok(body) => (Bytes::from(body), Some(conversion)),
+        Err(_) => (body, None),
+    }
+}
+
+/// Aggregates the SSE events of a streamed `OpenAI` chat completion into a
+/// single chat completion response.
+///
+/// Returns `None` if the stream doesn't contain any chunks.
+pub(super) fn aggregate(body: &[u8]) -> Option<Bytes> {
+    let mut response = Map::new();
+    let mut choices: Vec<Map<String, Value>> = Vec::new();
+    let mut usage = Value::Null;
+    let chunks = String::from_utf8_lossy(body)
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .collect::<Vec<_>>();
+    if chunks.is_empty() {
+        return None;
+    }
+    for chunk in chunks {
+        for field in ["id", "created", "model", "system_fingerprint"] {
+            if let Some(value) = chunk.get(field).filter(|v| !v.is_null()) {
+                response.entry(field).or_insert_with(|| value.clone());
+            }
+        }
+        if let Some(chunk_usage) = chunk.get("usage").filter(|v| !v.is_null()) {
+            usage = chunk_usage.clone();
+        }
+        for choice in chunk
+            .get("choices")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let index = choice
+                .get("index")
+                .and_then(Value::as_u64)
+                .and_then(|index| usize::try_from(index).ok())
+                .unwrap_or(0);
+            if choices.len() <= index {
+                choices.resize_with(index + 1, Map::new);
+            }
+            merge_choice(&mut choices[index], choice);
+        }
+    }
+
+    let choices = choices
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut choice)| {
+            let mut message = choice
+                .remove("delta")
+                .and_then(|delta| match delta {
+                    Value::Object(delta) => Some(delta),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            message
+                .entry("role")
+                .or_insert_with(|| Value::String("assistant".to_string()));
+            message.entry("content").or_insert(Value::Null);
+            json!({
+                "index": index,
+                "message": message,
+                "logprobs": choice.remove("logprobs").unwrap_or(Value::Null),
+                "finish_reason":
+                    choice.remove("finish_reason").unwrap_or(Value::Null),
+            })
+        })
+        .collect::<Vec<_>>();
+    response.insert(
+        "object".to_string(),
+        Value::String("chat.completion".to_string()),
+    );
+    response.insert("choices".to_string(), Value::Array(choices));
+    if !usage.is_null() {
+        response.insert("usage".to_string(), usage);
+    }
+    serde_json::to_vec(&response).ok().map(Bytes::from)
+}
+
+/// Merges the delta of a streamed choice into the choice accumulated so far.
+fn merge_choice(acc: &mut Map<String, Value>, choice: &Value) {
+    if let Some(finish_reason) =
+        choice.get("finish_reason").filter(|v| !v.is_null())
+    {
+        acc.insert("finish_reason".to_string(), finish_reason.clone());
+    }
+    if let Some(logprobs) = choice.get("logprobs").filter(|v| !v.is_null()) {
+        acc.insert("logprobs".to_string(), logprobs.clone());
+    }
+    let Some(delta) = choice.get("delta").and_then(Value::as_object) else {
+        return;
+    };
+    let message = acc
+        .entry("delta")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .expect("delta is always an object");
+    for (key, value) in delta {
+        match (key.as_str(), value) {
+            (_, Value::Null) => {}
+            ("tool_calls", Value::Array(tool_calls)) => {
+                let acc_tool_calls = message
+                    .entry("tool_calls")
+                    .or_insert_with(|| Value::Array(Vec::new()))
+                    .as_array_mut()
+                    .expect("tool calls are always an array");
+                for tool_call in tool_calls {
+                    merge_tool_call(acc_tool_calls, tool_call);
+                }
+            }
+            (_, Value::String(text)) => match message.get_mut(key) {
+                Some(Value::String(acc_text)) if key != "role" => {
+                    acc_text.push_str(text);
+                }
+                _ => {
+                    message.insert(key.clone(), value.clone());
+                }
+            },
+            _ => {
+                message.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Merges a streamed tool call delta, whose arguments arrive in pieces, into
+/// the tool calls accumulated so far.
+fn merge_tool_call(acc: &mut Vec<Value>, tool_call: &Value) {
+    let index = tool_call
+        .get("index")
+        .and_then(Value::as_u64)
+        .and_then(|index| usize::try_from(index).ok())
+        .unwrap_or(acc.len());
+    while acc.len() <= index {
+        acc.push(json!({
+            "id": Value::Null,
+            "type": "function",
+            "function": { "name": "", "arguments": "" },
+        }));
+    }
+    let acc = &mut acc[index];
+    if let Some(id) = tool_call.get("id").filter(|v| !v.is_null()) {
+        acc["id"] = id.clone();
+    }
+    for field in ["name", "arguments"] {
+        if let Some(text) = tool_call
+            .pointer(&format!("/function/{field}"))
+            .and_then(Value::as_str)
+            && let Some(Value::String(acc_text)) =
+                acc.pointer_mut(&format!("/function/{field}"))
+        {
+            acc_text.push_str(text);
+        }
+    }
+}
+
+/// Converts an `OpenAI` chat completion response into the SSE events of an
+/// equivalent streamed response, terminated by `data: [DONE]`.
+///
+/// Bodies that can't be parsed are returned unchanged.
+pub(super) fn chunk(body: Bytes, include_usage: bool) -> Bytes {
+    let Ok(response) = serde_json::from_slice::<Value>(&body) else {
+        return body;
+    };
+    let base = |choices: Value| {
+        json!({
+            "id": response.get("id"),
+            "object": "chat.completion.chunk",
+            "created": response.get("created"),
+            "model": response.get("model"),
+            "system_fingerprint": response.get("system_fingerprint"),
+            "choices": choices,
+        })
+    };
+    let mut events = Vec::new();
+    for choice in response
+        .get("choices")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let mut delta = choice.get("message").cloned().unwrap_or_default();
+        if let Some(tool_calls) =
+            delta.get_mut("tool_calls").and_then(Value::as_array_mut)
+        {
+            for (index, tool_call) in tool_calls.iter_mut().enumerate() {
+                tool_call["index"] = json!(index);
+            }
+        }
+        events.push(base(json!([{
+            "index": choice.get("index"),
+            "delta": delta,
+            "logprobs": choice.get("logprobs"),
+            "finish_reason": choice.get("finish_reason"),
+        }])));
+    }
+    if include_usage && let Some(usage) = response.get("usage") {
+        let mut event = base(json!([]));
+        event["usage"] = usage.clone();
+        events.push(event);
+    }
+
+    let mut bytes = BytesMut::new();
+    for event in events {
+        bytes.put("data: ".as_bytes());
+        bytes.put(serde_json::to_vec(&event).unwrap_or_default().as_slice());
+        bytes.put("\n\n".as_bytes());
+    }
+    bytes.put("data: [DONE]\n\n".as_bytes());
+    bytes.freeze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_converted_to_supported_kind() {
+        let body = Bytes::from(
+            json!({ "model": "gpt-4o-mini", "stream": true }).to_string(),
+        );
+        let (_, conversion) =
+            convert_request(StreamingSupport::StreamOnly, body.clone());
+        assert_eq!(conversion, None);
+        let (converted, conversion) =
+            convert_request(StreamingSupport::NonStreamOnly, body);
+        assert_eq!(
+            conversion,
+            Some(StreamConversion::Chunk {
+                include_usage: false
+            })
+        );
+        let converted = serde_json::from_slice::<Value>(&converted).unwrap();
+        assert_eq!(converted["stream"], json!(false));
+
+        let body = Bytes::from(json!({ "model": "gpt-4o-mini" }).to_string());
+        let (converted, conversion) =
+            convert_request(StreamingSupport::StreamOnly, body);
+        assert_eq!(conversion, Some(StreamConversion::Aggregate));
+        let converted = serde_json::from_slice::<Value>(&converted).unwrap();
+        assert_eq!(converted["stream"], json!(true));
+    }
+
+    #[test]
+    fn chunks_are_aggregated() {
+        let chunks = [
+            json!({"id": "chatcmpl-1", "created": 1, "model": "gpt-4o-mini", "choices": [{"index": 0, "delta": {"role": "assistant", "content": ""}}]}),
+            json!({"id": "chatcmpl-1", "created": 1, "model": "gpt-4o-mini", "choices": [{"index": 0, "delta": {"content": "Hello"}}]}),
+            json!({"id": "chatcmpl-1", "created": 1, "model": "gpt-4o-mini", "choices": [{"index": 0, "delta": {"tool_calls": [{"index": 0, "id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":"}}]}}]}),
+            json!({"id": "chatcmpl-1", "created": 1, "model": "gpt-4o-mini", "choices": [{"index": 0, "delta": {"content": ", world!", "tool_calls": [{"index": 0, "function": {"arguments": "\"Paris\"}"}}]}, "finish_reason": "stop"}]}),
+            json!({"id": "chatcmpl-1", "created": 1, "model": "gpt-4o-mini", "choices": [], "usage": {"prompt_tokens": 5, "completion_tokens": 3, "total_tokens": 8}}),
+        ];
+        let body = chunks
+            .iter()
+            .map(|chunk| format!("data: {chunk}\n\n"))
+            .chain(std::iter::once("data: [DONE]\n\n".to_string()))
+            .collect::<String>();
+
+        let response = aggregate(body.as_bytes()).unwrap();
+        let response = serde_json::from_slice::<Value>(&response).unwrap();
+        assert_eq!(response["object"], "chat.completion");
+        assert_eq!(response["id"], "chatcmpl-1");
+        assert_eq!(response["usage"]["total_tokens"], 8);
+        let choice = &response["choices"][0];
+        assert_eq!(choice["finish_reason"], "stop");
+        assert_eq!(choice["message"]["role"], "assistant");
+        assert_eq!(choice["message"]["content"], "Hello, world!");
+        let tool_call = &choice["message"]["tool_calls"][0];
+        assert_eq!(tool_call["id"], "call_1");
+        assert_eq!(tool_call["function"]["name"], "get_weather");
+        assert_eq!(tool_call["function"]["arguments"], "{\"city\":\"Paris\"}");
+    }
+
+    #[test]
+    fn responses_are_chunked() {
+        let response = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4o-mini",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Hello!" },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 5, "completion_tokens": 3, "total_tokens": 8 }
+        });
+        let body = chunk(Bytes::from(response.to_string()), true);
+
+        let aggregated = aggregate(&body).unwrap();
+        let aggregated = serde_json::from_slice::<Value>(&aggregated).unwrap();
+        assert_eq!(aggregated["choices"][0]["message"]["content"], "Hello!");
+        assert_eq!(aggregated["usage"], response["usage"]);
+        assert!(body.ends_with(b"data: [DONE]\n\n"));
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt::Write;
+
+use bytes::Bytes;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::mapper::{TenantAttributionConfig, TenantIdentifier},
+    endpoints::ApiEndpoint,
+    types::extensions::AuthContext,
+};
+
+/// Returns the identifier of the caller to send to the provider.
+pub(super) fn identifier(
+    config: TenantAttributionConfig,
+    auth_ctx: &AuthContext,
+) -> String {
+    let identifier = match config.identifier {
+        TenantIdentifier::Org => auth_ctx.org_id.to_string(),
+        TenantIdentifier::User => auth_ctx.user_id.to_string(),
+    };
+    if !config.hash {
+        return identifier;
+    }
+    let digest = Sha256::digest(identifier.as_bytes());
+    let mut hashed = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        let _ = write!(hashed, "{byte:02x}");
+    }
+    hashed
+}
+
+/// Sets the tenant identifier of a request for the target endpoint, unless
+/// the client already set one.
+///
+/// Bodies of endpoints without tenant attribution, or that can't be parsed,
+/// are returned unchanged.
+pub(super) fn inject(
+    target_endpoint: &ApiEndpoint,
+    body: Bytes,
+    identifier: &str,
+) -> Bytes {
+    let pointer = match target_endpoint {
+        ApiEndpoint::OpenAI(_) | ApiEndpoint::OpenAICompatible { .. } => {
+            "/user"
+        }
+        ApiEndpoint::Anthropic(_) => "/metadata/user_id",
+        _ => return body,
+    };
+    let Ok(mut value) = serde_json::from_slice::<Value>(&body) else {
+        return body;
+    };
+    if value.pointer(pointer).is_some_and(|v| !v.is_null()) {
+        return body;
+    }
+    let Some(request) = value.as_object_mut() else {
+        return body;
+    };
+    if pointer == "/user" {
+        request.insert("user".to_string(), json!(identifier));
+    } else {
+        let metadata = request.entry("metadata").or_insert_with(|| json!({}));
+        if !metadata.is_object() {
+            *metadata = json!({});
+        }
+        metadata["user_id"] = json!(identifier);
+    }
+    serde_json::to_vec(&value).map_or(body, Bytes::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        endpoints::{anthropic::Anthropic, openai::OpenAI},
+        types::{org::OrgId, provider::InferenceProvider, user::UserId},
+    };
+
+    fn auth_ctx() -> AuthContext {
+        AuthContext {
+            api_key: "sk-helicone-test".to_string().into(),
+            user_id: UserId::new(Uuid::new_v4()),
+            org_id: OrgId::new(Uuid::new_v4()),
+        }
+    }
+
+    fn inject_value(endpoint: &ApiEndpoint, body: &Value) -> Value {
+        let body = Bytes::from(serde_json::to_vec(body).unwrap());
+        let body = inject(endpoint, body, "tenant");
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn identifier_is_injected_into_openai_user() {
+        let body = json!({ "model": "gpt-4o-mini", "messages": [] });
+        let endpoints = [
+            ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+            ApiEndpoint::OpenAICompatible {
+                provider: InferenceProvider::GoogleGemini,
+                openai_endpoint: OpenAI::chat_completions(),
+            },
+        ];
+        for endpoint in &endpoints {
+            assert_eq!(inject_value(endpoint, &body)["user"], "tenant");
+        }
+    }
+
+    #[test]
+    fn identifier_is_injected_into_anthropic_metadata() {
+        let endpoint = ApiEndpoint::Anthropic(Anthropic::messages());
+        let body = json!({ "model": "claude-3-5-haiku", "messages": [] });
+        let injected = inject_value(&endpoint, &body);
+        assert_eq!(injected["metadata"]["user_id"], "tenant");
+    }
+
+    #[test]
+    fn client_identifier_is_kept() {
+        let endpoint = ApiEndpoint::OpenAI(OpenAI::chat_completions());
+        let body = json!({ "model": "gpt-4o-mini", "user": "client" });
+        assert_eq!(inject_value(&endpoint, &body)["user"], "client");
+
+        let endpoint = ApiEndpoint::Anthropic(Anthropic::messages());
+        let body = json!({
+            "model": "claude-3-5-haiku",
+            "metadata": { "user_id": "client" }
+        });
+        assert_eq!(
+            inject_value(&endpoint, &body)["metadata"]["user_id"],
+            "client"
+        );
+    }
+
+    #[test]
+    fn identifier_can_be_hashed() {
+        let auth_ctx = auth_ctx();
+        let plain = TenantAttributionConfig {
+            identifier: TenantIdentifier::User,
+            hash: false,
+        };
+        assert_eq!(identifier(plain, &auth_ctx), auth_ctx.user_id.to_string());
+
+        let hashed = TenantAttributionConfig {
+            identifier: TenantIdentifier::Org,
+            hash: true,
+        };
+        let hashed = identifier(hashed, &auth_ctx);
+        assert_eq!(hashed.len(), 64);
+        assert!(!hashed.contains(&auth_ctx.org_id.to_string()));
+    }
+}
@@ -0,0 +1,131 @@
+use bytes::Bytes;
+use serde_json::{Value, json};
+
+/// Appended to requests that are retried because the provider ignored
+/// `tool_choice: required`.
+const TOOL_REQUIRED_INSTRUCTION: &str = "You must respond by calling one of \
+                                         the provided tools. Do not respond \
+                                         with text.";
+
+/// Whether an `OpenAI` chat completion request requires the model to call
+/// a tool.
+///
+/// Streaming requests are never considered, since their responses can't be
+/// inspected before they are sent to the client.
+pub(super) fn requires_tool_call(body: &[u8]) -> bool {
+    let Ok(value) = serde_json::from_slice::<Value>(body) else {
+        return false;
+    };
+    let is_stream = value.get("stream").and_then(Value::as_bool) == Some(true);
+    let has_tools = value
+        .get("tools")
+        .and_then(Value::as_array)
+        .is_some_and(|tools| !tools.is_empty());
+    !is_stream
+        && has_tools
+        && value.get("tool_choice").and_then(Value::as_str) == Some("required")
+}
+
+/// Whether an `OpenAI` chat completion response contains at least one tool
+/// call.
+pub(super) fn has_tool_call(body: &[u8]) -> bool {
+    let Ok(value) = serde_json::from_slice::<Value>(body) else {
+        return false;
+    };
+    value
+        .get("choices")
+        .and_then(Value::as_array)
+        .is_some_and(|choices| {
+            choices.iter().any(|choice| {
+                choice
+                    .pointer("/message/tool_calls")
+                    .and_then(Value::as_array)
+                    .is_some_and(|tool_calls| !tool_calls.is_empty())
+            })
+        })
+}
+
+/// Adds a system message insisting that the model calls a tool to an
+/// `OpenAI` chat completion request.
+///
+/// Bodies that can't be parsed are returned unchanged.
+pub(super) fn with_tool_instruction(body: Bytes) -> Bytes {
+    let Ok(mut value) = serde_json::from_slice::<Value>(&body) else {
+        return body;
+    };
+    let Some(messages) =
+        value.get_mut("messages").and_then(Value::as_array_mut)
+    else {
+        return body;
+    };
+    messages.push(json!({
+        "role": "system",
+        "content": TOOL_REQUIRED_INSTRUCTION,
+    }));
+    serde_json::to_vec(&value).map_or(body, Bytes::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(tool_choice: &str, stream: bool) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [{ "role": "user", "content": "What's the weather?" }],
+            "tools": [{
+                "type": "function",
+                "function": { "name": "get_weather", "parameters": {} }
+            }],
+            "tool_choice": tool_choice,
+            "stream": stream,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn only_non_streaming_required_tool_choice_is_enforced() {
+        assert!(requires_tool_call(&request("required", false)));
+        assert!(!requires_tool_call(&request("auto", false)));
+        assert!(!requires_tool_call(&request("required", true)));
+    }
+
+    #[test]
+    fn tool_calls_are_detected() {
+        let with_tool_call = serde_json::to_vec(&json!({
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_123",
+                        "type": "function",
+                        "function": { "name": "get_weather", "arguments": "{}" }
+                    }]
+                }
+            }]
+        }))
+        .unwrap();
+        let without_tool_call = serde_json::to_vec(&json!({
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "It's sunny." }
+            }]
+        }))
+        .unwrap();
+        assert!(has_tool_call(&with_tool_call));
+        assert!(!has_tool_call(&without_tool_call));
+    }
+
+    #[test]
+    fn instruction_is_appended_to_messages() {
+        let body =
+            with_tool_instruction(Bytes::from(request("required", false)));
+        let value: Value = serde_json::from_slice(&body).unwrap();
+        let messages = value["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1]["role"], "system");
+        assert_eq!(messages[1]["content"], TOOL_REQUIRED_INSTRUCTION);
+    }
+}
@@ -0,0 +1,396 @@
+use serde_json::{Map, Value};
+
+use crate::endpoints::ApiEndpoint;
+
+/// Token usage reported by a provider, in terms of the `OpenAI` `usage`
+/// object.
+///
+/// As with `OpenAI`, prompt tokens include cached tokens and completion
+/// tokens include reasoning tokens, even for providers that report them
+/// separately.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Usage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    total_tokens: u64,
+    /// Prompt tokens read from the provider's prompt cache.
+    cached_tokens: Option<u64>,
+    /// Prompt tokens written to the provider's prompt cache.
+    cache_creation_tokens: Option<u64>,
+    reasoning_tokens: Option<u64>,
+}
+
+/// Extracts the token usage from a raw provider response body or stream
+/// chunk, before it is mapped to the `OpenAI` format and any breakdown the
+/// converter doesn't carry over is lost.
+pub(super) fn extract(
+    provider_endpoint: &ApiEndpoint,
+    value: &Value,
+    is_stream: bool,
+) -> Option<Usage> {
+    let tokens = |usage: &Value, pointer: &str| {
+        usage.pointer(pointer).and_then(Value::as_u64)
+    };
+    match (provider_endpoint, is_stream) {
+        (ApiEndpoint::Anthropic(_), _) => {
+            // streams report usage in `message_start` and `message_delta`
+            let usage = value
+                .pointer("/message/usage")
+                .or_else(|| value.get("usage"))?;
+            let cached_tokens = tokens(usage, "/cache_read_input_tokens");
+            let cache_creation_tokens =
+                tokens(usage, "/cache_creation_input_tokens");
+            // anthropic's input tokens exclude tokens read from or written
+            // to the cache
+            let prompt_tokens = tokens(usage, "/input_tokens")
+                .unwrap_or_default()
+                + cached_tokens.unwrap_or_default()
+                + cache_creation_tokens.unwrap_or_default();
+            let completion_tokens =
+                tokens(usage, "/output_tokens").unwrap_or_default();
+            Some(Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+                cached_tokens,
+                cache_creation_tokens,
+                reasoning_tokens: None,
+            })
+        }
+        (ApiEndpoint::Bedrock(_), false) => {
+            let usage = value.get("usage")?;
+            let cached_tokens = tokens(usage, "/cacheReadInputTokens");
+            let cache_creation_tokens = tokens(usage, "/cacheWriteInputTokens");
+            let prompt_tokens = tokens(usage, "/inputTokens")
+                .unwrap_or_default()
+                + cached_tokens.unwrap_or_default()
+                + cache_creation_tokens.unwrap_or_default();
+            let completion_tokens =
+                tokens(usage, "/outputTokens").unwrap_or_default();
+            Some(Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+                cached_tokens,
+                cache_creation_tokens,
+                reasoning_tokens: None,
+            })
+        }
+        // bedrock streams are decoded from the AWS event stream format
+        // before reaching the mapper, so usage is not available here
+        (ApiEndpoint::Bedrock(_), true) => None,
+        _ => {
+            let usage = value.get("usage").filter(|usage| !usage.is_null())?;
+            let prompt_tokens =
+                tokens(usage, "/prompt_tokens").unwrap_or_default();
+            let completion_tokens =
+                tokens(usage, "/completion_tokens").unwrap_or_default();
+            Some(Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: tokens(usage, "/total_tokens")
+                    .unwrap_or(prompt_tokens + completion_tokens),
+                cached_tokens: tokens(
+                    usage,
+                    "/prompt_tokens_details/cached_tokens",
+                )
+                // e.g. deepseek
+                .or_else(|| tokens(usage, "/prompt_cache_hit_tokens")),
+                cache_creation_tokens: tokens(
+                    usage,
+                    "/prompt_tokens_details/cache_creation_tokens",
+                ),
+                reasoning_tokens: tokens(
+                    usage,
+                    "/completion_tokens_details/reasoning_tokens",
+                ),
+            })
+        }
+    }
+}
+
+/// Sets the `usage` of a mapped `OpenAI` response body or stream chunk.
+///
+/// Details the mapped usage already has, e.g. audio tokens, are kept. Stream
+/// chunks without usage are left as is, since only some chunks carry it.
+pub(super) fn apply(mapped: &mut Value, usage: Usage, is_stream: bool) {
+    let Some(object) = mapped.as_object_mut() else {
+        return;
+    };
+    if is_stream && object.get("usage").is_none_or(Value::is_null) {
+        return;
+    }
+    let mapped_usage = object
+        .entry("usage")
+        .or_insert_with(|| Value::Object(Map::new()));
+    if !mapped_usage.is_object() {
+        *mapped_usage = Value::Object(Map::new());
+    }
+    let Some(mapped_usage) = mapped_usage.as_object_mut() else {
+        return;
+    };
+    mapped_usage.insert("prompt_tokens".into(), usage.prompt_tokens.into());
+    mapped_usage
+        .insert("completion_tokens".into(), usage.completion_tokens.into());
+    mapped_usage.insert("total_tokens".into(), usage.total_tokens.into());
+    set_details(
+        mapped_usage,
+        "prompt_tokens_details",
+        &[
+            ("cached_tokens", usage.cached_tokens),
+            ("cache_creation_tokens", usage.cache_creation_tokens),
+        ],
+    );
+    set_details(
+        mapped_usage,
+        "completion_tokens_details",
+        &[("reasoning_tokens", usage.reasoning_tokens)],
+    );
+}
+
+/// Sets the reported `fields` of a details object of `usage`, creating it
+/// if any were reported.
+fn set_details(
+    usage: &mut Map<String, Value>,
+    name: &str,
+    fields: &[(&str, Option<u64>)],
+) {
+    let reported = fields
+        .iter()
+        .filter_map(|(field, tokens)| Some((*field, (*tokens)?)))
+        .collect::<Vec<_>>();
+    if reported.is_empty() {
+        return;
+    }
+    let details = usage
+        .entry(name)
+        .or_insert_with(|| Value::Object(Map::new()));
+    if !details.is_object() {
+        *details = Value::Object(Map::new());
+    }
+    if let Some(details) = details.as_object_mut() {
+        for (field, tokens) in reported {
+            details.insert(field.to_string(), tokens.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        endpoints::{anthropic::Anthropic, bedrock::Bedrock, openai::OpenAI},
+        types::provider::InferenceProvider,
+    };
+
+    /// A mapped response with the usage a converter without any breakdown
+    /// reports.
+    fn mapped(prompt_tokens: u64, completion_tokens: u64) -> Value {
+        json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "choices": [],
+            "usage": {
+                "prompt_tokens": prompt_tokens,
+                "completion_tokens": completion_tokens,
+                "total_tokens": prompt_tokens + completion_tokens
+            }
+        })
+    }
+
+    fn normalized(
+        provider_endpoint: &ApiEndpoint,
+        provider_body: &Value,
+        mut mapped: Value,
+        is_stream: bool,
+    ) -> Value {
+        let usage =
+            extract(provider_endpoint, provider_body, is_stream).unwrap();
+        apply(&mut mapped, usage, is_stream);
+        mapped["usage"].clone()
+    }
+
+    #[test]
+    fn anthropic_cache_tokens_are_normalized() {
+        let provider_body = json!({
+            "id": "msg_123",
+            "type": "message",
+            "usage": {
+                "input_tokens": 10,
+                "output_tokens": 20,
+                "cache_read_input_tokens": 100,
+                "cache_creation_input_tokens": 50
+            }
+        });
+        let usage = normalized(
+            &ApiEndpoint::Anthropic(Anthropic::messages()),
+            &provider_body,
+            mapped(10, 20),
+            false,
+        );
+        assert_eq!(
+            usage,
+            json!({
+                "prompt_tokens": 160,
+                "completion_tokens": 20,
+                "total_tokens": 180,
+                "prompt_tokens_details": {
+                    "cached_tokens": 100,
+                    "cache_creation_tokens": 50
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn anthropic_message_start_usage_is_normalized() {
+        let provider_chunk = json!({
+            "type": "message_start",
+            "message": {
+                "id": "msg_123",
+                "usage": {
+                    "input_tokens": 10,
+                    "output_tokens": 1,
+                    "cache_read_input_tokens": 100
+                }
+            }
+        });
+        let mapped_chunk = json!({
+            "id": "msg_123",
+            "object": "chat.completion.chunk",
+            "choices": [],
+            "usage": {
+                "prompt_tokens": 10,
+                "completion_tokens": 1,
+                "total_tokens": 11
+            }
+        });
+        let usage = normalized(
+            &ApiEndpoint::Anthropic(Anthropic::messages()),
+            &provider_chunk,
+            mapped_chunk,
+            true,
+        );
+        assert_eq!(usage["prompt_tokens"], 110);
+        assert_eq!(usage["total_tokens"], 111);
+        assert_eq!(usage["prompt_tokens_details"]["cached_tokens"], 100);
+    }
+
+    #[test]
+    fn bedrock_cache_tokens_are_normalized() {
+        let provider_body = json!({
+            "output": { "message": { "role": "assistant", "content": [] } },
+            "usage": {
+                "inputTokens": 10,
+                "outputTokens": 20,
+                "totalTokens": 180,
+                "cacheReadInputTokens": 100,
+                "cacheWriteInputTokens": 50
+            }
+        });
+        let usage = normalized(
+            &ApiEndpoint::Bedrock(Bedrock::converse()),
+            &provider_body,
+            mapped(10, 20),
+            false,
+        );
+        assert_eq!(
+            usage,
+            json!({
+                "prompt_tokens": 160,
+                "completion_tokens": 20,
+                "total_tokens": 180,
+                "prompt_tokens_details": {
+                    "cached_tokens": 100,
+                    "cache_creation_tokens": 50
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn openai_compatible_details_are_kept() {
+        let provider_body = json!({
+            "id": "chatcmpl-123",
+            "usage": {
+                "prompt_tokens": 110,
+                "completion_tokens": 220,
+                "total_tokens": 330,
+                "prompt_tokens_details": { "cached_tokens": 100 },
+                "completion_tokens_details": { "reasoning_tokens": 200 }
+            }
+        });
+        let usage = normalized(
+            &ApiEndpoint::OpenAICompatible {
+                provider: InferenceProvider::Named("mistral".into()),
+                openai_endpoint: OpenAI::chat_completions(),
+            },
+            &provider_body,
+            mapped(110, 220),
+            false,
+        );
+        assert_eq!(
+            usage,
+            json!({
+                "prompt_tokens": 110,
+                "completion_tokens": 220,
+                "total_tokens": 330,
+                "prompt_tokens_details": { "cached_tokens": 100 },
+                "completion_tokens_details": { "reasoning_tokens": 200 }
+            })
+        );
+    }
+
+    #[test]
+    fn existing_details_are_merged() {
+        let provider_body = json!({
+            "usage": {
+                "prompt_tokens": 10,
+                "completion_tokens": 20,
+                "total_tokens": 30,
+                "completion_tokens_details": { "reasoning_tokens": 5 }
+            }
+        });
+        let mapped = json!({
+            "usage": {
+                "prompt_tokens": 10,
+                "completion_tokens": 20,
+                "total_tokens": 30,
+                "completion_tokens_details": { "audio_tokens": 3 }
+            }
+        });
+        let usage = normalized(
+            &ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+            &provider_body,
+            mapped,
+            false,
+        );
+        assert_eq!(
+            usage["completion_tokens_details"],
+            json!({ "audio_tokens": 3, "reasoning_tokens": 5 })
+        );
+    }
+
+    #[test]
+    fn stream_chunks_without_usage_are_unchanged() {
+        let provider_endpoint = ApiEndpoint::OpenAICompatible {
+            provider: InferenceProvider::GoogleGemini,
+            openai_endpoint: OpenAI::chat_completions(),
+        };
+        let usage = extract(
+            &provider_endpoint,
+            &json!({ "usage": { "prompt_tokens": 1 } }),
+            true,
+        )
+        .unwrap();
+        let mut chunk = json!({ "choices": [], "usage": null });
+        apply(&mut chunk, usage, true);
+        assert_eq!(chunk, json!({ "choices": [], "usage": null }));
+        assert!(
+            extract(&provider_endpoint, &json!({ "usage": null }), true)
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use serde_json::{Map, Value, json};
+
+/// Collects the token usage of a stream mapped to the `OpenAI` format, so
+/// that it's sent in a final chunk without choices, like `OpenAI` does when
+/// a client sets `stream_options.include_usage`.
+///
+/// Providers report usage on different events, e.g. `Anthropic` reports the
+/// prompt tokens when the message starts and the completion tokens when it
+/// ends, so usage is moved out of every chunk and merged. Counts are
+/// cumulative, so the highest of each is kept.
+#[derive(Debug, Default)]
+pub(super) struct UsageChunk {
+    usage: Option<Map<String, Value>>,
+    /// The `id`, `created` and `model` of the last chunk, repeated in the
+    /// usage chunk.
+    header: Option<(Value, Value, Value)>,
+}
+
+impl UsageChunk {
+    /// Moves the usage out of a mapped chunk, returning whether the chunk
+    /// has any choices left to send.
+    pub(super) fn take(&mut self, chunk: &mut Value) -> bool {
+        let Some(object) = chunk.as_object_mut() else {
+            return true;
+        };
+        self.header = Some((
+            object.get("id").cloned().unwrap_or_default(),
+            object.get("created").cloned().unwrap_or_default(),
+            object.get("model").cloned().unwrap_or_default(),
+        ));
+        let usage = object.remove("usage");
+        if let Some(Value::Object(usage)) = usage {
+            self.merge(usage);
+        }
+        object
+            .get("choices")
+            .and_then(Value::as_array)
+            .is_some_and(|choices| !choices.is_empty())
+    }
+
+    /// The chunk with the usage of the whole stream, or `None` if the
+    /// provider didn't report any.
+    pub(super) fn finish(&mut self) -> Option<Value> {
+        let mut usage = self.usage.take()?;
+        let tokens = |usage: &Map<String, Value>, field: &str| {
+            usage.get(field).and_then(Value::as_u64).unwrap_or_default()
+        };
+        let total = tokens(&usage, "total_tokens").max(
+            tokens(&usage, "prompt_tokens")
+                + tokens(&usage, "completion_tokens"),
+        );
+        usage.insert("total_tokens".to_string(), total.into());
+        let (id, created, model) = self.header.take().unwrap_or_default();
+        Some(json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "created": created,
+            "model": model,
+            "choices": [],
+            "usage": usage,
+        }))
+    }
+
+    fn merge(&mut self, usage: Map<String, Value>) {
+        let merged = self.usage.get_or_insert_with(Map::new);
+        for (field, value) in usage {
+            match (merged.get(&field).and_then(Value::as_u64), value.as_u64()) {
+                (Some(current), Some(new)) if current >= new => {}
+                _ if value.is_null() => {}
+                _ => {
+                    merged.insert(field, value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(choices: &Value, usage: &Value) -> Value {
+        json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion.chunk",
+            "created": 1,
+            "model": "claude-3-5-sonnet-latest",
+            "choices": choices,
+            "usage": usage,
+        })
+    }
+
+    #[test]
+    fn usage_is_merged_into_a_final_chunk() {
+        let choice = json!([{ "index": 0, "delta": { "content": "Hi" } }]);
+        let mut usage_chunk = UsageChunk::default();
+        let mut start = chunk(
+            &choice,
+            &json!({
+                "prompt_tokens": 12,
+                "completion_tokens": 1,
+                "total_tokens": 13
+            }),
+        );
+        assert!(usage_chunk.take(&mut start));
+        assert!(start.get("usage").is_none());
+        assert_eq!(start["choices"], choice);
+        let mut end = chunk(
+            &choice,
+            &json!({
+                "prompt_tokens": 0,
+                "completion_tokens": 20,
+                "total_tokens": 20,
+                "completion_tokens_details": { "reasoning_tokens": 8 }
+            }),
+        );
+        assert!(usage_chunk.take(&mut end));
+        assert!(end.get("usage").is_none());
+
+        let last = usage_chunk.finish().unwrap();
+        assert_eq!(last["choices"], json!([]));
+        assert_eq!(last["id"], "chatcmpl-123");
+        assert_eq!(
+            last["usage"],
+            json!({
+                "prompt_tokens": 12,
+                "completion_tokens": 20,
+                "total_tokens": 32,
+                "completion_tokens_details": { "reasoning_tokens": 8 }
+            })
+        );
+        assert_eq!(usage_chunk.finish(), None);
+    }
+
+    #[test]
+    fn usage_only_chunks_are_held_back() {
+        let mut usage_chunk = UsageChunk::default();
+        let usage = json!({
+            "prompt_tokens": 12,
+            "completion_tokens": 20,
+            "total_tokens": 32
+        });
+        assert!(!usage_chunk.take(&mut chunk(&json!([]), &usage)));
+        assert_eq!(usage_chunk.finish().unwrap()["usage"], usage);
+    }
+
+    #[test]
+    fn streams_without_usage_have_no_usage_chunk() {
+        let mut usage_chunk = UsageChunk::default();
+        let choice = json!([{ "index": 0, "delta": { "content": "Hi" } }]);
+        assert!(usage_chunk.take(&mut chunk(&choice, &Value::Null)));
+        assert_eq!(usage_chunk.finish(), None);
+    }
+}
@@ -0,0 +1,251 @@
+use async_openai::types::CreateChatCompletionRequest;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    config::mapper::{MapperConfig, UnknownFields},
+    endpoints::ApiEndpoint,
+    error::invalid_req::InvalidRequestError,
+};
+
+/// Top-level fields of chat completion requests that are read by the gateway
+/// rather than the provider.
+const GATEWAY_FIELDS: &[&str] = &["prompt_id", "prompt_version_id", "inputs"];
+
+/// The subset of an `OpenAI` chat completion request needed to validate the
+/// messages array before mapping it to the target provider.
+#[derive(Debug, Deserialize)]
+struct ChatMessages {
+    #[serde(default)]
+    messages: Vec<ChatMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    role: String,
+}
+
+impl ChatMessage {
+    fn is_system(&self) -> bool {
+        matches!(self.role.as_str(), "system" | "developer")
+    }
+}
+
+/// Providers which carry the system prompt outside of the messages array
+/// require at least one non-system message, even if system-only requests
+/// are allowed.
+fn supports_system_only_messages(target_endpoint: &ApiEndpoint) -> bool {
+    !matches!(
+        target_endpoint,
+        ApiEndpoint::Anthropic(_) | ApiEndpoint::Bedrock(_)
+    )
+}
+
+/// Rejects `OpenAI` chat completion requests with an empty messages array,
+/// or with only system messages unless allowed by config and supported by
+/// the target provider.
+///
+/// Bodies that fail to deserialize are left for the converter to reject.
+pub(super) fn validate_messages(
+    config: MapperConfig,
+    target_endpoint: &ApiEndpoint,
+    body: &[u8],
+) -> Result<(), InvalidRequestError> {
+    let Ok(request) = serde_json::from_slice::<ChatMessages>(body) else {
+        return Ok(());
+    };
+
+    if request.messages.is_empty() {
+        return Err(InvalidRequestError::EmptyMessages);
+    }
+
+    let system_only = request.messages.iter().all(ChatMessage::is_system);
+    if system_only
+        && !(config.allow_system_only_messages
+            && supports_system_only_messages(target_endpoint))
+    {
+        return Err(InvalidRequestError::SystemOnlyMessages(
+            target_endpoint.provider(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Rejects `OpenAI` chat completion requests with top-level fields that
+/// aren't part of the `OpenAI` API, if configured to.
+///
+/// Known fields are the ones that survive a round trip through the request
+/// type, so that the list of fields can't get out of date. Fields set to
+/// `null` are never rejected, since they are dropped either way.
+///
+/// Bodies that fail to deserialize are left for the converter to reject.
+pub(super) fn validate_fields(
+    config: MapperConfig,
+    body: &[u8],
+) -> Result<(), InvalidRequestError> {
+    if config.unknown_fields == UnknownFields::Passthrough {
+        return Ok(());
+    }
+    let Ok(Value::Object(fields)) = serde_json::from_slice::<Value>(body)
+    else {
+        return Ok(());
+    };
+    let Ok(request) =
+        serde_json::from_slice::<CreateChatCompletionRequest>(body)
+    else {
+        return Ok(());
+    };
+    let Ok(Value::Object(known)) = serde_json::to_value(&request) else {
+        return Ok(());
+    };
+    let unknown = fields
+        .iter()
+        .filter(|(field, value)| {
+            !value.is_null()
+                && !known.contains_key(*field)
+                && !GATEWAY_FIELDS.contains(&field.as_str())
+        })
+        .map(|(field, _)| format!("`{field}`"))
+        .collect::<Vec<_>>();
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(InvalidRequestError::UnknownFields(unknown.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::endpoints::{
+        anthropic::Anthropic, bedrock::Bedrock, google::Google, ollama::Ollama,
+        openai::OpenAI,
+    };
+
+    fn allow_system_only() -> MapperConfig {
+        MapperConfig {
+            allow_system_only_messages: true,
+            ..Default::default()
+        }
+    }
+
+    fn body(value: &serde_json::Value) -> Vec<u8> {
+        serde_json::to_vec(value).unwrap()
+    }
+
+    #[test]
+    fn empty_messages_are_rejected() {
+        let request = body(&json!({ "model": "gpt-4o", "messages": [] }));
+        let target = ApiEndpoint::OpenAI(OpenAI::chat_completions());
+        for config in [MapperConfig::default(), allow_system_only()] {
+            let result = validate_messages(config, &target, &request);
+            assert!(matches!(result, Err(InvalidRequestError::EmptyMessages)));
+        }
+    }
+
+    #[test]
+    fn system_only_messages_rejected_by_default() {
+        let request = body(&json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "system", "content": "be concise" }]
+        }));
+        let target = ApiEndpoint::OpenAI(OpenAI::chat_completions());
+        let result =
+            validate_messages(MapperConfig::default(), &target, &request);
+        assert!(matches!(
+            result,
+            Err(InvalidRequestError::SystemOnlyMessages(_))
+        ));
+    }
+
+    #[test]
+    fn system_only_messages_allowed_for_supporting_providers() {
+        let request = body(&json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "developer", "content": "be concise" }]
+        }));
+        let supported = [
+            ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+            ApiEndpoint::Google(Google::generate_contents()),
+            ApiEndpoint::Ollama(Ollama::chat_completions()),
+        ];
+        for target in supported {
+            assert!(
+                validate_messages(allow_system_only(), &target, &request)
+                    .is_ok(),
+                "system-only messages should be allowed for {target:?}"
+            );
+        }
+
+        let unsupported = [
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            ApiEndpoint::Bedrock(Bedrock::converse()),
+        ];
+        for target in unsupported {
+            assert!(
+                matches!(
+                    validate_messages(allow_system_only(), &target, &request),
+                    Err(InvalidRequestError::SystemOnlyMessages(_))
+                ),
+                "system-only messages should be rejected for {target:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn non_system_messages_are_accepted() {
+        let request = body(&json!({
+            "model": "gpt-4o",
+            "messages": [
+                { "role": "system", "content": "be concise" },
+                { "role": "user", "content": "hello" }
+            ]
+        }));
+        let target = ApiEndpoint::Anthropic(Anthropic::messages());
+        assert!(
+            validate_messages(MapperConfig::default(), &target, &request)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected_when_configured() {
+        let reject = MapperConfig {
+            unknown_fields: UnknownFields::Reject,
+            ..Default::default()
+        };
+        let request = body(&json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "hello" }],
+            "temprature": 0.5
+        }));
+        match validate_fields(reject, &request) {
+            Err(InvalidRequestError::UnknownFields(fields)) => {
+                assert_eq!(fields, "`temprature`");
+            }
+            result => panic!("unexpected result: {result:?}"),
+        }
+        assert!(validate_fields(MapperConfig::default(), &request).is_ok());
+    }
+
+    #[test]
+    fn known_fields_are_accepted() {
+        let reject = MapperConfig {
+            unknown_fields: UnknownFields::Reject,
+            ..Default::default()
+        };
+        let request = body(&json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "hello" }],
+            "temperature": 0.5,
+            "stream": false,
+            "stream_options": null,
+            "prompt_id": "prompt-123",
+            "inputs": { "name": "Ada" }
+        }));
+        assert!(validate_fields(reject, &request).is_ok());
+    }
+}
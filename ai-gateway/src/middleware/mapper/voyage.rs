@@ -0,0 +1,129 @@
+use std::str::FromStr;
+
+use http::response::Parts;
+
+use super::{
+    TryConvert, TryConvertError, TryConvertStreamData, embeddings,
+    model::ModelMapper,
+};
+use crate::{
+    endpoints::{
+        openai::embeddings::{
+            CreateEmbeddingRequest, CreateEmbeddingResponse, Embedding,
+            EmbeddingUsage, EmbeddingVector,
+        },
+        voyage::{
+            PROVIDER_NAME,
+            embeddings::{
+                EmbeddingsRequest, EmbeddingsResponse, VoyageApiError,
+            },
+        },
+    },
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+pub struct VoyageConverter {
+    model_mapper: ModelMapper,
+}
+
+impl VoyageConverter {
+    #[must_use]
+    pub fn new(model_mapper: ModelMapper) -> Self {
+        Self { model_mapper }
+    }
+}
+
+impl TryConvert<CreateEmbeddingRequest, EmbeddingsRequest> for VoyageConverter {
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: CreateEmbeddingRequest,
+    ) -> Result<EmbeddingsRequest, Self::Error> {
+        let source_model = ModelId::from_str(&value.model)?;
+        let target_model = self.model_mapper.map_model(
+            &source_model,
+            &InferenceProvider::Named(PROVIDER_NAME.into()),
+        )?;
+        tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
+        let input = value.input.into_texts().ok_or_else(|| {
+            MapperError::EmbeddingsMappingInvalid(
+                "token id inputs are not supported by Voyage".to_string(),
+            )
+        })?;
+
+        // the embeddings are always requested as floats, and encoded by the
+        // mapper if the client asked for base64
+        Ok(EmbeddingsRequest {
+            model: target_model.to_string(),
+            input,
+            output_dimension: value.dimensions,
+        })
+    }
+}
+
+impl TryConvert<EmbeddingsResponse, CreateEmbeddingResponse>
+    for VoyageConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: EmbeddingsResponse,
+    ) -> Result<CreateEmbeddingResponse, Self::Error> {
+        let data = value
+            .data
+            .into_iter()
+            .map(|embedding| {
+                Ok(Embedding {
+                    object: "embedding".to_string(),
+                    embedding: EmbeddingVector::Float(embeddings::into_floats(
+                        embedding.embedding,
+                    )?),
+                    index: embedding.index,
+                })
+            })
+            .collect::<Result<Vec<_>, MapperError>>()?;
+
+        Ok(CreateEmbeddingResponse {
+            object: "list".to_string(),
+            data,
+            model: value.model,
+            usage: EmbeddingUsage {
+                prompt_tokens: value.usage.total_tokens,
+                total_tokens: value.usage.total_tokens,
+            },
+        })
+    }
+}
+
+impl TryConvertStreamData<EmbeddingsResponse, CreateEmbeddingResponse>
+    for VoyageConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_chunk(
+        &self,
+        value: EmbeddingsResponse,
+    ) -> Result<Option<CreateEmbeddingResponse>, Self::Error> {
+        self.try_convert(value).map(Some)
+    }
+}
+
+impl TryConvertError<VoyageApiError, async_openai::error::WrappedError>
+    for VoyageConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        resp_parts: &Parts,
+        value: VoyageApiError,
+    ) -> Result<async_openai::error::WrappedError, Self::Error> {
+        Ok(super::openai_error_from_status(
+            resp_parts.status,
+            Some(value.detail),
+        ))
+    }
+}
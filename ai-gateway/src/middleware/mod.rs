@@ -1,8 +1,21 @@
 pub mod add_extension;
 pub mod auth;
 pub mod cache;
+pub mod embeddings;
 pub mod mapper;
+pub mod moderation;
 pub mod prompts;
+pub mod queue;
 pub mod rate_limit;
 pub mod request_context;
+pub mod request_fingerprint;
+pub mod request_id;
 pub mod response_headers;
+pub mod sampling_bounds;
+pub mod spend_limit;
+pub mod stream_limit;
+pub mod stream_moderation;
+pub mod system_prompt;
+pub mod tool_call_turns;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm_plugin;
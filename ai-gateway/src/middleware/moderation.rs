@@ -0,0 +1,366 @@
+//! Checks the prompt of chat requests with a moderation model before they are
+//! dispatched.
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{HeaderName, HeaderValue};
+use http_body_util::BodyExt;
+use rust_decimal::prelude::ToPrimitive;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::OnceCell;
+
+use crate::{
+    app_state::AppState,
+    config::{moderation::ModerationConfig, router::RouterConfig},
+    dispatcher::client::{Client, ProviderClient},
+    error::{
+        api::ApiError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    types::{extensions::AuthContext, request::Request, response::Response},
+};
+
+const MODERATIONS_PATH: &str = "v1/moderations";
+const PROPERTY_PREFIX: &str = "helicone-property-moderation-";
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    moderator: Option<Arc<Moderator>>,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn for_router(
+        app_state: &AppState,
+        router_config: &RouterConfig,
+    ) -> Self {
+        Self {
+            moderator: router_config.moderation.as_ref().map(|config| {
+                Arc::new(Moderator::new(app_state.clone(), config.clone()))
+            }),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            moderator: self.moderator.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    moderator: Option<Arc<Moderator>>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<
+            Request,
+            Response = http::Response<crate::types::body::Body>,
+            Error = ApiError,
+        > + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "moderation", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut inner = self.inner.clone();
+        let moderator = self.moderator.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        Box::pin(async move {
+            let Some(moderator) = moderator else {
+                return inner.call(req).await;
+            };
+            let (mut parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            // requests without a user message, e.g. embeddings, are passed
+            // through unchecked
+            if let Some(text) = serde_json::from_slice::<Value>(&body)
+                .ok()
+                .as_ref()
+                .and_then(last_user_message)
+            {
+                let auth_ctx = parts.extensions.get::<AuthContext>();
+                let scores = moderator.scores(&text, auth_ctx).await?;
+                let verdict = verdict(
+                    &moderator.config.blocking_categories,
+                    moderator.threshold,
+                    &scores,
+                );
+                if !verdict.blocked.is_empty() {
+                    return Err(ApiError::InvalidRequest(
+                        InvalidRequestError::FlaggedByModeration(
+                            verdict.blocked.join(", "),
+                        ),
+                    ));
+                }
+                for (category, score) in verdict.flagged {
+                    let Ok(name) = HeaderName::try_from(format!(
+                        "{PROPERTY_PREFIX}{}",
+                        category.replace('/', "-")
+                    )) else {
+                        continue;
+                    };
+                    parts.headers.insert(
+                        name,
+                        HeaderValue::from_str(&score.to_string())
+                            .expect("floats are valid header values"),
+                    );
+                }
+            }
+            let req =
+                Request::from_parts(parts, axum_core::body::Body::from(body));
+            inner.call(req).await
+        })
+    }
+}
+
+/// Sends prompts to the moderations endpoint of the configured model's
+/// provider.
+///
+/// Like embeddings for the semantic cache, these requests are sent directly
+/// with the provider's client rather than through the dispatcher, so they
+/// are never logged as requests.
+#[derive(Debug)]
+struct Moderator {
+    app_state: AppState,
+    config: ModerationConfig,
+    threshold: f64,
+    /// Created on first use, since creating a client may need to fetch the
+    /// provider's key.
+    client: OnceCell<Client>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Verdict {
+    /// Blocking categories that reached the threshold.
+    blocked: Vec<String>,
+    /// Other categories that reached the threshold, with their scores.
+    flagged: Vec<(String, f64)>,
+}
+
+#[derive(Deserialize)]
+struct ModerationResponse {
+    results: Vec<ModerationResult>,
+}
+
+#[derive(Deserialize)]
+struct ModerationResult {
+    category_scores: HashMap<String, f64>,
+}
+
+impl Moderator {
+    fn new(app_state: AppState, config: ModerationConfig) -> Self {
+        Self {
+            app_state,
+            threshold: config.threshold.to_f64().unwrap_or(1.0),
+            config,
+            client: OnceCell::new(),
+        }
+    }
+
+    async fn scores(
+        &self,
+        text: &str,
+        auth_ctx: Option<&AuthContext>,
+    ) -> Result<HashMap<String, f64>, ApiError> {
+        // checked when the router config is validated
+        let provider = self
+            .config
+            .model
+            .inference_provider()
+            .ok_or(InternalError::Internal)?;
+        let config = self.app_state.config();
+        let provider_config =
+            config.providers.get(&provider).ok_or_else(|| {
+                InternalError::ProviderNotConfigured(provider.clone())
+            })?;
+        let client = self
+            .client
+            .get_or_try_init(|| Client::new(&self.app_state, provider.clone()))
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, "failed to create moderation client");
+                InternalError::Internal
+            })?;
+
+        let body = serde_json::to_vec(&json!({
+            "model": self.config.model.to_string(),
+            "input": text,
+        }))
+        .map_err(|error| InternalError::Serialize {
+            ty: "serde_json::Value",
+            error,
+        })?;
+        let body = Bytes::from(body);
+        let target_url = provider_config
+            .base_url
+            .join(MODERATIONS_PATH)
+            .expect("PathAndQuery joined with valid url will always succeed");
+
+        let request_builder = client
+            .as_ref()
+            .post(target_url)
+            .header(http::header::CONTENT_TYPE, "application/json");
+        let request_builder = client
+            .authenticate(
+                &self.app_state,
+                request_builder,
+                &body,
+                auth_ctx,
+                provider,
+            )
+            .await?;
+        let body = request_builder
+            .body(body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(InternalError::ReqwestError)?
+            .bytes()
+            .await
+            .map_err(InternalError::ReqwestError)?;
+        let response: ModerationResponse = serde_json::from_slice(&body)
+            .map_err(|error| InternalError::Deserialize {
+                ty: "ModerationResponse",
+                error,
+            })?;
+        response
+            .results
+            .into_iter()
+            .next()
+            .map(|result| result.category_scores)
+            .ok_or_else(|| InternalError::Internal.into())
+    }
+}
+
+/// Splits the categories that reached the threshold into those that block
+/// the request and those that are only logged.
+fn verdict(
+    blocking_categories: &[String],
+    threshold: f64,
+    scores: &HashMap<String, f64>,
+) -> Verdict {
+    let mut verdict = Verdict::default();
+    for (category, score) in scores {
+        if *score < threshold {
+            continue;
+        }
+        if blocking_categories.contains(category) {
+            verdict.blocked.push(category.clone());
+        } else {
+            verdict.flagged.push((category.clone(), *score));
+        }
+    }
+    // scores are unordered, sort them so the error message is stable
+    verdict.blocked.sort();
+    verdict.flagged.sort_by(|(a, _), (b, _)| a.cmp(b));
+    verdict
+}
+
+/// The text of the last user message of a chat request, or `None` if it has
+/// no text content.
+fn last_user_message(body: &Value) -> Option<String> {
+    let message = body
+        .get("messages")
+        .and_then(Value::as_array)?
+        .iter()
+        .rev()
+        .find(|message| {
+            message.get("role").and_then(Value::as_str) == Some("user")
+        })?;
+    let text = match message.get("content")? {
+        Value::String(content) => content.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => return None,
+    };
+    (!text.trim().is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_user_message_is_extracted() {
+        let body = json!({
+            "messages": [
+                { "role": "user", "content": "first" },
+                { "role": "assistant", "content": "reply" },
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": "second" },
+                        { "type": "image_url", "image_url": { "url": "x" } },
+                        { "type": "text", "text": "part" }
+                    ]
+                },
+                { "role": "tool", "content": "result" }
+            ]
+        });
+        assert_eq!(last_user_message(&body).as_deref(), Some("second part"));
+    }
+
+    #[test]
+    fn only_blocking_categories_over_threshold_block() {
+        let scores = HashMap::from([
+            ("violence".to_string(), 0.9),
+            ("harassment".to_string(), 0.7),
+            ("self-harm/intent".to_string(), 0.2),
+        ]);
+        let blocking =
+            vec!["violence".to_string(), "self-harm/intent".to_string()];
+        assert_eq!(
+            verdict(&blocking, 0.5, &scores),
+            Verdict {
+                blocked: vec!["violence".to_string()],
+                flagged: vec![("harassment".to_string(), 0.7)],
+            }
+        );
+        assert_eq!(verdict(&blocking, 0.95, &scores), Verdict::default());
+    }
+
+    #[test]
+    fn bodies_without_user_messages_are_skipped() {
+        assert_eq!(last_user_message(&json!({ "input": "hello" })), None);
+        let body = json!({
+            "messages": [{ "role": "system", "content": "Be brief." }]
+        });
+        assert_eq!(last_user_message(&body), None);
+    }
+}
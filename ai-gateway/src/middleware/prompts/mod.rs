@@ -1,4 +1,5 @@
 pub mod optional;
 mod service;
+mod template;
 
 pub use optional::{Layer as PromptLayer, Service as PromptService};
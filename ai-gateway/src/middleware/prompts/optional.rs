@@ -6,6 +6,7 @@ use std::{
 
 use crate::{
     app_state::AppState,
+    config::router::RouterConfig,
     error::{api::ApiError, init::InitError},
     middleware::prompts::service::{PromptLayer, PromptService},
     types::{request::Request, response::Response},
@@ -17,12 +18,26 @@ pub struct Layer {
 }
 
 impl Layer {
-    pub fn new(app_state: &AppState) -> Result<Self, InitError> {
+    pub fn for_router(
+        app_state: &AppState,
+        router_config: &RouterConfig,
+    ) -> Result<Self, InitError> {
         if !app_state.config().helicone.is_prompts_enabled() {
             return Ok(Self { inner: None });
         }
 
-        let layer = PromptLayer::new(app_state.clone());
+        let max_request_bytes = [
+            router_config.max_request_body_bytes,
+            app_state.config().mapper.max_request_body_bytes,
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let layer = PromptLayer::new(
+            app_state.clone(),
+            router_config.strict_prompt_inputs,
+            max_request_bytes,
+        );
         Ok(Self { inner: Some(layer) })
     }
 
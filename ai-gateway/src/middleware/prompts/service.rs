@@ -1,5 +1,5 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     string::ToString,
     task::{Context, Poll},
 };
@@ -10,28 +10,46 @@ use regex::Regex;
 use serde_json::Value;
 use tracing::{Instrument, info_span};
 
+use super::template;
 use crate::{
     app_state::AppState,
+    config::prompts::MalformedPromptParams,
     error::{
         api::ApiError, internal::InternalError,
         invalid_req::InvalidRequestError, prompts::PromptError,
     },
     store::minio::MinioClient,
     types::{
-        extensions::{AuthContext, PromptContext},
+        extensions::{
+            AppliedTransformations, AuthContext, PromptContext, Transformation,
+        },
         request::Request,
         response::{JawnResponse, Response},
     },
 };
 
+/// Rejects requests that don't provide an input for every variable of the
+/// prompt, if set to `true`.
+const PROMPT_STRICT_HEADER: &str = "helicone-prompt-strict";
+
 #[derive(Debug, Clone)]
 pub struct PromptLayer {
     app_state: AppState,
+    strict: bool,
+    max_request_bytes: Option<usize>,
 }
 
 impl PromptLayer {
-    pub fn new(app_state: AppState) -> PromptLayer {
-        Self { app_state }
+    pub fn new(
+        app_state: AppState,
+        strict: bool,
+        max_request_bytes: Option<usize>,
+    ) -> PromptLayer {
+        Self {
+            app_state,
+            strict,
+            max_request_bytes,
+        }
     }
 }
 
@@ -42,6 +60,8 @@ impl<S> tower::Layer<S> for PromptLayer {
         PromptService {
             inner,
             app_state: self.app_state.clone(),
+            strict: self.strict,
+            max_request_bytes: self.max_request_bytes,
         }
     }
 }
@@ -50,6 +70,8 @@ impl<S> tower::Layer<S> for PromptLayer {
 pub struct PromptService<S> {
     inner: S,
     app_state: AppState,
+    strict: bool,
+    max_request_bytes: Option<usize>,
 }
 
 impl<S> tower::Service<Request> for PromptService<S>
@@ -79,10 +101,12 @@ where
     fn call(&mut self, req: Request) -> Self::Future {
         let mut inner = self.inner.clone();
         let app_state = self.app_state.clone();
+        let strict = self.strict;
+        let max_request_bytes = self.max_request_bytes;
         std::mem::swap(&mut self.inner, &mut inner);
         Box::pin(async move {
             let req = tokio::task::spawn_blocking(move || async move {
-                build_prompt_request(app_state, req)
+                build_prompt_request(app_state, strict, max_request_bytes, req)
                     .instrument(info_span!("build_prompt_request"))
                     .await
             })
@@ -95,6 +119,32 @@ where
     }
 }
 
+/// Buffers the request body, rejecting it without buffering more than
+/// `max_bytes` if it's larger.
+async fn collect_limited(
+    body: axum_core::body::Body,
+    max_bytes: Option<usize>,
+) -> Result<bytes::Bytes, ApiError> {
+    use http_body_util::{LengthLimitError, Limited};
+    let Some(max_bytes) = max_bytes else {
+        let body = body
+            .collect()
+            .await
+            .map_err(InternalError::CollectBodyError)?;
+        return Ok(body.to_bytes());
+    };
+    match Limited::new(body, max_bytes).collect().await {
+        Ok(body) => Ok(body.to_bytes()),
+        Err(e) if e.is::<LengthLimitError>() => {
+            Err(InvalidRequestError::MappedRequestTooLarge(max_bytes).into())
+        }
+        Err(e) => match e.downcast::<axum_core::Error>() {
+            Ok(e) => Err(InternalError::CollectBodyError(*e).into()),
+            Err(e) => Err(InternalError::RequestBodyError(e).into()),
+        },
+    }
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct Prompt2025Version {
     id: String,
@@ -102,14 +152,12 @@ struct Prompt2025Version {
 
 async fn build_prompt_request(
     app_state: AppState,
+    strict: bool,
+    max_request_bytes: Option<usize>,
     req: Request,
 ) -> Result<Request, ApiError> {
     let (parts, body) = req.into_parts();
-    let body_bytes = body
-        .collect()
-        .await
-        .map_err(InternalError::CollectBodyError)?
-        .to_bytes();
+    let body_bytes = collect_limited(body, max_request_bytes).await?;
 
     let request_json: serde_json::Value = serde_json::from_slice(&body_bytes)
         .map_err(|e| {
@@ -122,10 +170,20 @@ async fn build_prompt_request(
         return Ok(req);
     }
 
-    let Ok(mut prompt_ctx) = get_prompt_params(&request_json) else {
-        let req =
-            Request::from_parts(parts, axum_core::body::Body::from(body_bytes));
-        return Ok(req);
+    let mut prompt_ctx = match get_prompt_params(&request_json) {
+        Ok(prompt_ctx) => prompt_ctx,
+        Err(e)
+            if app_state.config().prompts.malformed_params
+                == MalformedPromptParams::Ignore =>
+        {
+            tracing::warn!(error = %e, "ignoring malformed prompt parameters");
+            let req = Request::from_parts(
+                parts,
+                axum_core::body::Body::from(body_bytes),
+            );
+            return Ok(req);
+        }
+        Err(e) => return Err(e.into()),
     };
     // TODO: Insert to extensions later and process in RequestLog
 
@@ -175,13 +233,35 @@ async fn build_prompt_request(
     let merged_body =
         merge_prompt_with_request(prompt_body_json, &request_json)?;
 
-    let processed_body = process_prompt_variables(merged_body, &prompt_ctx)?;
+    let strict = strict
+        || parts
+            .headers
+            .get(PROMPT_STRICT_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<bool>().ok())
+            .unwrap_or(false);
+    if strict {
+        check_missing_variables(&merged_body, prompt_ctx.inputs.as_ref())?;
+    }
+    if app_state.config().prompts.log_resolution {
+        log_resolution(&prompt_ctx, &merged_body);
+    }
+
+    let processed_body = process_prompt_variables(
+        merged_body,
+        &prompt_ctx,
+        app_state.config().prompts.max_schema_depth,
+    )?;
 
     let merged_bytes = serde_json::to_vec(&processed_body)
         .map_err(|_| ApiError::Internal(InternalError::Internal))?;
 
     let mut parts = parts;
     parts.extensions.insert(prompt_ctx);
+    AppliedTransformations::record(
+        &mut parts.extensions,
+        Transformation::Prompt,
+    );
 
     let req =
         Request::from_parts(parts, axum_core::body::Body::from(merged_bytes));
@@ -191,8 +271,8 @@ async fn build_prompt_request(
 fn get_prompt_params(
     request_json: &Value,
 ) -> Result<PromptContext, InvalidRequestError> {
-    let prompt_ctx = serde_json::from_value(request_json.clone())?;
-    Ok(prompt_ctx)
+    serde_json::from_value(request_json.clone())
+        .map_err(InvalidRequestError::MalformedPromptParams)
 }
 
 async fn get_prompt_version(
@@ -285,9 +365,19 @@ fn merge_prompt_with_request(
     Ok(prompt_body)
 }
 
+/// Matches `{{hc:name:type}}` variables, with an optional default used when
+/// no input is given for the variable, e.g. `{{hc:name:string:World}}`.
+const VARIABLE_PATTERN: &str = r"\{\{\s*hc\s*:\s*([a-zA-Z_-][a-zA-Z0-9_-]*)\s*:\s*([a-zA-Z_-][a-zA-Z0-9_-]*)(?:\s*:\s*([^{}]*?))?\s*\}\}";
+
+fn variable_regex() -> Result<Regex, ApiError> {
+    Regex::new(VARIABLE_PATTERN)
+        .map_err(|_| ApiError::Internal(InternalError::Internal))
+}
+
 fn process_prompt_variables(
     mut body: serde_json::Value,
     prompt_ctx: &PromptContext,
+    max_schema_depth: usize,
 ) -> Result<serde_json::Value, ApiError> {
     let Some(inputs) = &prompt_ctx.inputs else {
         return Ok(body);
@@ -297,8 +387,7 @@ fn process_prompt_variables(
         return Ok(body);
     };
 
-    let variable_regex = Regex::new(r"\{\{\s*hc\s*:\s*([a-zA-Z_-][a-zA-Z0-9_-]*)\s*:\s*([a-zA-Z_-][a-zA-Z0-9_-]*)\s*\}\}")
-        .map_err(|_| ApiError::Internal(InternalError::Internal))?;
+    let variable_regex = variable_regex()?;
 
     if let Some(messages_value) = body_obj.get_mut("messages")
         && let Some(messages_array) = messages_value.as_array_mut()
@@ -320,6 +409,7 @@ fn process_prompt_variables(
             response_format_value.clone(),
             inputs,
             &variable_regex,
+            SchemaDepth::new(max_schema_depth),
         )?;
         body_obj
             .insert("response_format".to_string(), processed_response_format);
@@ -330,6 +420,7 @@ fn process_prompt_variables(
             tools_value.clone(),
             inputs,
             &variable_regex,
+            SchemaDepth::new(max_schema_depth),
         )?;
         body_obj.insert("tools".to_string(), processed_tools);
     }
@@ -337,10 +428,175 @@ fn process_prompt_variables(
     Ok(body)
 }
 
+/// Returns an error listing every variable referenced by the messages,
+/// tools, or response format of the body that has neither an input nor a
+/// default.
+///
+/// Variables of block helpers aren't required, since missing inputs are
+/// falsy there.
+fn check_missing_variables(
+    body: &Value,
+    inputs: Option<&HashMap<String, Value>>,
+) -> Result<(), ApiError> {
+    let variable_regex = variable_regex()?;
+    let mut missing = Vec::new();
+    for field in ["messages", "tools", "response_format"] {
+        if let Some(value) = body.get(field) {
+            collect_missing_variables(
+                value,
+                inputs,
+                &variable_regex,
+                &mut missing,
+            );
+        }
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(InvalidRequestError::InvalidPromptInputs(format!(
+        "Missing inputs for prompt variables: {}",
+        missing.join(", ")
+    ))
+    .into())
+}
+
+/// Logs which prompt version was resolved for the request, and the names of
+/// the variables substituted in the messages, tools, or response format of
+/// the body, either with an input or with their default.
+///
+/// Only variable names are logged, since inputs may contain sensitive data.
+fn log_resolution(prompt_ctx: &PromptContext, body: &Value) {
+    let Ok(variable_regex) = variable_regex() else {
+        return;
+    };
+    let inputs = prompt_ctx.inputs.as_ref();
+    let mut substituted = Vec::new();
+    let mut defaulted = Vec::new();
+    for field in ["messages", "tools", "response_format"] {
+        if let Some(value) = body.get(field) {
+            visit_strings(value, &mut |text| {
+                for caps in variable_regex.captures_iter(text) {
+                    let name = &caps[1];
+                    let has_input =
+                        inputs.is_some_and(|inputs| inputs.contains_key(name));
+                    let resolved = if has_input {
+                        &mut substituted
+                    } else if caps.get(3).is_some() {
+                        &mut defaulted
+                    } else {
+                        continue;
+                    };
+                    if !resolved.iter().any(|r| r == name) {
+                        resolved.push(name.to_string());
+                    }
+                }
+            });
+        }
+    }
+    tracing::info!(
+        prompt_id = %prompt_ctx.prompt_id,
+        prompt_version_id = prompt_ctx.prompt_version_id.as_deref(),
+        substituted = ?substituted,
+        defaulted = ?defaulted,
+        "resolved prompt template"
+    );
+}
+
+/// Calls `f` with every string, including object keys, in `value`.
+fn visit_strings(value: &Value, f: &mut impl FnMut(&str)) {
+    match value {
+        Value::String(text) => f(text),
+        Value::Array(items) => {
+            for item in items {
+                visit_strings(item, f);
+            }
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                f(key);
+                visit_strings(value, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_missing_variables(
+    value: &Value,
+    inputs: Option<&HashMap<String, Value>>,
+    variable_regex: &Regex,
+    missing: &mut Vec<String>,
+) {
+    let check = |text: &str, missing: &mut Vec<String>| {
+        for caps in variable_regex.captures_iter(text) {
+            let name = &caps[1];
+            let has_input =
+                inputs.is_some_and(|inputs| inputs.contains_key(name));
+            if caps.get(3).is_none()
+                && !has_input
+                && !missing.iter().any(|m| m == name)
+            {
+                missing.push(name.to_string());
+            }
+        }
+    };
+    match value {
+        Value::String(text) => check(text, missing),
+        Value::Array(items) => {
+            for item in items {
+                collect_missing_variables(
+                    item,
+                    inputs,
+                    variable_regex,
+                    missing,
+                );
+            }
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                check(key, missing);
+                collect_missing_variables(
+                    value,
+                    inputs,
+                    variable_regex,
+                    missing,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Tracks how deeply nested the value currently being processed by
+/// [`process_prompt_schema`] is, so that deeply nested schemas are rejected
+/// before they can overflow the stack.
+#[derive(Debug, Clone, Copy)]
+struct SchemaDepth {
+    current: usize,
+    max: usize,
+}
+
+impl SchemaDepth {
+    fn new(max: usize) -> Self {
+        Self { current: 0, max }
+    }
+
+    fn nested(self) -> Result<Self, InvalidRequestError> {
+        if self.current >= self.max {
+            return Err(InvalidRequestError::PromptSchemaTooDeep(self.max));
+        }
+        Ok(Self {
+            current: self.current + 1,
+            max: self.max,
+        })
+    }
+}
+
 fn process_prompt_schema(
     value: serde_json::Value,
     inputs: &std::collections::HashMap<String, serde_json::Value>,
     variable_regex: &Regex,
+    depth: SchemaDepth,
 ) -> Result<serde_json::Value, ApiError> {
     // Any KV in a tool or response schema can have a variable, with two cases:
     // "{{hc:name:type}}" or "{{hc:name:type}} world." If the former, then
@@ -368,15 +624,17 @@ fn process_prompt_schema(
             Ok(serde_json::Value::String(processed_text))
         }
         serde_json::Value::Array(arr) => {
+            let depth = depth.nested()?;
             let mut processed_array = Vec::new();
             for item in arr {
                 let processed_item =
-                    process_prompt_schema(item, inputs, variable_regex)?;
+                    process_prompt_schema(item, inputs, variable_regex, depth)?;
                 processed_array.push(processed_item);
             }
             Ok(serde_json::Value::Array(processed_array))
         }
         serde_json::Value::Object(obj) => {
+            let depth = depth.nested()?;
             let mut processed_object = serde_json::Map::new();
             for (key, val) in obj {
                 let processed_key =
@@ -412,7 +670,7 @@ fn process_prompt_schema(
                     };
 
                 let processed_value =
-                    process_prompt_schema(val, inputs, variable_regex)?;
+                    process_prompt_schema(val, inputs, variable_regex, depth)?;
                 processed_object.insert(processed_key, processed_value);
             }
             Ok(serde_json::Value::Object(processed_object))
@@ -496,6 +754,8 @@ fn replace_variables(
     variable_regex: &Regex,
     validated_variables: &mut std::collections::HashSet<String>,
 ) -> Result<String, ApiError> {
+    let text = template::render_blocks(text, inputs)?;
+    let text = text.as_ref();
     for caps in variable_regex.captures_iter(text) {
         let variable_name =
             caps.get(1).ok_or(InvalidRequestError::InvalidPromptInputs(
@@ -513,15 +773,23 @@ fn replace_variables(
         if let Some(value) = inputs.get(variable_name.as_str()) {
             validate_variable_type(value, variable_type.as_str())?;
             validated_variables.insert(variable_name.as_str().to_string());
+        } else if let Some(default) = caps.get(3) {
+            // defaults may differ between occurrences of a variable, so
+            // they're validated every time
+            validate_variable_type(
+                &Value::String(default.as_str().to_string()),
+                variable_type.as_str(),
+            )?;
         }
     }
 
     let result = variable_regex.replace_all(text, |caps: &regex::Captures| {
         let variable_name = &caps[1];
-        inputs.get(variable_name).map_or_else(
-            || caps.get(0).unwrap().as_str().to_string(),
-            std::string::ToString::to_string,
-        )
+        match (inputs.get(variable_name), caps.get(3)) {
+            (Some(value), _) => value.to_string(),
+            (None, Some(default)) => default.as_str().to_string(),
+            (None, None) => caps[0].to_string(),
+        }
     });
 
     Ok(result.to_string())
@@ -531,7 +799,10 @@ fn validate_variable_type(
     value: &serde_json::Value,
     expected_type: &str,
 ) -> Result<String, ApiError> {
-    let value_string = value.to_string();
+    let value_string = match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    };
 
     match expected_type {
         "number" => {
@@ -570,3 +841,278 @@ fn validate_variable_type(
         _ => Ok(value_string),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    /// Builds a schema with `depth` nested objects around a templated
+    /// string.
+    fn nested_schema(depth: usize) -> Value {
+        let mut schema = json!("{{hc:name:string}}");
+        for _ in 0..depth {
+            schema = json!({ "properties": schema });
+        }
+        schema
+    }
+
+    fn prompt_ctx(inputs: HashMap<String, Value>) -> PromptContext {
+        PromptContext {
+            inputs: Some(inputs),
+            prompt_id: "prompt".to_string(),
+            prompt_version_id: None,
+        }
+    }
+
+    #[test]
+    fn schema_within_max_depth_is_processed() {
+        let prompt_ctx = prompt_ctx(HashMap::from([(
+            "name".to_string(),
+            json!("helicone"),
+        )]));
+        let body = json!({ "response_format": nested_schema(8) });
+        let processed = process_prompt_variables(body, &prompt_ctx, 8).unwrap();
+        let pointer = format!("/response_format{}", "/properties".repeat(8));
+        assert_eq!(processed.pointer(&pointer), Some(&json!("helicone")));
+    }
+
+    #[test]
+    fn schema_exceeding_max_depth_is_rejected() {
+        let prompt_ctx = prompt_ctx(HashMap::from([(
+            "name".to_string(),
+            json!("helicone"),
+        )]));
+        let body = json!({ "tools": nested_schema(9) });
+        let result = process_prompt_variables(body, &prompt_ctx, 8);
+        assert!(matches!(
+            result,
+            Err(ApiError::InvalidRequest(
+                InvalidRequestError::PromptSchemaTooDeep(8)
+            ))
+        ));
+    }
+
+    #[test]
+    fn message_blocks_are_rendered_before_variables() {
+        let prompt_ctx = PromptContext {
+            inputs: Some(HashMap::from([
+                ("name".to_string(), json!(7)),
+                ("greet".to_string(), json!(true)),
+                ("steps".to_string(), json!(["plan", "build"])),
+            ])),
+            prompt_id: "prompt".to_string(),
+            prompt_version_id: None,
+        };
+        let body = json!({
+            "messages": [{
+                "role": "user",
+                "content": "{{#if hc:greet:boolean}}Hi {{hc:name:number}}. \
+                            {{/if}}{{#each hc:steps:array}}{{this}};{{/each}}\
+                            {{#if hc:missing:boolean}}never{{/if}}"
+            }]
+        });
+        let processed = process_prompt_variables(body, &prompt_ctx, 8).unwrap();
+        assert_eq!(
+            processed["messages"][0]["content"],
+            json!("Hi 7. plan;build;")
+        );
+    }
+
+    #[test]
+    fn missing_variables_fall_back_to_defaults() {
+        let inputs = HashMap::from([("count".to_string(), json!(3))]);
+        let text = "Hello {{hc:name:string:World}}, {{hc:count:number:1}} {{ \
+                    hc:unit:string : apples }}{{hc:other:string}}";
+        let replaced = replace_variables(
+            text,
+            &inputs,
+            &variable_regex().unwrap(),
+            &mut HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(replaced, "Hello World, 3 apples{{hc:other:string}}");
+    }
+
+    #[test]
+    fn defaults_are_type_validated() {
+        let result = replace_variables(
+            "{{hc:count:number:many}}",
+            &HashMap::new(),
+            &variable_regex().unwrap(),
+            &mut HashSet::new(),
+        );
+        assert!(matches!(
+            result,
+            Err(ApiError::InvalidRequest(
+                InvalidRequestError::InvalidPromptInputs(_)
+            ))
+        ));
+        let replaced = replace_variables(
+            "{{hc:verbose:boolean:yes}}",
+            &HashMap::new(),
+            &variable_regex().unwrap(),
+            &mut HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(replaced, "yes");
+    }
+
+    #[tokio::test]
+    async fn strict_mode_lists_every_missing_variable() {
+        let request = json!({
+            "messages": [{
+                "role": "user",
+                "content": "Hi {{hc:name:string}}, {{hc:greeting:string:hello}} \
+                            {{#if hc:vip:boolean}}VIP{{/if}}"
+            }],
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "lookup",
+                    "parameters": { "city": "{{hc:city:string}}" }
+                }
+            }],
+            "response_format": { "type": "{{hc:name:string}}" }
+        });
+        let inputs = HashMap::from([("vip".to_string(), json!(true))]);
+        let error =
+            check_missing_variables(&request, Some(&inputs)).unwrap_err();
+        let response = axum_core::response::IntoResponse::into_response(error);
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let message = body["error"]["message"].as_str().unwrap();
+        assert!(
+            message.contains("name, city"),
+            "unexpected message: {message}"
+        );
+
+        let inputs = HashMap::from([
+            ("name".to_string(), json!("Ada")),
+            ("city".to_string(), json!("Paris")),
+        ]);
+        assert!(check_missing_variables(&request, Some(&inputs)).is_ok());
+    }
+
+    #[test]
+    fn malformed_prompt_params_are_an_error() {
+        let params = get_prompt_params(&json!({
+            "prompt_id": "prompt-123",
+            "inputs": { "name": "Ada" },
+            "messages": []
+        }))
+        .unwrap();
+        assert_eq!(params.prompt_id, "prompt-123");
+
+        for request in [
+            json!({ "prompt_id": 123, "messages": [] }),
+            json!({ "prompt_id": "prompt-123", "inputs": ["Ada"] }),
+            json!({ "prompt_id": "prompt-123", "prompt_version_id": false }),
+        ] {
+            let error = get_prompt_params(&request).unwrap_err();
+            assert!(
+                matches!(error, InvalidRequestError::MalformedPromptParams(_)),
+                "unexpected error for {request}: {error:?}"
+            );
+            assert!(
+                error
+                    .to_string()
+                    .starts_with("Malformed prompt parameters: "),
+                "unexpected message: {error}"
+            );
+        }
+    }
+
+    /// Collects the formatted log lines written within `f`.
+    fn captured_logs(f: impl FnOnce()) -> String {
+        #[derive(Clone, Default)]
+        struct Writer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Writer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let writer = Writer::default();
+        let captured = writer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, f);
+        let logs = captured.0.lock().unwrap().clone();
+        String::from_utf8(logs).unwrap()
+    }
+
+    #[test]
+    fn resolution_is_logged_without_variable_values() {
+        let prompt_ctx = PromptContext {
+            inputs: Some(HashMap::from([
+                ("name".to_string(), json!("Ada Lovelace")),
+                ("city".to_string(), json!("Secret City")),
+                ("unused".to_string(), json!("Hidden Value")),
+            ])),
+            prompt_id: "prompt-123".to_string(),
+            prompt_version_id: Some("version-456".to_string()),
+        };
+        let body = json!({
+            "messages": [{
+                "role": "user",
+                "content": "Hi {{hc:name:string}}, {{hc:greeting:string:hello}}"
+            }],
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "lookup",
+                    "parameters": { "city": "{{hc:city:string}}" }
+                }
+            }]
+        });
+
+        let logs = captured_logs(|| log_resolution(&prompt_ctx, &body));
+        assert!(logs.contains("resolved prompt template"), "logs: {logs}");
+        assert!(logs.contains("prompt_id=prompt-123"), "logs: {logs}");
+        assert!(
+            logs.contains("prompt_version_id=\"version-456\""),
+            "logs: {logs}"
+        );
+        assert!(
+            logs.contains(r#"substituted=["name", "city"]"#),
+            "logs: {logs}"
+        );
+        assert!(logs.contains(r#"defaulted=["greeting"]"#), "logs: {logs}");
+        for value in ["Ada Lovelace", "Secret City", "Hidden Value", "hello"] {
+            assert!(!logs.contains(value), "{value} was logged: {logs}");
+        }
+    }
+
+    #[test]
+    fn deeply_nested_arrays_are_rejected() {
+        let mut schema = json!([]);
+        for _ in 0..1_000 {
+            schema = json!([schema]);
+        }
+        let result = process_prompt_schema(
+            schema,
+            &HashMap::new(),
+            &variable_regex().unwrap(),
+            SchemaDepth::new(64),
+        );
+        assert!(matches!(
+            result,
+            Err(ApiError::InvalidRequest(
+                InvalidRequestError::PromptSchemaTooDeep(64)
+            ))
+        ));
+    }
+}
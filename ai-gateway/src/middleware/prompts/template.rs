@@ -0,0 +1,322 @@
+//! Block helpers for prompt templates:
+//!
+//! - `{{#if hc:name:type}}...{{else}}...{{/if}}` renders its first branch if
+//!   the input is truthy and its `else` branch (if any) otherwise. Inputs that
+//!   are not provided are falsy.
+//! - `{{#each hc:name:type}}...{{/each}}` renders its body once per item of an
+//!   array input, with `{{this}}` replaced by the item.
+//!
+//! Blocks are rendered before the `{{hc:name:type}}` variables, which are
+//! left untouched.
+use std::{borrow::Cow, collections::HashMap, sync::LazyLock};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::error::{api::ApiError, invalid_req::InvalidRequestError};
+
+static TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\{\{\s*(?:#(if|each)\s+hc\s*:\s*([a-zA-Z_-][a-zA-Z0-9_-]*)\s*:\s*[a-zA-Z_-][a-zA-Z0-9_-]*|(else|/if|/each|this))\s*\}\}",
+    )
+    .expect("always valid if tests pass")
+});
+
+/// Blocks nested deeper than this are rejected, so that a template can't
+/// overflow the stack.
+const MAX_NESTING: usize = 32;
+
+#[derive(Debug)]
+enum Node<'a> {
+    Text(&'a str),
+    This,
+    If {
+        name: &'a str,
+        then: Vec<Node<'a>>,
+        otherwise: Vec<Node<'a>>,
+    },
+    Each {
+        name: &'a str,
+        body: Vec<Node<'a>>,
+    },
+}
+
+/// A block that has been opened but not yet closed while parsing.
+struct OpenBlock<'a> {
+    kind: &'a str,
+    name: &'a str,
+    nodes: Vec<Node<'a>>,
+    /// The nodes before the `{{else}}` of an `if` block, once seen.
+    then: Option<Vec<Node<'a>>>,
+}
+
+/// Renders the block helpers in `text`.
+pub(super) fn render_blocks<'a>(
+    text: &'a str,
+    inputs: &HashMap<String, Value>,
+) -> Result<Cow<'a, str>, ApiError> {
+    if !text.contains("{{") || !TAG.is_match(text) {
+        return Ok(Cow::Borrowed(text));
+    }
+    let nodes = parse(text)?;
+    let mut rendered = String::with_capacity(text.len());
+    render(&nodes, inputs, None, &mut rendered)?;
+    Ok(Cow::Owned(rendered))
+}
+
+fn invalid(message: impl Into<String>) -> ApiError {
+    InvalidRequestError::InvalidPromptInputs(message.into()).into()
+}
+
+fn parse(text: &str) -> Result<Vec<Node<'_>>, ApiError> {
+    let mut root = Vec::new();
+    let mut open: Vec<OpenBlock<'_>> = Vec::new();
+    let mut last_end = 0;
+    for caps in TAG.captures_iter(text) {
+        let tag = caps.get(0).expect("capture group 0 always matches");
+        let nodes = open.last_mut().map_or(&mut root, |block| &mut block.nodes);
+        if tag.start() > last_end {
+            nodes.push(Node::Text(&text[last_end..tag.start()]));
+        }
+        last_end = tag.end();
+
+        if let (Some(kind), Some(name)) = (caps.get(1), caps.get(2)) {
+            if open.len() >= MAX_NESTING {
+                return Err(invalid(format!(
+                    "Prompt blocks can be nested at most {MAX_NESTING} deep"
+                )));
+            }
+            open.push(OpenBlock {
+                kind: kind.as_str(),
+                name: name.as_str(),
+                nodes: Vec::new(),
+                then: None,
+            });
+            continue;
+        }
+        match &caps[3] {
+            "this" => nodes.push(Node::This),
+            "else" => match open.last_mut() {
+                Some(block) if block.kind == "if" && block.then.is_none() => {
+                    block.then = Some(std::mem::take(&mut block.nodes));
+                }
+                _ => return Err(invalid("Unexpected {{else}} in prompt")),
+            },
+            closing => {
+                let kind = &closing[1..];
+                let block = match open.pop() {
+                    Some(block) if block.kind == kind => block,
+                    _ => {
+                        return Err(invalid(format!(
+                            "Unexpected {{{{{closing}}}}} in prompt"
+                        )));
+                    }
+                };
+                let node = if kind == "if" {
+                    match block.then {
+                        Some(then) => Node::If {
+                            name: block.name,
+                            then,
+                            otherwise: block.nodes,
+                        },
+                        None => Node::If {
+                            name: block.name,
+                            then: block.nodes,
+                            otherwise: Vec::new(),
+                        },
+                    }
+                } else {
+                    Node::Each {
+                        name: block.name,
+                        body: block.nodes,
+                    }
+                };
+                open.last_mut()
+                    .map_or(&mut root, |block| &mut block.nodes)
+                    .push(node);
+            }
+        }
+    }
+    if let Some(block) = open.last() {
+        return Err(invalid(format!(
+            "Unclosed {{{{#{} hc:{}}}}} in prompt",
+            block.kind, block.name
+        )));
+    }
+    if last_end < text.len() {
+        root.push(Node::Text(&text[last_end..]));
+    }
+    Ok(root)
+}
+
+fn render(
+    nodes: &[Node<'_>],
+    inputs: &HashMap<String, Value>,
+    this: Option<&Value>,
+    out: &mut String,
+) -> Result<(), ApiError> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::This => match this {
+                Some(Value::String(item)) => out.push_str(item),
+                Some(item) => out.push_str(&item.to_string()),
+                // outside of an each block, left for the caller to reject
+                None => out.push_str("{{this}}"),
+            },
+            Node::If {
+                name,
+                then,
+                otherwise,
+            } => {
+                let branch = if inputs.get(*name).is_some_and(is_truthy) {
+                    then
+                } else {
+                    otherwise
+                };
+                render(branch, inputs, this, out)?;
+            }
+            Node::Each { name, body } => {
+                let items = match inputs.get(*name) {
+                    None | Some(Value::Null) => continue,
+                    Some(Value::Array(items)) => Cow::Borrowed(items),
+                    // arrays may also be passed as JSON encoded strings
+                    Some(Value::String(s)) => {
+                        match serde_json::from_str::<Value>(s) {
+                            Ok(Value::Array(items)) => Cow::Owned(items),
+                            _ => return Err(not_an_array(name)),
+                        }
+                    }
+                    Some(_) => return Err(not_an_array(name)),
+                };
+                for item in items.iter() {
+                    render(body, inputs, Some(item), out)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn not_an_array(name: &str) -> ApiError {
+    invalid(format!(
+        "Variable '{name}' used in an each block must be an array"
+    ))
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n.abs() > 0.0),
+        Value::String(s) => !matches!(
+            s.trim().to_lowercase().as_str(),
+            "" | "false" | "no" | "0"
+        ),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn inputs(inputs: Value) -> HashMap<String, Value> {
+        serde_json::from_value(inputs).unwrap()
+    }
+
+    #[test]
+    fn if_else_renders_matching_branch() {
+        let template =
+            "Be {{#if hc:formal:boolean}}formal{{else}}casual{{/if}}.";
+        let rendered =
+            render_blocks(template, &inputs(json!({ "formal": true })))
+                .unwrap();
+        assert_eq!(rendered, "Be formal.");
+        let rendered =
+            render_blocks(template, &inputs(json!({ "formal": "no" })))
+                .unwrap();
+        assert_eq!(rendered, "Be casual.");
+    }
+
+    #[test]
+    fn each_renders_every_item_of_array() {
+        let template = "Topics:{{#each hc:topics:array}} [{{this}}]{{/each}}";
+        let rendered = render_blocks(
+            template,
+            &inputs(json!({ "topics": ["rust", 42, { "a": 1 }] })),
+        )
+        .unwrap();
+        assert_eq!(rendered, r#"Topics: [rust] [42] [{"a":1}]"#);
+
+        let rendered = render_blocks(
+            template,
+            &inputs(json!({ "topics": "[\"json\", \"strings\"]" })),
+        )
+        .unwrap();
+        assert_eq!(rendered, "Topics: [json] [strings]");
+    }
+
+    #[test]
+    fn missing_variables_are_falsy() {
+        let template = "{{#if hc:missing:boolean}}shown{{/if}}{{#each \
+                        hc:missing:array}}{{this}}{{/each}}done";
+        let rendered = render_blocks(template, &HashMap::new()).unwrap();
+        assert_eq!(rendered, "done");
+    }
+
+    #[test]
+    fn nested_blocks_are_rendered() {
+        let template = "{{#each hc:items:array}}{{#if \
+                        hc:loud:boolean}}{{this}}!{{else}}{{this}}.{{/if}}{{/\
+                        each}}";
+        let rendered = render_blocks(
+            template,
+            &inputs(json!({ "items": ["a", "b"], "loud": true })),
+        )
+        .unwrap();
+        assert_eq!(rendered, "a!b!");
+    }
+
+    #[test]
+    fn simple_variables_are_left_untouched() {
+        let template =
+            "Hi {{hc:name:string}}{{#if hc:vip:boolean}}, welcome back{{/if}}";
+        let rendered =
+            render_blocks(template, &inputs(json!({ "vip": false }))).unwrap();
+        assert_eq!(rendered, "Hi {{hc:name:string}}");
+        assert!(matches!(
+            render_blocks("{{hc:name:string}}", &HashMap::new()).unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn unbalanced_blocks_are_rejected() {
+        for template in [
+            "{{#if hc:a:boolean}}open",
+            "close{{/if}}",
+            "{{#if hc:a:boolean}}{{/each}}",
+            "{{#each hc:a:array}}{{else}}{{/each}}",
+        ] {
+            assert!(
+                render_blocks(template, &HashMap::new()).is_err(),
+                "{template}"
+            );
+        }
+        let deep = "{{#if hc:a:boolean}}".repeat(MAX_NESTING + 1);
+        assert!(render_blocks(&deep, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn each_over_non_array_is_rejected() {
+        let result = render_blocks(
+            "{{#each hc:items:array}}{{this}}{{/each}}",
+            &inputs(json!({ "items": 3 })),
+        );
+        assert!(result.is_err());
+    }
+}
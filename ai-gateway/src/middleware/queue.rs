@@ -0,0 +1,307 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::{StreamExt, future::BoxFuture};
+use http_body_util::BodyExt;
+use serde_json::Value;
+use tokio::sync::oneshot;
+use tower::ServiceExt;
+
+use crate::{
+    config::{
+        queue::{ModelTier, QueueConfig},
+        router::RouterConfig,
+    },
+    error::{api::ApiError, internal::InternalError},
+    types::{model_id::ModelId, request::Request, response::Response},
+};
+
+/// Admits up to `max_concurrent` requests at once, and queues the rest by
+/// tier.
+#[derive(Debug)]
+struct AdmissionQueue {
+    config: QueueConfig,
+    state: Mutex<QueueState>,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    running: usize,
+    waiting: BinaryHeap<Waiter>,
+    /// Orders waiters of the same tier by arrival.
+    next_seq: u64,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    tier: ModelTier,
+    seq: u64,
+    tx: oneshot::Sender<Permit>,
+}
+
+impl Waiter {
+    fn priority(&self) -> (ModelTier, Reverse<u64>) {
+        (self.tier, Reverse(self.seq))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority() == other.priority()
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority().cmp(&other.priority())
+    }
+}
+
+impl AdmissionQueue {
+    /// Waits until a request for a model of the given tier may be
+    /// dispatched.
+    async fn acquire(self: &Arc<Self>, tier: ModelTier) -> Permit {
+        let rx = {
+            let mut state = self.state.lock().expect("queue lock poisoned");
+            if state.running < self.config.max_concurrent.get()
+                && state.waiting.is_empty()
+            {
+                state.running += 1;
+                return Permit::new(self);
+            }
+            let (tx, rx) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiting.push(Waiter { tier, seq, tx });
+            tracing::trace!(tier = ?tier, waiting = state.waiting.len(), "request queued");
+            rx
+        };
+        // the sender is only dropped after a permit is sent
+        rx.await.expect("queued requests are always admitted")
+    }
+
+    /// Hands a finished request's slot to the highest priority waiter, or
+    /// frees it if nothing is waiting.
+    fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock().expect("queue lock poisoned");
+        while let Some(waiter) = state.waiting.pop() {
+            match waiter.tx.send(Permit::new(self)) {
+                Ok(()) => return,
+                // the waiting request was cancelled, so its permit is
+                // discarded rather than released, which would deadlock
+                Err(mut permit) => permit.queue = None,
+            }
+        }
+        state.running = state.running.saturating_sub(1);
+    }
+}
+
+/// A slot of the queue, released to the next waiter when dropped.
+#[derive(Debug)]
+struct Permit {
+    queue: Option<Arc<AdmissionQueue>>,
+}
+
+impl Permit {
+    fn new(queue: &Arc<AdmissionQueue>) -> Self {
+        Self {
+            queue: Some(Arc::clone(queue)),
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.take() {
+            queue.release();
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    queue: Option<Arc<AdmissionQueue>>,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn for_router(router_config: &RouterConfig) -> Self {
+        Self {
+            queue: router_config.queue.clone().map(|config| {
+                Arc::new(AdmissionQueue {
+                    config,
+                    state: Mutex::default(),
+                })
+            }),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    queue: Option<Arc<AdmissionQueue>>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = ApiError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        if self.queue.is_some() {
+            // the inner service is driven to readiness once the request is
+            // admitted, so that queued requests don't hold on to capacity of
+            // the inner service
+            Poll::Ready(Ok(()))
+        } else {
+            self.inner.poll_ready(cx)
+        }
+    }
+
+    #[tracing::instrument(name = "queue", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        let queue = self.queue.clone();
+        Box::pin(async move {
+            let Some(queue) = queue else {
+                return inner.call(req).await;
+            };
+            let (parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            let model =
+                serde_json::from_slice::<Value>(&body)
+                    .ok()
+                    .and_then(|body| {
+                        body.get("model")
+                            .and_then(Value::as_str)
+                            .and_then(|model| ModelId::from_str(model).ok())
+                    });
+            let tier = queue.config.tier(model.as_ref());
+
+            let permit = queue.acquire(tier).await;
+            let req =
+                Request::from_parts(parts, axum_core::body::Body::from(body));
+            let response = inner.ready().await?.call(req).await?;
+
+            // the permit is released once the body is dropped, i.e. when it
+            // has been read or the client disconnects
+            let (parts, body) = response.into_parts();
+            let body = body.into_data_stream().map(move |chunk| {
+                let _permit = &permit;
+                chunk
+            });
+            Ok(Response::from_parts(
+                parts,
+                axum_core::body::Body::from_stream(body),
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, num::NonZeroUsize};
+
+    use super::*;
+
+    fn queue() -> Arc<AdmissionQueue> {
+        Arc::new(AdmissionQueue {
+            config: QueueConfig {
+                max_concurrent: NonZeroUsize::new(1).unwrap(),
+                tiers: HashMap::new(),
+                default_tier: ModelTier::Standard,
+            },
+            state: Mutex::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn higher_tiers_are_admitted_first() {
+        let queue = queue();
+        let running = queue.acquire(ModelTier::Standard).await;
+
+        let (order_tx, mut order_rx) = tokio::sync::mpsc::unbounded_channel();
+        for (name, tier) in [
+            ("free", ModelTier::Free),
+            ("premium", ModelTier::Premium),
+            ("standard", ModelTier::Standard),
+            ("premium-2", ModelTier::Premium),
+        ] {
+            let queue = Arc::clone(&queue);
+            let order_tx = order_tx.clone();
+            tokio::spawn(async move {
+                let _permit = queue.acquire(tier).await;
+                order_tx.send(name).unwrap();
+            });
+            // let each request join the queue before the next one
+            tokio::task::yield_now().await;
+        }
+        drop(order_tx);
+        drop(running);
+
+        let mut order = Vec::new();
+        while let Some(name) = order_rx.recv().await {
+            order.push(name);
+        }
+        assert_eq!(order, ["premium", "premium-2", "standard", "free"]);
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiters_are_skipped() {
+        let queue = queue();
+        let running = queue.acquire(ModelTier::Standard).await;
+        let cancelled = tokio::spawn({
+            let queue = Arc::clone(&queue);
+            async move { queue.acquire(ModelTier::Premium).await }
+        });
+        tokio::task::yield_now().await;
+        cancelled.abort();
+        let _ = cancelled.await;
+
+        drop(running);
+        // the slot isn't leaked to the cancelled request
+        let _permit = queue.acquire(ModelTier::Free).await;
+    }
+}
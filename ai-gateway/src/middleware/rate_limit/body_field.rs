@@ -0,0 +1,211 @@
+use std::{
+    num::NonZeroU32,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use governor::{
+    RateLimiter,
+    clock::{Clock, DefaultClock},
+    state::keyed::DefaultKeyedStateStore,
+};
+use http_body_util::BodyExt;
+use serde_json::Value;
+
+use crate::{
+    config::{rate_limit::BodyFieldLimitConfig, router::RouterConfig},
+    error::{
+        api::ApiError,
+        internal::InternalError,
+        invalid_req::{InvalidRequestError, TooManyRequestsError},
+    },
+    types::{request::Request, response::Response},
+};
+
+/// Once this many distinct values are tracked, the state of values that
+/// have been fully refilled is discarded.
+const MAX_TRACKED_VALUES: usize = 10_000;
+
+type KeyedLimiter =
+    RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock>;
+
+/// Limits requests per distinct value of a field of the request body.
+#[derive(Debug)]
+struct BodyFieldLimiter {
+    pointer: String,
+    capacity: NonZeroU32,
+    limiter: KeyedLimiter,
+}
+
+impl BodyFieldLimiter {
+    fn new(config: &BodyFieldLimitConfig) -> Self {
+        Self {
+            pointer: config.pointer.clone(),
+            capacity: config.limit.capacity,
+            limiter: RateLimiter::keyed(config.limit.quota()),
+        }
+    }
+
+    /// The value of the limited field in the request body, if it's a string
+    /// or a number.
+    fn key(&self, body: &[u8]) -> Option<String> {
+        let body = serde_json::from_slice::<Value>(body).ok()?;
+        match body.pointer(&self.pointer)? {
+            Value::String(value) => Some(value.clone()),
+            Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    fn check(&self, key: &str) -> Result<(), TooManyRequestsError> {
+        let result = self.limiter.check_key(&key.to_string());
+        if self.limiter.len() > MAX_TRACKED_VALUES {
+            self.limiter.retain_recent();
+        }
+        result.map_err(|not_until| {
+            let wait = not_until.wait_time_from(self.limiter.clock().now());
+            tracing::debug!(
+                pointer = %self.pointer,
+                value = %key,
+                "body field rate limit exceeded"
+            );
+            TooManyRequestsError {
+                ratelimit_limit: u64::from(self.capacity.get()),
+                ratelimit_remaining: 0,
+                // add a second to prevent rounding errors
+                retry_after: wait.as_secs() + 1,
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    limiter: Option<Arc<BodyFieldLimiter>>,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn per_router(router_config: &RouterConfig) -> Self {
+        Self {
+            limiter: router_config
+                .rate_limit
+                .as_ref()
+                .and_then(|rate_limit| {
+                    rate_limit.limits.per_body_field.as_ref()
+                })
+                .map(|config| Arc::new(BodyFieldLimiter::new(config))),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    limiter: Option<Arc<BodyFieldLimiter>>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = ApiError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "body_field_rate_limit", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        let limiter = self.limiter.clone();
+        Box::pin(async move {
+            let Some(limiter) = limiter else {
+                return inner.call(req).await;
+            };
+            let (parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            if let Some(key) = limiter.key(&body) {
+                limiter
+                    .check(&key)
+                    .map_err(InvalidRequestError::TooManyRequests)?;
+            }
+            let req =
+                Request::from_parts(parts, axum_core::body::Body::from(body));
+            inner.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::rate_limit::GcraConfig;
+
+    fn limiter(pointer: &str) -> BodyFieldLimiter {
+        BodyFieldLimiter::new(&BodyFieldLimitConfig {
+            pointer: pointer.to_string(),
+            limit: GcraConfig {
+                refill_frequency: Duration::from_secs(60),
+                capacity: NonZeroU32::new(2).unwrap(),
+            },
+        })
+    }
+
+    #[test]
+    fn key_is_string_or_number_at_pointer() {
+        let limiter = limiter("/metadata/project_id");
+        let body = |value: Value| {
+            serde_json::to_vec(&json!({ "metadata": { "project_id": value } }))
+                .unwrap()
+        };
+        assert_eq!(limiter.key(&body(json!("a"))).as_deref(), Some("a"));
+        assert_eq!(limiter.key(&body(json!(7))).as_deref(), Some("7"));
+        assert_eq!(limiter.key(&body(json!({ "nested": 1 }))), None);
+        assert_eq!(limiter.key(b"{}"), None);
+        assert_eq!(limiter.key(b"not json"), None);
+    }
+
+    #[test]
+    fn each_value_has_its_own_limit() {
+        let limiter = limiter("/project_id");
+        assert!(limiter.check("a").is_ok());
+        assert!(limiter.check("a").is_ok());
+        let error = limiter.check("a").unwrap_err();
+        assert_eq!(error.ratelimit_limit, 2);
+        assert!(error.retry_after > 0);
+        assert!(limiter.check("b").is_ok());
+    }
+}
@@ -41,6 +41,9 @@ impl meltdown::Service for GarbageCollector {
                             for rate_limit_config in router_limits.values() {
                                 rate_limit_config.limiter().retain_recent();
                             }
+                            drop(router_limits);
+
+                            app_state.0.model_rate_limits.read().await.retain_recent();
                         }
                         () = &mut token => {
                             info!(name = "rate-limiting-cleanup-task", "task shutting down");
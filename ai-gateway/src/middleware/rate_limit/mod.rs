@@ -1,6 +1,9 @@
+pub mod body_field;
 pub mod cleanup;
 pub mod extractor;
+pub mod model;
 pub mod redis_service;
 pub mod service;
+pub mod tokens;
 
 pub use self::service::{Layer, Service};
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use governor::{
+    RateLimiter,
+    clock::{Clock, DefaultClock},
+    state::keyed::DefaultKeyedStateStore,
+};
+
+use crate::{
+    config::{rate_limit::GcraConfig, router::RouterConfig},
+    error::invalid_req::TooManyRequestsError,
+    types::{
+        model_id::{ModelId, ModelIdWithoutVersion},
+        router::RouterId,
+    },
+};
+
+type BucketKey = (RouterId, ModelIdWithoutVersion);
+type KeyedLimiter =
+    RateLimiter<BucketKey, DefaultKeyedStateStore<BucketKey>, DefaultClock>;
+
+/// Limits requests per model of each router, keeping a bucket per router and
+/// model.
+///
+/// Buckets of models with the same limit share a limiter, so that stale
+/// buckets can be evicted from all of them periodically.
+#[derive(Debug, Default)]
+pub struct ModelRateLimits {
+    limits: HashMap<BucketKey, GcraConfig>,
+    limiters: HashMap<GcraConfig, KeyedLimiter>,
+}
+
+impl ModelRateLimits {
+    /// Replaces the per model limits of a router with the ones in its
+    /// config.
+    pub fn register(&mut self, router_id: &RouterId, config: &RouterConfig) {
+        self.limits.retain(|(id, _), _| id != router_id);
+        let per_model = config
+            .rate_limit
+            .as_ref()
+            .map(|rate_limit| &rate_limit.limits.per_model);
+        for (model, limit) in per_model.into_iter().flatten() {
+            self.limiters
+                .entry(limit.clone())
+                .or_insert_with(|| RateLimiter::keyed(limit.quota()));
+            self.limits.insert(
+                (router_id.clone(), model.clone().into()),
+                limit.clone(),
+            );
+        }
+        self.limiters
+            .retain(|limit, _| self.limits.values().any(|l| l == limit));
+    }
+
+    /// Checks the limit of `model` for the router, if it has one.
+    pub fn check(
+        &self,
+        router_id: &RouterId,
+        model: &ModelId,
+    ) -> Result<(), TooManyRequestsError> {
+        if self.limits.is_empty() {
+            return Ok(());
+        }
+        let key = (router_id.clone(), model.clone().into());
+        let Some(limit) = self.limits.get(&key) else {
+            return Ok(());
+        };
+        let Some(limiter) = self.limiters.get(limit) else {
+            return Ok(());
+        };
+        limiter.check_key(&key).map_err(|not_until| {
+            let wait = not_until.wait_time_from(limiter.clock().now());
+            tracing::debug!(
+                router_id = %router_id,
+                model = %model,
+                "model rate limit exceeded"
+            );
+            TooManyRequestsError {
+                ratelimit_limit: u64::from(limit.capacity.get()),
+                ratelimit_remaining: 0,
+                // add a second to prevent rounding errors
+                retry_after: wait.as_secs() + 1,
+            }
+        })
+    }
+
+    /// Discards the buckets that have been fully refilled.
+    pub fn retain_recent(&self) {
+        for limiter in self.limiters.values() {
+            limiter.retain_recent();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroU32, str::FromStr, time::Duration};
+
+    use compact_str::CompactString;
+
+    use super::*;
+    use crate::config::rate_limit::{LimitsConfig, RateLimitConfig};
+
+    fn model(model: &str) -> ModelId {
+        ModelId::from_str(model).unwrap()
+    }
+
+    fn router_config(per_model: &[(&str, u32)]) -> RouterConfig {
+        RouterConfig {
+            rate_limit: Some(RateLimitConfig {
+                store: None,
+                limits: LimitsConfig {
+                    per_model: per_model
+                        .iter()
+                        .map(|(name, capacity)| {
+                            let limit = GcraConfig {
+                                refill_frequency: Duration::from_secs(60),
+                                capacity: NonZeroU32::new(*capacity).unwrap(),
+                            };
+                            (model(name), limit)
+                        })
+                        .collect(),
+                    ..Default::default()
+                },
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn router(name: &str) -> RouterId {
+        RouterId::Named(CompactString::new(name))
+    }
+
+    #[test]
+    fn each_router_and_model_has_its_own_bucket() {
+        let mut limits = ModelRateLimits::default();
+        let config = router_config(&[("openai/gpt-4o", 1)]);
+        limits.register(&router("a"), &config);
+        limits.register(&router("b"), &config);
+
+        let gpt_4o = model("openai/gpt-4o");
+        assert!(limits.check(&router("a"), &gpt_4o).is_ok());
+        let error = limits.check(&router("a"), &gpt_4o).unwrap_err();
+        assert_eq!(error.ratelimit_limit, 1);
+        assert!(error.retry_after > 0);
+        // versions of a model share its bucket
+        let dated = model("openai/gpt-4o-2024-08-06");
+        assert!(limits.check(&router("a"), &dated).is_err());
+
+        assert!(limits.check(&router("b"), &gpt_4o).is_ok());
+        for _ in 0..10 {
+            let mini = model("openai/gpt-4o-mini");
+            assert!(limits.check(&router("a"), &mini).is_ok());
+        }
+    }
+
+    #[test]
+    fn registering_a_router_replaces_its_limits() {
+        let mut limits = ModelRateLimits::default();
+        limits.register(&router("a"), &router_config(&[("openai/gpt-4o", 1)]));
+        limits.register(&router("a"), &RouterConfig::default());
+        assert!(limits.limits.is_empty());
+        assert!(limits.limiters.is_empty());
+        let gpt_4o = model("openai/gpt-4o");
+        for _ in 0..10 {
+            assert!(limits.check(&router("a"), &gpt_4o).is_ok());
+        }
+    }
+}
@@ -365,6 +365,9 @@ mod tests {
                 capacity: NonZeroU32::new(10).unwrap(),
                 refill_frequency: Duration::from_secs(1),
             },
+            per_body_field: None,
+            per_model: Default::default(),
+            token_limit: None,
         }
     }
 
@@ -0,0 +1,548 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::{StreamExt, future::BoxFuture};
+use http::{HeaderName, HeaderValue};
+use http_body_util::BodyExt;
+use serde_json::Value;
+
+use crate::{
+    config::{rate_limit::TokenLimitConfig, router::RouterConfig},
+    error::{
+        api::ApiError,
+        internal::InternalError,
+        invalid_req::{InvalidRequestError, TooManyRequestsError},
+    },
+    types::{
+        extensions::AuthContext, request::Request, response::Response,
+        user::UserId,
+    },
+};
+
+/// Rough number of bytes per token, used to estimate the prompt tokens of a
+/// request from the size of its body.
+const BYTES_PER_TOKEN: u64 = 4;
+/// Once this many API keys are tracked, the buckets that have been fully
+/// refilled are discarded.
+const MAX_TRACKED_KEYS: usize = 10_000;
+const TOKENS_REMAINING_HEADER: HeaderName =
+    HeaderName::from_static("helicone-ratelimit-tokens-remaining");
+
+#[derive(Debug)]
+struct Bucket {
+    /// Negative once a request used more tokens than were left.
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Limits the tokens consumed per API key.
+#[derive(Debug)]
+struct TokenLimiter {
+    capacity: u64,
+    refill_frequency: Duration,
+    buckets: Mutex<HashMap<UserId, Bucket>>,
+}
+
+impl TokenLimiter {
+    fn new(config: &TokenLimitConfig) -> Self {
+        Self {
+            capacity: config.capacity.get(),
+            refill_frequency: config.refill_frequency,
+            buckets: Mutex::default(),
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn tokens_per_sec(&self) -> f64 {
+        self.capacity as f64 / self.refill_frequency.as_secs_f64().max(1e-3)
+    }
+
+    /// Refills the bucket of `key` up to `now` and applies `update` to it,
+    /// returning its tokens afterwards.
+    fn with_bucket<T>(
+        &self,
+        key: UserId,
+        now: Instant,
+        update: impl FnOnce(&mut Bucket) -> T,
+    ) -> T {
+        #[allow(clippy::cast_precision_loss)]
+        let capacity = self.capacity as f64;
+        let tokens_per_sec = self.tokens_per_sec();
+        let refill = |bucket: &mut Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated);
+            bucket.tokens = (bucket.tokens
+                + elapsed.as_secs_f64() * tokens_per_sec)
+                .min(capacity);
+            bucket.updated = now;
+        };
+        let mut buckets =
+            self.buckets.lock().expect("token limit lock poisoned");
+        if buckets.len() > MAX_TRACKED_KEYS {
+            buckets.retain(|_, bucket| {
+                refill(bucket);
+                bucket.tokens < capacity
+            });
+        }
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        refill(bucket);
+        update(bucket)
+    }
+
+    /// Rejects requests estimated to use more tokens than the budget ever
+    /// holds, since waiting for it to refill would never admit them.
+    fn check_capacity(&self, estimate: u64) -> Result<(), InvalidRequestError> {
+        if estimate > self.capacity {
+            return Err(InvalidRequestError::TokenEstimateOverCapacity(
+                estimate,
+                self.capacity,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Takes the estimated tokens of a request from the budget of `key`,
+    /// returning the remaining budget.
+    fn reserve(
+        &self,
+        key: UserId,
+        estimate: u64,
+        now: Instant,
+    ) -> Result<u64, TooManyRequestsError> {
+        let tokens_per_sec = self.tokens_per_sec();
+        self.with_bucket(key, now, |bucket| {
+            #[allow(clippy::cast_precision_loss)]
+            let estimate = estimate as f64;
+            if bucket.tokens < estimate {
+                let wait = (estimate - bucket.tokens) / tokens_per_sec;
+                tracing::debug!(
+                    user_id = %key,
+                    remaining = bucket.tokens,
+                    estimate,
+                    "token rate limit exceeded"
+                );
+                return Err(TooManyRequestsError {
+                    ratelimit_limit: self.capacity,
+                    ratelimit_remaining: whole_tokens(bucket.tokens),
+                    // add a second to prevent rounding errors
+                    retry_after: Duration::try_from_secs_f64(wait)
+                        .map_or(0, |wait| wait.as_secs())
+                        + 1,
+                });
+            }
+            bucket.tokens -= estimate;
+            Ok(whole_tokens(bucket.tokens))
+        })
+    }
+
+    /// Replaces the estimated tokens of a request with the tokens it
+    /// actually used, returning the remaining budget.
+    fn reconcile(
+        &self,
+        key: UserId,
+        estimate: u64,
+        actual: u64,
+        now: Instant,
+    ) -> u64 {
+        self.with_bucket(key, now, |bucket| {
+            #[allow(clippy::cast_precision_loss)]
+            let difference = estimate as f64 - actual as f64;
+            bucket.tokens += difference;
+            whole_tokens(bucket.tokens)
+        })
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn whole_tokens(tokens: f64) -> u64 {
+    tokens.max(0.0).floor() as u64
+}
+
+/// Estimates the prompt tokens of a request from the size of its body.
+fn estimate_tokens(body: &[u8]) -> u64 {
+    u64::try_from(body.len()).unwrap_or(u64::MAX) / BYTES_PER_TOKEN
+}
+
+/// Reads the total tokens used from a response body, in either the `OpenAI`
+/// or `Anthropic` format.
+fn used_tokens(body: &[u8]) -> Option<u64> {
+    let mut usage = StreamUsage::default();
+    usage.observe(body);
+    usage.total()
+}
+
+/// The usage reported by the events of a streamed response.
+///
+/// `OpenAI` streams report the total in their final chunk, while `Anthropic`
+/// streams report input tokens when the message starts and output tokens as
+/// it goes on.
+#[derive(Debug, Default)]
+struct StreamUsage {
+    total: Option<u64>,
+    input: Option<u64>,
+    output: Option<u64>,
+}
+
+impl StreamUsage {
+    fn observe(&mut self, data: &[u8]) {
+        let Ok(value) = serde_json::from_slice::<Value>(data) else {
+            return;
+        };
+        let Some(usage) = value
+            .get("usage")
+            .or_else(|| value.pointer("/message/usage"))
+            .filter(|usage| !usage.is_null())
+        else {
+            return;
+        };
+        let tokens = |field| usage.get(field).and_then(Value::as_u64);
+        if let Some(total) = tokens("total_tokens") {
+            self.total = Some(total);
+        }
+        if let Some(input) = tokens("input_tokens") {
+            self.input = Some(input);
+        }
+        if let Some(output) = tokens("output_tokens") {
+            self.output = Some(output);
+        }
+    }
+
+    fn total(&self) -> Option<u64> {
+        self.total.or_else(|| Some(self.input? + self.output?))
+    }
+}
+
+/// Reads the usage from the events of a streamed response as they pass
+/// through, and reconciles the estimate of the request with it once the
+/// stream is finished or dropped.
+struct StreamReconciler {
+    limiter: Arc<TokenLimiter>,
+    user_id: UserId,
+    estimate: u64,
+    usage: StreamUsage,
+    /// The part of the stream after the last complete line.
+    line: Vec<u8>,
+}
+
+impl StreamReconciler {
+    fn observe(&mut self, chunk: &[u8]) {
+        self.line.extend_from_slice(chunk);
+        let Some(end) = self.line.iter().rposition(|b| *b == b'\n') else {
+            return;
+        };
+        let rest = self.line.split_off(end + 1);
+        let lines = std::mem::replace(&mut self.line, rest);
+        for line in lines.split(|b| *b == b'\n') {
+            if let Some(data) = line.strip_prefix(b"data:") {
+                self.usage.observe(data.trim_ascii());
+            }
+        }
+    }
+}
+
+impl Drop for StreamReconciler {
+    fn drop(&mut self) {
+        if let Some(used) = self.usage.total() {
+            self.limiter.reconcile(
+                self.user_id,
+                self.estimate,
+                used,
+                Instant::now(),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    limiter: Option<Arc<TokenLimiter>>,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn per_router(router_config: &RouterConfig) -> Self {
+        Self {
+            limiter: router_config
+                .rate_limit
+                .as_ref()
+                .and_then(|rate_limit| rate_limit.limits.token_limit.as_ref())
+                .map(|config| Arc::new(TokenLimiter::new(config))),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    limiter: Option<Arc<TokenLimiter>>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = ApiError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "token_rate_limit", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        let limiter = self.limiter.clone();
+        Box::pin(async move {
+            let Some(limiter) = limiter else {
+                return inner.call(req).await;
+            };
+            let Some(user_id) =
+                req.extensions().get::<AuthContext>().map(|ctx| ctx.user_id)
+            else {
+                // tokens can only be attributed to authenticated requests
+                return inner.call(req).await;
+            };
+            make_request(&mut inner, &limiter, user_id, req).await
+        })
+    }
+}
+
+async fn make_request<S>(
+    inner: &mut S,
+    limiter: &Arc<TokenLimiter>,
+    user_id: UserId,
+    req: Request,
+) -> Result<Response, ApiError>
+where
+    S: tower::Service<Request, Response = Response, Error = ApiError>
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    let (parts, body) = req.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map_err(InternalError::CollectBodyError)?
+        .to_bytes();
+    let estimate = estimate_tokens(&body);
+    limiter.check_capacity(estimate)?;
+    let remaining = limiter
+        .reserve(user_id, estimate, Instant::now())
+        .map_err(InvalidRequestError::TooManyRequests)?;
+    let req = Request::from_parts(parts, axum_core::body::Body::from(body));
+
+    let response = match inner.call(req).await {
+        Ok(response) => response,
+        Err(e) => {
+            limiter.reconcile(user_id, estimate, 0, Instant::now());
+            return Err(e);
+        }
+    };
+    if !response.status().is_success() {
+        let remaining = limiter.reconcile(user_id, estimate, 0, Instant::now());
+        return Ok(with_remaining(response, remaining));
+    }
+    let is_stream = response
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    if is_stream {
+        // streamed responses are passed through as they arrive, so the
+        // remaining budget is reported from the estimate and reconciled
+        // once the usage at the end of the stream is known
+        let mut reconciler = StreamReconciler {
+            limiter: Arc::clone(limiter),
+            user_id,
+            estimate,
+            usage: StreamUsage::default(),
+            line: Vec::new(),
+        };
+        let (parts, body) = response.into_parts();
+        let body = body.into_data_stream().map(move |chunk| {
+            if let Ok(chunk) = &chunk {
+                reconciler.observe(chunk);
+            }
+            chunk
+        });
+        let response = Response::from_parts(
+            parts,
+            axum_core::body::Body::from_stream(body),
+        );
+        return Ok(with_remaining(response, remaining));
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map_err(InternalError::CollectBodyError)?
+        .to_bytes();
+    let remaining = match used_tokens(&body) {
+        Some(used) => {
+            limiter.reconcile(user_id, estimate, used, Instant::now())
+        }
+        None => remaining,
+    };
+    let response =
+        Response::from_parts(parts, axum_core::body::Body::from(body));
+    Ok(with_remaining(response, remaining))
+}
+
+fn with_remaining(mut response: Response, remaining: u64) -> Response {
+    response
+        .headers_mut()
+        .insert(TOKENS_REMAINING_HEADER, HeaderValue::from(remaining));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn limiter(capacity: u64) -> TokenLimiter {
+        TokenLimiter::new(&TokenLimitConfig {
+            refill_frequency: Duration::from_secs(100),
+            capacity: NonZeroU64::new(capacity).unwrap(),
+        })
+    }
+
+    fn new_user() -> UserId {
+        UserId::new(Uuid::now_v7())
+    }
+
+    #[test]
+    fn requests_over_the_estimated_budget_are_rejected() {
+        let limiter = limiter(100);
+        let user = new_user();
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(user, 60, now).unwrap(), 40);
+        let error = limiter.reserve(user, 60, now).unwrap_err();
+        assert_eq!(error.ratelimit_limit, 100);
+        assert_eq!(error.ratelimit_remaining, 40);
+        // 20 tokens are missing, at a token per second
+        assert_eq!(error.retry_after, 21);
+        // other API keys have their own budget
+        assert_eq!(limiter.reserve(new_user(), 60, now).unwrap(), 40);
+    }
+
+    #[test]
+    fn estimates_over_the_capacity_are_invalid() {
+        let limiter = limiter(100);
+        assert!(limiter.check_capacity(100).is_ok());
+        assert!(matches!(
+            limiter.check_capacity(101),
+            Err(InvalidRequestError::TokenEstimateOverCapacity(101, 100))
+        ));
+    }
+
+    #[test]
+    fn streamed_usage_replaces_the_estimate() {
+        let limiter = Arc::new(limiter(100));
+        let user = new_user();
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(user, 10, now).unwrap(), 90);
+        let mut reconciler = StreamReconciler {
+            limiter: Arc::clone(&limiter),
+            user_id: user,
+            estimate: 10,
+            usage: StreamUsage::default(),
+            line: Vec::new(),
+        };
+        // events are split across chunks at arbitrary points
+        let stream = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":",
+            "{\"usage\":{\"input_tokens\":25,\"output_tokens\":1}}}\n\n",
+            "event: message_delta\n",
+            "data: {\"type\":\"message_delta\",\"usage\":{\"output_tokens\":\
+             15}}\n\n",
+        );
+        for chunk in stream.as_bytes().chunks(7) {
+            reconciler.observe(chunk);
+        }
+        drop(reconciler);
+        assert_eq!(limiter.reconcile(user, 0, 0, now), 60);
+    }
+
+    #[test]
+    fn actual_usage_replaces_the_estimate() {
+        let limiter = limiter(100);
+        let user = new_user();
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(user, 10, now).unwrap(), 90);
+        assert_eq!(limiter.reconcile(user, 10, 30, now), 70);
+        // going over budget leaves a debt that must be refilled first
+        assert_eq!(limiter.reserve(user, 70, now).unwrap(), 0);
+        assert_eq!(limiter.reconcile(user, 70, 90, now), 0);
+        assert!(limiter.reserve(user, 10, now).is_err());
+        let later = now + Duration::from_secs(30);
+        assert_eq!(limiter.reserve(user, 5, later).unwrap(), 5);
+    }
+
+    #[test]
+    fn budget_refills_up_to_capacity() {
+        let limiter = limiter(100);
+        let user = new_user();
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(user, 100, now).unwrap(), 0);
+        let later = now + Duration::from_secs(50);
+        assert_eq!(limiter.reconcile(user, 0, 0, later), 50);
+        let much_later = now + Duration::from_secs(1_000);
+        assert_eq!(limiter.reconcile(user, 0, 0, much_later), 100);
+    }
+
+    #[test]
+    fn used_tokens_are_read_from_openai_and_anthropic_responses() {
+        let openai = json!({
+            "usage": {
+                "prompt_tokens": 19,
+                "completion_tokens": 10,
+                "total_tokens": 29
+            }
+        });
+        let anthropic = json!({
+            "usage": { "input_tokens": 19, "output_tokens": 10 }
+        });
+        for body in [openai, anthropic] {
+            let body = serde_json::to_vec(&body).unwrap();
+            assert_eq!(used_tokens(&body), Some(29));
+        }
+        assert_eq!(used_tokens(b"{}"), None);
+        assert_eq!(used_tokens(b"not json"), None);
+    }
+}
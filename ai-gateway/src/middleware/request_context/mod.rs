@@ -1,17 +1,27 @@
 use std::{
+    collections::HashMap,
+    str::FromStr,
     sync::Arc,
     task::{Context, Poll},
 };
 
+use http::HeaderMap;
+use rust_decimal::Decimal;
+
 use crate::{
     config::router::RouterConfig,
     types::{
         extensions::{AuthContext, RequestContext},
+        model_id::ModelId,
         request::Request,
         response::Response,
     },
 };
 
+/// Overrides the per 1k token prices of a `cost-weighted` router, as a comma
+/// separated list of `{provider}/{model}={price}` entries.
+pub(crate) const COST_PRIORITY_HEADER: &str = "helicone-cost-priority";
+
 #[derive(Debug, Clone)]
 pub struct Service<S> {
     inner: S,
@@ -50,15 +60,42 @@ where
     fn call(&mut self, mut req: Request) -> Self::Future {
         let router_config = self.router_config.clone();
         let auth_context = req.extensions_mut().remove::<AuthContext>();
+        let cost_overrides = cost_overrides(req.headers());
         let req_ctx = RequestContext {
             router_config,
             auth_context,
+            cost_overrides,
         };
         req.extensions_mut().insert(Arc::new(req_ctx));
         self.inner.call(req)
     }
 }
 
+/// Parses the [`COST_PRIORITY_HEADER`], skipping malformed entries.
+fn cost_overrides(headers: &HeaderMap) -> HashMap<ModelId, Decimal> {
+    let Some(value) = headers
+        .get(COST_PRIORITY_HEADER)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return HashMap::new();
+    };
+    value
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(|entry| {
+            let parsed = entry.split_once('=').and_then(|(model, price)| {
+                let model = ModelId::from_str(model.trim()).ok()?;
+                let price = Decimal::from_str(price.trim()).ok()?;
+                (!price.is_sign_negative()).then_some((model, price))
+            });
+            if parsed.is_none() {
+                tracing::warn!(entry = %entry, "invalid cost priority entry");
+            }
+            parsed
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct Layer {
     router_config: Option<Arc<RouterConfig>>,
@@ -87,3 +124,39 @@ impl<S> tower::Layer<S> for Layer {
         Service::new(inner, self.router_config.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(value: &'static str) -> HashMap<ModelId, Decimal> {
+        let mut headers = HeaderMap::new();
+        headers.insert(COST_PRIORITY_HEADER, value.parse().unwrap());
+        cost_overrides(&headers)
+    }
+
+    #[test]
+    fn parses_cost_priority_header() {
+        let overrides = overrides(
+            "openai/gpt-4o-mini=0.5, anthropic/claude-3-haiku-20240307=0.25",
+        );
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(
+            overrides[&ModelId::from_str("openai/gpt-4o-mini").unwrap()],
+            Decimal::from_str("0.5").unwrap()
+        );
+        assert_eq!(
+            overrides[&ModelId::from_str("anthropic/claude-3-haiku-20240307")
+                .unwrap()],
+            Decimal::from_str("0.25").unwrap()
+        );
+    }
+
+    #[test]
+    fn skips_invalid_entries() {
+        let overrides =
+            overrides("openai/gpt-4o-mini=cheap,gpt-4o=1,openai/gpt-4o=-1,");
+        assert!(overrides.is_empty());
+        assert!(cost_overrides(&HeaderMap::new()).is_empty());
+    }
+}
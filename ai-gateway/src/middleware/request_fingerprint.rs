@@ -0,0 +1,243 @@
+use std::{
+    convert::Infallible,
+    fmt::Write,
+    num::NonZeroU16,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use opentelemetry::KeyValue;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    app_state::AppState,
+    error::{api::ApiError, internal::InternalError},
+    types::{request::Request, response::Response},
+};
+
+/// Number of hex characters kept from the digest.
+const FINGERPRINT_HEX_LEN: usize = 16;
+
+/// Fingerprint of a request, i.e. a hash of its path and normalized body.
+///
+/// Requests with the same path whose bodies only differ in formatting or key
+/// order have the same fingerprint.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestFingerprint(String);
+
+impl RequestFingerprint {
+    #[must_use]
+    pub fn new(path: &str, body: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(path.as_bytes());
+        hasher.update(b"\0");
+        match serde_json::from_slice::<Value>(body) {
+            Ok(value) => hash_value(&mut hasher, &value),
+            Err(_) => hasher.update(body),
+        }
+        let digest = hasher.finalize();
+
+        let mut fingerprint = String::with_capacity(FINGERPRINT_HEX_LEN);
+        for byte in digest.iter().take(FINGERPRINT_HEX_LEN / 2) {
+            let _ = write!(fingerprint, "{byte:02x}");
+        }
+        Self(fingerprint)
+    }
+
+    /// The bucket this fingerprint falls into, out of `buckets`.
+    #[must_use]
+    pub fn bucket(&self, buckets: NonZeroU16) -> u16 {
+        let prefix = u64::from_str_radix(&self.0, 16).unwrap_or_default();
+        let bucket = prefix % u64::from(buckets.get());
+        u16::try_from(bucket).expect("bucket is less than a u16")
+    }
+}
+
+impl std::fmt::Display for RequestFingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Hashes a JSON value with object keys in sorted order, so that the hash
+/// doesn't depend on how the client serialized the body.
+fn hash_value(hasher: &mut Sha256, value: &Value) {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+            hasher.update(b"{");
+            for (key, value) in entries {
+                hash_value(hasher, &Value::String(key.clone()));
+                hasher.update(b":");
+                hash_value(hasher, value);
+                hasher.update(b",");
+            }
+            hasher.update(b"}");
+        }
+        Value::Array(values) => {
+            hasher.update(b"[");
+            for value in values {
+                hash_value(hasher, value);
+                hasher.update(b",");
+            }
+            hasher.update(b"]");
+        }
+        scalar => hasher.update(scalar.to_string().as_bytes()),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    app_state: AppState,
+    buckets: Option<NonZeroU16>,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn global(app_state: &AppState) -> Self {
+        let buckets = app_state
+            .config()
+            .request_fingerprint
+            .as_ref()
+            .map(|config| config.buckets);
+        Self {
+            app_state: app_state.clone(),
+            buckets,
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            app_state: self.app_state.clone(),
+            buckets: self.buckets,
+        }
+    }
+}
+
+/// Records the fingerprint of each request in logs and metrics, and adds
+/// it to the request extensions.
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    app_state: AppState,
+    /// If `None`, fingerprinting is disabled.
+    buckets: Option<NonZeroU16>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = Infallible>
+        + Send
+        + Clone
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(|e| match e {})
+    }
+
+    #[tracing::instrument(name = "request_fingerprint", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut this = self.clone();
+        std::mem::swap(self, &mut this);
+        Box::pin(async move {
+            let Some(buckets) = this.buckets else {
+                return this.inner.call(req).await.map_err(|e| match e {});
+            };
+            let (mut parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(|e| InternalError::RequestBodyError(Box::new(e)))?
+                .to_bytes();
+            let fingerprint = RequestFingerprint::new(parts.uri.path(), &body);
+            let bucket = fingerprint.bucket(buckets);
+            tracing::info!(fingerprint = %fingerprint, bucket, "request fingerprint");
+            this.app_state.0.metrics.request_fingerprints.add(
+                1,
+                &[KeyValue::new("fingerprint_bucket", i64::from(bucket))],
+            );
+            parts.extensions.insert(fingerprint);
+            let req =
+                Request::from_parts(parts, axum_core::body::Body::from(body));
+            this.inner.call(req).await.map_err(|e| match e {})
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn identical_requests_have_same_fingerprint() {
+        let body = json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [{ "role": "user", "content": "Hello, world!" }]
+        });
+        let compact = serde_json::to_vec(&body).unwrap();
+        let pretty = serde_json::to_vec_pretty(&body).unwrap();
+        let reordered = br#"{"messages":[{"content":"Hello, world!","role":"user"}],"model":"openai/gpt-4o-mini"}"#;
+
+        let fingerprint =
+            RequestFingerprint::new("/ai/chat/completions", &compact);
+        assert_eq!(
+            fingerprint,
+            RequestFingerprint::new("/ai/chat/completions", &pretty)
+        );
+        assert_eq!(
+            fingerprint,
+            RequestFingerprint::new("/ai/chat/completions", reordered)
+        );
+        assert_eq!(fingerprint.to_string().len(), FINGERPRINT_HEX_LEN);
+    }
+
+    #[test]
+    fn different_requests_have_different_fingerprints() {
+        let body = |content: &str| {
+            serde_json::to_vec(&json!({
+                "model": "openai/gpt-4o-mini",
+                "messages": [{ "role": "user", "content": content }]
+            }))
+            .unwrap()
+        };
+        let fingerprint =
+            RequestFingerprint::new("/ai/chat/completions", &body("Hello"));
+        assert_ne!(
+            fingerprint,
+            RequestFingerprint::new("/ai/chat/completions", &body("Goodbye"))
+        );
+        assert_ne!(
+            fingerprint,
+            RequestFingerprint::new("/ai/embeddings", &body("Hello"))
+        );
+    }
+
+    #[test]
+    fn buckets_are_in_range() {
+        let buckets = NonZeroU16::new(16).unwrap();
+        for content in ["a", "b", "c", "d"] {
+            let fingerprint = RequestFingerprint::new("/", content.as_bytes());
+            assert!(fingerprint.bucket(buckets) < 16);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::ready;
+use http::{HeaderName, HeaderValue, Request, Response};
+use pin_project_lite::pin_project;
+use tracing::{Instrument, instrument::Instrumented};
+use uuid::Uuid;
+
+use crate::types::extensions::HeliconeRequestId;
+
+pub const HELICONE_REQUEST_ID_HEADER: HeaderName =
+    HeaderName::from_static("x-helicone-request-id");
+
+/// Generates the Helicone request id for every request and returns it in the
+/// `x-helicone-request-id` header of every response, including error
+/// responses, so that clients can correlate failures with logs.
+///
+/// The id is recorded on the request's trace span. Each upstream attempt is
+/// logged to Helicone under its own id, since a single client request may be
+/// dispatched several times (failover, retries, fan out).
+#[derive(Debug, Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S> RequestIdService<S> {
+    pub const fn new(inner: S) -> RequestIdService<S> {
+        RequestIdService { inner }
+    }
+}
+
+impl<S, ReqBody, RespBody> tower::Service<Request<ReqBody>>
+    for RequestIdService<S>
+where
+    S: tower::Service<Request<ReqBody>, Response = Response<RespBody>>
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let request_id = HeliconeRequestId(Uuid::new_v4());
+        let span = tracing::info_span!(
+            "helicone_request",
+            helicone_request_id = %request_id
+        );
+        ResponseFuture {
+            request_id,
+            inner: span.in_scope(|| self.inner.call(req)).instrument(span),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RequestIdLayer;
+
+impl RequestIdLayer {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> tower::Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, service: S) -> RequestIdService<S> {
+        RequestIdService::new(service)
+    }
+}
+
+pin_project! {
+    pub struct ResponseFuture<F> {
+        request_id: HeliconeRequestId,
+        #[pin]
+        inner: Instrumented<F>,
+    }
+}
+
+impl<F, RespBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<RespBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = ready!(this.inner.poll(cx))?;
+        let header_value = HeaderValue::from_str(&this.request_id.to_string())
+            .expect("a uuid is always a valid header value");
+        response
+            .headers_mut()
+            .insert(HELICONE_REQUEST_ID_HEADER, header_value);
+        Poll::Ready(Ok(response))
+    }
+}
@@ -10,7 +10,10 @@ use pin_project_lite::pin_project;
 
 use crate::{
     config::response_headers::ResponseHeadersConfig,
-    types::{extensions::ProviderRequestId, provider::InferenceProvider},
+    types::{
+        extensions::{EffectiveRequest, ProviderRequestId},
+        provider::InferenceProvider,
+    },
 };
 
 #[derive(Debug, Clone)]
@@ -119,6 +122,20 @@ where
                     .insert("helicone-provider-req-id", provider_request_id.0);
             }
         }
+
+        if this.config.effective_request {
+            let effective_request = response
+                .extensions()
+                .get::<EffectiveRequest>()
+                .and_then(|summary| {
+                    http::HeaderValue::from_str(&summary.to_string()).ok()
+                });
+            if let Some(effective_request) = effective_request {
+                response
+                    .headers_mut()
+                    .insert("helicone-effective-request", effective_request);
+            }
+        }
         Poll::Ready(Ok(response))
     }
 }
@@ -154,6 +171,7 @@ mod tests {
         let config = ResponseHeadersConfig {
             provider: false,
             provider_request_id: false,
+            effective_request: false,
         };
 
         let mut service = ResponseHeaderService::new(
@@ -181,6 +199,7 @@ mod tests {
         let config = ResponseHeadersConfig {
             provider: true,
             provider_request_id: false,
+            effective_request: false,
         };
 
         let mut service = ResponseHeaderService::new(
@@ -210,6 +229,7 @@ mod tests {
         let config = ResponseHeadersConfig {
             provider: false,
             provider_request_id: true,
+            effective_request: false,
         };
 
         let mut service = ResponseHeaderService::new(
@@ -239,6 +259,7 @@ mod tests {
         let config = ResponseHeadersConfig {
             provider: true,
             provider_request_id: true,
+            effective_request: false,
         };
 
         let mut service = ResponseHeaderService::new(
@@ -274,6 +295,7 @@ mod tests {
         let config = ResponseHeadersConfig {
             provider: true,
             provider_request_id: false,
+            effective_request: false,
         };
 
         let mut service = ResponseHeaderService::new(
@@ -293,6 +315,7 @@ mod tests {
         let config = ResponseHeadersConfig {
             provider: false,
             provider_request_id: true,
+            effective_request: false,
         };
 
         let mut service = ResponseHeaderService::new(
@@ -306,4 +329,36 @@ mod tests {
 
         assert!(!response.headers().contains_key("helicone-provider-req-id"));
     }
+
+    #[tokio::test]
+    async fn test_effective_request_header_enabled() {
+        let config = ResponseHeadersConfig {
+            provider: false,
+            provider_request_id: false,
+            effective_request: true,
+        };
+        let summary = EffectiveRequest::new(b"abc");
+
+        let mut service = ResponseHeaderService::new(
+            config,
+            create_mock_service(move || {
+                let mut response = Response::new("test".to_string());
+                response.extensions_mut().insert(summary.clone());
+                response
+            }),
+        );
+
+        let request = Request::new(());
+        let response =
+            service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(
+            response
+                .headers()
+                .get("helicone-effective-request")
+                .unwrap(),
+            "sha256=ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad; \
+             bytes=3"
+        );
+    }
 }
@@ -0,0 +1,218 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use serde_json::Value;
+
+use crate::{
+    config::{
+        router::RouterConfig,
+        sampling_bounds::{Bounds, SamplingBoundsConfig},
+    },
+    error::{api::ApiError, internal::InternalError},
+    types::{
+        extensions::{AppliedTransformations, Transformation},
+        request::Request,
+        response::Response,
+    },
+};
+
+/// Clamps the sampling parameters of chat requests into a router's allowed
+/// ranges, so that clients can't send values the provider would reject.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    bounds: Option<Arc<[(&'static str, Range)]>>,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn for_router(router_config: &RouterConfig) -> Self {
+        Self {
+            bounds: router_config.sampling_bounds.as_ref().map(ranges),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            bounds: self.bounds.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    bounds: Option<Arc<[(&'static str, Range)]>>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<
+            Request,
+            Response = http::Response<crate::types::body::Body>,
+            Error = ApiError,
+        > + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "sampling_bounds", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut inner = self.inner.clone();
+        let bounds = self.bounds.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        Box::pin(async move {
+            let Some(bounds) = bounds else {
+                return inner.call(req).await;
+            };
+            let (mut parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            // invalid bodies are rejected further down the stack
+            let body = match serde_json::from_slice::<Value>(&body) {
+                Ok(mut value) if clamp(&mut value, &bounds) => {
+                    AppliedTransformations::record(
+                        &mut parts.extensions,
+                        Transformation::SamplingBounds,
+                    );
+                    serde_json::to_vec(&value)
+                        .map_err(|_| InternalError::Internal)?
+                        .into()
+                }
+                _ => body,
+            };
+            let req =
+                Request::from_parts(parts, axum_core::body::Body::from(body));
+            inner.call(req).await
+        })
+    }
+}
+
+/// An inclusive range, converted from the configured [`Bounds`] once
+/// rather than for every request.
+#[derive(Debug, Clone, Copy)]
+struct Range {
+    min: f64,
+    max: f64,
+}
+
+impl From<Bounds> for Range {
+    fn from(bounds: Bounds) -> Self {
+        let to_f64 = |bound: Option<Decimal>, default: f64| {
+            bound.and_then(|bound| bound.to_f64()).unwrap_or(default)
+        };
+        Self {
+            min: to_f64(bounds.min, f64::NEG_INFINITY),
+            max: to_f64(bounds.max, f64::INFINITY),
+        }
+    }
+}
+
+/// The ranges of the body fields that are bounded by `config`.
+fn ranges(config: &SamplingBoundsConfig) -> Arc<[(&'static str, Range)]> {
+    [("temperature", config.temperature), ("top_p", config.top_p)]
+        .into_iter()
+        .filter_map(|(field, bounds)| Some((field, bounds?.into())))
+        .collect()
+}
+
+/// Clamps the bounded fields of a request body into their ranges, returning
+/// whether any of them was out of range.
+fn clamp(body: &mut Value, bounds: &[(&'static str, Range)]) -> bool {
+    let mut clamped = false;
+    for (field, range) in bounds {
+        let Some(value) = body.get_mut(*field) else {
+            continue;
+        };
+        let Some(requested) = value.as_f64() else {
+            continue;
+        };
+        if (range.min..=range.max).contains(&requested) {
+            continue;
+        }
+        let bounded = requested.clamp(range.min, range.max);
+        tracing::trace!(
+            field,
+            requested,
+            bounded,
+            "clamped sampling parameter"
+        );
+        *value = Value::from(bounded);
+        clamped = true;
+    }
+    clamped
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn bounds() -> Arc<[(&'static str, Range)]> {
+        ranges(&SamplingBoundsConfig {
+            temperature: Some(Bounds {
+                min: None,
+                max: Some(Decimal::ONE),
+            }),
+            top_p: Some(Bounds {
+                min: Some(Decimal::new(1, 1)),
+                max: Some(Decimal::new(95, 2)),
+            }),
+        })
+    }
+
+    #[test]
+    fn out_of_range_params_are_clamped() {
+        let mut body = json!({
+            "model": "openai/gpt-4o-mini",
+            "temperature": 1.7,
+            "top_p": 0.05,
+            "messages": []
+        });
+        assert!(clamp(&mut body, &bounds()));
+        assert_eq!(body["temperature"], json!(1.0));
+        assert_eq!(body["top_p"], json!(0.1));
+    }
+
+    #[test]
+    fn in_range_params_are_unchanged() {
+        let original = json!({
+            "model": "openai/gpt-4o-mini",
+            "temperature": 0,
+            "top_p": 0.95,
+            "messages": []
+        });
+        let mut body = original.clone();
+        assert!(!clamp(&mut body, &bounds()));
+        assert_eq!(body, original);
+
+        let mut body = json!({ "messages": [], "temperature": null });
+        assert!(!clamp(&mut body, &bounds()));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod service;
+pub mod store;
+
+pub use self::service::{Layer, Service};
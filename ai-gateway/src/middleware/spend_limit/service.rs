@@ -0,0 +1,337 @@
+use std::{
+    convert::Infallible,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use rust_decimal::Decimal;
+use serde_json::Value;
+
+use super::store::{SpendStore, window_end};
+use crate::{
+    app_state::AppState,
+    config::{rate_limit::RateLimitStore, spend_limit::SpendLimitConfig},
+    error::{
+        api::ApiError,
+        init::InitError,
+        internal::InternalError,
+        invalid_req::{InvalidRequestError, SpendLimitExceededError},
+    },
+    types::{
+        extensions::AuthContext, org::OrgId, request::Request,
+        response::Response,
+    },
+};
+
+/// Rough number of bytes per token, used to estimate the cost of requests
+/// whose responses don't report their usage.
+const BYTES_PER_TOKEN: u64 = 4;
+
+#[derive(Debug)]
+struct SpendLimiter {
+    config: SpendLimitConfig,
+    store: SpendStore,
+}
+
+impl SpendLimiter {
+    /// Returns an error if the org has reached any of its spend limits.
+    async fn check(
+        &self,
+        org_id: &OrgId,
+        now: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        for (window, limit) in self.config.limits() {
+            let spent = self.store.spent(org_id, window, now).await?;
+            if spent >= limit {
+                tracing::debug!(org_id = %org_id, window = %window, spent = %spent, "spend limit exceeded");
+                let retry_after = window_end(window, now)
+                    .signed_duration_since(now)
+                    .num_seconds()
+                    + 1;
+                return Err(InvalidRequestError::SpendLimitExceeded(
+                    SpendLimitExceededError {
+                        window,
+                        limit,
+                        retry_after: u64::try_from(retry_after).unwrap_or(0),
+                    },
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    async fn record(&self, org_id: &OrgId, usage: &Usage, now: DateTime<Utc>) {
+        let cost = self
+            .config
+            .cost(usage.model.as_deref())
+            .total(usage.input_tokens, usage.output_tokens);
+        if cost == Decimal::ZERO {
+            return;
+        }
+        for (window, _limit) in self.config.limits() {
+            if let Err(e) = self.store.record(org_id, window, cost, now).await {
+                // the request has already been served, so we can only
+                // under-count the org's spend
+                tracing::error!(error = %e, org_id = %org_id, "failed to record spend");
+            }
+        }
+    }
+}
+
+/// Token usage of a single request.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Usage {
+    model: Option<String>,
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+impl Usage {
+    /// Estimates the usage of a request from its body, assuming the model
+    /// generates as many tokens as it is allowed to.
+    fn estimate(body: &[u8]) -> Self {
+        let value = serde_json::from_slice::<Value>(body).unwrap_or_default();
+        let output_tokens = value
+            .get("max_completion_tokens")
+            .or_else(|| value.get("max_tokens"))
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        Self {
+            model: value
+                .get("model")
+                .and_then(Value::as_str)
+                .map(ToString::to_string),
+            input_tokens: u64::try_from(body.len()).unwrap_or(u64::MAX)
+                / BYTES_PER_TOKEN,
+            output_tokens,
+        }
+    }
+
+    /// Reads the usage reported in a response body, in either the `OpenAI`
+    /// or `Anthropic` format.
+    fn from_response(body: &[u8]) -> Option<Self> {
+        let value = serde_json::from_slice::<Value>(body).ok()?;
+        let usage = value.get("usage")?;
+        let tokens = |openai: &str, anthropic: &str| {
+            usage
+                .get(openai)
+                .or_else(|| usage.get(anthropic))
+                .and_then(Value::as_u64)
+        };
+        Some(Self {
+            model: value
+                .get("model")
+                .and_then(Value::as_str)
+                .map(ToString::to_string),
+            input_tokens: tokens("prompt_tokens", "input_tokens")?,
+            output_tokens: tokens("completion_tokens", "output_tokens")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    limiter: Option<Arc<SpendLimiter>>,
+}
+
+impl Layer {
+    /// Create a new spend limit layer to be applied globally.
+    pub fn global(app_state: &AppState) -> Result<Self, InitError> {
+        let Some(config) = app_state.config().spend_limit.clone() else {
+            return Ok(Self::disabled());
+        };
+        let store_config = config
+            .store
+            .as_ref()
+            .or(app_state.config().rate_limit_store.as_ref())
+            .cloned()
+            .unwrap_or(RateLimitStore::InMemory);
+        let store = SpendStore::new(&store_config)?;
+        Ok(Self {
+            limiter: Some(Arc::new(SpendLimiter { config, store })),
+        })
+    }
+
+    /// For when we statically know that spend limits are disabled.
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self { limiter: None }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    limiter: Option<Arc<SpendLimiter>>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = Infallible>
+        + Send
+        + Clone
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(|e| match e {})
+    }
+
+    #[tracing::instrument(name = "spend_limit", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut this = self.clone();
+        std::mem::swap(self, &mut this);
+        Box::pin(async move {
+            if let Some(limiter) = this.limiter {
+                make_request(&mut this.inner, &limiter, req).await
+            } else {
+                this.inner.call(req).await.map_err(|e| match e {})
+            }
+        })
+    }
+}
+
+async fn make_request<S>(
+    inner: &mut S,
+    limiter: &SpendLimiter,
+    req: Request,
+) -> Result<Response, ApiError>
+where
+    S: tower::Service<Request, Response = Response, Error = Infallible>
+        + Send
+        + Clone
+        + 'static,
+    S::Future: Send + 'static,
+{
+    let Some(org_id) =
+        req.extensions().get::<AuthContext>().map(|ctx| ctx.org_id)
+    else {
+        // spend can only be attributed to authenticated requests
+        return inner.call(req).await.map_err(|e| match e {});
+    };
+    let now = req
+        .extensions()
+        .get::<DateTime<Utc>>()
+        .copied()
+        .unwrap_or_else(Utc::now);
+    limiter.check(&org_id, now).await?;
+
+    let (parts, body) = req.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map_err(|e| InternalError::RequestBodyError(Box::new(e)))?
+        .to_bytes();
+    let estimate = Usage::estimate(&body);
+    let req = Request::from_parts(parts, axum_core::body::Body::from(body));
+
+    let response = inner.call(req).await.map_err(|e| match e {})?;
+    let is_cache_hit = response
+        .headers()
+        .get("helicone-cache")
+        .is_some_and(|v| v == "HIT");
+    if !response.status().is_success() || is_cache_hit {
+        return Ok(response);
+    }
+    let is_stream = response
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    if is_stream {
+        // streamed responses are passed through as they arrive, so we can't
+        // wait for the usage at the end of the stream
+        limiter.record(&org_id, &estimate, now).await;
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map_err(InternalError::CollectBodyError)?
+        .to_bytes();
+    let usage = match Usage::from_response(&body) {
+        Some(usage) => Usage {
+            model: usage.model.or(estimate.model),
+            ..usage
+        },
+        None => estimate,
+    };
+    limiter.record(&org_id, &usage, now).await;
+    Ok(Response::from_parts(
+        parts,
+        axum_core::body::Body::from(body),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn usage_is_read_from_openai_and_anthropic_responses() {
+        let openai = json!({
+            "model": "gpt-4o-mini",
+            "usage": { "prompt_tokens": 19, "completion_tokens": 10 }
+        });
+        let anthropic = json!({
+            "model": "claude-3-5-sonnet-latest",
+            "usage": { "input_tokens": 19, "output_tokens": 10 }
+        });
+        for (body, model) in [
+            (openai, "gpt-4o-mini"),
+            (anthropic, "claude-3-5-sonnet-latest"),
+        ] {
+            let usage =
+                Usage::from_response(&serde_json::to_vec(&body).unwrap());
+            assert_eq!(
+                usage,
+                Some(Usage {
+                    model: Some(model.to_string()),
+                    input_tokens: 19,
+                    output_tokens: 10,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn usage_is_estimated_from_request() {
+        let body = serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [{ "role": "user", "content": "Hello, world!" }],
+            "max_tokens": 100
+        }))
+        .unwrap();
+        let usage = Usage::estimate(&body);
+        assert_eq!(usage.model.as_deref(), Some("openai/gpt-4o-mini"));
+        assert_eq!(usage.input_tokens, body.len() as u64 / BYTES_PER_TOKEN);
+        assert_eq!(usage.output_tokens, 100);
+    }
+}
@@ -0,0 +1,207 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, Utc};
+use r2d2::Pool;
+use redis::{Client, Commands};
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use tokio::sync::Mutex;
+
+use crate::{
+    config::{rate_limit::RateLimitStore, spend_limit::SpendWindow},
+    error::{init::InitError, internal::InternalError},
+    types::org::OrgId,
+};
+
+/// Spend is stored in millionths of a USD so that it can be incremented
+/// atomically in Redis.
+const MICROS_PER_USD: i64 = 1_000_000;
+
+type InMemorySpend = HashMap<(OrgId, SpendWindow), (DateTime<Utc>, i64)>;
+
+/// Tracks how much each organization has spent in the current window.
+///
+/// The Redis store is shared between replicas, whereas the in-memory store
+/// only sees requests handled by this instance.
+#[derive(Debug, Clone)]
+pub enum SpendStore {
+    /// Keyed by org and window, with the start of the window the spend
+    /// was recorded in.
+    InMemory(Arc<Mutex<InMemorySpend>>),
+    Redis(Pool<Client>),
+}
+
+impl SpendStore {
+    pub fn new(store: &RateLimitStore) -> Result<Self, InitError> {
+        match store {
+            RateLimitStore::InMemory => {
+                Ok(Self::InMemory(Arc::new(Mutex::new(HashMap::new()))))
+            }
+            RateLimitStore::Redis(redis_config) => {
+                let client =
+                    Client::open(redis_config.host_url.expose().clone())?;
+                let pool = Pool::builder().build(client)?;
+                Ok(Self::Redis(pool))
+            }
+        }
+    }
+
+    /// Returns how much the org has spent in the window containing `now`.
+    pub async fn spent(
+        &self,
+        org_id: &OrgId,
+        window: SpendWindow,
+        now: DateTime<Utc>,
+    ) -> Result<Decimal, InternalError> {
+        let start = window_start(window, now);
+        let micros = match self {
+            Self::InMemory(spend) => spend
+                .lock()
+                .await
+                .get(&(*org_id, window))
+                .filter(|(recorded_start, _)| *recorded_start == start)
+                .map_or(0, |(_, micros)| *micros),
+            Self::Redis(pool) => {
+                let mut conn = pool.get().map_err(InternalError::PoolError)?;
+                let micros: Option<i64> = conn
+                    .get(redis_key(org_id, window, start))
+                    .map_err(InternalError::RedisError)?;
+                micros.unwrap_or(0)
+            }
+        };
+        Ok(Decimal::new(micros, 6))
+    }
+
+    /// Adds `cost` to the org's spend in the window containing `now`.
+    pub async fn record(
+        &self,
+        org_id: &OrgId,
+        window: SpendWindow,
+        cost: Decimal,
+        now: DateTime<Utc>,
+    ) -> Result<(), InternalError> {
+        let micros = (cost * Decimal::from(MICROS_PER_USD))
+            .ceil()
+            .to_i64()
+            .unwrap_or(i64::MAX);
+        let start = window_start(window, now);
+        match self {
+            Self::InMemory(spend) => {
+                let mut spend = spend.lock().await;
+                let entry =
+                    spend.entry((*org_id, window)).or_insert((start, 0));
+                if entry.0 != start {
+                    // the previous window has ended
+                    *entry = (start, 0);
+                }
+                entry.1 = entry.1.saturating_add(micros);
+            }
+            Self::Redis(pool) => {
+                let mut conn = pool.get().map_err(InternalError::PoolError)?;
+                let key = redis_key(org_id, window, start);
+                let ttl = window_end(window, now)
+                    .signed_duration_since(now)
+                    .num_seconds()
+                    + 1;
+                let _: i64 = conn
+                    .incr(&key, micros)
+                    .map_err(InternalError::RedisError)?;
+                let _: () = conn
+                    .expire(&key, ttl)
+                    .map_err(InternalError::RedisError)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn redis_key(
+    org_id: &OrgId,
+    window: SpendWindow,
+    start: DateTime<Utc>,
+) -> String {
+    format!("spend:{window}:{org_id}:{}", start.timestamp())
+}
+
+/// The start of the window containing `now`.
+#[must_use]
+pub fn window_start(window: SpendWindow, now: DateTime<Utc>) -> DateTime<Utc> {
+    let date = match window {
+        SpendWindow::Daily => now.date_naive(),
+        SpendWindow::Monthly => now
+            .date_naive()
+            .with_day(1)
+            .expect("the first of the month is always valid"),
+    };
+    date.and_time(chrono::NaiveTime::MIN).and_utc()
+}
+
+/// The end of the window containing `now`, i.e. when the spend resets.
+#[must_use]
+pub fn window_end(window: SpendWindow, now: DateTime<Utc>) -> DateTime<Utc> {
+    let start = window_start(window, now);
+    match window {
+        SpendWindow::Daily => start + TimeDelta::days(1),
+        SpendWindow::Monthly => {
+            let (year, month) = if start.month() == 12 {
+                (start.year() + 1, 1)
+            } else {
+                (start.year(), start.month() + 1)
+            };
+            NaiveDate::from_ymd_opt(year, month, 1)
+                .expect("the first of the month is always valid")
+                .and_time(chrono::NaiveTime::MIN)
+                .and_utc()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn windows_are_calendar_aligned() {
+        let now = at(2025, 12, 31, 18);
+        assert_eq!(window_start(SpendWindow::Daily, now), at(2025, 12, 31, 0));
+        assert_eq!(window_end(SpendWindow::Daily, now), at(2026, 1, 1, 0));
+        assert_eq!(window_start(SpendWindow::Monthly, now), at(2025, 12, 1, 0));
+        assert_eq!(window_end(SpendWindow::Monthly, now), at(2026, 1, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn in_memory_spend_resets_with_window() {
+        let store = SpendStore::new(&RateLimitStore::InMemory).unwrap();
+        let org_id = OrgId::new(Uuid::new_v4());
+        let today = at(2025, 6, 15, 12);
+        let tomorrow = at(2025, 6, 16, 1);
+
+        store
+            .record(&org_id, SpendWindow::Daily, Decimal::new(15, 1), today)
+            .await
+            .unwrap();
+        store
+            .record(&org_id, SpendWindow::Monthly, Decimal::new(15, 1), today)
+            .await
+            .unwrap();
+        let spent = |window, now| store.spent(&org_id, window, now);
+        assert_eq!(
+            spent(SpendWindow::Daily, today).await.unwrap(),
+            Decimal::new(15, 1)
+        );
+        assert_eq!(
+            spent(SpendWindow::Daily, tomorrow).await.unwrap(),
+            Decimal::ZERO
+        );
+        assert_eq!(
+            spent(SpendWindow::Monthly, tomorrow).await.unwrap(),
+            Decimal::new(15, 1)
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::{StreamExt, future::BoxFuture};
+use http_body_util::BodyExt;
+use serde_json::Value;
+
+use crate::{
+    app_state::AppState,
+    config::{mapper::TenantIdentifier, stream_limit::StreamLimitConfig},
+    error::{
+        api::ApiError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    types::{extensions::AuthContext, request::Request, response::Response},
+};
+
+/// Number of open streams per tenant.
+type OpenStreams = Arc<Mutex<HashMap<String, u32>>>;
+
+#[derive(Debug)]
+struct StreamLimiter {
+    config: StreamLimitConfig,
+    open: OpenStreams,
+}
+
+impl StreamLimiter {
+    /// Reserves a stream for the tenant, or returns an error if it already
+    /// has the maximum number of streams open.
+    fn acquire(&self, tenant: String) -> Result<StreamSlot, ApiError> {
+        let max = self.config.max_concurrent;
+        let mut open = self.open.lock().expect("stream limit lock poisoned");
+        let count = open.entry(tenant.clone()).or_default();
+        if *count >= max.get() {
+            tracing::debug!(tenant = %tenant, max = max.get(), "concurrent stream limit exceeded");
+            return Err(InvalidRequestError::ConcurrentStreamLimitExceeded(
+                max,
+            )
+            .into());
+        }
+        *count += 1;
+        Ok(StreamSlot {
+            tenant,
+            open: self.open.clone(),
+        })
+    }
+}
+
+/// A reserved stream, released when dropped.
+#[derive(Debug)]
+struct StreamSlot {
+    tenant: String,
+    open: OpenStreams,
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        let mut open = self.open.lock().expect("stream limit lock poisoned");
+        if let Some(count) = open.get_mut(&self.tenant) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                open.remove(&self.tenant);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    limiter: Option<Arc<StreamLimiter>>,
+}
+
+impl Layer {
+    /// Create a new stream limit layer to be applied globally.
+    #[must_use]
+    pub fn global(app_state: &AppState) -> Self {
+        let Some(config) = app_state.config().stream_limit.clone() else {
+            return Self::disabled();
+        };
+        Self {
+            limiter: Some(Arc::new(StreamLimiter {
+                config,
+                open: Arc::default(),
+            })),
+        }
+    }
+
+    /// For when we statically know that stream limits are disabled.
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self { limiter: None }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    limiter: Option<Arc<StreamLimiter>>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = Infallible>
+        + Send
+        + Clone
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(|e| match e {})
+    }
+
+    #[tracing::instrument(name = "stream_limit", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut this = self.clone();
+        std::mem::swap(self, &mut this);
+        Box::pin(async move {
+            if let Some(limiter) = this.limiter {
+                make_request(&mut this.inner, &limiter, req).await
+            } else {
+                this.inner.call(req).await.map_err(|e| match e {})
+            }
+        })
+    }
+}
+
+async fn make_request<S>(
+    inner: &mut S,
+    limiter: &StreamLimiter,
+    req: Request,
+) -> Result<Response, ApiError>
+where
+    S: tower::Service<Request, Response = Response, Error = Infallible>
+        + Send
+        + Clone
+        + 'static,
+    S::Future: Send + 'static,
+{
+    let Some(tenant) = req.extensions().get::<AuthContext>().map(|ctx| {
+        match limiter.config.identifier {
+            TenantIdentifier::Org => ctx.org_id.to_string(),
+            TenantIdentifier::User => ctx.user_id.to_string(),
+        }
+    }) else {
+        // streams can only be attributed to authenticated requests
+        return inner.call(req).await.map_err(|e| match e {});
+    };
+
+    let (parts, body) = req.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map_err(|e| InternalError::RequestBodyError(Box::new(e)))?
+        .to_bytes();
+    if !is_stream(&body) {
+        let req = Request::from_parts(parts, axum_core::body::Body::from(body));
+        return inner.call(req).await.map_err(|e| match e {});
+    }
+
+    let slot = limiter.acquire(tenant)?;
+    let req = Request::from_parts(parts, axum_core::body::Body::from(body));
+    let response = inner.call(req).await.map_err(|e| match e {})?;
+    let is_event_stream = response
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    if !is_event_stream {
+        // e.g. an error response, which isn't held open
+        return Ok(response);
+    }
+
+    // the slot is released once the body is dropped, i.e. when the stream
+    // completes or the client disconnects
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        let _slot = &slot;
+        chunk
+    });
+    Ok(Response::from_parts(
+        parts,
+        axum_core::body::Body::from_stream(body),
+    ))
+}
+
+fn is_stream(body: &Bytes) -> bool {
+    serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|value| value.get("stream").and_then(Value::as_bool))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+
+    #[test]
+    fn slots_are_released_when_dropped() {
+        let limiter = StreamLimiter {
+            config: StreamLimitConfig {
+                max_concurrent: NonZeroU32::new(1).unwrap(),
+                identifier: TenantIdentifier::Org,
+            },
+            open: Arc::default(),
+        };
+        let slot = limiter.acquire("org1".to_string()).unwrap();
+        assert!(limiter.acquire("org1".to_string()).is_err());
+        // other tenants are unaffected
+        let _other = limiter.acquire("org2".to_string()).unwrap();
+
+        drop(slot);
+        let _slot = limiter.acquire("org1".to_string()).unwrap();
+    }
+}
@@ -0,0 +1,314 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use futures::{StreamExt, future::BoxFuture, stream};
+use serde_json::Value;
+
+use crate::{
+    config::{router::RouterConfig, stream_moderation::StreamModerationConfig},
+    error::{
+        api::ApiError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    types::{request::Request, response::Response},
+};
+
+/// Buffers the start of a router's streaming responses and blocks those
+/// whose buffered content contains a blocked term, before anything has been
+/// sent to the client.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    config: Option<Arc<StreamModerationConfig>>,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn for_router(router_config: &RouterConfig) -> Self {
+        Self {
+            config: router_config.stream_moderation.clone().map(Arc::new),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    config: Option<Arc<StreamModerationConfig>>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<
+            Request,
+            Response = http::Response<crate::types::body::Body>,
+            Error = ApiError,
+        > + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "stream_moderation", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut inner = self.inner.clone();
+        let config = self.config.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        Box::pin(async move {
+            let response = inner.call(req).await?;
+            match config {
+                Some(config) if is_event_stream(&response) => {
+                    moderate(response, &config).await
+                }
+                _ => Ok(response),
+            }
+        })
+    }
+}
+
+fn is_event_stream(response: &Response) -> bool {
+    response.status().is_success()
+        && response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"))
+}
+
+/// Holds back the stream until enough content has been buffered to check it,
+/// then either rejects it or emits the buffered chunks followed by the rest
+/// of the stream.
+async fn moderate(
+    response: Response,
+    config: &StreamModerationConfig,
+) -> Result<Response, ApiError> {
+    let (parts, body) = response.into_parts();
+    let mut body = body.into_data_stream();
+    let mut moderator = Moderator::new(config);
+    let mut buffered = Vec::new();
+    while !moderator.is_full() {
+        let Some(chunk) = body.next().await else {
+            break;
+        };
+        let chunk = chunk.map_err(InternalError::CollectBodyError)?;
+        moderator.push(&chunk);
+        buffered.push(chunk);
+    }
+    if let Some(term) = moderator.flagged_term() {
+        tracing::info!(term = %term, "stream blocked by moderation");
+        return Err(InvalidRequestError::BlockedByModeration.into());
+    }
+
+    let body = stream::iter(buffered.into_iter().map(Ok)).chain(body);
+    Ok(Response::from_parts(
+        parts,
+        axum_core::body::Body::from_stream(body),
+    ))
+}
+
+/// Accumulates the content of a stream of `OpenAI` chat completion chunks
+/// until it reaches the configured buffer size.
+#[derive(Debug)]
+struct Moderator<'a> {
+    config: &'a StreamModerationConfig,
+    /// Bytes of an event that hasn't been fully received yet.
+    pending: BytesMut,
+    content: String,
+    chars: usize,
+    done: bool,
+}
+
+impl<'a> Moderator<'a> {
+    fn new(config: &'a StreamModerationConfig) -> Self {
+        Self {
+            config,
+            pending: BytesMut::new(),
+            content: String::new(),
+            chars: 0,
+            done: false,
+        }
+    }
+
+    /// Whether enough content has been buffered to check the stream, or the
+    /// stream has finished.
+    fn is_full(&self) -> bool {
+        self.done || self.chars >= self.config.buffer_chars.get()
+    }
+
+    /// Appends the content of the complete events in `chunk` to the buffer.
+    ///
+    /// Events may be split across chunks, so the trailing partial event is
+    /// kept until the rest of it arrives.
+    fn push(&mut self, chunk: &[u8]) {
+        self.pending.extend_from_slice(chunk);
+        while let Some(end) =
+            self.pending.windows(2).position(|window| window == b"\n\n")
+        {
+            let event = self.pending.split_to(end + 2);
+            for line in String::from_utf8_lossy(&event).lines() {
+                let Some(data) = line.strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    self.done = true;
+                } else if let Ok(value) = serde_json::from_str::<Value>(data) {
+                    self.push_content(&value);
+                }
+            }
+        }
+    }
+
+    fn push_content(&mut self, chunk: &Value) {
+        let Some(choices) = chunk.get("choices").and_then(Value::as_array)
+        else {
+            return;
+        };
+        for choice in choices {
+            if let Some(content) = choice
+                .get("delta")
+                .and_then(|delta| delta.get("content"))
+                .and_then(Value::as_str)
+            {
+                self.chars += content.chars().count();
+                self.content.push_str(&content.to_lowercase());
+            }
+        }
+    }
+
+    /// Returns the first blocked term found in the buffered content, if any.
+    fn flagged_term(&self) -> Option<&str> {
+        self.config
+            .blocked_terms
+            .iter()
+            .map(String::as_str)
+            .find(|term| self.content.contains(&term.to_lowercase()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn config(buffer_chars: usize) -> StreamModerationConfig {
+        StreamModerationConfig {
+            buffer_chars: NonZeroUsize::new(buffer_chars).unwrap(),
+            blocked_terms: vec!["Forbidden".to_string()],
+        }
+    }
+
+    fn event(content: &str) -> Bytes {
+        let chunk = json!({
+            "object": "chat.completion.chunk",
+            "choices": [{ "index": 0, "delta": { "content": content } }]
+        });
+        format!("data: {chunk}\n\n").into()
+    }
+
+    fn event_stream(events: Vec<Bytes>) -> Response {
+        let body = stream::iter(
+            events.into_iter().map(Ok::<_, std::convert::Infallible>),
+        );
+        http::Response::builder()
+            .header(http::header::CONTENT_TYPE, "text/event-stream")
+            .body(axum_core::body::Body::from_stream(body))
+            .unwrap()
+    }
+
+    async fn collect(response: Response) -> Bytes {
+        use http_body_util::BodyExt;
+        response.into_body().collect().await.unwrap().to_bytes()
+    }
+
+    #[tokio::test]
+    async fn flagged_buffer_blocks_stream() {
+        let config = config(20);
+        let response = event_stream(vec![
+            event("This is a forb"),
+            event("idden answer"),
+            event(" that keeps going"),
+        ]);
+        let result = moderate(response, &config).await;
+        assert!(matches!(
+            result,
+            Err(ApiError::InvalidRequest(
+                InvalidRequestError::BlockedByModeration
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn clean_buffer_emits_whole_stream() {
+        let config = config(10);
+        let events = vec![
+            event("Hello"),
+            event(", world!"),
+            event(" How are you?"),
+            "data: [DONE]\n\n".into(),
+        ];
+        let expected = events.concat();
+        let response = moderate(event_stream(events), &config).await.unwrap();
+        assert_eq!(collect(response).await, expected);
+    }
+
+    #[tokio::test]
+    async fn terms_after_buffer_are_not_checked() {
+        let config = config(5);
+        let events = vec![event("Hello"), event(" forbidden")];
+        let expected = events.concat();
+        let response = moderate(event_stream(events), &config).await.unwrap();
+        assert_eq!(collect(response).await, expected);
+    }
+
+    #[tokio::test]
+    async fn short_streams_are_checked_once_finished() {
+        let config = config(200);
+        let response =
+            event_stream(vec![event("forbidden"), "data: [DONE]\n\n".into()]);
+        assert!(moderate(response, &config).await.is_err());
+
+        let response = event_stream(vec![event("fine")]);
+        assert!(moderate(response, &config).await.is_ok());
+    }
+
+    #[test]
+    fn events_split_across_chunks_are_buffered() {
+        let config = config(200);
+        let mut moderator = Moderator::new(&config);
+        let event = event("forbidden");
+        let (start, end) = event.split_at(20);
+        moderator.push(start);
+        assert!(moderator.flagged_term().is_none());
+        moderator.push(end);
+        assert_eq!(moderator.flagged_term(), Some("Forbidden"));
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+
+use crate::{
+    config::router::RouterConfig,
+    error::{api::ApiError, internal::InternalError},
+    types::{
+        extensions::{AppliedTransformations, Transformation},
+        request::Request,
+        response::Response,
+    },
+};
+
+/// Prepends a router's mandatory system prompt, e.g. a safety instruction,
+/// to the messages of every chat request.
+///
+/// The layer runs after stored prompts are merged into the request, so the
+/// mandatory prompt comes ahead of both template and client system messages.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    prefix: Option<Arc<str>>,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn for_router(router_config: &RouterConfig) -> Self {
+        Self {
+            prefix: router_config
+                .system_prompt_prefix
+                .as_deref()
+                .map(Arc::from),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            prefix: self.prefix.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    prefix: Option<Arc<str>>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<
+            Request,
+            Response = http::Response<crate::types::body::Body>,
+            Error = ApiError,
+        > + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "system_prompt", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut inner = self.inner.clone();
+        let prefix = self.prefix.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        Box::pin(async move {
+            let Some(prefix) = prefix else {
+                return inner.call(req).await;
+            };
+            let (mut parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            // requests that aren't chat requests are passed through as is,
+            // invalid ones are rejected further down the stack
+            let body = match serde_json::from_slice::<Value>(&body) {
+                Ok(mut value) if prepend(&mut value, &prefix) => {
+                    AppliedTransformations::record(
+                        &mut parts.extensions,
+                        Transformation::SystemPrompt,
+                    );
+                    serde_json::to_vec(&value)
+                        .map_err(|_| InternalError::Internal)?
+                        .into()
+                }
+                _ => body,
+            };
+            let req =
+                Request::from_parts(parts, axum_core::body::Body::from(body));
+            inner.call(req).await
+        })
+    }
+}
+
+/// Prepends a system message with the `prefix` to the messages of a chat
+/// request body, returning whether the body had messages.
+fn prepend(body: &mut Value, prefix: &str) -> bool {
+    let Some(messages) = body.get_mut("messages").and_then(Value::as_array_mut)
+    else {
+        return false;
+    };
+    messages.insert(0, json!({ "role": "system", "content": prefix }));
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_comes_before_all_system_messages() {
+        // e.g. a stored prompt's system message merged ahead of the client's
+        let mut body = json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                { "role": "system", "content": "You are a pirate." },
+                { "role": "system", "content": "Be brief." },
+                { "role": "user", "content": "Hello, world!" }
+            ]
+        });
+        assert!(prepend(&mut body, "Never share secrets."));
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(
+            messages[0],
+            json!({ "role": "system", "content": "Never share secrets." })
+        );
+        assert_eq!(messages[1]["content"], "You are a pirate.");
+    }
+
+    #[test]
+    fn bodies_without_messages_are_unchanged() {
+        let mut body = json!({ "input": "Hello, world!" });
+        assert!(!prepend(&mut body, "Never share secrets."));
+        assert_eq!(body, json!({ "input": "Hello, world!" }));
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    convert::Infallible,
+    num::NonZeroU32,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    config::tool_call_turns::ToolCallTurnsConfig,
+    error::{
+        api::ApiError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    types::{request::Request, response::Response},
+};
+
+const CHAT_COMPLETIONS_PATH: &str = "chat/completions";
+
+/// The subset of a chat completion request needed to count its tool call
+/// turns.
+#[derive(Debug, Deserialize)]
+struct ChatMessages {
+    #[serde(default)]
+    messages: Vec<ChatMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    role: String,
+    #[serde(default)]
+    tool_calls: Option<Vec<serde::de::IgnoredAny>>,
+    #[serde(default)]
+    function_call: Option<serde::de::IgnoredAny>,
+}
+
+impl ChatMessage {
+    fn is_tool_call_turn(&self) -> bool {
+        self.role == "assistant"
+            && (self
+                .tool_calls
+                .as_ref()
+                .is_some_and(|calls| !calls.is_empty())
+                || self.function_call.is_some())
+    }
+}
+
+/// The number of assistant messages in a chat completion request body that
+/// call at least one tool, including deprecated function calls.
+///
+/// Returns `None` if the body isn't a JSON object.
+fn count_turns(body: &[u8]) -> Option<u32> {
+    let request = serde_json::from_slice::<ChatMessages>(body).ok()?;
+    let turns = request
+        .messages
+        .iter()
+        .filter(|message| message.is_tool_call_turn())
+        .count();
+    Some(u32::try_from(turns).unwrap_or(u32::MAX))
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    app_state: AppState,
+    config: Option<ToolCallTurnsConfig>,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn global(app_state: &AppState) -> Self {
+        Self {
+            app_state: app_state.clone(),
+            config: app_state.config().tool_call_turns.clone(),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            app_state: self.app_state.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Records the number of tool call turns in each chat completion request in
+/// logs and metrics, and rejects requests with more turns than allowed.
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    app_state: AppState,
+    /// If `None`, tool call turns are not tracked.
+    config: Option<ToolCallTurnsConfig>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = Infallible>
+        + Send
+        + Clone
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(|e| match e {})
+    }
+
+    #[tracing::instrument(name = "tool_call_turns", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut this = self.clone();
+        std::mem::swap(self, &mut this);
+        Box::pin(async move {
+            let Some(config) = this.config.as_ref() else {
+                return this.inner.call(req).await.map_err(|e| match e {});
+            };
+            if !req.uri().path().ends_with(CHAT_COMPLETIONS_PATH) {
+                return this.inner.call(req).await.map_err(|e| match e {});
+            }
+            let (parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(|e| InternalError::RequestBodyError(Box::new(e)))?
+                .to_bytes();
+            if let Some(turns) = count_turns(&body) {
+                tracing::info!(turns, "tool call turns");
+                this.app_state
+                    .0
+                    .metrics
+                    .tool_call_turns
+                    .record(u64::from(turns), &[]);
+                check_turns(turns, config.max_turns)?;
+            }
+            let req =
+                Request::from_parts(parts, axum_core::body::Body::from(body));
+            this.inner.call(req).await.map_err(|e| match e {})
+        })
+    }
+}
+
+fn check_turns(
+    turns: u32,
+    max_turns: Option<NonZeroU32>,
+) -> Result<(), InvalidRequestError> {
+    match max_turns {
+        Some(max_turns) if turns > max_turns.get() => {
+            Err(InvalidRequestError::TooManyToolCallTurns(turns, max_turns))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn body(value: &serde_json::Value) -> Vec<u8> {
+        serde_json::to_vec(value).unwrap()
+    }
+
+    #[test]
+    fn only_assistant_messages_with_tool_calls_are_turns() {
+        let request = body(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                { "role": "user", "content": "What's the weather?" },
+                {
+                    "role": "assistant",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "weather", "arguments": "{}" }
+                    }]
+                },
+                { "role": "tool", "tool_call_id": "call_1", "content": "sun" },
+                {
+                    "role": "assistant",
+                    "function_call": { "name": "weather", "arguments": "{}" }
+                },
+                { "role": "assistant", "content": "Sunny", "tool_calls": [] }
+            ]
+        }));
+        assert_eq!(count_turns(&request), Some(2));
+    }
+
+    #[test]
+    fn bodies_without_messages_have_no_turns() {
+        assert_eq!(count_turns(b"not json"), None);
+        let request = body(&json!({ "model": "gpt-4o", "input": "hello" }));
+        assert_eq!(count_turns(&request), Some(0));
+    }
+
+    #[test]
+    fn turns_over_the_limit_are_rejected() {
+        let max_turns = NonZeroU32::new(2);
+        assert!(check_turns(2, max_turns).is_ok());
+        assert!(check_turns(100, None).is_ok());
+        assert!(matches!(
+            check_turns(3, max_turns),
+            Err(InvalidRequestError::TooManyToolCallTurns(3, _))
+        ));
+    }
+}
@@ -0,0 +1,31 @@
+//! Optional WASM plugin hook that can transform request and response bodies.
+//!
+//! # ABI (version 1)
+//!
+//! Plugins are core WASM modules with no imports, so they have no access to
+//! the host beyond the bodies passed to them. They must export:
+//!
+//! - `memory`: the linear memory bodies are exchanged through.
+//! - `alloc(len: i32) -> i32`: returns a pointer to `len` writable bytes.
+//!
+//! And at least one of:
+//!
+//! - `transform_request(ptr: i32, len: i32) -> i64`
+//! - `transform_response(ptr: i32, len: i32) -> i64`
+//!
+//! The host writes the body into memory returned by `alloc` and calls the
+//! transform, which returns the new body packed as `(out_ptr << 32) |
+//! out_len`. Returning `0` leaves the body unchanged.
+//!
+//! Plugins may also export `abi_version() -> i32`, which must return
+//! [`ABI_VERSION`].
+//!
+//! Every invocation runs in a fresh instance with the fuel and memory limits
+//! from [`WasmPluginConfig`](crate::config::wasm_plugin::WasmPluginConfig).
+//! Streaming responses are passed through without being transformed.
+pub mod optional;
+mod runtime;
+mod service;
+
+pub use optional::{Layer as WasmPluginLayer, Service as WasmPluginService};
+pub use runtime::{ABI_VERSION, Hook, WasmPlugin};
@@ -0,0 +1,291 @@
+use bytes::Bytes;
+use wasmtime::{
+    Engine, InstancePre, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+use crate::config::wasm_plugin::WasmPluginConfig;
+
+/// The version of the host ABI implemented by this gateway.
+pub const ABI_VERSION: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    Request,
+    Response,
+}
+
+impl Hook {
+    fn export_name(self) -> &'static str {
+        match self {
+            Self::Request => "transform_request",
+            Self::Response => "transform_response",
+        }
+    }
+}
+
+struct PluginState {
+    limits: StoreLimits,
+}
+
+/// A compiled plugin, ready to be instantiated for each invocation.
+pub struct WasmPlugin {
+    engine: Engine,
+    instance_pre: InstancePre<PluginState>,
+    request_hook: bool,
+    response_hook: bool,
+    fuel: u64,
+    max_memory_bytes: usize,
+}
+
+impl std::fmt::Debug for WasmPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmPlugin")
+            .field("request_hook", &self.request_hook)
+            .field("response_hook", &self.response_hook)
+            .field("fuel", &self.fuel)
+            .field("max_memory_bytes", &self.max_memory_bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WasmPlugin {
+    pub fn load(config: &WasmPluginConfig) -> Result<Self, wasmtime::Error> {
+        let bytes = std::fs::read(&config.path)?;
+        Self::new(&bytes, config.fuel, config.max_memory_bytes)
+    }
+
+    /// Compiles a plugin from either a `.wasm` binary or `.wat` text.
+    pub fn new(
+        bytes: &[u8],
+        fuel: u64,
+        max_memory_bytes: usize,
+    ) -> Result<Self, wasmtime::Error> {
+        let mut engine_config = wasmtime::Config::new();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config)?;
+        let module = Module::new(&engine, bytes)?;
+
+        for export in ["memory", "alloc"] {
+            if module.get_export(export).is_none() {
+                return Err(wasmtime::Error::msg(format!(
+                    "plugin must export `{export}`"
+                )));
+            }
+        }
+        let request_hook =
+            module.get_export(Hook::Request.export_name()).is_some();
+        let response_hook =
+            module.get_export(Hook::Response.export_name()).is_some();
+        if !request_hook && !response_hook {
+            return Err(wasmtime::Error::msg(
+                "plugin must export `transform_request` or \
+                 `transform_response`",
+            ));
+        }
+
+        // No host functions are linked, so plugins with imports are rejected
+        // here.
+        let linker = Linker::<PluginState>::new(&engine);
+        let instance_pre = linker.instantiate_pre(&module)?;
+
+        let plugin = Self {
+            engine,
+            instance_pre,
+            request_hook,
+            response_hook,
+            fuel,
+            max_memory_bytes,
+        };
+        if module.get_export("abi_version").is_some() {
+            plugin.check_abi_version()?;
+        }
+        Ok(plugin)
+    }
+
+    #[must_use]
+    pub fn has_hook(&self, hook: Hook) -> bool {
+        match hook {
+            Hook::Request => self.request_hook,
+            Hook::Response => self.response_hook,
+        }
+    }
+
+    /// Runs the plugin's transform for `hook` over `body`.
+    ///
+    /// Returns `None` if the plugin left the body unchanged.
+    pub fn transform(
+        &self,
+        hook: Hook,
+        body: &[u8],
+    ) -> Result<Option<Bytes>, wasmtime::Error> {
+        let mut store = self.store()?;
+        let instance = self.instance_pre.instantiate(&mut store)?;
+        let memory =
+            instance.get_memory(&mut store, "memory").ok_or_else(|| {
+                wasmtime::Error::msg("plugin must export `memory`")
+            })?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let transform = instance.get_typed_func::<(i32, i32), i64>(
+            &mut store,
+            hook.export_name(),
+        )?;
+
+        let len = i32::try_from(body.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, usize::try_from(ptr)?, body)?;
+
+        let packed = transform.call(&mut store, (ptr, len))?;
+        if packed == 0 {
+            return Ok(None);
+        }
+        let packed = u64::from_ne_bytes(packed.to_ne_bytes());
+        let out_ptr = usize::try_from(packed >> 32)?;
+        let out_len = usize::try_from(packed & u64::from(u32::MAX))?;
+        let mut out = vec![0; out_len];
+        memory.read(&store, out_ptr, &mut out)?;
+        Ok(Some(Bytes::from(out)))
+    }
+
+    fn store(&self) -> Result<Store<PluginState>, wasmtime::Error> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.max_memory_bytes)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, PluginState { limits });
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(self.fuel)?;
+        Ok(store)
+    }
+
+    fn check_abi_version(&self) -> Result<(), wasmtime::Error> {
+        let mut store = self.store()?;
+        let instance = self.instance_pre.instantiate(&mut store)?;
+        let abi_version =
+            instance.get_typed_func::<(), i32>(&mut store, "abi_version")?;
+        let version = abi_version.call(&mut store, ())?;
+        if version != ABI_VERSION {
+            return Err(wasmtime::Error::msg(format!(
+                "plugin ABI version {version} is not supported, expected \
+                 {ABI_VERSION}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FUEL: u64 = 1_000_000;
+    const MAX_MEMORY_BYTES: usize = 1024 * 1024;
+
+    /// Uppercases every ASCII letter of the request body in place.
+    const UPPERCASE_PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "abi_version") (result i32) (i32.const 1))
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "transform_request")
+            (param $ptr i32) (param $len i32) (result i64)
+            (local $i i32)
+            (local $c i32)
+            (block $done
+              (loop $next_byte
+                (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+                (local.set $c
+                  (i32.load8_u (i32.add (local.get $ptr) (local.get $i))))
+                (if (i32.and
+                      (i32.ge_u (local.get $c) (i32.const 97))
+                      (i32.le_u (local.get $c) (i32.const 122)))
+                  (then
+                    (i32.store8
+                      (i32.add (local.get $ptr) (local.get $i))
+                      (i32.sub (local.get $c) (i32.const 32)))))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $next_byte)))
+            (i64.or
+              (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+              (i64.extend_i32_u (local.get $len)))))
+    "#;
+
+    /// Never returns, so it must be stopped by the fuel limit.
+    const LOOPING_PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "transform_request")
+            (param i32) (param i32) (result i64)
+            (loop $forever (br $forever))
+            (i64.const 0)))
+    "#;
+
+    #[test]
+    fn plugin_mutates_request_body() {
+        let plugin = WasmPlugin::new(
+            UPPERCASE_PLUGIN.as_bytes(),
+            FUEL,
+            MAX_MEMORY_BYTES,
+        )
+        .unwrap();
+        assert!(plugin.has_hook(Hook::Request));
+        assert!(!plugin.has_hook(Hook::Response));
+
+        let body = serde_json::to_vec(&serde_json::json!({
+            "user": "alice"
+        }))
+        .unwrap();
+        let transformed = plugin
+            .transform(Hook::Request, &body)
+            .unwrap()
+            .expect("plugin should return a body");
+        let transformed: serde_json::Value =
+            serde_json::from_slice(&transformed).unwrap();
+        assert_eq!(transformed, serde_json::json!({ "USER": "ALICE" }));
+    }
+
+    #[test]
+    fn plugin_is_stopped_when_out_of_fuel() {
+        let plugin =
+            WasmPlugin::new(LOOPING_PLUGIN.as_bytes(), FUEL, MAX_MEMORY_BYTES)
+                .unwrap();
+        assert!(plugin.transform(Hook::Request, b"{}").is_err());
+    }
+
+    #[test]
+    fn plugin_with_imports_is_rejected() {
+        let plugin = r#"
+            (module
+              (import "env" "log" (func (param i32)))
+              (memory (export "memory") 1)
+              (func (export "alloc") (param i32) (result i32) (i32.const 0))
+              (func (export "transform_request")
+                (param i32) (param i32) (result i64)
+                (i64.const 0)))
+        "#;
+        assert!(
+            WasmPlugin::new(plugin.as_bytes(), FUEL, MAX_MEMORY_BYTES).is_err()
+        );
+    }
+
+    #[test]
+    fn plugin_with_unsupported_abi_version_is_rejected() {
+        let plugin = r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "abi_version") (result i32) (i32.const 2))
+              (func (export "alloc") (param i32) (result i32) (i32.const 0))
+              (func (export "transform_request")
+                (param i32) (param i32) (result i64)
+                (i64.const 0)))
+        "#;
+        assert!(
+            WasmPlugin::new(plugin.as_bytes(), FUEL, MAX_MEMORY_BYTES).is_err()
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use tracing::{Instrument, info_span};
+
+use super::runtime::{Hook, WasmPlugin};
+use crate::{
+    app_state::AppState,
+    error::{api::ApiError, init::InitError, internal::InternalError},
+    types::{request::Request, response::Response},
+};
+
+#[derive(Debug, Clone)]
+pub struct WasmPluginLayer {
+    plugin: Arc<WasmPlugin>,
+}
+
+impl WasmPluginLayer {
+    pub fn new(app_state: &AppState) -> Result<Option<Self>, InitError> {
+        let Some(config) = &app_state.config().wasm_plugin else {
+            return Ok(None);
+        };
+        let plugin = WasmPlugin::load(config).map_err(InitError::WasmPlugin)?;
+        tracing::info!(path = %config.path.display(), "loaded wasm plugin");
+        Ok(Some(Self {
+            plugin: Arc::new(plugin),
+        }))
+    }
+}
+
+impl<S> tower::Layer<S> for WasmPluginLayer {
+    type Service = WasmPluginService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WasmPluginService {
+            inner,
+            plugin: self.plugin.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WasmPluginService<S> {
+    inner: S,
+    plugin: Arc<WasmPlugin>,
+}
+
+impl<S> tower::Service<Request> for WasmPluginService<S>
+where
+    S: tower::Service<Request, Response = Response, Error = ApiError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "wasm_plugin", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        let mut inner = self.inner.clone();
+        let plugin = self.plugin.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        Box::pin(async move {
+            let req = if plugin.has_hook(Hook::Request) {
+                let (mut parts, body) = req.into_parts();
+                let body = body
+                    .collect()
+                    .await
+                    .map_err(InternalError::CollectBodyError)?
+                    .to_bytes();
+                let body = transform(plugin.clone(), Hook::Request, body)
+                    .instrument(info_span!("transform_request"))
+                    .await?;
+                parts.headers.remove(http::header::CONTENT_LENGTH);
+                Request::from_parts(parts, axum_core::body::Body::from(body))
+            } else {
+                req
+            };
+
+            let response = inner.call(req).await?;
+            if !plugin.has_hook(Hook::Response) || is_event_stream(&response) {
+                return Ok(response);
+            }
+
+            let (mut parts, body) = response.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            let body = transform(plugin, Hook::Response, body)
+                .instrument(info_span!("transform_response"))
+                .await?;
+            parts.headers.remove(http::header::CONTENT_LENGTH);
+            Ok(Response::from_parts(
+                parts,
+                axum_core::body::Body::from(body),
+            ))
+        })
+    }
+}
+
+/// Runs the plugin on a blocking thread, since plugins may run for as long
+/// as their fuel allows.
+async fn transform(
+    plugin: Arc<WasmPlugin>,
+    hook: Hook,
+    body: Bytes,
+) -> Result<Bytes, ApiError> {
+    if body.is_empty() {
+        return Ok(body);
+    }
+    let transformed = tokio::task::spawn_blocking(move || {
+        plugin
+            .transform(hook, &body)
+            .map(|transformed| transformed.unwrap_or(body))
+    })
+    .await
+    .map_err(InternalError::WasmPluginTaskError)?
+    .map_err(InternalError::WasmPluginError)?;
+    Ok(transformed)
+}
+
+fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"))
+}
@@ -0,0 +1,223 @@
+use std::{
+    convert::Infallible,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use serde_json::Value;
+use tower::ServiceExt;
+
+use crate::{
+    app_state::AppState,
+    config::{balance::ComplexityThresholds, router::RouterConfig},
+    dispatcher::{Dispatcher, DispatcherService},
+    error::{api::ApiError, init::InitError, internal::InternalError},
+    types::{
+        model_id::ModelId, request::Request, response::Response,
+        router::RouterId,
+    },
+};
+
+/// Sends simple requests to a cheaper model and complex requests to a
+/// stronger model.
+#[derive(Clone)]
+pub struct ComplexityRouter {
+    thresholds: ComplexityThresholds,
+    simple: DispatcherService,
+    complex: DispatcherService,
+}
+
+impl std::fmt::Debug for ComplexityRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ComplexityRouter")
+            .field("thresholds", &self.thresholds)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ComplexityRouter {
+    pub async fn new(
+        app_state: AppState,
+        router_id: &RouterId,
+        router_config: &Arc<RouterConfig>,
+        simple: &ModelId,
+        complex: &ModelId,
+        thresholds: ComplexityThresholds,
+    ) -> Result<Self, InitError> {
+        tracing::debug!("creating complexity routing strategy");
+        let simple =
+            Self::dispatcher(&app_state, router_id, router_config, simple)
+                .await?;
+        let complex =
+            Self::dispatcher(&app_state, router_id, router_config, complex)
+                .await?;
+        Ok(Self {
+            thresholds,
+            simple,
+            complex,
+        })
+    }
+
+    async fn dispatcher(
+        app_state: &AppState,
+        router_id: &RouterId,
+        router_config: &Arc<RouterConfig>,
+        model: &ModelId,
+    ) -> Result<DispatcherService, InitError> {
+        let provider = model.inference_provider().ok_or_else(|| {
+            InitError::ModelIdNotRecognized(model.to_string())
+        })?;
+        Dispatcher::new_with_model_id(
+            app_state.clone(),
+            router_id,
+            router_config,
+            provider,
+            model.clone(),
+        )
+        .await
+    }
+}
+
+impl tower::Service<Request> for ComplexityRouter {
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        // the target is only known once the request is inspected, so it is
+        // driven to readiness when called
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let this = self.clone();
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            let complex = serde_json::from_slice::<Value>(&body)
+                .is_ok_and(|value| is_complex(&value, &this.thresholds));
+            tracing::trace!(complex, "routing request by complexity");
+            let dispatcher = if complex { this.complex } else { this.simple };
+            let req =
+                Request::from_parts(parts, axum_core::body::Body::from(body));
+            let response = dispatcher
+                .oneshot(req)
+                .await
+                .unwrap_or_else(|e: Infallible| match e {});
+            Ok(response)
+        })
+    }
+}
+
+/// Estimates whether a chat completion request is complex: if its messages
+/// are long, or if it provides tools or includes images.
+fn is_complex(body: &Value, thresholds: &ComplexityThresholds) -> bool {
+    if thresholds.tools
+        && body
+            .get("tools")
+            .and_then(Value::as_array)
+            .is_some_and(|tools| !tools.is_empty())
+    {
+        return true;
+    }
+    let messages = body
+        .get("messages")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let mut chars = 0;
+    for message in messages {
+        match message.get("content") {
+            Some(Value::String(content)) => chars += content.chars().count(),
+            Some(Value::Array(parts)) => {
+                for part in parts {
+                    match part.get("type").and_then(Value::as_str) {
+                        Some("image_url" | "input_image")
+                            if thresholds.images =>
+                        {
+                            return true;
+                        }
+                        _ => {}
+                    }
+                    if let Some(text) = part.get("text").and_then(Value::as_str)
+                    {
+                        chars += text.chars().count();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    chars > thresholds.max_prompt_chars
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn short_prompt_is_simple() {
+        let body = json!({
+            "messages": [{ "role": "user", "content": "Hello, world!" }]
+        });
+        assert!(!is_complex(&body, &ComplexityThresholds::default()));
+    }
+
+    #[test]
+    fn long_prompt_is_complex() {
+        let thresholds = ComplexityThresholds {
+            max_prompt_chars: 10,
+            ..Default::default()
+        };
+        let body = json!({
+            "messages": [
+                { "role": "system", "content": "Be brief." },
+                {
+                    "role": "user",
+                    "content": [{ "type": "text", "text": "Hello, world!" }]
+                }
+            ]
+        });
+        assert!(is_complex(&body, &thresholds));
+    }
+
+    #[test]
+    fn tools_and_images_are_complex_unless_disabled() {
+        let tools = json!({
+            "messages": [{ "role": "user", "content": "Hi" }],
+            "tools": [{ "type": "function", "function": { "name": "f" } }]
+        });
+        let images = json!({
+            "messages": [{
+                "role": "user",
+                "content": [{
+                    "type": "image_url",
+                    "image_url": { "url": "https://example.com/a.png" }
+                }]
+            }]
+        });
+        let defaults = ComplexityThresholds::default();
+        assert!(is_complex(&tools, &defaults));
+        assert!(is_complex(&images, &defaults));
+
+        let disabled = ComplexityThresholds {
+            tools: false,
+            images: false,
+            ..defaults
+        };
+        assert!(!is_complex(&tools, &disabled));
+        assert!(!is_complex(&images, &disabled));
+    }
+}
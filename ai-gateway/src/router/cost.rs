@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+use nonempty_collections::NEMap;
+use rust_decimal::Decimal;
+use tokio::sync::{RwLock, mpsc::channel};
+use tower::ServiceExt;
+
+use crate::{
+    app_state::AppState,
+    config::router::RouterConfig,
+    discover::monitor::health::provider::is_provider_healthy,
+    dispatcher::{Dispatcher, DispatcherService},
+    error::{api::ApiError, init::InitError},
+    types::{
+        extensions::RequestContext, model_id::ModelId,
+        provider::InferenceProvider, request::Request, response::Response,
+        router::RouterId,
+    },
+};
+
+const CHANNEL_CAPACITY: usize = 16;
+/// How long a model is skipped for when the provider rate limits it without
+/// a `retry-after` header.
+const DEFAULT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(30);
+
+type RateLimited = Arc<RwLock<HashMap<ModelId, Instant>>>;
+
+#[derive(Clone)]
+struct Target {
+    model: ModelId,
+    provider: InferenceProvider,
+    price: Decimal,
+    dispatcher: DispatcherService,
+}
+
+/// A target as seen by a single request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Candidate {
+    price: Decimal,
+    ready: bool,
+    latency: Option<Duration>,
+}
+
+/// Sends each request to the cheapest model that is healthy and not rate
+/// limited, breaking ties by latency.
+#[derive(Clone)]
+pub struct CostRouter {
+    app_state: AppState,
+    targets: Vec<Target>,
+    /// Models rate limited by their provider, until when.
+    rate_limited: RateLimited,
+}
+
+impl std::fmt::Debug for CostRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CostRouter").finish_non_exhaustive()
+    }
+}
+
+impl CostRouter {
+    pub async fn new(
+        app_state: AppState,
+        router_id: RouterId,
+        router_config: Arc<RouterConfig>,
+        models: &NEMap<ModelId, Decimal>,
+    ) -> Result<Self, InitError> {
+        tracing::debug!("creating cost weighted routing strategy");
+        let mut targets = Vec::with_capacity(models.len().get());
+        for (model, price) in models {
+            let provider = model.inference_provider().ok_or_else(|| {
+                InitError::ModelIdNotRecognized(model.to_string())
+            })?;
+            let dispatcher = Dispatcher::new_with_model_id(
+                app_state.clone(),
+                &router_id,
+                &router_config,
+                provider.clone(),
+                model.clone(),
+            )
+            .await?;
+            targets.push(Target {
+                model: model.clone(),
+                provider,
+                price: *price,
+                dispatcher,
+            });
+        }
+
+        let rate_limited = RateLimited::default();
+        let (rate_limit_tx, mut rate_limit_rx) = channel(CHANNEL_CAPACITY);
+        app_state
+            .add_rate_limit_tx(router_id.clone(), rate_limit_tx)
+            .await;
+        let models_by_provider = targets
+            .iter()
+            .map(|t| (t.model.clone(), t.provider.clone()))
+            .collect::<Vec<_>>();
+        let rate_limited_writer = rate_limited.clone();
+        tokio::spawn(async move {
+            while let Some(event) = rate_limit_rx.recv().await {
+                let wait = event
+                    .retry_after_seconds
+                    .map_or(DEFAULT_RATE_LIMIT_WAIT, Duration::from_secs);
+                let until = Instant::now() + wait;
+                let provider = event.api_endpoint.provider();
+                let mut rate_limited = rate_limited_writer.write().await;
+                for (model, model_provider) in &models_by_provider {
+                    let is_limited = match &event.model_id {
+                        Some(limited) => limited == model,
+                        None => *model_provider == provider,
+                    };
+                    if is_limited {
+                        tracing::debug!(model = %model, wait = ?wait, "cost weighted target rate limited");
+                        rate_limited.insert(model.clone(), until);
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            app_state,
+            targets,
+            rate_limited,
+        })
+    }
+
+    async fn candidates(
+        &self,
+        cost_overrides: Option<&HashMap<ModelId, Decimal>>,
+    ) -> Result<Vec<Candidate>, ApiError> {
+        let now = Instant::now();
+        let rate_limited = self.rate_limited.read().await;
+        let mut candidates = Vec::with_capacity(self.targets.len());
+        for target in &self.targets {
+            let price = cost_overrides
+                .and_then(|overrides| overrides.get(&target.model))
+                .copied()
+                .unwrap_or(target.price);
+            let is_rate_limited = rate_limited
+                .get(&target.model)
+                .is_some_and(|until| *until > now);
+            let ready = !is_rate_limited
+                && is_provider_healthy(&self.app_state, &target.provider)?;
+            candidates.push(Candidate {
+                price,
+                ready,
+                latency: self.latency(&target.provider),
+            });
+        }
+        Ok(candidates)
+    }
+
+    /// The lowest rolling latency among the endpoints of the `provider`.
+    fn latency(&self, provider: &InferenceProvider) -> Option<Duration> {
+        provider
+            .endpoints()
+            .into_iter()
+            .filter_map(|endpoint| {
+                self.app_state
+                    .0
+                    .endpoint_metrics
+                    .health_metrics(endpoint)
+                    .ok()?
+                    .mean_latency()
+            })
+            .min()
+    }
+}
+
+/// Picks the cheapest ready candidate, preferring the lower latency one among
+/// candidates with the same price. If no candidate is ready, the cheapest of
+/// all candidates is picked rather than failing the request.
+fn cheapest(candidates: &[Candidate]) -> Option<usize> {
+    let any_ready = candidates.iter().any(|c| c.ready);
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.ready || !any_ready)
+        .min_by_key(|(_, c)| (c.price, c.latency.unwrap_or(Duration::MAX)))
+        .map(|(idx, _)| idx)
+}
+
+impl tower::Service<Request> for CostRouter {
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        // targets are driven to readiness individually when called
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let this = self.clone();
+        Box::pin(async move {
+            let req_ctx = req.extensions().get::<Arc<RequestContext>>();
+            let cost_overrides = req_ctx.map(|ctx| &ctx.cost_overrides);
+            let candidates = this.candidates(cost_overrides).await?;
+            // the router config guarantees at least one target
+            let idx = cheapest(&candidates).unwrap_or_default();
+            let target = &this.targets[idx];
+            tracing::trace!(model = %target.model, price = %candidates[idx].price, "cost weighted target");
+            let response = target
+                .dispatcher
+                .clone()
+                .oneshot(req)
+                .await
+                .unwrap_or_else(|e: Infallible| match e {});
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(
+        price: &str,
+        ready: bool,
+        latency_ms: Option<u64>,
+    ) -> Candidate {
+        Candidate {
+            price: price.parse().unwrap(),
+            ready,
+            latency: latency_ms.map(Duration::from_millis),
+        }
+    }
+
+    #[test]
+    fn picks_cheapest_ready_candidate() {
+        let candidates = [
+            candidate("0.6", true, Some(100)),
+            candidate("0.25", true, Some(500)),
+            candidate("0.1", false, Some(50)),
+        ];
+        assert_eq!(cheapest(&candidates), Some(1));
+    }
+
+    #[test]
+    fn breaks_ties_by_latency() {
+        let candidates = [
+            candidate("0.25", true, None),
+            candidate("0.25", true, Some(500)),
+            candidate("0.25", true, Some(200)),
+        ];
+        assert_eq!(cheapest(&candidates), Some(2));
+    }
+
+    #[test]
+    fn falls_back_to_cheapest_when_none_are_ready() {
+        let candidates = [
+            candidate("0.6", false, Some(100)),
+            candidate("0.25", false, Some(500)),
+        ];
+        assert_eq!(cheapest(&candidates), Some(1));
+        assert_eq!(cheapest(&[]), None);
+    }
+}
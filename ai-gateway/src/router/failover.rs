@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{HeaderName, HeaderValue};
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use tower::{ServiceExt, buffer::Buffer};
+
+use crate::{
+    app_state::AppState,
+    config::{failover::FailoverConfig, router::RouterConfig},
+    dispatcher::{Dispatcher, DispatcherService},
+    error::{
+        api::ApiError, init::InitError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    router::strategy::{ResponseFuture, RoutingStrategyService},
+    types::{
+        extensions::ProviderErrorType, provider::InferenceProvider,
+        request::Request, response::Response, router::RouterId,
+    },
+};
+
+/// Lists the providers a request was sent to, in order, when it was failed
+/// over.
+const FALLBACK_USED_HEADER: HeaderName =
+    HeaderName::from_static("helicone-fallback-used");
+
+/// Balances requests with the router's strategy, and resends requests that
+/// fail with a configured status code or error type to the failover
+/// providers, in order.
+#[derive(Clone)]
+pub struct FailoverRouter {
+    config: Arc<FailoverConfig>,
+    max_request_bytes: Option<usize>,
+    dispatchers: Arc<HashMap<InferenceProvider, DispatcherService>>,
+    primary: Buffer<Request, ResponseFuture>,
+}
+
+impl std::fmt::Debug for FailoverRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FailoverRouter")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FailoverRouter {
+    pub async fn new(
+        app_state: AppState,
+        router_id: &RouterId,
+        router_config: &Arc<RouterConfig>,
+        config: FailoverConfig,
+        primary: RoutingStrategyService,
+    ) -> Result<Self, InitError> {
+        tracing::debug!("creating failover routing strategy");
+        let mut dispatchers = HashMap::new();
+        for provider in &config.providers {
+            if dispatchers.contains_key(provider) {
+                continue;
+            }
+            let dispatcher = Dispatcher::new(
+                app_state.clone(),
+                router_id,
+                router_config,
+                provider.clone(),
+            )
+            .await?;
+            dispatchers.insert(provider.clone(), dispatcher);
+        }
+        let max_request_bytes = [
+            router_config.max_request_body_bytes,
+            app_state.config().mapper.max_request_body_bytes,
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        Ok(Self {
+            config: Arc::new(config),
+            max_request_bytes,
+            dispatchers: Arc::new(dispatchers),
+            primary: Buffer::new(
+                primary,
+                crate::router::meta::MIDDLEWARE_BUFFER_SIZE,
+            ),
+        })
+    }
+}
+
+impl tower::Service<Request> for FailoverRouter {
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        // whether a failover is needed is only known once the response is
+        // inspected, so targets are driven to readiness when called
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let config = self.config.clone();
+        let max_request_bytes = self.max_request_bytes;
+        let dispatchers = self.dispatchers.clone();
+        let primary = self.primary.clone();
+        Box::pin(async move {
+            // keep a copy of the request so it can be resent
+            let (parts, body) = req.into_parts();
+            let body = collect_limited(body, max_request_bytes).await?;
+            let req = Request::from_parts(parts.clone(), body.clone().into());
+            let response = primary.oneshot(req).await.map_err(|e| {
+                match e.downcast::<ApiError>() {
+                    Ok(e) => *e,
+                    Err(e) => InternalError::BufferError(e).into(),
+                }
+            })?;
+            let (mut response, mut failed) =
+                check_failover(&config, response).await?;
+            let max_attempts = config.max_attempts.map_or(usize::MAX, |max| {
+                usize::try_from(max.get()).unwrap_or(usize::MAX)
+            });
+            let mut tried = Vec::new();
+            let mut failed_over = false;
+            for provider in &config.providers {
+                let Some(failed_provider) = &failed else {
+                    break;
+                };
+                if !tried.contains(failed_provider) {
+                    tried.push(failed_provider.clone());
+                }
+                if tried.len() >= max_attempts {
+                    break;
+                }
+                if tried.contains(provider) {
+                    continue;
+                }
+                let Some(dispatcher) = dispatchers.get(provider) else {
+                    continue;
+                };
+                tracing::debug!(
+                    failed_provider = %failed_provider,
+                    provider = %provider,
+                    "failing over request"
+                );
+                let req =
+                    Request::from_parts(parts.clone(), body.clone().into());
+                failed_over = true;
+                let failover_response = dispatcher
+                    .clone()
+                    .oneshot(req)
+                    .await
+                    .unwrap_or_else(|e: Infallible| match e {});
+                (response, failed) =
+                    check_failover(&config, failover_response).await?;
+            }
+            if failed_over {
+                set_fallback_used(&mut response, tried);
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Records the providers that were tried before the `response`, and the one
+/// that sent it, on the response.
+fn set_fallback_used(
+    response: &mut Response,
+    mut tried: Vec<InferenceProvider>,
+) {
+    if let Some(provider) = response.extensions().get::<InferenceProvider>()
+        && !tried.contains(provider)
+    {
+        tried.push(provider.clone());
+    }
+    let providers = tried
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    if let Ok(value) = HeaderValue::from_str(&providers) {
+        response.headers_mut().insert(FALLBACK_USED_HEADER, value);
+    }
+}
+
+/// Buffers the request body so it can be resent, rejecting bodies larger
+/// than `max_bytes`.
+async fn collect_limited(
+    body: axum_core::body::Body,
+    max_bytes: Option<usize>,
+) -> Result<Bytes, ApiError> {
+    let Some(max_bytes) = max_bytes else {
+        let body = body
+            .collect()
+            .await
+            .map_err(InternalError::CollectBodyError)?;
+        return Ok(body.to_bytes());
+    };
+    match Limited::new(body, max_bytes).collect().await {
+        Ok(body) => Ok(body.to_bytes()),
+        Err(e) if e.is::<LengthLimitError>() => {
+            Err(InvalidRequestError::RequestTooLarge(max_bytes).into())
+        }
+        Err(e) => match e.downcast::<axum_core::Error>() {
+            Ok(e) => Err(InternalError::CollectBodyError(*e).into()),
+            Err(e) => Err(InternalError::RequestBodyError(e).into()),
+        },
+    }
+}
+
+/// Returns the provider that sent the `response` if it should be failed
+/// over, along with the response itself, its body read back in if needed.
+async fn check_failover(
+    config: &FailoverConfig,
+    response: Response,
+) -> Result<(Response, Option<InferenceProvider>), ApiError> {
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return Ok((response, None));
+    }
+    let Some(provider) = response.extensions().get::<InferenceProvider>()
+    else {
+        return Ok((response, None));
+    };
+    let provider = provider.clone();
+    let (parts, body) = response.into_parts();
+    let body: Bytes = body
+        .collect()
+        .await
+        .map_err(InternalError::CollectBodyError)?
+        .to_bytes();
+    // mapped error responses may no longer carry the provider's error type,
+    // in which case it's recorded by the mapper
+    let error_type = parts
+        .extensions
+        .get::<ProviderErrorType>()
+        .cloned()
+        .or_else(|| ProviderErrorType::parse(&body));
+    let triggers = config.triggers(
+        &provider,
+        status,
+        error_type.as_ref().map(|error_type| error_type.0.as_str()),
+    );
+    let response = Response::from_parts(parts, body.into());
+    Ok((response, triggers.then_some(provider)))
+}
@@ -0,0 +1,321 @@
+use std::{
+    convert::Infallible,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum_core::response::IntoResponse;
+use bytes::Bytes;
+use futures::{StreamExt, future::BoxFuture, stream::FuturesUnordered};
+use http_body_util::BodyExt;
+use nonempty_collections::NESet;
+use rust_decimal::prelude::ToPrimitive;
+use serde_json::{Value, json};
+use tokio::{sync::mpsc::channel, time::Instant};
+use tower::ServiceExt;
+
+use crate::{
+    app_state::AppState,
+    config::{
+        balance::{
+            FanOutConfig, FanOutOnTimeout, FanOutSelection, WeightedModel,
+        },
+        router::RouterConfig,
+    },
+    dispatcher::{Dispatcher, DispatcherService},
+    error::{
+        api::ApiError, init::InitError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    types::{
+        json::Json, model_id::ModelId, request::Request, response::Response,
+        router::RouterId,
+    },
+};
+
+const CHANNEL_CAPACITY: usize = 16;
+
+#[derive(Clone)]
+struct Target {
+    model: ModelId,
+    weight: f64,
+    dispatcher: DispatcherService,
+}
+
+/// Sends each request to several models in parallel, sampled according to
+/// their weights, and returns either the first successful response or all
+/// of them.
+#[derive(Clone)]
+pub struct FanOutRouter {
+    targets: Vec<Target>,
+    config: FanOutConfig,
+}
+
+impl std::fmt::Debug for FanOutRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FanOutRouter")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FanOutRouter {
+    pub async fn new(
+        app_state: AppState,
+        router_id: RouterId,
+        router_config: Arc<RouterConfig>,
+        models: &NESet<WeightedModel>,
+        config: FanOutConfig,
+    ) -> Result<Self, InitError> {
+        tracing::debug!("creating fan out routing strategy");
+        let (rate_limit_tx, mut rate_limit_rx) = channel(CHANNEL_CAPACITY);
+        app_state
+            .add_rate_limit_tx(router_id.clone(), rate_limit_tx)
+            .await;
+        // every request is sent to multiple models anyway, so rate limited
+        // models are not removed from the set of targets
+        tokio::spawn(async move {
+            while let Some(event) = rate_limit_rx.recv().await {
+                tracing::debug!(event = ?event, "fan out target rate limited");
+            }
+        });
+
+        let mut targets = Vec::with_capacity(models.len().get());
+        for target in models {
+            let provider =
+                target.model.inference_provider().ok_or_else(|| {
+                    InitError::ModelIdNotRecognized(target.model.to_string())
+                })?;
+            let weight = target
+                .weight
+                .to_f64()
+                .ok_or_else(|| InitError::InvalidWeight(provider.clone()))?;
+            let dispatcher = Dispatcher::new_with_model_id(
+                app_state.clone(),
+                &router_id,
+                &router_config,
+                provider,
+                target.model.clone(),
+            )
+            .await?;
+            targets.push(Target {
+                model: target.model.clone(),
+                weight,
+                dispatcher,
+            });
+        }
+
+        Ok(Self { targets, config })
+    }
+
+    /// Samples `count` distinct targets according to their weights.
+    fn sample(&self) -> Vec<Target> {
+        use rand::seq::IndexedRandom;
+        let count = self.config.count.get();
+        let mut rng = rand::rng();
+        match self
+            .targets
+            .choose_multiple_weighted(&mut rng, count, |t| t.weight)
+        {
+            Ok(targets) => targets.cloned().collect(),
+            Err(e) => {
+                tracing::warn!(error = %e, "invalid fan out weights, using first targets");
+                self.targets.iter().take(count).cloned().collect()
+            }
+        }
+    }
+}
+
+impl tower::Service<Request> for FanOutRouter {
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        // targets are driven to readiness individually when called
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let targets = self.sample();
+        let FanOutConfig {
+            selection,
+            timeout,
+            on_timeout,
+            ..
+        } = self.config;
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            if selection == FanOutSelection::All && is_stream(&body) {
+                return Err(InvalidRequestError::StreamingFanOut.into());
+            }
+
+            let mut responses = targets
+                .into_iter()
+                .enumerate()
+                .map(|(idx, target)| {
+                    let req = Request::from_parts(
+                        parts.clone(),
+                        axum_core::body::Body::from(body.clone()),
+                    );
+                    async move {
+                        let response = target
+                            .dispatcher
+                            .oneshot(req)
+                            .await
+                            .unwrap_or_else(|e: Infallible| match e {});
+                        (idx, target.model, response)
+                    }
+                })
+                .collect::<FuturesUnordered<_>>();
+
+            let timed_out = || -> ApiError {
+                InternalError::ProviderTimeout(timeout.unwrap_or_default())
+                    .into()
+            };
+            match selection {
+                FanOutSelection::First => {
+                    let mut last_failure = None;
+                    loop {
+                        let (_, model, response) =
+                            match next_response(&mut responses, deadline).await
+                            {
+                                Next::Response(response) => response,
+                                Next::TimedOut => {
+                                    abort(responses);
+                                    if on_timeout == FanOutOnTimeout::Fail {
+                                        return Err(timed_out());
+                                    }
+                                    return last_failure.ok_or_else(timed_out);
+                                }
+                                Next::Done => break,
+                            };
+                        if response.status().is_success() {
+                            tracing::trace!(model = %model, "fan out winner");
+                            tokio::spawn(drain(responses));
+                            return Ok(response);
+                        }
+                        last_failure = Some(response);
+                    }
+                    last_failure.ok_or_else(|| InternalError::Internal.into())
+                }
+                FanOutSelection::All => {
+                    let mut candidates = Vec::with_capacity(responses.len());
+                    loop {
+                        let (idx, model, response) =
+                            match next_response(&mut responses, deadline).await
+                            {
+                                Next::Response(response) => response,
+                                Next::TimedOut => {
+                                    abort(responses);
+                                    if on_timeout == FanOutOnTimeout::Fail
+                                        || candidates.is_empty()
+                                    {
+                                        return Err(timed_out());
+                                    }
+                                    break;
+                                }
+                                Next::Done => break,
+                            };
+                        candidates
+                            .push((idx, candidate(model, response).await?));
+                    }
+                    candidates.sort_by_key(|(idx, _)| *idx);
+                    let data = candidates
+                        .into_iter()
+                        .map(|(_, candidate)| candidate)
+                        .collect::<Vec<_>>();
+                    Ok(Json(json!({ "object": "list", "data": data }))
+                        .into_response())
+                }
+            }
+        })
+    }
+}
+
+fn is_stream(body: &Bytes) -> bool {
+    serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|value| value.get("stream").and_then(Value::as_bool))
+        .unwrap_or(false)
+}
+
+enum Next<T> {
+    Response(T),
+    TimedOut,
+    /// Every response has arrived.
+    Done,
+}
+
+/// Waits for the next response, unless the deadline passes first.
+async fn next_response<S>(
+    responses: &mut S,
+    deadline: Option<Instant>,
+) -> Next<S::Item>
+where
+    S: futures::Stream + Unpin,
+{
+    let next = match deadline {
+        Some(deadline) => {
+            match tokio::time::timeout_at(deadline, responses.next()).await {
+                Ok(next) => next,
+                Err(_) => return Next::TimedOut,
+            }
+        }
+        None => responses.next().await,
+    };
+    next.map_or(Next::Done, Next::Response)
+}
+
+/// Drops the requests that are still pending, which cancels them so that
+/// the providers stop generating (and charging for) their responses.
+fn abort<S>(responses: S)
+where
+    S: futures::Stream + Unpin,
+{
+    tracing::debug!("fan out timed out, aborting pending requests");
+    drop(responses);
+}
+
+/// Reads the remaining responses to completion so that they are logged.
+async fn drain<S>(mut responses: S)
+where
+    S: futures::Stream<Item = (usize, ModelId, Response)> + Unpin,
+{
+    while let Some((_, model, response)) = responses.next().await {
+        if let Err(e) = response.into_body().collect().await {
+            tracing::warn!(model = %model, error = %e, "failed to read fan out response");
+        }
+    }
+}
+
+async fn candidate(
+    model: ModelId,
+    response: Response,
+) -> Result<Value, ApiError> {
+    let status = response.status();
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .map_err(InternalError::CollectBodyError)?
+        .to_bytes();
+    let body = serde_json::from_slice::<Value>(&body).unwrap_or_else(|_| {
+        Value::String(String::from_utf8_lossy(&body).into_owned())
+    });
+    Ok(json!({
+        "model": model.to_string(),
+        "status": status.as_u16(),
+        "response": body,
+    }))
+}
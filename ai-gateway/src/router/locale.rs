@@ -0,0 +1,250 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use serde_json::Value;
+use tower::{ServiceExt, buffer::Buffer};
+
+use crate::{
+    app_state::AppState,
+    config::{locale_routing::LocaleRoutingConfig, router::RouterConfig},
+    dispatcher::{Dispatcher, DispatcherService},
+    error::{api::ApiError, init::InitError, internal::InternalError},
+    router::strategy::{ResponseFuture, RoutingStrategyService},
+    types::{
+        provider::InferenceProvider, request::Request, response::Response,
+        router::RouterId,
+    },
+};
+
+/// Header clients use to declare the locale of a request, e.g. `de-DE`.
+pub(crate) const LOCALE_HEADER: http::HeaderName =
+    http::HeaderName::from_static("x-helicone-locale");
+
+/// Routes requests with a configured locale directly to that locale's
+/// provider, and balances all other requests with the router's strategy.
+#[derive(Clone)]
+pub struct LocaleRouter {
+    config: Arc<LocaleRoutingConfig>,
+    dispatchers: Arc<HashMap<InferenceProvider, DispatcherService>>,
+    fallback: Buffer<Request, ResponseFuture>,
+}
+
+impl std::fmt::Debug for LocaleRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocaleRouter")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LocaleRouter {
+    pub async fn new(
+        app_state: AppState,
+        router_id: &RouterId,
+        router_config: &Arc<RouterConfig>,
+        config: LocaleRoutingConfig,
+        fallback: RoutingStrategyService,
+    ) -> Result<Self, InitError> {
+        tracing::debug!("creating locale routing strategy");
+        let mut dispatchers = HashMap::new();
+        for provider in config.locales.values() {
+            if dispatchers.contains_key(provider) {
+                continue;
+            }
+            let dispatcher = Dispatcher::new(
+                app_state.clone(),
+                router_id,
+                router_config,
+                provider.clone(),
+            )
+            .await?;
+            dispatchers.insert(provider.clone(), dispatcher);
+        }
+        Ok(Self {
+            config: Arc::new(config),
+            dispatchers: Arc::new(dispatchers),
+            fallback: Buffer::new(
+                fallback,
+                crate::router::meta::MIDDLEWARE_BUFFER_SIZE,
+            ),
+        })
+    }
+}
+
+impl tower::Service<Request> for LocaleRouter {
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        // the target is only known once the request is inspected, so it is
+        // driven to readiness when called
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let config = self.config.clone();
+        let dispatchers = self.dispatchers.clone();
+        let fallback = self.fallback.clone();
+        Box::pin(async move {
+            let (req, locale) = resolve_locale(req, config.detect).await?;
+            let dispatcher = locale
+                .as_deref()
+                .and_then(|locale| config.provider(locale))
+                .and_then(|provider| dispatchers.get(provider));
+            if let Some(dispatcher) = dispatcher {
+                tracing::trace!(locale = ?locale, "routing request by locale");
+                let response = dispatcher
+                    .clone()
+                    .oneshot(req)
+                    .await
+                    .unwrap_or_else(|e: Infallible| match e {});
+                Ok(response)
+            } else {
+                fallback.oneshot(req).await.map_err(|e| {
+                    match e.downcast::<ApiError>() {
+                        Ok(e) => *e,
+                        Err(e) => InternalError::BufferError(e).into(),
+                    }
+                })
+            }
+        })
+    }
+}
+
+/// Returns the locale declared by the client, or if `detect` is enabled, the
+/// locale detected from the prompt.
+async fn resolve_locale(
+    req: Request,
+    detect: bool,
+) -> Result<(Request, Option<String>), ApiError> {
+    let declared = req
+        .headers()
+        .get(LOCALE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(ToString::to_string);
+    if declared.is_some() || !detect {
+        return Ok((req, declared));
+    }
+    let (parts, body) = req.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map_err(InternalError::CollectBodyError)?
+        .to_bytes();
+    let locale = serde_json::from_slice::<Value>(&body)
+        .ok()
+        .and_then(|value| detect_locale(&prompt_text(&value)))
+        .map(ToString::to_string);
+    let req = Request::from_parts(parts, axum_core::body::Body::from(body));
+    Ok((req, locale))
+}
+
+/// The text of the user messages of a chat completion request.
+fn prompt_text(body: &Value) -> String {
+    let mut text = String::new();
+    let messages = body
+        .get("messages")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for message in messages {
+        if message.get("role").and_then(Value::as_str) != Some("user") {
+            continue;
+        }
+        match message.get("content") {
+            Some(Value::String(content)) => text.push_str(content),
+            Some(Value::Array(parts)) => {
+                for part in parts {
+                    if let Some(part) = part.get("text").and_then(Value::as_str)
+                    {
+                        text.push_str(part);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Detects the language of `text` from its script.
+///
+/// Returns `None` for text in the Latin script, since the language can't be
+/// told apart from the script alone.
+fn detect_locale(text: &str) -> Option<&'static str> {
+    let mut counts = HashMap::<&'static str, usize>::new();
+    let mut has_kana = false;
+    for c in text.chars() {
+        let locale = match c {
+            '\u{3040}'..='\u{30ff}' => {
+                has_kana = true;
+                "ja"
+            }
+            '\u{ac00}'..='\u{d7af}' | '\u{1100}'..='\u{11ff}' => "ko",
+            '\u{4e00}'..='\u{9fff}' => "zh",
+            '\u{0400}'..='\u{04ff}' => "ru",
+            '\u{0600}'..='\u{06ff}' => "ar",
+            '\u{0590}'..='\u{05ff}' => "he",
+            '\u{0370}'..='\u{03ff}' => "el",
+            '\u{0900}'..='\u{097f}' => "hi",
+            '\u{0e00}'..='\u{0e7f}' => "th",
+            c if c.is_alphabetic() => "latin",
+            _ => continue,
+        };
+        *counts.entry(locale).or_default() += 1;
+    }
+    // japanese text mixes kana with han characters
+    if has_kana {
+        let han = counts.remove("zh").unwrap_or_default();
+        *counts.entry("ja").or_default() += han;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(locale, _)| locale)
+        .filter(|locale| *locale != "latin")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn locale_is_detected_from_script() {
+        assert_eq!(detect_locale("こんにちは、世界"), Some("ja"));
+        assert_eq!(detect_locale("你好，世界"), Some("zh"));
+        assert_eq!(detect_locale("안녕하세요"), Some("ko"));
+        assert_eq!(detect_locale("Привет, мир"), Some("ru"));
+        assert_eq!(detect_locale("Hello, world!"), None);
+        assert_eq!(detect_locale("Hallo, Welt!"), None);
+        assert_eq!(detect_locale(""), None);
+    }
+
+    #[test]
+    fn prompt_text_only_includes_user_messages() {
+        let body = json!({
+            "messages": [
+                { "role": "system", "content": "Answer in English." },
+                { "role": "user", "content": "Привет" },
+                {
+                    "role": "user",
+                    "content": [{ "type": "text", "text": ", мир" }]
+                }
+            ]
+        });
+        assert_eq!(prompt_text(&body), "Привет, мир");
+    }
+}
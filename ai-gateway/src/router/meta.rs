@@ -1,6 +1,7 @@
 use std::{
     convert::Infallible,
     future::{Ready, ready},
+    sync::Arc,
     task::{Context, Poll},
 };
 
@@ -25,9 +26,14 @@ use crate::{
         rate_limit::service::{
             Layer as RateLimitLayer, Service as RateLimitService,
         },
+        request_fingerprint::Layer as RequestFingerprintLayer,
+        spend_limit::Layer as SpendLimitLayer,
+        stream_limit::Layer as StreamLimitLayer,
+        tool_call_turns::Layer as ToolCallTurnsLayer,
     },
     router::{
         direct::{DirectProxiesWithoutMapper, DirectProxyServiceWithoutMapper},
+        models::{ModelList, is_models_request},
         router_details::{RouteType, RouterDetailsLayer},
         unified_api,
     },
@@ -45,6 +51,7 @@ pub struct MetaRouter {
     dynamic_router: DynamicRouter<RouterDiscovery, axum_core::body::Body>,
     unified_api: UnifiedApiService,
     direct_proxies: DirectProxiesWithoutMapper,
+    models: Arc<ModelList>,
 }
 
 pub type MetaRouterService = BoxCloneService<
@@ -69,6 +76,14 @@ impl MetaRouter {
                 crate::middleware::auth::AuthService::new(app_state.clone()),
             ))
             .layer(RateLimitLayer::global(&app_state)?)
+            .layer(SpendLimitLayer::global(&app_state)?)
+            .layer(ErrorHandlerLayer::new(app_state.clone()))
+            .layer(StreamLimitLayer::global(&app_state))
+            .layer(ErrorHandlerLayer::new(app_state.clone()))
+            .layer(RequestFingerprintLayer::global(&app_state))
+            .layer(ErrorHandlerLayer::new(app_state.clone()))
+            .layer(ToolCallTurnsLayer::global(&app_state))
+            .layer(ErrorHandlerLayer::new(app_state.clone()))
             .layer(CacheLayer::global(&app_state)?)
             .layer(ErrorHandlerLayer::new(app_state.clone()))
             .map_err(crate::error::internal::InternalError::BufferError)
@@ -94,10 +109,12 @@ impl MetaRouter {
         let direct_proxies =
             DirectProxiesWithoutMapper::new(&app_state).await?;
 
+        let models = Arc::new(ModelList::all(&app_state.config().providers));
         let meta_router = Self {
             dynamic_router,
             unified_api,
             direct_proxies,
+            models,
         };
         Ok(meta_router)
     }
@@ -114,10 +131,12 @@ impl MetaRouter {
             .service(unified_api::Service::new(&app_state).await?);
         let direct_proxies =
             DirectProxiesWithoutMapper::new(&app_state).await?;
+        let models = Arc::new(ModelList::all(&app_state.config().providers));
         let meta_router = Self {
             dynamic_router,
             unified_api,
             direct_proxies,
+            models,
         };
         Ok(meta_router)
     }
@@ -144,6 +163,11 @@ impl MetaRouter {
         rest: &str,
     ) -> ResponseFuture {
         tracing::trace!(api_path = rest, "received /ai request");
+        if is_models_request(&req, rest) {
+            return ResponseFuture::Ready {
+                future: ready(Ok(self.models.to_response())),
+            };
+        }
         // assumes request is from OpenAI compatible client
         // and uses the model name to determine the provider.
         ResponseFuture::UnifiedApi {
@@ -1,8 +1,15 @@
+pub mod complexity;
+pub mod cost;
 pub mod direct;
+pub mod failover;
+pub mod fan_out;
 pub mod latency;
+pub mod locale;
 pub mod meta;
+pub mod models;
 pub mod router_details;
 pub mod service;
+pub mod sticky;
 pub mod strategy;
 pub mod unified_api;
 
@@ -0,0 +1,157 @@
+//! An `OpenAI` compatible `/v1/models` listing of the models the gateway can
+//! route requests to.
+use axum_core::response::IntoResponse;
+use indexmap::IndexSet;
+use serde::Serialize;
+
+use crate::{
+    config::{
+        balance::{BalanceConfig, BalanceConfigInner},
+        providers::ProvidersConfig,
+    },
+    types::{
+        json::Json, model_id::ModelId, provider::InferenceProvider,
+        response::Response,
+    },
+};
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ModelList {
+    object: &'static str,
+    pub data: Vec<Model>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Model {
+    /// The model id in the `{provider}/{model}` form accepted in request
+    /// bodies.
+    pub id: String,
+    object: &'static str,
+    pub owned_by: String,
+}
+
+impl Model {
+    fn new(provider: &InferenceProvider, model: &ModelId) -> Self {
+        Self {
+            id: format!("{provider}/{model}"),
+            object: "model",
+            owned_by: provider.to_string(),
+        }
+    }
+}
+
+impl ModelList {
+    /// Every model of every configured provider.
+    #[must_use]
+    pub fn all(providers: &ProvidersConfig) -> Self {
+        let data = providers
+            .iter()
+            .flat_map(|(provider, config)| {
+                config
+                    .models
+                    .iter()
+                    .map(move |model| Model::new(provider, model))
+            })
+            .collect();
+        Self::new(data)
+    }
+
+    /// The models reachable through a router's balance config: the models
+    /// named by model based strategies, and every configured model of the
+    /// providers named by provider based strategies.
+    #[must_use]
+    pub fn for_router(
+        providers: &ProvidersConfig,
+        balance: &BalanceConfig,
+    ) -> Self {
+        let mut reachable: IndexSet<(InferenceProvider, ModelId)> =
+            IndexSet::new();
+        let mut add = |model: &ModelId| {
+            if let Some(provider) = model.inference_provider() {
+                reachable.insert((provider, model.clone()));
+            }
+        };
+        for balance_config in balance.0.values() {
+            match balance_config {
+                BalanceConfigInner::ModelWeighted { models, .. } => {
+                    for weighted in models {
+                        add(&weighted.model);
+                    }
+                }
+                BalanceConfigInner::ModelLatency { models } => {
+                    for model in models {
+                        add(model);
+                    }
+                }
+                BalanceConfigInner::CostWeighted { models } => {
+                    for (model, _) in models {
+                        add(model);
+                    }
+                }
+                BalanceConfigInner::Complexity {
+                    simple, complex, ..
+                } => {
+                    add(simple);
+                    add(complex);
+                }
+                BalanceConfigInner::ProviderWeighted { .. }
+                | BalanceConfigInner::BalancedLatency { .. }
+                | BalanceConfigInner::WeightedLatency { .. }
+                | BalanceConfigInner::LeastInFlight { .. }
+                | BalanceConfigInner::RoundRobin { .. } => {
+                    for provider in balance_config.providers() {
+                        let Some(config) = providers.get(&provider) else {
+                            continue;
+                        };
+                        for model in &config.models {
+                            add(model);
+                        }
+                    }
+                }
+            }
+        }
+        let data = reachable
+            .iter()
+            .map(|(provider, model)| Model::new(provider, model))
+            .collect();
+        Self::new(data)
+    }
+
+    fn new(data: Vec<Model>) -> Self {
+        Self {
+            object: "list",
+            data,
+        }
+    }
+
+    #[must_use]
+    pub fn to_response(&self) -> Response {
+        Json(self).into_response()
+    }
+}
+
+/// Whether a request for the given path (with any router or `/ai` prefix
+/// already removed) is a model listing request.
+#[must_use]
+pub fn is_models_request<B>(req: &http::Request<B>, path: &str) -> bool {
+    req.method() == http::Method::GET
+        && matches!(path.trim_matches('/'), "models" | "v1/models")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, tests::TestDefault};
+
+    #[test]
+    fn router_models_are_limited_to_balanced_providers() {
+        let config = Config::test_default();
+        let list = ModelList::for_router(
+            &config.providers,
+            &BalanceConfig::openai_chat(),
+        );
+        assert!(!list.data.is_empty());
+        assert!(list.data.iter().all(|m| m.owned_by == "openai"));
+        assert!(list.data.iter().any(|m| m.id == "openai/gpt-4o-mini"));
+    }
+}
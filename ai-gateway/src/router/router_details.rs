@@ -248,6 +248,10 @@ where
                     let mapper_ctx = MapperContext {
                         is_stream: false,
                         model: None,
+                        reasoning: false,
+                        base64_embeddings: false,
+                        seed_dropped: false,
+                        include_usage: false,
                     };
                     req.extensions_mut().insert(mapper_ctx);
                 }
@@ -21,9 +21,14 @@ use crate::{
         invalid_req::InvalidRequestError,
     },
     middleware::{
-        cache::CacheLayer, prompts::PromptLayer, rate_limit, request_context,
+        cache::CacheLayer, moderation, prompts::PromptLayer, queue, rate_limit,
+        request_context, sampling_bounds, stream_moderation, system_prompt,
+    },
+    router::{
+        meta::MIDDLEWARE_BUFFER_SIZE,
+        models::{ModelList, is_models_request},
+        strategy::RoutingStrategyService,
     },
-    router::{meta::MIDDLEWARE_BUFFER_SIZE, strategy::RoutingStrategyService},
     types::router::RouterId,
     utils::handle_error::ErrorHandlerLayer,
 };
@@ -40,6 +45,7 @@ type InnerRouterService = BoxCloneService<
 pub struct Router {
     inner: HashMap<EndpointType, InnerRouterService>,
     pub(crate) router_config: Arc<RouterConfig>,
+    models: Arc<ModelList>,
 }
 
 impl Router {
@@ -57,10 +63,29 @@ impl Router {
             &router_config,
         )
         .await?;
-        let prompt_layer = PromptLayer::new(&app_state)?;
+        let prompt_layer = PromptLayer::for_router(&app_state, &router_config)?;
+        let system_prompt_layer =
+            system_prompt::Layer::for_router(&router_config);
+        let sampling_bounds_layer =
+            sampling_bounds::Layer::for_router(&router_config);
+        let moderation_layer =
+            moderation::Layer::for_router(&app_state, &router_config);
+        let stream_moderation_layer =
+            stream_moderation::Layer::for_router(&router_config);
         let cache_layer = CacheLayer::for_router(&app_state, &router_config)?;
         let request_context_layer =
             request_context::Layer::for_router(router_config.clone());
+        let body_field_rl_layer =
+            rate_limit::body_field::Layer::per_router(&router_config);
+        let token_rl_layer =
+            rate_limit::tokens::Layer::per_router(&router_config);
+        app_state
+            .0
+            .model_rate_limits
+            .write()
+            .await
+            .register(&id, &router_config);
+        let queue_layer = queue::Layer::for_router(&router_config);
         for (endpoint_type, balance_config) in
             router_config.load_balance.as_ref()
         {
@@ -74,9 +99,16 @@ impl Router {
             let service_stack = ServiceBuilder::new()
                 .layer(ErrorHandlerLayer::new(app_state.clone()))
                 .layer(prompt_layer.clone())
+                .layer(system_prompt_layer.clone())
+                .layer(sampling_bounds_layer.clone())
+                .layer(moderation_layer.clone())
+                .layer(stream_moderation_layer.clone())
                 .layer(cache_layer.clone())
                 .layer(ErrorHandlerLayer::new(app_state.clone()))
                 .layer(rl_layer.clone())
+                .layer(body_field_rl_layer.clone())
+                .layer(token_rl_layer.clone())
+                .layer(queue_layer.clone())
                 .map_err(|e| ApiError::from(InternalError::BufferError(e)))
                 .layer(buffer::BufferLayer::new(MIDDLEWARE_BUFFER_SIZE))
                 .layer(request_context_layer.clone())
@@ -85,11 +117,17 @@ impl Router {
             inner.insert(*endpoint_type, BoxCloneService::new(service_stack));
         }
 
+        let models = Arc::new(ModelList::for_router(
+            &app_state.config().providers,
+            &router_config.load_balance,
+        ));
+
         tracing::info!(id = %id, "router created");
 
         Ok(Self {
             inner,
             router_config,
+            models,
         })
     }
 }
@@ -123,6 +161,15 @@ impl tower::Service<crate::types::request::Request> for Router {
         &mut self,
         mut req: crate::types::request::Request,
     ) -> Self::Future {
+        if let Some(header) = self.router_config.missing_header(req.headers()) {
+            let api_error = ApiError::InvalidRequest(
+                InvalidRequestError::MissingRequiredHeader(header.to_string()),
+            );
+            let response = api_error.into_response();
+            return ResponseFuture::Ready {
+                response: Some(response),
+            };
+        }
         let Some(extracted_path_and_query) =
             req.extensions().get::<PathAndQuery>()
         else {
@@ -135,6 +182,12 @@ impl tower::Service<crate::types::request::Request> for Router {
             };
         };
 
+        if is_models_request(&req, extracted_path_and_query.path()) {
+            return ResponseFuture::Ready {
+                response: Some(self.models.to_response()),
+            };
+        }
+
         let api_endpoint = ApiEndpoint::new(extracted_path_and_query.path());
         if let Some(api_endpoint) = api_endpoint {
             let endpoint_type = api_endpoint.endpoint_type();
@@ -0,0 +1,288 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt::Write,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use moka::future::Cache;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tower::{ServiceExt, buffer::Buffer};
+
+use crate::{
+    app_state::AppState,
+    config::{
+        router::RouterConfig,
+        stickiness::{ConversationKeyConfig, StickinessConfig},
+    },
+    discover::monitor::health::provider::is_provider_healthy,
+    dispatcher::{Dispatcher, DispatcherService},
+    error::{api::ApiError, init::InitError, internal::InternalError},
+    router::strategy::{ResponseFuture, RoutingStrategyService},
+    types::{
+        extensions::AuthContext, provider::InferenceProvider, request::Request,
+        response::Response, router::RouterId,
+    },
+};
+
+/// Header clients use to identify the conversation a request belongs to.
+pub(crate) const CONVERSATION_ID_HEADER: http::HeaderName =
+    http::HeaderName::from_static("x-helicone-conversation-id");
+
+/// The maximum number of conversations whose provider is remembered.
+const CONVERSATIONS_CAPACITY: u64 = 100_000;
+
+/// Sends the requests of a conversation to the provider that served the
+/// conversation before, as long as it's healthy, and balances all other
+/// requests with the router's strategy.
+#[derive(Clone)]
+pub struct StickyRouter {
+    app_state: AppState,
+    config: Arc<StickinessConfig>,
+    conversations: Cache<String, InferenceProvider>,
+    dispatchers: Arc<HashMap<InferenceProvider, DispatcherService>>,
+    fallback: Buffer<Request, ResponseFuture>,
+}
+
+impl std::fmt::Debug for StickyRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StickyRouter")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StickyRouter {
+    pub async fn new(
+        app_state: AppState,
+        router_id: &RouterId,
+        router_config: &Arc<RouterConfig>,
+        config: StickinessConfig,
+        fallback: RoutingStrategyService,
+    ) -> Result<Self, InitError> {
+        tracing::debug!("creating sticky routing strategy");
+        let mut dispatchers = HashMap::new();
+        for provider in router_config.load_balance.providers() {
+            if dispatchers.contains_key(&provider) {
+                continue;
+            }
+            let dispatcher = Dispatcher::new(
+                app_state.clone(),
+                router_id,
+                router_config,
+                provider.clone(),
+            )
+            .await?;
+            dispatchers.insert(provider, dispatcher);
+        }
+        let conversations = Cache::builder()
+            .max_capacity(CONVERSATIONS_CAPACITY)
+            .time_to_idle(config.ttl)
+            .build();
+        Ok(Self {
+            app_state,
+            config: Arc::new(config),
+            conversations,
+            dispatchers: Arc::new(dispatchers),
+            fallback: Buffer::new(
+                fallback,
+                crate::router::meta::MIDDLEWARE_BUFFER_SIZE,
+            ),
+        })
+    }
+}
+
+impl tower::Service<Request> for StickyRouter {
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        // the target is only known once the request is inspected, so it is
+        // driven to readiness when called
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let app_state = self.app_state.clone();
+        let config = self.config.clone();
+        let conversations = self.conversations.clone();
+        let dispatchers = self.dispatchers.clone();
+        let fallback = self.fallback.clone();
+        Box::pin(async move {
+            let (req, key) = conversation_key(req, config.derive).await?;
+            let Some(key) = key else {
+                return call_fallback(fallback, req).await;
+            };
+            // conversations move on from providers that became unhealthy
+            let provider = conversations.get(&key).await.filter(|provider| {
+                is_provider_healthy(&app_state, provider).unwrap_or(true)
+            });
+            if let Some(dispatcher) =
+                provider.as_ref().and_then(|p| dispatchers.get(p))
+            {
+                tracing::trace!(provider = ?provider, "routing request to conversation's provider");
+                let response = dispatcher
+                    .clone()
+                    .oneshot(req)
+                    .await
+                    .unwrap_or_else(|e: Infallible| match e {});
+                return Ok(response);
+            }
+            let response = call_fallback(fallback, req).await?;
+            if response.status().is_success()
+                && let Some(provider) =
+                    response.extensions().get::<InferenceProvider>()
+            {
+                conversations.insert(key, provider.clone()).await;
+            }
+            Ok(response)
+        })
+    }
+}
+
+async fn call_fallback(
+    fallback: Buffer<Request, ResponseFuture>,
+    req: Request,
+) -> Result<Response, ApiError> {
+    fallback
+        .oneshot(req)
+        .await
+        .map_err(|e| match e.downcast::<ApiError>() {
+            Ok(e) => *e,
+            Err(e) => InternalError::BufferError(e).into(),
+        })
+}
+
+/// Returns the key of the conversation the request belongs to, read from the
+/// `x-helicone-conversation-id` header or, if `derive` is set, derived from
+/// the leading messages of the request.
+///
+/// Keys are scoped to the user, so that users sending the same conversation
+/// id or leading messages don't share a provider.
+async fn conversation_key(
+    req: Request,
+    derive: Option<ConversationKeyConfig>,
+) -> Result<(Request, Option<String>), ApiError> {
+    let mut hasher = Sha256::new();
+    if let Some(auth_ctx) = req.extensions().get::<AuthContext>() {
+        hasher.update(auth_ctx.user_id.to_string());
+    }
+    if let Some(id) = req.headers().get(CONVERSATION_ID_HEADER) {
+        hasher.update(b"id:");
+        hasher.update(id.as_bytes());
+        return Ok((req, Some(hex_digest(hasher))));
+    }
+    let Some(derive) = derive else {
+        return Ok((req, None));
+    };
+    let (parts, body) = req.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map_err(InternalError::CollectBodyError)?
+        .to_bytes();
+    hasher.update(b"messages:");
+    let derived = serde_json::from_slice::<Value>(&body).is_ok_and(|body| {
+        hash_leading_messages(&mut hasher, &body, derive.prefix_user_messages)
+    });
+    let key = derived.then(|| hex_digest(hasher));
+    let req = Request::from_parts(parts, axum_core::body::Body::from(body));
+    Ok((req, key))
+}
+
+/// Hashes the system prompt and the messages of a chat request up to and
+/// including its `prefix_user_messages`th user message, returning whether
+/// the request had that many user messages.
+fn hash_leading_messages(
+    hasher: &mut Sha256,
+    body: &Value,
+    prefix_user_messages: usize,
+) -> bool {
+    let messages = body
+        .get("messages")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    // anthropic formatted requests have the system prompt outside of the
+    // messages
+    if let Some(system) = body.get("system") {
+        hasher.update(system.to_string());
+    }
+    let mut user_messages = 0;
+    for message in messages {
+        let role = message.get("role").and_then(Value::as_str);
+        hasher.update(json!([role, message.get("content")]).to_string());
+        if role == Some("user") {
+            user_messages += 1;
+            if user_messages == prefix_user_messages {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn hex_digest(hasher: Sha256) -> String {
+    let digest = hasher.finalize();
+    let mut hex = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(body: &Value, prefix_user_messages: usize) -> Option<String> {
+        let mut hasher = Sha256::new();
+        hash_leading_messages(&mut hasher, body, prefix_user_messages)
+            .then(|| hex_digest(hasher))
+    }
+
+    #[test]
+    fn follow_up_turns_derive_the_same_key() {
+        let first_turn = json!({
+            "messages": [
+                { "role": "system", "content": "You are a helpful assistant." },
+                { "role": "user", "content": "Hello!" }
+            ]
+        });
+        let second_turn = json!({
+            "messages": [
+                { "role": "system", "content": "You are a helpful assistant." },
+                { "role": "user", "content": "Hello!" },
+                { "role": "assistant", "content": "Hi! How can I help?" },
+                { "role": "user", "content": "Tell me a joke." }
+            ]
+        });
+        let other_conversation = json!({
+            "messages": [
+                { "role": "system", "content": "You are a helpful assistant." },
+                { "role": "user", "content": "Goodbye!" }
+            ]
+        });
+        assert!(key(&first_turn, 1).is_some());
+        assert_eq!(key(&first_turn, 1), key(&second_turn, 1));
+        assert_ne!(key(&first_turn, 1), key(&other_conversation, 1));
+    }
+
+    #[test]
+    fn requests_shorter_than_the_prefix_have_no_key() {
+        let body = json!({
+            "messages": [{ "role": "user", "content": "Hello!" }]
+        });
+        assert_eq!(key(&body, 2), None);
+        assert_eq!(key(&json!({}), 1), None);
+    }
+}
@@ -3,6 +3,7 @@ use std::{
     task::{Context, Poll},
 };
 
+use dynamic_router::router::DynamicRouter;
 use futures::{Future, ready};
 use pin_project_lite::pin_project;
 use tokio::sync::mpsc::channel;
@@ -17,7 +18,11 @@ use crate::{
         model, provider,
     },
     error::{api::ApiError, init::InitError, internal::InternalError},
-    router::latency::LatencyRouter,
+    router::{
+        complexity::ComplexityRouter, cost::CostRouter,
+        failover::FailoverRouter, fan_out::FanOutRouter,
+        latency::LatencyRouter, locale::LocaleRouter, sticky::StickyRouter,
+    },
     types::{request::Request, response::Response, router::RouterId},
 };
 
@@ -41,7 +46,10 @@ pub enum RoutingStrategyService {
     /// Strategy:
     /// 1. receive request
     /// 2. according to configured weighted distribution, randomly sample a
-    ///    single provider from the set of providers.
+    ///    single provider from the set of providers. For `weighted-latency`
+    ///    routers, each weight is first scaled by the provider's rolling
+    ///    latency from the
+    ///    [`EndpointMetricsRegistry`](crate::discover::monitor::metrics::EndpointMetricsRegistry).
     /// 3. if the provider does not have requested model, map it to a model
     ///    offered by the target provider.
     /// 4. send request
@@ -72,6 +80,81 @@ pub enum RoutingStrategyService {
     /// 3. pick the lowest latency provider that serves the requested model
     /// 4. send request
     ModelLatency(LatencyRouter),
+    /// Strategy:
+    /// 1. receive request
+    /// 2. according to configured weighted distribution, randomly sample
+    ///    several distinct (provider, model) pairs.
+    /// 3. send request to all of them in parallel
+    /// 4. return the first successful response, or all responses
+    FanOut(FanOutRouter),
+    /// Strategy:
+    /// 1. receive request
+    /// 2. apply the per request price overrides from the
+    ///    `helicone-cost-priority` header
+    /// 3. pick the cheapest (provider, model) that is healthy and not rate
+    ///    limited, preferring the lower latency one on equal prices
+    /// 4. send request
+    Cost(CostRouter),
+    /// Strategy:
+    /// 1. receive request
+    /// 2. estimate the complexity of the prompt from its length and whether it
+    ///    provides tools or includes images
+    /// 3. send simple requests to the cheaper model and complex requests to the
+    ///    stronger model
+    Complexity(ComplexityRouter),
+    /// Strategy:
+    /// 1. receive request
+    /// 2. according to configured weighted distribution, randomly sample two
+    ///    providers for the request's endpoint type, and pick the one with
+    ///    fewer requests in flight
+    /// 3. if the provider does not have requested model, map it to a model
+    ///    offered by the target provider.
+    /// 4. send request
+    LeastInFlight(
+        DynamicRouter<
+            WeightedDiscover<
+                DispatcherDiscovery<provider::weighted_key::WeightedKey>,
+            >,
+            axum_core::body::Body,
+        >,
+    ),
+    /// Strategy:
+    /// 1. receive request
+    /// 2. pick the next ready provider for the request's endpoint type, cycling
+    ///    through the providers in the order they are configured
+    /// 3. if the provider does not have requested model, map it to a model
+    ///    offered by the target provider.
+    /// 4. send request
+    RoundRobin(
+        DynamicRouter<
+            PeakEwmaDiscover<DispatcherDiscovery<provider::key::Key>>,
+            axum_core::body::Body,
+        >,
+    ),
+    /// Strategy:
+    /// 1. receive request
+    /// 2. read the locale from the `x-helicone-locale` header, or detect it
+    ///    from the prompt
+    /// 3. if a provider is configured for the locale, send the request to it
+    /// 4. otherwise, balance the request with the router's strategy
+    Locale(LocaleRouter),
+    /// Strategy:
+    /// 1. receive request
+    /// 2. identify the conversation of the request from the
+    ///    `x-helicone-conversation-id` header, or derive it from the leading
+    ///    messages
+    /// 3. if a healthy provider served the conversation before, send the
+    ///    request to it
+    /// 4. otherwise, balance the request with the router's strategy and
+    ///    remember the provider that served it
+    Sticky(StickyRouter),
+    /// Strategy:
+    /// 1. receive request
+    /// 2. balance the request with the router's strategy
+    /// 3. if the provider fails with a configured status code or error type,
+    ///    e.g. `overloaded_error`, resend the request to the next failover
+    ///    provider
+    Failover(FailoverRouter),
 }
 
 impl RoutingStrategyService {
@@ -80,9 +163,61 @@ impl RoutingStrategyService {
         router_id: RouterId,
         router_config: Arc<RouterConfig>,
         balance_config: &BalanceConfigInner,
+    ) -> Result<RoutingStrategyService, InitError> {
+        let strategy = Self::balanced(
+            app_state.clone(),
+            router_id.clone(),
+            router_config.clone(),
+            balance_config,
+        )
+        .await?;
+        let strategy = match router_config.locale_routing.clone() {
+            Some(locale_routing) => LocaleRouter::new(
+                app_state.clone(),
+                &router_id,
+                &router_config,
+                locale_routing,
+                strategy,
+            )
+            .await
+            .map(Self::Locale)?,
+            None => strategy,
+        };
+        let strategy = match router_config.stickiness.clone() {
+            Some(stickiness) => StickyRouter::new(
+                app_state.clone(),
+                &router_id,
+                &router_config,
+                stickiness,
+                strategy,
+            )
+            .await
+            .map(Self::Sticky)?,
+            None => strategy,
+        };
+        match router_config.failover.clone() {
+            Some(failover) => FailoverRouter::new(
+                app_state,
+                &router_id,
+                &router_config,
+                failover,
+                strategy,
+            )
+            .await
+            .map(Self::Failover),
+            None => Ok(strategy),
+        }
+    }
+
+    async fn balanced(
+        app_state: AppState,
+        router_id: RouterId,
+        router_config: Arc<RouterConfig>,
+        balance_config: &BalanceConfigInner,
     ) -> Result<RoutingStrategyService, InitError> {
         match balance_config {
-            BalanceConfigInner::ProviderWeighted { .. } => {
+            BalanceConfigInner::ProviderWeighted { .. }
+            | BalanceConfigInner::WeightedLatency { .. } => {
                 Self::provider_weighted(app_state, router_id, router_config)
                     .await
             }
@@ -90,7 +225,22 @@ impl RoutingStrategyService {
                 Self::provider_latency(app_state, router_id, router_config)
                     .await
             }
-            BalanceConfigInner::ModelWeighted { .. } => {
+            BalanceConfigInner::LeastInFlight { .. } => {
+                Self::least_in_flight(app_state, router_id, router_config).await
+            }
+            BalanceConfigInner::ModelWeighted {
+                models,
+                fan_out: Some(fan_out),
+            } => FanOutRouter::new(
+                app_state,
+                router_id,
+                router_config,
+                models,
+                fan_out.clone(),
+            )
+            .await
+            .map(Self::FanOut),
+            BalanceConfigInner::ModelWeighted { fan_out: None, .. } => {
                 Self::model_weighted(app_state, router_id, router_config).await
             }
             BalanceConfigInner::ModelLatency { .. } => {
@@ -98,6 +248,28 @@ impl RoutingStrategyService {
                     .await
                     .map(Self::ModelLatency)
             }
+            BalanceConfigInner::RoundRobin { .. } => {
+                Self::round_robin(app_state, router_id, router_config).await
+            }
+            BalanceConfigInner::CostWeighted { models } => {
+                CostRouter::new(app_state, router_id, router_config, models)
+                    .await
+                    .map(Self::Cost)
+            }
+            BalanceConfigInner::Complexity {
+                simple,
+                complex,
+                thresholds,
+            } => ComplexityRouter::new(
+                app_state,
+                &router_id,
+                &router_config,
+                simple,
+                complex,
+                *thresholds,
+            )
+            .await
+            .map(Self::Complexity),
         }
     }
 
@@ -143,6 +315,48 @@ impl RoutingStrategyService {
         Ok(provider_balancer)
     }
 
+    async fn least_in_flight(
+        app_state: AppState,
+        router_id: RouterId,
+        router_config: Arc<RouterConfig>,
+    ) -> Result<RoutingStrategyService, InitError> {
+        tracing::debug!("creating least in flight routing strategy");
+        let (change_tx, change_rx) = channel(CHANNEL_CAPACITY);
+        let (rate_limit_tx, rate_limit_rx) = channel(CHANNEL_CAPACITY);
+        let mut discover_factory = DispatcherDiscoverFactory::new(
+            app_state.clone(),
+            router_id.clone(),
+            router_config.clone(),
+        );
+        app_state
+            .add_provider_weighted_router_health_monitor(
+                router_id.clone(),
+                router_config.clone(),
+                change_tx.clone(),
+            )
+            .await;
+        app_state
+            .add_rate_limit_tx(router_id.clone(), rate_limit_tx)
+            .await;
+        app_state
+            .add_rate_limit_rx(router_id.clone(), rate_limit_rx)
+            .await;
+        app_state
+            .add_provider_weighted_router_rate_limit_monitor(
+                router_id.clone(),
+                router_config,
+                change_tx,
+            )
+            .await;
+        let discover = discover_factory.call(change_rx).await?;
+        let router = DynamicRouter::p2c(
+            discover,
+            provider::weighted_key::WeightedKey::serves,
+        );
+
+        Ok(RoutingStrategyService::LeastInFlight(router))
+    }
+
     async fn model_weighted(
         app_state: AppState,
         router_id: RouterId,
@@ -225,6 +439,46 @@ impl RoutingStrategyService {
 
         Ok(provider_balancer)
     }
+
+    async fn round_robin(
+        app_state: AppState,
+        router_id: RouterId,
+        router_config: Arc<RouterConfig>,
+    ) -> Result<RoutingStrategyService, InitError> {
+        tracing::debug!("creating round robin routing strategy");
+        let (change_tx, change_rx) = channel(CHANNEL_CAPACITY);
+        let (rate_limit_tx, rate_limit_rx) = channel(CHANNEL_CAPACITY);
+        let mut discover_factory = DispatcherDiscoverFactory::new(
+            app_state.clone(),
+            router_id.clone(),
+            router_config.clone(),
+        );
+        app_state
+            .add_provider_latency_router_health_monitor(
+                router_id.clone(),
+                router_config.clone(),
+                change_tx.clone(),
+            )
+            .await;
+        app_state
+            .add_rate_limit_tx(router_id.clone(), rate_limit_tx)
+            .await;
+        app_state
+            .add_rate_limit_rx(router_id.clone(), rate_limit_rx)
+            .await;
+        app_state
+            .add_provider_latency_router_rate_limit_monitor(
+                router_id.clone(),
+                router_config,
+                change_tx,
+            )
+            .await;
+        let discover = discover_factory.call(change_rx).await?;
+        let router =
+            DynamicRouter::round_robin(discover, provider::key::Key::serves);
+
+        Ok(RoutingStrategyService::RoundRobin(router))
+    }
 }
 
 impl tower::Service<Request> for RoutingStrategyService {
@@ -250,6 +504,30 @@ impl tower::Service<Request> for RoutingStrategyService {
             RoutingStrategyService::ModelLatency(inner) => {
                 return inner.poll_ready(cx);
             }
+            RoutingStrategyService::FanOut(inner) => {
+                return inner.poll_ready(cx);
+            }
+            RoutingStrategyService::Cost(inner) => {
+                return inner.poll_ready(cx);
+            }
+            RoutingStrategyService::Complexity(inner) => {
+                return inner.poll_ready(cx);
+            }
+            RoutingStrategyService::LeastInFlight(inner) => {
+                return inner.poll_ready(cx).map_err(Into::into);
+            }
+            RoutingStrategyService::RoundRobin(inner) => {
+                return inner.poll_ready(cx).map_err(Into::into);
+            }
+            RoutingStrategyService::Locale(inner) => {
+                return inner.poll_ready(cx);
+            }
+            RoutingStrategyService::Sticky(inner) => {
+                return inner.poll_ready(cx);
+            }
+            RoutingStrategyService::Failover(inner) => {
+                return inner.poll_ready(cx);
+            }
         }
         .map_err(InternalError::PollReadyError)
         .map_err(Into::into)
@@ -277,6 +555,38 @@ impl tower::Service<Request> for RoutingStrategyService {
                     future: inner.call(req),
                 }
             }
+            RoutingStrategyService::FanOut(inner) => ResponseFuture::FanOut {
+                future: inner.call(req),
+            },
+            RoutingStrategyService::Cost(inner) => ResponseFuture::Cost {
+                future: inner.call(req),
+            },
+            RoutingStrategyService::Complexity(inner) => {
+                ResponseFuture::Complexity {
+                    future: inner.call(req),
+                }
+            }
+            RoutingStrategyService::LeastInFlight(inner) => {
+                ResponseFuture::LeastInFlight {
+                    future: inner.call(req),
+                }
+            }
+            RoutingStrategyService::RoundRobin(inner) => {
+                ResponseFuture::RoundRobin {
+                    future: inner.call(req),
+                }
+            }
+            RoutingStrategyService::Locale(inner) => ResponseFuture::Locale {
+                future: inner.call(req),
+            },
+            RoutingStrategyService::Sticky(inner) => ResponseFuture::Sticky {
+                future: inner.call(req),
+            },
+            RoutingStrategyService::Failover(inner) => {
+                ResponseFuture::Failover {
+                    future: inner.call(req),
+                }
+            }
         }
     }
 }
@@ -312,6 +622,46 @@ pin_project! {
             #[pin]
             future: <LatencyRouter as tower::Service<Request>>::Future,
         },
+        FanOut {
+            #[pin]
+            future: <FanOutRouter as tower::Service<Request>>::Future,
+        },
+        Cost {
+            #[pin]
+            future: <CostRouter as tower::Service<Request>>::Future,
+        },
+        Complexity {
+            #[pin]
+            future: <ComplexityRouter as tower::Service<Request>>::Future,
+        },
+        LeastInFlight {
+            #[pin]
+            future: <
+                DynamicRouter<WeightedDiscover<DispatcherDiscovery<provider::weighted_key::WeightedKey>>, axum_core::body::Body> as tower::Service<
+                    Request,
+                >
+            >::Future,
+        },
+        RoundRobin {
+            #[pin]
+            future: <
+                DynamicRouter<PeakEwmaDiscover<DispatcherDiscovery<provider::key::Key>>, axum_core::body::Body> as tower::Service<
+                    Request,
+                >
+            >::Future,
+        },
+        Locale {
+            #[pin]
+            future: <LocaleRouter as tower::Service<Request>>::Future,
+        },
+        Sticky {
+            #[pin]
+            future: <StickyRouter as tower::Service<Request>>::Future,
+        },
+        Failover {
+            #[pin]
+            future: <FailoverRouter as tower::Service<Request>>::Future,
+        },
     }
 }
 
@@ -339,6 +689,22 @@ impl Future for ResponseFuture {
             EnumProj::ModelLatency { future } => {
                 Poll::Ready(ready!(future.poll(cx)))
             }
+            EnumProj::FanOut { future } => Poll::Ready(ready!(future.poll(cx))),
+            EnumProj::Cost { future } => Poll::Ready(ready!(future.poll(cx))),
+            EnumProj::Complexity { future } => {
+                Poll::Ready(ready!(future.poll(cx)))
+            }
+            EnumProj::LeastInFlight { future } => {
+                Poll::Ready(ready!(future.poll(cx).map_err(Into::into)))
+            }
+            EnumProj::RoundRobin { future } => {
+                Poll::Ready(ready!(future.poll(cx).map_err(Into::into)))
+            }
+            EnumProj::Locale { future } => Poll::Ready(ready!(future.poll(cx))),
+            EnumProj::Sticky { future } => Poll::Ready(ready!(future.poll(cx))),
+            EnumProj::Failover { future } => {
+                Poll::Ready(ready!(future.poll(cx)))
+            }
         }
     }
 }
@@ -1,6 +1,8 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, fmt::Write, sync::Arc};
 
-use derive_more::{AsRef, From, Into};
+use derive_more::{AsRef, Display, From, Into};
+use rust_decimal::Decimal;
+use sha2::{Digest, Sha256};
 
 use super::{model_id::ModelId, org::OrgId, user::UserId};
 use crate::{config::router::RouterConfig, types::secret::Secret};
@@ -8,6 +10,42 @@ use crate::{config::router::RouterConfig, types::secret::Secret};
 #[derive(Debug, Clone, AsRef, From, Into)]
 pub struct ProviderRequestId(pub(crate) http::HeaderValue);
 
+/// A summary of the body sent to the provider after all transformations,
+/// formatted as `sha256=<hex digest>; bytes=<size>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectiveRequest {
+    /// Hex encoded SHA-256 digest of the body.
+    pub sha256: String,
+    /// Size of the body in bytes.
+    pub bytes: usize,
+}
+
+impl EffectiveRequest {
+    #[must_use]
+    pub fn new(body: &[u8]) -> Self {
+        let digest = Sha256::digest(body);
+        let mut sha256 = String::with_capacity(digest.len() * 2);
+        for byte in digest {
+            let _ = write!(sha256, "{byte:02x}");
+        }
+        Self {
+            sha256,
+            bytes: body.len(),
+        }
+    }
+}
+
+impl std::fmt::Display for EffectiveRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "sha256={}; bytes={}", self.sha256, self.bytes)
+    }
+}
+
+/// The id Helicone uses to identify a request, returned to clients in the
+/// `x-helicone-request-id` response header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub struct HeliconeRequestId(pub uuid::Uuid);
+
 #[derive(Debug, Clone)]
 pub struct AuthContext {
     pub api_key: Secret<String>,
@@ -23,15 +61,106 @@ pub struct RequestContext {
     /// If `None`, the router is configured to not require auth for requests,
     /// disabling some features.
     pub auth_context: Option<AuthContext>,
+    /// Per 1k token prices that override the ones configured for a
+    /// `cost-weighted` router, from the `helicone-cost-priority` header.
+    pub cost_overrides: HashMap<ModelId, Decimal>,
 }
 
 #[derive(Debug, Clone)]
 pub struct MapperContext {
+    /// Whether the response is streamed to the client.
+    ///
+    /// If the provider doesn't support the kind of request the client sent,
+    /// this is the kind sent to the provider until the mapper converts the
+    /// response back to the kind the client asked for.
     pub is_stream: bool,
     /// If `None`, the request was for an endpoint without
     /// first class support for mapping between different provider
     /// models.
     pub model: Option<ModelId>,
+    /// Whether the client opted in to reasoning, in which case any reasoning
+    /// returned by the provider is surfaced in the mapped response.
+    pub reasoning: bool,
+    /// Whether the client asked for base64 encoded embeddings, which are
+    /// encoded by the gateway for providers that only return floats.
+    pub base64_embeddings: bool,
+    /// Whether the client set a `seed` that was dropped because the
+    /// provider has no equivalent for it, so the response is not
+    /// reproducible.
+    pub seed_dropped: bool,
+    /// Whether the client asked for the token usage of a streamed response
+    /// with `stream_options.include_usage`.
+    pub include_usage: bool,
+}
+
+/// A transformation the gateway applied to a request, or to its response,
+/// between the client and the provider.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transformation {
+    /// A stored prompt was merged into the request.
+    Prompt,
+    /// The router's mandatory system prompt was prepended to the request.
+    SystemPrompt,
+    /// The request was mapped to the format of another provider.
+    Mapping,
+    /// A streaming request was sent as a non-streaming one, or vice versa.
+    StreamingConversion,
+    /// The tenant the request was made for was added to the request.
+    TenantAttribution,
+    /// An instruction to call a tool was added when retrying the request.
+    ToolInstruction,
+    /// Patterns were redacted from the streamed response.
+    Redaction,
+    /// Messages were merged or inserted so that their roles alternate.
+    RoleAlternation,
+    /// Parts of the prompt were marked as cacheable by the provider.
+    PromptCaching,
+    /// Sampling parameters were clamped into the router's bounds.
+    SamplingBounds,
+}
+
+/// The transformations applied to a request, in the order they were first
+/// applied.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppliedTransformations(pub Vec<Transformation>);
+
+impl AppliedTransformations {
+    /// Records that `transformation` was applied to the request the
+    /// `extensions` belong to.
+    pub fn record(
+        extensions: &mut http::Extensions,
+        transformation: Transformation,
+    ) {
+        let applied = &mut extensions
+            .get_or_insert_default::<AppliedTransformations>()
+            .0;
+        if !applied.contains(&transformation) {
+            applied.push(transformation);
+        }
+    }
+}
+
+/// The type of error a provider reported in an error response, e.g.
+/// `overloaded_error`, read before the response is mapped for the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderErrorType(pub String);
+
+impl ProviderErrorType {
+    /// Reads the error type from the `error.type` field of an error response
+    /// body, falling back to `error.status` for providers like Gemini.
+    #[must_use]
+    pub fn parse(body: &[u8]) -> Option<Self> {
+        let value = serde_json::from_slice::<serde_json::Value>(body).ok()?;
+        let error = value.get("error")?;
+        error
+            .get("type")
+            .or_else(|| error.get("status"))
+            .and_then(serde_json::Value::as_str)
+            .map(|error_type| Self(error_type.to_string()))
+    }
 }
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
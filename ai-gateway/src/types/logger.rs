@@ -12,7 +12,10 @@ use crate::{
         DeploymentTarget, DeploymentTargetDiscriminants,
     },
     error::logger::LoggerError,
-    types::{extensions::PromptContext, router::RouterId},
+    types::{
+        extensions::{PromptContext, Transformation},
+        router::RouterId,
+    },
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -154,6 +157,24 @@ pub struct RequestLog {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[builder(default)]
     pub cache_reference_id: Option<String>,
+    /// The strategy the router used to balance the request, if it was sent
+    /// to a load balanced router.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub balance_strategy: Option<String>,
+    /// The provider the request was routed to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub selected_provider: Option<String>,
+    /// The model the request was routed to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub selected_model: Option<String>,
+    /// The transformations the gateway applied to the request, if the
+    /// router audits them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub applied_transformations: Option<Vec<Transformation>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, TypedBuilder)]
@@ -167,6 +188,30 @@ pub struct ResponseLog {
     pub time_to_first_token: Option<f64>,
     pub response_created_at: DateTime<Utc>,
     pub delay_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub prompt_tokens: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub completion_tokens: Option<f64>,
+    /// Set if the token counts were estimated by the gateway because the
+    /// provider didn't report them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub usage_estimated: Option<bool>,
+    /// Prompt tokens the provider reported writing to its prompt cache.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub prompt_cache_write_tokens: Option<f64>,
+    /// Prompt tokens the provider reported reading from its prompt cache.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub prompt_cache_read_tokens: Option<f64>,
+    /// Cost in USD of the tokens reported by the provider, if the model has
+    /// a price in the gateway's pricing config.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub cost: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -190,3 +235,61 @@ pub struct LogMessage {
     pub helicone_meta: HeliconeLogMetadata,
     pub log: Log,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routing_decision_is_logged() {
+        let request_log = RequestLog::builder()
+            .id(Uuid::new_v4())
+            .user_id(UserId::new(Uuid::new_v4()))
+            .target_url(
+                "https://api.openai.com/v1/chat/completions"
+                    .parse()
+                    .unwrap(),
+            )
+            .provider("OPENAI".to_string())
+            .body_size(0.0)
+            .path("/v1/chat/completions".to_string())
+            .request_created_at(Utc::now())
+            .is_stream(false)
+            .balance_strategy(Some("model-weighted".to_string()))
+            .selected_provider(Some("openai".to_string()))
+            .selected_model(Some("gpt-4o-mini".to_string()))
+            .build();
+        let value = serde_json::to_value(&request_log).unwrap();
+        assert_eq!(value["balanceStrategy"], "model-weighted");
+        assert_eq!(value["selectedProvider"], "openai");
+        assert_eq!(value["selectedModel"], "gpt-4o-mini");
+    }
+
+    #[test]
+    fn estimated_usage_is_flagged() {
+        let response_log = ResponseLog::builder()
+            .id(Uuid::new_v4())
+            .status(200.0)
+            .body_size(0.0)
+            .response_created_at(Utc::now())
+            .delay_ms(0.0)
+            .prompt_tokens(Some(4.0))
+            .completion_tokens(Some(3.0))
+            .usage_estimated(Some(true))
+            .build();
+        let value = serde_json::to_value(&response_log).unwrap();
+        assert_eq!(value["promptTokens"], 4.0);
+        assert_eq!(value["completionTokens"], 3.0);
+        assert_eq!(value["usageEstimated"], true);
+
+        let response_log = ResponseLog::builder()
+            .id(Uuid::new_v4())
+            .status(200.0)
+            .body_size(0.0)
+            .response_created_at(Utc::now())
+            .delay_ms(0.0)
+            .build();
+        let value = serde_json::to_value(&response_log).unwrap();
+        assert!(value.get("usageEstimated").is_none());
+    }
+}
@@ -9,6 +9,7 @@ use tokio::sync::RwLock;
 use super::secret::Secret;
 use crate::{
     config::{Config, providers::ProvidersConfig},
+    dispatcher::vertex_client,
     endpoints::ApiEndpoint,
     error::provider::ProviderError,
     metrics::Metrics,
@@ -110,11 +111,13 @@ impl InferenceProvider {
                     .map(ApiEndpoint::Google)
                     .collect()
             }
+            // named providers are mostly `OpenAI` compatible, but some have
+            // their own format for e.g. embeddings
             InferenceProvider::Named(_) => {
                 crate::endpoints::openai::OpenAI::iter()
-                    .map(|endpoint| ApiEndpoint::OpenAICompatible {
-                        provider: self.clone(),
-                        openai_endpoint: endpoint,
+                    .filter_map(|endpoint| {
+                        ApiEndpoint::mapped(ApiEndpoint::OpenAI(endpoint), self)
+                            .ok()
                     })
                     .collect()
             }
@@ -214,6 +217,23 @@ impl ProviderKey {
         }
     }
 
+    /// Whether the key can be used to authenticate requests, i.e. it is not
+    /// an empty string.
+    #[must_use]
+    pub fn is_configured(&self) -> bool {
+        match self {
+            ProviderKey::Secret(key) => !key.expose().is_empty(),
+            ProviderKey::AwsCredentials {
+                access_key,
+                secret_key,
+            } => {
+                !access_key.expose().is_empty()
+                    && !secret_key.expose().is_empty()
+            }
+            ProviderKey::NotRequired => true,
+        }
+    }
+
     #[must_use]
     pub fn from_env(provider: &InferenceProvider) -> Option<Self> {
         if *provider == InferenceProvider::Bedrock {
@@ -233,6 +253,16 @@ impl ProviderKey {
             let env_var = format!("{provider_str}_API_KEY");
             if let Ok(key) = std::env::var(&env_var) {
                 Some(ProviderKey::Secret(Secret::from(key)))
+            } else if provider.as_ref() == vertex_client::PROVIDER_NAME {
+                // the service account key file used by google cloud sdks
+                let path =
+                    std::env::var("GOOGLE_APPLICATION_CREDENTIALS").ok()?;
+                let key = std::fs::read_to_string(&path)
+                    .inspect_err(|e| {
+                        tracing::warn!(error = %e, path = %path, "failed to read google application credentials");
+                    })
+                    .ok()?;
+                Some(ProviderKey::Secret(Secret::from(key)))
             } else {
                 None
             }
@@ -362,4 +392,57 @@ mod tests {
         let named_provider_str = named_provider.to_string();
         assert_eq!("test", named_provider_str);
     }
+
+    fn org_keys(key: &str) -> ProviderKeyMap {
+        ProviderKeyMap::from_db(HashMap::from_iter([(
+            InferenceProvider::OpenAI,
+            ProviderKey::Secret(Secret::from(key.to_string())),
+        )]))
+    }
+
+    fn exposed(key: Option<ProviderKey>) -> Option<String> {
+        key.and_then(|key| key.as_secret().map(|key| key.expose().clone()))
+    }
+
+    #[tokio::test]
+    async fn cloud_provider_keys_are_scoped_to_org() {
+        let org_a = OrgId::new(uuid::Uuid::new_v4());
+        let org_b = OrgId::new(uuid::Uuid::new_v4());
+        let keys = ProviderKeys::Cloud(RwLock::new(HashMap::default()));
+        keys.set_all_provider_keys(HashMap::from_iter([
+            (org_a, org_keys("sk-org-a")),
+            (org_b, org_keys("sk-org-b")),
+        ]))
+        .await;
+
+        let provider = InferenceProvider::OpenAI;
+        assert_eq!(
+            exposed(keys.get_provider_key(&provider, Some(&org_a)).await),
+            Some("sk-org-a".to_string())
+        );
+        assert_eq!(
+            exposed(keys.get_provider_key(&provider, Some(&org_b)).await),
+            Some("sk-org-b".to_string())
+        );
+        // keys are never shared across orgs
+        let org_c = OrgId::new(uuid::Uuid::new_v4());
+        assert!(
+            keys.get_provider_key(&provider, Some(&org_c))
+                .await
+                .is_none()
+        );
+        assert!(keys.get_provider_key(&provider, None).await.is_none());
+
+        // updating one org's keys leaves the other org's keys untouched
+        keys.set_org_provider_keys(org_a, org_keys("sk-org-a-rotated"))
+            .await;
+        assert_eq!(
+            exposed(keys.get_provider_key(&provider, Some(&org_a)).await),
+            Some("sk-org-a-rotated".to_string())
+        );
+        assert_eq!(
+            exposed(keys.get_provider_key(&provider, Some(&org_b)).await),
+            Some("sk-org-b".to_string())
+        );
+    }
 }
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{body_partial_json, method, path},
+};
+use tower::Service;
+
+async fn harness() -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing response mapping
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+fn chat_request(stream: bool) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "anthropic/claude-3-7-sonnet-latest",
+            "max_tokens": 4096,
+            "reasoning_effort": "low",
+            "temperature": 0.2,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "What is 2 + 2?"
+                }
+            ],
+            "stream": stream
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn thinking_is_requested_and_surfaced_as_reasoning() {
+    let mut harness = harness().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(json!({
+            "thinking": { "type": "enabled", "budget_tokens": 1024 }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_123",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-3-7-sonnet-20250219",
+            "content": [
+                { "type": "thinking", "thinking": "2 + 2 is 4", "signature": "sig" },
+                { "type": "text", "text": "The answer is 4." }
+            ],
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": { "input_tokens": 12, "output_tokens": 20 }
+        })))
+        .with_priority(1)
+        .expect(1)
+        .mount(&harness.mock.anthropic_mock.http_server)
+        .await;
+
+    let response = harness.call(chat_request(false)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let message = &body["choices"][0]["message"];
+    assert_eq!(message["content"], "The answer is 4.");
+    assert_eq!(message["reasoning_content"], "2 + 2 is 4");
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn streamed_thinking_delta_is_not_content() {
+    let mut harness = harness().await;
+    let events = [
+        json!({
+            "type": "content_block_start",
+            "index": 0,
+            "content_block": {
+                "type": "thinking",
+                "thinking": "",
+                "signature": ""
+            }
+        }),
+        json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": { "type": "thinking_delta", "thinking": "Let me think" }
+        }),
+        json!({ "type": "content_block_stop", "index": 0 }),
+        json!({
+            "type": "content_block_delta",
+            "index": 1,
+            "delta": { "type": "text_delta", "text": "4" }
+        }),
+        json!({ "type": "message_stop" }),
+    ];
+    let body = events
+        .iter()
+        .map(|event| {
+            let kind = event["type"].as_str().unwrap();
+            format!("event: {kind}\ndata: {event}\n\n")
+        })
+        .collect::<String>();
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(body),
+        )
+        .with_priority(1)
+        .expect(1)
+        .mount(&harness.mock.anthropic_mock.http_server)
+        .await;
+
+    let response = harness.call(chat_request(true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let chunks = String::from_utf8_lossy(&body)
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter_map(|data| serde_json::from_str::<Value>(data).ok())
+        .collect::<Vec<_>>();
+    let deltas = chunks
+        .iter()
+        .map(|chunk| &chunk["choices"][0]["delta"])
+        .collect::<Vec<_>>();
+
+    let reasoning = deltas
+        .iter()
+        .filter_map(|delta| delta["reasoning_content"].as_str())
+        .collect::<String>();
+    assert_eq!(reasoning, "Let me think");
+    let content = deltas
+        .iter()
+        .filter_map(|delta| delta["content"].as_str())
+        .collect::<String>();
+    assert_eq!(content, "4");
+    // the text follows a thinking block but is still the first choice
+    assert!(chunks.iter().all(|chunk| chunk["choices"][0]["index"] == 0));
+}
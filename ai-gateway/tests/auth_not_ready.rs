@@ -0,0 +1,102 @@
+use std::{collections::HashMap, time::Duration};
+
+use ai_gateway::{
+    config::{Config, helicone::HeliconeFeatures},
+    control_plane::types::{ControlPlaneState, Key, hash_key},
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::org::OrgId,
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+use uuid::Uuid;
+
+const API_KEY: &str = "sk-helicone-test-key";
+
+fn test_config(wait: Duration) -> Config {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::Auth;
+    config.control_plane.auth_not_ready.wait = wait;
+    config.control_plane.auth_not_ready.retry_after =
+        Duration::from_millis(1500);
+    config
+}
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    let body = serde_json::to_vec(&json!({
+        "model": "gpt-4o-mini",
+        "messages": [{ "role": "user", "content": "Hello, world!" }]
+    }))
+    .unwrap();
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/openai/v1/chat/completions")
+        .header("authorization", format!("Bearer {API_KEY}"))
+        .body(axum_core::body::Body::from(body))
+        .unwrap()
+}
+
+fn synced_state() -> ControlPlaneState {
+    let mut state = ControlPlaneState::test_default();
+    state.keys = vec![Key {
+        key_hash: hash_key(API_KEY),
+        owner_id: Uuid::new_v4().into(),
+        organization_id: OrgId::new(Uuid::new_v4()),
+    }];
+    state
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn requests_before_sync_are_rejected_with_retry_after() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([(
+            "success:openai:chat_completion",
+            0.into(),
+        )]))
+        .build();
+    // no control plane state, as if the sync hasn't completed yet
+    let mut harness = Harness::builder()
+        .with_config(test_config(Duration::ZERO))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers().get("retry-after").unwrap(), "2");
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["code"], "auth_data_not_ready");
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn requests_before_sync_wait_for_auth_data() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([(
+            "success:openai:chat_completion",
+            1.into(),
+        )]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config(Duration::from_secs(5)))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let control_plane_state =
+        harness.app_factory.state.0.control_plane_state.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        control_plane_state.write().await.state = Some(synced_state());
+    });
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    harness.mock.verify().await;
+}
@@ -0,0 +1,160 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{model_id::ModelId, provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use stubr::wiremock_rs::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{header, method, path, query_param},
+};
+use tower::Service;
+use url::Url;
+
+/// Builds a harness whose Azure `OpenAI` resource is served by `server`,
+/// with `gpt-4o` deployed as `my-gpt-4o`.
+async fn harness(server: &MockServer) -> Harness {
+    // SAFETY: This must only be called within the single threaded tokio
+    // runtime in tests
+    unsafe {
+        std::env::set_var("AZURE_API_KEY", "azure-test-key");
+    }
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing provider requests
+    config.helicone.features = HeliconeFeatures::None;
+    let azure = config
+        .providers
+        .get_mut(&InferenceProvider::Named("azure".into()))
+        .unwrap();
+    azure.base_url = Url::parse(&server.uri()).unwrap();
+    azure.version = Some("2024-10-21".to_string());
+    azure.deployments.insert(
+        ModelId::from_str("azure/gpt-4o").unwrap(),
+        "my-gpt-4o".to_string(),
+    );
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::azure(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    // SAFETY: see above
+    unsafe {
+        std::env::remove_var("AZURE_API_KEY");
+    }
+    harness
+}
+
+fn chat_completion() -> Value {
+    json!({
+        "id": "chatcmpl-azure",
+        "object": "chat.completion",
+        "created": 1_748_543_700,
+        "model": "gpt-4o-2024-11-20",
+        "choices": [{
+            "index": 0,
+            "finish_reason": "stop",
+            "message": { "role": "assistant", "content": "Hello!" }
+        }],
+        "usage": {
+            "prompt_tokens": 6,
+            "completion_tokens": 2,
+            "total_tokens": 8
+        }
+    })
+}
+
+fn chat_request(model: &str) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn requests_are_sent_to_the_models_deployment() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/my-gpt-4o/chat/completions"))
+        .and(query_param("api-version", "2024-10-21"))
+        .and(header("api-key", "azure-test-key"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(chat_completion()),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut harness = harness(&server).await;
+
+    let response = harness.call(chat_request("azure/gpt-4o")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["choices"][0]["message"]["content"], "Hello!");
+
+    // the body still names the model, only the url names the deployment
+    let received = server.received_requests().await.unwrap();
+    let sent: Value = serde_json::from_slice(&received[0].body).unwrap();
+    assert_eq!(sent["model"], "gpt-4o");
+    assert!(!received[0].headers.contains_key("authorization"));
+    server.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn models_without_deployment_use_their_name() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/gpt-4o-mini/chat/completions"))
+        .and(query_param("api-version", "2024-10-21"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(chat_completion()),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut harness = harness(&server).await;
+
+    let response = harness
+        .call(chat_request("azure/gpt-4o-mini"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    server.verify().await;
+}
@@ -1,12 +1,20 @@
 use std::{collections::HashMap, time::Duration};
 
 use ai_gateway::{
-    config::{Config, cache::CacheConfig, helicone::HeliconeFeatures},
+    config::{
+        Config,
+        cache::{CacheConfig, ErrorResponseCacheConfig},
+        helicone::HeliconeFeatures,
+    },
     tests::{TestDefault, harness::Harness, mock::MockArgs},
 };
 use http::{Method, Request, StatusCode};
 use http_body_util::BodyExt;
 use serde_json::json;
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
 use tower::Service;
 
 /// Helper function to make a POST request to the specified URL
@@ -40,6 +48,29 @@ fn make_request(
         .unwrap()
 }
 
+/// Helper function to make a streaming POST request to the specified URL
+fn make_stream_request(url: &str) -> Request<axum_core::body::Body> {
+    let request_body = serde_json::to_vec(&json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ],
+        "stream": true
+    }))
+    .unwrap();
+
+    Request::builder()
+        .method(Method::POST)
+        .uri(url)
+        .header("content-type", "application/json")
+        .header("cache-control", "max-age=3600")
+        .body(axum_core::body::Body::from(request_body))
+        .unwrap()
+}
+
 /// Test that requests are cached when enabled globally via config.
 /// This should check that requests on any of the three possible URLs
 /// (`/ai/chat/completions`, `/openai/v1/chat/completions`,
@@ -314,6 +345,11 @@ async fn cache_enabled_per_router() {
                     directive: None,
                     buckets: 1,
                     seed: Some("router-cached-seed".to_string()),
+                    cache_streams: false,
+                    share_across_tenants: false,
+                    tenant_header: None,
+                    semantic: None,
+                    error_responses: None,
                 }),
                 load_balance:
                     ai_gateway::config::balance::BalanceConfig::openai_chat(),
@@ -432,3 +468,463 @@ async fn cache_enabled_per_router() {
          default router"
     );
 }
+
+/// Test that streaming requests bypass the cache by default, even when
+/// caching is enabled.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn streaming_requests_bypass_cache_by_default() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.global.cache = Some(CacheConfig::test_default());
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            // every request should hit the backend
+            ("success:openai:chat_completion_stream", 2.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    for _ in 0..2 {
+        let request = make_stream_request(
+            "http://router.helicone.com/router/my-router/chat/completions",
+        );
+        let response = harness.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(
+            response.headers().get("helicone-cache").is_none(),
+            "Streaming requests should bypass the cache"
+        );
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+}
+
+/// Test that streaming responses are cached when `cache-streams` is enabled.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn streaming_requests_cached_when_enabled() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.global.cache = Some(CacheConfig {
+        cache_streams: true,
+        ..CacheConfig::test_default()
+    });
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion_stream", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request = make_stream_request(
+        "http://router.helicone.com/router/my-router/chat/completions",
+    );
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("helicone-cache").unwrap(),
+        "MISS",
+        "First streaming request should be a cache miss"
+    );
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    let request = make_stream_request(
+        "http://router.helicone.com/router/my-router/chat/completions",
+    );
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("helicone-cache").unwrap(),
+        "HIT",
+        "Second streaming request should be a cache hit"
+    );
+    let _response_body = response.into_body().collect().await.unwrap();
+}
+
+/// Makes an identical cacheable request as each of the two organizations,
+/// then again as the first, returning the `helicone-cache` header of each
+/// response.
+async fn cache_statuses_for_two_orgs(
+    share_across_tenants: bool,
+    expected_provider_calls: u64,
+) -> Vec<String> {
+    use ai_gateway::{
+        control_plane::types::{Key, hash_key},
+        types::org::OrgId,
+    };
+    use uuid::Uuid;
+
+    let org1_auth = "sk-helicone-org1-key";
+    let org2_auth = "sk-helicone-org2-key";
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::All;
+    config.global.cache = Some(CacheConfig {
+        share_across_tenants,
+        ..CacheConfig::test_default()
+    });
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            (
+                "success:openai:chat_completion_cacheable",
+                expected_provider_calls.into(),
+            ),
+            ("success:minio:upload_request", (0..).into()),
+            ("success:jawn:sign_s3_url", (0..).into()),
+            ("success:jawn:log_request", (0..).into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .with_auth_keys(vec![
+            Key {
+                key_hash: hash_key(org1_auth),
+                owner_id: Uuid::new_v4().into(),
+                organization_id: OrgId::new(Uuid::new_v4()),
+            },
+            Key {
+                key_hash: hash_key(org2_auth),
+                owner_id: Uuid::new_v4().into(),
+                organization_id: OrgId::new(Uuid::new_v4()),
+            },
+        ])
+        .build()
+        .await;
+
+    let mut statuses = Vec::new();
+    for auth in [org1_auth, org2_auth, org1_auth] {
+        let mut request = make_request(
+            "http://router.helicone.com/router/my-router/chat/completions",
+            Some(("cache-control", "max-age=3600")),
+        );
+        request
+            .headers_mut()
+            .insert("authorization", format!("Bearer {auth}").parse().unwrap());
+        let response = harness.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        statuses.push(
+            response.headers()["helicone-cache"]
+                .to_str()
+                .unwrap()
+                .to_string(),
+        );
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+    harness.mock.verify().await;
+    statuses
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn cache_entries_isolated_per_org_by_default() {
+    let statuses = cache_statuses_for_two_orgs(false, 2).await;
+    assert_eq!(statuses, ["MISS", "MISS", "HIT"]);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn cache_entries_shared_across_orgs_when_configured() {
+    let statuses = cache_statuses_for_two_orgs(true, 1).await;
+    assert_eq!(statuses, ["MISS", "HIT", "HIT"]);
+}
+
+async fn ttl_harness(chat_completions: u64) -> Harness {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.global.cache = Some(CacheConfig::test_default());
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            (
+                "success:openai:chat_completion_cacheable",
+                chat_completions.into(),
+            ),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+/// Sends a request with the given headers and returns its status and
+/// `helicone-cache` header.
+async fn call_with_headers(
+    harness: &mut Harness,
+    headers: &[(&'static str, &'static str)],
+) -> (StatusCode, Option<String>) {
+    let mut request = make_request(
+        "http://router.helicone.com/router/my-router/chat/completions",
+        Some(("cache-control", "max-age=3600")),
+    );
+    for (name, value) in headers {
+        request
+            .headers_mut()
+            .insert(*name, http::HeaderValue::from_static(value));
+    }
+    let response = harness.call(request).await.unwrap();
+    let status = response.status();
+    let cache_status = response
+        .headers()
+        .get("helicone-cache")
+        .map(|value| value.to_str().unwrap().to_string());
+    let _body = response.into_body().collect().await.unwrap();
+    (status, cache_status)
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn cache_ttl_header_overrides_configured_ttl() {
+    // the cached response expires after a second, so the last request
+    // reaches the provider again
+    let mut harness = ttl_harness(2).await;
+    let ttl = [("helicone-cache-ttl", "1")];
+
+    let (status, cache_status) = call_with_headers(&mut harness, &ttl).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(cache_status.as_deref(), Some("MISS"));
+
+    let (_, cache_status) = call_with_headers(&mut harness, &ttl).await;
+    assert_eq!(cache_status.as_deref(), Some("HIT"));
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let (_, cache_status) = call_with_headers(&mut harness, &ttl).await;
+    assert_eq!(cache_status.as_deref(), Some("MISS"));
+
+    harness.mock.verify().await;
+}
+
+fn cacheable_completion(content: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200)
+        .insert_header("cache-control", "max-age=3600")
+        .set_body_json(json!({
+            "id": "chatcmpl-B9MBs8CjcvOU2jLn4n570S5qMJKcT",
+            "object": "chat.completion",
+            "created": 1_741_569_952,
+            "model": "gpt-4o-mini",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "prompt_tokens": 19,
+                "completion_tokens": 10,
+                "total_tokens": 29
+            }
+        }))
+}
+
+/// Sends a request with the given headers and returns its `helicone-cache`
+/// header and the content of its completion.
+async fn call_for_content(
+    harness: &mut Harness,
+    headers: &[(&'static str, &'static str)],
+) -> (Option<String>, String) {
+    let mut request = make_request(
+        "http://router.helicone.com/router/my-router/chat/completions",
+        Some(("cache-control", "max-age=3600")),
+    );
+    for (name, value) in headers {
+        request
+            .headers_mut()
+            .insert(*name, http::HeaderValue::from_static(value));
+    }
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cache_status = response
+        .headers()
+        .get("helicone-cache")
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let content = body["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    (cache_status, content)
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn cache_bypass_header_refreshes_entry() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.global.cache = Some(CacheConfig {
+        buckets: 2,
+        ..CacheConfig::test_default()
+    });
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion_cacheable", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(cacheable_completion("stale"))
+        .with_priority(1)
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&harness.mock.openai_mock.http_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(cacheable_completion("refreshed"))
+        .with_priority(2)
+        .expect(1)
+        .mount(&harness.mock.openai_mock.http_server)
+        .await;
+
+    let (cache_status, content) = call_for_content(&mut harness, &[]).await;
+    assert_eq!(cache_status.as_deref(), Some("MISS"));
+    assert_eq!(content, "stale");
+
+    let (cache_status, content) =
+        call_for_content(&mut harness, &[("helicone-cache-bypass", "true")])
+            .await;
+    assert_eq!(cache_status.as_deref(), Some("MISS"));
+    assert_eq!(content, "refreshed");
+
+    // the stale entry is evicted from every bucket, so only the refreshed
+    // entry is served to later requests
+    for _ in 0..8 {
+        let (cache_status, content) = call_for_content(&mut harness, &[]).await;
+        assert_eq!(cache_status.as_deref(), Some("HIT"));
+        assert_eq!(content, "refreshed");
+    }
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn invalid_cache_ttl_header_is_rejected() {
+    let mut harness = ttl_harness(0).await;
+    for ttl in ["0", "-5", "1.5", "an hour"] {
+        let (status, _) =
+            call_with_headers(&mut harness, &[("helicone-cache-ttl", ttl)])
+                .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "ttl: {ttl}");
+    }
+    harness.mock.verify().await;
+}
+
+async fn error_cache_harness() -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing error caching
+    config.helicone.features = HeliconeFeatures::None;
+    config.global.cache = Some(CacheConfig {
+        error_responses: Some(ErrorResponseCacheConfig {
+            status_codes: vec![400],
+            ttl: Duration::from_secs(30),
+        }),
+        ..CacheConfig::test_default()
+    });
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+/// Test that a client error with a status code configured as cacheable is
+/// served from the cache, with its original status, after the first request.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn configured_client_error_is_cached() {
+    let mut harness = error_cache_harness().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": {
+                "message": "Invalid value for 'temperature'",
+                "type": "invalid_request_error",
+                "param": "temperature",
+                "code": null
+            }
+        })))
+        .with_priority(1)
+        .expect(1)
+        .mount(&harness.mock.openai_mock.http_server)
+        .await;
+
+    let url = "http://router.helicone.com/router/my-router/chat/completions";
+    let response = harness.call(make_request(url, None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.headers().get("helicone-cache").unwrap(), "MISS");
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    let response = harness.call(make_request(url, None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.headers().get("helicone-cache").unwrap(), "HIT");
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["param"], "temperature");
+}
+
+/// Test that server errors are never cached, even with error caching
+/// enabled.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn server_error_is_not_cached() {
+    let mut harness = error_cache_harness().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(500).set_body_json(json!({
+            "error": {
+                "message": "Internal server error",
+                "type": "internal_server_error",
+                "param": null,
+                "code": null
+            }
+        })))
+        .with_priority(1)
+        .expect(2)
+        .mount(&harness.mock.openai_mock.http_server)
+        .await;
+
+    let url = "http://router.helicone.com/router/my-router/chat/completions";
+    for _ in 0..2 {
+        let response = harness.call(make_request(url, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_ne!(
+            response
+                .headers()
+                .get("helicone-cache")
+                .map(|v| v.as_bytes()),
+            Some(b"HIT".as_slice())
+        );
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::{BalanceConfig, BalanceConfigInner, ComplexityThresholds},
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    endpoints::EndpointType,
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{model_id::ModelId, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use serde_json::{Value, json};
+use tower::Service;
+
+const SIMPLE_MODEL: &str = "openai/gpt-4o-mini";
+const COMPLEX_MODEL: &str = "anthropic/claude-3-haiku-20240307";
+
+async fn harness(openai_calls: u64, anthropic_calls: u64) -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're not testing authentication
+    config.helicone.features = HeliconeFeatures::None;
+    let balance_config = BalanceConfig::from(HashMap::from([(
+        EndpointType::Chat,
+        BalanceConfigInner::Complexity {
+            simple: ModelId::from_str(SIMPLE_MODEL).unwrap(),
+            complex: ModelId::from_str(COMPLEX_MODEL).unwrap(),
+            thresholds: ComplexityThresholds {
+                max_prompt_chars: 100,
+                ..Default::default()
+            },
+        },
+    )]));
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: balance_config,
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", openai_calls.into()),
+            ("success:anthropic:messages", anthropic_calls.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+fn chat_request(body: Value) -> Request<axum_core::body::Body> {
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(axum_core::body::Body::from(
+            serde_json::to_vec(&body).unwrap(),
+        ))
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn short_prompt_routes_to_simple_model() {
+    let mut harness = harness(1, 0).await;
+    let request = chat_request(json!({
+        "model": SIMPLE_MODEL,
+        "messages": [{ "role": "user", "content": "Hello, world!" }]
+    }));
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn long_prompt_routes_to_complex_model() {
+    let mut harness = harness(0, 1).await;
+    let request = chat_request(json!({
+        "model": SIMPLE_MODEL,
+        "messages": [{ "role": "user", "content": "Hello, world! ".repeat(10) }]
+    }));
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn tool_using_prompt_routes_to_complex_model() {
+    let mut harness = harness(0, 1).await;
+    let request = chat_request(json!({
+        "model": SIMPLE_MODEL,
+        "messages": [{ "role": "user", "content": "What's the weather?" }],
+        "tools": [{
+            "type": "function",
+            "function": {
+                "name": "get_weather",
+                "parameters": { "type": "object", "properties": {} }
+            }
+        }]
+    }));
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    harness.mock.verify().await;
+}
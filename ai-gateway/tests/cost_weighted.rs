@@ -0,0 +1,113 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::{BalanceConfig, BalanceConfigInner},
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    endpoints::EndpointType,
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{model_id::ModelId, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use nonempty_collections::NEMap;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tower::Service;
+
+const OPENAI_MODEL: &str = "openai/gpt-4o-mini";
+const ANTHROPIC_MODEL: &str = "anthropic/claude-3-haiku-20240307";
+
+fn config() -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're not testing authentication
+    config.helicone.features = HeliconeFeatures::None;
+    let mut models = NEMap::new(
+        ModelId::from_str(OPENAI_MODEL).unwrap(),
+        Decimal::new(60, 2),
+    );
+    models.insert(
+        ModelId::from_str(ANTHROPIC_MODEL).unwrap(),
+        Decimal::new(25, 2),
+    );
+    let balance_config = BalanceConfig::from(HashMap::from([(
+        EndpointType::Chat,
+        BalanceConfigInner::CostWeighted { models },
+    )]));
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: balance_config,
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn chat_request(cost_priority: Option<&str>) -> Request<axum_core::body::Body> {
+    let body = serde_json::to_vec(&json!({
+        "model": OPENAI_MODEL,
+        "messages": [{ "role": "user", "content": "Hello, world!" }]
+    }))
+    .unwrap();
+    let mut builder = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions");
+    if let Some(cost_priority) = cost_priority {
+        builder = builder.header("helicone-cost-priority", cost_priority);
+    }
+    builder.body(axum_core::body::Body::from(body)).unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn cheapest_healthy_provider_is_chosen() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:anthropic:messages", 3.into()),
+            // When auth is disabled, logging services should not be called
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    for _ in 0..3 {
+        let response = harness.call(chat_request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn cost_priority_header_overrides_configured_prices() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request = chat_request(Some("openai/gpt-4o-mini=0.1"));
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    harness.mock.verify().await;
+}
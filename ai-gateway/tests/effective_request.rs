@@ -0,0 +1,123 @@
+use std::{collections::HashMap, fmt::Write};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tower::Service;
+
+fn test_config(effective_request: bool) -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing response headers
+    config.helicone.features = HeliconeFeatures::None;
+    config.response_headers.effective_request = effective_request;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            system_prompt_prefix: Some("Be concise.".to_string()),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    let body = json!({
+        "model": "anthropic/claude-3-5-sonnet-latest",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ]
+    });
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(axum_core::body::Body::from(
+            serde_json::to_vec(&body).unwrap(),
+        ))
+        .unwrap()
+}
+
+fn mock_args() -> MockArgs {
+    MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn effective_request_summarizes_transformed_body() {
+    let mut harness = Harness::builder()
+        .with_config(test_config(true))
+        .with_mock_args(mock_args())
+        .build()
+        .await;
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let summary = response
+        .headers()
+        .get("helicone-effective-request")
+        .expect("effective request header is enabled")
+        .to_str()
+        .unwrap()
+        .to_string();
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    let sent = harness
+        .mock
+        .anthropic_mock
+        .http_server
+        .received_requests()
+        .await
+        .expect("request recording is enabled");
+    let sent = &sent[0].body;
+    // the body was mapped to the Anthropic format and prefixed with the
+    // router's system prompt before it was sent
+    let sent_json: serde_json::Value = serde_json::from_slice(sent).unwrap();
+    assert!(sent_json["system"].to_string().contains("Be concise."));
+    let mut sha256 = String::new();
+    for byte in Sha256::digest(sent) {
+        write!(sha256, "{byte:02x}").unwrap();
+    }
+    assert_eq!(summary, format!("sha256={sha256}; bytes={}", sent.len()));
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn effective_request_is_disabled_by_default() {
+    let mut harness = Harness::builder()
+        .with_config(test_config(false))
+        .with_mock_args(mock_args())
+        .build()
+        .await;
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        !response
+            .headers()
+            .contains_key("helicone-effective-request")
+    );
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    harness.mock.verify().await;
+}
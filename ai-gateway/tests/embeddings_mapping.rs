@@ -0,0 +1,223 @@
+use ai_gateway::{
+    app::App,
+    config::{Config, helicone::HeliconeFeatures},
+    endpoints::{ApiEndpoint, openai::OpenAI},
+    middleware::mapper::{
+        model::ModelMapper, registry::EndpointConverterRegistry,
+    },
+    tests::TestDefault,
+    types::provider::InferenceProvider,
+};
+use bytes::Bytes;
+use http::StatusCode;
+use serde_json::{Value, json};
+
+/// Maps an `OpenAI` embeddings request with the given body to the given
+/// provider, returning the mapped request body and whether the client asked
+/// for base64 embeddings, along with the provider's `response` mapped back to
+/// an `OpenAI` response.
+async fn map_embeddings(
+    provider: &str,
+    body: Value,
+    response: Value,
+) -> (Value, bool, Value) {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    let app = App::new(config).await.expect("failed to create app");
+    let model_mapper = ModelMapper::new(app.state.clone());
+    let registry = EndpointConverterRegistry::new(&model_mapper);
+
+    let source_endpoint = ApiEndpoint::OpenAI(OpenAI::embeddings());
+    let target_endpoint = ApiEndpoint::mapped(
+        source_endpoint.clone(),
+        &InferenceProvider::Named(provider.into()),
+    )
+    .unwrap();
+    let converter = registry
+        .get_converter(&source_endpoint, &target_endpoint)
+        .expect("converter is registered");
+    let body = Bytes::from(serde_json::to_vec(&body).unwrap());
+    let (mapped, mapper_ctx) = converter.convert_req_body(body).unwrap();
+
+    let (parts, ()) = http::Response::builder()
+        .status(StatusCode::OK)
+        .body(())
+        .unwrap()
+        .into_parts();
+    let response = Bytes::from(serde_json::to_vec(&response).unwrap());
+    let response = converter
+        .convert_resp_body(parts, response, false)
+        .unwrap()
+        .expect("response is mapped");
+    (
+        serde_json::from_slice(&mapped).unwrap(),
+        mapper_ctx.base64_embeddings,
+        serde_json::from_slice(&response).unwrap(),
+    )
+}
+
+fn embedding(index: u16) -> Value {
+    json!([f32::from(index), f32::from(index) + 0.5])
+}
+
+fn cohere_response(count: u16) -> Value {
+    let embeddings = (0..count).map(embedding).collect::<Vec<_>>();
+    json!({
+        "id": "5807ee2e-0cda-445a-9ec8-864c60a06606",
+        "embeddings": embeddings,
+        "texts": [],
+        "meta": { "billed_units": { "input_tokens": 7 } },
+        "response_type": "embeddings_floats"
+    })
+}
+
+fn voyage_response(count: u16) -> Value {
+    let data = (0..count)
+        .map(|i| {
+            json!({
+                "object": "embedding",
+                "embedding": embedding(i),
+                "index": i
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "object": "list",
+        "data": data,
+        "model": "voyage-3.5-lite",
+        "usage": { "total_tokens": 7 }
+    })
+}
+
+fn assert_openai_embeddings(response: &Value, count: u16) {
+    assert_eq!(response["object"], "list");
+    let data = response["data"].as_array().unwrap();
+    assert_eq!(data.len(), usize::from(count));
+    for (i, item) in (0..count).zip(data) {
+        assert_eq!(item["object"], "embedding");
+        assert_eq!(item["index"], i);
+        assert_eq!(item["embedding"], embedding(i));
+    }
+    assert_eq!(response["usage"]["prompt_tokens"], 7);
+    assert_eq!(response["usage"]["total_tokens"], 7);
+}
+
+#[tokio::test]
+async fn cohere_single_input_is_sent_as_texts() {
+    let body = json!({
+        "model": "openai/text-embedding-3-small",
+        "input": "Hello, world!"
+    });
+    let (mapped, base64, response) =
+        map_embeddings("cohere", body, cohere_response(1)).await;
+    assert_eq!(
+        mapped,
+        json!({
+            "model": "embed-english-light-v3.0",
+            "texts": ["Hello, world!"],
+            "input_type": "search_document"
+        })
+    );
+    assert!(!base64);
+    assert_openai_embeddings(&response, 1);
+}
+
+#[tokio::test]
+async fn cohere_batch_input_is_sent_as_texts() {
+    let body = json!({
+        "model": "openai/text-embedding-3-large",
+        "input": ["Hello", "world"],
+        "encoding_format": "base64"
+    });
+    let (mapped, base64, response) =
+        map_embeddings("cohere", body, cohere_response(2)).await;
+    assert_eq!(mapped["model"], "embed-english-v3.0");
+    assert_eq!(mapped["texts"], json!(["Hello", "world"]));
+    // floats are requested from cohere and encoded for the client
+    assert!(mapped.get("encoding_format").is_none());
+    assert!(base64);
+    assert_openai_embeddings(&response, 2);
+}
+
+#[tokio::test]
+async fn voyage_single_input_is_sent_as_array() {
+    let body = json!({
+        "model": "openai/text-embedding-3-small",
+        "input": "Hello, world!",
+        "dimensions": 512
+    });
+    let (mapped, base64, response) =
+        map_embeddings("voyage", body, voyage_response(1)).await;
+    assert_eq!(
+        mapped,
+        json!({
+            "model": "voyage-3.5-lite",
+            "input": ["Hello, world!"],
+            "output_dimension": 512
+        })
+    );
+    assert!(!base64);
+    assert_openai_embeddings(&response, 1);
+    assert_eq!(response["model"], "voyage-3.5-lite");
+}
+
+#[tokio::test]
+async fn voyage_batch_input_is_sent_as_array() {
+    let body = json!({
+        "model": "openai/text-embedding-3-large",
+        "input": ["Hello", "world", "!"],
+        "encoding_format": "base64"
+    });
+    let (mapped, base64, response) =
+        map_embeddings("voyage", body, voyage_response(3)).await;
+    assert_eq!(mapped["model"], "voyage-3.5");
+    assert_eq!(mapped["input"], json!(["Hello", "world", "!"]));
+    assert!(mapped.get("encoding_format").is_none());
+    assert!(base64);
+    assert_openai_embeddings(&response, 3);
+}
+
+#[tokio::test]
+async fn token_inputs_are_not_mapped() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    let app = App::new(config).await.expect("failed to create app");
+    let model_mapper = ModelMapper::new(app.state.clone());
+    let registry = EndpointConverterRegistry::new(&model_mapper);
+
+    let source_endpoint = ApiEndpoint::OpenAI(OpenAI::embeddings());
+    for provider in ["cohere", "voyage"] {
+        let target_endpoint = ApiEndpoint::mapped(
+            source_endpoint.clone(),
+            &InferenceProvider::Named(provider.into()),
+        )
+        .unwrap();
+        let converter = registry
+            .get_converter(&source_endpoint, &target_endpoint)
+            .expect("converter is registered");
+        let body = json!({
+            "model": "openai/text-embedding-3-small",
+            "input": [[15339, 11, 1917]]
+        });
+        let body = Bytes::from(serde_json::to_vec(&body).unwrap());
+        assert!(converter.convert_req_body(body).is_err(), "{provider}");
+    }
+}
+
+#[test]
+fn embeddings_are_not_mapped_to_chat_providers() {
+    let source_endpoint = ApiEndpoint::OpenAI(OpenAI::embeddings());
+    for provider in [InferenceProvider::Anthropic, InferenceProvider::Bedrock] {
+        assert!(
+            ApiEndpoint::mapped(source_endpoint.clone(), &provider).is_err()
+        );
+    }
+    assert_eq!(
+        ApiEndpoint::mapped(
+            source_endpoint.clone(),
+            &InferenceProvider::OpenAI
+        )
+        .unwrap(),
+        source_endpoint
+    );
+}
@@ -0,0 +1,166 @@
+use std::{collections::HashMap, num::NonZeroUsize};
+
+use ai_gateway::{
+    config::{
+        Config,
+        helicone::HeliconeFeatures,
+        providers::{EmbeddingsConfig, PartialFailure},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::provider::InferenceProvider,
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use tower::Service;
+
+fn test_config(on_partial_failure: PartialFailure) -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing request splitting
+    config.helicone.features = HeliconeFeatures::None;
+    config
+        .providers
+        .get_mut(&InferenceProvider::OpenAI)
+        .unwrap()
+        .embeddings = EmbeddingsConfig {
+        max_inputs: NonZeroUsize::new(2),
+        on_partial_failure,
+    };
+    config
+}
+
+fn embeddings_request(inputs: &[&str]) -> Request<axum_core::body::Body> {
+    let body = serde_json::to_vec(&json!({
+        "model": "text-embedding-3-small",
+        "input": inputs,
+    }))
+    .unwrap();
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/openai/v1/embeddings")
+        .header("content-type", "application/json")
+        .body(axum_core::body::Body::from(body))
+        .unwrap()
+}
+
+fn indexed_embeddings(body: &Value) -> Vec<(u64, Value)> {
+    body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|embedding| {
+            (
+                embedding["index"].as_u64().unwrap(),
+                embedding["embedding"].clone(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn over_limit_inputs_are_split_and_reassembled_in_order() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:embeddings_first", 1.into()),
+            ("success:openai:embeddings_second", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config(PartialFailure::Fail))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request = embeddings_request(&["one", "two", "three", "four"]);
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response
+            .headers()
+            .get("helicone-partial-response")
+            .is_none()
+    );
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        indexed_embeddings(&body),
+        vec![
+            (0, json!([0.1])),
+            (1, json!([0.2])),
+            (2, json!([0.3])),
+            (3, json!([0.4])),
+        ]
+    );
+    assert_eq!(body["usage"]["prompt_tokens"], 4);
+    assert_eq!(body["object"], "list");
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn partial_failure_fails_whole_request() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:embeddings_first", 1.into()),
+            ("success:openai:embeddings_second", 1.into()),
+            ("internal_error:openai:embeddings", (1..).into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config(PartialFailure::Fail))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request = embeddings_request(&["one", "two", "three", "four", "five"]);
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn partial_failure_returns_successful_embeddings() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:embeddings_first", 1.into()),
+            ("success:openai:embeddings_second", 1.into()),
+            ("internal_error:openai:embeddings", (1..).into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config(PartialFailure::Partial))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request = embeddings_request(&["one", "two", "three", "four", "five"]);
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("helicone-partial-response").unwrap(),
+        "true"
+    );
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        indexed_embeddings(&body),
+        vec![
+            (0, json!([0.1])),
+            (1, json!([0.2])),
+            (2, json!([0.3])),
+            (3, json!([0.4])),
+        ]
+    );
+
+    harness.mock.verify().await;
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use serde_json::json;
+use tower::Service;
+
+fn config(
+    load_balance: BalanceConfig,
+    allow_system_only_messages: bool,
+) -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing request validation
+    config.helicone.features = HeliconeFeatures::None;
+    config.mapper.allow_system_only_messages = allow_system_only_messages;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance,
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn chat_request(
+    model: &str,
+    messages: serde_json::Value,
+) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "messages": messages,
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn empty_messages_are_rejected() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(BalanceConfig::openai_chat(), true))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request = chat_request("openai/gpt-4o-mini", json!([]));
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn system_only_messages_rejected_by_default() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(BalanceConfig::openai_chat(), false))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request = chat_request(
+        "openai/gpt-4o-mini",
+        json!([{ "role": "system", "content": "You are a helpful assistant." }]),
+    );
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn system_only_messages_allowed_for_openai() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(BalanceConfig::openai_chat(), true))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request = chat_request(
+        "openai/gpt-4o-mini",
+        json!([{ "role": "system", "content": "You are a helpful assistant." }]),
+    );
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn system_only_messages_rejected_for_anthropic() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(BalanceConfig::anthropic_chat(), true))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request = chat_request(
+        "anthropic/claude-3-5-sonnet-latest",
+        json!([{ "role": "system", "content": "You are a helpful assistant." }]),
+    );
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
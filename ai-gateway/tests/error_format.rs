@@ -1,11 +1,19 @@
 use std::collections::HashMap;
 
 use ai_gateway::{
-    config::{Config, helicone::HeliconeFeatures},
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
     tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
 };
+use compact_str::CompactString;
 use http::{Method, Request, StatusCode};
 use http_body_util::BodyExt;
+use serde_json::json;
 use tower::Service;
 
 #[tokio::test]
@@ -98,3 +106,84 @@ async fn invalid_request_body() {
     );
     assert_eq!(response_body.error.code, None);
 }
+
+/// Sends a chat completion to a router balanced to Anthropic, which responds
+/// with an invalid request error.
+async fn anthropic_error(error_format: Option<&str>) -> serde_json::Value {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            ..Default::default()
+        },
+    )]));
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("invalid_request:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let body = serde_json::to_vec(&json!({
+        "model": "anthropic/claude-3-5-sonnet-latest",
+        "messages": [{ "role": "user", "content": "Hello, world!" }]
+    }))
+    .unwrap();
+    let mut builder = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions");
+    if let Some(error_format) = error_format {
+        builder = builder.header("x-helicone-error-format", error_format);
+    }
+    let request = builder.body(axum_core::body::Body::from(body)).unwrap();
+
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    harness.mock.verify().await;
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn provider_errors_are_mapped_to_openai_format() {
+    for error_format in [None, Some("openai")] {
+        let body = anthropic_error(error_format).await;
+        let error =
+            serde_json::from_value::<async_openai::error::WrappedError>(body)
+                .expect("error should be in openai format");
+        assert_eq!(
+            error.error.r#type,
+            Some("invalid_request_error".to_string())
+        );
+        assert_eq!(
+            error.error.message,
+            "messages.0.content: Input should be a valid list"
+        );
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn provider_errors_are_returned_raw_on_request() {
+    let body = anthropic_error(Some("raw")).await;
+    assert_eq!(
+        body,
+        json!({
+            "type": "error",
+            "error": {
+                "type": "invalid_request_error",
+                "message": "messages.0.content: Input should be a valid list",
+            },
+        })
+    );
+}
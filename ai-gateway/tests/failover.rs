@@ -0,0 +1,254 @@
+use std::{collections::HashMap, num::NonZeroU32};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        failover::FailoverConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+fn config(error_types: Vec<&str>) -> Config {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            failover: Some(FailoverConfig {
+                providers: vec![InferenceProvider::OpenAI],
+                status_codes: Vec::new(),
+                error_types: HashMap::from([(
+                    InferenceProvider::Anthropic,
+                    error_types.into_iter().map(String::from).collect(),
+                )]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+/// Falls back from Anthropic to `OpenAI` on any server error.
+fn fallback_config(max_attempts: Option<u32>) -> Config {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            failover: Some(FailoverConfig {
+                providers: vec![InferenceProvider::OpenAI],
+                server_errors: true,
+                max_attempts: max_attempts.and_then(NonZeroU32::new),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    request(false)
+}
+
+fn request(stream: bool) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "anthropic/claude-3-5-sonnet-latest",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ],
+            "stream": stream
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn overloaded_error_fails_over_to_next_provider() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("overloaded:anthropic:messages", 1.into()),
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(vec!["overloaded_error"]))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn other_error_types_do_not_fail_over() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("overloaded:anthropic:messages", 1.into()),
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(vec!["rate_limit_error"]))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status().as_u16(), 529);
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn server_error_falls_back_to_next_provider() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("unavailable:anthropic:messages", 1.into()),
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(fallback_config(None))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("helicone-fallback-used").unwrap(),
+        "anthropic,openai"
+    );
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn server_error_falls_back_before_stream_starts() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("unavailable:anthropic:messages", 1.into()),
+            ("success:openai:chat_completion_stream", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(fallback_config(None))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(request(true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("helicone-fallback-used").unwrap(),
+        "anthropic,openai"
+    );
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(String::from_utf8_lossy(&body).contains("Hello!"));
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn fallback_stops_at_max_attempts() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("unavailable:anthropic:messages", 1.into()),
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(fallback_config(Some(1)))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().get("helicone-fallback-used").is_none());
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn oversized_request_is_rejected_before_dispatch() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            failover: Some(FailoverConfig {
+                providers: vec![InferenceProvider::OpenAI],
+                server_errors: true,
+                ..Default::default()
+            }),
+            max_request_body_bytes: Some(16),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 0.into()),
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    harness.mock.verify().await;
+}
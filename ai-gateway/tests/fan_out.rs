@@ -0,0 +1,235 @@
+use std::{
+    collections::HashMap, num::NonZeroUsize, str::FromStr, time::Duration,
+};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::{
+            BalanceConfig, BalanceConfigInner, FanOutConfig, FanOutOnTimeout,
+            FanOutSelection, WeightedModel,
+        },
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    endpoints::EndpointType,
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{model_id::ModelId, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use nonempty_collections::nes;
+use rust_decimal::Decimal;
+use serde_json::json;
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+use tower::Service;
+
+const OPENAI_MODEL: &str = "openai/gpt-4o-mini";
+const ANTHROPIC_MODEL: &str = "anthropic/claude-3-haiku-20240307";
+
+fn config(selection: FanOutSelection) -> Config {
+    config_with_timeout(selection, None, FanOutOnTimeout::default())
+}
+
+fn config_with_timeout(
+    selection: FanOutSelection,
+    timeout: Option<Duration>,
+    on_timeout: FanOutOnTimeout,
+) -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're not testing authentication
+    config.helicone.features = HeliconeFeatures::None;
+    let balance_config = BalanceConfig::from(HashMap::from([(
+        EndpointType::Chat,
+        BalanceConfigInner::ModelWeighted {
+            models: nes![
+                WeightedModel {
+                    model: ModelId::from_str(OPENAI_MODEL).unwrap(),
+                    weight: Decimal::try_from(0.5).unwrap(),
+                },
+                WeightedModel {
+                    model: ModelId::from_str(ANTHROPIC_MODEL).unwrap(),
+                    weight: Decimal::try_from(0.5).unwrap(),
+                },
+            ],
+            fan_out: Some(FanOutConfig {
+                count: NonZeroUsize::new(2).unwrap(),
+                selection,
+                timeout,
+                on_timeout,
+            }),
+        },
+    )]));
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: balance_config,
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+async fn harness(selection: FanOutSelection) -> Harness {
+    // every request must be dispatched to both models
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:anthropic:messages", 1.into()),
+            // When auth is disabled, logging services should not be called
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config(selection))
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+fn chat_request(stream: bool) -> Request<axum_core::body::Body> {
+    let body = serde_json::to_vec(&json!({
+        "model": OPENAI_MODEL,
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ],
+        "stream": stream,
+    }))
+    .unwrap();
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(axum_core::body::Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn fan_out_returns_first_response() {
+    let mut harness = harness(FanOutSelection::First).await;
+    let response = harness.call(chat_request(false)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["object"], "chat.completion");
+
+    // the slower request still completes in the background
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn fan_out_returns_all_responses() {
+    let mut harness = harness(FanOutSelection::All).await;
+    let response = harness.call(chat_request(false)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["object"], "list");
+
+    let candidates = body["data"].as_array().unwrap();
+    assert_eq!(candidates.len(), 2);
+    let mut models = candidates
+        .iter()
+        .map(|candidate| {
+            assert_eq!(candidate["status"], 200);
+            assert_eq!(candidate["response"]["object"], "chat.completion");
+            candidate["model"].as_str().unwrap().to_string()
+        })
+        .collect::<Vec<_>>();
+    models.sort();
+    assert_eq!(models, vec![ANTHROPIC_MODEL, OPENAI_MODEL]);
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn fan_out_all_rejects_streaming() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(FanOutSelection::All))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let response = harness.call(chat_request(true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    harness.mock.verify().await;
+}
+
+/// A harness where the anthropic branch of the fan out takes longer than the
+/// fan out timeout.
+async fn slow_anthropic_harness(on_timeout: FanOutOnTimeout) -> Harness {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let harness = Harness::builder()
+        .with_config(config_with_timeout(
+            FanOutSelection::All,
+            Some(Duration::from_millis(200)),
+            on_timeout,
+        ))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_delay(Duration::from_secs(5))
+                .set_body_json(json!({})),
+        )
+        .with_priority(1)
+        .expect(1)
+        .mount(&harness.mock.anthropic_mock.http_server)
+        .await;
+    harness
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn fan_out_returns_completed_responses_on_timeout() {
+    let mut harness = slow_anthropic_harness(FanOutOnTimeout::Partial).await;
+    let started = std::time::Instant::now();
+    let response = harness.call(chat_request(false)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // the slow branch was aborted rather than waited for
+    assert!(started.elapsed() < Duration::from_secs(5));
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    let candidates = body["data"].as_array().unwrap();
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0]["model"], OPENAI_MODEL);
+    assert_eq!(candidates[0]["status"], 200);
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn fan_out_fails_on_timeout_when_configured() {
+    let mut harness = slow_anthropic_harness(FanOutOnTimeout::Fail).await;
+    let response = harness.call(chat_request(false)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    harness.mock.verify().await;
+}
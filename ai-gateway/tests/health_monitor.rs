@@ -5,9 +5,12 @@ use ai_gateway::{
         Config,
         balance::{BalanceConfig, BalanceConfigInner, WeightedProvider},
         helicone::HeliconeFeatures,
+        monitor::{
+            GracePeriod, HealthMonitorConfig, ProbeConfig, ProbeEndpoint,
+        },
         router::{RouterConfig, RouterConfigs},
     },
-    discover::monitor::health::HealthMonitor,
+    discover::monitor::health::{HealthMonitor, HealthProbe},
     endpoints::EndpointType,
     tests::{TestDefault, harness::Harness, mock::MockArgs},
     types::{provider::InferenceProvider, router::RouterId},
@@ -18,6 +21,10 @@ use http_body_util::BodyExt;
 use nonempty_collections::nes;
 use rust_decimal::Decimal;
 use serde_json::json;
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
 use tower::Service;
 
 #[tokio::test]
@@ -107,3 +114,277 @@ async fn errors_remove_provider_from_lb_pool() {
     // but this is totes good for now
     tokio::time::sleep(std::time::Duration::from_millis(100)).await;
 }
+
+/// An idle provider that fails its probes should be removed from the load
+/// balancer before any live traffic is sent to it.
+#[tokio::test]
+#[serial_test::serial]
+async fn failed_probes_remove_idle_provider_from_lb_pool() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.discover.monitor.health = HealthMonitorConfig::ErrorRatio {
+        ratio: Decimal::try_from(0.10).unwrap(),
+        // long enough that the probe results don't expire during the test
+        window: std::time::Duration::from_secs(10),
+        buckets: 10,
+        interval: std::time::Duration::from_millis(1),
+        grace_period: GracePeriod::Requests { min_requests: 10 },
+    };
+    config.discover.monitor.probe = Some(ProbeConfig {
+        providers: vec![InferenceProvider::Anthropic],
+        ..Default::default()
+    });
+    config.routers = probed_router_config();
+    let num_probes = 12;
+    let num_requests = 20;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", num_requests.into()),
+            // only the probes should reach the unhealthy provider
+            ("error:anthropic:messages", num_probes.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let health_probe = HealthProbe::new(harness.app_factory.state.clone())
+        .await
+        .unwrap()
+        .expect("probing is enabled");
+    for _ in 0..num_probes {
+        health_probe.probe_all().await;
+    }
+    HealthMonitor::new(harness.app_factory.state.clone())
+        .check_all()
+        .await
+        .unwrap();
+    assert!(is_removed(&harness, &InferenceProvider::Anthropic).await);
+
+    let body_bytes = serde_json::to_vec(&json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ]
+    }))
+    .unwrap();
+    for _ in 0..num_requests {
+        let request_body = axum_core::body::Body::from(body_bytes.clone());
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("http://router.helicone.com/router/my-router/chat/completions")
+            .body(request_body)
+            .unwrap();
+        let response = harness.call(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+
+    // mocks are verified on drop
+}
+
+fn probed_router_config() -> RouterConfigs {
+    let balance_config = BalanceConfig::from(HashMap::from([(
+        EndpointType::Chat,
+        BalanceConfigInner::ProviderWeighted {
+            providers: nes![
+                WeightedProvider {
+                    provider: InferenceProvider::OpenAI,
+                    weight: Decimal::try_from(0.50).unwrap(),
+                },
+                WeightedProvider {
+                    provider: InferenceProvider::Anthropic,
+                    weight: Decimal::try_from(0.50).unwrap(),
+                },
+            ],
+        },
+    )]));
+    RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: balance_config,
+            ..Default::default()
+        },
+    )]))
+}
+
+fn readmission_config(endpoint: ProbeEndpoint) -> Config {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.discover.monitor.health = HealthMonitorConfig::ErrorRatio {
+        ratio: Decimal::try_from(0.50).unwrap(),
+        // long enough that the failed probes don't expire during the test
+        window: std::time::Duration::from_secs(10),
+        buckets: 10,
+        interval: std::time::Duration::from_millis(1),
+        grace_period: GracePeriod::Requests { min_requests: 10 },
+    };
+    config.discover.monitor.probe = Some(ProbeConfig {
+        endpoint,
+        providers: vec![InferenceProvider::Anthropic],
+        ..Default::default()
+    });
+    config.routers = probed_router_config();
+    config
+}
+
+/// Whether the health monitor of `my-router` removed the provider from its
+/// load balancer.
+async fn is_removed(harness: &Harness, provider: &InferenceProvider) -> bool {
+    harness
+        .app_factory
+        .state
+        .0
+        .health_monitors
+        .read()
+        .await
+        .get(&RouterId::Named(CompactString::new("my-router")))
+        .expect("router is monitored")
+        .is_removed(provider)
+}
+
+/// Fails the first `num_failed_probes` probes, checking that the provider is
+/// removed, then succeeds `num_successful_probes` probes, checking that the
+/// provider is added back.
+async fn assert_probes_readmit(
+    harness: &mut Harness,
+    num_failed_probes: u64,
+    num_successful_probes: u64,
+) {
+    let health_probe = HealthProbe::new(harness.app_factory.state.clone())
+        .await
+        .unwrap()
+        .expect("probing is enabled");
+    let health_monitor = HealthMonitor::new(harness.app_factory.state.clone());
+
+    for _ in 0..num_failed_probes {
+        health_probe.probe_all().await;
+    }
+    health_monitor.check_all().await.unwrap();
+    assert!(is_removed(harness, &InferenceProvider::Anthropic).await);
+
+    let body_bytes = serde_json::to_vec(&json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ]
+    }))
+    .unwrap();
+    // while removed, all requests are sent to the healthy provider
+    for _ in 0..20 {
+        let request_body = axum_core::body::Body::from(body_bytes.clone());
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("http://router.helicone.com/router/my-router/chat/completions")
+            .body(request_body)
+            .unwrap();
+        let response = harness.call(request).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+
+    for _ in 0..num_successful_probes {
+        health_probe.probe_all().await;
+    }
+    health_monitor.check_all().await.unwrap();
+    assert!(!is_removed(harness, &InferenceProvider::Anthropic).await);
+}
+
+/// A provider removed for failing its probes should be added back once
+/// enough of its probes succeed, without waiting for live traffic.
+#[tokio::test]
+#[serial_test::serial]
+async fn successful_probes_readmit_recovered_provider() {
+    let num_failed_probes = 10;
+    // enough to bring the error ratio back under the threshold
+    let num_successful_probes = 11;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 20.into()),
+            ("success:anthropic:messages", num_successful_probes.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(readmission_config(ProbeEndpoint::ChatCompletion))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(num_failed_probes)
+        .expect(num_failed_probes)
+        .with_priority(1)
+        .mount(&harness.mock.anthropic_mock.http_server)
+        .await;
+
+    assert_probes_readmit(
+        &mut harness,
+        num_failed_probes,
+        num_successful_probes,
+    )
+    .await;
+
+    // mocks are verified on drop
+}
+
+/// Probes of the models endpoint should remove and add back providers the
+/// same way as chat completion probes, without sending any completions.
+#[tokio::test]
+#[serial_test::serial]
+async fn models_probes_readmit_recovered_provider() {
+    let num_failed_probes = 10;
+    let num_successful_probes = 11;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 20.into()),
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(readmission_config(ProbeEndpoint::Models))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(num_failed_probes)
+        .expect(num_failed_probes)
+        .with_priority(1)
+        .mount(&harness.mock.anthropic_mock.http_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [{ "type": "model", "id": "claude-3-5-haiku-20241022" }],
+            "has_more": false
+        })))
+        .expect(num_successful_probes)
+        .with_priority(2)
+        .mount(&harness.mock.anthropic_mock.http_server)
+        .await;
+
+    assert_probes_readmit(
+        &mut harness,
+        num_failed_probes,
+        num_successful_probes,
+    )
+    .await;
+
+    // mocks are verified on drop
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use stubr::wiremock_rs::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path, path_regex},
+};
+use tower::Service;
+
+async fn harness(load_balance: BalanceConfig) -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing response mapping
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance,
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+/// The body of the given stub's response, with `edit` applied.
+fn stub_body(stub: &str, edit: impl FnOnce(&mut Value)) -> Value {
+    let stub = std::fs::read(format!(
+        "{}/stubs/{stub}.json",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+    .unwrap();
+    let stub: Value = serde_json::from_slice(&stub).unwrap();
+    let mut body = stub["response"]["jsonBody"].clone();
+    edit(&mut body);
+    body
+}
+
+async fn mount(server: &MockServer, matcher_path: &str, body: Value) {
+    Mock::given(method("POST"))
+        .and(path(matcher_path))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .with_priority(1)
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+/// Sends a chat completion request for `model` and returns the finish
+/// reason of the mapped response.
+async fn finish_reason(harness: &mut Harness, model: &str) -> Value {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "max_tokens": 5,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    body["choices"][0]["finish_reason"].clone()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn anthropic_max_tokens_is_length() {
+    let mut harness = harness(BalanceConfig::anthropic_chat()).await;
+    let body = stub_body("anthropic/messages_success", |body| {
+        body["stop_reason"] = json!("max_tokens");
+    });
+    mount(
+        &harness.mock.anthropic_mock.http_server,
+        "/v1/messages",
+        body,
+    )
+    .await;
+
+    let finish_reason =
+        finish_reason(&mut harness, "anthropic/claude-3-5-sonnet-latest").await;
+    assert_eq!(finish_reason, "length");
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn bedrock_max_tokens_is_length() {
+    let mut harness = harness(BalanceConfig::bedrock()).await;
+    let body = stub_body("bedrock/converse_sucesss", |body| {
+        body["stopReason"] = json!("max_tokens");
+    });
+    Mock::given(method("POST"))
+        .and(path_regex("/model/[^/]+/converse"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .with_priority(1)
+        .expect(1)
+        .mount(&harness.mock.bedrock_mock.http_server)
+        .await;
+
+    let finish_reason = finish_reason(
+        &mut harness,
+        "bedrock/anthropic.claude-3-5-sonnet-20240620-v1:0",
+    )
+    .await;
+    assert_eq!(finish_reason, "length");
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn mistral_model_length_is_length() {
+    let mut harness = harness(BalanceConfig::mistral()).await;
+    let body = stub_body("mistral/chat_completion", |body| {
+        body["choices"][0]["finish_reason"] = json!("model_length");
+    });
+    mount(
+        &harness.mock.mistral_mock.http_server,
+        "/v1/chat/completions",
+        body,
+    )
+    .await;
+
+    let finish_reason =
+        finish_reason(&mut harness, "mistral/mistral-large-latest").await;
+    assert_eq!(finish_reason, "length");
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn gemini_max_tokens_is_length() {
+    let mut harness = harness(BalanceConfig::google_gemini()).await;
+    let body = stub_body("gemini/generate_content_success", |body| {
+        body["choices"][0]["finish_reason"] = json!("MAX_TOKENS");
+    });
+    mount(
+        &harness.mock.google_mock.http_server,
+        "/v1beta/openai/chat/completions",
+        body,
+    )
+    .await;
+
+    let finish_reason =
+        finish_reason(&mut harness, "gemini/gemini-2.0-flash").await;
+    assert_eq!(finish_reason, "length");
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn ollama_length_is_unchanged() {
+    let mut harness = harness(BalanceConfig::ollama_chat()).await;
+    let body = stub_body("ollama/chat_completions_success", |body| {
+        body["choices"][0]["finish_reason"] = json!("length");
+    });
+    mount(
+        &harness.mock.ollama_mock.http_server,
+        "/v1/chat/completions",
+        body,
+    )
+    .await;
+
+    let finish_reason = finish_reason(&mut harness, "ollama/llama3").await;
+    assert_eq!(finish_reason, "length");
+}
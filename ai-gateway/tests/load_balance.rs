@@ -34,8 +34,24 @@ fn p2c_config_openai_anthropic_google() -> RouterConfigs {
             model_mappings: None,
             cache: None,
             retries: None,
+            retry_status_codes: Vec::new(),
             rate_limit: None,
             providers: None,
+            locale_routing: None,
+            failover: None,
+            required_headers: Vec::new(),
+            weight_schedule: None,
+            system_prompt_prefix: None,
+            audit_transformations: false,
+            stream_moderation: None,
+            moderation: None,
+            request_timeout: None,
+            model_timeouts: HashMap::new(),
+            queue: None,
+            strict_prompt_inputs: false,
+            max_request_body_bytes: None,
+            sampling_bounds: None,
+            stickiness: None,
         },
     )]))
 }
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::{BalanceConfig, BalanceConfigInner},
+        helicone::HeliconeFeatures,
+        locale_routing::LocaleRoutingConfig,
+        router::{RouterConfig, RouterConfigs},
+    },
+    endpoints::EndpointType,
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use nonempty_collections::nes;
+use serde_json::json;
+use tower::Service;
+
+fn test_config() -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing routing behavior
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig(HashMap::from([(
+                EndpointType::Chat,
+                BalanceConfigInner::BalancedLatency {
+                    providers: nes![
+                        InferenceProvider::OpenAI,
+                        InferenceProvider::Anthropic
+                    ],
+                },
+            )])),
+            locale_routing: Some(LocaleRoutingConfig {
+                locales: HashMap::from([
+                    ("de".to_string(), InferenceProvider::Anthropic),
+                    ("ja".to_string(), InferenceProvider::OpenAI),
+                ]),
+                detect: true,
+            }),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn chat_request(
+    content: &str,
+    locale: Option<&str>,
+) -> Request<axum_core::body::Body> {
+    let body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": content
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions");
+    if let Some(locale) = locale {
+        request = request.header("x-helicone-locale", locale);
+    }
+    request.body(body).unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn declared_locale_routes_to_locale_provider() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:anthropic:messages", 3.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    for _ in 0..3 {
+        let request = chat_request("Hallo, Welt!", Some("de-DE"));
+        let response = harness.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn detected_locale_routes_to_locale_provider() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 3.into()),
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    for _ in 0..3 {
+        let request = chat_request("こんにちは、世界", None);
+        let response = harness.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    harness.mock.verify().await;
+}
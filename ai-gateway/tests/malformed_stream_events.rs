@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+use tower::Service;
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn malformed_events_are_dropped_from_mapped_streams() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let delta = |text: &str| {
+        json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": { "type": "text_delta", "text": text }
+        })
+    };
+    let body = [
+        ": heartbeat\n\n".to_string(),
+        format!("event: content_block_delta\ndata: {}\n\n", delta("Hello")),
+        // truncated by the provider
+        "event: content_block_delta\ndata: \
+         {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\"\n\n"
+            .to_string(),
+        ": heartbeat\n\n".to_string(),
+        format!(
+            "event: content_block_delta\ndata: {}\n\n",
+            delta(", world!")
+        ),
+        format!(
+            "event: message_stop\ndata: {}\n\n",
+            json!({ "type": "message_stop" })
+        ),
+    ]
+    .concat();
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(body),
+        )
+        .with_priority(1)
+        .expect(1)
+        .mount(&harness.mock.anthropic_mock.http_server)
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "anthropic/claude-3-5-sonnet-latest",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ],
+            "stream": true
+        }))
+        .unwrap(),
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let content = String::from_utf8_lossy(&body)
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str::<Value>(data).unwrap())
+        .filter_map(|chunk| {
+            chunk["choices"][0]["delta"]["content"]
+                .as_str()
+                .map(str::to_string)
+        })
+        .collect::<String>();
+    assert_eq!(content, "Hello, world!");
+}
@@ -0,0 +1,398 @@
+use std::{collections::HashMap, io::Cursor};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        mapper::RequiredToolChoice,
+        providers::MappingLimits,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use image::{DynamicImage, ImageFormat, RgbImage};
+use serde_json::{Value, json};
+use tower::Service;
+
+fn test_config(mapping_limits: MappingLimits) -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing body limits
+    config.helicone.features = HeliconeFeatures::None;
+    config
+        .providers
+        .get_mut(&InferenceProvider::Anthropic)
+        .unwrap()
+        .mapping_limits = mapping_limits;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn large_body() -> axum_core::body::Body {
+    axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "anthropic/claude-3-5-sonnet-latest",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world! ".repeat(100)
+                }
+            ]
+        }))
+        .unwrap(),
+    )
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn mapped_request_over_limit_is_rejected() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config(MappingLimits {
+            max_request_bytes: Some(256),
+            max_response_bytes: None,
+            ..Default::default()
+        }))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(large_body())
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["error"]["message"],
+        "Request body exceeds the 256 byte limit for mapping"
+    );
+    harness.mock.verify().await;
+}
+
+/// Requests buffered to be retried when a required tool call is missing are
+/// held to the same limit.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn request_buffered_for_retries_is_limited() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut config = test_config(MappingLimits {
+        max_request_bytes: Some(256),
+        max_response_bytes: None,
+        ..Default::default()
+    });
+    config.mapper.required_tool_choice = RequiredToolChoice::Retry;
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(large_body())
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn mapped_response_over_limit_is_rejected() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config(MappingLimits {
+            max_request_bytes: None,
+            max_response_bytes: Some(16),
+            ..Default::default()
+        }))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(large_body())
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn request_over_router_limit_is_rejected() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut config = test_config(MappingLimits::default());
+    // the lower of the global and router limits applies
+    config.mapper.max_request_body_bytes = Some(1024 * 1024);
+    let router_config = config
+        .routers
+        .get(&RouterId::Named(CompactString::new("my-router")))
+        .unwrap()
+        .clone();
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            max_request_body_bytes: Some(256),
+            ..router_config
+        },
+    )]));
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(large_body())
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+    assert_eq!(
+        body["error"]["message"],
+        "Request body exceeds the 256 byte limit for mapping"
+    );
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn response_over_global_limit_is_a_bad_gateway() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut config = test_config(MappingLimits::default());
+    config.mapper.max_response_body_bytes = Some(16);
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(large_body())
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["error"]["message"],
+        "Response body exceeds the 16 byte limit for mapping"
+    );
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn passthrough_request_is_not_limited() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:fake_endpoint", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config(MappingLimits {
+            max_request_bytes: Some(256),
+            max_response_bytes: Some(16),
+            ..Default::default()
+        }))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/anthropic/v1/fake_endpoint")
+        .header("content-type", "application/json")
+        .body(large_body())
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    harness.mock.verify().await;
+}
+
+/// A PNG data URL of a noisy image, which doesn't compress well.
+fn png_data_url(width: u32, height: u32) -> String {
+    let image = RgbImage::from_fn(width, height, |x, y| {
+        let seed = x.wrapping_mul(31).wrapping_add(y.wrapping_mul(17));
+        let [r, g, b, _] = seed.wrapping_mul(2_654_435_761).to_le_bytes();
+        image::Rgb([r, g, b])
+    });
+    let mut encoded = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(image)
+        .write_to(&mut encoded, ImageFormat::Png)
+        .unwrap();
+    format!(
+        "data:image/png;base64,{}",
+        STANDARD.encode(encoded.into_inner())
+    )
+}
+
+fn image_request(urls: &[&str]) -> Request<axum_core::body::Body> {
+    let content = urls
+        .iter()
+        .map(|url| json!({ "type": "image_url", "image_url": { "url": url } }))
+        .chain([json!({ "type": "text", "text": "What's in these images?" })])
+        .collect::<Vec<_>>();
+    let body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "anthropic/claude-3-5-sonnet-latest",
+            "messages": [{ "role": "user", "content": content }]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn request_with_too_many_images_is_rejected() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config(MappingLimits {
+            max_images: Some(1),
+            ..Default::default()
+        }))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let request = image_request(&[
+        "https://example.com/cat.png",
+        "https://example.com/dog.png",
+    ]);
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["error"]["message"],
+        "Request contains more than the 1 images allowed by provider: \
+         anthropic"
+    );
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn oversized_image_is_downscaled() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let max_image_bytes = 16 * 1024;
+    let mut harness = Harness::builder()
+        .with_config(test_config(MappingLimits {
+            max_image_bytes: Some(max_image_bytes),
+            downscale_images: true,
+            ..Default::default()
+        }))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let url = png_data_url(256, 256);
+    let response = harness.call(image_request(&[&url])).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    let sent = harness
+        .mock
+        .anthropic_mock
+        .http_server
+        .received_requests()
+        .await
+        .expect("request recording is enabled");
+    let sent: Value = serde_json::from_slice(&sent[0].body).unwrap();
+    let source = &sent["messages"][0]["content"][0]["source"];
+    assert_eq!(source["media_type"], "image/png");
+    let data = STANDARD.decode(source["data"].as_str().unwrap()).unwrap();
+    assert!(data.len() <= max_image_bytes);
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn oversized_image_is_rejected_without_downscaling() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config(MappingLimits {
+            max_image_bytes: Some(16 * 1024),
+            ..Default::default()
+        }))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let url = png_data_url(256, 256);
+    let response = harness.call(image_request(&[&url])).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    harness.mock.verify().await;
+}
@@ -0,0 +1,128 @@
+use std::{collections::HashMap, num::NonZeroUsize};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+use tower::Service;
+
+async fn harness(max_choices: Option<usize>) -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing response mapping
+    config.helicone.features = HeliconeFeatures::None;
+    config.mapper.max_choices = max_choices.and_then(NonZeroUsize::new);
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let choices = (0..3)
+        .map(|index| {
+            json!({
+                "index": index,
+                "message": {
+                    "role": "assistant",
+                    "content": format!("choice {index}")
+                },
+                "logprobs": null,
+                "finish_reason": "stop"
+            })
+        })
+        .collect::<Vec<_>>();
+    let body = json!({
+        "id": "chatcmpl-123",
+        "object": "chat.completion",
+        "created": 1_741_569_952,
+        "model": "gpt-4o-mini",
+        "choices": choices,
+        "usage": {
+            "prompt_tokens": 10,
+            "completion_tokens": 15,
+            "total_tokens": 25
+        }
+    });
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .with_priority(1)
+        .expect(1)
+        .mount(&harness.mock.openai_mock.http_server)
+        .await;
+    harness
+}
+
+/// Sends a chat completion request asking for three choices and returns the
+/// choices of the response.
+async fn choices(harness: &mut Harness) -> Vec<Value> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "n": 3,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    body["choices"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn choices_are_capped_to_max() {
+    let mut harness = harness(Some(2)).await;
+
+    let choices = choices(&mut harness).await;
+    assert_eq!(choices.len(), 2);
+    assert_eq!(choices[0]["message"]["content"], "choice 0");
+    assert_eq!(choices[1]["message"]["content"], "choice 1");
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn all_choices_returned_by_default() {
+    let mut harness = harness(None).await;
+
+    let choices = choices(&mut harness).await;
+    assert_eq!(choices.len(), 3);
+}
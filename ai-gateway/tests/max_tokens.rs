@@ -0,0 +1,100 @@
+use ai_gateway::{
+    app::App,
+    config::{Config, helicone::HeliconeFeatures},
+    endpoints::{ApiEndpoint, openai::OpenAI},
+    middleware::mapper::{
+        model::ModelMapper, registry::EndpointConverterRegistry,
+    },
+    tests::TestDefault,
+    types::provider::InferenceProvider,
+};
+use bytes::Bytes;
+use serde_json::{Value, json};
+
+/// Maps an `OpenAI` chat completion request with the given body to the
+/// given provider and returns the mapped request body.
+async fn map_request(provider: InferenceProvider, body: Value) -> Value {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    let app = App::new(config).await.expect("failed to create app");
+    let model_mapper = ModelMapper::new(app.state.clone());
+    let registry = EndpointConverterRegistry::new(&model_mapper);
+
+    let source_endpoint = ApiEndpoint::OpenAI(OpenAI::chat_completions());
+    let target_endpoint =
+        ApiEndpoint::mapped(source_endpoint.clone(), &provider).unwrap();
+    let converter = registry
+        .get_converter(&source_endpoint, &target_endpoint)
+        .expect("converter is registered");
+    let body = Bytes::from(serde_json::to_vec(&body).unwrap());
+    let (mapped, _mapper_ctx) = converter.convert_req_body(body).unwrap();
+    serde_json::from_slice(&mapped).unwrap()
+}
+
+fn request(max_tokens_field: Option<&str>) -> Value {
+    let mut body = json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ]
+    });
+    if let Some(field) = max_tokens_field {
+        body[field] = json!(100);
+    }
+    body
+}
+
+#[tokio::test]
+async fn anthropic_max_tokens_from_either_field() {
+    for field in ["max_tokens", "max_completion_tokens"] {
+        let mapped =
+            map_request(InferenceProvider::Anthropic, request(Some(field)))
+                .await;
+        assert_eq!(mapped["max_tokens"], 100, "mapped from {field}");
+    }
+    let mapped = map_request(InferenceProvider::Anthropic, request(None)).await;
+    assert_eq!(mapped["max_tokens"], 2000);
+}
+
+#[tokio::test]
+async fn bedrock_max_tokens_from_either_field() {
+    for field in ["max_tokens", "max_completion_tokens"] {
+        let mapped =
+            map_request(InferenceProvider::Bedrock, request(Some(field))).await;
+        assert_eq!(
+            mapped["inferenceConfig"]["maxTokens"], 100,
+            "mapped from {field}"
+        );
+    }
+    let mapped = map_request(InferenceProvider::Bedrock, request(None)).await;
+    assert_eq!(mapped["inferenceConfig"]["maxTokens"], 2000);
+}
+
+#[tokio::test]
+async fn openai_compatible_max_tokens_from_either_field() {
+    let providers = [
+        InferenceProvider::GoogleGemini,
+        InferenceProvider::Ollama,
+        InferenceProvider::Named("mistral".into()),
+    ];
+    for provider in providers {
+        for field in ["max_tokens", "max_completion_tokens"] {
+            let mapped =
+                map_request(provider.clone(), request(Some(field))).await;
+            assert_eq!(
+                mapped["max_tokens"], 100,
+                "mapped from {field} for {provider}"
+            );
+            assert!(
+                mapped.get("max_completion_tokens").is_none(),
+                "max_completion_tokens set for {provider}"
+            );
+        }
+        // providers apply their own default
+        let mapped = map_request(provider.clone(), request(None)).await;
+        assert!(mapped.get("max_tokens").is_none());
+    }
+}
@@ -0,0 +1,272 @@
+use ai_gateway::{
+    app::App,
+    config::{Config, helicone::HeliconeFeatures},
+    endpoints::{ApiEndpoint, openai::OpenAI},
+    middleware::mapper::{
+        EndpointConverter, model::ModelMapper,
+        registry::EndpointConverterRegistry,
+    },
+    tests::TestDefault,
+    types::provider::InferenceProvider,
+};
+use bytes::Bytes;
+use http::StatusCode;
+use serde_json::{Value, json};
+
+async fn registry() -> EndpointConverterRegistry {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    let app = App::new(config).await.expect("failed to create app");
+    let model_mapper = ModelMapper::new(app.state.clone());
+    EndpointConverterRegistry::new(&model_mapper)
+}
+
+fn converter(
+    registry: &EndpointConverterRegistry,
+) -> &(dyn EndpointConverter + Send + Sync + 'static) {
+    let source_endpoint = ApiEndpoint::OpenAI(OpenAI::chat_completions());
+    let target_endpoint = ApiEndpoint::mapped(
+        source_endpoint.clone(),
+        &InferenceProvider::Named("mistral".into()),
+    )
+    .unwrap();
+    registry
+        .get_converter(&source_endpoint, &target_endpoint)
+        .expect("converter is registered")
+}
+
+async fn map_request(body: Value) -> Value {
+    let registry = registry().await;
+    let body = Bytes::from(serde_json::to_vec(&body).unwrap());
+    let (mapped, _mapper_ctx) =
+        converter(&registry).convert_req_body(body).unwrap();
+    serde_json::from_slice(&mapped).unwrap()
+}
+
+async fn map_response(
+    status: StatusCode,
+    body: Value,
+    is_stream: bool,
+) -> Value {
+    let registry = registry().await;
+    let (parts, ()) = http::Response::builder()
+        .status(status)
+        .body(())
+        .unwrap()
+        .into_parts();
+    let body = Bytes::from(serde_json::to_vec(&body).unwrap());
+    let mapped = converter(&registry)
+        .convert_resp_body(parts, body, is_stream)
+        .unwrap()
+        .expect("response is mapped");
+    serde_json::from_slice(&mapped).unwrap()
+}
+
+#[tokio::test]
+async fn safe_prompt_is_forwarded() {
+    let mapped = map_request(json!({
+        "model": "mistral/mistral-large-latest",
+        "safe_prompt": true,
+        "messages": [{ "role": "user", "content": "Hello" }]
+    }))
+    .await;
+    assert_eq!(mapped["safe_prompt"], true);
+
+    let mapped = map_request(json!({
+        "model": "mistral/mistral-large-latest",
+        "messages": [{ "role": "user", "content": "Hello" }]
+    }))
+    .await;
+    assert!(mapped.get("safe_prompt").is_none());
+}
+
+#[tokio::test]
+async fn prefix_assistant_messages_are_kept() {
+    let mapped = map_request(json!({
+        "model": "mistral/mistral-large-latest",
+        "messages": [
+            { "role": "system", "content": "Answer in French" },
+            { "role": "user", "content": "Hello" },
+            { "role": "assistant", "content": "Bonjour", "prefix": true }
+        ]
+    }))
+    .await;
+    assert_eq!(mapped["messages"][0]["role"], "system");
+    assert!(mapped["messages"][1].get("prefix").is_none());
+    assert_eq!(
+        mapped["messages"][2],
+        json!({ "role": "assistant", "content": "Bonjour", "prefix": true })
+    );
+}
+
+#[tokio::test]
+async fn tool_calls_are_mapped_in_requests() {
+    let mapped = map_request(json!({
+        "model": "mistral/mistral-large-latest",
+        "messages": [
+            { "role": "user", "content": "Weather in Paris?" },
+            {
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "D681PevKs",
+                    "type": "function",
+                    "function": {
+                        "name": "get_weather",
+                        "arguments": "{\"city\":\"Paris\"}"
+                    }
+                }]
+            },
+            { "role": "tool", "tool_call_id": "D681PevKs", "content": "sunny" }
+        ],
+        "tools": [{
+            "type": "function",
+            "function": {
+                "name": "get_weather",
+                "parameters": {
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } }
+                }
+            }
+        }],
+        "tool_choice": "required"
+    }))
+    .await;
+    assert_eq!(mapped["tool_choice"], "any");
+    assert_eq!(mapped["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(
+        mapped["messages"][1]["tool_calls"][0],
+        json!({
+            "id": "D681PevKs",
+            "function": {
+                "name": "get_weather",
+                "arguments": "{\"city\":\"Paris\"}"
+            }
+        })
+    );
+    assert_eq!(
+        mapped["messages"][2],
+        json!({ "role": "tool", "tool_call_id": "D681PevKs", "content": "sunny" })
+    );
+}
+
+#[tokio::test]
+async fn tool_calls_are_mapped_in_responses() {
+    let mapped = map_response(
+        StatusCode::OK,
+        json!({
+            "id": "cmpl-1",
+            "object": "chat.completion",
+            "created": 1_741_569_952,
+            "model": "mistral-large-latest",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{
+                        "id": "D681PevKs",
+                        "function": {
+                            "name": "get_weather",
+                            "arguments": { "city": "Paris" }
+                        },
+                        "index": 0
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {
+                "prompt_tokens": 10,
+                "completion_tokens": 5,
+                "total_tokens": 15
+            }
+        }),
+        false,
+    )
+    .await;
+    let choice = &mapped["choices"][0];
+    assert_eq!(choice["finish_reason"], "tool_calls");
+    assert_eq!(
+        choice["message"]["tool_calls"][0],
+        json!({
+            "id": "D681PevKs",
+            "type": "function",
+            "function": {
+                "name": "get_weather",
+                "arguments": "{\"city\":\"Paris\"}"
+            }
+        })
+    );
+    assert_eq!(mapped["usage"]["total_tokens"], 15);
+}
+
+#[tokio::test]
+async fn tool_calls_are_mapped_in_stream_chunks() {
+    let mapped = map_response(
+        StatusCode::OK,
+        json!({
+            "id": "cmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1_741_569_952,
+            "model": "mistral-large-latest",
+            "choices": [{
+                "index": 0,
+                "delta": {
+                    "content": "",
+                    "tool_calls": [{
+                        "id": "D681PevKs",
+                        "function": {
+                            "name": "get_weather",
+                            "arguments": "{\"city\":\"Paris\"}"
+                        },
+                        "index": 0
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        }),
+        true,
+    )
+    .await;
+    let tool_call = &mapped["choices"][0]["delta"]["tool_calls"][0];
+    assert_eq!(tool_call["id"], "D681PevKs");
+    assert_eq!(tool_call["function"]["name"], "get_weather");
+    assert_eq!(tool_call["function"]["arguments"], "{\"city\":\"Paris\"}");
+    assert_eq!(mapped["object"], "chat.completion.chunk");
+}
+
+#[tokio::test]
+async fn errors_are_mapped_to_openai_errors() {
+    let mapped = map_response(
+        StatusCode::BAD_REQUEST,
+        json!({
+            "object": "error",
+            "message": "Expected last role to be user but got assistant",
+            "type": "invalid_request_message_order",
+            "param": null,
+            "code": "3230"
+        }),
+        false,
+    )
+    .await;
+    assert_eq!(
+        mapped["error"]["message"],
+        "Expected last role to be user but got assistant"
+    );
+    assert_eq!(mapped["error"]["type"], "invalid_request_error");
+    assert_eq!(mapped["error"]["code"], "3230");
+
+    let mapped = map_response(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        json!({
+            "detail": [{
+                "type": "missing",
+                "loc": ["body", "messages"],
+                "msg": "Field required"
+            }]
+        }),
+        false,
+    )
+    .await;
+    assert_eq!(mapped["error"]["message"], "body.messages: Field required");
+}
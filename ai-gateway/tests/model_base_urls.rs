@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{model_id::ModelId, provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use stubr::wiremock_rs::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+use tower::Service;
+use url::Url;
+
+/// Builds a harness that sends requests for `gpt-4o` to `server`, and
+/// requests for other `OpenAI` models to the default mock.
+async fn harness(server: &MockServer, openai_requests: u64) -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing request routing
+    config.helicone.features = HeliconeFeatures::None;
+    let openai = config
+        .providers
+        .get_mut(&InferenceProvider::OpenAI)
+        .unwrap();
+    openai.model_base_urls.insert(
+        ModelId::from_str_and_provider(InferenceProvider::OpenAI, "gpt-4o")
+            .unwrap(),
+        Url::parse(&format!("{}/beta/", server.uri())).unwrap(),
+    );
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", openai_requests.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+async fn mount_beta_endpoint(server: &MockServer, expected_requests: u64) {
+    Mock::given(method("POST"))
+        .and(path("/beta/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-beta",
+            "object": "chat.completion",
+            "created": 1_748_543_700,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "finish_reason": "stop",
+                "message": { "role": "assistant", "content": "Hello!" }
+            }],
+            "usage": {
+                "prompt_tokens": 6,
+                "completion_tokens": 2,
+                "total_tokens": 8
+            }
+        })))
+        .expect(expected_requests)
+        .mount(server)
+        .await;
+}
+
+fn chat_request(model: &str) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn model_with_base_url_is_sent_to_its_host() {
+    let server = MockServer::start().await;
+    mount_beta_endpoint(&server, 1).await;
+    let mut harness = harness(&server, 0).await;
+
+    let response = harness.call(chat_request("openai/gpt-4o")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("chatcmpl-beta"), "unexpected body: {body}");
+    server.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn other_models_are_sent_to_the_provider_base_url() {
+    let server = MockServer::start().await;
+    mount_beta_endpoint(&server, 0).await;
+    let mut harness = harness(&server, 1).await;
+
+    let response = harness
+        .call(chat_request("openai/gpt-4o-mini"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+    server.verify().await;
+}
@@ -0,0 +1,109 @@
+use std::collections::{HashMap, HashSet};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::Value;
+use tower::Service;
+
+async fn harness(config: Config) -> Harness {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+async fn list_models(harness: &mut Harness, uri: &str) -> HashSet<String> {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(uri)
+        .body(axum_core::body::Body::empty())
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["object"], "list");
+    body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|model| {
+            assert_eq!(model["object"], "model");
+            let id = model["id"].as_str().unwrap();
+            let owned_by = model["owned_by"].as_str().unwrap();
+            assert!(id.starts_with(&format!("{owned_by}/")));
+            id.to_string()
+        })
+        .collect()
+}
+
+fn configured_models(
+    config: &Config,
+    provider: &InferenceProvider,
+) -> HashSet<String> {
+    config.providers[provider]
+        .models
+        .iter()
+        .map(|model| format!("{provider}/{model}"))
+        .collect()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn unified_api_lists_all_configured_models() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    let expected: HashSet<String> = config
+        .providers
+        .keys()
+        .flat_map(|provider| configured_models(&config, provider))
+        .collect();
+    let mut harness = harness(config).await;
+
+    let models =
+        list_models(&mut harness, "http://router.helicone.com/ai/v1/models")
+            .await;
+    assert_eq!(models, expected);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn router_lists_only_balanced_models() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            ..Default::default()
+        },
+    )]));
+    let expected = configured_models(&config, &InferenceProvider::OpenAI);
+    let mut harness = harness(config).await;
+
+    let models = list_models(
+        &mut harness,
+        "http://router.helicone.com/router/my-router/v1/models",
+    )
+    .await;
+    assert_eq!(models, expected);
+}
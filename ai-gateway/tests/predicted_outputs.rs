@@ -0,0 +1,84 @@
+use ai_gateway::{
+    app::App,
+    config::{Config, helicone::HeliconeFeatures},
+    endpoints::{ApiEndpoint, openai::OpenAI},
+    middleware::mapper::{
+        model::ModelMapper, registry::EndpointConverterRegistry,
+    },
+    tests::TestDefault,
+    types::provider::InferenceProvider,
+};
+use bytes::Bytes;
+use serde_json::{Value, json};
+
+/// Maps an `OpenAI` chat completion request with the given body to the
+/// given provider and returns the mapped request body.
+async fn map_request(provider: InferenceProvider, body: Value) -> Value {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    let app = App::new(config).await.expect("failed to create app");
+    let model_mapper = ModelMapper::new(app.state.clone());
+    let registry = EndpointConverterRegistry::new(&model_mapper);
+
+    let source_endpoint = ApiEndpoint::OpenAI(OpenAI::chat_completions());
+    let target_endpoint =
+        ApiEndpoint::mapped(source_endpoint.clone(), &provider).unwrap();
+    let converter = registry
+        .get_converter(&source_endpoint, &target_endpoint)
+        .expect("converter is registered");
+    let body = Bytes::from(serde_json::to_vec(&body).unwrap());
+    let (mapped, _mapper_ctx) = converter.convert_req_body(body).unwrap();
+    serde_json::from_slice(&mapped).unwrap()
+}
+
+fn prediction() -> Value {
+    json!({
+        "type": "content",
+        "content": "fn main() {\n    println!(\"Hello, world!\");\n}"
+    })
+}
+
+fn request() -> Value {
+    json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Rename the function to `run`."
+            }
+        ],
+        "prediction": prediction()
+    })
+}
+
+#[tokio::test]
+async fn openai_prediction_is_passed_through() {
+    let mapped = map_request(InferenceProvider::OpenAI, request()).await;
+    assert_eq!(mapped["prediction"], prediction());
+
+    // predictions may also be given as an array of text parts
+    let mut body = request();
+    body["prediction"]["content"] =
+        json!([{ "type": "text", "text": "fn main() {}" }]);
+    let mapped = map_request(InferenceProvider::OpenAI, body.clone()).await;
+    assert_eq!(mapped["prediction"], body["prediction"]);
+}
+
+#[tokio::test]
+async fn prediction_is_dropped_for_other_providers() {
+    let providers = [
+        InferenceProvider::Anthropic,
+        InferenceProvider::Bedrock,
+        InferenceProvider::GoogleGemini,
+        InferenceProvider::Ollama,
+        InferenceProvider::Named("mistral".into()),
+    ];
+    for provider in providers {
+        let mapped = map_request(provider.clone(), request()).await;
+        assert!(
+            mapped.get("prediction").is_none(),
+            "prediction sent to {provider}"
+        );
+        assert!(mapped != Value::Null, "request mapped for {provider}");
+    }
+}
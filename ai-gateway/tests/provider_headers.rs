@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use serde_json::json;
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{header, method, path},
+};
+use tower::Service;
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn custom_headers_reach_the_provider() {
+    // SAFETY: This must only be called within the single threaded tokio
+    // runtime in tests
+    unsafe {
+        std::env::set_var("AI_GATEWAY_TEST_TEAM", "team-123");
+    }
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing provider requests
+    config.helicone.features = HeliconeFeatures::None;
+    let openai = config
+        .providers
+        .get_mut(&InferenceProvider::OpenAI)
+        .unwrap();
+    openai.headers.insert(
+        "x-team-id".to_string(),
+        "${AI_GATEWAY_TEST_TEAM}".to_string(),
+    );
+    openai
+        .headers
+        .insert("authorization".to_string(), "Bearer clobbered".to_string());
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    // SAFETY: see above
+    unsafe {
+        std::env::remove_var("AI_GATEWAY_TEST_TEAM");
+    }
+    let openai_mock = &harness.mock.openai_mock.http_server;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("x-team-id", "team-123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": 1_741_569_952,
+            "model": "gpt-4o-mini",
+            "choices": [{
+                "index": 0,
+                "finish_reason": "stop",
+                "message": { "role": "assistant", "content": "Hello!" }
+            }]
+        })))
+        .with_priority(1)
+        .expect(1)
+        .mount(openai_mock)
+        .await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let received = harness
+        .mock
+        .openai_mock
+        .http_server
+        .received_requests()
+        .await
+        .unwrap();
+    let authorization = received[0].headers.get("authorization").unwrap();
+    assert_ne!(authorization, "Bearer clobbered");
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::{BalanceConfig, BalanceConfigInner, WeightedProvider},
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    endpoints::EndpointType,
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use nonempty_collections::nes;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tower::Service;
+
+fn chat_completion_request(model: &str) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+/// Requests to a provider without a configured key should be rejected before
+/// being dispatched when `require_provider_keys` is enabled.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn keyless_provider_is_rejected() {
+    // SAFETY: This must only be called within the single threaded tokio
+    // runtime in tests
+    unsafe {
+        std::env::remove_var("MISTRAL_API_KEY");
+    }
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.dispatcher.require_provider_keys = true;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::mistral(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:mistral:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request = chat_completion_request("mistral/mistral-large-latest");
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body =
+        serde_json::from_slice::<async_openai::error::WrappedError>(&body)
+            .expect("error should be in the openai error format");
+    assert_eq!(
+        body.error.code,
+        Some("provider_key_not_configured".to_string())
+    );
+    assert!(body.error.message.contains("mistral"));
+}
+
+/// Providers with a configured key are unaffected by `require_provider_keys`.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn provider_with_key_is_admitted() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.dispatcher.require_provider_keys = true;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request = chat_completion_request("openai/gpt-4o-mini");
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// Keyless providers are left out of the balancer when `require_provider_keys`
+/// is enabled, so every request goes to the provider with a key rather than
+/// some of them being rejected.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn keyless_provider_is_not_balanced_to() {
+    // SAFETY: This must only be called within the single threaded tokio
+    // runtime in tests
+    unsafe {
+        std::env::remove_var("MISTRAL_API_KEY");
+    }
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.dispatcher.require_provider_keys = true;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::from(HashMap::from([(
+                EndpointType::Chat,
+                BalanceConfigInner::ProviderWeighted {
+                    providers: nes![
+                        WeightedProvider {
+                            provider: InferenceProvider::OpenAI,
+                            weight: Decimal::try_from(0.50).unwrap(),
+                        },
+                        WeightedProvider {
+                            provider: InferenceProvider::Named(
+                                "mistral".into()
+                            ),
+                            weight: Decimal::try_from(0.50).unwrap(),
+                        },
+                    ],
+                },
+            )])),
+            ..Default::default()
+        },
+    )]));
+    let num_requests = 10;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", num_requests.into()),
+            ("success:mistral:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    for _ in 0..num_requests {
+        let request = chat_completion_request("openai/gpt-4o-mini");
+        let response = harness.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let _body = response.into_body().collect().await.unwrap();
+    }
+
+    harness.mock.verify().await;
+}
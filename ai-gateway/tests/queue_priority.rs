@@ -0,0 +1,139 @@
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        queue::{ModelTier, QueueConfig},
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{model_id::ModelId, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+use tokio::time::Instant;
+use tower::Service;
+
+const PREMIUM_MODEL: &str = "openai/gpt-4o";
+const FREE_MODEL: &str = "openai/gpt-4o-mini";
+
+async fn harness() -> Harness {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            queue: Some(QueueConfig {
+                tiers: HashMap::from([
+                    (
+                        ModelId::from_str(PREMIUM_MODEL).unwrap(),
+                        ModelTier::Premium,
+                    ),
+                    (ModelId::from_str(FREE_MODEL).unwrap(), ModelTier::Free),
+                ]),
+                ..QueueConfig::test_default()
+            }),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    // a slow provider, so that queued requests pile up
+    let stub = std::fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/stubs/openai/chat_completion.json"
+    ))
+    .unwrap();
+    let stub: serde_json::Value = serde_json::from_slice(&stub).unwrap();
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_delay(Duration::from_millis(300))
+                .set_body_json(stub["response"]["jsonBody"].clone()),
+        )
+        .with_priority(1)
+        .expect(3)
+        .mount(&harness.mock.openai_mock.http_server)
+        .await;
+    harness
+}
+
+fn chat_request(model: &str) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .header("content-type", "application/json")
+        .body(request_body)
+        .unwrap()
+}
+
+/// Sends a request in the background, returning when it completed.
+fn spawn_request(
+    harness: &mut Harness,
+    model: &str,
+) -> tokio::task::JoinHandle<Instant> {
+    let response = harness.call(chat_request(model));
+    tokio::spawn(async move {
+        let response = response.await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let _body = response.into_body().collect().await.unwrap();
+        Instant::now()
+    })
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn premium_requests_are_dispatched_before_free_requests() {
+    let mut harness = harness().await;
+
+    // occupies the only slot, so that the next requests are queued
+    let running = spawn_request(&mut harness, FREE_MODEL);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let free = spawn_request(&mut harness, FREE_MODEL);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let premium = spawn_request(&mut harness, PREMIUM_MODEL);
+
+    let running = running.await.unwrap();
+    let free = free.await.unwrap();
+    let premium = premium.await.unwrap();
+    assert!(running < premium, "the running request finishes first");
+    assert!(
+        premium < free,
+        "the premium request is dispatched ahead of the earlier free request"
+    );
+
+    harness.mock.verify().await;
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+use tower::Service;
+
+async fn harness() -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing response mapping
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+/// Mounts an Anthropic response with thinking content, which is returned
+/// whether or not the client asked for it.
+async fn mount_thinking_response(harness: &Harness, stream: bool) {
+    let template = if stream {
+        let events = [
+            json!({
+                "type": "content_block_start",
+                "index": 0,
+                "content_block": {
+                    "type": "thinking",
+                    "thinking": "",
+                    "signature": ""
+                }
+            }),
+            json!({
+                "type": "content_block_delta",
+                "index": 0,
+                "delta": { "type": "thinking_delta", "thinking": "2 + 2 is 4" }
+            }),
+            json!({ "type": "content_block_stop", "index": 0 }),
+            json!({
+                "type": "content_block_delta",
+                "index": 1,
+                "delta": { "type": "text_delta", "text": "4" }
+            }),
+            json!({ "type": "message_stop" }),
+        ];
+        let body = events
+            .iter()
+            .map(|event| {
+                let kind = event["type"].as_str().unwrap();
+                format!("event: {kind}\ndata: {event}\n\n")
+            })
+            .collect::<String>();
+        ResponseTemplate::new(200)
+            .insert_header("content-type", "text/event-stream")
+            .set_body_string(body)
+    } else {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_123",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-3-7-sonnet-20250219",
+            "content": [
+                { "type": "thinking", "thinking": "2 + 2 is 4", "signature": "sig" },
+                { "type": "text", "text": "4" }
+            ],
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": { "input_tokens": 12, "output_tokens": 20 }
+        }))
+    };
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(template)
+        .with_priority(1)
+        .expect(1)
+        .mount(&harness.mock.anthropic_mock.http_server)
+        .await;
+}
+
+/// Sends a chat completion request and returns the `message`, or for streams
+/// the `delta`s, of its first choice.
+async fn first_choice(
+    harness: &mut Harness,
+    stream: bool,
+    reasoning_effort: Option<&str>,
+) -> Vec<Value> {
+    let mut body = json!({
+        "model": "anthropic/claude-3-7-sonnet-latest",
+        "max_tokens": 4096,
+        "messages": [
+            {
+                "role": "user",
+                "content": "What is 2 + 2?"
+            }
+        ],
+        "stream": stream
+    });
+    if let Some(reasoning_effort) = reasoning_effort {
+        body["reasoning_effort"] = json!(reasoning_effort);
+    }
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(axum_core::body::Body::from(
+            serde_json::to_vec(&body).unwrap(),
+        ))
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    if !stream {
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        return vec![body["choices"][0]["message"].clone()];
+    }
+    String::from_utf8_lossy(&body)
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter_map(|data| serde_json::from_str::<Value>(data).ok())
+        .map(|chunk| chunk["choices"][0]["delta"].clone())
+        .collect()
+}
+
+fn reasoning(messages: &[Value]) -> Option<String> {
+    let reasoning = messages
+        .iter()
+        .filter_map(|message| message.get("reasoning_content"))
+        .map(|reasoning| reasoning.as_str().unwrap())
+        .collect::<Vec<_>>();
+    (!reasoning.is_empty()).then(|| reasoning.concat())
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn reasoning_is_surfaced_when_requested() {
+    let mut harness = harness().await;
+    mount_thinking_response(&harness, false).await;
+    let messages = first_choice(&mut harness, false, Some("low")).await;
+    assert_eq!(reasoning(&messages).as_deref(), Some("2 + 2 is 4"));
+    assert_eq!(messages[0]["content"], "4");
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn reasoning_is_omitted_when_not_requested() {
+    let mut harness = harness().await;
+    mount_thinking_response(&harness, false).await;
+    let messages = first_choice(&mut harness, false, None).await;
+    assert_eq!(reasoning(&messages), None);
+    assert_eq!(messages[0]["content"], "4");
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn streamed_reasoning_is_surfaced_when_requested() {
+    let mut harness = harness().await;
+    mount_thinking_response(&harness, true).await;
+    let deltas = first_choice(&mut harness, true, Some("low")).await;
+    assert_eq!(reasoning(&deltas).as_deref(), Some("2 + 2 is 4"));
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn streamed_reasoning_is_omitted_when_not_requested() {
+    let mut harness = harness().await;
+    mount_thinking_response(&harness, true).await;
+    let deltas = first_choice(&mut harness, true, None).await;
+    assert_eq!(reasoning(&deltas), None);
+    let content = deltas
+        .iter()
+        .filter_map(|delta| delta["content"].as_str())
+        .collect::<String>();
+    assert_eq!(content, "4");
+}
@@ -322,6 +322,11 @@ async fn cache_enabled_per_router() {
                     directive: None,
                     buckets: 1,
                     seed: Some("router-cached-seed".to_string()),
+                    cache_streams: false,
+                    share_across_tenants: false,
+                    tenant_header: None,
+                    semantic: None,
+                    error_responses: None,
                 }),
                 load_balance:
                     ai_gateway::config::balance::BalanceConfig::openai_chat(),
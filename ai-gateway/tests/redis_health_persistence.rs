@@ -0,0 +1,159 @@
+use std::{collections::HashMap, time::Duration};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::{BalanceConfig, BalanceConfigInner, WeightedProvider},
+        helicone::HeliconeFeatures,
+        monitor::{
+            GracePeriod, HealthMonitorConfig, HealthPersistenceConfig,
+            ProbeConfig,
+        },
+        redis::RedisConfig,
+        router::{RouterConfig, RouterConfigs},
+    },
+    discover::monitor::health::{HealthMonitor, HealthProbe},
+    endpoints::EndpointType,
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId, secret::Secret},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use nonempty_collections::nes;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tower::Service;
+
+const REDIS_URL: &str = "redis://localhost:6340";
+
+fn config() -> Config {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.discover.monitor.health = HealthMonitorConfig::ErrorRatio {
+        ratio: Decimal::try_from(0.10).unwrap(),
+        window: Duration::from_secs(10),
+        buckets: 10,
+        interval: Duration::from_millis(1),
+        grace_period: GracePeriod::Requests { min_requests: 10 },
+    };
+    config.discover.monitor.persistence = Some(HealthPersistenceConfig {
+        redis: RedisConfig {
+            host_url: Secret::from(REDIS_URL.parse::<url::Url>().unwrap()),
+            connection_timeout: Duration::from_secs(10),
+        },
+        ttl: Duration::from_secs(60),
+    });
+    let balance_config = BalanceConfig::from(HashMap::from([(
+        EndpointType::Chat,
+        BalanceConfigInner::ProviderWeighted {
+            providers: nes![
+                WeightedProvider {
+                    provider: InferenceProvider::OpenAI,
+                    weight: Decimal::try_from(0.50).unwrap(),
+                },
+                WeightedProvider {
+                    provider: InferenceProvider::Anthropic,
+                    weight: Decimal::try_from(0.50).unwrap(),
+                },
+            ],
+        },
+    )]));
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: balance_config,
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+/// A gateway that starts after another one found a provider unhealthy
+/// shouldn't send traffic to it, even though it hasn't seen any errors from
+/// the provider itself.
+#[tokio::test]
+#[serial_test::serial]
+async fn restarted_gateway_respects_persisted_unhealthy_provider() {
+    // the first gateway finds anthropic unhealthy through its probes
+    let num_probes = 12;
+    let mut first_config = config();
+    first_config.discover.monitor.probe = Some(ProbeConfig {
+        providers: vec![InferenceProvider::Anthropic],
+        ..Default::default()
+    });
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("error:anthropic:messages", num_probes.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let harness = Harness::builder()
+        .with_config(first_config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let health_probe = HealthProbe::new(harness.app_factory.state.clone())
+        .await
+        .unwrap()
+        .expect("probing is enabled");
+    for _ in 0..num_probes {
+        health_probe.probe_all().await;
+    }
+    let health_monitor = HealthMonitor::new(harness.app_factory.state.clone());
+    let first_monitor = tokio::spawn(async move {
+        health_monitor.run_forever().await.unwrap();
+    });
+    // give the health monitor time to persist the unhealthy provider
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    first_monitor.abort();
+    harness.mock.verify().await;
+    drop(harness);
+
+    // the second gateway starts without any metrics of its own
+    let num_requests = 20;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", num_requests.into()),
+            ("error:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let health_monitor = HealthMonitor::new(harness.app_factory.state.clone());
+    tokio::spawn(async move {
+        health_monitor.run_forever().await.unwrap();
+    });
+    // give the health monitor time to remove the provider
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let body_bytes = serde_json::to_vec(&json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ]
+    }))
+    .unwrap();
+    for _ in 0..num_requests {
+        let request_body = axum_core::body::Body::from(body_bytes.clone());
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("http://router.helicone.com/router/my-router/chat/completions")
+            .body(request_body)
+            .unwrap();
+        let response = harness.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+
+    // mocks are verified on drop
+}
@@ -25,6 +25,9 @@ fn create_test_limits(capacity: u32, duration_ms: u64) -> LimitsConfig {
             capacity: capacity.try_into().unwrap(),
             refill_frequency: Duration::from_millis(duration_ms),
         },
+        per_body_field: None,
+        per_model: HashMap::new(),
+        token_limit: None,
     }
 }
 
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{Config, helicone::HeliconeFeatures},
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+use uuid::Uuid;
+
+const HELICONE_REQUEST_ID_HEADER: &str = "x-helicone-request-id";
+
+fn chat_completion_request() -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+fn request_id<B>(response: &http::Response<B>) -> Uuid {
+    let header = response
+        .headers()
+        .get(HELICONE_REQUEST_ID_HEADER)
+        .expect("x-helicone-request-id should be set on every response");
+    Uuid::parse_str(header.to_str().unwrap())
+        .expect("x-helicone-request-id should be a uuid")
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn request_id_on_success() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_completion_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let request_id = request_id(&response);
+    // the upstream attempt is logged under its own id
+    let helicone_id = response
+        .headers()
+        .get("helicone-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| Uuid::parse_str(value).ok())
+        .expect("helicone-id should be set on proxied responses");
+    assert_ne!(helicone_id, request_id);
+    let _response_body = response.into_body().collect().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn request_id_on_auth_failure() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::Auth;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    // missing authorization header
+    let response = harness.call(chat_completion_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    request_id(&response);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn request_id_on_internal_error() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("internal_error:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_completion_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    request_id(&response);
+    let _response_body = response.into_body().collect().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn request_ids_are_unique() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::Auth;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let first = harness.call(chat_completion_request()).await.unwrap();
+    let second = harness.call(chat_completion_request()).await.unwrap();
+    assert_ne!(request_id(&first), request_id(&second));
+}
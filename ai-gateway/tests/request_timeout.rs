@@ -0,0 +1,178 @@
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{ModelTimeout, RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{model_id::ModelId, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+use tower::Service;
+
+async fn harness(
+    request_timeout: Duration,
+    model_timeouts: HashMap<ModelId, ModelTimeout>,
+) -> Harness {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            request_timeout: Some(request_timeout),
+            model_timeouts,
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+/// A provider that responds after `delay`.
+async fn mock_slow_provider(harness: &Harness, delay: Duration) {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_delay(delay)
+                .set_body_json(json!({
+                    "id": "chatcmpl-123",
+                    "object": "chat.completion",
+                    "created": 1_741_569_952,
+                    "model": "o3",
+                    "choices": [
+                        {
+                            "index": 0,
+                            "message": {
+                                "role": "assistant",
+                                "content": "Hello! How can I assist you today?"
+                            },
+                            "finish_reason": "stop"
+                        }
+                    ],
+                    "usage": {
+                        "prompt_tokens": 19,
+                        "completion_tokens": 10,
+                        "total_tokens": 29
+                    }
+                })),
+        )
+        .with_priority(1)
+        .mount(&harness.mock.openai_mock.http_server)
+        .await;
+}
+
+fn chat_request(model: &str) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .header("content-type", "application/json")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn slow_provider_times_out_with_gateway_timeout() {
+    let mut harness = harness(Duration::from_millis(100), HashMap::new()).await;
+    // a provider that takes longer to respond than the router allows
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_delay(Duration::from_secs(2))
+                .set_body_json(json!({})),
+        )
+        .with_priority(1)
+        .mount(&harness.mock.openai_mock.http_server)
+        .await;
+
+    let response = harness
+        .call(chat_request("openai/gpt-4o-mini"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["type"], "server_error");
+
+    harness.mock.verify().await;
+}
+
+fn reasoning_model_timeouts() -> HashMap<ModelId, ModelTimeout> {
+    HashMap::from([(
+        ModelId::from_str("openai/o3").unwrap(),
+        ModelTimeout {
+            request: Duration::from_secs(5),
+            stream: None,
+        },
+    )])
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn reasoning_model_gets_its_longer_timeout() {
+    let mut harness =
+        harness(Duration::from_millis(100), reasoning_model_timeouts()).await;
+    mock_slow_provider(&harness, Duration::from_millis(300)).await;
+
+    let response = harness.call(chat_request("openai/o3")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["choices"][0]["message"]["content"],
+        "Hello! How can I assist you today?"
+    );
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn fast_model_uses_the_router_timeout() {
+    let mut harness =
+        harness(Duration::from_millis(100), reasoning_model_timeouts()).await;
+    mock_slow_provider(&harness, Duration::from_millis(300)).await;
+
+    let response = harness
+        .call(chat_request("openai/gpt-4o-mini"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+    harness.mock.verify().await;
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+fn test_config() -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing header enforcement
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            required_headers: vec!["x-tenant-id".to_string()],
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn chat_request(tenant: Option<&str>) -> Request<axum_core::body::Body> {
+    let body = serde_json::to_vec(&json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [{ "role": "user", "content": "Hello, world!" }]
+    }))
+    .unwrap();
+    let mut builder = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions");
+    if let Some(tenant) = tenant {
+        builder = builder.header("x-tenant-id", tenant);
+    }
+    builder.body(axum_core::body::Body::from(body)).unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn missing_required_header_is_rejected() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_request(None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["error"]["message"],
+        "Missing required header: x-tenant-id"
+    );
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn request_with_required_header_is_dispatched() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_request(Some("acme"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    harness.mock.verify().await;
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        mapper::RequiredToolChoice,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+fn config(required_tool_choice: RequiredToolChoice) -> Config {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.mapper.required_tool_choice = required_tool_choice;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn tool_request(tool_choice: &str) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "What's the weather in San Francisco?"
+                }
+            ],
+            "tools": [
+                {
+                    "type": "function",
+                    "function": {
+                        "name": "get_weather",
+                        "parameters": {
+                            "type": "object",
+                            "properties": {
+                                "location": { "type": "string" }
+                            }
+                        }
+                    }
+                }
+            ],
+            "tool_choice": tool_choice
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+/// The `success:openai:chat_completion` stub never calls a tool, so it
+/// behaves like a provider that ignores `tool_choice: required`.
+async fn harness(
+    required_tool_choice: RequiredToolChoice,
+    expected_provider_calls: u64,
+) -> Harness {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            (
+                "success:openai:chat_completion",
+                expected_provider_calls.into(),
+            ),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config(required_tool_choice))
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn missing_tool_call_passed_through_by_default() {
+    let mut harness = harness(RequiredToolChoice::Passthrough, 1).await;
+    let response = harness.call(tool_request("required")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn missing_tool_call_is_an_error() {
+    let mut harness = harness(RequiredToolChoice::Error, 1).await;
+    let response = harness.call(tool_request("required")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("did not call a tool")),
+        "unexpected error: {body}"
+    );
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn missing_tool_call_is_retried_once_then_errors() {
+    // the original request plus a single retry
+    let mut harness = harness(RequiredToolChoice::Retry, 2).await;
+    let response = harness.call(tool_request("required")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    let _body = response.into_body().collect().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn auto_tool_choice_is_not_enforced() {
+    let mut harness = harness(RequiredToolChoice::Error, 1).await;
+    let response = harness.call(tool_request("auto")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use bytes::Bytes;
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+fn config(load_balance: BalanceConfig, pretty_print_responses: bool) -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing response mapping
+    config.helicone.features = HeliconeFeatures::None;
+    config.mapper.pretty_print_responses = pretty_print_responses;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance,
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn chat_request(model: &str) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+async fn anthropic_response_body(pretty_print_responses: bool) -> Bytes {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(
+            BalanceConfig::anthropic_chat(),
+            pretty_print_responses,
+        ))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let response = harness
+        .call(chat_request("anthropic/claude-3-5-sonnet-latest"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.into_body().collect().await.unwrap().to_bytes()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn responses_are_minified_by_default() {
+    let body = anthropic_response_body(false).await;
+    assert!(
+        !body.contains(&b'\n'),
+        "expected a minified body, got: {}",
+        String::from_utf8_lossy(&body)
+    );
+    serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn responses_are_pretty_printed_when_enabled() {
+    let minified = anthropic_response_body(false).await;
+    let pretty = anthropic_response_body(true).await;
+    assert!(
+        pretty.starts_with(b"{\n  \""),
+        "expected a pretty-printed body, got: {}",
+        String::from_utf8_lossy(&pretty)
+    );
+    let minified: serde_json::Value =
+        serde_json::from_slice(&minified).unwrap();
+    let pretty: serde_json::Value = serde_json::from_slice(&pretty).unwrap();
+    assert_eq!(minified, pretty);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn passthrough_response_is_not_modified() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(BalanceConfig::openai_chat(), false))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let response = harness
+        .call(chat_request("openai/gpt-4o-mini"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    let stub = std::fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/stubs/openai/chat_completion.json"
+    ))
+    .unwrap();
+    let stub: serde_json::Value = serde_json::from_slice(&stub).unwrap();
+    assert_eq!(body, stub["response"]["jsonBody"]);
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        mapper::ResponseSchemaValidation,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+fn config(response_schema_validation: ResponseSchemaValidation) -> Config {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.mapper.response_schema_validation = response_schema_validation;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn json_schema_request() -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "What's the weather in San Francisco?"
+                }
+            ],
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": {
+                    "name": "weather",
+                    "strict": true,
+                    "schema": {
+                        "type": "object",
+                        "properties": {
+                            "temperature": { "type": "number" }
+                        },
+                        "required": ["temperature"],
+                        "additionalProperties": false
+                    }
+                }
+            }
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+/// The `success:openai:chat_completion` stub always responds with plain
+/// text, so it behaves like a provider that doesn't honor the schema.
+async fn harness(
+    response_schema_validation: ResponseSchemaValidation,
+    expected_provider_calls: u64,
+) -> Harness {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            (
+                "success:openai:chat_completion",
+                expected_provider_calls.into(),
+            ),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config(response_schema_validation))
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn schema_violation_passed_through_by_default() {
+    let mut harness = harness(ResponseSchemaValidation::Passthrough, 1).await;
+    let response = harness.call(json_schema_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn schema_violation_is_an_error() {
+    let mut harness = harness(ResponseSchemaValidation::Error, 1).await;
+    let response = harness.call(json_schema_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(
+        body["error"]["message"].as_str().is_some_and(|message| {
+            message.contains("does not match the requested JSON schema")
+        }),
+        "unexpected error: {body}"
+    );
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn schema_violation_is_retried_once_then_errors() {
+    // the original request plus a single retry
+    let mut harness = harness(ResponseSchemaValidation::Retry, 2).await;
+    let response = harness.call(json_schema_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    let _body = response.into_body().collect().await.unwrap();
+    harness.mock.verify().await;
+}
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use ai_gateway::{
     config::{
@@ -15,6 +18,10 @@ use compact_str::CompactString;
 use http::{Method, Request, StatusCode};
 use http_body_util::BodyExt;
 use serde_json::json;
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
 use tower::Service;
 
 #[tokio::test]
@@ -134,3 +141,241 @@ async fn router() {
     // sleep so that the background task for logging can complete
     tokio::time::sleep(std::time::Duration::from_millis(10)).await;
 }
+
+/// Sends a chat request to a router that retries, where every attempt fails,
+/// and returns the `Idempotency-Key` header of each attempt.
+async fn idempotency_keys_sent(client_key: Option<&str>) -> Vec<String> {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            retries: Some(RetryConfig::test_default()),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("internal_error:openai:chat_completion", 3.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .header("content-type", "application/json");
+    if let Some(client_key) = client_key {
+        request = request.header("idempotency-key", client_key);
+    }
+    let request = request.body(request_body).unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let _response_body = response.into_body().collect().await.unwrap();
+    harness.mock.verify().await;
+
+    let sent = harness
+        .mock
+        .openai_mock
+        .http_server
+        .received_requests()
+        .await
+        .expect("request recording is enabled");
+    sent.iter()
+        .map(|request| {
+            request
+                .headers
+                .get("idempotency-key")
+                .expect("idempotency key is sent to openai")
+                .to_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn retries_reuse_idempotency_key() {
+    let keys = idempotency_keys_sent(None).await;
+    // the first attempt and two retries
+    assert_eq!(keys.len(), 3);
+    assert!(!keys[0].is_empty());
+    assert!(keys.iter().all(|key| *key == keys[0]));
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn client_idempotency_key_is_forwarded() {
+    let keys = idempotency_keys_sent(Some("client-key")).await;
+    assert_eq!(keys.len(), 3);
+    assert!(keys.iter().all(|key| key == "client-key"));
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn rate_limited_requests_are_retried_after_retry_after() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            retries: Some(RetryConfig::test_default()),
+            retry_status_codes: vec![429],
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    // the first two attempts are rate limited, ahead of the success stub
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("retry-after", "1")
+                .set_body_json(json!({
+                    "error": {
+                        "message": "Rate limit reached for requests.",
+                        "type": "rate_limit_error",
+                        "param": null,
+                        "code": "rate_limit_exceeded"
+                    }
+                })),
+        )
+        .up_to_n_times(2)
+        .expect(2)
+        .with_priority(1)
+        .mount(&harness.mock.openai_mock.http_server)
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .header("content-type", "application/json")
+        .body(request_body)
+        .unwrap();
+    let start = Instant::now();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+    // each retry waits for the `Retry-After` rather than the 5ms delay
+    assert!(start.elapsed() >= Duration::from_secs(2));
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn retry_after_beyond_max_delay_is_not_waited_for() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            retries: Some(RetryConfig::test_default()),
+            retry_status_codes: vec![429],
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    // an hour is far beyond the max delay of any retry
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("retry-after", "3600")
+                .set_body_json(json!({
+                    "error": {
+                        "message": "Rate limit reached for requests.",
+                        "type": "rate_limit_error",
+                        "param": null,
+                        "code": "rate_limit_exceeded"
+                    }
+                })),
+        )
+        .expect(1)
+        .with_priority(1)
+        .mount(&harness.mock.openai_mock.http_server)
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .header("content-type", "application/json")
+        .body(request_body)
+        .unwrap();
+    let start = Instant::now();
+    let response = harness.call(request).await.unwrap();
+    // the rate limited response is returned rather than waited out
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let _response_body = response.into_body().collect().await.unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    harness.mock.verify().await;
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        mapper::RoleAlternation,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use tower::Service;
+
+async fn harness(role_alternation: RoleAlternation) -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing request mapping
+    config.helicone.features = HeliconeFeatures::None;
+    config.mapper.role_alternation = role_alternation;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+fn consecutive_user_messages() -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "anthropic/claude-3-5-sonnet-latest",
+            "messages": [
+                { "role": "system", "content": "Be brief." },
+                { "role": "user", "content": "Hello, world!" },
+                { "role": "user", "content": "How are you?" }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+/// Sends the request and returns the messages the provider received.
+async fn sent_messages(harness: &mut Harness) -> Value {
+    let response = harness.call(consecutive_user_messages()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+    let received = harness
+        .mock
+        .anthropic_mock
+        .http_server
+        .received_requests()
+        .await
+        .expect("request recording is enabled");
+    let sent: Value = serde_json::from_slice(&received[0].body).unwrap();
+    harness.mock.verify().await;
+    sent["messages"].clone()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn consecutive_user_messages_are_merged() {
+    let mut harness = harness(RoleAlternation::Merge).await;
+    let messages = sent_messages(&mut harness).await;
+    assert_eq!(
+        messages,
+        json!([{
+            "role": "user",
+            "content": [
+                { "type": "text", "text": "Hello, world!" },
+                { "type": "text", "text": "How are you?" }
+            ]
+        }])
+    );
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn placeholder_is_inserted_between_user_messages() {
+    let mut harness = harness(RoleAlternation::Insert).await;
+    let messages = sent_messages(&mut harness).await;
+    let roles = messages
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["role"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(roles, ["user", "assistant", "user"]);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn consecutive_messages_are_sent_as_is_by_default() {
+    let mut harness = harness(RoleAlternation::Passthrough).await;
+    let messages = sent_messages(&mut harness).await;
+    assert_eq!(messages.as_array().unwrap().len(), 2);
+}
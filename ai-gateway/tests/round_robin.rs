@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::{BalanceConfig, BalanceConfigInner},
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    endpoints::EndpointType,
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use nonempty_collections::nev;
+use serde_json::json;
+use tower::Service;
+
+fn round_robin_config() -> RouterConfigs {
+    router_configs(BalanceConfig::from(HashMap::from([(
+        EndpointType::Chat,
+        BalanceConfigInner::RoundRobin {
+            providers: nev![
+                InferenceProvider::OpenAI,
+                InferenceProvider::Anthropic,
+                InferenceProvider::GoogleGemini,
+            ],
+        },
+    )])))
+}
+
+fn router_configs(balance_config: BalanceConfig) -> RouterConfigs {
+    RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: balance_config,
+            ..Default::default()
+        },
+    )]))
+}
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    let body_bytes = serde_json::to_vec(&json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ]
+    }))
+    .unwrap();
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(axum_core::body::Body::from(body_bytes))
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn sequential_requests_hit_each_provider_in_turn() {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing load balancing behavior
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = round_robin_config();
+    // each provider must be called exactly once
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:anthropic:messages", 1.into()),
+            ("success:gemini:generate_content", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    for _ in 0..3 {
+        let response = harness.call(chat_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn requests_rotate_in_config_order_over_their_endpoint_type() {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing load balancing behavior
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = router_configs(BalanceConfig::from(HashMap::from([
+        (
+            EndpointType::Chat,
+            BalanceConfigInner::RoundRobin {
+                providers: nev![
+                    InferenceProvider::Anthropic,
+                    InferenceProvider::OpenAI,
+                ],
+            },
+        ),
+        (
+            EndpointType::Embeddings,
+            BalanceConfigInner::RoundRobin {
+                providers: nev![InferenceProvider::OpenAI],
+            },
+        ),
+    ])));
+    // the first configured chat provider is called first, and chat requests
+    // never take the turn of the embeddings provider
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 2.into()),
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    for _ in 0..3 {
+        let response = harness.call(chat_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    harness.mock.verify().await;
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+        sampling_bounds::{Bounds, SamplingBoundsConfig},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use tower::Service;
+
+/// Sends a chat request with the given sampling parameters to a router that
+/// caps `temperature` at `1.0` and `top_p` at `0.75`, and returns the body
+/// sent to the provider.
+///
+/// The values are exactly representable as `f32`s, so they survive the
+/// request being deserialized.
+async fn dispatched_body(temperature: f64, top_p: f64) -> Value {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            sampling_bounds: Some(SamplingBoundsConfig {
+                temperature: Some(Bounds {
+                    min: None,
+                    max: Some(Decimal::ONE),
+                }),
+                top_p: Some(Bounds {
+                    min: None,
+                    max: Some(Decimal::new(75, 2)),
+                }),
+            }),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "temperature": temperature,
+            "top_p": top_p,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    let sent = harness
+        .mock
+        .openai_mock
+        .http_server
+        .received_requests()
+        .await
+        .expect("request recording is enabled");
+    harness.mock.verify().await;
+    serde_json::from_slice(&sent[0].body).unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn out_of_range_sampling_params_are_clamped() {
+    let sent = dispatched_body(1.8, 0.99).await;
+    assert_eq!(sent["temperature"], json!(1.0));
+    assert_eq!(sent["top_p"], json!(0.75));
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn in_range_sampling_params_pass_through() {
+    let sent = dispatched_body(0.5, 0.25).await;
+    assert_eq!(sent["temperature"], json!(0.5));
+    assert_eq!(sent["top_p"], json!(0.25));
+}
@@ -0,0 +1,195 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ai_gateway::{
+    config::{
+        Config,
+        cache::{CacheConfig, SemanticCacheConfig},
+        helicone::HeliconeFeatures,
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::model_id::ModelId,
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{body_string_contains, method, path},
+};
+use tower::Service;
+
+async fn harness(chat_completions: u64) -> Harness {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.global.cache = Some(CacheConfig {
+        semantic: Some(SemanticCacheConfig {
+            threshold: Decimal::from_str("0.9").unwrap(),
+            embedding_model: ModelId::from_str("openai/text-embedding-3-small")
+                .unwrap(),
+        }),
+        ..CacheConfig::test_default()
+    });
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            (
+                "success:openai:chat_completion_cacheable",
+                chat_completions.into(),
+            ),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+/// Embeds prompts containing `needle` as `embedding`.
+async fn mock_embedding(
+    harness: &Harness,
+    needle: &str,
+    embedding: [f32; 2],
+    expected_calls: u64,
+) {
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .and(body_string_contains(needle))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": [
+                {
+                    "object": "embedding",
+                    "index": 0,
+                    "embedding": embedding
+                }
+            ],
+            "model": "text-embedding-3-small",
+            "usage": {
+                "prompt_tokens": 8,
+                "total_tokens": 8
+            }
+        })))
+        .with_priority(1)
+        .expect(expected_calls)
+        .mount(&harness.mock.openai_mock.http_server)
+        .await;
+}
+
+fn chat_request(
+    prompt: &str,
+    tools: Option<Value>,
+) -> Request<axum_core::body::Body> {
+    let mut body = json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": prompt
+            }
+        ]
+    });
+    if let Some(tools) = tools {
+        body["tools"] = tools;
+    }
+    let request_body =
+        axum_core::body::Body::from(serde_json::to_vec(&body).unwrap());
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .header("content-type", "application/json")
+        .header("cache-control", "max-age=3600")
+        .body(request_body)
+        .unwrap()
+}
+
+async fn call(harness: &mut Harness, prompt: &str) -> String {
+    call_with_tools(harness, prompt, None).await
+}
+
+async fn call_with_tools(
+    harness: &mut Harness,
+    prompt: &str,
+    tools: Option<Value>,
+) -> String {
+    let response = harness.call(chat_request(prompt, tools)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cache_header = response
+        .headers()
+        .get("helicone-cache")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let _body = response.into_body().collect().await.unwrap();
+    cache_header
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn similar_prompt_is_served_from_cache() {
+    // only the first request reaches the provider
+    let mut harness = harness(1).await;
+    mock_embedding(&harness, "France", [1.0, 0.0], 2).await;
+
+    // the cache is empty, so nothing is similar enough
+    assert_eq!(
+        call(&mut harness, "What is the capital of France?").await,
+        "MISS"
+    );
+    assert_eq!(
+        call(&mut harness, "What's the capital city of France?").await,
+        "HIT"
+    );
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn dissimilar_prompt_falls_through_to_provider() {
+    let mut harness = harness(2).await;
+    mock_embedding(&harness, "France", [1.0, 0.0], 1).await;
+    mock_embedding(&harness, "haiku", [0.0, 1.0], 1).await;
+
+    assert_eq!(
+        call(&mut harness, "What is the capital of France?").await,
+        "MISS"
+    );
+    assert_eq!(
+        call(&mut harness, "Write a haiku about the sea.").await,
+        "MISS"
+    );
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn same_prompt_with_different_tools_misses() {
+    let mut harness = harness(2).await;
+    mock_embedding(&harness, "weather", [1.0, 0.0], 2).await;
+    let tool = |name: &str| {
+        json!([{
+            "type": "function",
+            "function": {
+                "name": name,
+                "parameters": { "type": "object", "properties": {} }
+            }
+        }])
+    };
+
+    let prompt = "What is the weather in Paris?";
+    assert_eq!(
+        call_with_tools(&mut harness, prompt, Some(tool("get_weather"))).await,
+        "MISS"
+    );
+    assert_eq!(
+        call_with_tools(&mut harness, prompt, Some(tool("get_forecast"))).await,
+        "MISS"
+    );
+
+    harness.mock.verify().await;
+}
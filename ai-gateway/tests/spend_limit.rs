@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config, helicone::HeliconeFeatures, spend_limit::SpendLimitConfig,
+    },
+    control_plane::types::{Key, hash_key},
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::org::OrgId,
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+use uuid::Uuid;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn requests_rejected_once_org_spend_limit_reached() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::All;
+    // each stubbed chat completion uses 29 tokens at $0.01 per token, so the
+    // $1 daily limit is reached after 4 requests
+    config.spend_limit = Some(SpendLimitConfig::test_default());
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 5.into()),
+            ("success:minio:upload_request", 5.into()),
+            ("success:jawn:log_request", 5.into()),
+            ("success:jawn:sign_s3_url", 5.into()),
+        ]))
+        .build();
+
+    let org1_auth = "sk-helicone-org1-key";
+    let org2_auth = "sk-helicone-org2-key";
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .with_auth_keys(vec![
+            Key {
+                key_hash: hash_key(org1_auth),
+                owner_id: Uuid::new_v4().into(),
+                organization_id: OrgId::new(Uuid::new_v4()),
+            },
+            Key {
+                key_hash: hash_key(org2_auth),
+                owner_id: Uuid::new_v4().into(),
+                organization_id: OrgId::new(Uuid::new_v4()),
+            },
+        ])
+        .build()
+        .await;
+
+    for i in 1..=4 {
+        let response = make_chat_request(&mut harness, org1_auth).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::OK,
+            "Request {i} should succeed"
+        );
+        let _body = response.into_body().collect().await.unwrap();
+    }
+
+    let response = make_chat_request(&mut harness, org1_auth).await;
+    assert_eq!(
+        response.status(),
+        StatusCode::PAYMENT_REQUIRED,
+        "5th request should exceed the spend limit"
+    );
+    assert!(
+        response.headers().get("retry-after").is_some(),
+        "retry-after header should be present"
+    );
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(
+        message.contains("daily limit"),
+        "unexpected message: {message}"
+    );
+
+    // other orgs are unaffected
+    let response = make_chat_request(&mut harness, org2_auth).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+}
+
+async fn make_chat_request(
+    harness: &mut Harness,
+    api_key: &str,
+) -> http::Response<
+    tower_http::body::UnsyncBoxBody<
+        bytes::Bytes,
+        Box<dyn std::error::Error + Send + Sync + 'static>,
+    >,
+> {
+    let body_bytes = serde_json::to_vec(&json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ]
+    }))
+    .unwrap();
+
+    let request_body = axum_core::body::Body::from(body_bytes);
+    let request = Request::builder()
+        .method(Method::POST)
+        .header("authorization", format!("Bearer {api_key}"))
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap();
+
+    let response = harness.call(request).await.unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    response
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::{BalanceConfig, BalanceConfigInner, WeightedProvider},
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+        stickiness::{ConversationKeyConfig, StickinessConfig},
+    },
+    endpoints::EndpointType,
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use nonempty_collections::nes;
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use tower::Service;
+
+fn test_config() -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing routing behavior
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::from(HashMap::from([(
+                EndpointType::Chat,
+                BalanceConfigInner::ProviderWeighted {
+                    providers: nes![
+                        WeightedProvider {
+                            provider: InferenceProvider::OpenAI,
+                            weight: Decimal::try_from(0.50).unwrap(),
+                        },
+                        WeightedProvider {
+                            provider: InferenceProvider::Anthropic,
+                            weight: Decimal::try_from(0.50).unwrap(),
+                        },
+                    ],
+                },
+            )])),
+            stickiness: Some(StickinessConfig {
+                derive: Some(ConversationKeyConfig::default()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn chat_request(
+    messages: &[Value],
+    conversation_id: Option<&str>,
+) -> Request<axum_core::body::Body> {
+    let body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": messages
+        }))
+        .unwrap(),
+    );
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions");
+    if let Some(conversation_id) = conversation_id {
+        request = request.header("x-helicone-conversation-id", conversation_id);
+    }
+    request.body(body).unwrap()
+}
+
+/// The number of requests each provider received.
+async fn requests_per_provider(harness: &Harness) -> (usize, usize) {
+    let openai = harness
+        .mock
+        .openai_mock
+        .http_server
+        .received_requests()
+        .await
+        .unwrap()
+        .len();
+    let anthropic = harness
+        .mock
+        .anthropic_mock
+        .http_server
+        .received_requests()
+        .await
+        .unwrap()
+        .len();
+    (openai, anthropic)
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn follow_up_turns_stick_to_the_same_provider() {
+    let num_turns = 10;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", (0..).into()),
+            ("success:anthropic:messages", (0..).into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let mut messages = vec![
+        json!({ "role": "system", "content": "You are a helpful assistant." }),
+        json!({ "role": "user", "content": "Hello, world!" }),
+    ];
+    for turn in 0..num_turns {
+        let response =
+            harness.call(chat_request(&messages, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        messages.push(json!({ "role": "assistant", "content": "Hi!" }));
+        messages.push(json!({ "role": "user", "content": format!("#{turn}") }));
+    }
+
+    let (openai, anthropic) = requests_per_provider(&harness).await;
+    assert!(
+        (openai, anthropic) == (num_turns, 0)
+            || (openai, anthropic) == (0, num_turns),
+        "turns were split between providers: openai={openai}, \
+         anthropic={anthropic}"
+    );
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn conversation_id_sticks_to_the_same_provider() {
+    let num_requests = 10;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", (0..).into()),
+            ("success:anthropic:messages", (0..).into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    // the messages differ on every request, so only the header ties them to
+    // one conversation
+    for i in 0..num_requests {
+        let messages =
+            [json!({ "role": "user", "content": format!("Question #{i}") })];
+        let request = chat_request(&messages, Some("conversation-1"));
+        let response = harness.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let (openai, anthropic) = requests_per_provider(&harness).await;
+    assert!(
+        (openai, anthropic) == (num_requests, 0)
+            || (openai, anthropic) == (0, num_requests),
+        "requests were split between providers: openai={openai}, \
+         anthropic={anthropic}"
+    );
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config, helicone::HeliconeFeatures, stream_limit::StreamLimitConfig,
+    },
+    control_plane::types::{Key, hash_key},
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::org::OrgId,
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+use uuid::Uuid;
+
+type TestResponse = http::Response<
+    tower_http::body::UnsyncBoxBody<
+        bytes::Bytes,
+        Box<dyn std::error::Error + Send + Sync + 'static>,
+    >,
+>;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn streams_rejected_once_concurrent_limit_reached() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::All;
+    // at most 2 concurrent streams per org
+    config.stream_limit = Some(StreamLimitConfig::test_default());
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion_stream", 5.into()),
+            // aborted streams may not be logged
+            ("success:minio:upload_request", (0..).into()),
+            ("success:jawn:log_request", (0..).into()),
+            ("success:jawn:sign_s3_url", (0..).into()),
+        ]))
+        .build();
+
+    let org1_auth = "sk-helicone-org1-key";
+    let org2_auth = "sk-helicone-org2-key";
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .with_auth_keys(vec![
+            Key {
+                key_hash: hash_key(org1_auth),
+                owner_id: Uuid::new_v4().into(),
+                organization_id: OrgId::new(Uuid::new_v4()),
+            },
+            Key {
+                key_hash: hash_key(org2_auth),
+                owner_id: Uuid::new_v4().into(),
+                organization_id: OrgId::new(Uuid::new_v4()),
+            },
+        ])
+        .build()
+        .await;
+
+    let first = make_chat_request(&mut harness, org1_auth).await;
+    assert_eq!(first.status(), StatusCode::OK);
+    let second = make_chat_request(&mut harness, org1_auth).await;
+    assert_eq!(second.status(), StatusCode::OK);
+
+    let response = make_chat_request(&mut harness, org1_auth).await;
+    assert_eq!(
+        response.status(),
+        StatusCode::TOO_MANY_REQUESTS,
+        "3rd concurrent stream should exceed the limit"
+    );
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(
+        message.contains("concurrent streams"),
+        "unexpected message: {message}"
+    );
+
+    // other orgs are unaffected
+    let response = make_chat_request(&mut harness, org2_auth).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+
+    // aborting a stream releases its slot
+    drop(first);
+    let third = make_chat_request(&mut harness, org1_auth).await;
+    assert_eq!(third.status(), StatusCode::OK);
+
+    // as does completing one
+    let _body = second.into_body().collect().await.unwrap();
+    let response = make_chat_request(&mut harness, org1_auth).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+    let _body = third.into_body().collect().await.unwrap();
+
+    harness.mock.verify().await;
+}
+
+async fn make_chat_request(
+    harness: &mut Harness,
+    api_key: &str,
+) -> TestResponse {
+    let body_bytes = serde_json::to_vec(&json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ],
+        "stream": true
+    }))
+    .unwrap();
+
+    let request_body = axum_core::body::Body::from(body_bytes);
+    let request = Request::builder()
+        .method(Method::POST)
+        .header("authorization", format!("Bearer {api_key}"))
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap();
+
+    harness.call(request).await.unwrap()
+}
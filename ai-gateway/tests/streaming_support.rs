@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        providers::StreamingSupport,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use stubr::wiremock_rs::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, method, path},
+};
+use tower::Service;
+use url::Url;
+
+fn test_config(streaming: StreamingSupport) -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing response handling
+    config.helicone.features = HeliconeFeatures::None;
+    config
+        .providers
+        .get_mut(&InferenceProvider::OpenAI)
+        .unwrap()
+        .streaming = streaming;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+/// Builds a harness that sends requests for the `groq` provider, which is
+/// mapped with the `OpenAI` compatible converter, to `server`.
+async fn groq_harness(
+    server: &MockServer,
+    streaming: StreamingSupport,
+) -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing response handling
+    config.helicone.features = HeliconeFeatures::None;
+    let groq = config
+        .providers
+        .get_mut(&InferenceProvider::Named("groq".into()))
+        .unwrap();
+    groq.base_url = Url::parse(&format!("{}/openai/", server.uri())).unwrap();
+    groq.streaming = streaming;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::groq(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+const GROQ_CHAT_COMPLETIONS_PATH: &str = "/openai/v1/chat/completions";
+
+fn chat_request(stream: bool) -> Request<axum_core::body::Body> {
+    model_chat_request("openai/gpt-4o-mini", stream)
+}
+
+fn model_chat_request(
+    model: &str,
+    stream: bool,
+) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ],
+            "stream": stream
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn non_streaming_request_to_stream_only_provider_is_aggregated() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion_stream", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config(StreamingSupport::StreamOnly))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let response = harness.call(chat_request(false)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let content_type = response.headers().get(http::header::CONTENT_TYPE);
+    assert_eq!(content_type.unwrap(), "application/json");
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<Value>(&body).unwrap();
+    assert_eq!(body["object"], "chat.completion");
+    assert_eq!(body["choices"][0]["message"]["content"], "Hello!");
+    assert_eq!(body["choices"][0]["finish_reason"], "stop");
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn streaming_request_to_non_stream_only_provider_is_chunked() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(test_config(StreamingSupport::NonStreamOnly))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let response = harness.call(chat_request(true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let content_type = response
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(content_type.starts_with("text/event-stream"));
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8_lossy(&body);
+    assert!(
+        body.contains("chat.completion.chunk"),
+        "unexpected body: {body}"
+    );
+    assert!(
+        body.ends_with("data: [DONE]\n\n"),
+        "unexpected body: {body}"
+    );
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn streaming_request_to_native_streaming_provider_is_streamed() {
+    let server = MockServer::start().await;
+    let chunk = json!({
+        "id": "chatcmpl-groq",
+        "object": "chat.completion.chunk",
+        "created": 1_748_543_700,
+        "model": "llama-3.1-8b-instant",
+        "choices": [{
+            "index": 0,
+            "delta": { "role": "assistant", "content": "Hello!" },
+            "finish_reason": "stop"
+        }]
+    });
+    Mock::given(method("POST"))
+        .and(path(GROQ_CHAT_COMPLETIONS_PATH))
+        .and(body_partial_json(json!({ "stream": true })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            format!("data: {chunk}\n\ndata: [DONE]\n\n"),
+            "text/event-stream",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut harness = groq_harness(&server, StreamingSupport::Both).await;
+
+    let response = harness
+        .call(model_chat_request("groq/llama-3.1-8b-instant", true))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("\"Hello!\""), "unexpected body: {body}");
+    assert!(body.contains("data: [DONE]"), "unexpected body: {body}");
+    server.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn streaming_request_to_non_streaming_provider_is_downgraded() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(GROQ_CHAT_COMPLETIONS_PATH))
+        .and(body_partial_json(json!({ "stream": false })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-groq",
+            "object": "chat.completion",
+            "created": 1_748_543_700,
+            "model": "llama-3.1-8b-instant",
+            "choices": [{
+                "index": 0,
+                "finish_reason": "stop",
+                "message": { "role": "assistant", "content": "Hello!" }
+            }],
+            "usage": {
+                "prompt_tokens": 6,
+                "completion_tokens": 2,
+                "total_tokens": 8
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut harness =
+        groq_harness(&server, StreamingSupport::NonStreamOnly).await;
+
+    let response = harness
+        .call(model_chat_request("groq/llama-3.1-8b-instant", true))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let content_type = response
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(content_type.starts_with("text/event-stream"));
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8_lossy(&body);
+    let events = body
+        .split("\n\n")
+        .filter_map(|event| event.strip_prefix("data: "))
+        .collect::<Vec<_>>();
+    assert_eq!(events.len(), 2, "unexpected body: {body}");
+    let chunk = serde_json::from_str::<Value>(events[0]).unwrap();
+    assert_eq!(chunk["object"], "chat.completion.chunk");
+    assert_eq!(chunk["choices"][0]["delta"]["content"], "Hello!");
+    assert_eq!(chunk["choices"][0]["finish_reason"], "stop");
+    assert_eq!(events[1], "[DONE]");
+    server.verify().await;
+}
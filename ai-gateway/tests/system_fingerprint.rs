@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+fn anthropic_config(synthesize_system_fingerprint: bool) -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing response mapping
+    config.helicone.features = HeliconeFeatures::None;
+    config.mapper.synthesize_system_fingerprint = synthesize_system_fingerprint;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "anthropic/claude-3-5-sonnet-latest",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+async fn system_fingerprint(harness: &mut Harness) -> serde_json::Value {
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    body["system_fingerprint"].clone()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn synthesized_fingerprint_is_present_and_stable() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 2.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(anthropic_config(true))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let first = system_fingerprint(&mut harness).await;
+    let second = system_fingerprint(&mut harness).await;
+    assert!(
+        first.as_str().is_some_and(|fp| fp.starts_with("fp_")),
+        "expected a synthesized fingerprint, got: {first}"
+    );
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn fingerprint_not_synthesized_by_default() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(anthropic_config(false))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let fingerprint = system_fingerprint(&mut harness).await;
+    assert!(fingerprint.is_null());
+}
@@ -0,0 +1,118 @@
+use std::{collections::HashMap, num::NonZeroU32};
+
+use ai_gateway::{
+    config::{
+        Config, helicone::HeliconeFeatures,
+        tool_call_turns::ToolCallTurnsConfig,
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+};
+use http::{Method, Request, StatusCode};
+use serde_json::{Value, json};
+use tower::Service;
+
+async fn harness(expected_chat_completions: u64) -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing request validation
+    config.helicone.features = HeliconeFeatures::None;
+    config.tool_call_turns = Some(ToolCallTurnsConfig {
+        max_turns: NonZeroU32::new(2),
+    });
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            (
+                "success:openai:chat_completion",
+                expected_chat_completions.into(),
+            ),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+/// A conversation in which the assistant called a tool `turns` times.
+fn messages(turns: usize) -> Value {
+    let mut messages = vec![json!({
+        "role": "user",
+        "content": "What's the weather in Paris?"
+    })];
+    for turn in 0..turns {
+        let id = format!("call_{turn}");
+        messages.push(json!({
+            "role": "assistant",
+            "tool_calls": [{
+                "id": id,
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "arguments": "{\"city\":\"Paris\"}"
+                }
+            }]
+        }));
+        messages.push(json!({
+            "role": "tool",
+            "tool_call_id": id,
+            "content": "Sunny"
+        }));
+    }
+    Value::Array(messages)
+}
+
+fn chat_request(messages: Value) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": messages,
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn tool_call_turns_within_limit_are_forwarded() {
+    let mut harness = harness(1).await;
+
+    let response = harness.call(chat_request(messages(2))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn tool_call_turns_over_limit_are_rejected() {
+    let mut harness = harness(0).await;
+
+    let response = harness.call(chat_request(messages(3))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn tool_call_turns_over_limit_are_rejected_for_unified_api() {
+    let mut harness = harness(0).await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": messages(5),
+        }))
+        .unwrap(),
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/ai/chat/completions")
+        .body(request_body)
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
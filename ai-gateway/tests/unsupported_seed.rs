@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        mapper::UnsupportedSeed,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use serde_json::json;
+use tower::Service;
+
+const NONDETERMINISTIC_HEADER: &str = "helicone-nondeterministic";
+
+async fn harness(
+    unsupported_seed: UnsupportedSeed,
+    load_balance: BalanceConfig,
+    stubs: HashMap<&'static str, u64>,
+) -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing request mapping
+    config.helicone.features = HeliconeFeatures::None;
+    config.mapper.unsupported_seed = unsupported_seed;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance,
+            ..Default::default()
+        },
+    )]));
+    let mut stubs = stubs
+        .into_iter()
+        .map(|(stub, times)| (stub, times.into()))
+        .collect::<HashMap<_, _>>();
+    stubs.insert("success:minio:upload_request", 0.into());
+    stubs.insert("success:jawn:log_request", 0.into());
+    let mock_args = MockArgs::builder().stubs(stubs).build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+fn seeded_request(model: &str) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "seed": 42,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn seed_is_dropped_silently_by_default() {
+    let mut harness = harness(
+        UnsupportedSeed::Passthrough,
+        BalanceConfig::anthropic_chat(),
+        HashMap::from([("success:anthropic:messages", 1)]),
+    )
+    .await;
+
+    let response = harness
+        .call(seeded_request("anthropic/claude-3-5-sonnet-latest"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key(NONDETERMINISTIC_HEADER));
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn seed_is_rejected_when_configured() {
+    let mut harness = harness(
+        UnsupportedSeed::Reject,
+        BalanceConfig::anthropic_chat(),
+        HashMap::from([("success:anthropic:messages", 0)]),
+    )
+    .await;
+
+    let response = harness
+        .call(seeded_request("anthropic/claude-3-5-sonnet-latest"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn response_is_marked_nondeterministic_when_configured() {
+    let mut harness = harness(
+        UnsupportedSeed::Header,
+        BalanceConfig::anthropic_chat(),
+        HashMap::from([("success:anthropic:messages", 1)]),
+    )
+    .await;
+
+    let response = harness
+        .call(seeded_request("anthropic/claude-3-5-sonnet-latest"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(NONDETERMINISTIC_HEADER).unwrap(),
+        "true"
+    );
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn seed_supported_by_provider_is_unaffected() {
+    let mut harness = harness(
+        UnsupportedSeed::Reject,
+        BalanceConfig::openai_chat(),
+        HashMap::from([("success:openai:chat_completion", 1)]),
+    )
+    .await;
+
+    let response = harness
+        .call(seeded_request("openai/gpt-4o-mini"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key(NONDETERMINISTIC_HEADER));
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use stubr::wiremock_rs::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+use tower::Service;
+
+async fn harness(normalize_usage: bool) -> Harness {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.mapper.normalize_usage = normalize_usage;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::anthropic_chat(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_123",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-3-5-sonnet-20241022",
+            "content": [{ "type": "text", "text": "Hello!" }],
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {
+                "input_tokens": 12,
+                "output_tokens": 20,
+                "cache_read_input_tokens": 1024,
+                "cache_creation_input_tokens": 256
+            }
+        })))
+        .with_priority(1)
+        .expect(1)
+        .mount(&harness.mock.anthropic_mock.http_server)
+        .await;
+    harness
+}
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "anthropic/claude-3-5-sonnet-latest",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+async fn response_usage(normalize_usage: bool) -> Value {
+    let mut harness = harness(normalize_usage).await;
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    harness.mock.verify().await;
+    body["usage"].clone()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn anthropic_cache_tokens_are_normalized() {
+    let usage = response_usage(true).await;
+    assert_eq!(usage["prompt_tokens"], 12 + 1024 + 256);
+    assert_eq!(usage["completion_tokens"], 20);
+    assert_eq!(usage["total_tokens"], 12 + 1024 + 256 + 20);
+    assert_eq!(usage["prompt_tokens_details"]["cached_tokens"], 1024);
+    assert_eq!(usage["prompt_tokens_details"]["cache_creation_tokens"], 256);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn usage_is_not_normalized_unless_enabled() {
+    let usage = response_usage(false).await;
+    assert_eq!(usage["prompt_tokens"], 12);
+    assert_eq!(usage["total_tokens"], 32);
+    assert!(usage["prompt_tokens_details"].is_null());
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use stubr::wiremock_rs::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_string_contains, header, method, path},
+};
+use tower::Service;
+use url::Url;
+
+const PRIVATE_KEY: &str =
+    include_str!("fixtures/vertex_service_account_key.pem");
+const CHAT_COMPLETIONS_PATH: &str = "/v1/projects/my-project/locations/\
+                                     us-central1/endpoints/openapi/chat/\
+                                     completions";
+
+/// Builds a harness whose Vertex AI service account exchanges its tokens
+/// with, and sends requests to, `server`.
+async fn harness(server: &MockServer) -> Harness {
+    let service_account = json!({
+        "type": "service_account",
+        "project_id": "my-project",
+        "client_email": "gateway@my-project.iam.gserviceaccount.com",
+        "private_key": PRIVATE_KEY,
+        "token_uri": format!("{}/token", server.uri()),
+    });
+    // SAFETY: This must only be called within the single threaded tokio
+    // runtime in tests
+    unsafe {
+        std::env::set_var("VERTEX_API_KEY", service_account.to_string());
+    }
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing provider auth
+    config.helicone.features = HeliconeFeatures::None;
+    let vertex = config
+        .providers
+        .get_mut(&InferenceProvider::Named("vertex".into()))
+        .unwrap();
+    vertex.base_url = Url::parse(&server.uri()).unwrap();
+    vertex.region = Some("us-central1".to_string());
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::vertex(),
+            ..Default::default()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    // SAFETY: see above
+    unsafe {
+        std::env::remove_var("VERTEX_API_KEY");
+    }
+    harness
+}
+
+async fn mount_token_endpoint(server: &MockServer, expected_calls: u64) {
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains(
+            "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": "ya29.test-token",
+            "expires_in": 3599,
+            "token_type": "Bearer"
+        })))
+        .expect(expected_calls)
+        .mount(server)
+        .await;
+}
+
+fn chat_completion() -> Value {
+    json!({
+        "id": "chatcmpl-vertex",
+        "object": "chat.completion",
+        "created": 1_748_543_700,
+        "model": "google/gemini-2.0-flash",
+        "choices": [{
+            "index": 0,
+            "finish_reason": "stop",
+            "message": { "role": "assistant", "content": "Hello!" }
+        }],
+        "usage": {
+            "prompt_tokens": 6,
+            "completion_tokens": 2,
+            "total_tokens": 8
+        }
+    })
+}
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "vertex/google/gemini-2.0-flash",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn access_token_is_obtained_once_and_cached() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server, 1).await;
+    Mock::given(method("POST"))
+        .and(path(CHAT_COMPLETIONS_PATH))
+        .and(header("authorization", "Bearer ya29.test-token"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(chat_completion()),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+    let mut harness = harness(&server).await;
+
+    for _ in 0..2 {
+        let response = harness.call(chat_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["choices"][0]["message"]["content"], "Hello!");
+    }
+    server.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn access_token_is_refreshed_after_unauthorized() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server, 2).await;
+    Mock::given(method("POST"))
+        .and(path(CHAT_COMPLETIONS_PATH))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": {
+                "code": 401,
+                "message": "Request had invalid authentication credentials.",
+                "status": "UNAUTHENTICATED"
+            }
+        })))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(CHAT_COMPLETIONS_PATH))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(chat_completion()),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut harness = harness(&server).await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    server.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn token_endpoint_failure_is_an_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": "invalid_grant",
+            "error_description": "Invalid JWT Signature."
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(CHAT_COMPLETIONS_PATH))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(chat_completion()),
+        )
+        .expect(0)
+        .mount(&server)
+        .await;
+    let mut harness = harness(&server).await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    server.verify().await;
+}
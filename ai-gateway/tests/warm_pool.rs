@@ -0,0 +1,153 @@
+use std::{collections::HashMap, num::NonZeroU32, time::Duration};
+
+use ai_gateway::{
+    config::{
+        Config, dispatcher::WarmPoolConfig, helicone::HeliconeFeatures,
+        monitor::HealthMonitorConfig,
+    },
+    dispatcher::warm_pool::WarmPool,
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::provider::InferenceProvider,
+};
+use http::{Method, Request, StatusCode};
+use serde_json::json;
+use tower::Service;
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    let body = serde_json::to_vec(&json!({
+        "model": "gpt-4o-mini",
+        "messages": [{ "role": "user", "content": "Hello, world!" }]
+    }))
+    .unwrap();
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/openai/v1/chat/completions")
+        .body(axum_core::body::Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn warm_pool_size_tracks_recent_traffic() {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're not testing authentication
+    config.helicone.features = HeliconeFeatures::None;
+    // keep the requests in the rolling window for the whole test
+    if let HealthMonitorConfig::ErrorRatio { window, .. } =
+        &mut config.discover.monitor.health
+    {
+        *window = Duration::from_secs(60);
+    }
+    config.dispatcher.warm_pool = Some(WarmPoolConfig {
+        min_connections: 1,
+        max_connections: 4,
+        requests_per_connection: NonZeroU32::new(2).unwrap(),
+        ..Default::default()
+    });
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 5.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let warm_pool = WarmPool::new(harness.app_factory.state.clone()).unwrap();
+
+    // no traffic yet, so only the minimum is kept warm
+    assert_eq!(warm_pool.pool_size(&InferenceProvider::OpenAI), 1);
+
+    for _ in 0..5 {
+        let response = harness.call(chat_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    assert_eq!(warm_pool.pool_size(&InferenceProvider::OpenAI), 3);
+    // providers without traffic stay at the minimum
+    assert_eq!(warm_pool.pool_size(&InferenceProvider::Anthropic), 1);
+
+    harness.mock.verify().await;
+}
+
+/// The number of warmup requests the OpenAI mock received.
+async fn openai_warmups(harness: &Harness) -> usize {
+    harness
+        .mock
+        .openai_mock
+        .http_server
+        .received_requests()
+        .await
+        .expect("request recording is enabled")
+        .iter()
+        .filter(|request| request.method.to_string() == "HEAD")
+        .count()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn warm_pool_opens_connections_only_when_the_pool_grows() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    if let HealthMonitorConfig::ErrorRatio { window, .. } =
+        &mut config.discover.monitor.health
+    {
+        *window = Duration::from_secs(60);
+    }
+    config.dispatcher.warm_pool = Some(WarmPoolConfig {
+        min_connections: 2,
+        max_connections: 4,
+        requests_per_connection: NonZeroU32::new(2).unwrap(),
+        ..Default::default()
+    });
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 5.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let warm_pool = WarmPool::new(harness.app_factory.state.clone()).unwrap();
+
+    // the minimum is split between the provider's dispatchers, rather than
+    // being opened by each of them
+    warm_pool.warm_all().await;
+    assert_eq!(openai_warmups(&harness).await, 2);
+
+    // the warm connections are still idle in the pool, so they aren't
+    // opened again
+    warm_pool.warm_all().await;
+    assert_eq!(openai_warmups(&harness).await, 2);
+
+    for _ in 0..5 {
+        let response = harness.call(chat_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    assert_eq!(warm_pool.pool_size(&InferenceProvider::OpenAI), 3);
+    warm_pool.warm_all().await;
+    let warmups = openai_warmups(&harness).await;
+    assert!(
+        warmups > 2 && warmups <= 2 + 3,
+        "the grown pool is topped up, got {warmups} warmups"
+    );
+
+    harness.mock.verify().await;
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn warm_pool_is_disabled_by_default() {
+    let harness = Harness::builder()
+        .with_config(Config::test_default())
+        .build()
+        .await;
+
+    assert!(WarmPool::new(harness.app_factory.state.clone()).is_none());
+}
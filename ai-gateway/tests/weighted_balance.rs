@@ -501,6 +501,7 @@ async fn model_weighted() {
                     weight: Decimal::try_from(0.75).unwrap(),
                 },
             ],
+            fan_out: None,
         },
     )]));
     config.routers = RouterConfigs::new(HashMap::from([(
@@ -568,3 +569,87 @@ async fn model_weighted() {
     // sleep so that the background task for logging can complete
     tokio::time::sleep(std::time::Duration::from_millis(10)).await;
 }
+
+#[tokio::test]
+#[serial_test::serial]
+async fn least_in_flight_balancer_routes_within_endpoint_type() {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're not testing authentication
+    config.helicone.features = HeliconeFeatures::None;
+    let balance_config = BalanceConfig::from(HashMap::from([
+        (
+            EndpointType::Chat,
+            BalanceConfigInner::LeastInFlight {
+                providers: nes![
+                    WeightedProvider {
+                        provider: InferenceProvider::OpenAI,
+                        weight: Decimal::try_from(0.5).unwrap(),
+                    },
+                    WeightedProvider {
+                        provider: InferenceProvider::Anthropic,
+                        weight: Decimal::try_from(0.5).unwrap(),
+                    },
+                ],
+            },
+        ),
+        (
+            EndpointType::Embeddings,
+            BalanceConfigInner::LeastInFlight {
+                providers: nes![WeightedProvider {
+                    provider: InferenceProvider::GoogleGemini,
+                    weight: Decimal::ONE,
+                }],
+            },
+        ),
+    ]));
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: balance_config,
+            ..Default::default()
+        },
+    )]));
+    let num_requests = 20;
+    // chat requests are only balanced over the chat providers
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", (0..=num_requests).into()),
+            ("success:anthropic:messages", (0..=num_requests).into()),
+            ("success:gemini:generate_content", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let body_bytes = serde_json::to_vec(&json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ]
+    }))
+    .unwrap();
+
+    for _ in 0..num_requests {
+        let request_body = axum_core::body::Body::from(body_bytes.clone());
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("http://router.helicone.com/router/my-router/chat/completions")
+            .body(request_body)
+            .unwrap();
+        let response = harness.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // we need to collect the body here in order to poll the underlying body
+        // so that the async logging task can complete
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+
+    harness.mock.verify().await;
+}
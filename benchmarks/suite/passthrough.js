@@ -0,0 +1,60 @@
+import http from 'k6/http';
+import { check } from 'k6';
+
+// Compares requests that need no response mapping (OpenAI -> OpenAI) with
+// requests whose responses are mapped (OpenAI -> Anthropic), so the cost of
+// mapping can be read from the per-scenario `http_req_duration` metrics.
+const scenario = (exec) => ({
+  executor: 'constant-arrival-rate',
+  exec,
+  rate: 750,
+  timeUnit: '1s',
+  duration: '2m',
+  preAllocatedVUs: 50,
+  maxVUs: 250,
+});
+
+export const options = {
+  scenarios: {
+    passthrough: scenario('passthrough'),
+    mapped: scenario('mapped'),
+  },
+  thresholds: {
+    'http_req_duration{scenario:passthrough}': ['p(95)<100'],
+    'http_req_duration{scenario:mapped}': ['p(95)<100'],
+  },
+};
+
+const payload = (model) => JSON.stringify({
+  model,
+  messages: [
+    {
+        "role": "system",
+        "content": "You are a helpful assistant that can answer questions and help with tasks."
+    },
+    {
+        "role": "user",
+        "content": "Hello, world!"
+    }
+  ],
+  max_tokens: 1000,
+});
+
+const params = {
+  headers: {
+    'Content-Type': 'application/json',
+    'Authorization': 'sk-helicone-test-key',
+  },
+};
+
+const url = 'https://helicone-ai-gateway.fly.dev/ai/chat/completions';
+
+export function passthrough() {
+  const res = http.post(url, payload('openai/gpt-4o-mini'), params);
+  check(res, { 'status is 200': (r) => r.status === 200 });
+}
+
+export function mapped() {
+  const res = http.post(url, payload('anthropic/claude-3-5-haiku'), params);
+  check(res, { 'status is 200': (r) => r.status === 200 });
+}
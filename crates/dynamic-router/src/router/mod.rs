@@ -26,22 +26,29 @@
 pub mod make;
 
 use std::{
+    collections::HashMap,
     convert::Infallible,
     fmt::{self, Display},
     hash::Hash,
     marker::PhantomData,
     pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
     task::{Context, Poll},
 };
 
 use futures::ready;
 use pin_project::pin_project;
+use rand::{SeedableRng, rngs::SmallRng};
 use tower::{
     Service,
     discover::{Change, Discover},
     ready_cache::ReadyCache,
 };
 use tracing::{debug, trace};
+use weighted_balance::weight::{HasWeight, Weight};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -51,6 +58,39 @@ pub enum Error {
     Discover(tower::BoxError),
     #[error("Router not found: {0}")]
     RouterNotFound(String),
+    #[error("No ready service")]
+    NoReadyService,
+    #[error("Failed to sample ready services: {0}")]
+    SampleFailed(#[from] rand::distr::weighted::Error),
+}
+
+/// How a [`DynamicRouter`] picks the service for a request.
+enum Selection<K, ReqBody> {
+    /// The service is looked up by the key in the request extensions.
+    Keyed,
+    /// Ready services whose keys match the request are called in turn, in
+    /// the order they were first discovered.
+    RoundRobin {
+        /// Keys of all discovered services in the order they were first
+        /// discovered, with the call at which each was last picked.
+        ///
+        /// Keys are kept when their service is removed, so that a service
+        /// which is inserted again keeps its place in the rotation.
+        keys: Vec<(K, u64)>,
+        /// Number of requests routed so far.
+        calls: u64,
+        matches: fn(&K, &http::Request<ReqBody>) -> bool,
+    },
+    /// Two ready services whose keys match the request are sampled by the
+    /// weight of their keys, and the one with fewer requests in flight is
+    /// called.
+    PowerOfTwoChoices {
+        rng: SmallRng,
+        weight: fn(&K) -> Weight,
+        /// Requests in flight, by the key of the service handling them.
+        in_flight: HashMap<K, Arc<AtomicUsize>>,
+        matches: fn(&K, &http::Request<ReqBody>) -> bool,
+    },
 }
 
 pub struct DynamicRouter<D, ReqBody>
@@ -62,6 +102,8 @@ where
 
     services: ReadyCache<D::Key, D::Service, http::Request<ReqBody>>,
 
+    selection: Selection<D::Key, ReqBody>,
+
     _req: PhantomData<ReqBody>,
 }
 
@@ -90,6 +132,86 @@ where
         Self {
             discover,
             services: ReadyCache::default(),
+            selection: Selection::Keyed,
+
+            _req: PhantomData,
+        }
+    }
+
+    /// Creates a router that ignores request keys and instead cycles
+    /// through the ready services in the order they were discovered.
+    ///
+    /// Only services whose keys `matches` the request take part in the
+    /// rotation for it, and each distinct set of matching services is
+    /// rotated through independently.
+    pub fn round_robin(
+        discover: D,
+        matches: fn(&D::Key, &http::Request<ReqBody>) -> bool,
+    ) -> Self {
+        tracing::trace!("DynamicRouter::round_robin");
+        Self {
+            discover,
+            services: ReadyCache::default(),
+            selection: Selection::RoundRobin {
+                keys: Vec::new(),
+                calls: 0,
+                matches,
+            },
+
+            _req: PhantomData,
+        }
+    }
+
+    /// Creates a router that ignores request keys and instead balances
+    /// requests over the ready services with power-of-two-choices, weighted
+    /// by the weight of their keys.
+    ///
+    /// Only services whose keys `matches` the request are sampled for it.
+    pub fn p2c(
+        discover: D,
+        matches: fn(&D::Key, &http::Request<ReqBody>) -> bool,
+    ) -> Self
+    where
+        D::Key: HasWeight,
+    {
+        Self::p2c_with_rng(
+            discover,
+            matches,
+            SmallRng::from_rng(&mut rand::rng()),
+        )
+    }
+
+    /// Like [`DynamicRouter::p2c`], but with a seeded rng so that the
+    /// sampled services are deterministic.
+    pub fn p2c_with_seed(
+        discover: D,
+        matches: fn(&D::Key, &http::Request<ReqBody>) -> bool,
+        seed: u64,
+    ) -> Self
+    where
+        D::Key: HasWeight,
+    {
+        Self::p2c_with_rng(discover, matches, SmallRng::seed_from_u64(seed))
+    }
+
+    fn p2c_with_rng(
+        discover: D,
+        matches: fn(&D::Key, &http::Request<ReqBody>) -> bool,
+        rng: SmallRng,
+    ) -> Self
+    where
+        D::Key: HasWeight,
+    {
+        tracing::trace!("DynamicRouter::p2c");
+        Self {
+            discover,
+            services: ReadyCache::default(),
+            selection: Selection::PowerOfTwoChoices {
+                rng,
+                weight: <D::Key as HasWeight>::weight,
+                in_flight: HashMap::new(),
+                matches,
+            },
 
             _req: PhantomData,
         }
@@ -129,10 +251,29 @@ where
                 None => return Poll::Ready(None),
                 Some(Change::Remove(key)) => {
                     trace!("remove");
+                    // round robin keys are kept, an evicted service is
+                    // skipped since it is never ready
                     self.services.evict(&key);
+                    if let Selection::PowerOfTwoChoices { in_flight, .. } =
+                        &mut self.selection
+                    {
+                        // requests still in flight hold their own counter
+                        in_flight.remove(&key);
+                    }
                 }
                 Some(Change::Insert(key, svc)) => {
                     trace!("insert");
+                    if let Selection::RoundRobin { keys, .. } =
+                        &mut self.selection
+                        && !keys.iter().any(|(k, _)| *k == key)
+                    {
+                        keys.push((key.clone(), 0));
+                    }
+                    if let Selection::PowerOfTwoChoices { in_flight, .. } =
+                        &mut self.selection
+                    {
+                        in_flight.entry(key.clone()).or_default();
+                    }
                     // If this service already existed in the set, it will be
                     // replaced as the new one becomes ready.
                     self.services.push(key, svc);
@@ -169,6 +310,66 @@ where
     }
 }
 
+/// Samples two of the ready services whose keys `matches` by `weight` and
+/// returns the index of the one with fewer requests in flight.
+fn p2c_index<K, S, Req>(
+    services: &ReadyCache<K, S, Req>,
+    rng: &mut SmallRng,
+    weight: fn(&K) -> Weight,
+    in_flight: &HashMap<K, Arc<AtomicUsize>>,
+    matches: impl Fn(&K) -> bool,
+) -> Result<Option<usize>, Error>
+where
+    K: Hash + Eq,
+    S: Service<Req>,
+{
+    let key = |idx| {
+        let (key, _service) =
+            services.get_ready_index(idx).expect("invalid index");
+        key
+    };
+    let candidates = (0..services.ready_len())
+        .filter(|idx| matches(key(*idx)))
+        .collect::<Vec<_>>();
+    match candidates.as_slice() {
+        [] => Ok(None),
+        [idx] => Ok(Some(*idx)),
+        _ => {
+            let sample = rand::seq::index::sample_weighted(
+                rng,
+                candidates.len(),
+                |idx| weight(key(candidates[idx])),
+                2,
+            )?;
+            let chosen =
+                sample.iter().map(|idx| candidates[idx]).min_by_key(|idx| {
+                    in_flight
+                        .get(key(*idx))
+                        .map_or(0, |count| count.load(Ordering::Relaxed))
+                });
+            trace!(chosen = ?chosen, "p2c");
+            Ok(chosen)
+        }
+    }
+}
+
+/// Counts a request as in flight for as long as it is alive.
+#[derive(Debug)]
+struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    fn new(count: Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(count)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 impl<D, ReqBody> Service<http::Request<ReqBody>> for DynamicRouter<D, ReqBody>
 where
     D: Discover + Unpin,
@@ -190,10 +391,82 @@ where
     ) -> Poll<Result<(), Self::Error>> {
         let _ = self.update_pending_from_discover(cx)?;
         self.promote_pending_to_ready(cx);
+        if !matches!(self.selection, Selection::Keyed)
+            && self.services.ready_len() == 0
+        {
+            // woken by either discover or a pending service becoming ready
+            return Poll::Pending;
+        }
         Poll::Ready(Ok(()))
     }
 
     fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        if let Selection::RoundRobin {
+            keys,
+            calls,
+            matches,
+        } = &mut self.selection
+        {
+            let matches = *matches;
+            // the least recently picked of the matching ready services, so
+            // that requests matching different services don't skip over
+            // each other's turns
+            let next = keys
+                .iter_mut()
+                .filter(|(key, _)| {
+                    matches(key, &request)
+                        && self.services.get_ready(key).is_some()
+                })
+                .min_by_key(|(_, last_picked)| *last_picked);
+            let Some((key, last_picked)) = next else {
+                return ResponseFuture::Ready {
+                    error: Some(Error::NoReadyService),
+                };
+            };
+            *calls += 1;
+            *last_picked = *calls;
+            let future = self.services.call_ready(&*key, request);
+            return ResponseFuture::Inner {
+                future,
+                in_flight: None,
+            };
+        }
+
+        if let Selection::PowerOfTwoChoices {
+            rng,
+            weight,
+            in_flight,
+            matches,
+        } = &mut self.selection
+        {
+            let matches = *matches;
+            let idx = match p2c_index(
+                &self.services,
+                rng,
+                *weight,
+                in_flight,
+                |key| matches(key, &request),
+            ) {
+                Ok(Some(idx)) => idx,
+                Ok(None) => {
+                    return ResponseFuture::Ready {
+                        error: Some(Error::NoReadyService),
+                    };
+                }
+                Err(error) => {
+                    return ResponseFuture::Ready { error: Some(error) };
+                }
+            };
+            let (key, _service) =
+                self.services.get_ready_index(idx).expect("invalid index");
+            let count = in_flight.entry(key.clone()).or_default().clone();
+            let future = self.services.call_ready_index(idx, request);
+            return ResponseFuture::Inner {
+                future,
+                in_flight: Some(InFlight::new(count)),
+            };
+        }
+
         let Some(key) = request.extensions().get::<D::Key>().cloned() else {
             return ResponseFuture::Ready {
                 error: Some(Error::ExtensionNotFound),
@@ -202,7 +475,10 @@ where
 
         if let Some((_, _, _)) = self.services.get_ready(&key) {
             let future = self.services.call_ready(&key, request);
-            ResponseFuture::Inner { future }
+            ResponseFuture::Inner {
+                future,
+                in_flight: None,
+            }
         } else {
             ResponseFuture::Ready {
                 error: Some(Error::RouterNotFound(key.to_string())),
@@ -229,6 +505,7 @@ where
     Inner {
         #[pin]
         future: <D::Service as Service<http::Request<ReqBody>>>::Future,
+        in_flight: Option<InFlight>,
     },
 }
 
@@ -253,8 +530,12 @@ where
             ResponseFutureProj::Ready { error } => Poll::Ready(Err(error
                 .take()
                 .expect("future polled after completion"))),
-            ResponseFutureProj::Inner { future } => {
-                match ready!(future.poll(cx)) {
+            ResponseFutureProj::Inner { future, in_flight } => {
+                let result = ready!(future.poll(cx));
+                // the request is no longer in flight once it has resolved,
+                // even if this future is kept around
+                in_flight.take();
+                match result {
                     Ok(res) => Poll::Ready(Ok(res)),
                     // never happens due to `Infallible` bound
                     Err(e) => match e {},
@@ -263,3 +544,127 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::{future, stream};
+    use tower::service_fn;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct Key(&'static str);
+
+    impl Display for Key {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl HasWeight for Key {
+        fn weight(&self) -> Weight {
+            Weight::UNIT
+        }
+    }
+
+    fn in_flight_counts<D, ReqBody>(
+        router: &DynamicRouter<D, ReqBody>,
+    ) -> Vec<usize>
+    where
+        D: Discover<Key = Key>,
+    {
+        let Selection::PowerOfTwoChoices { in_flight, .. } = &router.selection
+        else {
+            panic!("router is not p2c");
+        };
+        in_flight
+            .values()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Matches keys named `<group>:<name>` to requests for that group.
+    fn same_group(key: &Key, request: &http::Request<&'static str>) -> bool {
+        key.0.split(':').next() == Some(*request.body())
+    }
+
+    #[test]
+    fn round_robin_rotates_over_matching_services_in_order() {
+        let services = ["chat:a", "embed:a", "chat:b", "embed:b", "chat:c"]
+            .map(|name| {
+                let service =
+                    service_fn(move |_req: http::Request<&'static str>| {
+                        future::ready(Ok::<_, Infallible>(name))
+                    });
+                Ok::<_, Infallible>(Change::Insert(Key(name), service))
+            });
+        let mut router =
+            DynamicRouter::round_robin(stream::iter(services), same_group);
+        let mut task = tokio_test::task::spawn(());
+
+        let mut called = Vec::new();
+        for group in ["chat", "chat", "embed", "chat", "embed", "chat"] {
+            tokio_test::assert_ready_ok!(
+                task.enter(|cx, _| router.poll_ready(cx))
+            );
+            let response = router.call(http::Request::new(group));
+            called.push(tokio_test::block_on(response).unwrap());
+        }
+        assert_eq!(
+            called,
+            ["chat:a", "chat:b", "embed:a", "chat:c", "embed:b", "chat:a"]
+        );
+    }
+
+    #[test]
+    fn p2c_only_samples_matching_services() {
+        let services = ["chat:a", "embed:a", "chat:b", "embed:b"].map(|name| {
+            let service =
+                service_fn(move |_req: http::Request<&'static str>| {
+                    future::ready(Ok::<_, Infallible>(name))
+                });
+            Ok::<_, Infallible>(Change::Insert(Key(name), service))
+        });
+        let mut router =
+            DynamicRouter::p2c_with_seed(stream::iter(services), same_group, 7);
+        let mut task = tokio_test::task::spawn(());
+
+        for _ in 0..8 {
+            tokio_test::assert_ready_ok!(
+                task.enter(|cx, _| router.poll_ready(cx))
+            );
+            let response = router.call(http::Request::new("embed"));
+            let called = tokio_test::block_on(response).unwrap();
+            assert!(called.starts_with("embed:"), "called {called}");
+        }
+    }
+
+    #[test]
+    fn p2c_prefers_service_with_fewer_requests_in_flight() {
+        let services = ["a", "b"].map(|name| {
+            // requests never resolve, so they stay in flight until dropped
+            let service = service_fn(|_req: http::Request<()>| {
+                future::pending::<Result<(), Infallible>>()
+            });
+            Ok::<_, Infallible>(Change::Insert(Key(name), service))
+        });
+        let mut router = DynamicRouter::p2c_with_seed(
+            stream::iter(services),
+            |_, _| true,
+            7,
+        );
+        let mut task = tokio_test::task::spawn(());
+
+        let mut responses = Vec::new();
+        for _ in 0..2 {
+            tokio_test::assert_ready_ok!(
+                task.enter(|cx, _| router.poll_ready(cx))
+            );
+            responses.push(router.call(http::Request::new(())));
+        }
+        assert_eq!(in_flight_counts(&router), vec![1, 1]);
+
+        drop(responses);
+        assert_eq!(in_flight_counts(&router), vec![0, 0]);
+    }
+}